@@ -6,6 +6,9 @@ use std::{
 
 use crate::core::{RbxReadExt, RbxWriteExt};
 
+/// The first four bytes of every ZSTD frame.
+const ZSTD_MAGIC_NUMBER: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
 /// Represents one chunk from a binary model file.
 #[derive(Debug)]
 pub struct Chunk {
@@ -30,7 +33,15 @@ impl Chunk {
                 .take(header.compressed_len as u64)
                 .read_to_end(&mut compressed_data)?;
 
-            lz4::block::decompress(&compressed_data, Some(header.len as i32))?
+            // ZSTD and LZ4 compressed chunks share the same header layout, so
+            // the only way to tell them apart is the magic number that every
+            // ZSTD frame starts with.
+            if compressed_data.starts_with(ZSTD_MAGIC_NUMBER) {
+                decompress_zstd(&compressed_data, header.len as usize)?
+            } else {
+                lz4_flex::block::decompress(&compressed_data, header.len as usize)
+                    .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?
+            }
         };
 
         assert_eq!(data.len(), header.len as usize);
@@ -42,12 +53,29 @@ impl Chunk {
     }
 }
 
+#[cfg(feature = "zstd")]
+fn decompress_zstd(data: &[u8], len: usize) -> io::Result<Vec<u8>> {
+    zstd::bulk::decompress(data, len)
+}
+
+#[cfg(not(feature = "zstd"))]
+fn decompress_zstd(_data: &[u8], _len: usize) -> io::Result<Vec<u8>> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "ZSTD compressed chunks require rbx_binary's zstd feature",
+    ))
+}
+
 /// The compression format of a chunk in the binary model format.
 #[derive(Debug, Clone, Copy)]
 pub enum ChunkCompression {
     /// The contents of the chunk should be LZ4 compressed.
     Compressed,
 
+    /// The contents of the chunk should be ZSTD compressed.
+    #[cfg(feature = "zstd")]
+    Zstd,
+
     /// The contents of the chunk should be uncompressed.
     Uncompressed,
 }
@@ -59,7 +87,7 @@ pub enum ChunkCompression {
 /// automatically.
 #[must_use]
 pub struct ChunkBuilder {
-    chunk_name: &'static [u8],
+    chunk_name: [u8; 4],
     compression: ChunkCompression,
     buffer: Vec<u8>,
 }
@@ -67,21 +95,36 @@ pub struct ChunkBuilder {
 impl ChunkBuilder {
     /// Creates a new `ChunkBuilder` with the given name and compression
     /// setting.
-    pub fn new(chunk_name: &'static [u8], compression: ChunkCompression) -> Self {
+    pub fn new(chunk_name: &[u8; 4], compression: ChunkCompression) -> Self {
         ChunkBuilder {
-            chunk_name,
+            chunk_name: *chunk_name,
             compression,
             buffer: Vec::new(),
         }
     }
 
+    /// Returns the name of the chunk being built, like `PROP`.
+    pub fn chunk_name(&self) -> [u8; 4] {
+        self.chunk_name
+    }
+
     /// Consume the chunk and write it to the given writer.
     pub fn dump<W: Write>(self, mut writer: W) -> io::Result<()> {
-        writer.write_all(self.chunk_name)?;
+        writer.write_all(&self.chunk_name)?;
 
         match self.compression {
             ChunkCompression::Compressed => {
-                let compressed = lz4::block::compress(&self.buffer, None, false)?;
+                let compressed = lz4_flex::block::compress(&self.buffer);
+
+                writer.write_le_u32(compressed.len() as u32)?;
+                writer.write_le_u32(self.buffer.len() as u32)?;
+                writer.write_le_u32(0)?;
+
+                writer.write_all(&compressed)?;
+            }
+            #[cfg(feature = "zstd")]
+            ChunkCompression::Zstd => {
+                let compressed = zstd::bulk::compress(&self.buffer, 0)?;
 
                 writer.write_le_u32(compressed.len() as u32)?;
                 writer.write_le_u32(self.buffer.len() as u32)?;
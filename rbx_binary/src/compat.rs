@@ -0,0 +1,111 @@
+use std::{fmt, str};
+
+/// Something in a file that a [`Deserializer`][crate::Deserializer] didn't
+/// understand and worked around instead of failing, passed to the callback
+/// given to [`Deserializer::diagnostics`][crate::Deserializer::diagnostics].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Diagnostic {
+    /// The file has a newer format version than rbx_binary knows about, and
+    /// was read as if it was the newest version that rbx_binary knows about.
+    /// Only reported in forward compatible mode.
+    NewerFileVersion {
+        /// The version in the file's header.
+        version: u16,
+    },
+
+    /// The file has a chunk with a name that rbx_binary doesn't know about.
+    /// The chunk was kept in the DOM's [`UnknownChunks`].
+    UnknownChunk {
+        /// The name of the chunk, like `SIGN`.
+        name: [u8; 4],
+    },
+
+    /// The file has a chunk with a version that rbx_binary can't read, which
+    /// was skipped. Only reported in forward compatible mode.
+    UnknownChunkVersion {
+        /// The name of the chunk, like `PRNT`.
+        name: [u8; 4],
+
+        /// The version of the chunk.
+        version: u32,
+    },
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Diagnostic::NewerFileVersion { version } => write!(
+                formatter,
+                "File version {} is newer than the known versions, reading it as version 0",
+                version
+            ),
+            Diagnostic::UnknownChunk { name } => {
+                write!(formatter, "Unknown binary chunk name {}", ChunkName(name))
+            }
+            Diagnostic::UnknownChunkVersion { name, version } => write!(
+                formatter,
+                "Unknown version {} for chunk {}, skipping it",
+                version,
+                ChunkName(name)
+            ),
+        }
+    }
+}
+
+/// A callback that is told about diagnostics.
+pub(crate) type DiagnosticCallback<'a> = dyn Fn(&Diagnostic) + Send + Sync + 'a;
+
+/// Writes a chunk name as text if it is valid UTF-8, and as bytes otherwise.
+struct ChunkName<'a>(&'a [u8; 4]);
+
+impl fmt::Display for ChunkName<'_> {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match str::from_utf8(self.0) {
+            Ok(name) => formatter.write_str(name),
+            Err(_) => write!(formatter, "{:?}", self.0),
+        }
+    }
+}
+
+/// Chunks that a [`Deserializer`][crate::Deserializer] kept without reading
+/// them because it didn't know their name.
+///
+/// They're attached to the root of the decoded DOM as an extension. A
+/// [`Serializer`][crate::Serializer] with
+/// [`preserve_unknown_chunks`][crate::Serializer::preserve_unknown_chunks]
+/// turned on writes them back out, unchanged and just before the file's END
+/// chunk. That's off by default, because chunks like signatures or hashes
+/// don't match a DOM that has been changed. Chunks with the names of ones the
+/// serializer writes itself, like `PRNT`, are never written back out.
+///
+/// ```no_run
+/// use std::fs::File;
+/// use std::io::BufReader;
+///
+/// use rbx_binary::UnknownChunks;
+///
+/// let dom = rbx_binary::from_reader(BufReader::new(File::open("Place.rbxl")?))?;
+///
+/// if let Some(unknown) = dom.extension::<UnknownChunks>(dom.root_ref()) {
+///     for chunk in &unknown.chunks {
+///         println!("{:?}: {} bytes", chunk.name, chunk.data.len());
+///     }
+/// }
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UnknownChunks {
+    /// The chunks, in the order they appeared in the file.
+    pub chunks: Vec<UnknownChunk>,
+}
+
+/// One of the chunks in [`UnknownChunks`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownChunk {
+    /// The name of the chunk.
+    pub name: [u8; 4],
+
+    /// The decompressed contents of the chunk.
+    pub data: Vec<u8>,
+}
@@ -3,10 +3,6 @@ use std::{
     mem,
 };
 
-use rbx_reflection::{
-    ClassDescriptor, PropertyDescriptor, PropertyKind, PropertySerialization, ReflectionDatabase,
-};
-
 pub static FILE_MAGIC_HEADER: &[u8] = b"<roblox!";
 pub static FILE_SIGNATURE: &[u8] = b"\x89\xff\x0d\x0a\x1a\x0a";
 pub const FILE_VERSION: u16 = 0;
@@ -373,137 +369,3 @@ pub fn transform_i64(value: i64) -> i64 {
 pub fn untransform_i64(value: i64) -> i64 {
     ((value as u64) >> 1) as i64 ^ -(value & 1)
 }
-
-pub struct PropertyDescriptors<'a> {
-    pub canonical: &'a PropertyDescriptor<'a>,
-    pub serialized: Option<&'a PropertyDescriptor<'a>>,
-}
-
-/// Find both the canonical and serialized property descriptors for a given
-/// class and property name pair. These might be the same descriptor!
-pub fn find_property_descriptors<'a>(
-    database: &'a ReflectionDatabase<'a>,
-    class_name: &str,
-    property_name: &str,
-) -> Option<PropertyDescriptors<'a>> {
-    let mut class_descriptor = database.classes.get(class_name)?;
-
-    // We need to find the canonical property descriptor associated with
-    // the property we're working with.
-    //
-    // At each step of the loop, we're checking a new class descriptor to see if
-    // it has an entry for the property name we're looking for. If that class
-    // doesn't have the property, we'll check its superclass until we reach the
-    // root.
-    loop {
-        // If this class descriptor knows about this property name, we're pretty
-        // much done!
-        if let Some(property_descriptor) = class_descriptor.properties.get(property_name) {
-            match &property_descriptor.kind {
-                // This property descriptor is the canonical form of this
-                // logical property. That means we've found one of the two
-                // descriptors we're looking for!
-                PropertyKind::Canonical { serialization } => {
-                    let serialized = find_serialized_from_canonical(
-                        class_descriptor,
-                        property_descriptor,
-                        serialization,
-                    );
-
-                    return Some(PropertyDescriptors {
-                        canonical: property_descriptor,
-                        serialized,
-                    });
-                }
-
-                // This descriptor is an alias for another property. While this
-                // descriptor might be one of the two descriptors we need to
-                // return, it's possible that both the canonical and serialized
-                // forms are different.
-                PropertyKind::Alias { alias_for } => {
-                    let canonical = class_descriptor.properties.get(alias_for.as_ref()).unwrap();
-
-                    if let PropertyKind::Canonical { serialization } = &canonical.kind {
-                        let serialized = find_serialized_from_canonical(
-                            class_descriptor,
-                            canonical,
-                            serialization,
-                        );
-
-                        return Some(PropertyDescriptors {
-                            canonical,
-                            serialized,
-                        });
-                    } else {
-                        // If one property in the database calls itself an alias
-                        // of another property, that property must be canonical.
-                        log::error!(
-                            "Property {}.{} is marked as an alias for {}.{}, but the latter is not canonical.",
-                            class_descriptor.name,
-                            property_descriptor.name,
-                            class_descriptor.name,
-                            alias_for
-                        );
-
-                        return None;
-                    }
-                }
-
-                // This descriptor is of an unknown kind and we don't know how
-                // to deal with it -- maybe rbx_binary is out of date?
-                _ => return None,
-            }
-        }
-
-        if let Some(superclass_name) = &class_descriptor.superclass {
-            // If a property descriptor isn't found in our class, check our
-            // superclass.
-
-            class_descriptor = database
-                .classes
-                .get(superclass_name)
-                .expect("Superclass in reflection database didn't exist");
-        } else {
-            // This property isn't known by any class in the reflection
-            // database.
-
-            return None;
-        }
-    }
-}
-
-/// Given the canonical property descriptor for a logical property along with
-/// its serialization, returns the serialized form of the logical property if
-/// this property is serializable.
-fn find_serialized_from_canonical<'a>(
-    class: &'a ClassDescriptor<'a>,
-    canonical: &'a PropertyDescriptor<'a>,
-    serialization: &'a PropertySerialization<'a>,
-) -> Option<&'a PropertyDescriptor<'a>> {
-    match serialization {
-        // This property serializes as-is. This is the happiest path: both the
-        // canonical and serialized descriptors are the same!
-        PropertySerialization::Serializes | PropertySerialization::Migrate { .. } => {
-            Some(canonical)
-        }
-
-        // This property serializes under an alias. That property should have a
-        // corresponding property descriptor within the same class descriptor.
-        PropertySerialization::SerializesAs(serialized_name) => {
-            let serialized_descriptor = class.properties.get(serialized_name.as_ref()).unwrap();
-
-            Some(serialized_descriptor)
-        }
-
-        // If this property does not serialize, there is no serialized
-        // descriptor to use.
-        PropertySerialization::DoesNotSerialize => None,
-
-        // This case will be hit if a new form of property serialization is
-        // introduced to the reflection database. This might happen if the
-        // database starts including more complex aliasing rules, like for the
-        // Grip properties of Tool, or the Rotation and Orientation properties
-        // of BasePart.
-        _ => None,
-    }
-}
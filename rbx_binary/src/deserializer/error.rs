@@ -11,6 +11,14 @@ pub struct Error {
     source: Box<InnerError>,
 }
 
+impl Error {
+    /// Tells whether this error happened because the operation was cancelled
+    /// using a cancel token.
+    pub fn is_cancelled(&self) -> bool {
+        matches!(*self.source, InnerError::Cancelled)
+    }
+}
+
 impl From<InnerError> for Error {
     fn from(inner: InnerError) -> Self {
         Self {
@@ -27,6 +35,9 @@ pub(crate) enum InnerError {
         source: io::Error,
     },
 
+    #[error("The operation was cancelled")]
+    Cancelled,
+
     #[error("Invalid file header")]
     BadHeader,
 
@@ -66,6 +77,9 @@ pub(crate) enum InnerError {
     #[error("File referred to type ID {type_id}, which was not declared")]
     InvalidTypeId { type_id: u32 },
 
+    #[error("More than one instance has the referent {referent}")]
+    DuplicateReferent { referent: i32 },
+
     #[error("Invalid property data: CFrame property {type_name}.{prop_name} had an invalid rotation ID {id:02x}")]
     BadRotationId {
         type_name: String,
@@ -1,12 +1,15 @@
 use std::io::Read;
 
-use crate::core::{RbxReadExt, FILE_MAGIC_HEADER, FILE_SIGNATURE, FILE_VERSION};
+use crate::core::{RbxReadExt, FILE_MAGIC_HEADER, FILE_SIGNATURE};
 
 use super::error::InnerError;
 
 /// All the information contained in the header before any chunks are read from
 /// the file.
 pub(crate) struct FileHeader {
+    /// The version of the file format. Only version 0 exists so far.
+    pub(crate) version: u16,
+
     /// The number of instance types (represented for us as `TypeInfo`) that are
     /// in this file. Generally useful to pre-size some containers before
     /// reading the file.
@@ -33,11 +36,6 @@ impl FileHeader {
         }
 
         let version = source.read_le_u16()?;
-
-        if version != FILE_VERSION {
-            return Err(InnerError::UnknownFileVersion { version });
-        }
-
         let num_types = source.read_le_u32()?;
         let num_instances = source.read_le_u32()?;
 
@@ -49,6 +47,7 @@ impl FileHeader {
         }
 
         Ok(Self {
+            version,
             num_types,
             num_instances,
         })
@@ -2,12 +2,24 @@ mod error;
 mod header;
 mod state;
 
-use std::{io::Read, str};
+use std::{
+    collections::HashSet,
+    io::Read,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
 
-use rbx_dom_weak::WeakDom;
+use rbx_dom_weak::{types::Ref, LineEndings, WeakDom};
 use rbx_reflection::ReflectionDatabase;
 
-use self::state::DeserializerState;
+use crate::{
+    compat::{Diagnostic, DiagnosticCallback},
+    progress::{Counting, Progress, ProgressCallback},
+};
+
+use self::{error::InnerError, state::DeserializerState};
 
 pub(crate) use self::header::FileHeader;
 
@@ -40,6 +52,16 @@ pub use self::error::Error;
 /// ```
 pub struct Deserializer<'a> {
     database: Option<&'a ReflectionDatabase<'a>>,
+    skip_classes: HashSet<String>,
+    top_level_classes: Option<HashSet<String>>,
+    only_classes: Option<HashSet<String>>,
+    decode_properties: bool,
+    progress: Option<Box<ProgressCallback<'a>>>,
+    cancel: Option<Arc<AtomicBool>>,
+    namespace: Option<Ref>,
+    forward_compatible: bool,
+    line_endings: LineEndings,
+    diagnostics: Option<Box<DiagnosticCallback<'a>>>,
 }
 
 impl<'a> Deserializer<'a> {
@@ -47,6 +69,226 @@ impl<'a> Deserializer<'a> {
     pub fn new() -> Self {
         Self {
             database: Some(rbx_reflection_database::get()),
+            skip_classes: HashSet::new(),
+            top_level_classes: None,
+            only_classes: None,
+            decode_properties: true,
+            progress: None,
+            cancel: None,
+            namespace: None,
+            forward_compatible: false,
+            line_endings: LineEndings::Preserve,
+            diagnostics: None,
+        }
+    }
+
+    /// Sets the reflection database used to decide how properties are read.
+    /// Defaults to the database bundled with `rbx_reflection_database`.
+    ///
+    /// This lets files from a specific version of Roblox Studio be read with
+    /// a matching database, which can be loaded with
+    /// `rbx_reflection_database::from_msgpack` or
+    /// `rbx_reflection_database::from_json`.
+    pub fn reflection_database(self, database: &'a ReflectionDatabase<'a>) -> Self {
+        Self {
+            database: Some(database),
+            ..self
+        }
+    }
+
+    /// Leaves out every instance with the given ClassName, along with all of
+    /// its descendants. Properties of these instances are not decoded at all,
+    /// which makes skipping large classes like Terrain much faster.
+    pub fn skip_class<S: Into<String>>(mut self, class: S) -> Self {
+        self.skip_classes.insert(class.into());
+        self
+    }
+
+    /// Keeps only the top-level instances with the given ClassName, along
+    /// with their descendants. Can be called more than once to keep more
+    /// classes; by default, every top-level instance is kept.
+    ///
+    /// This is useful for reading a single service, like ReplicatedStorage,
+    /// out of a place.
+    pub fn top_level_class<S: Into<String>>(mut self, class: S) -> Self {
+        self.top_level_classes
+            .get_or_insert_with(HashSet::new)
+            .insert(class.into());
+        self
+    }
+
+    /// Keeps only the instances with the given ClassName and their ancestors.
+    /// Can be called more than once to keep more classes; by default, every
+    /// class is kept.
+    ///
+    /// Ancestors are kept so that the instances stay where they were in the
+    /// tree, but only their names are decoded. Other properties are only
+    /// decoded for instances of the given classes. Classes are compared
+    /// exactly, so reading every script means passing `Script`,
+    /// `LocalScript`, and `ModuleScript`.
+    pub fn only_class<S: Into<String>>(mut self, class: S) -> Self {
+        self.only_classes
+            .get_or_insert_with(HashSet::new)
+            .insert(class.into());
+        self
+    }
+
+    /// Tells whether any of the options that leave instances out are set.
+    fn filters_instances(&self) -> bool {
+        !self.skip_classes.is_empty()
+            || self.top_level_classes.is_some()
+            || self.only_classes.is_some()
+    }
+
+    /// Sets whether properties other than `Name` are decoded. Defaults to
+    /// `true`.
+    ///
+    /// Turning this off reads just the names, classes, and hierarchy of the
+    /// instances in a file, which is much faster for large places and is
+    /// enough for browsing or searching them.
+    ///
+    /// ```no_run
+    /// use std::fs::File;
+    /// use std::io::BufReader;
+    ///
+    /// use rbx_binary::Deserializer;
+    ///
+    /// let input = BufReader::new(File::open("Place.rbxl")?);
+    /// let skeleton = Deserializer::new()
+    ///     .decode_properties(false)
+    ///     .deserialize(input)?;
+    ///
+    /// for &referent in skeleton.root().children() {
+    ///     let instance = skeleton.get_by_ref(referent).unwrap();
+    ///     println!("{} ({})", instance.name, instance.class);
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn decode_properties(self, decode_properties: bool) -> Self {
+        Self {
+            decode_properties,
+            ..self
+        }
+    }
+
+    /// Calls `callback` after every chunk is read, with how many bytes and
+    /// instances have been read so far. Useful for showing progress while
+    /// reading large places.
+    pub fn progress<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&Progress) + Send + Sync + 'a,
+    {
+        self.progress = Some(Box::new(callback));
+        self
+    }
+
+    /// Stops deserializing with an error once `token` is set to `true`. The
+    /// token is checked before every chunk is read, so setting it from another
+    /// thread lets interactive tools abandon work that is no longer needed.
+    ///
+    /// Errors caused by cancellation can be told apart with
+    /// [`Error::is_cancelled`].
+    pub fn cancel_token(self, token: Arc<AtomicBool>) -> Self {
+        Self {
+            cancel: Some(token),
+            ..self
+        }
+    }
+
+    /// Derives the referent of every instance from `namespace` and the
+    /// instance's referent in the file, using [`Ref::from_namespace`], instead
+    /// of choosing referents at random.
+    ///
+    /// Decoding the same file twice with the same namespace gives instances
+    /// the same referents both times, so separate processes can tell which
+    /// instances match without sharing a table of referents. The root
+    /// DataModel is given `namespace` itself.
+    pub fn namespace(self, namespace: Ref) -> Self {
+        Self {
+            namespace: Some(namespace),
+            ..self
+        }
+    }
+
+    /// Sets whether files from newer versions of Roblox are read as well as
+    /// possible instead of failing. Defaults to `false`.
+    ///
+    /// In forward compatible mode, files with a newer format version are read
+    /// as if they had the newest version that rbx_binary knows about, and
+    /// chunks with versions that rbx_binary can't read are skipped. Both are
+    /// reported through [`Deserializer::diagnostics`].
+    ///
+    /// Chunks with names that rbx_binary doesn't know about are always kept in
+    /// the DOM's [`UnknownChunks`][crate::UnknownChunks], whether or not this
+    /// is on.
+    pub fn forward_compatible(self, forward_compatible: bool) -> Self {
+        Self {
+            forward_compatible,
+            ..self
+        }
+    }
+
+    /// Determines which line endings rbx_binary will give script sources,
+    /// which are the `Source` property of scripts. Defaults to
+    /// [`LineEndings::Preserve`].
+    pub fn line_endings(self, line_endings: LineEndings) -> Self {
+        Self {
+            line_endings,
+            ..self
+        }
+    }
+
+    /// Calls `callback` with every [`Diagnostic`][crate::Diagnostic] about
+    /// parts of the file that weren't understood. Without a callback,
+    /// diagnostics are logged with the `log` crate.
+    pub fn diagnostics<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&Diagnostic) + Send + Sync + 'a,
+    {
+        self.diagnostics = Some(Box::new(callback));
+        self
+    }
+
+    /// Reports a diagnostic to the callback given to
+    /// [`Deserializer::diagnostics`], or to the log if there isn't one.
+    pub(crate) fn diagnose(&self, diagnostic: Diagnostic) {
+        match &self.diagnostics {
+            Some(callback) => callback(&diagnostic),
+            None => log::info!("{}", diagnostic),
+        }
+    }
+
+    /// Returns the referent to give the root DataModel, which isn't in the
+    /// file. With a namespace, that's the namespace itself.
+    pub(crate) fn root_referent(&self) -> Ref {
+        self.namespace
+            .filter(|namespace| namespace.is_some())
+            .unwrap_or_else(Ref::new)
+    }
+
+    /// Returns the referent to give an instance, given its referent in the
+    /// file.
+    pub(crate) fn referent_for(&self, file_referent: i32) -> Ref {
+        match self.namespace {
+            Some(namespace) => Ref::from_namespace(namespace, &file_referent.to_le_bytes()),
+            None => Ref::new(),
+        }
+    }
+
+    /// Tells whether the given property should be decoded for instances of
+    /// the given class.
+    pub(crate) fn decodes_property(&self, class: &str, property: &str) -> bool {
+        if self.skip_classes.contains(class) {
+            return false;
+        }
+
+        if !self.decode_properties && property != "Name" {
+            return false;
+        }
+
+        match &self.only_classes {
+            Some(only_classes) => property == "Name" || only_classes.contains(class),
+            None => true,
         }
     }
 
@@ -55,25 +297,50 @@ impl<'a> Deserializer<'a> {
     pub fn deserialize<R: Read>(&self, reader: R) -> Result<WeakDom, Error> {
         profiling::scope!("rbx_binary::deserialize");
 
-        let mut deserializer = DeserializerState::new(self, reader)?;
+        let mut deserializer = DeserializerState::new(self, Counting::new(reader))?;
 
         loop {
+            if let Some(cancel) = &self.cancel {
+                if cancel.load(Ordering::Relaxed) {
+                    return Err(InnerError::Cancelled.into());
+                }
+            }
+
             let chunk = deserializer.next_chunk()?;
+            let name = chunk.name;
+
+            let result = match &name {
+                b"META" => Some(deserializer.decode_meta_chunk(&chunk.data)),
+                b"SSTR" => Some(deserializer.decode_sstr_chunk(&chunk.data)),
+                b"INST" => Some(deserializer.decode_inst_chunk(&chunk.data)),
+                b"PROP" => Some(deserializer.decode_prop_chunk(&chunk.data)),
+                b"PRNT" => Some(deserializer.decode_prnt_chunk(&chunk.data)),
+                b"END\0" => Some(deserializer.decode_end_chunk(&chunk.data)),
+                _ => None,
+            };
 
-            match &chunk.name {
-                b"META" => deserializer.decode_meta_chunk(&chunk.data)?,
-                b"SSTR" => deserializer.decode_sstr_chunk(&chunk.data)?,
-                b"INST" => deserializer.decode_inst_chunk(&chunk.data)?,
-                b"PROP" => deserializer.decode_prop_chunk(&chunk.data)?,
-                b"PRNT" => deserializer.decode_prnt_chunk(&chunk.data)?,
-                b"END\0" => {
-                    deserializer.decode_end_chunk(&chunk.data)?;
-                    break;
+            match result {
+                Some(Err(InnerError::UnknownChunkVersion { version, .. }))
+                    if self.forward_compatible =>
+                {
+                    // Chunks like PRNT and SSTR are written again by the
+                    // serializer, so keeping the unread copy would only leave
+                    // stale data behind.
+                    self.diagnose(Diagnostic::UnknownChunkVersion { name, version });
                 }
-                _ => match str::from_utf8(&chunk.name) {
-                    Ok(name) => log::info!("Unknown binary chunk name {}", name),
-                    Err(_) => log::info!("Unknown binary chunk name {:?}", chunk.name),
-                },
+                Some(result) => result?,
+                None => {
+                    self.diagnose(Diagnostic::UnknownChunk { name });
+                    deserializer.keep_unknown_chunk(chunk);
+                }
+            }
+
+            if let Some(progress) = &self.progress {
+                progress(&deserializer.progress(name));
+            }
+
+            if &name == b"END\0" {
+                break;
             }
         }
 
@@ -1,5 +1,6 @@
 use std::{
-    collections::{HashMap, HashSet, VecDeque},
+    borrow::Cow,
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
     convert::TryInto,
     io::Read,
 };
@@ -12,13 +13,15 @@ use rbx_dom_weak::{
         PhysicalProperties, Ray, Rect, Ref, SharedString, Tags, UDim, UDim2, UniqueId, Variant,
         VariantType, Vector2, Vector3, Vector3int16,
     },
-    InstanceBuilder, WeakDom,
+    FileMetadata, InstanceBuilder, LineEndings, WeakDom,
 };
 use rbx_reflection::{DataType, PropertyKind, PropertySerialization, ReflectionDatabase};
 
 use crate::{
     chunk::Chunk,
-    core::{find_property_descriptors, RbxReadExt},
+    compat::{Diagnostic, UnknownChunk, UnknownChunks},
+    core::{RbxReadExt, FILE_VERSION},
+    progress::{Counting, Progress},
     types::Type,
 };
 
@@ -29,7 +32,10 @@ pub(super) struct DeserializerState<'a, R> {
     deserializer: &'a Deserializer<'a>,
 
     /// The input data encoded as a binary model.
-    input: R,
+    input: Counting<R>,
+
+    /// The total number of instances described by the file's header.
+    total_instances: usize,
 
     /// The tree that instances should be written into. Eventually returned to
     /// the user.
@@ -37,7 +43,7 @@ pub(super) struct DeserializerState<'a, R> {
 
     /// The metadata contained in the file, which affects how some constructs
     /// are interpreted by Roblox.
-    metadata: HashMap<String, String>,
+    metadata: BTreeMap<String, String>,
 
     /// The SharedStrings contained in the file, if any, in the order that they
     /// appear in the file.
@@ -57,6 +63,9 @@ pub(super) struct DeserializerState<'a, R> {
     /// deserializing this file. We use this map in order to ensure we only
     /// print one warning per unknown type ID when deserializing a file.
     unknown_type_ids: HashSet<u8>,
+
+    /// Chunks that weren't read, in the order they appear in the file.
+    unknown_chunks: Vec<UnknownChunk>,
 }
 
 /// Represents a unique instance class. Binary models define all their instance
@@ -80,8 +89,17 @@ struct Instance {
     /// A work-in-progress builder that will be used to construct this instance.
     builder: InstanceBuilder,
 
+    /// The file-defined ID of this instance's type.
+    type_id: u32,
+
     /// Document-defined IDs for the children of this instance.
     children: Vec<i32>,
+
+    /// The names of this instance's `Ref` properties that point to other
+    /// instances, along with the document-defined IDs they point to. Only
+    /// filled in when the deserializer leaves out instances, so that
+    /// references to instances that were left out can be cleared.
+    ref_targets: Vec<(String, i32)>,
 }
 
 /// Properties may be serialized under different names or types than
@@ -102,7 +120,7 @@ fn find_canonical_property<'de>(
     class_name: &str,
     prop_name: &'de str,
 ) -> Option<CanonicalProperty<'de>> {
-    match find_property_descriptors(database, class_name, prop_name) {
+    match database.find_property_descriptors(class_name, prop_name) {
         Some(descriptors) => {
             // If this descriptor is known but wasn't supposed to be
             // serialized, we should skip it.
@@ -213,25 +231,41 @@ fn add_property(instance: &mut Instance, canonical_property: &CanonicalProperty,
 impl<'a, R: Read> DeserializerState<'a, R> {
     pub(super) fn new(
         deserializer: &'a Deserializer<'a>,
-        mut input: R,
+        mut input: Counting<R>,
     ) -> Result<Self, InnerError> {
-        let tree = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let tree = WeakDom::new(
+            InstanceBuilder::new("DataModel").with_referent(deserializer.root_referent()),
+        );
 
         let header = FileHeader::decode(&mut input)?;
 
+        if header.version != FILE_VERSION {
+            if deserializer.forward_compatible && header.version > FILE_VERSION {
+                deserializer.diagnose(Diagnostic::NewerFileVersion {
+                    version: header.version,
+                });
+            } else {
+                return Err(InnerError::UnknownFileVersion {
+                    version: header.version,
+                });
+            }
+        }
+
         let type_infos = HashMap::with_capacity(header.num_types as usize);
         let instances_by_ref = HashMap::with_capacity(1 + header.num_instances as usize);
 
         Ok(DeserializerState {
             deserializer,
             input,
+            total_instances: header.num_instances as usize,
             tree,
-            metadata: HashMap::new(),
+            metadata: BTreeMap::new(),
             shared_strings: Vec::new(),
             type_infos,
             instances_by_ref,
             root_instance_refs: Vec::new(),
             unknown_type_ids: HashSet::new(),
+            unknown_chunks: Vec::new(),
         })
     }
 
@@ -239,10 +273,26 @@ impl<'a, R: Read> DeserializerState<'a, R> {
         Ok(Chunk::decode(&mut self.input)?)
     }
 
+    /// Keeps a chunk that wasn't read so that it can be written back out.
+    pub(super) fn keep_unknown_chunk(&mut self, chunk: Chunk) {
+        self.unknown_chunks.push(UnknownChunk {
+            name: chunk.name,
+            data: chunk.data,
+        });
+    }
+
+    pub(super) fn progress(&self, chunk: [u8; 4]) -> Progress {
+        Progress {
+            bytes: self.input.count(),
+            instances: self.instances_by_ref.len(),
+            total_instances: self.total_instances,
+            chunk,
+        }
+    }
+
     #[profiling::function]
     pub(super) fn decode_meta_chunk(&mut self, mut chunk: &[u8]) -> Result<(), InnerError> {
         let len = chunk.read_le_u32()?;
-        self.metadata.reserve(len as usize);
 
         for _ in 0..len {
             let key = chunk.read_string()?;
@@ -297,11 +347,18 @@ impl<'a, R: Read> DeserializerState<'a, R> {
         // TODO: Check object_format and check for service markers if it's 1?
 
         for &referent in &referents {
+            if self.instances_by_ref.contains_key(&referent) {
+                return Err(InnerError::DuplicateReferent { referent });
+            }
+
             self.instances_by_ref.insert(
                 referent,
                 Instance {
-                    builder: InstanceBuilder::new(&type_name),
+                    builder: InstanceBuilder::new(&type_name)
+                        .with_referent(self.deserializer.referent_for(referent)),
+                    type_id,
                     children: Vec::new(),
+                    ref_targets: Vec::new(),
                 },
             );
         }
@@ -328,6 +385,13 @@ impl<'a, R: Read> DeserializerState<'a, R> {
             .get(&type_id)
             .ok_or(InnerError::InvalidTypeId { type_id })?;
 
+        if !self
+            .deserializer
+            .decodes_property(&type_info.type_name, &prop_name)
+        {
+            return Ok(());
+        }
+
         // PROP chunks that contain no type byte are ignored by Roblox. This can
         // happen when a new type is introduced.
         //
@@ -397,9 +461,18 @@ impl<'a, R: Read> DeserializerState<'a, R> {
         match binary_type {
             Type::String => match canonical_type {
                 VariantType::String => {
+                    let line_endings = match property.name {
+                        "Source" => self.deserializer.line_endings,
+                        _ => LineEndings::Preserve,
+                    };
+
                     for referent in &type_info.referents {
                         let instance = self.instances_by_ref.get_mut(referent).unwrap();
                         let value = chunk.read_string()?;
+                        let value = match line_endings.apply(&value) {
+                            Cow::Borrowed(_) => value,
+                            Cow::Owned(value) => value,
+                        };
                         add_property(instance, &property, value.into());
                     }
                 }
@@ -887,6 +960,8 @@ impl<'a, R: Read> DeserializerState<'a, R> {
                     let mut refs = vec![0; type_info.referents.len()];
                     chunk.read_referent_array(&mut refs)?;
 
+                    let filters_instances = self.deserializer.filters_instances();
+
                     for (value, referent) in refs.into_iter().zip(&type_info.referents) {
                         let rbx_value = if let Some(instance) = self.instances_by_ref.get(&value) {
                             instance.builder.referent()
@@ -895,6 +970,9 @@ impl<'a, R: Read> DeserializerState<'a, R> {
                         };
 
                         let instance = self.instances_by_ref.get_mut(referent).unwrap();
+                        if filters_instances && rbx_value.is_some() {
+                            instance.ref_targets.push((property.name.to_owned(), value));
+                        }
                         add_property(instance, &property, rbx_value.into());
                     }
                 }
@@ -1368,6 +1446,54 @@ impl<'a, R: Read> DeserializerState<'a, R> {
         Ok(())
     }
 
+    fn class_of(&self, referent: i32) -> &str {
+        let type_id = self.instances_by_ref[&referent].type_id;
+        &self.type_infos[&type_id].type_name
+    }
+
+    /// Adds every instance under `roots` to `kept` if it or any of its
+    /// descendants has one of the given classes. Skipped classes and their
+    /// descendants are never kept.
+    ///
+    /// This uses a worklist instead of recursion so that deeply nested files
+    /// can't overflow the stack.
+    fn find_kept(&self, roots: &[i32], only_classes: &HashSet<String>) -> HashSet<i32> {
+        // Every instance comes after its parent in `order`, so walking it
+        // backwards visits children before their parents.
+        let mut order = Vec::new();
+        let mut visited = HashSet::new();
+        let mut to_visit: VecDeque<i32> = roots.iter().copied().collect();
+
+        while let Some(referent) = to_visit.pop_front() {
+            if !visited.insert(referent)
+                || self
+                    .deserializer
+                    .skip_classes
+                    .contains(self.class_of(referent))
+            {
+                continue;
+            }
+
+            order.push(referent);
+            to_visit.extend(&self.instances_by_ref[&referent].children);
+        }
+
+        let mut kept = HashSet::new();
+        for &referent in order.iter().rev() {
+            let keep = only_classes.contains(self.class_of(referent))
+                || self.instances_by_ref[&referent]
+                    .children
+                    .iter()
+                    .any(|child| kept.contains(child));
+
+            if keep {
+                kept.insert(referent);
+            }
+        }
+
+        kept
+    }
+
     /// Combines together all the decoded information to build and emplace
     /// instances in our tree.
     #[profiling::function]
@@ -1384,16 +1510,84 @@ impl<'a, R: Read> DeserializerState<'a, R> {
         // start at the top of the tree to begin construction.
         let root_ref = self.tree.root_ref();
         for &referent in &self.root_instance_refs {
+            if let Some(top_level_classes) = &self.deserializer.top_level_classes {
+                if !top_level_classes.contains(self.class_of(referent)) {
+                    continue;
+                }
+            }
+
             instances_to_construct.push_back((referent, root_ref));
         }
 
+        let kept = self.deserializer.only_classes.as_ref().map(|only_classes| {
+            let roots: Vec<i32> = instances_to_construct
+                .iter()
+                .map(|(referent, _)| *referent)
+                .collect();
+            self.find_kept(&roots, only_classes)
+        });
+
+        // Work out which instances will be constructed before constructing
+        // any of them, so that references to instances that are left out can
+        // be cleared instead of pointing at nothing.
+        let mut construct_order = Vec::new();
         while let Some((referent, parent_ref)) = instances_to_construct.pop_front() {
-            let instance = self.instances_by_ref.remove(&referent).unwrap();
-            let id = self.tree.insert(parent_ref, instance.builder);
+            let instance = &self.instances_by_ref[&referent];
+
+            let class = &self.type_infos[&instance.type_id].type_name;
+            if self.deserializer.skip_classes.contains(class) {
+                continue;
+            }
+            if let Some(kept) = &kept {
+                if !kept.contains(&referent) {
+                    continue;
+                }
+            }
+
+            let id = instance.builder.referent();
+            for &child in &instance.children {
+                instances_to_construct.push_back((child, id));
+            }
+            construct_order.push((referent, parent_ref));
+        }
+
+        let constructed: HashSet<i32> = if self.deserializer.filters_instances() {
+            construct_order
+                .iter()
+                .map(|(referent, _)| *referent)
+                .collect()
+        } else {
+            HashSet::new()
+        };
 
-            for referent in instance.children {
-                instances_to_construct.push_back((referent, id));
+        for (referent, parent_ref) in construct_order {
+            let mut instance = self.instances_by_ref.remove(&referent).unwrap();
+
+            for (name, target) in instance.ref_targets {
+                if !constructed.contains(&target) {
+                    instance.builder.add_property(name, Ref::none());
+                }
             }
+
+            self.tree.insert(parent_ref, instance.builder);
+        }
+
+        if !self.metadata.is_empty() {
+            self.tree.insert_extension(
+                root_ref,
+                FileMetadata {
+                    entries: self.metadata,
+                },
+            );
+        }
+
+        if !self.unknown_chunks.is_empty() {
+            self.tree.insert_extension(
+                root_ref,
+                UnknownChunks {
+                    chunks: self.unknown_chunks,
+                },
+            );
         }
 
         self.tree
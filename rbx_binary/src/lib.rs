@@ -49,13 +49,23 @@ rbx_binary::to_writer(output, &dom, &[dom.root_ref()])?;
 
 # Ok::<(), Box<dyn std::error::Error>>(())
 ```
+
+# WebAssembly
+
+rbx_binary can be built for `wasm32-unknown-unknown`. The `zstd` feature, which
+is enabled by default, links against the C implementation of ZSTD and should be
+disabled for this target; ZSTD compressed chunks can't be read without it.
+Enable rbx_dom_weak's `js` feature to generate referents using the browser's
+random number generator.
 */
 
 #![deny(missing_docs)]
 
 mod chunk;
+mod compat;
 mod core;
 mod deserializer;
+mod progress;
 mod serializer;
 mod types;
 
@@ -69,6 +79,8 @@ use std::io::{Read, Write};
 
 use rbx_dom_weak::{types::Ref, WeakDom};
 
+pub use rbx_dom_weak::LineEndings;
+
 /// An unstable textual format that can be used to debug binary models.
 #[cfg(feature = "unstable_text_format")]
 pub mod text_format {
@@ -76,8 +88,10 @@ pub mod text_format {
 }
 
 pub use crate::{
+    compat::{Diagnostic, UnknownChunk, UnknownChunks},
     deserializer::{Deserializer, Error as DecodeError},
-    serializer::{Error as EncodeError, Serializer},
+    progress::Progress,
+    serializer::{CompressionType, Error as EncodeError, Serializer},
 };
 
 /// Deserialize a Roblox binary model or place from a stream.
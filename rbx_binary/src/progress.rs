@@ -0,0 +1,59 @@
+use std::io::{self, Read, Write};
+
+/// How far along a [`Deserializer`][crate::Deserializer] or
+/// [`Serializer`][crate::Serializer] is, passed to the callback given to their
+/// `progress` methods after every chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Progress {
+    /// The number of bytes read or written so far, including the file header.
+    pub bytes: u64,
+
+    /// The number of instances declared so far.
+    pub instances: usize,
+
+    /// The number of instances in the whole file.
+    pub total_instances: usize,
+
+    /// The name of the chunk that was just read or written, like `PROP`.
+    pub chunk: [u8; 4],
+}
+
+/// A callback that is told about progress.
+pub(crate) type ProgressCallback<'a> = dyn Fn(&Progress) + Send + Sync + 'a;
+
+/// Wraps a stream to count how many bytes have gone through it.
+pub(crate) struct Counting<T> {
+    inner: T,
+    bytes: u64,
+}
+
+impl<T> Counting<T> {
+    pub(crate) fn new(inner: T) -> Self {
+        Counting { inner, bytes: 0 }
+    }
+
+    pub(crate) fn count(&self) -> u64 {
+        self.bytes
+    }
+}
+
+impl<R: Read> Read for Counting<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.bytes += read as u64;
+        Ok(read)
+    }
+}
+
+impl<W: Write> Write for Counting<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.bytes += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
@@ -10,6 +10,14 @@ pub struct Error {
     source: Box<InnerError>,
 }
 
+impl Error {
+    /// Tells whether this error happened because the operation was cancelled
+    /// using a cancel token.
+    pub fn is_cancelled(&self) -> bool {
+        matches!(*self.source, InnerError::Cancelled)
+    }
+}
+
 impl From<InnerError> for Error {
     fn from(inner: InnerError) -> Self {
         Self {
@@ -26,6 +34,9 @@ pub(super) enum InnerError {
         source: io::Error,
     },
 
+    #[error("The operation was cancelled")]
+    Cancelled,
+
     #[error(
         "Property type mismatch: Expected {type_name}.{prop_name} to be of type {valid_type_names}, \
         but it was of type {actual_type_name} on instance {instance_full_name}",
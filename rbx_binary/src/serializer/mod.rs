@@ -1,9 +1,16 @@
 mod error;
 mod state;
 
-use std::io::Write;
+use std::{
+    collections::{HashMap, HashSet},
+    io::Write,
+    sync::{atomic::AtomicBool, Arc},
+};
 
-use rbx_dom_weak::{types::Ref, WeakDom};
+use rbx_dom_weak::{types::Ref, Instance, LineEndings, WeakDom};
+use rbx_reflection::ReflectionDatabase;
+
+use crate::progress::{Progress, ProgressCallback};
 
 use self::state::SerializerState;
 
@@ -28,15 +35,161 @@ pub use self::error::Error;
 /// # Ok::<(), Box<dyn std::error::Error>>(())
 /// ```
 // future settings:
-// * reflection_database: Option<ReflectionDatabase> = default
 // * recursive: bool = true
 #[non_exhaustive]
-pub struct Serializer {}
+pub struct Serializer<'a> {
+    database: &'a ReflectionDatabase<'a>,
+    compression: CompressionType,
+    progress: Option<Box<ProgressCallback<'a>>>,
+    cancel: Option<Arc<AtomicBool>>,
+    skip_default_properties: bool,
+    preserve_unknown_chunks: bool,
+    line_endings: LineEndings,
+    strip_classes: HashSet<String>,
+    skip_instances: HashSet<Ref>,
+    class_map: HashMap<String, String>,
+}
 
-impl Serializer {
+impl<'a> Serializer<'a> {
     /// Create a new `Serializer` with the default settings.
     pub fn new() -> Self {
-        Serializer {}
+        Serializer {
+            database: rbx_reflection_database::get(),
+            compression: CompressionType::Lz4,
+            progress: None,
+            cancel: None,
+            skip_default_properties: false,
+            preserve_unknown_chunks: false,
+            line_endings: LineEndings::Preserve,
+            strip_classes: HashSet::new(),
+            skip_instances: HashSet::new(),
+            class_map: HashMap::new(),
+        }
+    }
+
+    /// Sets the reflection database used to decide how properties are written.
+    /// Defaults to the database bundled with `rbx_reflection_database`.
+    pub fn reflection_database(self, database: &'a ReflectionDatabase<'a>) -> Self {
+        Self { database, ..self }
+    }
+
+    /// Sets the compression that will be applied to chunks written by this
+    /// serializer. Defaults to [`CompressionType::Lz4`].
+    pub fn compression_type(self, compression: CompressionType) -> Self {
+        Self {
+            compression,
+            ..self
+        }
+    }
+
+    /// Calls `callback` after every chunk is written, with how many bytes and
+    /// instances have been written so far. Useful for showing progress while
+    /// writing large places.
+    pub fn progress<F>(self, callback: F) -> Self
+    where
+        F: Fn(&Progress) + Send + Sync + 'a,
+    {
+        Self {
+            progress: Some(Box::new(callback)),
+            ..self
+        }
+    }
+
+    /// Stops serializing with an error once `token` is set to `true`. The
+    /// token is checked before every chunk is written, so setting it from
+    /// another thread lets interactive tools abandon work that is no longer
+    /// needed. Whatever was written before then should be thrown away.
+    ///
+    /// Errors caused by cancellation can be told apart with
+    /// [`Error::is_cancelled`].
+    pub fn cancel_token(self, token: Arc<AtomicBool>) -> Self {
+        Self {
+            cancel: Some(token),
+            ..self
+        }
+    }
+
+    /// Sets whether properties are left out when they have the reflection
+    /// database's default value for their class. Defaults to `false`.
+    ///
+    /// The binary format stores each property for every instance of a class
+    /// together, so a property is only left out when every instance of that
+    /// class has the default value. Roblox fills in missing properties with
+    /// their defaults when loading a file, so this only changes the size of
+    /// the output.
+    pub fn skip_default_properties(self, skip_default_properties: bool) -> Self {
+        Self {
+            skip_default_properties,
+            ..self
+        }
+    }
+
+    /// Sets whether chunks that were kept in a DOM's
+    /// [`UnknownChunks`][crate::UnknownChunks] when it was deserialized are
+    /// written back out. Defaults to `false`.
+    ///
+    /// Only turn this on when the chunks are known to still make sense for
+    /// the DOM, since they're written unchanged even if the DOM was edited.
+    pub fn preserve_unknown_chunks(self, preserve_unknown_chunks: bool) -> Self {
+        Self {
+            preserve_unknown_chunks,
+            ..self
+        }
+    }
+
+    /// Determines which line endings rbx_binary will give script sources,
+    /// which are the `Source` property of scripts. Defaults to
+    /// [`LineEndings::Preserve`].
+    pub fn line_endings(self, line_endings: LineEndings) -> Self {
+        Self {
+            line_endings,
+            ..self
+        }
+    }
+
+    /// Leaves out every instance with the given ClassName, along with all of
+    /// its descendants.
+    ///
+    /// This is meant for classes that only exist inside a tool's own
+    /// pipeline, like marker instances, which Roblox would not be able to
+    /// load.
+    pub fn strip_class<S: Into<String>>(mut self, class: S) -> Self {
+        self.strip_classes.insert(class.into());
+        self
+    }
+
+    /// Leaves out the instances with the given referents, along with all of
+    /// their descendants, without changing the DOM.
+    ///
+    /// This lets tools leave out instances picked by their own rules, like
+    /// tests or editor-only helpers, and still write the whole DOM elsewhere.
+    pub fn skip_instances<I: IntoIterator<Item = Ref>>(mut self, referents: I) -> Self {
+        self.skip_instances.extend(referents);
+        self
+    }
+
+    /// Writes every instance with the ClassName `from` as if its ClassName was
+    /// `to`. Properties are written using the reflection information for
+    /// `to`.
+    ///
+    /// Like [`Serializer::strip_class`], this is meant for classes that only
+    /// exist inside a tool's own pipeline, which can be written out as a class
+    /// that Roblox knows about, like `Folder`.
+    pub fn map_class<F: Into<String>, T: Into<String>>(mut self, from: F, to: T) -> Self {
+        self.class_map.insert(from.into(), to.into());
+        self
+    }
+
+    /// Returns the ClassName that the instance should be written as, or
+    /// `None` if it should be left out.
+    pub(crate) fn written_class<'b>(&'b self, instance: &'b Instance) -> Option<&'b str> {
+        let class = instance.class.as_str();
+        if self.strip_classes.contains(class) || self.skip_instances.contains(&instance.referent())
+        {
+            None
+        } else {
+            Some(self.class_map.get(class).map_or(class, String::as_str))
+        }
     }
 
     /// Serialize a Roblox binary model or place into the given stream using
@@ -44,7 +197,7 @@ impl Serializer {
     pub fn serialize<W: Write>(&self, writer: W, dom: &WeakDom, refs: &[Ref]) -> Result<(), Error> {
         profiling::scope!("rbx_binary::seserialize");
 
-        let mut serializer = SerializerState::new(dom, writer);
+        let mut serializer = SerializerState::new(self, dom, writer);
 
         serializer.add_instances(refs)?;
         serializer.generate_referents();
@@ -54,13 +207,33 @@ impl Serializer {
         serializer.serialize_instances()?;
         serializer.serialize_properties()?;
         serializer.serialize_parents()?;
+        serializer.serialize_unknown_chunks()?;
         serializer.serialize_end()?;
 
         Ok(())
     }
 }
 
-impl Default for Serializer {
+/// The kinds of compression that the serializer can apply to chunks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CompressionType {
+    /// Compress chunks with LZ4. This is what Roblox Studio does.
+    Lz4,
+
+    /// Compress chunks with ZSTD. Newer versions of Roblox Studio can read
+    /// these chunks and they are usually smaller than LZ4.
+    ///
+    /// Requires the `zstd` feature, which is enabled by default.
+    #[cfg(feature = "zstd")]
+    Zstd,
+
+    /// Leave chunks uncompressed. This produces larger files that are faster
+    /// to write.
+    None,
+}
+
+impl Default for Serializer<'_> {
     fn default() -> Self {
         Self::new()
     }
@@ -2,7 +2,8 @@ use std::{
     borrow::{Borrow, Cow},
     collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
     convert::TryInto,
-    io::Write,
+    io::{self, Write},
+    sync::atomic::{AtomicBool, Ordering},
     u32,
 };
 
@@ -13,23 +14,26 @@ use rbx_dom_weak::{
         NumberSequenceKeypoint, PhysicalProperties, Ray, Rect, Ref, SharedString, Tags, UDim,
         UDim2, UniqueId, Variant, VariantType, Vector2, Vector3, Vector3int16,
     },
-    Instance, WeakDom,
+    FileMetadata, Instance, WeakDom,
 };
 
-use rbx_reflection::{ClassDescriptor, ClassTag, DataType};
+use rbx_reflection::{ClassDescriptor, ClassTag, DataType, ReflectionDatabase};
 
 use crate::{
     chunk::{ChunkBuilder, ChunkCompression},
-    core::{
-        find_property_descriptors, RbxWriteExt, FILE_MAGIC_HEADER, FILE_SIGNATURE, FILE_VERSION,
-    },
+    compat::UnknownChunks,
+    core::{RbxWriteExt, FILE_MAGIC_HEADER, FILE_SIGNATURE, FILE_VERSION},
+    progress::{Counting, Progress, ProgressCallback},
     types::Type,
 };
 
-use super::error::InnerError;
+use super::{error::InnerError, CompressionType, Serializer};
 
 static FILE_FOOTER: &[u8] = b"</roblox>";
 
+/// The names of every chunk that the serializer writes itself.
+static WRITTEN_CHUNKS: &[&[u8; 4]] = &[b"META", b"SSTR", b"INST", b"PROP", b"PRNT", b"END\0"];
+
 /// Represents all of the state during a single serialization session. A new
 /// `BinarySerializer` object should be created every time we want to serialize
 /// a binary model file.
@@ -37,8 +41,11 @@ pub(super) struct SerializerState<'dom, W> {
     /// The dom containing all of the instances that we're serializing.
     dom: &'dom WeakDom,
 
+    /// The serializer whose options are being used.
+    serializer: &'dom Serializer<'dom>,
+
     /// Where the binary output should be written.
-    output: W,
+    output: Output<'dom, W>,
 
     /// All of the instances, in a deterministic order, that we're going to be
     /// serializing.
@@ -59,6 +66,60 @@ pub(super) struct SerializerState<'dom, W> {
     /// A map of SharedStrings to where it is in the SSTR chunk. This is used
     /// for writing PROP chunks.
     shared_string_ids: HashMap<SharedString, u32>,
+
+    /// The compression applied to every chunk except END, which is always
+    /// written uncompressed.
+    compression: ChunkCompression,
+}
+
+/// Wraps the stream that the serializer writes to, keeping track of progress
+/// as chunks are written.
+struct Output<'a, W> {
+    writer: Counting<W>,
+
+    /// The number of instances that INST chunks have been written for.
+    instances: usize,
+
+    /// The number of instances being serialized.
+    total_instances: usize,
+
+    progress: Option<&'a ProgressCallback<'a>>,
+
+    cancel: Option<&'a AtomicBool>,
+}
+
+impl<W: Write> Output<'_, W> {
+    fn dump_chunk(&mut self, chunk: ChunkBuilder) -> Result<(), InnerError> {
+        if let Some(cancel) = self.cancel {
+            if cancel.load(Ordering::Relaxed) {
+                return Err(InnerError::Cancelled);
+            }
+        }
+
+        let name = chunk.chunk_name();
+        chunk.dump(&mut self.writer)?;
+
+        if let Some(progress) = self.progress {
+            progress(&Progress {
+                bytes: self.writer.count(),
+                instances: self.instances,
+                total_instances: self.total_instances,
+                chunk: name,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for Output<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
 }
 
 /// An instance class that our serializer knows about. We should have one struct
@@ -82,16 +143,16 @@ struct TypeInfo<'dom> {
     ///
     /// Stored in a sorted map to try to ensure that we write out properties in
     /// a deterministic order.
-    properties: BTreeMap<Cow<'static, str>, PropInfo>,
+    properties: BTreeMap<Cow<'dom, str>, PropInfo<'dom>>,
 
     /// A reference to the type's class descriptor from rbx_reflection, if this
     /// is a known class.
-    class_descriptor: Option<&'static ClassDescriptor<'static>>,
+    class_descriptor: Option<&'dom ClassDescriptor<'dom>>,
 
     /// A set containing the properties that we have seen so far in the file and
     /// processed. This helps us avoid traversing the reflection database
     /// multiple times if there are many copies of the same kind of instance.
-    properties_visited: HashSet<(Cow<'static, str>, VariantType)>,
+    properties_visited: HashSet<(Cow<'dom, str>, VariantType)>,
 }
 
 /// A property on a specific class that our serializer knows about.
@@ -101,7 +162,7 @@ struct TypeInfo<'dom> {
 /// `BasePart.size` are present in the same document, they should share a
 /// `PropInfo` as they are the same logical property.
 #[derive(Debug)]
-struct PropInfo {
+struct PropInfo<'dom> {
     /// The binary format type ID that will be use to serialize this property.
     /// This type is related to the type of the serialized form of the logical
     /// property, but is not 1:1.
@@ -114,7 +175,7 @@ struct PropInfo {
     /// The serialized name for this property. This is the name that is actually
     /// written as part of the PROP chunk and may not line up with the canonical
     /// name for the property.
-    serialized_name: Cow<'static, str>,
+    serialized_name: Cow<'dom, str>,
 
     /// A set containing the names of all aliases discovered while preparing to
     /// serialize this property. Ideally, this set will remain empty (and not
@@ -133,7 +194,7 @@ struct PropInfo {
     ///
     /// Default values are first populated from the reflection database, if
     /// present, followed by an educated guess based on the type of the value.
-    default_value: Cow<'static, Variant>,
+    default_value: Cow<'dom, Variant>,
 }
 
 /// Contains all of the `TypeInfo` objects known to the serializer so far. This
@@ -163,12 +224,16 @@ impl<'dom> TypeInfos<'dom> {
 
     /// Finds the type info from the given ClassName if it exists, or creates
     /// one and returns a reference to it if not.
-    fn get_or_create(&mut self, class: &str) -> &mut TypeInfo<'dom> {
+    fn get_or_create(
+        &mut self,
+        database: &'dom ReflectionDatabase<'dom>,
+        class: &str,
+    ) -> &mut TypeInfo<'dom> {
         if !self.values.contains_key(class) {
             let type_id = self.next_type_id;
             self.next_type_id += 1;
 
-            let class_descriptor = rbx_reflection_database::get().classes.get(class);
+            let class_descriptor = database.classes.get(class);
 
             let is_service = if let Some(descriptor) = &class_descriptor {
                 descriptor.tags.contains(&ClassTag::Service)
@@ -216,15 +281,30 @@ impl<'dom> TypeInfos<'dom> {
 }
 
 impl<'dom, W: Write> SerializerState<'dom, W> {
-    pub fn new(dom: &'dom WeakDom, output: W) -> Self {
+    pub fn new(serializer: &'dom Serializer<'dom>, dom: &'dom WeakDom, output: W) -> Self {
+        let compression = match serializer.compression {
+            CompressionType::Lz4 => ChunkCompression::Compressed,
+            #[cfg(feature = "zstd")]
+            CompressionType::Zstd => ChunkCompression::Zstd,
+            CompressionType::None => ChunkCompression::Uncompressed,
+        };
+
         SerializerState {
             dom,
-            output,
+            serializer,
+            output: Output {
+                writer: Counting::new(output),
+                instances: 0,
+                total_instances: 0,
+                progress: serializer.progress.as_deref(),
+                cancel: serializer.cancel.as_deref(),
+            },
             relevant_instances: Vec::new(),
             id_to_referent: HashMap::new(),
             type_infos: TypeInfos::new(),
             shared_strings: Vec::new(),
             shared_string_ids: HashMap::new(),
+            compression,
         }
     }
 
@@ -241,8 +321,13 @@ impl<'dom, W: Write> SerializerState<'dom, W> {
                 .get_by_ref(referent)
                 .ok_or(InnerError::InvalidInstanceId { referent })?;
 
+            let class = match self.serializer.written_class(instance) {
+                Some(class) => class,
+                None => continue,
+            };
+
             self.relevant_instances.push(referent);
-            self.collect_type_info(instance)?;
+            self.collect_type_info(instance, class)?;
 
             to_visit.extend(instance.children());
         }
@@ -265,8 +350,14 @@ impl<'dom, W: Write> SerializerState<'dom, W> {
     // clone canonical_name in a cold branch. We don't want to do that.
     #[allow(clippy::map_entry)]
     #[profiling::function]
-    pub fn collect_type_info(&mut self, instance: &'dom Instance) -> Result<(), InnerError> {
-        let type_info = self.type_infos.get_or_create(&instance.class);
+    pub fn collect_type_info(
+        &mut self,
+        instance: &'dom Instance,
+        class: &str,
+    ) -> Result<(), InnerError> {
+        let type_info = self
+            .type_infos
+            .get_or_create(self.serializer.database, class);
         type_info.instances.push(instance);
 
         for (prop_name, prop_value) in &instance.properties {
@@ -298,8 +389,11 @@ impl<'dom, W: Write> SerializerState<'dom, W> {
             let serialized_name;
             let serialized_ty;
 
-            let database = rbx_reflection_database::get();
-            match find_property_descriptors(database, &instance.class, prop_name) {
+            match self
+                .serializer
+                .database
+                .find_property_descriptors(class, prop_name)
+            {
                 Some(descriptors) => {
                     // For any properties that do not serialize, we can skip
                     // adding them to the set of type_infos.
@@ -417,6 +511,8 @@ impl<'dom, W: Write> SerializerState<'dom, W> {
     pub fn write_header(&mut self) -> Result<(), InnerError> {
         log::trace!("Writing header");
 
+        self.output.total_instances = self.relevant_instances.len();
+
         self.output.write_all(FILE_MAGIC_HEADER)?;
         self.output.write_all(FILE_SIGNATURE)?;
         self.output.write_le_u16(FILE_VERSION)?;
@@ -432,8 +528,23 @@ impl<'dom, W: Write> SerializerState<'dom, W> {
 
     /// Write out any metadata about this file, stored in a chunk named META.
     pub fn serialize_metadata(&mut self) -> Result<(), InnerError> {
-        log::trace!("Writing metadata (currently no-op)");
-        // TODO: There is no concept of metadata in a dom yet.
+        let metadata = match self.dom.extension::<FileMetadata>(self.dom.root_ref()) {
+            Some(metadata) if !metadata.entries.is_empty() => metadata,
+            _ => return Ok(()),
+        };
+
+        log::trace!("Writing metadata chunk");
+
+        let mut chunk = ChunkBuilder::new(b"META", self.compression);
+
+        chunk.write_le_u32(metadata.entries.len() as u32)?;
+        for (key, value) in &metadata.entries {
+            chunk.write_string(key)?;
+            chunk.write_string(value)?;
+        }
+
+        self.output.dump_chunk(chunk)?;
+
         Ok(())
     }
 
@@ -447,7 +558,7 @@ impl<'dom, W: Write> SerializerState<'dom, W> {
             return Ok(());
         }
 
-        let mut chunk = ChunkBuilder::new(b"SSTR", ChunkCompression::Compressed);
+        let mut chunk = ChunkBuilder::new(b"SSTR", self.compression);
 
         chunk.write_le_u32(0)?; // SSTR version number
         chunk.write_le_u32(self.shared_strings.len() as u32)?;
@@ -458,7 +569,7 @@ impl<'dom, W: Write> SerializerState<'dom, W> {
             chunk.write_binary_string(shared_string.data())?;
         }
 
-        chunk.dump(&mut self.output)?;
+        self.output.dump_chunk(chunk)?;
 
         Ok(())
     }
@@ -476,7 +587,7 @@ impl<'dom, W: Write> SerializerState<'dom, W> {
                 type_info.instances.len()
             );
 
-            let mut chunk = ChunkBuilder::new(b"INST", ChunkCompression::Compressed);
+            let mut chunk = ChunkBuilder::new(b"INST", self.compression);
 
             chunk.write_le_u32(type_info.type_id)?;
             chunk.write_string(type_name)?;
@@ -511,7 +622,9 @@ impl<'dom, W: Write> SerializerState<'dom, W> {
                 }
             }
 
-            chunk.dump(&mut self.output)?;
+            self.output.instances += type_info.instances.len();
+
+            self.output.dump_chunk(chunk)?;
         }
 
         Ok(())
@@ -534,12 +647,6 @@ impl<'dom, W: Write> SerializerState<'dom, W> {
                     prop_info.prop_type
                 );
 
-                let mut chunk = ChunkBuilder::new(b"PROP", ChunkCompression::Compressed);
-
-                chunk.write_le_u32(type_info.type_id)?;
-                chunk.write_string(&prop_info.serialized_name)?;
-                chunk.write_u8(prop_info.prop_type as u8)?;
-
                 let values = type_info
                     .instances
                     .iter()
@@ -573,6 +680,28 @@ impl<'dom, W: Write> SerializerState<'dom, W> {
                     })
                     .enumerate();
 
+                // Properties are written for every instance of a class at
+                // once, so a property can only be left out when every
+                // instance has the class's default value for it.
+                if self.serializer.skip_default_properties && prop_name != "Name" {
+                    let default = type_info
+                        .class_descriptor
+                        .and_then(|class| class.default_properties.get(prop_name.as_ref()));
+
+                    if let Some(default) = default {
+                        if values.clone().all(|(_, value)| value.as_ref() == default) {
+                            log::trace!("Skipping default property {}.{}", type_name, prop_name);
+                            continue;
+                        }
+                    }
+                }
+
+                let mut chunk = ChunkBuilder::new(b"PROP", self.compression);
+
+                chunk.write_le_u32(type_info.type_id)?;
+                chunk.write_string(&prop_info.serialized_name)?;
+                chunk.write_u8(prop_info.prop_type as u8)?;
+
                 // Helper to generate a type mismatch error with context from
                 // this chunk.
                 let type_mismatch =
@@ -598,6 +727,10 @@ impl<'dom, W: Write> SerializerState<'dom, W> {
                     Type::String => {
                         for (i, rbx_value) in values {
                             match rbx_value.as_ref() {
+                                Variant::String(value) if prop_name == "Source" => {
+                                    chunk
+                                        .write_string(&self.serializer.line_endings.apply(value))?;
+                                }
                                 Variant::String(value) => {
                                     chunk.write_string(value)?;
                                 }
@@ -1132,7 +1265,7 @@ impl<'dom, W: Write> SerializerState<'dom, W> {
                     }
                 }
 
-                chunk.dump(&mut self.output)?;
+                self.output.dump_chunk(chunk)?;
             }
         }
 
@@ -1145,7 +1278,7 @@ impl<'dom, W: Write> SerializerState<'dom, W> {
     pub fn serialize_parents(&mut self) -> Result<(), InnerError> {
         log::trace!("Writing parent relationships");
 
-        let mut chunk = ChunkBuilder::new(b"PRNT", ChunkCompression::Compressed);
+        let mut chunk = ChunkBuilder::new(b"PRNT", self.compression);
 
         chunk.write_u8(0)?; // PRNT version 0
         chunk.write_le_u32(self.relevant_instances.len() as u32)?;
@@ -1174,7 +1307,36 @@ impl<'dom, W: Write> SerializerState<'dom, W> {
         chunk.write_referent_array(object_referents)?;
         chunk.write_referent_array(parent_referents)?;
 
-        chunk.dump(&mut self.output)?;
+        self.output.dump_chunk(chunk)?;
+
+        Ok(())
+    }
+
+    /// Write out the chunks that were kept without being read when the DOM
+    /// was deserialized, if there are any and they should be kept.
+    pub fn serialize_unknown_chunks(&mut self) -> Result<(), InnerError> {
+        if !self.serializer.preserve_unknown_chunks {
+            return Ok(());
+        }
+
+        let unknown = match self.dom.extension::<UnknownChunks>(self.dom.root_ref()) {
+            Some(unknown) => unknown,
+            None => return Ok(()),
+        };
+
+        log::trace!("Writing {} unknown chunks", unknown.chunks.len());
+
+        for unknown_chunk in &unknown.chunks {
+            // These chunks were just written from the DOM, and a copy from an
+            // earlier file would contradict them.
+            if WRITTEN_CHUNKS.contains(&&unknown_chunk.name) {
+                continue;
+            }
+
+            let mut chunk = ChunkBuilder::new(&unknown_chunk.name, self.compression);
+            chunk.write_all(&unknown_chunk.data)?;
+            self.output.dump_chunk(chunk)?;
+        }
 
         Ok(())
     }
@@ -1188,7 +1350,7 @@ impl<'dom, W: Write> SerializerState<'dom, W> {
 
         let mut end = ChunkBuilder::new(b"END\0", ChunkCompression::Uncompressed);
         end.write_all(FILE_FOOTER)?;
-        end.dump(&mut self.output)?;
+        self.output.dump_chunk(end)?;
 
         Ok(())
     }
@@ -1269,9 +1431,21 @@ impl<'dom, W: Write> SerializerState<'dom, W> {
             VariantType::Tags => Variant::Tags(Tags::new()),
             VariantType::Content => Variant::Content(Content::new()),
             VariantType::Attributes => Variant::Attributes(Attributes::new()),
-            VariantType::UniqueId => Variant::UniqueId(UniqueId::now().unwrap()),
+            VariantType::UniqueId => Variant::UniqueId(new_unique_id()),
             VariantType::Font => Variant::Font(Font::default()),
             _ => return None,
         })
     }
 }
+
+/// Generates a `UniqueId` for an instance that doesn't have one. Ids are made
+/// at the `UniqueId` epoch when the clock can't be read, like on
+/// wasm32-unknown-unknown, which doesn't have one.
+fn new_unique_id() -> UniqueId {
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    if let Ok(unique_id) = UniqueId::now() {
+        return unique_id;
+    }
+
+    UniqueId::at(0)
+}
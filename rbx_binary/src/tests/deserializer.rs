@@ -0,0 +1,503 @@
+use rbx_dom_weak::{
+    types::{Ref, Variant, Vector3},
+    InstanceBuilder, WeakDom,
+};
+use rbx_reflection::ReflectionDatabase;
+
+use crate::{to_writer, Deserializer, Serializer};
+
+fn sample_place() -> Vec<u8> {
+    let tree =
+        WeakDom::new(
+            InstanceBuilder::new("DataModel")
+                .with_child(
+                    InstanceBuilder::new("Workspace")
+                        .with_child(InstanceBuilder::new("Terrain"))
+                        .with_child(InstanceBuilder::new("Model").with_name("House").with_child(
+                            InstanceBuilder::new("Script").with_property("Source", "a"),
+                        )),
+                )
+                .with_child(
+                    InstanceBuilder::new("ReplicatedStorage")
+                        .with_child(
+                            InstanceBuilder::new("StringValue")
+                                .with_name("Greeting")
+                                .with_property("Value", "Hello"),
+                        )
+                        .with_child(
+                            InstanceBuilder::new("ModuleScript")
+                                .with_name("Shared")
+                                .with_property("Source", "b"),
+                        ),
+                ),
+        );
+
+    let mut buffer = Vec::new();
+    to_writer(&mut buffer, &tree, tree.root().children()).expect("failed to encode place");
+    buffer
+}
+
+fn names(dom: &WeakDom) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut stack: Vec<_> = dom.root().children().iter().rev().copied().collect();
+
+    while let Some(referent) = stack.pop() {
+        let instance = dom.get_by_ref(referent).unwrap();
+        names.push(instance.name.clone());
+        stack.extend(instance.children().iter().rev());
+    }
+
+    names
+}
+
+/// Ensures that skipped classes are left out along with their descendants.
+#[test]
+fn skip_class() {
+    let dom = Deserializer::new()
+        .skip_class("Terrain")
+        .skip_class("Model")
+        .deserialize(sample_place().as_slice())
+        .expect("failed to decode place");
+
+    assert_eq!(
+        names(&dom),
+        ["Workspace", "ReplicatedStorage", "Greeting", "Shared"]
+    );
+}
+
+/// Ensures that only the requested top-level instances are kept.
+#[test]
+fn top_level_class() {
+    let dom = Deserializer::new()
+        .top_level_class("ReplicatedStorage")
+        .deserialize(sample_place().as_slice())
+        .expect("failed to decode place");
+
+    assert_eq!(names(&dom), ["ReplicatedStorage", "Greeting", "Shared"]);
+}
+
+/// Ensures that only the requested classes and their ancestors are kept, and
+/// that ancestors only have their names decoded.
+#[test]
+fn only_class() {
+    let dom = Deserializer::new()
+        .only_class("Script")
+        .only_class("ModuleScript")
+        .deserialize(sample_place().as_slice())
+        .expect("failed to decode place");
+
+    assert_eq!(
+        names(&dom),
+        [
+            "Workspace",
+            "House",
+            "Script",
+            "ReplicatedStorage",
+            "Shared"
+        ]
+    );
+
+    let workspace = dom.get_by_ref(dom.root().children()[0]).unwrap();
+    assert!(workspace.properties.is_empty());
+
+    let storage = dom.get_by_ref(dom.root().children()[1]).unwrap();
+    let shared = dom.get_by_ref(storage.children()[0]).unwrap();
+    assert_eq!(
+        shared.properties.get("Source"),
+        Some(&Variant::String("b".to_owned()))
+    );
+}
+
+/// Ensures that deeply nested files can be filtered without overflowing the
+/// stack.
+#[test]
+fn only_class_deep() {
+    let mut tree = WeakDom::new(InstanceBuilder::new("DataModel"));
+    let mut parent = tree.root_ref();
+    for _ in 0..100_000 {
+        parent = tree.insert(parent, InstanceBuilder::new("Folder"));
+    }
+    tree.insert(parent, InstanceBuilder::new("Script"));
+
+    let mut buffer = Vec::new();
+    to_writer(&mut buffer, &tree, tree.root().children()).expect("failed to encode model");
+
+    let dom = Deserializer::new()
+        .only_class("Script")
+        .deserialize(buffer.as_slice())
+        .expect("failed to decode model");
+
+    let mut depth = 0;
+    let mut referent = dom.root_ref();
+    while let Some(&child) = dom.get_by_ref(referent).unwrap().children().first() {
+        depth += 1;
+        referent = child;
+    }
+    assert_eq!(depth, 100_001);
+    assert_eq!(dom.get_by_ref(referent).unwrap().class, "Script");
+}
+
+/// Ensures that references to instances that were left out are cleared.
+#[test]
+fn filtered_refs_are_cleared() {
+    let house = InstanceBuilder::new("Model").with_name("House");
+    let greeting = InstanceBuilder::new("StringValue").with_name("Greeting");
+    let tree = WeakDom::new(
+        InstanceBuilder::new("DataModel").with_child(
+            InstanceBuilder::new("Workspace")
+                .with_child(
+                    InstanceBuilder::new("ObjectValue")
+                        .with_name("ToHouse")
+                        .with_property("Value", house.referent()),
+                )
+                .with_child(
+                    InstanceBuilder::new("ObjectValue")
+                        .with_name("ToGreeting")
+                        .with_property("Value", greeting.referent()),
+                )
+                .with_child(house)
+                .with_child(greeting),
+        ),
+    );
+
+    let mut buffer = Vec::new();
+    to_writer(&mut buffer, &tree, tree.root().children()).expect("failed to encode place");
+
+    let dom = Deserializer::new()
+        .skip_class("Model")
+        .deserialize(buffer.as_slice())
+        .expect("failed to decode place");
+
+    let workspace = dom.get_by_ref(dom.root().children()[0]).unwrap();
+    let value_of = |index: usize| {
+        let object_value = dom.get_by_ref(workspace.children()[index]).unwrap();
+        match object_value.properties.get("Value") {
+            Some(Variant::Ref(referent)) => *referent,
+            other => panic!("expected a Ref, got {:?}", other),
+        }
+    };
+
+    assert_eq!(value_of(0), Ref::none());
+    let greeting = value_of(1);
+    assert_eq!(dom.get_by_ref(greeting).unwrap().name, "Greeting");
+}
+
+/// Ensures that turning off property decoding still reads names, classes, and
+/// the hierarchy.
+#[test]
+fn skeleton() {
+    let dom = Deserializer::new()
+        .decode_properties(false)
+        .deserialize(sample_place().as_slice())
+        .expect("failed to decode place");
+
+    assert_eq!(
+        names(&dom),
+        [
+            "Workspace",
+            "Terrain",
+            "House",
+            "Script",
+            "ReplicatedStorage",
+            "Greeting",
+            "Shared"
+        ]
+    );
+
+    let storage = dom.get_by_ref(dom.root().children()[1]).unwrap();
+    let greeting = dom.get_by_ref(storage.children()[0]).unwrap();
+    assert_eq!(greeting.class, "StringValue");
+    assert!(greeting.properties.is_empty());
+}
+
+/// Ensures that the progress callback is told about every chunk that is read.
+#[test]
+fn progress() {
+    use std::sync::Mutex;
+
+    let place = sample_place();
+    let reports = Mutex::new(Vec::new());
+
+    Deserializer::new()
+        .progress(|progress| reports.lock().unwrap().push(*progress))
+        .deserialize(place.as_slice())
+        .expect("failed to decode place");
+
+    let reports = reports.into_inner().unwrap();
+    assert_eq!(&reports[0].chunk, b"INST");
+
+    let last = reports.last().unwrap();
+    assert_eq!(&last.chunk, b"END\0");
+    assert_eq!(last.bytes, place.len() as u64);
+    assert_eq!(last.instances, 7);
+    assert_eq!(last.total_instances, 7);
+}
+
+/// Ensures that setting the cancel token stops deserialization with an error.
+#[test]
+fn cancel_token() {
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+
+    let token = Arc::new(AtomicBool::new(false));
+    let error = Deserializer::new()
+        .cancel_token(Arc::clone(&token))
+        .progress(|_| token.store(true, Ordering::Relaxed))
+        .deserialize(sample_place().as_slice())
+        .expect_err("deserializing should have been cancelled");
+
+    assert!(error.is_cancelled());
+}
+
+/// Ensures that decoding with a namespace gives the same referents every time.
+#[test]
+fn namespace() {
+    let place = sample_place();
+    let namespace = Ref::new();
+
+    let decode = |namespace| {
+        let dom = Deserializer::new()
+            .namespace(namespace)
+            .deserialize(place.as_slice())
+            .expect("failed to decode place");
+
+        let mut referents = vec![dom.root_ref()];
+        let mut stack = dom.root().children().to_vec();
+
+        while let Some(referent) = stack.pop() {
+            referents.push(referent);
+            stack.extend(dom.get_by_ref(referent).unwrap().children());
+        }
+
+        referents
+    };
+
+    assert_eq!(decode(namespace), decode(namespace));
+    assert_ne!(decode(namespace), decode(Ref::new()));
+    assert_eq!(decode(namespace)[0], namespace);
+}
+
+/// Ensures that instances can be moved between DOMs decoded with the same
+/// namespace, which have the same referents, without replacing instances.
+#[test]
+fn namespace_transfer() {
+    let place = sample_place();
+    let namespace = Ref::new();
+    let decode = || {
+        Deserializer::new()
+            .namespace(namespace)
+            .deserialize(place.as_slice())
+            .expect("failed to decode place")
+    };
+
+    let mut source = decode();
+    let mut dest = decode();
+    let workspace = source.root().children()[0];
+    let house = source.get_by_ref(workspace).unwrap().children()[1];
+    let count = dest.descendant_count(dest.root_ref());
+
+    let dest_root = dest.root_ref();
+    let moved = source.transfer(house, &mut dest, dest_root);
+
+    assert_ne!(moved, house);
+    assert_eq!(dest.get_by_ref(house).unwrap().parent(), workspace);
+    assert_eq!(dest.root().children().last(), Some(&moved));
+    assert_eq!(dest.descendant_count(dest_root), count + 2);
+
+    let mut buffer = Vec::new();
+    to_writer(&mut buffer, &dest, &[dest_root]).expect("failed to encode place");
+    let decoded = Deserializer::new()
+        .deserialize(buffer.as_slice())
+        .expect("failed to decode place");
+    assert_eq!(decoded.descendant_count(decoded.root_ref()), count + 3);
+}
+
+/// Ensures that properties are read using the given reflection database.
+#[test]
+fn reflection_database() {
+    let tree = WeakDom::new(InstanceBuilder::new("Folder").with_child(
+        InstanceBuilder::new("Part").with_property("Size", Vector3::new(1.0, 2.0, 3.0)),
+    ));
+
+    let mut buffer = Vec::new();
+    to_writer(&mut buffer, &tree, &[tree.root_ref()]).expect("failed to encode model");
+
+    let empty = ReflectionDatabase::new();
+    let dom = Deserializer::new()
+        .reflection_database(&empty)
+        .deserialize(buffer.as_slice())
+        .expect("failed to decode model");
+
+    let folder = dom.get_by_ref(dom.root().children()[0]).unwrap();
+    let part = dom.get_by_ref(folder.children()[0]).unwrap();
+
+    // Part.Size is written to files as `size`. Without a database that knows
+    // about it, the property keeps that name.
+    assert!(part.properties.contains_key("size"));
+    assert!(!part.properties.contains_key("Size"));
+}
+
+/// Inserts an uncompressed chunk into an encoded file, just before its END
+/// chunk.
+fn insert_chunk(file: &mut Vec<u8>, name: &[u8; 4], data: &[u8]) {
+    let end = file
+        .windows(4)
+        .rposition(|window| window == b"END\0")
+        .expect("file has no END chunk");
+
+    let mut chunk = name.to_vec();
+    chunk.extend_from_slice(&0u32.to_le_bytes());
+    chunk.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    chunk.extend_from_slice(&0u32.to_le_bytes());
+    chunk.extend_from_slice(data);
+
+    file.splice(end..end, chunk);
+}
+
+/// Ensures that a file with two instances that share a referent is an error
+/// instead of one instance replacing the other.
+#[test]
+fn duplicate_referents() {
+    let mut place = sample_place();
+
+    // An INST chunk declaring one Folder with the referent 0, which the first
+    // instance in the file already has.
+    let mut inst = Vec::new();
+    inst.extend_from_slice(&99u32.to_le_bytes());
+    inst.extend_from_slice(&6u32.to_le_bytes());
+    inst.extend_from_slice(b"Folder");
+    inst.push(0);
+    inst.extend_from_slice(&1u32.to_le_bytes());
+    inst.extend_from_slice(&[0; 4]);
+    insert_chunk(&mut place, b"INST", &inst);
+
+    assert!(Deserializer::new().deserialize(place.as_slice()).is_err());
+}
+
+/// Ensures that unknown chunks are kept and written back out, and that files
+/// from newer versions are only read in forward compatible mode.
+#[test]
+fn forward_compatible() {
+    use std::sync::Mutex;
+
+    use crate::{Diagnostic, UnknownChunk, UnknownChunks};
+
+    let mut place = sample_place();
+    insert_chunk(&mut place, b"ABCD", b"hello");
+
+    let diagnostics = Mutex::new(Vec::new());
+    let dom = Deserializer::new()
+        .diagnostics(|diagnostic| diagnostics.lock().unwrap().push(diagnostic.clone()))
+        .deserialize(place.as_slice())
+        .expect("failed to decode place");
+
+    assert_eq!(
+        diagnostics.lock().unwrap().as_slice(),
+        [Diagnostic::UnknownChunk { name: *b"ABCD" }]
+    );
+
+    let expected = UnknownChunks {
+        chunks: vec![UnknownChunk {
+            name: *b"ABCD",
+            data: b"hello".to_vec(),
+        }],
+    };
+    assert_eq!(dom.extension(dom.root_ref()), Some(&expected));
+
+    // Unknown chunks are only written back out when asked for.
+    let mut encoded = Vec::new();
+    to_writer(&mut encoded, &dom, dom.root().children()).expect("failed to encode place");
+    let decoded = Deserializer::new()
+        .deserialize(encoded.as_slice())
+        .expect("failed to decode place");
+    assert_eq!(decoded.extension::<UnknownChunks>(decoded.root_ref()), None);
+
+    let mut encoded = Vec::new();
+    Serializer::new()
+        .preserve_unknown_chunks(true)
+        .serialize(&mut encoded, &dom, dom.root().children())
+        .expect("failed to encode place");
+    let decoded = Deserializer::new()
+        .deserialize(encoded.as_slice())
+        .expect("failed to decode place");
+    assert_eq!(decoded.extension(decoded.root_ref()), Some(&expected));
+
+    // The version comes right after the 8 byte magic header and the 6 byte
+    // signature.
+    let mut newer = sample_place();
+    newer[14] = 1;
+    insert_chunk(&mut newer, b"SSTR", &2u32.to_le_bytes());
+
+    assert!(Deserializer::new().deserialize(newer.as_slice()).is_err());
+
+    diagnostics.lock().unwrap().clear();
+    let dom = Deserializer::new()
+        .forward_compatible(true)
+        .diagnostics(|diagnostic| diagnostics.lock().unwrap().push(diagnostic.clone()))
+        .deserialize(newer.as_slice())
+        .expect("failed to decode place in forward compatible mode");
+
+    assert_eq!(
+        diagnostics.lock().unwrap().as_slice(),
+        [
+            Diagnostic::NewerFileVersion { version: 1 },
+            Diagnostic::UnknownChunkVersion {
+                name: *b"SSTR",
+                version: 2,
+            },
+        ]
+    );
+    assert_eq!(names(&dom).len(), 7);
+
+    // Chunks that couldn't be read aren't kept, since they would be written
+    // out next to the serializer's own copy.
+    assert_eq!(dom.extension::<UnknownChunks>(dom.root_ref()), None);
+
+    let mut dom = dom;
+    dom.insert_extension(
+        dom.root_ref(),
+        UnknownChunks {
+            chunks: vec![UnknownChunk {
+                name: *b"PRNT",
+                data: b"stale".to_vec(),
+            }],
+        },
+    );
+
+    let mut encoded = Vec::new();
+    Serializer::new()
+        .preserve_unknown_chunks(true)
+        .serialize(&mut encoded, &dom, dom.root().children())
+        .expect("failed to encode place");
+    let decoded = Deserializer::new()
+        .deserialize(encoded.as_slice())
+        .expect("the stale PRNT chunk should not have been written");
+    assert_eq!(names(&decoded).len(), 7);
+}
+
+/// Ensures that the META chunk is kept on the DOM and written back out.
+#[test]
+fn file_metadata() {
+    use rbx_dom_weak::FileMetadata;
+
+    let mut metadata = FileMetadata::default();
+    metadata
+        .entries
+        .insert("ExplicitAutoJoints".to_owned(), "true".to_owned());
+
+    let mut dom = Deserializer::new()
+        .deserialize(sample_place().as_slice())
+        .expect("failed to decode place");
+    assert_eq!(dom.extension::<FileMetadata>(dom.root_ref()), None);
+
+    dom.insert_extension(dom.root_ref(), metadata.clone());
+
+    let mut encoded = Vec::new();
+    to_writer(&mut encoded, &dom, dom.root().children()).expect("failed to encode place");
+    let decoded = Deserializer::new()
+        .deserialize(encoded.as_slice())
+        .expect("failed to decode place");
+    assert_eq!(decoded.extension(decoded.root_ref()), Some(&metadata));
+}
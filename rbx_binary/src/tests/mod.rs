@@ -1,4 +1,5 @@
 mod core_read_write;
+mod deserializer;
 mod models;
 mod places;
 mod serializer;
@@ -1,9 +1,12 @@
 use rbx_dom_weak::{
-    types::{Color3, Color3uint8, Ref, Region3, Vector3},
+    types::{Color3, Color3uint8, Ref, Region3, Variant, Vector3},
     InstanceBuilder, WeakDom,
 };
 
-use crate::{text_deserializer::DecodedModel, to_writer};
+use crate::{
+    text_deserializer::{DecodedChunk, DecodedModel},
+    to_writer,
+};
 
 /// A basic test to make sure we can serialize the simplest instance: a Folder.
 #[test]
@@ -153,3 +156,263 @@ fn part_color() {
     let decoded = DecodedModel::from_reader(buf.as_slice());
     insta::assert_yaml_snapshot!(decoded);
 }
+
+/// Ensures that disabling compression produces a file that only contains
+/// uncompressed chunks and still decodes to the same DOM.
+#[test]
+fn uncompressed() {
+    let tree = WeakDom::new(InstanceBuilder::new("Folder").with_children(
+        (0..8).map(|_| InstanceBuilder::new("StringValue").with_property("Value", "Hello, world!")),
+    ));
+
+    let mut compressed = Vec::new();
+    to_writer(&mut compressed, &tree, &[tree.root_ref()]).expect("failed to encode model");
+
+    let mut uncompressed = Vec::new();
+    crate::Serializer::new()
+        .compression_type(crate::CompressionType::None)
+        .serialize(&mut uncompressed, &tree, &[tree.root_ref()])
+        .expect("failed to encode model");
+
+    assert!(uncompressed.len() > compressed.len());
+
+    let decoded = crate::from_reader(uncompressed.as_slice()).expect("failed to decode model");
+    let viewed = rbx_dom_weak::DomViewer::new().view_children(&decoded);
+    let expected = rbx_dom_weak::DomViewer::new()
+        .view_children(&crate::from_reader(compressed.as_slice()).unwrap());
+    assert_eq!(format!("{:?}", viewed), format!("{:?}", expected));
+}
+
+/// Ensures that ZSTD compressed chunks can be written and read back.
+#[cfg(feature = "zstd")]
+#[test]
+fn zstd_round_trip() {
+    let tree = WeakDom::new(InstanceBuilder::new("Folder").with_children(
+        (0..8).map(|_| InstanceBuilder::new("StringValue").with_property("Value", "Hello, world!")),
+    ));
+
+    let mut buffer = Vec::new();
+    crate::Serializer::new()
+        .compression_type(crate::CompressionType::Zstd)
+        .serialize(&mut buffer, &tree, &[tree.root_ref()])
+        .expect("failed to encode model");
+
+    let decoded = crate::from_reader(buffer.as_slice()).expect("failed to decode model");
+    let folder = decoded.get_by_ref(decoded.root().children()[0]).unwrap();
+    assert_eq!(folder.children().len(), 8);
+
+    let value = decoded.get_by_ref(folder.children()[0]).unwrap();
+    assert_eq!(
+        value.properties.get("Value"),
+        Some(&rbx_dom_weak::types::Variant::String(
+            "Hello, world!".to_owned()
+        ))
+    );
+}
+
+/// Ensures that the progress callback is told about every chunk that is
+/// written, ending with the END chunk once everything has been written.
+#[test]
+fn progress() {
+    use std::sync::{Arc, Mutex};
+
+    let tree = WeakDom::new(InstanceBuilder::new("Folder").with_children(
+        (0..4).map(|_| InstanceBuilder::new("StringValue").with_property("Value", "Hello")),
+    ));
+
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    let reports_handle = Arc::clone(&reports);
+
+    let mut buffer = Vec::new();
+    crate::Serializer::new()
+        .progress(move |progress| reports_handle.lock().unwrap().push(*progress))
+        .serialize(&mut buffer, &tree, &[tree.root_ref()])
+        .expect("failed to encode model");
+
+    let reports = reports.lock().unwrap();
+    assert!(reports.windows(2).all(|pair| pair[0].bytes < pair[1].bytes));
+
+    let last = reports.last().unwrap();
+    assert_eq!(&last.chunk, b"END\0");
+    assert_eq!(last.bytes, buffer.len() as u64);
+    assert_eq!(last.instances, 5);
+    assert_eq!(last.total_instances, 5);
+}
+
+/// Ensures that setting the cancel token stops serialization with an error.
+#[test]
+fn cancel_token() {
+    use std::sync::{atomic::AtomicBool, Arc};
+
+    let tree = WeakDom::new(InstanceBuilder::new("Folder"));
+
+    let mut buffer = Vec::new();
+    let error = crate::Serializer::new()
+        .cancel_token(Arc::new(AtomicBool::new(true)))
+        .serialize(&mut buffer, &tree, &[tree.root_ref()])
+        .expect_err("serializing should have been cancelled");
+
+    assert!(error.is_cancelled());
+}
+
+/// Ensures that stripped classes are left out and mapped classes are renamed.
+#[test]
+fn strip_and_map_classes() {
+    let tree = WeakDom::new(
+        InstanceBuilder::new("Folder")
+            .with_child(
+                InstanceBuilder::new("BuildMarker").with_child(InstanceBuilder::new("Part")),
+            )
+            .with_child(
+                InstanceBuilder::new("ScriptGroup")
+                    .with_name("Scripts")
+                    .with_child(InstanceBuilder::new("Script")),
+            ),
+    );
+
+    let mut buffer = Vec::new();
+    crate::Serializer::new()
+        .strip_class("BuildMarker")
+        .map_class("ScriptGroup", "Folder")
+        .serialize(&mut buffer, &tree, &[tree.root_ref()])
+        .expect("failed to encode model");
+
+    let decoded = crate::from_reader(buffer.as_slice()).expect("failed to decode model");
+    let folder = decoded.get_by_ref(decoded.root().children()[0]).unwrap();
+    assert_eq!(folder.children().len(), 1);
+
+    let scripts = decoded.get_by_ref(folder.children()[0]).unwrap();
+    assert_eq!(scripts.name, "Scripts");
+    assert_eq!(scripts.class, "Folder");
+    assert_eq!(scripts.children().len(), 1);
+}
+
+/// Ensures that skipped instances and their descendants are left out without
+/// changing the tree.
+#[test]
+fn skip_instances() {
+    let mut tree = WeakDom::new(InstanceBuilder::new("Folder"));
+    let root = tree.root_ref();
+    let shop = tree.insert(root, InstanceBuilder::new("ModuleScript").with_name("Shop"));
+    let spec = tree.insert(
+        root,
+        InstanceBuilder::new("ModuleScript")
+            .with_name("Shop.spec")
+            .with_child(InstanceBuilder::new("ModuleScript")),
+    );
+    tree.insert(
+        shop,
+        InstanceBuilder::new("ObjectValue").with_property("Value", spec),
+    );
+
+    let mut buffer = Vec::new();
+    crate::Serializer::new()
+        .skip_instances([spec])
+        .serialize(&mut buffer, &tree, &[root])
+        .expect("failed to encode model");
+
+    assert_eq!(tree.root().children(), [shop, spec]);
+
+    let decoded = crate::from_reader(buffer.as_slice()).expect("failed to decode model");
+    let folder = decoded.get_by_ref(decoded.root().children()[0]).unwrap();
+    assert_eq!(folder.children().len(), 1);
+
+    let shop = decoded.get_by_ref(folder.children()[0]).unwrap();
+    assert_eq!(shop.name, "Shop");
+
+    let value = decoded.get_by_ref(shop.children()[0]).unwrap();
+    assert_eq!(
+        value.properties.get("Value"),
+        Some(&Variant::Ref(Ref::none()))
+    );
+}
+
+/// Ensures that properties are only left out when every instance of a class
+/// has the default value.
+#[test]
+fn skip_default_properties() {
+    let tree = WeakDom::new(
+        InstanceBuilder::new("Folder")
+            .with_child(
+                InstanceBuilder::new("Part")
+                    .with_property("Anchored", false)
+                    .with_property("Transparency", 0.0f32),
+            )
+            .with_child(
+                InstanceBuilder::new("Part")
+                    .with_property("Anchored", false)
+                    .with_property("Transparency", 0.5f32),
+            ),
+    );
+
+    let mut buffer = Vec::new();
+    crate::Serializer::new()
+        .skip_default_properties(true)
+        .serialize(&mut buffer, &tree, &[tree.root_ref()])
+        .expect("failed to encode model");
+
+    let decoded = DecodedModel::from_reader(buffer.as_slice());
+    let props: Vec<_> = decoded
+        .chunks
+        .iter()
+        .filter_map(|chunk| match chunk {
+            DecodedChunk::Prop { prop_name, .. } => Some(prop_name.as_str()),
+            _ => None,
+        })
+        .collect();
+
+    assert!(props.contains(&"Name"));
+    assert!(props.contains(&"Transparency"));
+    assert!(!props.contains(&"Anchored"));
+}
+
+/// Ensures that script sources are given the line endings that were asked for
+/// when writing and reading.
+#[test]
+fn line_endings() {
+    use crate::{Deserializer, LineEndings, Serializer};
+
+    let mixed = "local a = 1\r\nlocal b = 2\nprint(a + b)\r";
+    let tree = WeakDom::new(
+        InstanceBuilder::new("Folder")
+            .with_child(InstanceBuilder::new("Script").with_property("Source", mixed)),
+    );
+
+    let encode = |serializer: Serializer| {
+        let mut buffer = Vec::new();
+        serializer
+            .serialize(&mut buffer, &tree, tree.root().children())
+            .expect("failed to encode model");
+        buffer
+    };
+    let source = |buffer: &[u8], deserializer: Deserializer| {
+        let decoded = deserializer
+            .deserialize(buffer)
+            .expect("failed to decode model");
+        let script = decoded.get_by_ref(decoded.root().children()[0]).unwrap();
+        script.properties.get("Source").cloned()
+    };
+
+    let preserved = encode(Serializer::new());
+    assert_eq!(
+        source(&preserved, Deserializer::new()),
+        Some(Variant::String(mixed.to_owned()))
+    );
+    assert_eq!(
+        source(
+            &preserved,
+            Deserializer::new().line_endings(LineEndings::Lf)
+        ),
+        Some(Variant::String(
+            "local a = 1\nlocal b = 2\nprint(a + b)\n".to_owned()
+        ))
+    );
+
+    let crlf = encode(Serializer::new().line_endings(LineEndings::Crlf));
+    assert_eq!(
+        source(&crlf, Deserializer::new()),
+        Some(Variant::String(
+            "local a = 1\r\nlocal b = 2\r\nprint(a + b)\r\n".to_owned()
+        ))
+    );
+}
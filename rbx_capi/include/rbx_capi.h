@@ -0,0 +1,59 @@
+/*
+ * C bindings for rbx-dom. See the documentation in rbx_capi/src/lib.rs for
+ * details on ownership and error handling.
+ */
+
+#ifndef RBX_CAPI_H
+#define RBX_CAPI_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct RbxDom RbxDom;
+
+typedef struct RbxRef {
+    uint64_t high;
+    uint64_t low;
+} RbxRef;
+
+typedef enum RbxFormat {
+    RBX_FORMAT_BINARY = 0,
+    RBX_FORMAT_XML = 1,
+} RbxFormat;
+
+typedef struct RbxBuffer {
+    uint8_t *data;
+    size_t len;
+} RbxBuffer;
+
+const char *rbx_last_error(void);
+void rbx_string_free(char *value);
+void rbx_buffer_free(RbxBuffer buffer);
+
+RbxDom *rbx_dom_new(const char *class_name);
+void rbx_dom_free(RbxDom *dom);
+RbxRef rbx_dom_root(const RbxDom *dom);
+RbxRef rbx_dom_insert(RbxDom *dom, RbxRef parent, const char *class_name, const char *name);
+bool rbx_dom_destroy(RbxDom *dom, RbxRef referent);
+
+char *rbx_instance_class(const RbxDom *dom, RbxRef referent);
+char *rbx_instance_name(const RbxDom *dom, RbxRef referent);
+RbxRef rbx_instance_parent(const RbxDom *dom, RbxRef referent);
+size_t rbx_instance_child_count(const RbxDom *dom, RbxRef referent);
+RbxRef rbx_instance_child(const RbxDom *dom, RbxRef referent, size_t index);
+char *rbx_instance_get_property(const RbxDom *dom, RbxRef referent, const char *name);
+bool rbx_instance_set_property(RbxDom *dom, RbxRef referent, const char *name, const char *value);
+
+RbxDom *rbx_dom_decode(const uint8_t *data, size_t len);
+bool rbx_dom_encode(const RbxDom *dom, RbxRef referent, RbxFormat format, RbxBuffer *output);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,643 @@
+//! C bindings for rbx_dom_weak, rbx_binary, and rbx_xml.
+//!
+//! The matching header lives at `include/rbx_capi.h`. Every function that can
+//! fail reports a failure through its return value; a description of the most
+//! recent failure on the current thread is available from [`rbx_last_error`].
+//!
+//! Strings passed into these functions must be NUL-terminated UTF-8. Strings
+//! returned from them are owned by the caller and must be released with
+//! [`rbx_string_free`].
+//!
+//! Property values are passed as JSON, in the same form that `Variant` is
+//! serialized in by serde, like `{"Bool": true}` or
+//! `{"Vector3": [1.0, 2.0, 3.0]}`.
+//!
+//! Panics never unwind into C. A function that panics reports it as a failure
+//! like any other.
+
+#![deny(missing_docs)]
+
+use std::{
+    cell::RefCell,
+    ffi::{c_char, CStr, CString},
+    panic::{self, AssertUnwindSafe},
+    ptr, slice,
+};
+
+use rbx_dom_weak::{
+    types::{Ref, Variant},
+    InstanceBuilder, WeakDom,
+};
+use rbx_util::Format;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: impl ToString) {
+    let message = CString::new(message.to_string().replace('\0', ""))
+        .expect("NUL bytes should have been removed");
+
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+/// Runs `body`, returning `failure` and setting the last error if it panics.
+/// Unwinding out of an `extern "C"` function aborts the process, so every one
+/// of them goes through this.
+fn catch_panic<T>(failure: T, body: impl FnOnce() -> T) -> T {
+    match panic::catch_unwind(AssertUnwindSafe(body)) {
+        Ok(value) => value,
+        Err(payload) => {
+            let message = match payload.downcast_ref::<&str>() {
+                Some(message) => message.to_string(),
+                None => match payload.downcast_ref::<String>() {
+                    Some(message) => message.clone(),
+                    None => "unknown panic".to_owned(),
+                },
+            };
+
+            set_last_error(format!("panicked: {}", message));
+            failure
+        }
+    }
+}
+
+/// An opaque handle to a tree of instances.
+pub struct RbxDom(WeakDom);
+
+/// A referent to an instance, split into two halves so that it can be passed
+/// by value through a C ABI. The all-zero referent points to nothing.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RbxRef {
+    /// The upper 64 bits of the referent.
+    pub high: u64,
+    /// The lower 64 bits of the referent.
+    pub low: u64,
+}
+
+impl RbxRef {
+    const NONE: RbxRef = RbxRef { high: 0, low: 0 };
+}
+
+impl From<Ref> for RbxRef {
+    fn from(referent: Ref) -> Self {
+        let value = u128::from_be_bytes(referent.to_bytes());
+
+        RbxRef {
+            high: (value >> 64) as u64,
+            low: value as u64,
+        }
+    }
+}
+
+impl From<RbxRef> for Ref {
+    fn from(referent: RbxRef) -> Self {
+        let value = (u128::from(referent.high) << 64) | u128::from(referent.low);
+        Ref::from_bytes(value.to_be_bytes())
+    }
+}
+
+/// The file formats that can be written with [`rbx_dom_encode`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RbxFormat {
+    /// The binary format, used by `.rbxm` and `.rbxl` files.
+    Binary = 0,
+    /// The XML format, used by `.rbxmx` and `.rbxlx` files.
+    Xml = 1,
+}
+
+impl From<RbxFormat> for Format {
+    fn from(format: RbxFormat) -> Self {
+        match format {
+            RbxFormat::Binary => Format::Binary,
+            RbxFormat::Xml => Format::Xml,
+        }
+    }
+}
+
+/// A buffer of bytes allocated by this library.
+#[repr(C)]
+#[derive(Debug)]
+pub struct RbxBuffer {
+    /// Pointer to the first byte of the buffer.
+    pub data: *mut u8,
+    /// The number of bytes in the buffer.
+    pub len: usize,
+}
+
+unsafe fn read_str<'a>(value: *const c_char, what: &str) -> Option<&'a str> {
+    if value.is_null() {
+        set_last_error(format!("{} was null", what));
+        return None;
+    }
+
+    match CStr::from_ptr(value).to_str() {
+        Ok(value) => Some(value),
+        Err(_) => {
+            set_last_error(format!("{} was not valid UTF-8", what));
+            None
+        }
+    }
+}
+
+fn to_c_string(value: &str) -> *mut c_char {
+    CString::new(value.replace('\0', ""))
+        .expect("NUL bytes should have been removed")
+        .into_raw()
+}
+
+/// Returns a description of the last error that occurred on this thread, or
+/// null if no error has occurred. The string is owned by this library and is
+/// valid until the next call into it from the same thread.
+#[no_mangle]
+pub extern "C" fn rbx_last_error() -> *const c_char {
+    catch_panic(ptr::null(), || {
+        LAST_ERROR.with(|last| match &*last.borrow() {
+            Some(message) => message.as_ptr(),
+            None => ptr::null(),
+        })
+    })
+}
+
+/// Frees a string returned by this library.
+///
+/// # Safety
+/// `value` must be null or a string returned by this library that has not
+/// already been freed.
+#[no_mangle]
+pub unsafe extern "C" fn rbx_string_free(value: *mut c_char) {
+    catch_panic((), || {
+        if !value.is_null() {
+            drop(CString::from_raw(value));
+        }
+    })
+}
+
+/// Frees a buffer returned by [`rbx_dom_encode`].
+///
+/// # Safety
+/// `buffer` must have been returned by this library and not already freed.
+#[no_mangle]
+pub unsafe extern "C" fn rbx_buffer_free(buffer: RbxBuffer) {
+    catch_panic((), || {
+        if !buffer.data.is_null() {
+            drop(Box::from_raw(ptr::slice_from_raw_parts_mut(
+                buffer.data,
+                buffer.len,
+            )));
+        }
+    })
+}
+
+/// Creates a new tree whose root instance has the given ClassName. Returns null
+/// on failure.
+///
+/// # Safety
+/// `class_name` must be null or a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn rbx_dom_new(class_name: *const c_char) -> *mut RbxDom {
+    catch_panic(ptr::null_mut(), || {
+        match read_str(class_name, "class_name") {
+            Some(class_name) => Box::into_raw(Box::new(RbxDom(WeakDom::new(
+                InstanceBuilder::new(class_name),
+            )))),
+            None => ptr::null_mut(),
+        }
+    })
+}
+
+/// Frees a tree and every instance in it.
+///
+/// # Safety
+/// `dom` must be null or a tree returned by this library that has not already
+/// been freed.
+#[no_mangle]
+pub unsafe extern "C" fn rbx_dom_free(dom: *mut RbxDom) {
+    catch_panic((), || {
+        if !dom.is_null() {
+            drop(Box::from_raw(dom));
+        }
+    })
+}
+
+/// Returns the referent of the root instance of the tree.
+///
+/// # Safety
+/// `dom` must be a valid tree.
+#[no_mangle]
+pub unsafe extern "C" fn rbx_dom_root(dom: *const RbxDom) -> RbxRef {
+    catch_panic(RbxRef::NONE, || (*dom).0.root_ref().into())
+}
+
+/// Inserts a new instance with the given ClassName and Name under `parent`.
+/// Returns the referent of the new instance, or the all-zero referent on
+/// failure.
+///
+/// # Safety
+/// `dom` must be a valid tree, and `class_name` and `name` must be null or
+/// valid NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn rbx_dom_insert(
+    dom: *mut RbxDom,
+    parent: RbxRef,
+    class_name: *const c_char,
+    name: *const c_char,
+) -> RbxRef {
+    catch_panic(RbxRef::NONE, || {
+        let dom = &mut (*dom).0;
+        let (Some(class_name), Some(name)) =
+            (read_str(class_name, "class_name"), read_str(name, "name"))
+        else {
+            return RbxRef::NONE;
+        };
+
+        let parent = Ref::from(parent);
+        if dom.get_by_ref(parent).is_none() {
+            set_last_error("parent is not an instance in this tree");
+            return RbxRef::NONE;
+        }
+
+        match dom.try_insert(parent, InstanceBuilder::new(class_name).with_name(name)) {
+            Ok(referent) => referent.into(),
+            Err(err) => {
+                set_last_error(err);
+                RbxRef::NONE
+            }
+        }
+    })
+}
+
+/// Destroys an instance and all of its descendants. Returns false if the
+/// instance can't be destroyed.
+///
+/// # Safety
+/// `dom` must be a valid tree.
+#[no_mangle]
+pub unsafe extern "C" fn rbx_dom_destroy(dom: *mut RbxDom, referent: RbxRef) -> bool {
+    catch_panic(false, || {
+        let dom = &mut (*dom).0;
+        let referent = Ref::from(referent);
+
+        if referent == dom.root_ref() {
+            set_last_error("the root instance cannot be destroyed");
+            return false;
+        }
+
+        if dom.get_by_ref(referent).is_none() {
+            set_last_error("referent is not an instance in this tree");
+            return false;
+        }
+
+        dom.destroy(referent);
+        true
+    })
+}
+
+/// Returns the ClassName of an instance, or null if it doesn't exist.
+///
+/// # Safety
+/// `dom` must be a valid tree.
+#[no_mangle]
+pub unsafe extern "C" fn rbx_instance_class(dom: *const RbxDom, referent: RbxRef) -> *mut c_char {
+    catch_panic(ptr::null_mut(), || {
+        match (*dom).0.get_by_ref(referent.into()) {
+            Some(instance) => to_c_string(&instance.class),
+            None => {
+                set_last_error("referent is not an instance in this tree");
+                ptr::null_mut()
+            }
+        }
+    })
+}
+
+/// Returns the Name of an instance, or null if it doesn't exist.
+///
+/// # Safety
+/// `dom` must be a valid tree.
+#[no_mangle]
+pub unsafe extern "C" fn rbx_instance_name(dom: *const RbxDom, referent: RbxRef) -> *mut c_char {
+    catch_panic(ptr::null_mut(), || {
+        match (*dom).0.get_by_ref(referent.into()) {
+            Some(instance) => to_c_string(&instance.name),
+            None => {
+                set_last_error("referent is not an instance in this tree");
+                ptr::null_mut()
+            }
+        }
+    })
+}
+
+/// Returns the referent of an instance's parent. The root instance's parent is
+/// the all-zero referent.
+///
+/// # Safety
+/// `dom` must be a valid tree.
+#[no_mangle]
+pub unsafe extern "C" fn rbx_instance_parent(dom: *const RbxDom, referent: RbxRef) -> RbxRef {
+    catch_panic(RbxRef::NONE, || {
+        match (*dom).0.get_by_ref(referent.into()) {
+            Some(instance) => instance.parent().into(),
+            None => {
+                set_last_error("referent is not an instance in this tree");
+                RbxRef::NONE
+            }
+        }
+    })
+}
+
+/// Returns the number of children an instance has.
+///
+/// # Safety
+/// `dom` must be a valid tree.
+#[no_mangle]
+pub unsafe extern "C" fn rbx_instance_child_count(dom: *const RbxDom, referent: RbxRef) -> usize {
+    catch_panic(0, || match (*dom).0.get_by_ref(referent.into()) {
+        Some(instance) => instance.children().len(),
+        None => 0,
+    })
+}
+
+/// Returns the referent of an instance's child at `index`, or the all-zero
+/// referent if there is no such child.
+///
+/// # Safety
+/// `dom` must be a valid tree.
+#[no_mangle]
+pub unsafe extern "C" fn rbx_instance_child(
+    dom: *const RbxDom,
+    referent: RbxRef,
+    index: usize,
+) -> RbxRef {
+    catch_panic(RbxRef::NONE, || {
+        (*dom)
+            .0
+            .get_by_ref(referent.into())
+            .and_then(|instance| instance.children().get(index))
+            .map_or(RbxRef::NONE, |&child| child.into())
+    })
+}
+
+/// Returns the value of one of an instance's properties as JSON, or null if
+/// the instance doesn't exist or doesn't have the property.
+///
+/// # Safety
+/// `dom` must be a valid tree, and `name` must be null or a valid
+/// NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn rbx_instance_get_property(
+    dom: *const RbxDom,
+    referent: RbxRef,
+    name: *const c_char,
+) -> *mut c_char {
+    catch_panic(ptr::null_mut(), || {
+        let Some(name) = read_str(name, "name") else {
+            return ptr::null_mut();
+        };
+        let Some(instance) = (*dom).0.get_by_ref(referent.into()) else {
+            set_last_error("referent is not an instance in this tree");
+            return ptr::null_mut();
+        };
+        let Some(value) = instance.properties.get(name) else {
+            set_last_error(format!(
+                "{} does not have a {} property",
+                instance.name, name
+            ));
+            return ptr::null_mut();
+        };
+
+        match serde_json::to_string(value) {
+            Ok(json) => to_c_string(&json),
+            Err(err) => {
+                set_last_error(err);
+                ptr::null_mut()
+            }
+        }
+    })
+}
+
+/// Sets one of an instance's properties to a value given as JSON, converting
+/// it to the type the property already has if needed. Returns false on
+/// failure, in which case the instance is left untouched.
+///
+/// # Safety
+/// `dom` must be a valid tree, and `name` and `value` must be null or valid
+/// NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn rbx_instance_set_property(
+    dom: *mut RbxDom,
+    referent: RbxRef,
+    name: *const c_char,
+    value: *const c_char,
+) -> bool {
+    catch_panic(false, || {
+        let dom = &mut (*dom).0;
+        let (Some(name), Some(value)) = (read_str(name, "name"), read_str(value, "value")) else {
+            return false;
+        };
+
+        let referent = Ref::from(referent);
+        if dom.get_by_ref(referent).is_none() {
+            set_last_error("referent is not an instance in this tree");
+            return false;
+        }
+
+        let value: Variant = match serde_json::from_str(value) {
+            Ok(value) => value,
+            Err(err) => {
+                set_last_error(format!("value is not a valid property value: {}", err));
+                return false;
+            }
+        };
+
+        match dom.update_properties(referent, [(name, value)]) {
+            Ok(()) => true,
+            Err(err) => {
+                set_last_error(err);
+                false
+            }
+        }
+    })
+}
+
+/// Decodes a model or place in either the binary or XML format. Returns null on
+/// failure.
+///
+/// # Safety
+/// `data` must point to at least `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn rbx_dom_decode(data: *const u8, len: usize) -> *mut RbxDom {
+    catch_panic(ptr::null_mut(), || {
+        if data.is_null() {
+            set_last_error("data was null");
+            return ptr::null_mut();
+        }
+
+        match rbx_util::from_slice(slice::from_raw_parts(data, len)) {
+            Ok(dom) => Box::into_raw(Box::new(RbxDom(dom))),
+            Err(err) => {
+                set_last_error(err);
+                ptr::null_mut()
+            }
+        }
+    })
+}
+
+/// Encodes the children of the given instance in the given format, writing the
+/// result to `output`. Returns false on failure.
+///
+/// The buffer written to `output` must be freed with [`rbx_buffer_free`].
+///
+/// # Safety
+/// `dom` must be a valid tree and `output` must point to writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn rbx_dom_encode(
+    dom: *const RbxDom,
+    referent: RbxRef,
+    format: RbxFormat,
+    output: *mut RbxBuffer,
+) -> bool {
+    catch_panic(false, || {
+        let dom = &(*dom).0;
+        let Some(instance) = dom.get_by_ref(referent.into()) else {
+            set_last_error("referent is not an instance in this tree");
+            return false;
+        };
+
+        match rbx_util::to_vec(dom, instance.children(), format.into()) {
+            Ok(contents) => {
+                let len = contents.len();
+                let contents = Box::into_raw(contents.into_boxed_slice());
+
+                *output = RbxBuffer {
+                    data: contents as *mut u8,
+                    len,
+                };
+                true
+            }
+            Err(err) => {
+                set_last_error(err);
+                false
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    unsafe fn take_string(value: *mut c_char) -> String {
+        let owned = CStr::from_ptr(value).to_str().unwrap().to_owned();
+        rbx_string_free(value);
+        owned
+    }
+
+    #[test]
+    fn ref_round_trip() {
+        let referent = Ref::new();
+        assert_eq!(Ref::from(RbxRef::from(referent)), referent);
+        assert_eq!(RbxRef::from(Ref::none()), RbxRef::NONE);
+    }
+
+    #[test]
+    fn build_and_round_trip() {
+        unsafe {
+            let dom = rbx_dom_new(c"DataModel".as_ptr());
+            let root = rbx_dom_root(dom);
+
+            let folder = rbx_dom_insert(dom, root, c"Folder".as_ptr(), c"Hello".as_ptr());
+            assert_ne!(folder, RbxRef::NONE);
+            assert_eq!(rbx_instance_parent(dom, folder), root);
+
+            for format in [RbxFormat::Binary, RbxFormat::Xml] {
+                let mut buffer = RbxBuffer {
+                    data: ptr::null_mut(),
+                    len: 0,
+                };
+                assert!(rbx_dom_encode(dom, root, format, &mut buffer));
+
+                let decoded = rbx_dom_decode(buffer.data, buffer.len);
+                rbx_buffer_free(buffer);
+                assert!(!decoded.is_null());
+
+                let decoded_root = rbx_dom_root(decoded);
+                assert_eq!(rbx_instance_child_count(decoded, decoded_root), 1);
+
+                let child = rbx_instance_child(decoded, decoded_root, 0);
+                assert_eq!(take_string(rbx_instance_class(decoded, child)), "Folder");
+                assert_eq!(take_string(rbx_instance_name(decoded, child)), "Hello");
+
+                rbx_dom_free(decoded);
+            }
+
+            assert!(!rbx_dom_destroy(dom, root));
+            assert!(rbx_dom_destroy(dom, folder));
+            assert_eq!(rbx_instance_child_count(dom, root), 0);
+
+            rbx_dom_free(dom);
+        }
+    }
+
+    #[test]
+    fn properties() {
+        unsafe {
+            let dom = rbx_dom_new(c"DataModel".as_ptr());
+            let root = rbx_dom_root(dom);
+            let value = rbx_dom_insert(dom, root, c"IntValue".as_ptr(), c"Count".as_ptr());
+
+            assert!(rbx_instance_get_property(dom, value, c"Value".as_ptr()).is_null());
+            assert!(rbx_instance_set_property(
+                dom,
+                value,
+                c"Value".as_ptr(),
+                c"{\"Int64\": 5}".as_ptr()
+            ));
+            assert_eq!(
+                take_string(rbx_instance_get_property(dom, value, c"Value".as_ptr())),
+                r#"{"Int64":5}"#
+            );
+
+            // Values are converted to the type the property already has.
+            assert!(rbx_instance_set_property(
+                dom,
+                value,
+                c"Value".as_ptr(),
+                c"{\"Int32\": 7}".as_ptr()
+            ));
+            assert_eq!(
+                take_string(rbx_instance_get_property(dom, value, c"Value".as_ptr())),
+                r#"{"Int64":7}"#
+            );
+
+            assert!(!rbx_instance_set_property(
+                dom,
+                value,
+                c"Value".as_ptr(),
+                c"7".as_ptr()
+            ));
+            assert!(!rbx_last_error().is_null());
+
+            rbx_dom_free(dom);
+        }
+    }
+
+    #[test]
+    fn panics_become_errors() {
+        let result = catch_panic(false, || panic!("oh no"));
+        assert!(!result);
+
+        let message = unsafe { CStr::from_ptr(rbx_last_error()) };
+        assert_eq!(message.to_str().unwrap(), "panicked: oh no");
+    }
+
+    #[test]
+    fn decode_error() {
+        unsafe {
+            let data = b"not a model";
+            assert!(rbx_dom_decode(data.as_ptr(), data.len()).is_null());
+            assert!(!rbx_last_error().is_null());
+        }
+    }
+}
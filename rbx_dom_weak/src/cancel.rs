@@ -0,0 +1,7 @@
+use thiserror::Error;
+
+/// The error returned when an operation stops early because its cancel token
+/// was set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+#[error("The operation was cancelled")]
+pub struct Cancelled;
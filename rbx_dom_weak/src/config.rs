@@ -0,0 +1,368 @@
+use std::{borrow::Cow, collections::HashMap, ops::Deref, sync::Arc};
+
+use rbx_reflection::{DataType, ReflectionDatabase};
+use rbx_types::{Ref, Variant, VariantType};
+use thiserror::Error;
+
+use crate::{sanitize_name, Instance, InstanceBuilder, PropertyError};
+
+/// Chooses how strict a [`WeakDom`][crate::WeakDom] is about the instances
+/// and properties put into it. It's given to
+/// [`WeakDom::with_config`][crate::WeakDom::with_config] and honored by
+/// [`WeakDom::insert`][crate::WeakDom::insert],
+/// [`WeakDom::try_insert`][crate::WeakDom::try_insert],
+/// [`WeakDom::update_properties`][crate::WeakDom::update_properties],
+/// [`WeakDom::transfer`][crate::WeakDom::transfer] into the DOM, and
+/// [`InstanceEditor`][crate::InstanceEditor].
+///
+/// By default, nothing is checked, which is how `WeakDom` has always behaved.
+/// Checks that need to know about classes and properties only do anything
+/// once a reflection database has been given with
+/// [`TreeConfig::reflection_database`] or
+/// [`TreeConfig::shared_reflection_database`].
+///
+/// ```
+/// use rbx_dom_weak::{types::Ref, InstanceBuilder, TreeConfig, WeakDom};
+///
+/// let config = TreeConfig::new().deterministic_referents(Ref::none());
+/// let first = WeakDom::with_config(InstanceBuilder::new("DataModel"), config.clone());
+/// let second = WeakDom::with_config(InstanceBuilder::new("DataModel"), config);
+///
+/// assert_eq!(first.root_ref(), second.root_ref());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct TreeConfig {
+    database: Option<Database>,
+    strict_property_types: bool,
+    reject_unknown_classes: bool,
+    fill_defaults: bool,
+    sanitize_names: bool,
+    referent_namespace: Option<Ref>,
+}
+
+/// A reflection database given to a [`TreeConfig`].
+#[derive(Debug, Clone)]
+enum Database {
+    Static(&'static ReflectionDatabase<'static>),
+    Shared(Arc<ReflectionDatabase<'static>>),
+}
+
+impl Deref for Database {
+    type Target = ReflectionDatabase<'static>;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            Database::Static(database) => database,
+            Database::Shared(database) => database,
+        }
+    }
+}
+
+impl TreeConfig {
+    /// Constructs a config that checks nothing.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the reflection database used to look up classes, property types,
+    /// and default values.
+    #[inline]
+    pub fn reflection_database(self, database: &'static ReflectionDatabase<'static>) -> Self {
+        Self {
+            database: Some(Database::Static(database)),
+            ..self
+        }
+    }
+
+    /// Sets the reflection database like [`TreeConfig::reflection_database`],
+    /// for databases that are built at runtime, like ones with classes added
+    /// by a pipeline, and shared with whatever else uses them.
+    #[inline]
+    pub fn shared_reflection_database(self, database: Arc<ReflectionDatabase<'static>>) -> Self {
+        Self {
+            database: Some(Database::Shared(database)),
+            ..self
+        }
+    }
+
+    /// Sets whether property values must already have the right type. The
+    /// right type comes from the reflection database if it knows the
+    /// property, and is otherwise the type that the property already has.
+    ///
+    /// When this is on, values are never converted from one type to another,
+    /// and values with the wrong type are rejected instead.
+    #[inline]
+    pub fn strict_property_types(self, strict_property_types: bool) -> Self {
+        Self {
+            strict_property_types,
+            ..self
+        }
+    }
+
+    /// Sets whether instances of classes that aren't in the reflection
+    /// database are rejected.
+    #[inline]
+    pub fn reject_unknown_classes(self, reject_unknown_classes: bool) -> Self {
+        Self {
+            reject_unknown_classes,
+            ..self
+        }
+    }
+
+    /// Sets whether inserted instances are given the default value of every
+    /// property they don't have, according to the reflection database.
+    #[inline]
+    pub fn fill_defaults(self, fill_defaults: bool) -> Self {
+        Self {
+            fill_defaults,
+            ..self
+        }
+    }
+
+    /// Sets whether the names of inserted and renamed instances are cleaned
+    /// up with [`sanitize_name`][crate::sanitize_name].
+    #[inline]
+    pub fn sanitize_names(self, sanitize_names: bool) -> Self {
+        Self {
+            sanitize_names,
+            ..self
+        }
+    }
+
+    /// Makes inserted instances get referents derived from `namespace` and
+    /// the number of instances inserted before them, instead of the random
+    /// referents their builders were made with. Two DOMs built the same way
+    /// with the same namespace end up with the same referents.
+    ///
+    /// `Ref` properties that point to instances being inserted at the same
+    /// time are changed to match. Copies made by
+    /// [`WeakDom::clone_within`][crate::WeakDom::clone_within] get
+    /// deterministic referents too.
+    #[inline]
+    pub fn deterministic_referents(self, namespace: Ref) -> Self {
+        Self {
+            referent_namespace: Some(namespace),
+            ..self
+        }
+    }
+
+    pub(crate) fn referent_namespace(&self) -> Option<Ref> {
+        self.referent_namespace
+    }
+
+    /// Checks every instance in a builder before it is inserted.
+    pub(crate) fn check_builder(&self, root: &InstanceBuilder) -> Result<(), TreeConfigError> {
+        if !self.strict_property_types && !self.reject_unknown_classes {
+            return Ok(());
+        }
+
+        let mut stack = vec![root];
+        while let Some(builder) = stack.pop() {
+            self.check_class(&builder.class)?;
+
+            for (name, value) in &builder.properties {
+                self.check_property(&builder.class, name, value, None)?;
+            }
+
+            stack.extend(&builder.children);
+        }
+
+        Ok(())
+    }
+
+    /// Checks an instance that is about to be moved into the DOM from
+    /// another one.
+    pub(crate) fn check_instance(&self, instance: &Instance) -> Result<(), TreeConfigError> {
+        if !self.strict_property_types && !self.reject_unknown_classes {
+            return Ok(());
+        }
+
+        self.check_class(&instance.class)?;
+
+        for (name, value) in &instance.properties {
+            self.check_property(&instance.class, name, value, None)?;
+        }
+
+        Ok(())
+    }
+
+    fn check_class(&self, class: &str) -> Result<(), TreeConfigError> {
+        if self.reject_unknown_classes {
+            if let Some(database) = self.database.as_deref() {
+                if !database.classes.contains_key(class) {
+                    return Err(TreeConfigError::UnknownClass {
+                        class: class.to_owned(),
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks a value that is about to be given to a property of an instance
+    /// of the given class, which currently has the value `existing`.
+    pub(crate) fn check_property(
+        &self,
+        class: &str,
+        name: &str,
+        value: &Variant,
+        existing: Option<&Variant>,
+    ) -> Result<(), PropertyError> {
+        if !self.strict_property_types {
+            return Ok(());
+        }
+
+        match self
+            .known_property_type(class, name)
+            .or_else(|| existing.map(Variant::ty))
+        {
+            Some(expected) if expected != value.ty() => Err(PropertyError::WrongType {
+                class: class.to_owned(),
+                property: name.to_owned(),
+                expected,
+                actual: value.ty(),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the type that the reflection database says a property of the
+    /// given class has, if there is a database and it knows the property.
+    pub(crate) fn known_property_type(&self, class: &str, name: &str) -> Option<VariantType> {
+        self.database
+            .as_deref()
+            .and_then(|database| database.find_property_descriptors(class, name))
+            .and_then(|descriptors| match &descriptors.canonical.data_type {
+                DataType::Value(ty) => Some(*ty),
+                DataType::Enum(_) => Some(VariantType::Enum),
+                _ => None,
+            })
+    }
+
+    /// Returns the name that an instance should be given instead of `name`.
+    pub(crate) fn fix_name(&self, name: String) -> String {
+        if !self.sanitize_names {
+            return name;
+        }
+
+        match sanitize_name(&name) {
+            Cow::Borrowed(_) => name,
+            Cow::Owned(sanitized) => sanitized,
+        }
+    }
+
+    /// Cleans up the names of every instance in a builder, if names should
+    /// be sanitized.
+    pub(crate) fn apply_name_fixes(&self, root: &mut InstanceBuilder) {
+        if !self.sanitize_names {
+            return;
+        }
+
+        let mut stack = vec![root];
+        while let Some(builder) = stack.pop() {
+            builder.name = self.fix_name(std::mem::take(&mut builder.name));
+            stack.extend(&mut builder.children);
+        }
+    }
+
+    /// Gives every instance in a builder the default values of the properties
+    /// it doesn't have.
+    pub(crate) fn apply_defaults(&self, root: &mut InstanceBuilder) {
+        let database = match self.database.as_deref() {
+            Some(database) if self.fill_defaults => database,
+            _ => return,
+        };
+
+        let mut stack = vec![root];
+        while let Some(builder) = stack.pop() {
+            if let Some(descriptor) = database.classes.get(builder.class.as_str()) {
+                for (name, value) in &descriptor.default_properties {
+                    builder
+                        .properties
+                        .entry(name.to_string())
+                        .or_insert_with(|| value.clone());
+                }
+            }
+
+            stack.extend(&mut builder.children);
+        }
+    }
+
+    /// Fixes the name of an instance that is being moved into the DOM from
+    /// another one and gives it any default values it's missing, like
+    /// inserted instances get.
+    pub(crate) fn apply_to_instance(&self, instance: &mut Instance) {
+        instance.name = self.fix_name(std::mem::take(&mut instance.name));
+
+        let database = match self.database.as_deref() {
+            Some(database) if self.fill_defaults => database,
+            _ => return,
+        };
+
+        if let Some(descriptor) = database.classes.get(instance.class.as_str()) {
+            for (name, value) in &descriptor.default_properties {
+                if !instance.properties.contains_key(name.as_ref()) {
+                    instance.properties.insert(name.to_string(), value.clone());
+                }
+            }
+        }
+    }
+}
+
+/// Gives every instance in a builder a new referent from `next`, pointing
+/// `Ref` properties inside of the builder at the new referents.
+pub(crate) fn assign_referents(root: &mut InstanceBuilder, mut next: impl FnMut() -> Ref) {
+    let mut new_refs = HashMap::new();
+
+    let mut stack = vec![&*root];
+    while let Some(builder) = stack.pop() {
+        new_refs.insert(builder.referent, next());
+        stack.extend(builder.children.iter().rev());
+    }
+
+    let mut stack = vec![root];
+    while let Some(builder) = stack.pop() {
+        builder.referent = new_refs[&builder.referent];
+
+        for value in builder.properties.values_mut() {
+            if let Variant::Ref(target) = value {
+                if let Some(&new_target) = new_refs.get(target) {
+                    *target = new_target;
+                }
+            }
+        }
+
+        stack.extend(&mut builder.children);
+    }
+}
+
+/// An instance or property was rejected by a DOM's [`TreeConfig`], or an
+/// instance couldn't be inserted because of its referent.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum TreeConfigError {
+    /// The instance's class isn't in the reflection database.
+    #[error("class {class} is not in the reflection database")]
+    UnknownClass {
+        /// The ClassName of the instance.
+        class: String,
+    },
+
+    /// A property had the wrong type.
+    #[error(transparent)]
+    Property(#[from] PropertyError),
+
+    /// An instance had a referent that is already used by an instance in the
+    /// DOM, or by another instance being inserted with it.
+    #[error("referent {referent} is already in use")]
+    DuplicateReferent {
+        /// The referent that was used twice.
+        referent: Ref,
+    },
+
+    /// An instance had a referent that points to nothing.
+    #[error("instances cannot have a none referent")]
+    NoneReferent,
+}
@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+
+use rbx_types::Ref;
+use thiserror::Error;
+
+use crate::{Instance, InstanceBuilder, JournalEntry, TreeConfigError, WeakDom, WeakDomView};
+
+/// An instance and its descendants that were removed from a DOM with
+/// [`WeakDom::detach`].
+///
+/// The subtree can be looked at while it's detached, then either attached to
+/// a DOM again with [`DetachedSubtree::reattach_to`] or turned back into an
+/// [`InstanceBuilder`]. Referents, metadata, and `Ref` properties are kept as
+/// they were, unless the DOM it's attached to needs the subtree to have new
+/// referents, like [`WeakDom::transfer`] describes.
+///
+/// A detached subtree doesn't keep the [`TreeConfig`][crate::TreeConfig] of
+/// the DOM it came from. It's checked against the config of the DOM it's
+/// attached to instead.
+///
+/// ```
+/// use rbx_dom_weak::{InstanceBuilder, WeakDom};
+///
+/// let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+/// let root_ref = dom.root_ref();
+/// let folder = dom.insert(root_ref, InstanceBuilder::new("Folder"));
+/// let workspace = dom.insert(root_ref, InstanceBuilder::new("Workspace"));
+///
+/// let detached = dom.detach(folder);
+/// assert_eq!(detached.root().class, "Folder");
+/// assert!(dom.get_by_ref(folder).is_none());
+///
+/// detached.reattach_to(&mut dom, workspace);
+/// assert_eq!(dom.get_by_ref(folder).unwrap().parent(), workspace);
+/// ```
+#[derive(Debug, Clone)]
+pub struct DetachedSubtree {
+    dom: WeakDom,
+}
+
+impl DetachedSubtree {
+    pub(crate) fn new(dom: WeakDom) -> Self {
+        Self { dom }
+    }
+
+    /// Returns the referent of the instance that was detached.
+    pub fn root_ref(&self) -> Ref {
+        self.dom.root_ref()
+    }
+
+    /// Returns the instance that was detached. Its parent is always none.
+    pub fn root(&self) -> &Instance {
+        self.dom.root()
+    }
+
+    /// Returns the detached instance or one of its descendants by referent,
+    /// or `None` if it is not found.
+    pub fn get_by_ref(&self, referent: Ref) -> Option<&Instance> {
+        self.dom.get_by_ref(referent)
+    }
+
+    /// Returns a read-only view of the subtree.
+    pub fn view(&self) -> WeakDomView<'_> {
+        self.dom.view()
+    }
+
+    /// Attaches the subtree to `dest` under the given parent, returning the
+    /// referent of its root instance in `dest`.
+    ///
+    /// ## Panics
+    /// Panics if `parent_ref` does not refer to an instance in `dest`, or if
+    /// `dest`'s [`TreeConfig`][crate::TreeConfig] rejects any of the
+    /// instances. [`DetachedSubtree::try_reattach_to`] returns an error for
+    /// the latter instead.
+    pub fn reattach_to(self, dest: &mut WeakDom, parent_ref: Ref) -> Ref {
+        self.try_reattach_to(dest, parent_ref)
+            .unwrap_or_else(|err| panic!("cannot reattach subtree: {}", err.error))
+    }
+
+    /// Attaches the subtree to `dest` under the given parent like
+    /// [`DetachedSubtree::reattach_to`], returning an error that gives the
+    /// subtree back if `dest`'s [`TreeConfig`][crate::TreeConfig] rejects any
+    /// of the instances.
+    ///
+    /// ## Panics
+    /// Panics if `parent_ref` does not refer to an instance in `dest`.
+    pub fn try_reattach_to(
+        mut self,
+        dest: &mut WeakDom,
+        parent_ref: Ref,
+    ) -> Result<Ref, ReattachError> {
+        let referent = self.dom.root_ref();
+        let new_ref = match self.dom.move_subtree(referent, dest, parent_ref) {
+            Ok(new_ref) => new_ref,
+            Err(error) => {
+                return Err(ReattachError {
+                    subtree: Box::new(self),
+                    error,
+                })
+            }
+        };
+
+        dest.record(JournalEntry::TransferIn {
+            referent: new_ref,
+            parent: parent_ref,
+            instances: dest.journal_subtree(new_ref),
+        });
+
+        Ok(new_ref)
+    }
+
+    /// Turns the subtree into an [`InstanceBuilder`] that keeps the same
+    /// referents. Metadata isn't kept.
+    pub fn into_builder(self) -> InstanceBuilder {
+        let (root_ref, mut instances) = self.dom.into_raw();
+        builder(&mut instances, root_ref)
+    }
+}
+
+/// A [`DetachedSubtree`] couldn't be attached to a DOM because the DOM's
+/// [`TreeConfig`][crate::TreeConfig] rejected it. The subtree is given back
+/// unchanged.
+#[derive(Debug, Error)]
+#[error("{error}")]
+pub struct ReattachError {
+    /// The subtree that couldn't be attached. It's boxed to keep the error
+    /// small.
+    pub subtree: Box<DetachedSubtree>,
+
+    /// Why it couldn't be attached.
+    pub error: TreeConfigError,
+}
+
+/// Takes the instance with the given referent and its descendants out of
+/// `instances` and turns them into a builder with the same referents.
+pub(crate) fn builder(instances: &mut HashMap<Ref, Instance>, referent: Ref) -> InstanceBuilder {
+    let instance = instances.remove(&referent).unwrap();
+
+    InstanceBuilder::new(instance.class)
+        .with_referent(referent)
+        .with_name(instance.name)
+        .with_properties(instance.properties.into_inner())
+        .with_children(
+            instance
+                .children
+                .iter()
+                .map(|&child| builder(instances, child)),
+        )
+}
@@ -1,8 +1,29 @@
-use std::collections::{HashMap, HashSet, VecDeque};
-
-use rbx_types::{Ref, UniqueId, Variant};
-
-use crate::instance::{Instance, InstanceBuilder};
+use std::{
+    any::Any,
+    collections::{HashMap, HashSet, VecDeque},
+    hash::Hasher,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, MutexGuard, PoisonError,
+    },
+};
+
+use rbx_types::{Ref, StableHasher, UniqueId, Variant};
+
+use crate::{
+    cancel::Cancelled,
+    config::{assign_referents, TreeConfig, TreeConfigError},
+    detached::DetachedSubtree,
+    edit::InstanceEditor,
+    equality::{Comparison, EqualityOptions},
+    extensions::Extensions,
+    instance::{Instance, InstanceBuilder},
+    journal::JournalEntry,
+    property_map::PropertyInterner,
+    typed::PropertyError,
+    view::WeakDomView,
+    visitor::TreeVisitor,
+};
 
 /// Represents a DOM containing one or more Roblox instances.
 ///
@@ -11,26 +32,68 @@ use crate::instance::{Instance, InstanceBuilder};
 ///
 /// When constructing instances, you'll want to create [`InstanceBuilder`]
 /// objects and insert them into the tree.
-#[derive(Debug)]
+///
+/// `WeakDom` is `Send` and `Sync`. A DOM that needs to be read from many
+/// threads and written to occasionally can be wrapped in a
+/// [`SharedDom`][crate::SharedDom], which gives readers snapshots of it
+/// without holding a lock.
+///
+/// [`WeakDom::snapshot`] and cloning are cheap ways to copy a `WeakDom`:
+/// instances' properties are shared with the copy until either DOM changes
+/// them.
+#[derive(Debug, Clone)]
 pub struct WeakDom {
     instances: HashMap<Ref, Instance>,
     root_ref: Ref,
     unique_ids: HashSet<UniqueId>,
+    journal: Option<Vec<JournalEntry>>,
+    generation: u64,
+    descendant_counts: Option<(u64, HashMap<Ref, usize>)>,
+    metadata: HashMap<Ref, HashMap<String, String>>,
+    extensions: Extensions,
+    dirty: Option<HashSet<Ref>>,
+    config: TreeConfig,
+    inserted: u64,
+    subtree_hashes: HashCache,
 }
 
 impl WeakDom {
     /// Construct a new `WeakDom` described by the given [`InstanceBuilder`].
     pub fn new(builder: InstanceBuilder) -> WeakDom {
+        WeakDom::with_config(builder, TreeConfig::default())
+    }
+
+    /// Construct a new `WeakDom` described by the given [`InstanceBuilder`],
+    /// which checks and changes instances put into it as the given
+    /// [`TreeConfig`] says to.
+    ///
+    /// ## Panics
+    /// Panics if the config rejects any of the instances in `builder`.
+    pub fn with_config(builder: InstanceBuilder, config: TreeConfig) -> WeakDom {
         let mut dom = WeakDom {
             instances: HashMap::new(),
-            root_ref: builder.referent,
+            root_ref: Ref::none(),
             unique_ids: HashSet::new(),
+            journal: None,
+            generation: 0,
+            descendant_counts: None,
+            metadata: HashMap::new(),
+            extensions: Extensions::default(),
+            dirty: None,
+            config,
+            inserted: 0,
+            subtree_hashes: HashCache::default(),
         };
 
-        dom.insert(Ref::none(), builder);
+        dom.root_ref = dom.insert(Ref::none(), builder);
         dom
     }
 
+    /// Returns the [`TreeConfig`] that this DOM was made with.
+    pub fn config(&self) -> &TreeConfig {
+        &self.config
+    }
+
     /// Consumes the WeakDom, returning its underlying root ref and backing
     /// storage. This method is useful when tree-preserving operations are too
     /// slow.
@@ -50,6 +113,7 @@ impl WeakDom {
 
     /// Returns a _mutable_ reference to the root instance of the `WeakDom`.
     pub fn root_mut(&mut self) -> &mut Instance {
+        self.invalidate_hashes(self.root_ref);
         self.instances.get_mut(&self.root_ref).unwrap()
     }
 
@@ -62,14 +126,641 @@ impl WeakDom {
     /// Returns a _mutable_ reference to an instance by referent, or `None` if
     /// it is not found.
     pub fn get_by_ref_mut(&mut self, referent: Ref) -> Option<&mut Instance> {
+        self.invalidate_hashes(referent);
         self.instances.get_mut(&referent)
     }
 
-    /// Insert a new instance into the DOM with the given parent.
+    /// Returns an [`InstanceEditor`] for changing the name and properties of
+    /// the instance with the given referent. Unlike changes made through
+    /// [`WeakDom::get_by_ref_mut`], changes made through the editor are
+    /// recorded in the journal.
     ///
     /// ## Panics
-    /// Panics if `parent_ref` does not refer to an instance in the DOM.
+    /// Panics if `referent` does not refer to an instance in the DOM.
+    pub fn edit(&mut self, referent: Ref) -> InstanceEditor<'_> {
+        InstanceEditor::new(self, referent)
+    }
+
+    /// Sets many properties of the instance with the given referent at once.
+    ///
+    /// Values are converted to the types their properties already have like
+    /// [`Instance::set_typed`] does, unless the DOM's [`TreeConfig`] has strict
+    /// property types. Every value is checked before any of
+    /// them are set, so if one can't be converted, an error is returned and
+    /// the instance is left untouched. Otherwise, the changes are recorded in
+    /// the journal as a single [`JournalEntry::Edit`].
+    ///
+    /// ## Panics
+    /// Panics if `referent` does not refer to an instance in the DOM.
+    pub fn update_properties<K, V, I>(
+        &mut self,
+        referent: Ref,
+        properties: I,
+    ) -> Result<(), PropertyError>
+    where
+        K: Into<String>,
+        V: Into<Variant>,
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let instance = self
+            .instances
+            .get(&referent)
+            .unwrap_or_else(|| panic!("cannot update an instance that does not exist"));
+
+        let properties = properties
+            .into_iter()
+            .map(|(name, value)| {
+                let name = name.into();
+                let value = value.into();
+                self.config.check_property(
+                    &instance.class,
+                    &name,
+                    &value,
+                    instance.properties.get(&name),
+                )?;
+
+                let value = instance.coerce_for(&name, value)?;
+                Ok((name, value))
+            })
+            .collect::<Result<Vec<_>, PropertyError>>()?;
+
+        let mut editor = self.edit(referent);
+        for (name, value) in properties {
+            editor.set_property(name, value);
+        }
+
+        Ok(())
+    }
+
+    /// Returns an immutable copy of the DOM as it is right now, for comparing
+    /// against later, like a sync server that diffs a live tree against what
+    /// it last sent every time it polls.
+    ///
+    /// The snapshot shares every instance's properties with the DOM until
+    /// the DOM changes them, so taking one only costs as much as copying the
+    /// hierarchy. The journal and dirty tracking aren't part of the snapshot.
+    /// The snapshot can be cloned cheaply and shared between threads.
+    ///
+    /// ```
+    /// use rbx_dom_weak::{InstanceBuilder, WeakDom};
+    ///
+    /// let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+    /// let before = dom.snapshot();
+    ///
+    /// let root_ref = dom.root_ref();
+    /// dom.insert(root_ref, InstanceBuilder::new("Workspace"));
+    ///
+    /// assert!(before.root().children().is_empty());
+    /// assert_eq!(dom.root().children().len(), 1);
+    /// ```
+    pub fn snapshot(&self) -> Arc<WeakDom> {
+        Arc::new(WeakDom {
+            instances: self.instances.clone(),
+            root_ref: self.root_ref,
+            unique_ids: self.unique_ids.clone(),
+            journal: None,
+            generation: self.generation,
+            descendant_counts: self.descendant_counts.clone(),
+            metadata: self.metadata.clone(),
+            extensions: self.extensions.clone(),
+            dirty: None,
+            config: self.config.clone(),
+            inserted: self.inserted,
+            subtree_hashes: self.subtree_hashes.clone(),
+        })
+    }
+
+    /// Returns a read-only view of the DOM, for handing to code that should be
+    /// able to read it but not change it.
+    pub fn view(&self) -> WeakDomView<'_> {
+        WeakDomView::from(self)
+    }
+
+    /// Returns the metadata attached to the instance with the given referent,
+    /// if any has been attached with [`WeakDom::metadata_mut`].
+    pub fn metadata(&self, referent: Ref) -> Option<&HashMap<String, String>> {
+        self.metadata.get(&referent)
+    }
+
+    /// Returns a _mutable_ reference to the metadata attached to the instance
+    /// with the given referent, attaching an empty table if there is none.
+    ///
+    /// Metadata is for tools to keep track of things like which file an
+    /// instance came from. It is never serialized. It stays with an instance
+    /// when it is moved with [`WeakDom::transfer`] or
+    /// [`WeakDom::transfer_within`], is copied by [`WeakDom::clone_within`],
+    /// and is dropped when the instance is destroyed.
+    ///
+    /// ## Panics
+    /// Panics if `referent` does not refer to an instance in the DOM.
+    pub fn metadata_mut(&mut self, referent: Ref) -> &mut HashMap<String, String> {
+        if !self.instances.contains_key(&referent) {
+            panic!("cannot attach metadata to an instance that does not exist");
+        }
+
+        self.metadata.entry(referent).or_default()
+    }
+
+    /// Returns the value of type `T` attached to the instance with the given
+    /// referent with [`WeakDom::insert_extension`], if there is one.
+    pub fn extension<T: Any>(&self, referent: Ref) -> Option<&T> {
+        self.extensions.get(referent)
+    }
+
+    /// Returns a _mutable_ reference to the value of type `T` attached to the
+    /// instance with the given referent, if there is one.
+    pub fn extension_mut<T: Any>(&mut self, referent: Ref) -> Option<&mut T> {
+        self.extensions.get_mut(referent)
+    }
+
+    /// Attaches a value to the instance with the given referent, returning
+    /// the value of the same type that was attached before, if there was one.
+    /// Each instance can have one value of each type attached.
+    ///
+    /// Extensions are for tools to keep state like layout caches or analysis
+    /// results alongside instances without another map to keep in sync. Like
+    /// metadata, they are never serialized, stay with an instance when it is
+    /// moved to another DOM, and are dropped when the instance is destroyed.
+    /// Unlike metadata, they aren't copied by [`WeakDom::clone_within`].
+    ///
+    /// A clone of the whole DOM shares its extensions with the DOM it was
+    /// cloned from, and a value is copied the first time it's changed through
+    /// [`WeakDom::extension_mut`] by a DOM that shares it, so values that one
+    /// DOM changes aren't seen by the other.
+    ///
+    /// ```
+    /// use rbx_dom_weak::{InstanceBuilder, WeakDom};
+    ///
+    /// #[derive(Clone)]
+    /// struct Bounds(f32);
+    ///
+    /// let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+    /// let root_ref = dom.root_ref();
+    /// let part = dom.insert(root_ref, InstanceBuilder::new("Part"));
+    ///
+    /// dom.insert_extension(part, Bounds(4.0));
+    /// assert_eq!(dom.extension::<Bounds>(part).map(|bounds| bounds.0), Some(4.0));
+    ///
+    /// dom.destroy(part);
+    /// assert!(dom.extension::<Bounds>(part).is_none());
+    /// ```
+    ///
+    /// ## Panics
+    /// Panics if `referent` does not refer to an instance in the DOM.
+    pub fn insert_extension<T: Any + Clone + Send + Sync>(
+        &mut self,
+        referent: Ref,
+        value: T,
+    ) -> Option<T> {
+        if !self.instances.contains_key(&referent) {
+            panic!("cannot attach an extension to an instance that does not exist");
+        }
+
+        self.extensions.insert(referent, value)
+    }
+
+    /// Removes the value of type `T` attached to the instance with the given
+    /// referent, returning it if there was one.
+    pub fn remove_extension<T: Any>(&mut self, referent: Ref) -> Option<T> {
+        self.extensions.remove(referent)
+    }
+
+    /// Start recording changes made to this DOM. Recorded entries can be
+    /// retrieved with [`WeakDom::drain_journal`].
+    ///
+    /// Only changes made through `WeakDom` methods and [`WeakDom::edit`] are
+    /// recorded. Edits made to an instance's name or properties through
+    /// [`WeakDom::get_by_ref_mut`] are not visible to the journal.
+    pub fn enable_journal(&mut self) {
+        if self.journal.is_none() {
+            self.journal = Some(Vec::new());
+        }
+    }
+
+    /// Stop recording structural changes, discarding any entries that have
+    /// not been drained yet.
+    pub fn disable_journal(&mut self) {
+        self.journal = None;
+    }
+
+    /// Tells whether this DOM is currently recording structural changes.
+    pub fn is_journal_enabled(&self) -> bool {
+        self.journal.is_some()
+    }
+
+    /// Removes and returns all journal entries recorded since the last call to
+    /// `drain_journal`, in the order the changes were made.
+    ///
+    /// Returns an empty `Vec` if journaling is not enabled.
+    pub fn drain_journal(&mut self) -> Vec<JournalEntry> {
+        match &mut self.journal {
+            Some(journal) => std::mem::take(journal),
+            None => Vec::new(),
+        }
+    }
+
+    /// Start keeping track of which instances have changed. Each change
+    /// recorded in the journal, whether or not the journal is enabled, marks
+    /// the instances it affected as dirty along with all of their ancestors,
+    /// so only dirty subtrees have to be looked at to find what changed.
+    ///
+    /// Instances are clean when tracking starts. Like the journal, dirty
+    /// tracking doesn't see changes made through [`WeakDom::get_by_ref_mut`].
+    pub fn enable_dirty_tracking(&mut self) {
+        if self.dirty.is_none() {
+            self.dirty = Some(HashSet::new());
+        }
+    }
+
+    /// Stop keeping track of which instances have changed, forgetting which
+    /// ones are dirty.
+    pub fn disable_dirty_tracking(&mut self) {
+        self.dirty = None;
+    }
+
+    /// Tells whether the instance with the given referent, or anything inside
+    /// of it, has changed since dirty tracking was enabled or the instance was
+    /// last cleared with [`WeakDom::clear_dirty`].
+    ///
+    /// Returns `false` if dirty tracking isn't enabled.
+    pub fn is_dirty(&self, referent: Ref) -> bool {
+        self.dirty
+            .as_ref()
+            .is_some_and(|dirty| dirty.contains(&referent))
+    }
+
+    /// Returns every dirty instance from the instance with the given referent
+    /// down, parents before their children. Clean subtrees are skipped
+    /// without being walked.
+    ///
+    /// ```
+    /// use rbx_dom_weak::{InstanceBuilder, WeakDom};
+    ///
+    /// let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+    /// let root_ref = dom.root_ref();
+    /// let workspace = dom.insert(root_ref, InstanceBuilder::new("Workspace"));
+    /// let part = dom.insert(workspace, InstanceBuilder::new("Part"));
+    /// dom.insert(root_ref, InstanceBuilder::new("Lighting"));
+    ///
+    /// dom.enable_dirty_tracking();
+    /// dom.edit(part).set_property("Anchored", true);
+    /// assert_eq!(dom.dirty_descendants(root_ref), [root_ref, workspace, part]);
+    ///
+    /// dom.clear_dirty(root_ref);
+    /// assert!(dom.dirty_descendants(root_ref).is_empty());
+    /// ```
+    ///
+    /// ## Panics
+    /// Panics if `referent` does not refer to an instance in the DOM.
+    pub fn dirty_descendants(&self, referent: Ref) -> Vec<Ref> {
+        if !self.instances.contains_key(&referent) {
+            panic!("cannot look for changes in an instance that does not exist");
+        }
+
+        let mut found = Vec::new();
+        let mut stack = vec![referent];
+
+        while let Some(referent) = stack.pop() {
+            if !self.is_dirty(referent) {
+                continue;
+            }
+
+            found.push(referent);
+            stack.extend(self.instances[&referent].children.iter().rev());
+        }
+
+        found
+    }
+
+    /// Marks the instance with the given referent and all of its descendants
+    /// as clean, usually once their changes have been dealt with. Its
+    /// ancestors are left as they are.
+    ///
+    /// ## Panics
+    /// Panics if `referent` does not refer to an instance in the DOM.
+    pub fn clear_dirty(&mut self, referent: Ref) {
+        for referent in self.dirty_descendants(referent) {
+            self.dirty.as_mut().unwrap().remove(&referent);
+        }
+    }
+
+    /// Returns a counter that goes up every time the structure of the DOM is
+    /// changed through a `WeakDom` method. Two calls that return the same value
+    /// mean that no instances were added, removed, or moved in between.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Counts the descendants of every instance in the DOM in a single pass
+    /// and remembers the counts, so that [`WeakDom::descendant_count`] can
+    /// answer without walking the tree.
+    ///
+    /// The cached counts are thrown out as soon as the DOM's structure
+    /// changes, and this method must be called again to bring them back.
+    pub fn cache_descendant_counts(&mut self) {
+        let mut counts = HashMap::with_capacity(self.instances.len());
+
+        // Visit every instance after all of its children, like
+        // `subtree_hashes` does.
+        let mut stack = vec![(self.root_ref, false)];
+
+        while let Some((referent, children_done)) = stack.pop() {
+            let instance = &self.instances[&referent];
+
+            if !children_done {
+                stack.push((referent, true));
+                stack.extend(instance.children.iter().map(|&child| (child, false)));
+                continue;
+            }
+
+            let count = instance
+                .children
+                .iter()
+                .map(|child| counts[child] + 1)
+                .sum();
+
+            counts.insert(referent, count);
+        }
+
+        self.descendant_counts = Some((self.generation, counts));
+    }
+
+    /// Returns the number of descendants of the instance with the given
+    /// referent, not counting the instance itself.
+    ///
+    /// This uses the counts from [`WeakDom::cache_descendant_counts`] if the
+    /// DOM hasn't changed since they were made, and walks the subtree
+    /// otherwise.
+    ///
+    /// ## Panics
+    /// Panics if `referent` does not refer to an instance in the DOM.
+    pub fn descendant_count(&self, referent: Ref) -> usize {
+        if let Some((generation, counts)) = &self.descendant_counts {
+            if *generation == self.generation {
+                if let Some(&count) = counts.get(&referent) {
+                    return count;
+                }
+            }
+        }
+
+        let instance = self.instances.get(&referent).unwrap_or_else(|| {
+            panic!("cannot count the descendants of an instance that does not exist")
+        });
+
+        let mut count = 0;
+        let mut stack = instance.children.clone();
+
+        while let Some(referent) = stack.pop() {
+            count += 1;
+            stack.extend_from_slice(&self.instances[&referent].children);
+        }
+
+        count
+    }
+
+    /// Returns a path to the instance with the given referent that can be
+    /// pasted into Luau code, like `game.Workspace.Model.Part`, for logs and
+    /// error messages.
+    ///
+    /// This is the same as Roblox's `Instance:GetFullName`, except for two
+    /// things. If the root of the DOM is a DataModel, the path starts with
+    /// `game`, and otherwise it starts with the root's name. Names that can't
+    /// be written after a dot, like names with spaces or dots in them, are
+    /// written in brackets instead, like `game.Workspace["My Model"]`.
+    ///
+    /// ```
+    /// use rbx_dom_weak::{InstanceBuilder, WeakDom};
+    ///
+    /// let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+    /// let workspace = dom.insert(dom.root_ref(), InstanceBuilder::new("Workspace"));
+    /// let model = dom.insert(workspace, InstanceBuilder::new("Model").with_name("My Model"));
+    /// let part = dom.insert(model, InstanceBuilder::new("Part"));
+    ///
+    /// assert_eq!(dom.full_name(part), r#"game.Workspace["My Model"].Part"#);
+    /// ```
+    ///
+    /// ## Panics
+    /// Panics if `referent` does not refer to an instance in the DOM.
+    pub fn full_name(&self, referent: Ref) -> String {
+        let mut names = Vec::new();
+        let mut current = self.instances.get(&referent).unwrap_or_else(|| {
+            panic!("cannot get the full name of an instance that does not exist")
+        });
+
+        while current.referent != self.root_ref {
+            names.push(current.name.as_str());
+            current = &self.instances[&current.parent];
+        }
+
+        let mut full_name = if current.class == "DataModel" {
+            String::from("game")
+        } else {
+            current.name.clone()
+        };
+
+        for name in names.into_iter().rev() {
+            push_name(&mut full_name, name);
+        }
+
+        full_name
+    }
+
+    pub(crate) fn record(&mut self, entry: JournalEntry) {
+        self.generation += 1;
+        self.record_edit(entry);
+    }
+
+    /// Copies the instance with the given referent and its descendants,
+    /// parents before their children, for a journal entry. Nothing is copied
+    /// if the journal isn't enabled.
+    pub(crate) fn journal_subtree(&self, referent: Ref) -> Vec<Instance> {
+        let mut instances = Vec::new();
+        if self.journal.is_none() {
+            return instances;
+        }
+
+        let mut queue = VecDeque::new();
+        queue.push_back(referent);
+        while let Some(referent) = queue.pop_front() {
+            let instance = &self.instances[&referent];
+            queue.extend(instance.children.iter().copied());
+            instances.push(instance.clone());
+        }
+
+        instances
+    }
+
+    /// Records a change that doesn't affect the structure of the DOM, so
+    /// unlike `record`, it leaves the generation alone.
+    pub(crate) fn record_edit(&mut self, entry: JournalEntry) {
+        match entry {
+            JournalEntry::Insert { parent, .. }
+            | JournalEntry::TransferIn { parent, .. }
+            | JournalEntry::Destroy { parent, .. }
+            | JournalEntry::TransferOut { parent, .. } => self.invalidate_hashes(parent),
+            JournalEntry::Move {
+                old_parent,
+                new_parent,
+                ..
+            } => {
+                self.invalidate_hashes(old_parent);
+                self.invalidate_hashes(new_parent);
+            }
+            JournalEntry::Edit { referent, .. } => self.invalidate_hashes(referent),
+        }
+
+        if self.dirty.is_some() {
+            self.mark_dirty(&entry);
+        }
+
+        if let Some(journal) = &mut self.journal {
+            journal.push(entry);
+        }
+    }
+
+    /// Marks the instances a change affected as dirty, along with their
+    /// ancestors.
+    fn mark_dirty(&mut self, entry: &JournalEntry) {
+        let (subtree, parents) = match *entry {
+            JournalEntry::Insert {
+                referent, parent, ..
+            }
+            | JournalEntry::TransferIn {
+                referent, parent, ..
+            } => (Some(referent), [parent, Ref::none()]),
+            JournalEntry::Destroy { parent, .. } | JournalEntry::TransferOut { parent, .. } => {
+                (None, [parent, Ref::none()])
+            }
+            JournalEntry::Move {
+                referent,
+                old_parent,
+                new_parent,
+            } => (Some(referent), [old_parent, new_parent]),
+            JournalEntry::Edit { referent, .. } => {
+                self.mark_ancestors_dirty(referent);
+                return;
+            }
+        };
+
+        if let Some(referent) = subtree {
+            let mut stack = vec![referent];
+            while let Some(referent) = stack.pop() {
+                self.dirty.as_mut().unwrap().insert(referent);
+                stack.extend_from_slice(&self.instances[&referent].children);
+            }
+        }
+
+        for parent in parents {
+            self.mark_ancestors_dirty(parent);
+        }
+    }
+
+    /// Marks an instance and its ancestors as dirty, stopping at the first one
+    /// that already is, since its ancestors must be too.
+    fn mark_ancestors_dirty(&mut self, mut referent: Ref) {
+        let dirty = self.dirty.as_mut().unwrap();
+
+        while let Some(instance) = self.instances.get(&referent) {
+            if !dirty.insert(referent) {
+                break;
+            }
+
+            referent = instance.parent;
+        }
+    }
+
+    /// Insert a new instance into the DOM with the given parent, returning its
+    /// referent.
+    ///
+    /// The returned referent is the referent of `root_builder` unless the
+    /// DOM's [`TreeConfig`] gives inserted instances deterministic referents.
+    ///
+    /// ## Panics
+    /// Panics if `parent_ref` does not refer to an instance in the DOM, if the
+    /// DOM's [`TreeConfig`] rejects any of the instances being inserted, or if
+    /// any of their referents are already in use. [`WeakDom::try_insert`]
+    /// returns an error instead.
     pub fn insert(&mut self, parent_ref: Ref, root_builder: InstanceBuilder) -> Ref {
+        self.try_insert(parent_ref, root_builder)
+            .unwrap_or_else(|err| panic!("cannot insert instance: {}", err))
+    }
+
+    /// Insert a new instance into the DOM with the given parent like
+    /// [`WeakDom::insert`], returning an error if the DOM's [`TreeConfig`]
+    /// rejects any of the instances being inserted or if any of their
+    /// referents are already in use. Nothing is inserted if an error is
+    /// returned.
+    ///
+    /// ## Panics
+    /// Panics if `parent_ref` does not refer to an instance in the DOM.
+    pub fn try_insert(
+        &mut self,
+        parent_ref: Ref,
+        mut root_builder: InstanceBuilder,
+    ) -> Result<Ref, TreeConfigError> {
+        self.config.check_builder(&root_builder)?;
+        self.config.apply_defaults(&mut root_builder);
+        self.config.apply_name_fixes(&mut root_builder);
+
+        if self.config.referent_namespace().is_some() {
+            assign_referents(&mut root_builder, || self.new_referent());
+        }
+
+        self.check_referents(&root_builder)?;
+
+        Ok(self.insert_unchecked(parent_ref, root_builder))
+    }
+
+    /// Checks that none of the instances described by `root` would replace an
+    /// instance already in the DOM or one another, which referents chosen with
+    /// [`InstanceBuilder::with_referent`] could otherwise do.
+    fn check_referents(&self, root: &InstanceBuilder) -> Result<(), TreeConfigError> {
+        let check = |referent: Ref| {
+            if referent.is_none() {
+                Err(TreeConfigError::NoneReferent)
+            } else if self.instances.contains_key(&referent) {
+                Err(TreeConfigError::DuplicateReferent { referent })
+            } else {
+                Ok(())
+            }
+        };
+
+        // Most instances are inserted one at a time, which doesn't need a set
+        // of every referent being inserted.
+        if root.children.is_empty() {
+            return check(root.referent);
+        }
+
+        let mut seen = HashSet::new();
+        let mut stack = vec![root];
+        while let Some(builder) = stack.pop() {
+            check(builder.referent)?;
+
+            if !seen.insert(builder.referent) {
+                return Err(TreeConfigError::DuplicateReferent {
+                    referent: builder.referent,
+                });
+            }
+
+            stack.extend(&builder.children);
+        }
+
+        Ok(())
+    }
+
+    /// Returns a referent for a new instance, which is random unless the
+    /// DOM's [`TreeConfig`] gives new instances deterministic referents.
+    fn new_referent(&mut self) -> Ref {
+        match self.config.referent_namespace() {
+            Some(namespace) => {
+                self.inserted += 1;
+                Ref::from_namespace(namespace, &self.inserted.to_le_bytes())
+            }
+            None => Ref::new(),
+        }
+    }
+
+    fn insert_unchecked(&mut self, parent_ref: Ref, root_builder: InstanceBuilder) -> Ref {
         let root_referent = root_builder.referent;
 
         // Rather than performing this movement recursively, we instead use a
@@ -87,7 +778,7 @@ impl WeakDom {
                     parent,
                     name: builder.name,
                     class: builder.class,
-                    properties: builder.properties,
+                    properties: builder.properties.into(),
                 },
             );
 
@@ -104,6 +795,14 @@ impl WeakDom {
             }
         }
 
+        if parent_ref.is_some() {
+            self.record(JournalEntry::Insert {
+                referent: root_referent,
+                parent: parent_ref,
+                instances: self.journal_subtree(root_referent),
+            });
+        }
+
         root_referent
     }
 
@@ -119,220 +818,2333 @@ impl WeakDom {
             panic!("cannot destroy the root instance of a WeakDom");
         }
 
-        let instance = self
-            .instances
-            .get(&referent)
-            .unwrap_or_else(|| panic!("cannot destroy an instance that does not exist"));
+        let instance = self
+            .instances
+            .get(&referent)
+            .unwrap_or_else(|| panic!("cannot destroy an instance that does not exist"));
+
+        let parent_ref = instance.parent;
+        let parent = self.instances.get_mut(&parent_ref).unwrap();
+        parent.children.retain(|&child| child != referent);
+
+        let mut to_remove = VecDeque::new();
+        to_remove.push_back(referent);
+
+        // The destroyed instances are only kept if the journal needs them.
+        let mut destroyed = Vec::new();
+        while let Some(referent) = to_remove.pop_front() {
+            let instance = self.inner_remove(referent);
+            self.metadata.remove(&referent);
+            self.extensions.take_all(referent);
+            to_remove.extend(instance.children.iter().copied());
+
+            if self.journal.is_some() {
+                destroyed.push(instance);
+            }
+        }
+
+        self.record(JournalEntry::Destroy {
+            referent,
+            parent: parent_ref,
+            instances: destroyed,
+        });
+    }
+
+    /// Destroys every child of the instance with the given referent that
+    /// `keep` returns `false` for, along with its descendants. The order of
+    /// the remaining children is kept.
+    ///
+    /// ## Panics
+    /// Panics if `referent` does not refer to an instance in the DOM.
+    pub fn retain_children<F>(&mut self, referent: Ref, mut keep: F)
+    where
+        F: FnMut(&Instance) -> bool,
+    {
+        let instance = self.instances.get(&referent).unwrap_or_else(|| {
+            panic!("cannot retain the children of an instance that does not exist")
+        });
+
+        let to_destroy: Vec<Ref> = instance
+            .children
+            .iter()
+            .filter(|child| !keep(&self.instances[child]))
+            .copied()
+            .collect();
+
+        for child in to_destroy {
+            self.destroy(child);
+        }
+    }
+
+    /// Destroys every descendant of the instance with the given referent that
+    /// `keep` returns `false` for, along with its own descendants. Instances
+    /// are visited parents first, so `keep` isn't called on the descendants
+    /// of an instance that is destroyed.
+    ///
+    /// ## Panics
+    /// Panics if `referent` does not refer to an instance in the DOM.
+    pub fn retain_descendants<F>(&mut self, referent: Ref, mut keep: F)
+    where
+        F: FnMut(&Instance) -> bool,
+    {
+        let mut stack = vec![referent];
+
+        while let Some(referent) = stack.pop() {
+            self.retain_children(referent, &mut keep);
+            stack.extend(self.instances[&referent].children.iter().rev());
+        }
+    }
+
+    /// Move the instance with the given referent to a new `WeakDom`, parenting
+    /// it to the given ref, and return the referent it has in `dest`. To move
+    /// to within the same DOM, use [`WeakDom::transfer_within`].
+    ///
+    /// The moved instances are checked and changed as `dest`'s
+    /// [`TreeConfig`] says to, like inserted instances are. They keep their
+    /// referents, except when `dest` gives new instances deterministic
+    /// referents, or when a referent is already used by an instance in
+    /// `dest`, like when both DOMs were read from the same file. Those
+    /// instances are given new referents, and `Ref` properties inside of the
+    /// moved subtree are changed to match.
+    ///
+    /// This function would be called `move`, but that's a Rust keyword!
+    ///
+    /// ## Panics
+    /// Panics if `referent` does not refer to an instance in `self`, if
+    /// `dest_parent_ref` does not refer to an instance in `other_dom`, or if
+    /// `dest`'s [`TreeConfig`] rejects any of the instances being moved.
+    /// [`WeakDom::try_transfer`] returns an error for the latter instead.
+    ///
+    /// Will also panic if `referent` refers to the root instance in this
+    /// `WeakDom`.
+    pub fn transfer(&mut self, referent: Ref, dest: &mut WeakDom, dest_parent_ref: Ref) -> Ref {
+        self.try_transfer(referent, dest, dest_parent_ref)
+            .unwrap_or_else(|err| panic!("cannot transfer instance: {}", err))
+    }
+
+    /// Move the instance with the given referent to a new `WeakDom` like
+    /// [`WeakDom::transfer`], returning an error if `dest`'s [`TreeConfig`]
+    /// rejects any of the instances being moved. Nothing is moved if an
+    /// error is returned.
+    ///
+    /// ## Panics
+    /// Panics if `referent` does not refer to an instance in `self` or if
+    /// `dest_parent_ref` does not refer to an instance in `other_dom`.
+    ///
+    /// Will also panic if `referent` refers to the root instance in this
+    /// `WeakDom`.
+    pub fn try_transfer(
+        &mut self,
+        referent: Ref,
+        dest: &mut WeakDom,
+        dest_parent_ref: Ref,
+    ) -> Result<Ref, TreeConfigError> {
+        if referent == self.root_ref {
+            panic!("cannot transfer the root instance of WeakDom");
+        }
+
+        let parent_ref = self.parent_of(referent);
+        let moved = self.journal_subtree(referent);
+        let new_ref = self.move_subtree(referent, dest, dest_parent_ref)?;
+
+        self.record(JournalEntry::TransferOut {
+            referent,
+            parent: parent_ref,
+            instances: moved,
+        });
+        dest.record(JournalEntry::TransferIn {
+            referent: new_ref,
+            parent: dest_parent_ref,
+            instances: dest.journal_subtree(new_ref),
+        });
+
+        Ok(new_ref)
+    }
+
+    /// Removes the instance with the given referent and all of its
+    /// descendants from the DOM, returning them as a [`DetachedSubtree`] that
+    /// can be inspected and attached to this or another DOM later.
+    ///
+    /// ## Panics
+    /// Panics if `referent` does not refer to an instance in the DOM.
+    ///
+    /// Will also panic if `referent` refers to the root instance in this
+    /// `WeakDom`.
+    pub fn detach(&mut self, referent: Ref) -> DetachedSubtree {
+        if referent == self.root_ref {
+            panic!("cannot detach the root instance of WeakDom");
+        }
+
+        let parent_ref = self.parent_of(referent);
+        let moved = self.journal_subtree(referent);
+
+        // The detached subtree is checked against the config of whichever DOM
+        // it's attached to, so it doesn't keep this one's.
+        let mut detached = WeakDom {
+            instances: HashMap::new(),
+            root_ref: referent,
+            unique_ids: HashSet::new(),
+            journal: None,
+            generation: 0,
+            descendant_counts: None,
+            metadata: HashMap::new(),
+            extensions: Extensions::default(),
+            dirty: None,
+            config: TreeConfig::default(),
+            inserted: 0,
+            subtree_hashes: HashCache::default(),
+        };
+
+        // An empty DOM with the default config can't reject anything.
+        detached.root_ref = self
+            .move_subtree(referent, &mut detached, Ref::none())
+            .unwrap();
+
+        self.record(JournalEntry::TransferOut {
+            referent,
+            parent: parent_ref,
+            instances: moved,
+        });
+
+        DetachedSubtree::new(detached)
+    }
+
+    /// Returns the referent of the parent of the instance with the given
+    /// referent.
+    fn parent_of(&self, referent: Ref) -> Ref {
+        self.instances
+            .get(&referent)
+            .unwrap_or_else(|| panic!("cannot move an instance that does not exist"))
+            .parent
+    }
+
+    /// Moves the instance with the given referent and all of its descendants
+    /// into `dest` under `dest_parent_ref`, returning the referent it has in
+    /// `dest`. The instance becomes the root of `dest` if `dest_parent_ref` is
+    /// none.
+    ///
+    /// Every instance is checked against `dest`'s config before anything is
+    /// moved, and instances are given new referents as
+    /// [`WeakDom::transfer`] describes.
+    pub(crate) fn move_subtree(
+        &mut self,
+        referent: Ref,
+        dest: &mut WeakDom,
+        dest_parent_ref: Ref,
+    ) -> Result<Ref, TreeConfigError> {
+        if dest_parent_ref.is_some() && !dest.instances.contains_key(&dest_parent_ref) {
+            panic!("cannot move an instance into an instance that does not exist");
+        }
+
+        // Find every instance that's moving breadth-first, so that parents
+        // are moved before their children.
+        let mut to_move = vec![referent];
+        let mut index = 0;
+        while let Some(&referent) = to_move.get(index) {
+            let instance = self
+                .instances
+                .get(&referent)
+                .unwrap_or_else(|| panic!("cannot move an instance that does not exist"));
+
+            dest.config.check_instance(instance)?;
+            to_move.extend(instance.children.iter().copied());
+            index += 1;
+        }
+
+        // Moving instances over ones that are already in `dest` would replace
+        // them, so those are given new referents instead.
+        let rekey_all = dest.config.referent_namespace().is_some();
+        let mut new_refs = HashMap::new();
+        for &referent in &to_move {
+            if rekey_all || dest.instances.contains_key(&referent) {
+                let mut new_ref = dest.new_referent();
+                while dest.instances.contains_key(&new_ref) {
+                    new_ref = dest.new_referent();
+                }
+                new_refs.insert(referent, new_ref);
+            }
+        }
+
+        let remap = |referent: Ref| new_refs.get(&referent).copied().unwrap_or(referent);
+
+        // Nothing can fail from here on, so the instance can be taken out of
+        // its old parent's list of children.
+        let parent_ref = self.instances[&referent].parent;
+        if let Some(parent) = self.instances.get_mut(&parent_ref) {
+            parent.children.retain(|&child| child != referent);
+        }
+
+        for old_ref in to_move {
+            let mut instance = self.inner_remove(old_ref);
+            let new_ref = remap(old_ref);
+
+            instance.parent = if old_ref == referent {
+                dest_parent_ref
+            } else {
+                remap(instance.parent)
+            };
+
+            if !new_refs.is_empty() {
+                instance.referent = new_ref;
+
+                for child in &mut instance.children {
+                    *child = remap(*child);
+                }
+
+                let needs_remap = instance.properties.values().any(|value| match value {
+                    Variant::Ref(target) => new_refs.contains_key(target),
+                    _ => false,
+                });
+
+                if needs_remap {
+                    for value in instance.properties.values_mut() {
+                        if let Variant::Ref(target) = value {
+                            *target = remap(*target);
+                        }
+                    }
+                }
+            }
+
+            dest.config.apply_to_instance(&mut instance);
+            dest.inner_insert(new_ref, instance);
+            self.transfer_metadata(old_ref, new_ref, dest);
+        }
+
+        // Finally, notify the new parent instance that their adoption is
+        // complete. Enjoy!
+        let new_ref = remap(referent);
+        if let Some(dest_parent) = dest.instances.get_mut(&dest_parent_ref) {
+            dest_parent.children.push(new_ref);
+        }
+
+        Ok(new_ref)
+    }
+
+    /// Move the instance with the given referent to a new parent within the
+    /// same `WeakDom`. To move to another DOM, use [`WeakDom::transfer`].
+    ///
+    /// This function would be called `move_within`, but `move` is a Rust
+    /// keyword and consistency with `transfer` is valuable.
+    ///
+    /// ## Panics
+    /// Panics if `referent` or `dest_parent_ref` do not refer to instances in
+    /// `self`.
+    ///
+    /// Will also panic if `referent` refers to the root instance in this
+    /// `WeakDom`.
+    pub fn transfer_within(&mut self, referent: Ref, dest_parent_ref: Ref) {
+        if referent == self.root_ref {
+            panic!("cannot transfer the root instance of WeakDom");
+        }
+
+        let mut instance = self
+            .instances
+            .get_mut(&referent)
+            .unwrap_or_else(|| panic!("cannot move an instance that does not exist"));
+
+        // Tell the instance who its new parent is.
+        let parent_ref = instance.parent;
+        instance.parent = dest_parent_ref;
+
+        // Remove the instance's referent from its parent's list of children.
+        let parent = self.instances.get_mut(&parent_ref).unwrap();
+        parent.children.retain(|&child| child != referent);
+
+        // Add the instance's referent to its new parent's list of children.
+        let dest_parent = self
+            .instances
+            .get_mut(&dest_parent_ref)
+            .unwrap_or_else(|| panic!("cannot move into an instance that does not exist"));
+        dest_parent.children.push(referent);
+
+        self.record(JournalEntry::Move {
+            referent,
+            old_parent: parent_ref,
+            new_parent: dest_parent_ref,
+        });
+    }
+
+    /// Copy the instance with the given referent and all of its descendants,
+    /// parenting the copy to the given ref within the same `WeakDom`. Returns
+    /// the referent of the copy.
+    ///
+    /// Every copied instance is given a new referent. `Ref` properties that
+    /// point to instances inside the copied subtree are changed to point to
+    /// the matching copies, while ones pointing outside of it are kept as-is.
+    ///
+    /// ## Panics
+    /// Panics if `referent` or `dest_parent_ref` do not refer to instances in
+    /// `self`.
+    pub fn clone_within(&mut self, referent: Ref, dest_parent_ref: Ref) -> Ref {
+        if !self.instances.contains_key(&dest_parent_ref) {
+            panic!("cannot clone into an instance that does not exist");
+        }
+
+        // Walk the subtree breadth-first, handing out a new referent for each
+        // instance as we go.
+        let mut to_clone = Vec::new();
+        let mut new_refs = HashMap::new();
+        let mut queue = VecDeque::new();
+        queue.push_back(referent);
+
+        while let Some(referent) = queue.pop_front() {
+            let instance = self
+                .instances
+                .get(&referent)
+                .unwrap_or_else(|| panic!("cannot clone an instance that does not exist"));
+
+            queue.extend(instance.children.iter().copied());
+            to_clone.push(referent);
+        }
+
+        for &referent in &to_clone {
+            let new_ref = self.new_referent();
+            new_refs.insert(referent, new_ref);
+        }
+
+        let remap = |referent: Ref| new_refs.get(&referent).copied().unwrap_or(referent);
+
+        for old_ref in to_clone {
+            let instance = &self.instances[&old_ref];
+            let new_ref = new_refs[&old_ref];
+
+            let parent = if old_ref == referent {
+                dest_parent_ref
+            } else {
+                new_refs[&instance.parent]
+            };
+
+            // Instances with no Refs into the subtree can share their
+            // properties with the original until one of them is changed.
+            let needs_remap = instance.properties.values().any(|value| match value {
+                Variant::Ref(target) => new_refs.contains_key(target),
+                _ => false,
+            });
+
+            let properties = if needs_remap {
+                instance
+                    .properties
+                    .iter()
+                    .map(|(key, value)| {
+                        let value = match value {
+                            Variant::Ref(target) => Variant::Ref(remap(*target)),
+                            other => other.clone(),
+                        };
+                        (key.clone(), value)
+                    })
+                    .collect()
+            } else {
+                instance.properties.clone()
+            };
+
+            let copy = Instance {
+                referent: new_ref,
+                children: instance
+                    .children
+                    .iter()
+                    .map(|child| new_refs[child])
+                    .collect(),
+                parent,
+                name: instance.name.clone(),
+                class: instance.class.clone(),
+                properties,
+            };
+
+            self.inner_insert(new_ref, copy);
+
+            if let Some(metadata) = self.metadata.get(&old_ref).cloned() {
+                self.metadata.insert(new_ref, metadata);
+            }
+        }
+
+        let new_root = new_refs[&referent];
+        self.instances
+            .get_mut(&dest_parent_ref)
+            .unwrap()
+            .children
+            .push(new_root);
+
+        self.record(JournalEntry::Insert {
+            referent: new_root,
+            parent: dest_parent_ref,
+            instances: self.journal_subtree(new_root),
+        });
+
+        new_root
+    }
+
+    /// Copy the template instance with the given referent into `parent_ref`
+    /// using [`WeakDom::clone_within`], then apply property overrides to the
+    /// copy. Returns the referent of the copy.
+    ///
+    /// `overrides` is keyed by paths relative to the template, made of child
+    /// names separated by `/`. The empty path refers to the copy itself, and
+    /// each name refers to the first child with that name.
+    ///
+    /// Overrides are applied with [`InstanceEditor`], so they are recorded in
+    /// the journal, and they are converted and checked like
+    /// [`WeakDom::update_properties`] does. If any of them can't be, or a path
+    /// doesn't refer to an instance in the template, an error is returned and
+    /// nothing is copied.
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    ///
+    /// use rbx_dom_weak::{types::Variant, InstanceBuilder, WeakDom};
+    ///
+    /// let template = InstanceBuilder::new("Model")
+    ///     .with_name("Tree")
+    ///     .with_child(InstanceBuilder::new("Part").with_name("Trunk"));
+    /// let template_ref = template.referent();
+    ///
+    /// let mut dom = WeakDom::new(InstanceBuilder::new("DataModel").with_child(template));
+    ///
+    /// let mut trunk = HashMap::new();
+    /// trunk.insert("Transparency".to_owned(), Variant::Float32(0.5));
+    ///
+    /// let mut overrides = HashMap::new();
+    /// overrides.insert("Trunk".to_owned(), trunk);
+    ///
+    /// let root = dom.root_ref();
+    /// let copy = dom.instantiate_template(template_ref, root, &overrides)?;
+    ///
+    /// let copy_trunk = dom.get_by_ref(copy).unwrap().children()[0];
+    /// assert_eq!(
+    ///     dom.get_by_ref(copy_trunk).unwrap().properties.get("Transparency"),
+    ///     Some(&Variant::Float32(0.5)),
+    /// );
+    /// # Ok::<(), rbx_dom_weak::PropertyError>(())
+    /// ```
+    ///
+    /// ## Panics
+    /// Panics if `template_ref` or `parent_ref` do not refer to instances in
+    /// `self`.
+    pub fn instantiate_template(
+        &mut self,
+        template_ref: Ref,
+        parent_ref: Ref,
+        overrides: &HashMap<String, HashMap<String, Variant>>,
+    ) -> Result<Ref, PropertyError> {
+        assert!(
+            self.instances.contains_key(&template_ref),
+            "cannot instantiate a template that does not exist"
+        );
+
+        // The copy has the same names and properties as the template, so the
+        // overrides can be found and checked in the template before anything
+        // is copied.
+        let mut checked = Vec::with_capacity(overrides.len());
+        for (path, properties) in overrides {
+            let target = self
+                .find_template_path(template_ref, path)
+                .ok_or_else(|| PropertyError::NoInstanceAtPath { path: path.clone() })?;
+            let instance = &self.instances[&target];
+
+            let properties = properties
+                .iter()
+                .map(|(key, value)| {
+                    self.config.check_property(
+                        &instance.class,
+                        key,
+                        value,
+                        instance.properties.get(key),
+                    )?;
+
+                    Ok((key.clone(), instance.coerce_for(key, value.clone())?))
+                })
+                .collect::<Result<Vec<_>, PropertyError>>()?;
+
+            checked.push((path, properties));
+        }
+
+        let copy_ref = self.clone_within(template_ref, parent_ref);
+
+        for (path, properties) in checked {
+            // The copy has the same children as the template, which were just
+            // checked.
+            let target = self.find_template_path(copy_ref, path).unwrap();
+            let mut editor = self.edit(target);
+            for (key, value) in properties {
+                editor.set_property(key, value);
+            }
+        }
+
+        Ok(copy_ref)
+    }
+
+    /// Finds the instance at a path given to
+    /// [`WeakDom::instantiate_template`], or `None` if there isn't one.
+    fn find_template_path(&self, root: Ref, path: &str) -> Option<Ref> {
+        let mut target = root;
+
+        for name in path.split('/').filter(|name| !name.is_empty()) {
+            target = self.instances[&target]
+                .children
+                .iter()
+                .copied()
+                .find(|child| self.instances[child].name == name)?;
+        }
+
+        Some(target)
+    }
+
+    /// Makes equal property values share a single copy of them, returning how
+    /// many distinct values are left. Instances whose properties are all the
+    /// same share a single map of them too. See [`PropertyInterner`].
+    ///
+    /// This is most useful after reading a place made of many copies of the
+    /// same parts. Changing a value afterwards copies it again, so this can be
+    /// called again after making changes.
+    pub fn intern_properties(&mut self) -> usize {
+        let mut interner = PropertyInterner::new();
+
+        for instance in self.instances.values_mut() {
+            interner.intern(&mut instance.properties);
+        }
+
+        interner.len()
+    }
+
+    /// Returns a hash of the instance with the given referent and all of its
+    /// descendants, like the nodes of a Merkle tree. Two subtrees with the same
+    /// hash have the same contents in the same order, so tools comparing DOMs
+    /// can skip over whole branches that haven't changed.
+    ///
+    /// Each instance is hashed with [`Instance::stable_hash`], so referents
+    /// don't affect the hash except through `Ref` properties.
+    ///
+    /// Hashes are remembered until something inside the subtree changes, so
+    /// asking again after a change only hashes the instances on the way from
+    /// the changed instance up to `referent`, and the instances that changed.
+    /// Instances borrowed with [`WeakDom::get_by_ref_mut`] or
+    /// [`WeakDom::root_mut`] are assumed to have changed.
+    ///
+    /// ```
+    /// use rbx_dom_weak::{InstanceBuilder, WeakDom};
+    ///
+    /// let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+    /// let root_ref = dom.root_ref();
+    /// let part = dom.insert(root_ref, InstanceBuilder::new("Part"));
+    ///
+    /// let before = dom.subtree_hash(root_ref);
+    /// dom.edit(part).set_property("Anchored", true);
+    /// assert_ne!(dom.subtree_hash(root_ref), before);
+    ///
+    /// dom.edit(part).remove_property("Anchored");
+    /// assert_eq!(dom.subtree_hash(root_ref), before);
+    /// ```
+    ///
+    /// ## Panics
+    /// Panics if `referent` does not refer to an instance in the DOM.
+    pub fn subtree_hash(&self, referent: Ref) -> u64 {
+        let mut hashes = self.subtree_hashes.lock();
+        self.fill_hashes(&mut hashes, referent);
+        hashes[&referent]
+    }
+
+    /// Returns the [`WeakDom::subtree_hash`] of the instance with the given
+    /// referent and of every one of its descendants, hashing any that aren't
+    /// remembered in a single pass.
+    ///
+    /// ## Panics
+    /// Panics if `referent` does not refer to an instance in the DOM.
+    pub fn subtree_hashes(&self, referent: Ref) -> HashMap<Ref, u64> {
+        let mut hashes = self.subtree_hashes.lock();
+        self.fill_hashes(&mut hashes, referent);
+
+        let mut found = HashMap::new();
+        let mut stack = vec![referent];
+
+        while let Some(referent) = stack.pop() {
+            found.insert(referent, hashes[&referent]);
+            stack.extend_from_slice(&self.instances[&referent].children);
+        }
+
+        found
+    }
+
+    /// Hashes every instance in the subtree of `referent` that doesn't have a
+    /// hash in `hashes` yet. Every descendant of an instance with a hash has
+    /// one too, so subtrees with hashes can be skipped.
+    fn fill_hashes(&self, hashes: &mut HashMap<Ref, u64>, referent: Ref) {
+        // Visit every instance after all of its children by pushing each
+        // instance twice: once to queue up its children, and once to hash it.
+        let mut stack = vec![(referent, false)];
+
+        while let Some((referent, children_done)) = stack.pop() {
+            if !children_done && hashes.contains_key(&referent) {
+                continue;
+            }
+
+            let instance = self
+                .instances
+                .get(&referent)
+                .unwrap_or_else(|| panic!("cannot hash an instance that does not exist"));
+
+            if !children_done {
+                stack.push((referent, true));
+                stack.extend(instance.children.iter().map(|&child| (child, false)));
+                continue;
+            }
+
+            let mut hasher = StableHasher::new();
+            instance.hash_stable(&mut hasher);
+
+            hasher.write_usize(instance.children.len());
+            for child in &instance.children {
+                hasher.write_u64(hashes[child]);
+            }
+
+            hashes.insert(referent, hasher.finish());
+        }
+    }
+
+    /// Forgets the subtree hashes of an instance and its ancestors, which
+    /// include it, so they're hashed again next time they're asked for.
+    fn invalidate_hashes(&mut self, mut referent: Ref) {
+        let hashes = self.subtree_hashes.get_mut();
+
+        // If an instance has no hash, none of its ancestors have one either.
+        while hashes.remove(&referent).is_some() {
+            match self.instances.get(&referent) {
+                Some(instance) => referent = instance.parent,
+                None => break,
+            }
+        }
+    }
+
+    /// Tells whether this DOM has the same contents as another one. Referents
+    /// aren't compared, and `Ref` properties are compared by which instance
+    /// they point to, so DOMs read from the same file separately are equal.
+    ///
+    /// Strict equality often fails for DOMs that went through a file and came
+    /// back, since floating point values can be slightly different. The given
+    /// [`EqualityOptions`] can allow for that, and for children being in a
+    /// different order.
+    ///
+    /// Returns [`Cancelled`] if `options` has a cancel token that gets set
+    /// before the comparison finishes, so that a cancelled comparison can't be
+    /// mistaken for unequal trees.
+    ///
+    /// ```
+    /// use rbx_dom_weak::{types::Vector3, EqualityOptions, InstanceBuilder, WeakDom};
+    ///
+    /// let part = |y| InstanceBuilder::new("Part").with_property("Size", Vector3::new(1.0, y, 1.0));
+    /// let original = WeakDom::new(part(0.1));
+    /// let round_tripped = WeakDom::new(part(0.100001));
+    ///
+    /// assert!(!original.semantically_equals(&round_tripped, &EqualityOptions::new())?);
+    /// assert!(original.semantically_equals(
+    ///     &round_tripped,
+    ///     &EqualityOptions::new().tolerance(0.0001),
+    /// )?);
+    /// # Ok::<(), rbx_dom_weak::Cancelled>(())
+    /// ```
+    pub fn semantically_equals(
+        &self,
+        other: &WeakDom,
+        options: &EqualityOptions,
+    ) -> Result<bool, Cancelled> {
+        Comparison::new(self, other, options).subtrees_equal(self.root_ref, other.root_ref)
+    }
+
+    /// Walks through the instance with the given referent and all of its
+    /// descendants in depth-first order, calling `visitor` as it enters and
+    /// leaves each of them. Children are visited in order.
+    ///
+    /// ## Panics
+    /// Panics if `referent` does not refer to an instance in the DOM.
+    pub fn walk<V: TreeVisitor>(&self, referent: Ref, visitor: &mut V) {
+        let never = AtomicBool::new(false);
+        let _ = self.walk_cancellable(referent, visitor, &never);
+    }
+
+    /// Like [`WeakDom::walk`], but stops and returns [`Cancelled`] as soon as
+    /// `cancel` is set to `true`, which other threads can do to abandon a
+    /// walk through a large tree. `cancel` is checked before each instance is
+    /// entered, and instances that were entered aren't exited after that.
+    ///
+    /// ## Panics
+    /// Panics if `referent` does not refer to an instance in the DOM.
+    pub fn walk_cancellable<V: TreeVisitor>(
+        &self,
+        referent: Ref,
+        visitor: &mut V,
+        cancel: &AtomicBool,
+    ) -> Result<(), Cancelled> {
+        // Each instance is pushed twice, like in `subtree_hashes`: once to
+        // enter it and queue up its children, and once to exit it.
+        let mut stack = vec![(referent, false)];
+
+        while let Some((referent, entered)) = stack.pop() {
+            let instance = self
+                .instances
+                .get(&referent)
+                .unwrap_or_else(|| panic!("cannot walk an instance that does not exist"));
+
+            if entered {
+                visitor.exit_instance(self, instance);
+                continue;
+            }
+
+            if cancel.load(Ordering::Relaxed) {
+                return Err(Cancelled);
+            }
+
+            visitor.enter_instance(self, instance);
+            stack.push((referent, true));
+            stack.extend(instance.children.iter().rev().map(|&child| (child, false)));
+        }
+
+        Ok(())
+    }
+
+    /// Returns a parallel iterator over every descendant of the instance with
+    /// the given referent, not including the instance itself, for read-only
+    /// analysis of large trees. Descendants are found up front on the calling
+    /// thread, so only the work done with each instance is spread across
+    /// threads.
+    ///
+    /// The iterator is indexed in depth-first order, parents before children,
+    /// so collecting it gives the same order every time.
+    ///
+    /// ```
+    /// use rayon::prelude::*;
+    /// use rbx_dom_weak::{InstanceBuilder, WeakDom};
+    ///
+    /// let dom = WeakDom::new(InstanceBuilder::new("Folder").with_children(
+    ///     (0..100).map(|i| InstanceBuilder::new("Script").with_name(format!("Script{}", i))),
+    /// ));
+    ///
+    /// let scripts = dom
+    ///     .par_descendants(dom.root_ref())
+    ///     .filter(|instance| instance.class == "Script")
+    ///     .count();
+    ///
+    /// assert_eq!(scripts, 100);
+    /// ```
+    ///
+    /// ## Panics
+    /// Panics if `referent` does not refer to an instance in the DOM.
+    #[cfg(feature = "rayon")]
+    pub fn par_descendants(
+        &self,
+        referent: Ref,
+    ) -> impl rayon::iter::IndexedParallelIterator<Item = &Instance> + '_ {
+        use rayon::prelude::*;
+
+        let root = self
+            .instances
+            .get(&referent)
+            .unwrap_or_else(|| panic!("cannot iterate over an instance that does not exist"));
+
+        let mut descendants = Vec::new();
+        let mut stack: Vec<&Instance> = root
+            .children
+            .iter()
+            .rev()
+            .map(|child| &self.instances[child])
+            .collect();
+
+        while let Some(instance) = stack.pop() {
+            descendants.push(instance);
+            stack.extend(
+                instance
+                    .children
+                    .iter()
+                    .rev()
+                    .map(|child| &self.instances[child]),
+            );
+        }
+
+        descendants.into_par_iter()
+    }
+
+    fn inner_insert(&mut self, referent: Ref, instance: Instance) {
+        self.instances.insert(referent, instance);
+
+        // We need to ensure that the value of the Instance.UniqueId property does
+        // not collide with another instance. If it does, we must regenerate
+        // it. If we *don't* do this, it's possible to use WeakDom::insert to
+        // insert UniqueId properties that collide with other instances in the
+        // dom, violating the invariant that every UniqueId is unique.
+
+        // Unwrap is safe because we just inserted this referent into the instance map
+        let instance = self.instances.get_mut(&referent).unwrap();
+        if let Some(Variant::UniqueId(unique_id)) = instance.properties.get("UniqueId") {
+            if self.unique_ids.contains(unique_id) {
+                // We found a collision! We need to replace the UniqueId property with
+                // a new value. It keeps the time of the colliding one, so that
+                // no clock is needed, which wasm32-unknown-unknown doesn't have.
+                let new_unique_id = UniqueId::at(unique_id.time());
+
+                self.unique_ids.insert(new_unique_id);
+                instance
+                    .properties
+                    .insert("UniqueId".to_string(), Variant::UniqueId(new_unique_id));
+            } else {
+                self.unique_ids.insert(*unique_id);
+            };
+        }
+    }
+
+    fn transfer_metadata(&mut self, referent: Ref, new_ref: Ref, dest: &mut WeakDom) {
+        if let Some(metadata) = self.metadata.remove(&referent) {
+            dest.metadata.insert(new_ref, metadata);
+        }
+
+        if let Some(extensions) = self.extensions.take_all(referent) {
+            dest.extensions.insert_all(new_ref, extensions);
+        }
+    }
+
+    fn inner_remove(&mut self, referent: Ref) -> Instance {
+        let instance = self
+            .instances
+            .remove(&referent)
+            .unwrap_or_else(|| panic!("cannot remove an instance that does not exist"));
+
+        if let Some(Variant::UniqueId(unique_id)) = instance.properties.get("UniqueId") {
+            self.unique_ids.remove(unique_id);
+        }
+
+        if let Some(dirty) = &mut self.dirty {
+            dirty.remove(&referent);
+        }
+
+        self.subtree_hashes.get_mut().remove(&referent);
+
+        instance
+    }
+}
+
+/// The subtree hashes remembered by [`WeakDom::subtree_hash`]. They're filled
+/// in while the DOM is only borrowed, so they're kept behind a lock.
+#[derive(Debug, Default)]
+struct HashCache(Mutex<HashMap<Ref, u64>>);
+
+impl HashCache {
+    fn lock(&self) -> MutexGuard<'_, HashMap<Ref, u64>> {
+        // Hashes are only added once all of an instance's descendants have
+        // them, so the cache is still correct if hashing panicked.
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn get_mut(&mut self) -> &mut HashMap<Ref, u64> {
+        self.0.get_mut().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Clone for HashCache {
+    fn clone(&self) -> Self {
+        Self(Mutex::new(self.lock().clone()))
+    }
+}
+
+/// Luau keywords, which can't be written after a dot.
+const KEYWORDS: &[&str] = &[
+    "and", "break", "do", "else", "elseif", "end", "false", "for", "function", "if", "in", "local",
+    "nil", "not", "or", "repeat", "return", "then", "true", "until", "while",
+];
+
+/// Adds a name to the end of a path, after a dot if it's a valid identifier
+/// and in brackets as a string literal otherwise.
+fn push_name(path: &mut String, name: &str) {
+    let mut chars = name.chars();
+    let is_identifier = chars
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !KEYWORDS.contains(&name);
+
+    if is_identifier {
+        path.push('.');
+        path.push_str(name);
+        return;
+    }
+
+    path.push_str("[\"");
+    for c in name.chars() {
+        match c {
+            '"' => path.push_str("\\\""),
+            '\\' => path.push_str("\\\\"),
+            '\n' => path.push_str("\\n"),
+            '\r' => path.push_str("\\r"),
+            '\t' => path.push_str("\\t"),
+            c if c.is_control() => path.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => path.push(c),
+        }
+    }
+    path.push_str("\"]");
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::{DomViewer, JournalFilter, NameChange, PropertyChange, PropertyMap};
+    use rbx_types::{Color3, UniqueId, Variant};
+
+    #[test]
+    fn transfer() {
+        let target = InstanceBuilder::new("Folder")
+            .with_name("Target")
+            .with_child(InstanceBuilder::new("Part").with_name("Some Child"));
+        let target_ref = target.referent;
+
+        let mut source = WeakDom::new(InstanceBuilder::new("Folder").with_child(target));
+        let mut dest = WeakDom::new(InstanceBuilder::new("DataModel"));
+
+        let mut viewer = DomViewer::new();
+
+        // This snapshot should contain Target and Some Child
+        insta::assert_yaml_snapshot!(viewer.view_children(&source));
+
+        let dest_root = dest.root_ref();
+        source.transfer(target_ref, &mut dest, dest_root);
+
+        // This snapshot should be empty
+        insta::assert_yaml_snapshot!(viewer.view_children(&source));
+
+        // This snapshot should be exactly the same as the first snapshot,
+        // containing Target and Child.
+        insta::assert_yaml_snapshot!(viewer.view_children(&dest));
+    }
+
+    #[test]
+    fn transfer_referent_collision() {
+        let part = InstanceBuilder::new("Part");
+        let part_ref = part.referent();
+        let model = InstanceBuilder::new("Model")
+            .with_property("PrimaryPart", part_ref)
+            .with_child(part);
+        let model_ref = model.referent();
+
+        // Both DOMs have instances with the same referents, like DOMs read from
+        // the same file with the same namespace do.
+        let copy = |builder: &InstanceBuilder| {
+            InstanceBuilder::new("Model")
+                .with_referent(builder.referent())
+                .with_property("PrimaryPart", part_ref)
+                .with_child(InstanceBuilder::new("Part").with_referent(part_ref))
+        };
+        let mut dest = WeakDom::new(InstanceBuilder::new("Folder").with_child(copy(&model)));
+        let mut source = WeakDom::new(InstanceBuilder::new("Folder").with_child(model));
+
+        let dest_root = dest.root_ref();
+        source
+            .metadata_mut(part_ref)
+            .insert("Path".to_owned(), "a".to_owned());
+        dest.enable_journal();
+        let moved = source.transfer(model_ref, &mut dest, dest_root);
+
+        // The instances that were already in `dest` are left alone.
+        assert_ne!(moved, model_ref);
+        assert_eq!(dest.root().children(), [model_ref, moved]);
+        assert_eq!(dest.get_by_ref(model_ref).unwrap().children(), [part_ref]);
+        assert_eq!(dest.metadata(part_ref), None);
+        assert_eq!(dest.descendant_count(dest_root), 4);
+
+        let moved_part = dest.get_by_ref(moved).unwrap().children()[0];
+        assert_ne!(moved_part, part_ref);
+        assert_eq!(dest.get_by_ref(moved_part).unwrap().parent(), moved);
+        assert_eq!(
+            dest.get_by_ref(moved)
+                .unwrap()
+                .properties
+                .get("PrimaryPart"),
+            Some(&Variant::Ref(moved_part))
+        );
+        assert_eq!(dest.metadata(moved_part).unwrap()["Path"], "a");
+        assert_eq!(
+            dest.drain_journal(),
+            vec![JournalEntry::TransferIn {
+                referent: moved,
+                parent: dest_root,
+                instances: dest.journal_subtree(moved),
+            }]
+        );
+    }
+
+    #[test]
+    fn transfer_tree_config() {
+        use rbx_reflection::{ClassDescriptor, ReflectionDatabase};
+
+        let mut database = ReflectionDatabase::new();
+        database
+            .classes
+            .insert("Folder".into(), ClassDescriptor::new("Folder"));
+        let database = Arc::new(database);
+        let strict = TreeConfig::new()
+            .shared_reflection_database(database)
+            .reject_unknown_classes(true);
+        let mut strict_dest = WeakDom::with_config(InstanceBuilder::new("Folder"), strict);
+        let strict_root = strict_dest.root_ref();
+
+        let mut source = WeakDom::new(
+            InstanceBuilder::new("Folder")
+                .with_child(InstanceBuilder::new("Model").with_name(" Tree\n")),
+        );
+        let model = source.root().children()[0];
+
+        // Instances the destination rejects aren't moved at all.
+        assert!(matches!(
+            source.try_transfer(model, &mut strict_dest, strict_root),
+            Err(TreeConfigError::UnknownClass { .. })
+        ));
+        assert_eq!(source.root().children(), [model]);
+        assert!(strict_dest.root().children().is_empty());
+
+        let config = TreeConfig::new()
+            .sanitize_names(true)
+            .deterministic_referents(Ref::none());
+        let mut dest = WeakDom::with_config(InstanceBuilder::new("Folder"), config);
+        let dest_root = dest.root_ref();
+
+        let moved = source.transfer(model, &mut dest, dest_root);
+        assert_ne!(moved, model);
+        assert_eq!(dest.get_by_ref(moved).unwrap().name, "Tree");
+    }
+
+    #[test]
+    fn transfer_within() {
+        let subject = InstanceBuilder::new("Folder")
+            .with_name("Root")
+            .with_child(InstanceBuilder::new("SpawnLocation"));
+        let subject_ref = subject.referent;
+
+        let source_parent = InstanceBuilder::new("Folder")
+            .with_name("Source")
+            .with_child(subject);
+
+        let dest_parent = InstanceBuilder::new("Folder").with_name("Dest");
+        let dest_parent_ref = dest_parent.referent;
+
+        let mut dom = WeakDom::new(
+            InstanceBuilder::new("Folder")
+                .with_child(source_parent)
+                .with_child(dest_parent),
+        );
+
+        let mut viewer = DomViewer::new();
+
+        // This snapshot should have Root and SpawnLocation contained in Source.
+        insta::assert_yaml_snapshot!(viewer.view_children(&dom));
+
+        dom.transfer_within(subject_ref, dest_parent_ref);
+
+        // This snapshot should have Root and SpawnLocation contained in Dest.
+        insta::assert_yaml_snapshot!(viewer.view_children(&dom));
+    }
+
+    #[test]
+    fn clone_within() {
+        let handle = InstanceBuilder::new("Part").with_name("Handle");
+        let handle_ref = handle.referent;
+
+        let outside = InstanceBuilder::new("Part").with_name("Outside");
+        let outside_ref = outside.referent;
+
+        let template = InstanceBuilder::new("Model")
+            .with_name("Template")
+            .with_property("PrimaryPart", handle_ref)
+            .with_property("Target", outside_ref)
+            .with_child(handle);
+        let template_ref = template.referent;
+
+        let mut dom = WeakDom::new(
+            InstanceBuilder::new("Folder")
+                .with_child(template)
+                .with_child(outside),
+        );
+
+        let root_ref = dom.root_ref();
+        let copy_ref = dom.clone_within(template_ref, root_ref);
+        assert_ne!(copy_ref, template_ref);
+
+        let copy = dom.get_by_ref(copy_ref).unwrap();
+        assert_eq!(copy.parent(), root_ref);
+
+        let copy_handle = copy.children()[0];
+        assert_ne!(copy_handle, handle_ref);
+        assert_eq!(
+            copy.properties.get("PrimaryPart"),
+            Some(&Variant::Ref(copy_handle))
+        );
+        assert_eq!(
+            copy.properties.get("Target"),
+            Some(&Variant::Ref(outside_ref))
+        );
+
+        let mut viewer = DomViewer::new();
+        insta::assert_yaml_snapshot!(viewer.view_children(&dom));
+    }
+
+    #[test]
+    fn clone_shares_properties() {
+        let part = InstanceBuilder::new("Part").with_property("Anchored", true);
+        let part_ref = part.referent;
+
+        let mut dom = WeakDom::new(InstanceBuilder::new("Folder").with_child(part));
+        let snapshot = dom.clone();
+
+        fn properties(dom: &WeakDom, referent: Ref) -> &PropertyMap {
+            &dom.get_by_ref(referent).unwrap().properties
+        }
+        assert!(properties(&dom, part_ref).ptr_eq(properties(&snapshot, part_ref)));
+
+        let root_ref = dom.root_ref();
+        let copy_ref = dom.clone_within(part_ref, root_ref);
+        let copy = dom.get_by_ref(copy_ref).unwrap();
+        assert!(copy.properties.ptr_eq(properties(&snapshot, part_ref)));
+
+        dom.get_by_ref_mut(part_ref)
+            .unwrap()
+            .properties
+            .insert("Anchored".to_owned(), false.into());
+
+        assert!(!properties(&dom, part_ref).ptr_eq(properties(&snapshot, part_ref)));
+        assert_eq!(
+            properties(&snapshot, part_ref).get("Anchored"),
+            Some(&Variant::Bool(true))
+        );
+        assert_eq!(
+            dom.get_by_ref(copy_ref).unwrap().properties.get("Anchored"),
+            Some(&Variant::Bool(true))
+        );
+    }
+
+    #[test]
+    fn intern_properties() {
+        let tile = || {
+            InstanceBuilder::new("Part")
+                .with_property("Anchored", true)
+                .with_property("Color", Color3::new(0.2, 0.6, 0.2))
+        };
+        let first = tile();
+        let first_ref = first.referent;
+        let second = tile();
+        let second_ref = second.referent;
+
+        let third = tile().with_property("Anchored", false);
+        let third_ref = third.referent;
+
+        let mut dom = WeakDom::new(
+            InstanceBuilder::new("Model")
+                .with_child(first)
+                .with_child(second)
+                .with_child(third),
+        );
+
+        // true, false, and the one Color3.
+        assert_eq!(dom.intern_properties(), 3);
+
+        let first = &dom.get_by_ref(first_ref).unwrap().properties;
+        let second = &dom.get_by_ref(second_ref).unwrap().properties;
+        let third = &dom.get_by_ref(third_ref).unwrap().properties;
+        assert!(first.ptr_eq(second));
+        assert!(!first.ptr_eq(third));
+        assert!(first.value_ptr_eq(third, "Color"));
+        assert!(!first.value_ptr_eq(third, "Anchored"));
+    }
+
+    #[test]
+    fn semantically_equals() {
+        let tree = |order: &[&str], target: usize| {
+            let children: Vec<_> = order
+                .iter()
+                .map(|name| InstanceBuilder::new("Part").with_name(*name))
+                .collect();
+            let target_ref = children[target].referent;
+
+            WeakDom::new(
+                InstanceBuilder::new("Model")
+                    .with_property("PrimaryPart", target_ref)
+                    .with_children(children),
+            )
+        };
+
+        let strict = EqualityOptions::new();
+        let unordered = EqualityOptions::new().ignore_child_order(true);
+
+        let original = tree(&["A", "B"], 0);
+        assert_eq!(
+            original.semantically_equals(&tree(&["A", "B"], 0), &strict),
+            Ok(true)
+        );
+        assert_eq!(
+            original.semantically_equals(&tree(&["A", "B"], 1), &strict),
+            Ok(false)
+        );
+
+        let reordered = tree(&["B", "A"], 1);
+        assert_eq!(original.semantically_equals(&reordered, &strict), Ok(false));
+        assert_eq!(
+            original.semantically_equals(&reordered, &unordered),
+            Ok(true)
+        );
+        assert_eq!(
+            original.semantically_equals(&tree(&["B", "A"], 0), &unordered),
+            Ok(false)
+        );
+    }
+
+    #[test]
+    fn view_outlives_itself() {
+        let dom =
+            WeakDom::new(InstanceBuilder::new("Folder").with_child(InstanceBuilder::new("Part")));
+
+        fn first_child(view: WeakDomView<'_>) -> &Instance {
+            let child = view.root().children()[0];
+            view.get_by_ref(child).unwrap()
+        }
+
+        let view = dom.view();
+        assert_eq!(first_child(view).class, "Part");
+        assert_eq!(view.descendant_count(view.root_ref()), 1);
+        assert_eq!(
+            view.subtree_hash(view.root_ref()),
+            dom.subtree_hash(dom.root_ref())
+        );
+        assert_eq!(
+            view.semantically_equals(dom.view(), &EqualityOptions::new()),
+            Ok(true)
+        );
+    }
+
+    #[test]
+    fn snapshot() {
+        let mut dom = WeakDom::new(
+            InstanceBuilder::new("Folder")
+                .with_child(InstanceBuilder::new("Part").with_property("Anchored", true)),
+        );
+        dom.enable_journal();
+        let part = dom.root().children()[0];
+        dom.edit(part).set_name("Body");
+
+        let snapshot = dom.snapshot();
+        assert!(snapshot
+            .get_by_ref(part)
+            .unwrap()
+            .properties
+            .ptr_eq(&dom.get_by_ref(part).unwrap().properties));
+        assert!(!snapshot.is_journal_enabled());
+
+        dom.edit(part).set_property("Anchored", false);
+        assert_eq!(
+            snapshot
+                .get_by_ref(part)
+                .unwrap()
+                .properties
+                .get("Anchored"),
+            Some(&Variant::Bool(true))
+        );
+        assert_eq!(snapshot.get_by_ref(part).unwrap().name, "Body");
+        assert_eq!(dom.drain_journal().len(), 2);
+    }
+
+    #[test]
+    fn instantiate_template_unknown_path() {
+        let template = InstanceBuilder::new("Model").with_child(InstanceBuilder::new("Part"));
+        let template_ref = template.referent;
+        let mut dom = WeakDom::new(InstanceBuilder::new("Folder").with_child(template));
+
+        let mut overrides = HashMap::new();
+        overrides.insert("Part/Missing".to_owned(), HashMap::new());
+
+        let root_ref = dom.root_ref();
+        assert_eq!(
+            dom.instantiate_template(template_ref, root_ref, &overrides),
+            Err(PropertyError::NoInstanceAtPath {
+                path: "Part/Missing".to_owned()
+            })
+        );
+        assert_eq!(dom.root().children(), [template_ref]);
+    }
+
+    #[test]
+    fn instantiate_template_edits() {
+        let template = InstanceBuilder::new("Model")
+            .with_child(InstanceBuilder::new("Part").with_property("Transparency", 0.0f32));
+        let template_ref = template.referent;
+        let mut dom = WeakDom::new(InstanceBuilder::new("Folder").with_child(template));
+        let root_ref = dom.root_ref();
+        dom.enable_journal();
+
+        let mut part = HashMap::new();
+        part.insert("Transparency".to_owned(), Variant::Float64(0.5));
+        let mut overrides = HashMap::new();
+        overrides.insert("Part".to_owned(), part);
+
+        let copy_ref = dom
+            .instantiate_template(template_ref, root_ref, &overrides)
+            .unwrap();
+        let copy_part = dom.get_by_ref(dom.get_by_ref(copy_ref).unwrap().children()[0]);
+        assert_eq!(
+            copy_part.unwrap().properties.get("Transparency"),
+            Some(&Variant::Float32(0.5))
+        );
+        assert!(matches!(
+            dom.drain_journal().as_slice(),
+            [JournalEntry::Insert { .. }, JournalEntry::Edit { properties, .. }]
+                if properties.len() == 1 && properties[0].name == "Transparency"
+        ));
+
+        // Overrides are checked before anything is copied.
+        overrides
+            .get_mut("Part")
+            .unwrap()
+            .insert("Transparency".to_owned(), Variant::Bool(true));
+        assert!(dom
+            .instantiate_template(template_ref, root_ref, &overrides)
+            .is_err());
+        assert_eq!(dom.root().children().len(), 2);
+        assert!(dom.drain_journal().is_empty());
+    }
+
+    #[test]
+    fn subtree_hashes() {
+        let make_model = || {
+            InstanceBuilder::new("Model")
+                .with_child(InstanceBuilder::new("Part").with_property("Anchored", true))
+                .with_child(InstanceBuilder::new("Part").with_name("Other"))
+        };
+
+        let first = make_model();
+        let first_ref = first.referent;
+        let second = make_model();
+        let second_ref = second.referent;
+
+        let mut dom = WeakDom::new(
+            InstanceBuilder::new("Folder")
+                .with_child(first)
+                .with_child(second),
+        );
+
+        assert_eq!(dom.subtree_hash(first_ref), dom.subtree_hash(second_ref));
+
+        let before = dom.subtree_hashes(dom.root_ref());
+        assert_eq!(before.len(), 7);
+
+        let changed_part = dom.get_by_ref(second_ref).unwrap().children[1];
+        dom.get_by_ref_mut(changed_part).unwrap().name = "Renamed".to_owned();
+
+        let after = dom.subtree_hashes(dom.root_ref());
+        assert_eq!(before[&first_ref], after[&first_ref]);
+        assert_ne!(before[&second_ref], after[&second_ref]);
+        assert_ne!(before[&dom.root_ref()], after[&dom.root_ref()]);
+    }
+
+    #[test]
+    fn subtree_hash_cache() {
+        let mut dom = WeakDom::new(InstanceBuilder::new("Folder"));
+        let root_ref = dom.root_ref();
+        let model = dom.insert(
+            root_ref,
+            InstanceBuilder::new("Model").with_child(InstanceBuilder::new("Part")),
+        );
+        let part = dom.get_by_ref(model).unwrap().children[0];
+        let other = dom.insert(root_ref, InstanceBuilder::new("Model"));
+
+        let cached = |dom: &WeakDom, referent| dom.subtree_hashes.lock().contains_key(&referent);
+        let before = dom.subtree_hash(root_ref);
+        assert!(cached(&dom, part) && cached(&dom, other));
+
+        // Changing an instance forgets the hashes of it and its ancestors, and
+        // only those.
+        dom.edit(part).set_property("Anchored", true);
+        assert!(!cached(&dom, part) && !cached(&dom, model) && !cached(&dom, root_ref));
+        assert!(cached(&dom, other));
+        assert_ne!(dom.subtree_hash(root_ref), before);
+
+        dom.get_by_ref_mut(part)
+            .unwrap()
+            .properties
+            .remove("Anchored");
+        assert!(!cached(&dom, root_ref));
+        assert_eq!(dom.subtree_hash(root_ref), before);
+
+        let added = dom.insert(other, InstanceBuilder::new("Part"));
+        assert!(!cached(&dom, other) && !cached(&dom, root_ref));
+        let with_child = dom.subtree_hash(root_ref);
+        assert_ne!(with_child, before);
+
+        dom.transfer_within(added, model);
+        assert_ne!(dom.subtree_hash(root_ref), with_child);
+
+        dom.destroy(added);
+        assert!(!cached(&dom, added));
+        assert_eq!(dom.subtree_hash(root_ref), before);
+
+        let mut dest = WeakDom::new(InstanceBuilder::new("Folder"));
+        let dest_root = dest.root_ref();
+        let empty = dest.subtree_hash(dest_root);
+        dom.transfer(model, &mut dest, dest_root);
+        assert_ne!(dest.subtree_hash(dest_root), empty);
+        assert_eq!(dom.subtree_hashes(root_ref).len(), 2);
+    }
+
+    #[test]
+    fn walk() {
+        struct Recorder(Vec<String>);
+
+        impl TreeVisitor for Recorder {
+            fn enter_instance(&mut self, _dom: &WeakDom, instance: &Instance) {
+                self.0.push(format!("enter {}", instance.name));
+            }
+
+            fn exit_instance(&mut self, _dom: &WeakDom, instance: &Instance) {
+                self.0.push(format!("exit {}", instance.name));
+            }
+        }
+
+        let dom = WeakDom::new(
+            InstanceBuilder::new("Folder")
+                .with_name("Root")
+                .with_child(
+                    InstanceBuilder::new("Model")
+                        .with_name("A")
+                        .with_child(InstanceBuilder::new("Part").with_name("A1")),
+                )
+                .with_child(InstanceBuilder::new("Model").with_name("B")),
+        );
+
+        let mut recorder = Recorder(Vec::new());
+        dom.walk(dom.root_ref(), &mut recorder);
+
+        assert_eq!(
+            recorder.0,
+            [
+                "enter Root",
+                "enter A",
+                "enter A1",
+                "exit A1",
+                "exit A",
+                "enter B",
+                "exit B",
+                "exit Root",
+            ]
+        );
+    }
+
+    #[test]
+    fn cancel_token() {
+        struct Canceller<'a>(&'a AtomicBool, Vec<String>);
+
+        impl TreeVisitor for Canceller<'_> {
+            fn enter_instance(&mut self, _dom: &WeakDom, instance: &Instance) {
+                self.1.push(instance.name.clone());
+                if instance.name == "A" {
+                    self.0.store(true, Ordering::Relaxed);
+                }
+            }
+        }
+
+        let dom = WeakDom::new(
+            InstanceBuilder::new("Folder")
+                .with_name("Root")
+                .with_child(InstanceBuilder::new("Model").with_name("A"))
+                .with_child(InstanceBuilder::new("Model").with_name("B")),
+        );
+
+        let token = Arc::new(AtomicBool::new(false));
+        let mut canceller = Canceller(&token, Vec::new());
+        assert_eq!(
+            dom.walk_cancellable(dom.root_ref(), &mut canceller, &token),
+            Err(Cancelled)
+        );
+        assert_eq!(canceller.1, ["Root", "A"]);
+
+        let options = EqualityOptions::new().cancel_token(Arc::clone(&token));
+        assert_eq!(dom.semantically_equals(&dom, &options), Err(Cancelled));
+
+        token.store(false, Ordering::Relaxed);
+        assert_eq!(dom.semantically_equals(&dom, &options), Ok(true));
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn par_descendants() {
+        use rayon::prelude::*;
+
+        let dom = WeakDom::new(
+            InstanceBuilder::new("Folder")
+                .with_name("Root")
+                .with_child(
+                    InstanceBuilder::new("Model")
+                        .with_name("A")
+                        .with_child(InstanceBuilder::new("Part").with_name("A1")),
+                )
+                .with_child(InstanceBuilder::new("Model").with_name("B")),
+        );
+
+        let names: Vec<&str> = dom
+            .par_descendants(dom.root_ref())
+            .map(|instance| instance.name.as_str())
+            .collect();
 
-        let parent_ref = instance.parent;
-        let parent = self.instances.get_mut(&parent_ref).unwrap();
-        parent.children.retain(|&child| child != referent);
+        assert_eq!(names, ["A", "A1", "B"]);
+    }
 
-        let mut to_remove = VecDeque::new();
-        to_remove.push_back(referent);
+    #[test]
+    fn descendant_counts() {
+        let folder = InstanceBuilder::new("Folder")
+            .with_child(InstanceBuilder::new("Part").with_child(InstanceBuilder::new("Decal")))
+            .with_child(InstanceBuilder::new("Part"));
+        let folder_ref = folder.referent;
 
-        while let Some(referent) = to_remove.pop_front() {
-            let instance = self.inner_remove(referent);
-            to_remove.extend(instance.children);
-        }
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel").with_child(folder));
+        let root_ref = dom.root_ref();
+
+        assert_eq!(dom.descendant_count(root_ref), 4);
+
+        dom.cache_descendant_counts();
+        assert_eq!(dom.descendant_count(root_ref), 4);
+        assert_eq!(dom.descendant_count(folder_ref), 3);
+
+        let generation = dom.generation();
+        dom.insert(folder_ref, InstanceBuilder::new("Model"));
+        assert!(dom.generation() > generation);
+
+        // The cached counts are stale now and must not be used.
+        assert_eq!(dom.descendant_count(root_ref), 5);
+        assert_eq!(dom.descendant_count(folder_ref), 4);
     }
 
-    /// Move the instance with the given referent to a new `WeakDom`, parenting
-    /// it to the given ref. To move to within the same DOM, use
-    /// [`WeakDom::transfer_within`].
-    ///
-    /// This function would be called `move`, but that's a Rust keyword!
-    ///
-    /// ## Panics
-    /// Panics if `referent` does not refer to an instance in `self` or if
-    /// `dest_parent_ref` does not refer to an instance in `other_dom`.
-    ///
-    /// Will also panic if `referent` refers to the root instance in this
-    /// `WeakDom`.
-    pub fn transfer(&mut self, referent: Ref, dest: &mut WeakDom, dest_parent_ref: Ref) {
-        if referent == self.root_ref {
-            panic!("cannot transfer the root instance of WeakDom");
-        }
+    #[test]
+    fn metadata() {
+        let part = InstanceBuilder::new("Part");
+        let part_ref = part.referent;
 
-        let mut instance = self.inner_remove(referent);
+        let mut dom = WeakDom::new(InstanceBuilder::new("Folder").with_child(part));
+        let mut other_dom = WeakDom::new(InstanceBuilder::new("Folder"));
 
-        // Remove the instance being moved from its parent's list of children.
-        // If we care about panic tolerance in the future, doing this first is
-        // important to ensure this link is the one severed first.
-        let parent = self.instances.get_mut(&instance.parent).unwrap();
-        parent.children.retain(|&child| child != referent);
+        assert_eq!(dom.metadata(part_ref), None);
 
-        // We'll start tracking all of the instances that we're moving in a
-        // queue. We're about to move the moving instance, so we need to do this
-        // now.
-        let mut to_move = VecDeque::new();
-        to_move.extend(instance.children.iter().copied());
+        dom.metadata_mut(part_ref)
+            .insert("source".to_owned(), "src/Part.model.json".to_owned());
 
-        // Instance was released.
-        // Bye-bye, instance!
-        instance.parent = dest_parent_ref;
-        dest.inner_insert(referent, instance);
+        let copy_ref = dom.clone_within(part_ref, dom.root_ref());
+        assert_eq!(dom.metadata(copy_ref), dom.metadata(part_ref));
 
-        // Transfer all of the descendants of the moving instance breadth-first.
-        while let Some(referent) = to_move.pop_front() {
-            let instance = self.inner_remove(referent);
+        let other_root_ref = other_dom.root_ref();
+        dom.transfer(part_ref, &mut other_dom, other_root_ref);
+        assert_eq!(dom.metadata(part_ref), None);
+        assert_eq!(
+            other_dom.metadata(part_ref).unwrap()["source"],
+            "src/Part.model.json"
+        );
 
-            to_move.extend(instance.children.iter().copied());
-            dest.inner_insert(referent, instance);
-        }
+        dom.destroy(copy_ref);
+        assert_eq!(dom.metadata(copy_ref), None);
+    }
 
-        // Finally, notify the new parent instance that their adoption is
-        // complete. Enjoy!
-        let dest_parent = dest.instances.get_mut(&dest_parent_ref).unwrap_or_else(|| {
-            panic!("cannot move an instance into an instance that does not exist")
-        });
-        dest_parent.children.push(referent);
+    #[test]
+    fn extensions() {
+        #[derive(Debug, Clone, PartialEq)]
+        struct Cache(u32);
+
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let root_ref = dom.root_ref();
+        let part = dom.insert(
+            root_ref,
+            InstanceBuilder::new("Folder").with_child(InstanceBuilder::new("Part")),
+        );
+        let child = dom.get_by_ref(part).unwrap().children()[0];
+
+        assert_eq!(dom.insert_extension(part, Cache(1)), None);
+        assert_eq!(dom.insert_extension(part, Cache(2)), Some(Cache(1)));
+        dom.insert_extension(part, "label");
+        dom.insert_extension(child, Cache(3));
+        dom.extension_mut::<Cache>(child).unwrap().0 += 1;
+
+        assert_eq!(dom.extension::<Cache>(part), Some(&Cache(2)));
+        assert_eq!(dom.extension::<&str>(part), Some(&"label"));
+        assert_eq!(dom.extension::<u32>(part), None);
+
+        let copy = dom.clone_within(part, root_ref);
+        assert_eq!(dom.extension::<Cache>(copy), None);
+
+        // Clones share extensions until one of them changes a value.
+        let mut clone = dom.clone();
+        assert_eq!(clone.extension::<Cache>(part), Some(&Cache(2)));
+        clone.extension_mut::<Cache>(part).unwrap().0 = 5;
+        assert_eq!(clone.extension::<Cache>(part), Some(&Cache(5)));
+        assert_eq!(dom.extension::<Cache>(part), Some(&Cache(2)));
+        assert_eq!(clone.remove_extension::<Cache>(child), Some(Cache(4)));
+        assert_eq!(dom.extension::<Cache>(child), Some(&Cache(4)));
+
+        assert_eq!(dom.remove_extension::<&str>(part), Some("label"));
+        assert_eq!(dom.remove_extension::<&str>(part), None);
+
+        let mut other = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let other_root = other.root_ref();
+        dom.transfer(part, &mut other, other_root);
+        assert_eq!(other.extension::<Cache>(child), Some(&Cache(4)));
+
+        other.destroy(part);
+        assert_eq!(other.extension::<Cache>(part), None);
+        assert_eq!(other.extension::<Cache>(child), None);
     }
 
-    /// Move the instance with the given referent to a new parent within the
-    /// same `WeakDom`. To move to another DOM, use [`WeakDom::transfer`].
-    ///
-    /// This function would be called `move_within`, but `move` is a Rust
-    /// keyword and consistency with `transfer` is valuable.
-    ///
-    /// ## Panics
-    /// Panics if `referent` or `dest_parent_ref` do not refer to instances in
-    /// `self`.
-    ///
-    /// Will also panic if `referent` refers to the root instance in this
-    /// `WeakDom`.
-    pub fn transfer_within(&mut self, referent: Ref, dest_parent_ref: Ref) {
-        if referent == self.root_ref {
-            panic!("cannot transfer the root instance of WeakDom");
-        }
+    #[test]
+    fn send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
 
-        let mut instance = self
-            .instances
-            .get_mut(&referent)
-            .unwrap_or_else(|| panic!("cannot move an instance that does not exist"));
+        assert_send_sync::<WeakDom>();
+        assert_send_sync::<Instance>();
+        assert_send_sync::<InstanceBuilder>();
+    }
 
-        // Tell the instance who its new parent is.
-        let parent_ref = instance.parent;
-        instance.parent = dest_parent_ref;
+    #[test]
+    fn journal() {
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let root_ref = dom.root_ref();
 
-        // Remove the instance's referent from its parent's list of children.
-        let parent = self.instances.get_mut(&parent_ref).unwrap();
-        parent.children.retain(|&child| child != referent);
+        let untracked = dom.insert(root_ref, InstanceBuilder::new("Folder"));
+        dom.enable_journal();
 
-        // Add the instance's referent to its new parent's list of children.
-        let dest_parent = self
-            .instances
-            .get_mut(&dest_parent_ref)
-            .unwrap_or_else(|| panic!("cannot move into an instance that does not exist"));
-        dest_parent.children.push(referent);
-    }
+        let folder = dom.insert(
+            root_ref,
+            InstanceBuilder::new("Folder").with_child(InstanceBuilder::new("Part")),
+        );
+        let inserted = dom.journal_subtree(folder);
+        dom.transfer_within(folder, untracked);
+
+        let part = dom.get_by_ref(folder).unwrap().children()[0];
+        let destroyed: Vec<Instance> = [untracked, folder, part]
+            .iter()
+            .map(|referent| dom.get_by_ref(*referent).unwrap().clone())
+            .collect();
+        dom.destroy(untracked);
+
+        assert_eq!(
+            dom.drain_journal(),
+            vec![
+                JournalEntry::Insert {
+                    referent: folder,
+                    parent: root_ref,
+                    instances: inserted,
+                },
+                JournalEntry::Move {
+                    referent: folder,
+                    old_parent: root_ref,
+                    new_parent: untracked,
+                },
+                JournalEntry::Destroy {
+                    referent: untracked,
+                    parent: root_ref,
+                    instances: destroyed,
+                },
+            ]
+        );
+        assert!(dom.drain_journal().is_empty());
 
-    fn inner_insert(&mut self, referent: Ref, instance: Instance) {
-        self.instances.insert(referent, instance);
+        dom.disable_journal();
+        dom.insert(root_ref, InstanceBuilder::new("Folder"));
+        assert!(dom.drain_journal().is_empty());
+    }
 
-        // We need to ensure that the value of the Instance.UniqueId property does
-        // not collide with another instance. If it does, we must regenerate
-        // it. If we *don't* do this, it's possible to use WeakDom::insert to
-        // insert UniqueId properties that collide with other instances in the
-        // dom, violating the invariant that every UniqueId is unique.
+    #[test]
+    fn journal_transfer() {
+        let mut source = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let mut dest = WeakDom::new(InstanceBuilder::new("DataModel"));
+        source.enable_journal();
+        dest.enable_journal();
 
-        // Unwrap is safe because we just inserted this referent into the instance map
-        let instance = self.instances.get_mut(&referent).unwrap();
-        if let Some(Variant::UniqueId(unique_id)) = instance.properties.get("UniqueId") {
-            if self.unique_ids.contains(unique_id) {
-                // We found a collision! We need to replace the UniqueId property with
-                // a new value.
+        let source_root = source.root_ref();
+        let dest_root = dest.root_ref();
+        let folder = source.insert(source_root, InstanceBuilder::new("Folder"));
+        source.drain_journal();
+
+        let moved = source.journal_subtree(folder);
+        source.transfer(folder, &mut dest, dest_root);
+
+        assert_eq!(
+            source.drain_journal(),
+            vec![JournalEntry::TransferOut {
+                referent: folder,
+                parent: source_root,
+                instances: moved,
+            }]
+        );
+        assert_eq!(
+            dest.drain_journal(),
+            vec![JournalEntry::TransferIn {
+                referent: folder,
+                parent: dest_root,
+                instances: dest.journal_subtree(folder),
+            }]
+        );
+    }
 
-                // Unwrap is probably ok. Likely not worth making this method fallible
-                // just because the system clock might be out whack, so panicking is fine
-                let new_unique_id = UniqueId::now().unwrap();
+    /// Applies a journal entry to `dom`, or undoes it if `forward` is false.
+    fn replay(dom: &mut WeakDom, entry: &JournalEntry, forward: bool) {
+        let subtree = |instances: &[Instance]| {
+            let mut by_ref: HashMap<Ref, Instance> = instances
+                .iter()
+                .map(|instance| (instance.referent(), instance.clone()))
+                .collect();
+            crate::detached::builder(&mut by_ref, instances[0].referent())
+        };
 
-                self.unique_ids.insert(new_unique_id);
-                instance
-                    .properties
-                    .insert("UniqueId".to_string(), Variant::UniqueId(new_unique_id));
-            } else {
-                self.unique_ids.insert(*unique_id);
-            };
+        match entry {
+            JournalEntry::Insert {
+                referent,
+                parent,
+                instances,
+            }
+            | JournalEntry::TransferIn {
+                referent,
+                parent,
+                instances,
+            } => {
+                if forward {
+                    dom.insert(*parent, subtree(instances));
+                } else {
+                    dom.destroy(*referent);
+                }
+            }
+            JournalEntry::Destroy {
+                referent,
+                parent,
+                instances,
+            }
+            | JournalEntry::TransferOut {
+                referent,
+                parent,
+                instances,
+            } => {
+                if forward {
+                    dom.destroy(*referent);
+                } else {
+                    dom.insert(*parent, subtree(instances));
+                }
+            }
+            JournalEntry::Move {
+                referent,
+                old_parent,
+                new_parent,
+            } => {
+                let parent = if forward { new_parent } else { old_parent };
+                dom.transfer_within(*referent, *parent);
+            }
+            JournalEntry::Edit {
+                referent,
+                name,
+                properties,
+            } => {
+                let instance = dom.get_by_ref_mut(*referent).unwrap();
+                if let Some(name) = name {
+                    let name = if forward { &name.new } else { &name.old };
+                    instance.name = name.clone();
+                }
+                for change in properties {
+                    let value = if forward { &change.new } else { &change.old };
+                    match value {
+                        Some(value) => instance
+                            .properties
+                            .insert(change.name.clone(), value.clone()),
+                        None => instance.properties.remove(&change.name),
+                    };
+                }
+            }
         }
     }
 
-    fn inner_remove(&mut self, referent: Ref) -> Instance {
-        let instance = self
-            .instances
-            .remove(&referent)
-            .unwrap_or_else(|| panic!("cannot remove an instance that does not exist"));
+    #[test]
+    fn journal_replay() {
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let root_ref = dom.root_ref();
+        let storage = dom.insert(
+            root_ref,
+            InstanceBuilder::new("Folder")
+                .with_child(InstanceBuilder::new("Part").with_property("Anchored", true)),
+        );
+        let mut other = WeakDom::new(
+            InstanceBuilder::new("DataModel")
+                .with_child(InstanceBuilder::new("Model").with_child(InstanceBuilder::new("Part"))),
+        );
+        let other_model = other.root().children()[0];
 
-        if let Some(Variant::UniqueId(unique_id)) = instance.properties.get("UniqueId") {
-            self.unique_ids.remove(unique_id);
+        let original = dom.clone();
+        dom.enable_journal();
+
+        let folder = dom.insert(
+            root_ref,
+            InstanceBuilder::new("Folder").with_child(InstanceBuilder::new("Script")),
+        );
+        dom.edit(folder)
+            .set_name("Scripts")
+            .set_property("Archivable", false);
+        let part = dom.get_by_ref(storage).unwrap().children()[0];
+        dom.edit(part).remove_property("Anchored");
+        dom.transfer_within(part, folder);
+        other.transfer(other_model, &mut dom, storage);
+        let script = dom.get_by_ref(folder).unwrap().children()[0];
+        let other_root = other.root_ref();
+        dom.transfer(script, &mut other, other_root);
+        dom.destroy(storage);
+
+        let entries = dom.drain_journal();
+        let unordered = EqualityOptions::new().ignore_child_order(true);
+
+        // Replaying the entries against a copy from when the journal was
+        // enabled gives the same tree, with the same referents.
+        let mut replayed = original.clone();
+        for entry in &entries {
+            replay(&mut replayed, entry, true);
         }
+        assert_eq!(replayed.semantically_equals(&dom, &unordered), Ok(true));
+        assert_eq!(replayed.root().children(), dom.root().children());
 
-        instance
+        // Undoing them in reverse order gives back the tree from before.
+        let mut undone = dom.clone();
+        for entry in entries.iter().rev() {
+            replay(&mut undone, entry, false);
+        }
+        assert_eq!(undone.semantically_equals(&original, &unordered), Ok(true));
+        let part = undone.get_by_ref(storage).unwrap().children()[0];
+        assert_eq!(
+            undone.get_by_ref(part).unwrap().properties.get("Anchored"),
+            Some(&Variant::Bool(true))
+        );
     }
-}
 
-#[cfg(test)]
-mod test {
-    use super::*;
+    #[test]
+    fn journal_filter() {
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let root_ref = dom.root_ref();
+        let workspace = dom.insert(root_ref, InstanceBuilder::new("Workspace"));
+        let storage = dom.insert(root_ref, InstanceBuilder::new("ServerStorage"));
+        let part = dom.insert(workspace, InstanceBuilder::new("Part"));
+        let stored = dom.insert(storage, InstanceBuilder::new("Part"));
+
+        dom.enable_journal();
+        dom.edit(part).set_property("CFrame", true);
+        dom.edit(part).set_property("Transparency", 0.5f32);
+        dom.edit(stored).set_property("CFrame", true);
+        dom.edit(workspace).set_name("World");
+        dom.transfer_within(stored, workspace);
+        dom.destroy(part);
+        let journal = dom.drain_journal();
+
+        let matching = |filter: JournalFilter| -> Vec<usize> {
+            journal
+                .iter()
+                .enumerate()
+                .filter(|(_, entry)| filter.matches(&dom, entry))
+                .map(|(index, _)| index)
+                .collect()
+        };
+
+        assert_eq!(matching(JournalFilter::new()), [0, 1, 2, 3, 4, 5]);
+        assert_eq!(
+            matching(JournalFilter::new().property("CFrame")),
+            [0, 2, 4, 5]
+        );
+        assert_eq!(matching(JournalFilter::new().property("Name")), [3, 4, 5]);
+        // The part is gone, so entries about it can't be ruled out by class.
+        assert_eq!(
+            matching(JournalFilter::new().class("Workspace")),
+            [0, 1, 3, 5]
+        );
 
-    use crate::DomViewer;
-    use rbx_types::{UniqueId, Variant};
+        // The stored part was edited while it was in storage, but it's in the
+        // workspace now.
+        assert_eq!(
+            matching(JournalFilter::new().subtree(storage).property("CFrame")),
+            [4]
+        );
+    }
 
     #[test]
-    fn transfer() {
-        let target = InstanceBuilder::new("Folder")
-            .with_name("Target")
-            .with_child(InstanceBuilder::new("Part").with_name("Some Child"));
-        let target_ref = target.referent;
+    fn dirty_tracking() {
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let root_ref = dom.root_ref();
+        let workspace = dom.insert(root_ref, InstanceBuilder::new("Workspace"));
+        let storage = dom.insert(root_ref, InstanceBuilder::new("ServerStorage"));
+        let model = dom.insert(
+            workspace,
+            InstanceBuilder::new("Model").with_child(InstanceBuilder::new("Part")),
+        );
+        let part = dom.get_by_ref(model).unwrap().children()[0];
 
-        let mut source = WeakDom::new(InstanceBuilder::new("Folder").with_child(target));
-        let mut dest = WeakDom::new(InstanceBuilder::new("DataModel"));
+        dom.edit(part).set_name("Untracked");
+        assert!(!dom.is_dirty(part));
 
-        let mut viewer = DomViewer::new();
+        dom.enable_dirty_tracking();
+        assert!(dom.dirty_descendants(root_ref).is_empty());
 
-        // This snapshot should contain Target and Some Child
-        insta::assert_yaml_snapshot!(viewer.view_children(&source));
+        let folder = dom.insert(
+            storage,
+            InstanceBuilder::new("Folder").with_child(InstanceBuilder::new("Script")),
+        );
+        let script = dom.get_by_ref(folder).unwrap().children()[0];
+        assert_eq!(
+            dom.dirty_descendants(root_ref),
+            [root_ref, storage, folder, script]
+        );
 
-        let dest_root = dest.root_ref();
-        source.transfer(target_ref, &mut dest, dest_root);
+        dom.clear_dirty(folder);
+        assert_eq!(dom.dirty_descendants(root_ref), [root_ref, storage]);
+        dom.clear_dirty(root_ref);
 
-        // This snapshot should be empty
-        insta::assert_yaml_snapshot!(viewer.view_children(&source));
+        dom.transfer_within(model, storage);
+        assert_eq!(
+            dom.dirty_descendants(root_ref),
+            [root_ref, workspace, storage, model, part]
+        );
+        dom.clear_dirty(root_ref);
 
-        // This snapshot should be exactly the same as the first snapshot,
-        // containing Target and Child.
-        insta::assert_yaml_snapshot!(viewer.view_children(&dest));
+        dom.destroy(part);
+        assert_eq!(dom.dirty_descendants(root_ref), [root_ref, storage, model]);
+
+        dom.disable_dirty_tracking();
+        assert!(!dom.is_dirty(root_ref));
     }
 
     #[test]
-    fn transfer_within() {
-        let subject = InstanceBuilder::new("Folder")
-            .with_name("Root")
-            .with_child(InstanceBuilder::new("SpawnLocation"));
-        let subject_ref = subject.referent;
+    fn edit_journal() {
+        let mut dom =
+            WeakDom::new(InstanceBuilder::new("Part").with_property("Transparency", 0.5f32));
+        let root_ref = dom.root_ref();
+        dom.enable_journal();
+
+        dom.edit(root_ref);
+        dom.edit(root_ref).remove_property("Missing");
+        assert!(dom.drain_journal().is_empty());
+
+        let generation = dom.generation();
+        {
+            let mut editor = dom.edit(root_ref);
+            editor.set_property("Anchored", true);
+            editor.set_typed("Transparency", 1.0f64).unwrap();
+            assert_eq!(editor.class, "Part");
+        }
+        assert_eq!(dom.generation(), generation);
+        assert_eq!(
+            dom.root().properties.get("Transparency"),
+            Some(&Variant::Float32(1.0))
+        );
 
-        let source_parent = InstanceBuilder::new("Folder")
-            .with_name("Source")
-            .with_child(subject);
+        assert_eq!(
+            dom.drain_journal(),
+            vec![JournalEntry::Edit {
+                referent: root_ref,
+                name: None,
+                properties: vec![
+                    PropertyChange {
+                        name: "Anchored".to_owned(),
+                        old: None,
+                        new: Some(Variant::Bool(true)),
+                    },
+                    PropertyChange {
+                        name: "Transparency".to_owned(),
+                        old: Some(Variant::Float32(0.5)),
+                        new: Some(Variant::Float32(1.0)),
+                    },
+                ],
+            }]
+        );
 
-        let dest_parent = InstanceBuilder::new("Folder").with_name("Dest");
-        let dest_parent_ref = dest_parent.referent;
+        // Only the first and last values are kept when something is changed
+        // more than once.
+        {
+            let mut editor = dom.edit(root_ref);
+            editor.set_name("First");
+            editor.set_name("Second");
+            editor.set_property("Anchored", false);
+            editor.remove_property("Anchored");
+        }
+        assert_eq!(
+            dom.drain_journal(),
+            vec![JournalEntry::Edit {
+                referent: root_ref,
+                name: Some(NameChange {
+                    old: "Part".to_owned(),
+                    new: "Second".to_owned(),
+                }),
+                properties: vec![PropertyChange {
+                    name: "Anchored".to_owned(),
+                    old: Some(Variant::Bool(true)),
+                    new: None,
+                }],
+            }]
+        );
+    }
+
+    #[test]
+    fn update_properties() {
+        let mut dom = WeakDom::new(InstanceBuilder::new("Part").with_property("Anchored", false));
+        let root_ref = dom.root_ref();
+        dom.enable_journal();
+
+        let result = dom.update_properties(
+            root_ref,
+            [
+                ("Transparency", Variant::Float32(0.5)),
+                ("Anchored", Variant::String("yes".into())),
+            ],
+        );
+        assert!(result.is_err());
+        assert_eq!(dom.root().properties.len(), 1);
+        assert!(dom.drain_journal().is_empty());
+
+        dom.update_properties(
+            root_ref,
+            [
+                ("Transparency", Variant::Float32(0.5)),
+                ("Anchored", Variant::Bool(true)),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            dom.root().properties.get("Anchored"),
+            Some(&Variant::Bool(true))
+        );
+        assert_eq!(
+            dom.drain_journal(),
+            vec![JournalEntry::Edit {
+                referent: root_ref,
+                name: None,
+                properties: vec![
+                    PropertyChange {
+                        name: "Anchored".to_owned(),
+                        old: Some(Variant::Bool(false)),
+                        new: Some(Variant::Bool(true)),
+                    },
+                    PropertyChange {
+                        name: "Transparency".to_owned(),
+                        old: None,
+                        new: Some(Variant::Float32(0.5)),
+                    },
+                ],
+            }]
+        );
+    }
 
+    #[test]
+    fn retain() {
+        let part = |name: &str| InstanceBuilder::new("Part").with_name(name);
         let mut dom = WeakDom::new(
             InstanceBuilder::new("Folder")
-                .with_child(source_parent)
-                .with_child(dest_parent),
+                .with_child(part("A").with_child(part("Remove")).with_child(part("B")))
+                .with_child(part("Remove").with_child(part("C")))
+                .with_child(part("D")),
         );
+        let root_ref = dom.root_ref();
 
-        let mut viewer = DomViewer::new();
+        let mut visited = Vec::new();
+        dom.retain_descendants(root_ref, |instance| {
+            visited.push(instance.name.clone());
+            instance.name != "Remove"
+        });
+        assert_eq!(visited, ["A", "Remove", "D", "Remove", "B"]);
+
+        let names = |dom: &WeakDom, referent| -> Vec<String> {
+            dom.get_by_ref(referent)
+                .unwrap()
+                .children()
+                .iter()
+                .map(|child| dom.get_by_ref(*child).unwrap().name.clone())
+                .collect()
+        };
+        assert_eq!(names(&dom, root_ref), ["A", "D"]);
+        let a = dom.root().children()[0];
+        assert_eq!(names(&dom, a), ["B"]);
+        assert_eq!(dom.descendant_count(root_ref), 3);
+
+        dom.retain_children(root_ref, |instance| instance.name == "D");
+        assert_eq!(names(&dom, root_ref), ["D"]);
+        assert!(dom.get_by_ref(a).is_none());
+    }
 
-        // This snapshot should have Root and SpawnLocation contained in Source.
-        insta::assert_yaml_snapshot!(viewer.view_children(&dom));
+    #[test]
+    fn detach() {
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let root_ref = dom.root_ref();
+        let part = InstanceBuilder::new("Part");
+        let part_ref = part.referent();
+        let model = dom.insert(
+            root_ref,
+            InstanceBuilder::new("Model")
+                .with_property("PrimaryPart", part_ref)
+                .with_child(part),
+        );
+        dom.metadata_mut(part_ref)
+            .insert("Path".to_owned(), "part.json".to_owned());
+        let before = dom.subtree_hash(model);
+
+        dom.enable_journal();
+        let detached_instances = dom.journal_subtree(model);
+        let detached = dom.detach(model);
+        assert_eq!(dom.root().children(), []);
+        assert!(dom.get_by_ref(part_ref).is_none());
+        assert_eq!(detached.root().parent(), Ref::none());
+        assert_eq!(detached.get_by_ref(part_ref).unwrap().parent(), model);
+
+        let mut other = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let other_root = other.root_ref();
+        assert_eq!(detached.clone().reattach_to(&mut other, other_root), model);
+        assert_eq!(other.subtree_hash(model), before);
+        assert_eq!(other.metadata(part_ref).unwrap()["Path"], "part.json");
+
+        let builder = detached.into_builder();
+        assert_eq!(builder.referent(), model);
+        dom.insert(root_ref, builder);
+        assert_eq!(dom.subtree_hash(model), before);
+
+        assert_eq!(
+            dom.drain_journal(),
+            vec![
+                JournalEntry::TransferOut {
+                    referent: model,
+                    parent: root_ref,
+                    instances: detached_instances,
+                },
+                JournalEntry::Insert {
+                    referent: model,
+                    parent: root_ref,
+                    instances: dom.journal_subtree(model),
+                },
+            ]
+        );
+    }
 
-        dom.transfer_within(subject_ref, dest_parent_ref);
+    #[test]
+    fn reattach_referent_collision() {
+        use rbx_reflection::{ClassDescriptor, ReflectionDatabase};
+
+        let part = InstanceBuilder::new("Part");
+        let part_ref = part.referent();
+        let model = InstanceBuilder::new("Model")
+            .with_property("PrimaryPart", part_ref)
+            .with_child(part);
+
+        let config = TreeConfig::new().deterministic_referents(Ref::none());
+        let mut dom = WeakDom::with_config(InstanceBuilder::new("DataModel"), config);
+        let root_ref = dom.root_ref();
+        let model_ref = dom.insert(root_ref, model);
+
+        // The detached subtree doesn't keep the DOM's config, so attaching it
+        // to a DOM without one keeps its referents.
+        let detached = dom.detach(model_ref);
+        assert!(detached.view().get_by_ref(model_ref).is_some());
+
+        let mut other = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let other_root = other.root_ref();
+        let first = detached.clone().reattach_to(&mut other, other_root);
+        assert_eq!(first, model_ref);
+
+        // Attaching the same subtree again can't replace the first copy.
+        other.enable_journal();
+        let second = detached.clone().reattach_to(&mut other, other_root);
+        assert_ne!(second, first);
+        assert_eq!(other.root().children(), [first, second]);
+        assert_eq!(other.descendant_count(other_root), 4);
+
+        let second_part = other.get_by_ref(second).unwrap().children()[0];
+        assert_eq!(other.get_by_ref(second_part).unwrap().parent(), second);
+        assert_eq!(
+            other
+                .get_by_ref(second)
+                .unwrap()
+                .properties
+                .get("PrimaryPart"),
+            Some(&Variant::Ref(second_part))
+        );
+        assert_eq!(
+            other.drain_journal(),
+            vec![JournalEntry::TransferIn {
+                referent: second,
+                parent: other_root,
+                instances: other.journal_subtree(second),
+            }]
+        );
 
-        // This snapshot should have Root and SpawnLocation contained in Dest.
-        insta::assert_yaml_snapshot!(viewer.view_children(&dom));
+        // Subtrees the destination rejects are given back.
+        let mut database = ReflectionDatabase::new();
+        database
+            .classes
+            .insert("Folder".into(), ClassDescriptor::new("Folder"));
+        let strict = TreeConfig::new()
+            .shared_reflection_database(Arc::new(database))
+            .reject_unknown_classes(true);
+        let mut strict_dom = WeakDom::with_config(InstanceBuilder::new("Folder"), strict);
+        let strict_root = strict_dom.root_ref();
+
+        let err = detached
+            .try_reattach_to(&mut strict_dom, strict_root)
+            .unwrap_err();
+        assert!(matches!(err.error, TreeConfigError::UnknownClass { .. }));
+        assert_eq!(err.subtree.root_ref(), model_ref);
+        assert!(strict_dom.root().children().is_empty());
+    }
+
+    #[test]
+    fn tree_config() {
+        use rbx_reflection::{ClassDescriptor, DataType, PropertyDescriptor, ReflectionDatabase};
+        use rbx_types::VariantType;
+
+        let mut part = ClassDescriptor::new("Part");
+        part.properties.insert(
+            "Anchored".into(),
+            PropertyDescriptor::new("Anchored", DataType::Value(VariantType::Bool)),
+        );
+        part.default_properties
+            .insert("Anchored".into(), Variant::Bool(false));
+        let mut database = ReflectionDatabase::new();
+        database.classes.insert("Part".into(), part);
+        let database = Arc::new(database);
+
+        let config = TreeConfig::new()
+            .shared_reflection_database(database)
+            .strict_property_types(true)
+            .reject_unknown_classes(true)
+            .fill_defaults(true)
+            .deterministic_referents(Ref::none());
+
+        let mut dom = WeakDom::with_config(InstanceBuilder::new("Part"), config.clone());
+        let root_ref = dom.root_ref();
+        assert_eq!(
+            dom.root().properties.get("Anchored"),
+            Some(&Variant::Bool(false))
+        );
+
+        assert!(matches!(
+            dom.try_insert(root_ref, InstanceBuilder::new("Folder")),
+            Err(TreeConfigError::UnknownClass { .. })
+        ));
+        assert!(matches!(
+            dom.try_insert(
+                root_ref,
+                InstanceBuilder::new("Part").with_property("Anchored", 1)
+            ),
+            Err(TreeConfigError::Property(PropertyError::WrongType { .. }))
+        ));
+        assert!(dom.root().children().is_empty());
+
+        let target = InstanceBuilder::new("Part");
+        let target_ref = target.referent();
+        let child = dom.insert(
+            root_ref,
+            InstanceBuilder::new("Part")
+                .with_property("Target", target_ref)
+                .with_child(target),
+        );
+        let new_target = dom.get_by_ref(child).unwrap().children()[0];
+        assert_ne!(new_target, target_ref);
+        assert_eq!(
+            dom.get_by_ref(child).unwrap().properties.get("Target"),
+            Some(&Variant::Ref(new_target))
+        );
+
+        assert!(dom.edit(child).set_typed("Anchored", 1).is_err());
+        assert!(dom.edit(child).set_typed("Anchored", true).is_ok());
+
+        let other = WeakDom::with_config(InstanceBuilder::new("Part"), config);
+        assert_eq!(other.root_ref(), root_ref);
     }
 
     #[test]
@@ -458,4 +3270,103 @@ mod test {
             panic!("UniqueId property must exist and contain a Variant::UniqueId")
         };
     }
+
+    #[test]
+    fn full_name() {
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let workspace = dom.insert(dom.root_ref(), InstanceBuilder::new("Workspace"));
+        let odd = dom.insert(
+            workspace,
+            InstanceBuilder::new("Folder").with_name("a.b \"c\"\\\n"),
+        );
+        let keyword = dom.insert(odd, InstanceBuilder::new("Folder").with_name("end"));
+        let part = dom.insert(keyword, InstanceBuilder::new("Part").with_name("_Part2"));
+        let digit = dom.insert(workspace, InstanceBuilder::new("Part").with_name("1st"));
+
+        assert_eq!(dom.full_name(dom.root_ref()), "game");
+        assert_eq!(dom.full_name(workspace), "game.Workspace");
+        assert_eq!(
+            dom.full_name(part),
+            r#"game.Workspace["a.b \"c\"\\\n"]["end"]._Part2"#
+        );
+        assert_eq!(dom.full_name(digit), r#"game.Workspace["1st"]"#);
+
+        let model = WeakDom::new(
+            InstanceBuilder::new("Model")
+                .with_name("Car")
+                .with_child(InstanceBuilder::new("Part").with_name("Wheel")),
+        );
+        let wheel = model.root().children()[0];
+        assert_eq!(model.full_name(wheel), "Car.Wheel");
+    }
+
+    #[test]
+    fn sanitize_names() {
+        let long_name = "a".repeat(150);
+        let config = TreeConfig::new().sanitize_names(true);
+        let mut dom = WeakDom::with_config(
+            InstanceBuilder::new("DataModel").with_name("\u{202E}Game"),
+            config,
+        );
+        assert_eq!(dom.root().name, "Game");
+
+        let folder = dom.insert(
+            dom.root_ref(),
+            InstanceBuilder::new("Folder")
+                .with_name(" Tabs\tand\u{A0}spaces ")
+                .with_child(InstanceBuilder::new("Part").with_name(long_name.as_str())),
+        );
+        assert_eq!(dom.get_by_ref(folder).unwrap().name, "Tabsand spaces");
+
+        let part = dom.get_by_ref(folder).unwrap().children()[0];
+        assert_eq!(
+            dom.get_by_ref(part).unwrap().name.chars().count(),
+            crate::MAX_NAME_LENGTH
+        );
+
+        dom.edit(part).set_name("Wheel\n");
+        assert_eq!(dom.get_by_ref(part).unwrap().name, "Wheel");
+        assert!(crate::is_valid_name("Wheel"));
+        assert!(!crate::is_valid_name("Wheel\n"));
+
+        let mut unchecked = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let folder = unchecked.insert(
+            unchecked.root_ref(),
+            InstanceBuilder::new("Folder").with_name(" Odd\n"),
+        );
+        assert_eq!(unchecked.get_by_ref(folder).unwrap().name, " Odd\n");
+    }
+
+    #[test]
+    fn duplicate_referents() {
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let root_ref = dom.root_ref();
+        let folder = dom.insert(root_ref, InstanceBuilder::new("Folder"));
+
+        assert!(matches!(
+            dom.try_insert(root_ref, InstanceBuilder::new("Part").with_referent(folder)),
+            Err(TreeConfigError::DuplicateReferent { referent }) if referent == folder
+        ));
+        assert!(matches!(
+            dom.try_insert(
+                root_ref,
+                InstanceBuilder::new("Part").with_referent(Ref::none())
+            ),
+            Err(TreeConfigError::NoneReferent)
+        ));
+
+        let shared = Ref::new();
+        assert!(matches!(
+            dom.try_insert(
+                root_ref,
+                InstanceBuilder::new("Model")
+                    .with_referent(shared)
+                    .with_child(InstanceBuilder::new("Part").with_referent(shared))
+            ),
+            Err(TreeConfigError::DuplicateReferent { .. })
+        ));
+
+        assert_eq!(dom.root().children(), [folder]);
+        assert_eq!(dom.get_by_ref(folder).unwrap().class, "Folder");
+    }
 }
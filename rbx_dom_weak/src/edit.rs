@@ -0,0 +1,207 @@
+use std::{collections::BTreeMap, ops::Deref};
+
+use rbx_types::{Ref, Variant};
+
+use crate::{
+    journal::{NameChange, PropertyChange},
+    Instance, JournalEntry, PropertyError, WeakDom,
+};
+
+/// Changes the name and properties of an instance while keeping track of what
+/// it changed, made with [`WeakDom::edit`].
+///
+/// When the editor is dropped, a single [`JournalEntry::Edit`] listing
+/// everything it changed, with the values from before and after the edit, is
+/// added to the DOM's journal, if journaling is enabled and anything was
+/// changed. The instance can be read through the
+/// editor, but it can only be changed through the editor's methods, so no
+/// change can be missed.
+///
+/// ```
+/// use rbx_dom_weak::{types::Variant, InstanceBuilder, JournalEntry, WeakDom};
+///
+/// let mut dom = WeakDom::new(InstanceBuilder::new("Part"));
+/// let root_ref = dom.root_ref();
+/// dom.enable_journal();
+///
+/// dom.edit(root_ref)
+///     .set_name("Floor")
+///     .set_property("Anchored", true);
+///
+/// assert_eq!(dom.root().name, "Floor");
+/// match dom.drain_journal().as_slice() {
+///     [JournalEntry::Edit { name: Some(name), properties, .. }] => {
+///         assert_eq!(name.new, "Floor");
+///         assert_eq!(properties[0].name, "Anchored");
+///         assert_eq!(properties[0].old, None);
+///         assert_eq!(properties[0].new, Some(Variant::Bool(true)));
+///     }
+///     entries => panic!("unexpected entries {:?}", entries),
+/// }
+/// ```
+pub struct InstanceEditor<'a> {
+    dom: &'a mut WeakDom,
+    referent: Ref,
+
+    /// The name the instance had before it was first renamed.
+    old_name: Option<String>,
+
+    /// The values that changed properties had before they were first
+    /// changed. Values are only kept when journaling is enabled, since
+    /// nothing else needs them.
+    old_properties: BTreeMap<String, Option<Variant>>,
+}
+
+impl<'a> InstanceEditor<'a> {
+    pub(crate) fn new(dom: &'a mut WeakDom, referent: Ref) -> Self {
+        assert!(
+            dom.get_by_ref(referent).is_some(),
+            "cannot edit an instance that does not exist"
+        );
+
+        Self {
+            dom,
+            referent,
+            old_name: None,
+            old_properties: BTreeMap::new(),
+        }
+    }
+
+    /// Sets the name of the instance, cleaned up with
+    /// [`sanitize_name`][crate::sanitize_name] if the DOM's
+    /// [`TreeConfig`][crate::TreeConfig] says to.
+    pub fn set_name<S: Into<String>>(&mut self, name: S) -> &mut Self {
+        let name = self.dom.config().fix_name(name.into());
+        let old_name = std::mem::replace(&mut self.instance_mut().name, name);
+        self.old_name.get_or_insert(old_name);
+        self
+    }
+
+    /// Sets a property of the instance, replacing any value it already had.
+    ///
+    /// ## Panics
+    /// Panics if the DOM's [`TreeConfig`][crate::TreeConfig] has strict
+    /// property types and the value has the wrong type.
+    /// [`InstanceEditor::set_typed`] returns an error instead.
+    pub fn set_property<K, V>(&mut self, key: K, value: V) -> &mut Self
+    where
+        K: Into<String>,
+        V: Into<Variant>,
+    {
+        let key = key.into();
+        let value = value.into();
+        if let Err(err) = self.check_property(&key, &value) {
+            panic!("cannot set property: {}", err);
+        }
+
+        self.remember(&key);
+        self.instance_mut().properties.insert(key, value);
+        self
+    }
+
+    /// Sets a property of the instance like [`Instance::set_typed`] does,
+    /// converting the value to the type the property already has. If the
+    /// DOM's [`TreeConfig`][crate::TreeConfig] has strict property types,
+    /// values with the wrong type are rejected instead of converted.
+    pub fn set_typed<K, V>(&mut self, key: K, value: V) -> Result<&mut Self, PropertyError>
+    where
+        K: Into<String>,
+        V: Into<Variant>,
+    {
+        let key = key.into();
+        let value = value.into();
+        self.check_property(&key, &value)?;
+
+        let old_value = self.get_property_for_journal(&key);
+        self.instance_mut().set_typed(key.clone(), value)?;
+        self.old_properties.entry(key).or_insert(old_value);
+        Ok(self)
+    }
+
+    /// Removes a property from the instance, returning its value if it had
+    /// one.
+    pub fn remove_property(&mut self, key: &str) -> Option<Variant> {
+        if !self.properties.contains_key(key) {
+            return None;
+        }
+
+        self.remember(key);
+        self.instance_mut().properties.remove(key)
+    }
+
+    /// Remembers the value a property has before it's changed for the first
+    /// time.
+    fn remember(&mut self, key: &str) {
+        if !self.old_properties.contains_key(key) {
+            let old_value = self.get_property_for_journal(key);
+            self.old_properties.insert(key.to_owned(), old_value);
+        }
+    }
+
+    /// Returns the current value of a property if the journal will need it.
+    fn get_property_for_journal(&self, key: &str) -> Option<Variant> {
+        if self.old_properties.contains_key(key) || !self.dom.is_journal_enabled() {
+            return None;
+        }
+
+        self.dom
+            .get_by_ref(self.referent)
+            .unwrap()
+            .properties
+            .get(key)
+            .cloned()
+    }
+
+    fn check_property(&self, key: &str, value: &Variant) -> Result<(), PropertyError> {
+        let instance = self.dom.get_by_ref(self.referent).unwrap();
+        self.dom
+            .config()
+            .check_property(&instance.class, key, value, instance.properties.get(key))
+    }
+
+    fn instance_mut(&mut self) -> &mut Instance {
+        self.dom.get_by_ref_mut(self.referent).unwrap()
+    }
+}
+
+impl Deref for InstanceEditor<'_> {
+    type Target = Instance;
+
+    fn deref(&self) -> &Instance {
+        self.dom.get_by_ref(self.referent).unwrap()
+    }
+}
+
+impl Drop for InstanceEditor<'_> {
+    fn drop(&mut self) {
+        if self.old_name.is_none() && self.old_properties.is_empty() {
+            return;
+        }
+
+        let instance = self.dom.get_by_ref(self.referent).unwrap();
+        let journaling = self.dom.is_journal_enabled();
+
+        let name = self.old_name.take().map(|old| NameChange {
+            old,
+            new: instance.name.clone(),
+        });
+        let properties = std::mem::take(&mut self.old_properties)
+            .into_iter()
+            .map(|(name, old)| PropertyChange {
+                new: if journaling {
+                    instance.properties.get(&name).cloned()
+                } else {
+                    None
+                },
+                name,
+                old,
+            })
+            .collect();
+
+        self.dom.record_edit(JournalEntry::Edit {
+            referent: self.referent,
+            name,
+            properties,
+        });
+    }
+}
@@ -0,0 +1,334 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use rbx_types::{
+    Attributes, CFrame, Color3, PhysicalProperties, Ref, UDim, Variant, Vector2, Vector3,
+};
+
+use crate::{Cancelled, Instance, WeakDom};
+
+/// Options for [`WeakDom::semantically_equals`].
+///
+/// By default, comparisons are exact and children must be in the same order.
+#[derive(Debug, Clone, Default)]
+pub struct EqualityOptions {
+    tolerance: f32,
+    ignore_child_order: bool,
+    cancel: Option<Arc<AtomicBool>>,
+}
+
+impl EqualityOptions {
+    /// Constructs options that compare exactly.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets how far apart floating point values can be while still being
+    /// considered equal. This applies to the components of types like
+    /// `Vector3`, `CFrame`, and `Color3` as well as to plain numbers, so that
+    /// values that lost precision while being written out and read back still
+    /// compare equal.
+    #[inline]
+    pub fn tolerance(self, tolerance: f32) -> Self {
+        Self { tolerance, ..self }
+    }
+
+    /// Sets whether children can be in a different order. DOMs and files keep
+    /// the order of children, and Roblox uses it for things like which of two
+    /// children with the same name `FindFirstChild` returns, so it's compared
+    /// by default. Tools that sort or shuffle children can turn this on.
+    #[inline]
+    pub fn ignore_child_order(self, ignore_child_order: bool) -> Self {
+        Self {
+            ignore_child_order,
+            ..self
+        }
+    }
+
+    /// Lets another thread stop the comparison by setting `token` to `true`.
+    /// The token is checked before each pair of instances is compared.
+    ///
+    /// Cancelled comparisons return [`Cancelled`] instead of telling whether
+    /// the trees are equal.
+    #[inline]
+    pub fn cancel_token(self, token: Arc<AtomicBool>) -> Self {
+        Self {
+            cancel: Some(token),
+            ..self
+        }
+    }
+}
+
+/// Compares two subtrees, pairing up their instances as it goes so that `Ref`
+/// properties can be compared by which instance they point to.
+pub(crate) struct Comparison<'a> {
+    left: &'a WeakDom,
+    right: &'a WeakDom,
+    options: &'a EqualityOptions,
+
+    /// Instances that have been matched up, in the order they were matched.
+    pairs: Vec<(Ref, Ref)>,
+
+    /// `Ref` properties that have to point to matching instances once every
+    /// instance has been paired up.
+    pending_refs: Vec<(Ref, Ref)>,
+
+    /// Whether the comparison stopped because the cancel token was set.
+    cancelled: bool,
+}
+
+impl<'a> Comparison<'a> {
+    pub(crate) fn new(left: &'a WeakDom, right: &'a WeakDom, options: &'a EqualityOptions) -> Self {
+        Self {
+            left,
+            right,
+            options,
+            pairs: Vec::new(),
+            pending_refs: Vec::new(),
+            cancelled: false,
+        }
+    }
+
+    pub(crate) fn subtrees_equal(mut self, left: Ref, right: Ref) -> Result<bool, Cancelled> {
+        if !self.instances_equal(left, right) {
+            return if self.cancelled {
+                Err(Cancelled)
+            } else {
+                Ok(false)
+            };
+        }
+
+        let matched: HashMap<Ref, Ref> = self.pairs.iter().copied().collect();
+
+        Ok(self
+            .pending_refs
+            .iter()
+            .all(
+                |&(left_target, right_target)| match matched.get(&left_target) {
+                    Some(&matched_target) => matched_target == right_target,
+
+                    // Refs that point outside of the compared subtrees can only
+                    // be compared by value.
+                    None => {
+                        !self.pairs.iter().any(|&(_, right)| right == right_target)
+                            && left_target == right_target
+                    }
+                },
+            ))
+    }
+
+    fn instances_equal(&mut self, left: Ref, right: Ref) -> bool {
+        let (pairs_len, refs_len) = (self.pairs.len(), self.pending_refs.len());
+
+        let equal = self.instances_equal_inner(left, right);
+        if equal {
+            self.pairs.push((left, right));
+        } else {
+            self.pairs.truncate(pairs_len);
+            self.pending_refs.truncate(refs_len);
+        }
+
+        equal
+    }
+
+    fn instances_equal_inner(&mut self, left: Ref, right: Ref) -> bool {
+        // Once cancelled, every comparison fails so that the walk unwinds as
+        // quickly as possible.
+        if self.cancelled
+            || self
+                .options
+                .cancel
+                .as_ref()
+                .is_some_and(|cancel| cancel.load(Ordering::Relaxed))
+        {
+            self.cancelled = true;
+            return false;
+        }
+
+        let (left, right) = match (self.left.get_by_ref(left), self.right.get_by_ref(right)) {
+            (Some(left), Some(right)) => (left, right),
+            _ => return false,
+        };
+
+        if left.class != right.class
+            || left.name != right.name
+            || left.children.len() != right.children.len()
+            || !self.properties_equal(left, right)
+        {
+            return false;
+        }
+
+        if self.options.ignore_child_order {
+            self.unordered_children_equal(left.children(), right.children())
+        } else {
+            left.children()
+                .iter()
+                .zip(right.children())
+                .all(|(&left, &right)| self.instances_equal(left, right))
+        }
+    }
+
+    /// Pairs each child on the left with the first unpaired child on the
+    /// right that it's equal to.
+    fn unordered_children_equal(&mut self, left: &[Ref], right: &[Ref]) -> bool {
+        let mut unmatched = right.to_vec();
+
+        left.iter().all(|&left| {
+            let found = unmatched
+                .iter()
+                .position(|&right| self.instances_equal(left, right));
+
+            match found {
+                Some(index) => {
+                    unmatched.swap_remove(index);
+                    true
+                }
+                None => false,
+            }
+        })
+    }
+
+    fn properties_equal(&mut self, left: &Instance, right: &Instance) -> bool {
+        if left.properties.len() != right.properties.len() {
+            return false;
+        }
+
+        left.properties.iter().all(|(name, left_value)| {
+            let right_value = match right.properties.get(name) {
+                Some(value) => value,
+                None => return false,
+            };
+
+            // Interned values can be told apart without comparing them. Refs
+            // still have to be paired up below, even when they're shared.
+            if !matches!(left_value, Variant::Ref(_))
+                && left.properties.value_ptr_eq(&right.properties, name)
+            {
+                return true;
+            }
+
+            match (left_value, right_value) {
+                (Variant::Ref(left_target), Variant::Ref(right_target)) => {
+                    if left_target.is_some() != right_target.is_some() {
+                        return false;
+                    }
+
+                    if left_target.is_some() {
+                        self.pending_refs.push((*left_target, *right_target));
+                    }
+
+                    true
+                }
+                _ => variants_equal(left_value, right_value, self.options.tolerance),
+            }
+        })
+    }
+}
+
+/// Compares two values, allowing their floating point components to differ by
+/// up to `tolerance`.
+fn variants_equal(left: &Variant, right: &Variant, tolerance: f32) -> bool {
+    let float = |left: f32, right: f32| (left - right).abs() <= tolerance || left == right;
+    let vector2 =
+        |left: &Vector2, right: &Vector2| float(left.x, right.x) && float(left.y, right.y);
+    let vector3 = |left: &Vector3, right: &Vector3| {
+        float(left.x, right.x) && float(left.y, right.y) && float(left.z, right.z)
+    };
+    let cframe = |left: &CFrame, right: &CFrame| {
+        vector3(&left.position, &right.position)
+            && vector3(&left.orientation.x, &right.orientation.x)
+            && vector3(&left.orientation.y, &right.orientation.y)
+            && vector3(&left.orientation.z, &right.orientation.z)
+    };
+    let color3 = |left: &Color3, right: &Color3| {
+        float(left.r, right.r) && float(left.g, right.g) && float(left.b, right.b)
+    };
+    let udim =
+        |left: &UDim, right: &UDim| float(left.scale, right.scale) && left.offset == right.offset;
+
+    match (left, right) {
+        (Variant::Float32(left), Variant::Float32(right)) => float(*left, *right),
+        (Variant::Float64(left), Variant::Float64(right)) => {
+            (left - right).abs() <= tolerance as f64 || left == right
+        }
+        (Variant::Vector2(left), Variant::Vector2(right)) => vector2(left, right),
+        (Variant::Vector3(left), Variant::Vector3(right)) => vector3(left, right),
+        (Variant::CFrame(left), Variant::CFrame(right)) => cframe(left, right),
+        (Variant::OptionalCFrame(left), Variant::OptionalCFrame(right)) => match (left, right) {
+            (Some(left), Some(right)) => cframe(left, right),
+            (None, None) => true,
+            _ => false,
+        },
+        (Variant::Color3(left), Variant::Color3(right)) => color3(left, right),
+        (Variant::UDim(left), Variant::UDim(right)) => udim(left, right),
+        (Variant::UDim2(left), Variant::UDim2(right)) => {
+            udim(&left.x, &right.x) && udim(&left.y, &right.y)
+        }
+        (Variant::Rect(left), Variant::Rect(right)) => {
+            vector2(&left.min, &right.min) && vector2(&left.max, &right.max)
+        }
+        (Variant::Ray(left), Variant::Ray(right)) => {
+            vector3(&left.origin, &right.origin) && vector3(&left.direction, &right.direction)
+        }
+        (Variant::Region3(left), Variant::Region3(right)) => {
+            vector3(&left.min, &right.min) && vector3(&left.max, &right.max)
+        }
+        (Variant::NumberRange(left), Variant::NumberRange(right)) => {
+            float(left.min, right.min) && float(left.max, right.max)
+        }
+        (Variant::NumberSequence(left), Variant::NumberSequence(right)) => {
+            left.keypoints.len() == right.keypoints.len()
+                && left
+                    .keypoints
+                    .iter()
+                    .zip(&right.keypoints)
+                    .all(|(left, right)| {
+                        float(left.time, right.time)
+                            && float(left.value, right.value)
+                            && float(left.envelope, right.envelope)
+                    })
+        }
+        (Variant::ColorSequence(left), Variant::ColorSequence(right)) => {
+            left.keypoints.len() == right.keypoints.len()
+                && left
+                    .keypoints
+                    .iter()
+                    .zip(&right.keypoints)
+                    .all(|(left, right)| {
+                        float(left.time, right.time) && color3(&left.color, &right.color)
+                    })
+        }
+        (Variant::PhysicalProperties(left), Variant::PhysicalProperties(right)) => {
+            match (left, right) {
+                (PhysicalProperties::Custom(left), PhysicalProperties::Custom(right)) => {
+                    float(left.density, right.density)
+                        && float(left.friction, right.friction)
+                        && float(left.elasticity, right.elasticity)
+                        && float(left.friction_weight, right.friction_weight)
+                        && float(left.elasticity_weight, right.elasticity_weight)
+                }
+                (left, right) => left == right,
+            }
+        }
+        (Variant::Attributes(left), Variant::Attributes(right)) => {
+            attributes_equal(left, right, tolerance)
+        }
+        (left, right) => left == right,
+    }
+}
+
+fn attributes_equal(left: &Attributes, right: &Attributes, tolerance: f32) -> bool {
+    left.iter().count() == right.iter().count()
+        && left
+            .iter()
+            .all(|(name, left)| match right.get(name.as_str()) {
+                Some(right) => variants_equal(left, right, tolerance),
+                None => false,
+            })
+}
@@ -0,0 +1,122 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    fmt,
+    sync::Arc,
+};
+
+use rbx_types::Ref;
+
+/// A value attached to an instance. Clones of a DOM share their values, and
+/// a value is copied the first time it's changed through a DOM that shares
+/// it, like property values are.
+pub(crate) trait Extension: Any + Send + Sync {
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    fn into_any(self: Arc<Self>) -> Box<dyn Any>;
+    fn clone_arc(&self) -> Arc<dyn Extension>;
+}
+
+impl<T: Any + Clone + Send + Sync> Extension for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn into_any(self: Arc<Self>) -> Box<dyn Any> {
+        Box::new(Arc::try_unwrap(self).unwrap_or_else(|value| (*value).clone()))
+    }
+
+    fn clone_arc(&self) -> Arc<dyn Extension> {
+        Arc::new(self.clone())
+    }
+}
+
+/// Values of any type attached to instances of a [`WeakDom`][crate::WeakDom],
+/// at most one of each type per instance.
+#[derive(Default, Clone)]
+pub(crate) struct Extensions {
+    map: HashMap<Ref, HashMap<TypeId, Arc<dyn Extension>>>,
+}
+
+impl Extensions {
+    pub(crate) fn get<T: Any>(&self, referent: Ref) -> Option<&T> {
+        let value = self.map.get(&referent)?.get(&TypeId::of::<T>())?;
+        Extension::as_any(&**value).downcast_ref()
+    }
+
+    pub(crate) fn get_mut<T: Any>(&mut self, referent: Ref) -> Option<&mut T> {
+        let value = self.map.get_mut(&referent)?.get_mut(&TypeId::of::<T>())?;
+        Extension::as_any_mut(make_mut(value)).downcast_mut()
+    }
+
+    pub(crate) fn insert<T: Any + Clone + Send + Sync>(
+        &mut self,
+        referent: Ref,
+        value: T,
+    ) -> Option<T> {
+        let old = self
+            .map
+            .entry(referent)
+            .or_default()
+            .insert(TypeId::of::<T>(), Arc::new(value))?;
+
+        into_value(old)
+    }
+
+    pub(crate) fn remove<T: Any>(&mut self, referent: Ref) -> Option<T> {
+        let extensions = self.map.get_mut(&referent)?;
+        let old = extensions.remove(&TypeId::of::<T>())?;
+
+        if extensions.is_empty() {
+            self.map.remove(&referent);
+        }
+
+        into_value(old)
+    }
+
+    /// Removes every value attached to an instance, so they can be dropped or
+    /// attached to the same instance in another DOM.
+    pub(crate) fn take_all(
+        &mut self,
+        referent: Ref,
+    ) -> Option<HashMap<TypeId, Arc<dyn Extension>>> {
+        self.map.remove(&referent)
+    }
+
+    pub(crate) fn insert_all(
+        &mut self,
+        referent: Ref,
+        extensions: HashMap<TypeId, Arc<dyn Extension>>,
+    ) {
+        self.map.insert(referent, extensions);
+    }
+}
+
+/// Returns a value that no other DOM shares, copying it if it's shared.
+fn make_mut(value: &mut Arc<dyn Extension>) -> &mut dyn Extension {
+    if Arc::get_mut(value).is_none() {
+        *value = Extension::clone_arc(&**value);
+    }
+
+    Arc::get_mut(value).unwrap()
+}
+
+/// Takes a value out of its `Arc`, copying it if another DOM shares it.
+fn into_value<T: Any>(value: Arc<dyn Extension>) -> Option<T> {
+    Extension::into_any(value)
+        .downcast()
+        .ok()
+        .map(|value| *value)
+}
+
+impl fmt::Debug for Extensions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Extensions")
+            .field("instances", &self.map.len())
+            .finish()
+    }
+}
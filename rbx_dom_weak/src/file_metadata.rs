@@ -0,0 +1,27 @@
+use std::collections::BTreeMap;
+
+/// Metadata about a whole model or place file, stored in the binary format's
+/// `META` chunk and the XML format's `<Meta>` elements. Roblox uses it for
+/// settings like `ExplicitAutoJoints`.
+///
+/// rbx_binary and rbx_xml attach it to the root of the DOMs they decode with
+/// [`WeakDom::insert_extension`][crate::WeakDom::insert_extension], and write
+/// it back out when it's attached to the DOM they're encoding.
+///
+/// ```
+/// use rbx_dom_weak::{FileMetadata, InstanceBuilder, WeakDom};
+///
+/// let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+///
+/// let mut metadata = FileMetadata::default();
+/// metadata
+///     .entries
+///     .insert("ExplicitAutoJoints".to_owned(), "true".to_owned());
+/// dom.insert_extension(dom.root_ref(), metadata);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileMetadata {
+    /// The metadata's keys and values, ordered by key so that they're always
+    /// written in the same order.
+    pub entries: BTreeMap<String, String>,
+}
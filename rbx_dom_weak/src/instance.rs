@@ -1,6 +1,44 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, hash::Hasher};
 
-use rbx_types::{Ref, Variant};
+use rbx_reflection::ReflectionDatabase;
+use rbx_types::{Ref, StableHasher, Variant};
+
+use crate::{
+    property_map::PropertyMap,
+    typed::{class_chain, coerce_variant, PropertyError, PropertyType},
+};
+
+/// Creates a `HashMap<String, Variant>` from a list of property names and
+/// values. Values can be anything that implements `Into<Variant>`.
+///
+/// ```
+/// use rbx_dom_weak::{properties, InstanceBuilder};
+/// use rbx_dom_weak::types::{Variant, Vector3};
+///
+/// let properties = properties! {
+///     "Anchored" => true,
+///     "Size" => Vector3::new(4.0, 1.0, 2.0),
+/// };
+///
+/// assert_eq!(properties.get("Anchored"), Some(&Variant::Bool(true)));
+///
+/// let part = InstanceBuilder::new("Part").with_properties(properties);
+/// ```
+#[macro_export]
+macro_rules! properties {
+    ( $( $key:expr => $value:expr ),* $(,)? ) => {{
+        #[allow(unused_mut)]
+        let mut map: ::std::collections::HashMap<::std::string::String, $crate::types::Variant> =
+            ::std::collections::HashMap::new();
+        $(
+            map.insert(
+                ::std::convert::Into::into($key),
+                ::std::convert::Into::into($value),
+            );
+        )*
+        map
+    }};
+}
 
 /**
 Represents an instance that can be turned into a new
@@ -72,6 +110,17 @@ impl InstanceBuilder {
         self.referent
     }
 
+    /// Change the referent of the `InstanceBuilder`, which is otherwise chosen
+    /// at random.
+    ///
+    /// The referent must not be none, and must not already be used by another
+    /// instance in the DOM that the builder is inserted into.
+    /// [`WeakDom::try_insert`][crate::WeakDom::try_insert] returns an error if
+    /// it is.
+    pub fn with_referent(self, referent: Ref) -> Self {
+        Self { referent, ..self }
+    }
+
     /// Change the name of the `InstanceBuilder`.
     pub fn with_name<S: Into<String>>(self, name: S) -> Self {
         Self {
@@ -175,7 +224,7 @@ impl InstanceBuilder {
 ///
 /// Operations that could affect other instances contained in the
 /// [`WeakDom`][crate::WeakDom] cannot be performed on an `Instance` correctly.
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Instance {
     pub(crate) referent: Ref,
     pub(crate) children: Vec<Ref>,
@@ -188,7 +237,7 @@ pub struct Instance {
     pub class: String,
 
     /// Any properties stored on the object that are not `Name` or `ClassName`.
-    pub properties: HashMap<String, Variant>,
+    pub properties: PropertyMap,
 }
 
 impl Instance {
@@ -213,4 +262,156 @@ impl Instance {
     pub fn parent(&self) -> Ref {
         self.parent
     }
+
+    /// Returns a copy of the property with the given name as a concrete type,
+    /// converting it if the stored type differs but can be converted without
+    /// surprises, like `Int32` to `Float32` or `Color3uint8` to `Color3`.
+    ///
+    /// ```
+    /// use rbx_dom_weak::{InstanceBuilder, WeakDom};
+    /// use rbx_dom_weak::types::Vector3;
+    ///
+    /// let dom = WeakDom::new(
+    ///     InstanceBuilder::new("Part").with_property("Size", Vector3::new(4.0, 1.0, 2.0)),
+    /// );
+    ///
+    /// let size = dom.root().get_typed::<Vector3>("Size")?;
+    /// assert_eq!(size, Vector3::new(4.0, 1.0, 2.0));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn get_typed<T: PropertyType>(&self, name: &str) -> Result<T, PropertyError> {
+        let value = self
+            .properties
+            .get(name)
+            .ok_or_else(|| PropertyError::Missing {
+                class: self.class.clone(),
+                property: name.to_owned(),
+            })?;
+
+        self.convert_typed(name, value)
+    }
+
+    /// Reads a property like [`Instance::get_typed`], but uses the default
+    /// value from the reflection database if the instance doesn't have the
+    /// property. Defaults of superclasses are used too.
+    pub fn get_typed_or_default<T: PropertyType>(
+        &self,
+        name: &str,
+        database: &ReflectionDatabase,
+    ) -> Result<T, PropertyError> {
+        if self.properties.contains_key(name) {
+            return self.get_typed(name);
+        }
+
+        let default = class_chain(database, &self.class)
+            .into_iter()
+            .find_map(|class| class.default_properties.get(name))
+            .ok_or_else(|| PropertyError::Missing {
+                class: self.class.clone(),
+                property: name.to_owned(),
+            })?;
+
+        self.convert_typed(name, default)
+    }
+
+    fn convert_typed<T: PropertyType>(
+        &self,
+        name: &str,
+        value: &Variant,
+    ) -> Result<T, PropertyError> {
+        coerce_variant(value.clone(), T::VARIANT_TYPE)
+            .and_then(T::from_variant)
+            .ok_or_else(|| PropertyError::WrongType {
+                class: self.class.clone(),
+                property: name.to_owned(),
+                expected: T::VARIANT_TYPE,
+                actual: value.ty(),
+            })
+    }
+
+    /// Returns whether the instance is of the given class or one of its
+    /// subclasses, according to the reflection database. Instances of classes
+    /// that the database doesn't know about are only of their own class.
+    pub fn is_a(&self, class_name: &str, database: &ReflectionDatabase) -> bool {
+        self.class == class_name
+            || class_chain(database, &self.class)
+                .iter()
+                .any(|class| class.name == class_name)
+    }
+
+    /// Sets the property with the given name.
+    ///
+    /// If the property already exists with a different type, the new value is
+    /// converted to that type using the same rules as
+    /// [`Instance::get_typed`]. An error is returned, and the property is left
+    /// untouched, if no such conversion exists.
+    pub fn set_typed<K, V>(&mut self, name: K, value: V) -> Result<(), PropertyError>
+    where
+        K: Into<String>,
+        V: Into<Variant>,
+    {
+        let name = name.into();
+        let value = self.coerce_for(&name, value.into())?;
+
+        self.properties.insert(name, value);
+        Ok(())
+    }
+
+    /// Converts `value` to the type that the property with the given name
+    /// already has, if it has one.
+    pub(crate) fn coerce_for(&self, name: &str, value: Variant) -> Result<Variant, PropertyError> {
+        match self.properties.get(name) {
+            Some(existing) if existing.ty() != value.ty() => {
+                let expected = existing.ty();
+                let actual = value.ty();
+
+                coerce_variant(value, expected).ok_or_else(|| PropertyError::WrongType {
+                    class: self.class.clone(),
+                    property: name.to_owned(),
+                    expected,
+                    actual,
+                })
+            }
+            _ => Ok(value),
+        }
+    }
+
+    /// Returns a hash of this instance's class, name, and properties, which is
+    /// the same across runs and platforms. Its referent, parent, and children
+    /// are not included, so instances with the same contents hash the same
+    /// wherever they are.
+    ///
+    /// Properties are hashed in order of name, so the order they were added in
+    /// doesn't matter. `Ref` properties are hashed by referent, which differs
+    /// every time a file is read.
+    ///
+    /// ```
+    /// use rbx_dom_weak::{InstanceBuilder, WeakDom};
+    ///
+    /// let dom = WeakDom::new(
+    ///     InstanceBuilder::new("Folder")
+    ///         .with_child(InstanceBuilder::new("BoolValue").with_property("Value", true))
+    ///         .with_child(InstanceBuilder::new("BoolValue").with_property("Value", true)),
+    /// );
+    ///
+    /// let children = dom.root().children();
+    /// let first = dom.get_by_ref(children[0]).unwrap();
+    /// let second = dom.get_by_ref(children[1]).unwrap();
+    /// assert_eq!(first.stable_hash(), second.stable_hash());
+    /// ```
+    pub fn stable_hash(&self) -> u64 {
+        let mut hasher = StableHasher::new();
+        self.hash_stable(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Feeds this instance's class, name, and properties into the given
+    /// hasher. See [`Instance::stable_hash`].
+    pub fn hash_stable<H: Hasher>(&self, state: &mut H) {
+        state.write(self.class.as_bytes());
+        state.write_u8(0xff);
+        state.write(self.name.as_bytes());
+        state.write_u8(0xff);
+        self.properties.hash_stable(state);
+    }
 }
@@ -0,0 +1,266 @@
+use std::collections::HashSet;
+
+use rbx_types::{Ref, Variant};
+
+use crate::{Instance, WeakDom};
+
+/// Describes a single change made to a [`WeakDom`][crate::WeakDom] while
+/// journaling was enabled.
+///
+/// Entries are recorded in the order that the changes were applied, and keep
+/// the instances that each change added or removed as well as the old and
+/// new values of edits. Replaying them in order against a copy of the DOM
+/// from when the journal was enabled reproduces its current instances and
+/// hierarchy, and applying them backwards in reverse order undoes them,
+/// which makes the journal usable as a log for recovering from crashes.
+///
+/// Instances are added to the end of their parent's children, so undoing a
+/// removal or a move puts the instance back under its old parent, but not
+/// necessarily in the same place among its siblings.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum JournalEntry {
+    /// An instance (and any descendants it was built with) was inserted under
+    /// `parent`.
+    Insert {
+        /// The referent of the newly-inserted instance.
+        referent: Ref,
+        /// The referent of the instance it was inserted into.
+        parent: Ref,
+        /// The inserted instance followed by its descendants, parents before
+        /// their children, as they were just after they were inserted.
+        instances: Vec<Instance>,
+    },
+
+    /// An instance and all of its descendants were destroyed.
+    Destroy {
+        /// The referent of the destroyed instance.
+        referent: Ref,
+        /// The referent of the instance that used to be its parent.
+        parent: Ref,
+        /// The destroyed instance followed by its descendants, parents before
+        /// their children, as they were just before they were destroyed.
+        instances: Vec<Instance>,
+    },
+
+    /// An instance and all of its descendants were moved out of this DOM and
+    /// into another one.
+    TransferOut {
+        /// The referent of the instance that was moved.
+        referent: Ref,
+        /// The referent of the instance that used to be its parent.
+        parent: Ref,
+        /// The moved instance followed by its descendants, parents before
+        /// their children, as they were just before they were moved.
+        instances: Vec<Instance>,
+    },
+
+    /// An instance and all of its descendants were moved into this DOM from
+    /// another one.
+    TransferIn {
+        /// The referent of the instance that was moved.
+        referent: Ref,
+        /// The referent of its new parent.
+        parent: Ref,
+        /// The moved instance followed by its descendants, parents before
+        /// their children, as they were just after they were moved in.
+        instances: Vec<Instance>,
+    },
+
+    /// An instance was moved to a new parent within the same DOM.
+    Move {
+        /// The referent of the instance that was moved.
+        referent: Ref,
+        /// The referent of the instance that used to be its parent.
+        old_parent: Ref,
+        /// The referent of its new parent.
+        new_parent: Ref,
+    },
+
+    /// The name or properties of an instance were changed through an
+    /// [`InstanceEditor`][crate::InstanceEditor].
+    Edit {
+        /// The referent of the instance that was changed.
+        referent: Ref,
+        /// The instance's name before and after the edit, if it was renamed.
+        name: Option<NameChange>,
+        /// The properties that were set or removed, sorted by name.
+        properties: Vec<PropertyChange>,
+    },
+}
+
+/// The old and new name of an instance, as recorded in a
+/// [`JournalEntry::Edit`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NameChange {
+    /// The name the instance had before the edit.
+    pub old: String,
+    /// The name the instance had after the edit.
+    pub new: String,
+}
+
+/// The old and new value of a property, as recorded in a
+/// [`JournalEntry::Edit`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct PropertyChange {
+    /// The name of the property.
+    pub name: String,
+    /// The value before the edit, or `None` if the instance didn't have the
+    /// property.
+    pub old: Option<Variant>,
+    /// The value after the edit, or `None` if the property was removed.
+    pub new: Option<Variant>,
+}
+
+/// Picks out the [`JournalEntry`] values that one consumer of the journal
+/// cares about, so that a renderer that only needs to know about changes to
+/// `CFrame` and `Size` doesn't have to look at every script edit.
+///
+/// Every condition that is set has to match. Conditions on classes and
+/// subtrees are checked against the DOM as it is when the entry is checked,
+/// not as it was when the change was made, so entries are best checked soon
+/// after they're drained. Entries about instances that aren't in the DOM any
+/// more, like destroyed ones, always match the class condition, since their
+/// class can't be known.
+///
+/// ```
+/// use rbx_dom_weak::{InstanceBuilder, JournalFilter, WeakDom};
+///
+/// let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+/// let root_ref = dom.root_ref();
+/// let part = dom.insert(root_ref, InstanceBuilder::new("Part"));
+/// let script = dom.insert(root_ref, InstanceBuilder::new("Script"));
+///
+/// dom.enable_journal();
+/// dom.edit(part).set_property("Size", rbx_dom_weak::types::Vector3::new(1.0, 1.0, 1.0));
+/// dom.edit(script).set_property("Source", "print('hi')");
+///
+/// let filter = JournalFilter::new().property("CFrame").property("Size");
+/// let entries: Vec<_> = dom
+///     .drain_journal()
+///     .into_iter()
+///     .filter(|entry| filter.matches(&dom, entry))
+///     .collect();
+///
+/// assert_eq!(entries.len(), 1);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct JournalFilter {
+    classes: Option<HashSet<String>>,
+    properties: Option<HashSet<String>>,
+    subtree: Option<Ref>,
+}
+
+impl JournalFilter {
+    /// Constructs a filter that matches every entry.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a class that entries can be about. Once a class has been added,
+    /// entries about instances of other classes don't match.
+    pub fn class<S: Into<String>>(mut self, class: S) -> Self {
+        self.classes
+            .get_or_insert_with(HashSet::new)
+            .insert(class.into());
+        self
+    }
+
+    /// Adds a property that edits can change. Once a property has been added,
+    /// [`JournalEntry::Edit`] entries that only change other properties don't
+    /// match. Changes to an instance's name match the property `Name`.
+    ///
+    /// Entries that aren't edits, like inserting or destroying instances,
+    /// aren't affected by this condition.
+    pub fn property<S: Into<String>>(mut self, property: S) -> Self {
+        self.properties
+            .get_or_insert_with(HashSet::new)
+            .insert(property.into());
+        self
+    }
+
+    /// Sets an instance that entries have to be about, or about one of its
+    /// descendants. Changes to the hierarchy that move instances into or out
+    /// of the subtree match.
+    #[inline]
+    pub fn subtree(self, root: Ref) -> Self {
+        Self {
+            subtree: Some(root),
+            ..self
+        }
+    }
+
+    /// Tells whether an entry from the journal of `dom` matches this filter.
+    pub fn matches(&self, dom: &WeakDom, entry: &JournalEntry) -> bool {
+        let (referent, parents): (Ref, &[Ref]) = match entry {
+            JournalEntry::Insert {
+                referent, parent, ..
+            }
+            | JournalEntry::Destroy {
+                referent, parent, ..
+            }
+            | JournalEntry::TransferOut {
+                referent, parent, ..
+            }
+            | JournalEntry::TransferIn {
+                referent, parent, ..
+            } => (*referent, std::slice::from_ref(parent)),
+            JournalEntry::Move {
+                referent,
+                old_parent,
+                new_parent,
+            } => (*referent, &[*old_parent, *new_parent]),
+            JournalEntry::Edit { referent, .. } => (*referent, &[]),
+        };
+
+        if let Some(classes) = &self.classes {
+            if let Some(instance) = dom.get_by_ref(referent) {
+                if !classes.contains(&instance.class) {
+                    return false;
+                }
+            }
+        }
+
+        if let (
+            Some(properties),
+            JournalEntry::Edit {
+                name,
+                properties: changed,
+                ..
+            },
+        ) = (&self.properties, entry)
+        {
+            let name_matches = name.is_some() && properties.contains("Name");
+            if !name_matches
+                && !changed
+                    .iter()
+                    .any(|change| properties.contains(&change.name))
+            {
+                return false;
+            }
+        }
+
+        if let Some(root) = self.subtree {
+            let in_subtree = |referent| is_in_subtree(dom, root, referent);
+            if !in_subtree(referent) && !parents.iter().copied().any(in_subtree) {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// Tells whether `referent` is `root` or one of its descendants.
+fn is_in_subtree(dom: &WeakDom, root: Ref, mut referent: Ref) -> bool {
+    while let Some(instance) = dom.get_by_ref(referent) {
+        if referent == root {
+            return true;
+        }
+
+        referent = instance.parent();
+    }
+
+    false
+}
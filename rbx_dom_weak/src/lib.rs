@@ -41,14 +41,48 @@
 
 #![deny(missing_docs)]
 
+mod cancel;
+mod config;
+mod detached;
 mod dom;
+mod edit;
+mod equality;
+mod extensions;
+mod file_metadata;
 mod instance;
+mod journal;
+mod line_endings;
+#[cfg(feature = "mlua")]
+pub mod lua;
+mod name;
+pub mod property_map;
+mod shared;
+mod typed;
+mod view;
 mod viewer;
+mod visitor;
 
 pub use rbx_types as types;
 
+#[doc(hidden)]
+pub use rbx_reflection as __reflection;
+
 pub use crate::{
+    cancel::Cancelled,
+    config::{TreeConfig, TreeConfigError},
+    detached::{DetachedSubtree, ReattachError},
     dom::WeakDom,
+    edit::InstanceEditor,
+    equality::EqualityOptions,
+    file_metadata::FileMetadata,
     instance::{Instance, InstanceBuilder},
+    journal::{JournalEntry, JournalFilter, NameChange, PropertyChange},
+    line_endings::LineEndings,
+    name::{is_valid_name, sanitize_name, MAX_NAME_LENGTH},
+    property_map::{PropertyInterner, PropertyMap},
+    shared::SharedDom,
+    typed::{coerce_variant, PropertyError, PropertyType},
+    view::WeakDomView,
     viewer::{DomViewer, ViewedInstance},
+    visitor::TreeVisitor,
 };
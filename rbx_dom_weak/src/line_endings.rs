@@ -0,0 +1,86 @@
+//! Applies [`LineEndings`] to script sources.
+
+use std::borrow::Cow;
+
+/// Describes which line endings a serializer or deserializer should give
+/// script sources, which are the `Source` property of scripts. rbx_binary and
+/// rbx_xml both accept it.
+///
+/// Roblox Studio keeps whatever line endings a script was written with, so
+/// files edited on both Windows and other platforms tend to switch back and
+/// forth, which makes every line of a script show up as changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub enum LineEndings {
+    /// Keeps line endings as they are.
+    ///
+    /// This is the default.
+    #[default]
+    Preserve,
+
+    /// Turns every line ending into `\n`.
+    Lf,
+
+    /// Turns every line ending into `\r\n`.
+    Crlf,
+}
+
+impl LineEndings {
+    /// Returns `source` with its line endings changed. Lone `\r` characters
+    /// are treated as line endings too.
+    pub fn apply<'a>(self, source: &'a str) -> Cow<'a, str> {
+        let ending = match self {
+            LineEndings::Preserve => return Cow::Borrowed(source),
+            LineEndings::Lf => "\n",
+            LineEndings::Crlf => "\r\n",
+        };
+
+        if !source.contains('\r') && (self == LineEndings::Lf || !source.contains('\n')) {
+            return Cow::Borrowed(source);
+        }
+
+        let mut normalized = String::with_capacity(source.len());
+        let mut lines = source.split('\n').peekable();
+
+        while let Some(line) = lines.next() {
+            let is_last = lines.peek().is_none();
+            let line = match line.strip_suffix('\r') {
+                Some(stripped) if !is_last => stripped,
+                _ => line,
+            };
+            let mut pieces = line.split('\r');
+
+            if let Some(piece) = pieces.next() {
+                normalized.push_str(piece);
+            }
+
+            for piece in pieces {
+                normalized.push_str(ending);
+                normalized.push_str(piece);
+            }
+
+            if !is_last {
+                normalized.push_str(ending);
+            }
+        }
+
+        Cow::Owned(normalized)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn apply() {
+        let mixed = "a\r\nb\nc\rd";
+
+        assert_eq!(LineEndings::Preserve.apply(mixed), mixed);
+        assert_eq!(LineEndings::Lf.apply(mixed), "a\nb\nc\nd");
+        assert_eq!(LineEndings::Crlf.apply(mixed), "a\r\nb\r\nc\r\nd");
+
+        assert!(matches!(LineEndings::Lf.apply("a\nb"), Cow::Borrowed(_)));
+        assert!(matches!(LineEndings::Crlf.apply("ab"), Cow::Borrowed(_)));
+    }
+}
@@ -0,0 +1,537 @@
+//! Integration with [mlua](https://docs.rs/mlua), enabled with the `mlua`
+//! feature.
+//!
+//! This allows embedding Luau in tools that work with a [`WeakDom`] so that
+//! users can write their own transforms. Instances are exposed to Luau as
+//! userdata with an API that resembles Roblox's:
+//!
+//! ```
+//! use mlua::Lua;
+//! use rbx_dom_weak::{lua::LuaDom, InstanceBuilder, WeakDom};
+//!
+//! let dom = LuaDom::new(WeakDom::new(InstanceBuilder::new("DataModel")));
+//!
+//! let lua = Lua::new();
+//! lua.globals().set("game", dom.root())?;
+//! lua.load(r#"
+//!     local folder = game:AddChild("Folder", "Assets")
+//!     folder:AddChild("StringValue").Value = "Hello"
+//!     folder.Name = "Stuff"
+//! "#).exec()?;
+//! drop(lua);
+//!
+//! let dom = dom.into_inner().unwrap();
+//! let folder = dom.get_by_ref(dom.root().children()[0]).unwrap();
+//! assert_eq!(folder.name, "Stuff");
+//! # Ok::<(), mlua::Error>(())
+//! ```
+//!
+//! Besides `Name`, `ClassName`, and `Parent`, indexing an instance reads or
+//! writes its properties. Only properties whose types map naturally to Luau
+//! values (booleans, numbers, strings, and references to other instances) can
+//! be accessed.
+//!
+//! Luau numbers don't say which of Roblox's number types they are, so a
+//! number can only be assigned to a property whose type is known, either
+//! because the instance already has it or because the DOM's
+//! [`TreeConfig`][crate::TreeConfig] has a reflection database that knows it.
+//! Other properties can be set with `instance:SetProperty(name, value, type)`,
+//! where `type` is the name of a [`VariantType`], like `"Int32"`.
+//!
+//! Every change is made through [`WeakDom::edit`] and the other methods of
+//! [`WeakDom`], so changes are recorded in the journal and checked by the
+//! DOM's `TreeConfig`.
+
+use std::{cell::RefCell, rc::Rc};
+
+use mlua::{Error, FromLua, IntoLua, Lua, MetaMethod, Result, UserData, UserDataMethods, Value};
+use rbx_types::{Enum, Ref, Variant, VariantType};
+use serde::{de::value::StrDeserializer, Deserialize};
+
+use crate::{coerce_variant, InstanceBuilder, WeakDom};
+
+/// Converts a [`Variant`] into a Luau value, failing if there is no natural
+/// representation of its type in Luau.
+///
+/// `Ref` values can't be converted by this function since they are only
+/// meaningful alongside a DOM; instance properties handle them separately.
+pub fn variant_to_lua<'lua>(lua: &'lua Lua, value: &Variant) -> Result<Value<'lua>> {
+    match value {
+        Variant::Bool(value) => value.into_lua(lua),
+        Variant::Int32(value) => value.into_lua(lua),
+        Variant::Int64(value) => value.into_lua(lua),
+        Variant::Float32(value) => value.into_lua(lua),
+        Variant::Float64(value) => value.into_lua(lua),
+        Variant::String(value) => value.as_str().into_lua(lua),
+        Variant::Content(value) => AsRef::<str>::as_ref(value).into_lua(lua),
+        Variant::BinaryString(value) => lua.create_string(value).map(Value::String),
+        other => Err(Error::ToLuaConversionError {
+            from: "Variant",
+            to: "Luau value",
+            message: Some(format!("values of type {:?} are not supported", other.ty())),
+        }),
+    }
+}
+
+/// Converts a Luau value into a [`Variant`] of the given type, or of the type
+/// that suits it best if no type is given.
+///
+/// Booleans become `Bool` and strings become `String` unless another type is
+/// asked for. Numbers need a type, since Luau doesn't say which of Roblox's
+/// number types they are. They are only converted to integer types if they
+/// are whole numbers that the type can hold exactly, and to `Float32` if they
+/// are within its range.
+pub fn lua_to_variant(value: Value, ty: Option<VariantType>) -> Result<Variant> {
+    let conversion_error = |from: &'static str, message: String| Error::FromLuaConversionError {
+        from,
+        to: "Variant",
+        message: Some(message),
+    };
+
+    let variant = match value {
+        Value::Boolean(value) => Variant::Bool(value),
+        Value::String(value) => Variant::String(value.to_str()?.to_owned()),
+        Value::Integer(value) => number_to_variant(value as f64, ty)
+            .ok_or_else(|| conversion_error("integer", number_message(ty)))?,
+        Value::Number(value) => number_to_variant(value, ty)
+            .ok_or_else(|| conversion_error("number", number_message(ty)))?,
+        other => {
+            return Err(Error::FromLuaConversionError {
+                from: other.type_name(),
+                to: "Variant",
+                message: None,
+            })
+        }
+    };
+
+    match ty {
+        Some(ty) => {
+            let from = variant.ty();
+            coerce_variant(variant, ty).ok_or_else(|| {
+                conversion_error(
+                    "value",
+                    format!("{:?} can't be converted to {:?}", from, ty),
+                )
+            })
+        }
+        None => Ok(variant),
+    }
+}
+
+/// Converts a Luau number into a number type, if it fits.
+fn number_to_variant(value: f64, ty: Option<VariantType>) -> Option<Variant> {
+    let is_integer = value.fract() == 0.0;
+
+    Some(match ty? {
+        VariantType::Int32 if is_integer && value.abs() <= i32::MAX as f64 => {
+            Variant::Int32(value as i32)
+        }
+        // Integers up to 2^53 are the only ones a Luau number can hold exactly.
+        VariantType::Int64 if is_integer && value.abs() <= (1u64 << 53) as f64 => {
+            Variant::Int64(value as i64)
+        }
+        VariantType::Enum if is_integer && (0.0..=u32::MAX as f64).contains(&value) => {
+            Variant::Enum(Enum::from_u32(value as u32))
+        }
+        VariantType::Float32 if value.abs() <= f32::MAX as f64 || !value.is_finite() => {
+            Variant::Float32(value as f32)
+        }
+        VariantType::Float64 => Variant::Float64(value),
+        _ => return None,
+    })
+}
+
+fn number_message(ty: Option<VariantType>) -> String {
+    match ty {
+        Some(ty) => format!("number doesn't fit in {:?}", ty),
+        None => "numbers can only be assigned to properties with a known type; \
+            use SetProperty to give one"
+            .to_owned(),
+    }
+}
+
+/// A [`WeakDom`] that can be shared with Luau.
+#[derive(Debug, Clone)]
+pub struct LuaDom {
+    dom: Rc<RefCell<WeakDom>>,
+}
+
+impl LuaDom {
+    /// Wraps a `WeakDom` so that its instances can be passed to Luau.
+    pub fn new(dom: WeakDom) -> Self {
+        LuaDom {
+            dom: Rc::new(RefCell::new(dom)),
+        }
+    }
+
+    /// Returns the root instance of the DOM as a Luau value.
+    pub fn root(&self) -> LuaInstance {
+        let referent = self.dom.borrow().root_ref();
+        self.instance(referent)
+    }
+
+    /// Returns the instance with the given referent as a Luau value. The
+    /// instance doesn't need to exist.
+    pub fn instance(&self, referent: Ref) -> LuaInstance {
+        LuaInstance {
+            dom: self.clone(),
+            referent,
+        }
+    }
+
+    /// Calls the given function with a reference to the DOM.
+    pub fn with<R>(&self, callback: impl FnOnce(&WeakDom) -> R) -> R {
+        callback(&self.dom.borrow())
+    }
+
+    /// Calls the given function with a mutable reference to the DOM.
+    pub fn with_mut<R>(&self, callback: impl FnOnce(&mut WeakDom) -> R) -> R {
+        callback(&mut self.dom.borrow_mut())
+    }
+
+    /// Unwraps the DOM, returning `Err(self)` if Luau values or other clones
+    /// still refer to it.
+    pub fn into_inner(self) -> std::result::Result<WeakDom, Self> {
+        Rc::try_unwrap(self.dom)
+            .map(RefCell::into_inner)
+            .map_err(|dom| LuaDom { dom })
+    }
+}
+
+/// An instance in a [`LuaDom`], exposed to Luau as userdata.
+#[derive(Debug, Clone)]
+pub struct LuaInstance {
+    dom: LuaDom,
+    referent: Ref,
+}
+
+impl LuaInstance {
+    /// The referent of this instance.
+    pub fn referent(&self) -> Ref {
+        self.referent
+    }
+
+    fn missing(&self) -> Error {
+        Error::RuntimeError("instance has been destroyed".to_owned())
+    }
+
+    fn get<'lua>(&self, lua: &'lua Lua, key: &str) -> Result<Value<'lua>> {
+        let dom = self.dom.dom.borrow();
+        let instance = dom
+            .get_by_ref(self.referent)
+            .ok_or_else(|| self.missing())?;
+
+        match key {
+            "Name" => instance.name.as_str().into_lua(lua),
+            "ClassName" => instance.class.as_str().into_lua(lua),
+            "Parent" => self.instance_or_nil(lua, instance.parent()),
+            _ => match instance.properties.get(key) {
+                Some(Variant::Ref(referent)) => self.instance_or_nil(lua, *referent),
+                Some(value) => variant_to_lua(lua, value),
+                None => Ok(Value::Nil),
+            },
+        }
+    }
+
+    fn set<'lua>(&self, lua: &'lua Lua, key: &str, value: Value<'lua>) -> Result<()> {
+        let mut dom = self.dom.dom.borrow_mut();
+        let instance = dom
+            .get_by_ref(self.referent)
+            .ok_or_else(|| self.missing())?;
+
+        match key {
+            "ClassName" => Err(Error::RuntimeError(
+                "ClassName cannot be changed".to_owned(),
+            )),
+            "Parent" => {
+                let parent = match value {
+                    Value::UserData(data) => data.borrow::<LuaInstance>()?.referent,
+                    _ => {
+                        return Err(Error::RuntimeError(
+                            "Parent must be set to another instance".to_owned(),
+                        ))
+                    }
+                };
+
+                if self.referent == dom.root_ref() {
+                    return Err(Error::RuntimeError(
+                        "the root instance cannot be moved".to_owned(),
+                    ));
+                }
+
+                let mut ancestor = parent;
+                while let Some(instance) = dom.get_by_ref(ancestor) {
+                    if ancestor == self.referent {
+                        return Err(Error::RuntimeError(
+                            "an instance cannot be parented to itself or its descendants"
+                                .to_owned(),
+                        ));
+                    }
+                    ancestor = instance.parent();
+                }
+
+                if dom.get_by_ref(parent).is_none() {
+                    return Err(Error::RuntimeError(
+                        "new parent has been destroyed".to_owned(),
+                    ));
+                }
+
+                dom.transfer_within(self.referent, parent);
+                Ok(())
+            }
+            "Name" => {
+                let name = String::from_lua(value, lua)?;
+                dom.edit(self.referent).set_name(name);
+                Ok(())
+            }
+            _ => {
+                let ty = dom
+                    .config()
+                    .known_property_type(&instance.class, key)
+                    .or_else(|| instance.properties.get(key).map(Variant::ty));
+
+                drop(dom);
+                self.set_property(key, value, ty)
+            }
+        }
+    }
+
+    /// Sets a property through the DOM's editor, converting `value` to `ty`
+    /// if one is given. Assigning nil removes the property.
+    fn set_property(&self, key: &str, value: Value, ty: Option<VariantType>) -> Result<()> {
+        let mut dom = self.dom.dom.borrow_mut();
+        if dom.get_by_ref(self.referent).is_none() {
+            return Err(self.missing());
+        }
+
+        let variant = match value {
+            Value::Nil => {
+                dom.edit(self.referent).remove_property(key);
+                return Ok(());
+            }
+            Value::UserData(data) => Variant::Ref(data.borrow::<LuaInstance>()?.referent),
+            other => lua_to_variant(other, ty).map_err(|err| {
+                Error::RuntimeError(format!("property {} can't be assigned: {}", key, err))
+            })?,
+        };
+
+        dom.update_properties(self.referent, [(key, variant)])
+            .map_err(Error::external)
+    }
+
+    fn instance_or_nil<'lua>(&self, lua: &'lua Lua, referent: Ref) -> Result<Value<'lua>> {
+        if self.dom.dom.borrow().get_by_ref(referent).is_some() {
+            self.dom.instance(referent).into_lua(lua)
+        } else {
+            Ok(Value::Nil)
+        }
+    }
+}
+
+impl UserData for LuaInstance {
+    fn add_methods<'lua, M: UserDataMethods<'lua, Self>>(methods: &mut M) {
+        methods.add_method("GetChildren", |_, this, ()| {
+            let dom = this.dom.dom.borrow();
+            let instance = dom
+                .get_by_ref(this.referent)
+                .ok_or_else(|| this.missing())?;
+
+            Ok(instance
+                .children()
+                .iter()
+                .map(|&child| this.dom.instance(child))
+                .collect::<Vec<_>>())
+        });
+
+        methods.add_method("FindFirstChild", |_, this, name: String| {
+            let dom = this.dom.dom.borrow();
+            let instance = dom
+                .get_by_ref(this.referent)
+                .ok_or_else(|| this.missing())?;
+
+            Ok(instance
+                .children()
+                .iter()
+                .find(|&&child| dom.get_by_ref(child).unwrap().name == name)
+                .map(|&child| this.dom.instance(child)))
+        });
+
+        methods.add_method(
+            "AddChild",
+            |_, this, (class_name, name): (String, Option<String>)| {
+                let mut dom = this.dom.dom.borrow_mut();
+                if dom.get_by_ref(this.referent).is_none() {
+                    return Err(this.missing());
+                }
+
+                let mut builder = InstanceBuilder::new(class_name);
+                if let Some(name) = name {
+                    builder = builder.with_name(name);
+                }
+
+                let child = dom
+                    .try_insert(this.referent, builder)
+                    .map_err(Error::external)?;
+                Ok(this.dom.instance(child))
+            },
+        );
+
+        methods.add_method(
+            "SetProperty",
+            |_, this, (name, value, ty): (String, Value, String)| {
+                let ty =
+                    VariantType::deserialize(StrDeserializer::<serde::de::value::Error>::new(&ty))
+                        .map_err(|_| {
+                            Error::RuntimeError(format!("{} is not a type of value", ty))
+                        })?;
+
+                this.set_property(&name, value, Some(ty))
+            },
+        );
+
+        methods.add_method("Destroy", |_, this, ()| {
+            let mut dom = this.dom.dom.borrow_mut();
+            if this.referent == dom.root_ref() {
+                return Err(Error::RuntimeError(
+                    "the root instance cannot be destroyed".to_owned(),
+                ));
+            }
+
+            if dom.get_by_ref(this.referent).is_some() {
+                dom.destroy(this.referent);
+            }
+            Ok(())
+        });
+
+        methods.add_meta_method(MetaMethod::Index, |lua, this, key: String| {
+            this.get(lua, &key)
+        });
+
+        methods.add_meta_method(
+            MetaMethod::NewIndex,
+            |lua, this, (key, value): (String, Value)| this.set(lua, &key, value),
+        );
+
+        methods.add_meta_method(MetaMethod::Eq, |_, this, other: mlua::AnyUserData| {
+            Ok(other
+                .borrow::<LuaInstance>()
+                .map(|other| {
+                    Rc::ptr_eq(&this.dom.dom, &other.dom.dom) && this.referent == other.referent
+                })
+                .unwrap_or(false))
+        });
+
+        methods.add_meta_method(MetaMethod::ToString, |_, this, ()| {
+            let dom = this.dom.dom.borrow();
+            Ok(dom
+                .get_by_ref(this.referent)
+                .map(|instance| instance.name.clone())
+                .unwrap_or_default())
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn properties() {
+        let dom = LuaDom::new(WeakDom::new(
+            InstanceBuilder::new("DataModel").with_child(
+                InstanceBuilder::new("NumberValue")
+                    .with_name("Number")
+                    .with_property("Value", 1.0f32),
+            ),
+        ));
+
+        let lua = Lua::new();
+        lua.globals().set("game", dom.root()).unwrap();
+        lua.load(
+            r#"
+            local number = game:FindFirstChild("Number")
+            assert(number.ClassName == "NumberValue")
+            assert(number.Parent == game)
+            assert(number.Value == 1)
+
+            -- Assigning keeps the property's existing type.
+            number.Value = 3
+
+            local folder = game:AddChild("Folder")
+            number.Parent = folder
+            assert(#game:GetChildren() == 1)
+            assert(not pcall(function() folder.Parent = number end))
+            "#,
+        )
+        .exec()
+        .unwrap();
+        drop(lua);
+
+        let dom = dom.into_inner().unwrap();
+        let folder = dom.get_by_ref(dom.root().children()[0]).unwrap();
+        let number = dom.get_by_ref(folder.children()[0]).unwrap();
+        assert_eq!(number.properties.get("Value"), Some(&Variant::Float32(3.0)));
+    }
+
+    #[test]
+    fn edits_are_checked_and_journaled() {
+        use std::sync::Arc;
+
+        use rbx_reflection::{ClassDescriptor, DataType, PropertyDescriptor, ReflectionDatabase};
+
+        use crate::{JournalEntry, TreeConfig};
+
+        let mut part = ClassDescriptor::new("Part");
+        part.properties.insert(
+            "Transparency".into(),
+            PropertyDescriptor::new("Transparency", DataType::Value(VariantType::Float32)),
+        );
+        let mut database = ReflectionDatabase::new();
+        database.classes.insert("Part".into(), part);
+        let database = Arc::new(database);
+
+        let config = TreeConfig::new()
+            .shared_reflection_database(database)
+            .reject_unknown_classes(true);
+        let mut dom = WeakDom::with_config(InstanceBuilder::new("Part"), config);
+        dom.enable_journal();
+        let dom = LuaDom::new(dom);
+
+        let lua = Lua::new();
+        lua.globals().set("part", dom.root()).unwrap();
+        lua.load(
+            r#"
+            -- The reflection database knows that Transparency is a Float32.
+            part.Transparency = 0.5
+
+            -- Nothing knows what type Count should have.
+            assert(not pcall(function() part.Count = 1 end))
+            part:SetProperty("Count", 1, "Int32")
+            assert(not pcall(function() part.Count = 1.5 end))
+            assert(not pcall(function() part:SetProperty("Big", 2^40, "Int32") end))
+
+            part.Name = "Window"
+            assert(not pcall(function() part:AddChild("Folder") end))
+            "#,
+        )
+        .exec()
+        .unwrap();
+        drop(lua);
+
+        let mut dom = dom.into_inner().unwrap();
+        let part = dom.root();
+        assert_eq!(part.name, "Window");
+        assert_eq!(
+            part.properties.get("Transparency"),
+            Some(&Variant::Float32(0.5))
+        );
+        assert_eq!(part.properties.get("Count"), Some(&Variant::Int32(1)));
+        assert!(part.properties.get("Big").is_none());
+
+        let journal = dom.drain_journal();
+        assert_eq!(journal.len(), 3);
+        assert!(journal
+            .iter()
+            .all(|entry| matches!(entry, JournalEntry::Edit { .. })));
+    }
+}
@@ -0,0 +1,62 @@
+use std::borrow::Cow;
+
+/// The longest name, in characters, that Roblox lets an instance have.
+pub const MAX_NAME_LENGTH: usize = 100;
+
+/// Cleans up an instance name so that it can be shown, logged, and typed
+/// without surprises:
+///
+/// - Control characters, like newlines and tabs, are removed.
+/// - Invisible formatting characters, like zero-width spaces, byte order
+///   marks, and characters that change the direction of text, are removed.
+/// - Other kinds of whitespace, like non-breaking spaces, become spaces.
+/// - Whitespace at the start and end is removed.
+/// - Names longer than [`MAX_NAME_LENGTH`] characters are cut short.
+///
+/// Names that don't need any of these changes are returned as they are.
+/// Names can still be empty afterwards, which Roblox allows.
+///
+/// ```
+/// use rbx_dom_weak::sanitize_name;
+///
+/// assert_eq!(sanitize_name("Part"), "Part");
+/// assert_eq!(sanitize_name("  My\u{200B}\nModel\u{A0}2 "), "MyModel 2");
+/// ```
+pub fn sanitize_name(name: &str) -> Cow<'_, str> {
+    if is_valid_name(name) {
+        return Cow::Borrowed(name);
+    }
+
+    let cleaned: String = name
+        .chars()
+        .filter(|&c| !c.is_control() && !is_invisible(c))
+        .map(|c| if c.is_whitespace() { ' ' } else { c })
+        .collect();
+
+    let shortened: String = cleaned.trim().chars().take(MAX_NAME_LENGTH).collect();
+    Cow::Owned(shortened.trim_end().to_owned())
+}
+
+/// Tells whether [`sanitize_name`] would leave a name as it is.
+pub fn is_valid_name(name: &str) -> bool {
+    name.trim() == name
+        && name.chars().count() <= MAX_NAME_LENGTH
+        && name
+            .chars()
+            .all(|c| !c.is_control() && !is_invisible(c) && (c == ' ' || !c.is_whitespace()))
+}
+
+/// Characters that take up no space when shown, which can make two names
+/// look the same or reorder the text around them.
+fn is_invisible(c: char) -> bool {
+    matches!(
+        c,
+        '\u{AD}'
+            | '\u{180E}'
+            | '\u{200B}'..='\u{200F}'
+            | '\u{202A}'..='\u{202E}'
+            | '\u{2060}'..='\u{2064}'
+            | '\u{2066}'..='\u{206F}'
+            | '\u{FEFF}'
+    )
+}
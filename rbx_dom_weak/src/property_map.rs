@@ -0,0 +1,483 @@
+//! [`PropertyMap`], which holds the properties of an instance, and the
+//! iterators over it.
+
+use std::{
+    borrow::Borrow,
+    collections::{hash_map, HashMap},
+    hash::{Hash, Hasher},
+    iter::FromIterator,
+    ops::Index,
+    sync::Arc,
+};
+
+use rbx_types::{StableHasher, Variant};
+
+/// The properties of an [`Instance`][crate::Instance], keyed by name.
+///
+/// `PropertyMap` has the same methods as a `HashMap<String, Variant>` for
+/// reading and changing properties. Cloning it is cheap: clones share the
+/// same map until one of them is changed, at which point that one is copied.
+/// This makes cloning a [`WeakDom`][crate::WeakDom] to keep a snapshot of it
+/// cost little more than copying its hierarchy.
+///
+/// Each value is also stored on its own, so that equal values on different
+/// instances can share one copy of it. See [`PropertyInterner`].
+///
+/// ```
+/// use rbx_dom_weak::PropertyMap;
+///
+/// let mut original = PropertyMap::new();
+/// original.insert("Anchored".to_owned(), true.into());
+///
+/// let mut snapshot = original.clone();
+/// assert!(snapshot.ptr_eq(&original));
+///
+/// snapshot.insert("Anchored".to_owned(), false.into());
+/// assert!(!snapshot.ptr_eq(&original));
+/// assert_eq!(original.get("Anchored"), Some(&true.into()));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PropertyMap(Arc<HashMap<String, Arc<Variant>>>);
+
+impl PropertyMap {
+    /// Creates an empty `PropertyMap`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns how many properties are in the map.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Tells whether the map has no properties.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the value of the property with the given name.
+    #[inline]
+    pub fn get<Q>(&self, name: &Q) -> Option<&Variant>
+    where
+        String: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.0.get(name).map(Arc::as_ref)
+    }
+
+    /// Returns the value of the property with the given name for changing it,
+    /// copying the map and the value first if they're shared.
+    pub fn get_mut<Q>(&mut self, name: &Q) -> Option<&mut Variant>
+    where
+        String: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        // Looking the property up first avoids copying the map when it
+        // isn't there.
+        if !self.0.contains_key(name) {
+            return None;
+        }
+
+        Arc::make_mut(&mut self.0).get_mut(name).map(Arc::make_mut)
+    }
+
+    /// Tells whether the map has a property with the given name.
+    #[inline]
+    pub fn contains_key<Q>(&self, name: &Q) -> bool
+    where
+        String: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.0.contains_key(name)
+    }
+
+    /// Sets the value of a property, returning its old value if it had one.
+    pub fn insert(&mut self, name: String, value: Variant) -> Option<Variant> {
+        Arc::make_mut(&mut self.0)
+            .insert(name, Arc::new(value))
+            .map(unwrap_value)
+    }
+
+    /// Removes a property, returning its value if it had one.
+    pub fn remove<Q>(&mut self, name: &Q) -> Option<Variant>
+    where
+        String: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        if !self.0.contains_key(name) {
+            return None;
+        }
+
+        Arc::make_mut(&mut self.0).remove(name).map(unwrap_value)
+    }
+
+    /// Keeps only the properties that `keep` returns `true` for.
+    pub fn retain<F: FnMut(&String, &Variant) -> bool>(&mut self, mut keep: F) {
+        if self.0.iter().all(|(name, value)| keep(name, value)) {
+            return;
+        }
+
+        Arc::make_mut(&mut self.0).retain(|name, value| keep(name, value));
+    }
+
+    /// Returns an iterator over the names and values of every property, in
+    /// no particular order.
+    #[inline]
+    pub fn iter(&self) -> Iter<'_> {
+        Iter(self.0.iter())
+    }
+
+    /// Returns an iterator over the names and values of every property for
+    /// changing them, copying the map and any shared values first.
+    pub fn iter_mut(&mut self) -> IterMut<'_> {
+        IterMut(Arc::make_mut(&mut self.0).iter_mut())
+    }
+
+    /// Returns an iterator over the names of every property.
+    #[inline]
+    pub fn keys(&self) -> Keys<'_> {
+        Keys(self.0.keys())
+    }
+
+    /// Returns an iterator over the values of every property.
+    pub fn values(&self) -> impl Iterator<Item = &Variant> + '_ {
+        self.0.values().map(Arc::as_ref)
+    }
+
+    /// Returns an iterator over the values of every property for changing
+    /// them, copying the map and any shared values first.
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut Variant> + '_ {
+        Arc::make_mut(&mut self.0).values_mut().map(Arc::make_mut)
+    }
+
+    /// Tells whether both maps are shared copies of the same map, meaning
+    /// that neither has been changed since one was cloned from the other.
+    /// Maps that share storage are always equal, so tools comparing snapshots
+    /// can skip comparing their contents.
+    #[inline]
+    pub fn ptr_eq(&self, other: &PropertyMap) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+
+    /// Tells whether both maps have a property called `name` whose values
+    /// share storage, because one was cloned from the other or both were
+    /// interned. Values that share storage are always equal.
+    pub fn value_ptr_eq(&self, other: &PropertyMap, name: &str) -> bool {
+        match (self.0.get(name), other.0.get(name)) {
+            (Some(value), Some(other)) => Arc::ptr_eq(value, other),
+            _ => false,
+        }
+    }
+
+    /// Returns the properties as a `HashMap`, copying them only if they're
+    /// shared with another `PropertyMap`.
+    pub fn into_inner(self) -> HashMap<String, Variant> {
+        self.into_iter().collect()
+    }
+
+    /// Feeds the properties into the given hasher in order of name, so the
+    /// order they were added in doesn't matter. See
+    /// [`Instance::stable_hash`][crate::Instance::stable_hash].
+    pub fn hash_stable<H: Hasher>(&self, state: &mut H) {
+        let mut properties: Vec<_> = self.iter().collect();
+        properties.sort_unstable_by(|a, b| a.0.cmp(b.0));
+
+        state.write_usize(properties.len());
+        for (name, value) in properties {
+            state.write(name.as_bytes());
+            state.write_u8(0xff);
+            value.hash_stable(state);
+        }
+    }
+}
+
+/// Takes a value out of its `Arc`, copying it if it's shared.
+fn unwrap_value(value: Arc<Variant>) -> Variant {
+    Arc::try_unwrap(value).unwrap_or_else(|shared| (*shared).clone())
+}
+
+impl PartialEq for PropertyMap {
+    fn eq(&self, other: &Self) -> bool {
+        if self.ptr_eq(other) {
+            return true;
+        }
+
+        self.len() == other.len()
+            && self.0.iter().all(|(name, value)| match other.0.get(name) {
+                Some(other) => Arc::ptr_eq(value, other) || value == other,
+                None => false,
+            })
+    }
+}
+
+impl<Q> Index<&Q> for PropertyMap
+where
+    String: Borrow<Q>,
+    Q: Hash + Eq + ?Sized,
+{
+    type Output = Variant;
+
+    /// ## Panics
+    /// Panics if the map has no property called `name`.
+    fn index(&self, name: &Q) -> &Variant {
+        self.get(name).expect("no property with that name")
+    }
+}
+
+impl From<HashMap<String, Variant>> for PropertyMap {
+    fn from(map: HashMap<String, Variant>) -> Self {
+        map.into_iter().collect()
+    }
+}
+
+impl From<PropertyMap> for HashMap<String, Variant> {
+    fn from(map: PropertyMap) -> Self {
+        map.into_inner()
+    }
+}
+
+impl FromIterator<(String, Variant)> for PropertyMap {
+    fn from_iter<I: IntoIterator<Item = (String, Variant)>>(iter: I) -> Self {
+        PropertyMap(Arc::new(
+            iter.into_iter()
+                .map(|(name, value)| (name, Arc::new(value)))
+                .collect(),
+        ))
+    }
+}
+
+impl Extend<(String, Variant)> for PropertyMap {
+    fn extend<I: IntoIterator<Item = (String, Variant)>>(&mut self, iter: I) {
+        Arc::make_mut(&mut self.0).extend(
+            iter.into_iter()
+                .map(|(name, value)| (name, Arc::new(value))),
+        );
+    }
+}
+
+/// An iterator over the properties of a [`PropertyMap`], made by
+/// [`PropertyMap::iter`].
+#[derive(Debug, Clone)]
+pub struct Iter<'a>(hash_map::Iter<'a, String, Arc<Variant>>);
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = (&'a String, &'a Variant);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(name, value)| (name, value.as_ref()))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl ExactSizeIterator for Iter<'_> {}
+
+/// An iterator over the names of the properties of a [`PropertyMap`], made
+/// by [`PropertyMap::keys`].
+#[derive(Debug, Clone)]
+pub struct Keys<'a>(hash_map::Keys<'a, String, Arc<Variant>>);
+
+impl<'a> Iterator for Keys<'a> {
+    type Item = &'a String;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl ExactSizeIterator for Keys<'_> {}
+
+/// An iterator over the properties of a [`PropertyMap`] for changing them,
+/// made by [`PropertyMap::iter_mut`].
+#[derive(Debug)]
+pub struct IterMut<'a>(hash_map::IterMut<'a, String, Arc<Variant>>);
+
+impl<'a> Iterator for IterMut<'a> {
+    type Item = (&'a String, &'a mut Variant);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0
+            .next()
+            .map(|(name, value)| (name, Arc::make_mut(value)))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl ExactSizeIterator for IterMut<'_> {}
+
+/// An iterator that moves the properties out of a [`PropertyMap`].
+#[derive(Debug)]
+pub struct IntoIter(hash_map::IntoIter<String, Arc<Variant>>);
+
+impl Iterator for IntoIter {
+    type Item = (String, Variant);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0
+            .next()
+            .map(|(name, value)| (name, unwrap_value(value)))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl ExactSizeIterator for IntoIter {}
+
+impl<'a> IntoIterator for &'a PropertyMap {
+    type Item = (&'a String, &'a Variant);
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut PropertyMap {
+    type Item = (&'a String, &'a mut Variant);
+    type IntoIter = IterMut<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl IntoIterator for PropertyMap {
+    type Item = (String, Variant);
+    type IntoIter = IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        let map = Arc::try_unwrap(self.0).unwrap_or_else(|shared| (*shared).clone());
+        IntoIter(map.into_iter())
+    }
+}
+
+/// Makes equal property values share one copy of them.
+///
+/// Maps built from tiles or copies of the same model often hold the same
+/// values over and over, like the same Color3 or Material on thousands of
+/// parts that are all in different places. Interning keeps only one copy of
+/// each distinct value in memory, and lets [`PropertyMap::value_ptr_eq`]
+/// tell that two values are equal without comparing them. Instances whose
+/// properties are all the same share one map as well, which
+/// [`PropertyMap::ptr_eq`] can tell. See also
+/// [`WeakDom::intern_properties`][crate::WeakDom::intern_properties].
+///
+/// ```
+/// use rbx_dom_weak::{types::Vector3, PropertyInterner, PropertyMap};
+///
+/// let part = |x| -> PropertyMap {
+///     vec![
+///         ("Anchored".to_owned(), true.into()),
+///         ("Position".to_owned(), Vector3::new(x, 0.0, 0.0).into()),
+///     ]
+///     .into_iter()
+///     .collect()
+/// };
+/// let (mut first, mut second) = (part(0.0), part(1.0));
+///
+/// let mut interner = PropertyInterner::new();
+/// assert_eq!(interner.intern(&mut first), 0);
+/// assert_eq!(interner.intern(&mut second), 1);
+/// assert!(first.value_ptr_eq(&second, "Anchored"));
+/// assert!(!first.value_ptr_eq(&second, "Position"));
+/// ```
+#[derive(Debug, Default)]
+pub struct PropertyInterner {
+    values: HashMap<u64, Vec<Arc<Variant>>>,
+    maps: HashMap<u64, Vec<PropertyMap>>,
+}
+
+impl PropertyInterner {
+    /// Creates an interner that has not seen any values yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces the values in `properties` with shared copies of equal values
+    /// that were interned before, returning how many were replaced. Values
+    /// that haven't been seen before are remembered for the maps that come
+    /// after it.
+    ///
+    /// If every property is the same as in a map that was interned before,
+    /// `properties` is replaced with a shared copy of that map too.
+    pub fn intern(&mut self, properties: &mut PropertyMap) -> usize {
+        let mut replaced = 0;
+
+        // The map is only copied if one of its values is replaced, so that
+        // interning a map that's already shared doesn't unshare it.
+        let needs_replacing: Vec<(String, Arc<Variant>)> = properties
+            .0
+            .iter()
+            .filter_map(|(name, value)| {
+                let existing = self.intern_value(value);
+                (!Arc::ptr_eq(&existing, value)).then(|| (name.clone(), existing))
+            })
+            .collect();
+
+        if !needs_replacing.is_empty() {
+            let map = Arc::make_mut(&mut properties.0);
+            for (name, value) in needs_replacing {
+                map.insert(name, value);
+                replaced += 1;
+            }
+        }
+
+        let mut hasher = StableHasher::new();
+        properties.hash_stable(&mut hasher);
+        let candidates = self.maps.entry(hasher.finish()).or_default();
+
+        match candidates.iter().find(|candidate| *candidate == properties) {
+            Some(existing) => *properties = existing.clone(),
+            None => candidates.push(properties.clone()),
+        }
+
+        replaced
+    }
+
+    /// Returns the shared copy of a value equal to `value`, remembering
+    /// `value` if there isn't one yet.
+    fn intern_value(&mut self, value: &Arc<Variant>) -> Arc<Variant> {
+        let mut hasher = StableHasher::new();
+        value.hash_stable(&mut hasher);
+
+        let candidates = self.values.entry(hasher.finish()).or_default();
+        match candidates.iter().find(|candidate| ***candidate == **value) {
+            Some(existing) => Arc::clone(existing),
+            None => {
+                candidates.push(Arc::clone(value));
+                Arc::clone(value)
+            }
+        }
+    }
+
+    /// Returns how many distinct values have been interned.
+    pub fn len(&self) -> usize {
+        self.values.values().map(Vec::len).sum()
+    }
+
+    /// Tells whether no values have been interned yet.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
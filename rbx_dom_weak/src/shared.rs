@@ -0,0 +1,151 @@
+use std::sync::{Arc, RwLock};
+
+use crate::WeakDom;
+
+/// A [`WeakDom`] that many threads can read from while others occasionally
+/// change it, like the DOM behind a multi-threaded HTTP server.
+///
+/// Readers take a snapshot of the DOM with [`SharedDom::snapshot`], which
+/// only clones an `Arc`, and keep reading it for as long as they like without
+/// holding a lock. Writers change the DOM with [`SharedDom::write`]. If any
+/// snapshot of the current DOM is still alive, the writer changes a copy of
+/// it instead, so snapshots never see a change happen. Copies share their
+/// properties and extensions, like [`FileMetadata`][crate::FileMetadata],
+/// with the DOM they were copied from, so this only costs as much as copying
+/// the hierarchy.
+///
+/// Cloning a `SharedDom` gives another handle to the same DOM.
+///
+/// ```
+/// use rbx_dom_weak::{InstanceBuilder, SharedDom, WeakDom};
+///
+/// let shared = SharedDom::new(WeakDom::new(InstanceBuilder::new("DataModel")));
+///
+/// let before = shared.snapshot();
+/// shared.write(|dom| {
+///     let root_ref = dom.root_ref();
+///     dom.insert(root_ref, InstanceBuilder::new("Workspace"));
+/// });
+///
+/// assert!(before.root().children().is_empty());
+/// assert_eq!(shared.snapshot().root().children().len(), 1);
+/// ```
+#[derive(Debug, Clone)]
+pub struct SharedDom {
+    inner: Arc<RwLock<Arc<WeakDom>>>,
+}
+
+impl SharedDom {
+    /// Wraps a DOM so that it can be shared between threads.
+    pub fn new(dom: WeakDom) -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(Arc::new(dom))),
+        }
+    }
+
+    /// Returns the DOM as it is right now. Changes made after this is called
+    /// aren't visible through the snapshot.
+    ///
+    /// ## Panics
+    /// Panics if a call to [`SharedDom::write`] panicked.
+    pub fn snapshot(&self) -> Arc<WeakDom> {
+        Arc::clone(&self.inner.read().unwrap())
+    }
+
+    /// Calls `read` with the DOM as it is right now, holding a read lock
+    /// until it returns. Writers wait for the lock, so `read` should be
+    /// quick. Use [`SharedDom::snapshot`] for reads that take a while.
+    ///
+    /// ## Panics
+    /// Panics if a call to [`SharedDom::write`] panicked.
+    pub fn read<R, F: FnOnce(&WeakDom) -> R>(&self, read: F) -> R {
+        read(&self.inner.read().unwrap())
+    }
+
+    /// Calls `write` with the DOM for changing it, holding a write lock
+    /// until it returns. If a snapshot of the DOM is alive, `write` is given
+    /// a copy of it, which replaces it once `write` returns.
+    ///
+    /// ## Panics
+    /// Panics if a previous call to `write` panicked.
+    pub fn write<R, F: FnOnce(&mut WeakDom) -> R>(&self, write: F) -> R {
+        let mut dom = self.inner.write().unwrap();
+        write(Arc::make_mut(&mut dom))
+    }
+}
+
+impl From<WeakDom> for SharedDom {
+    fn from(dom: WeakDom) -> Self {
+        Self::new(dom)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::thread;
+
+    use crate::{FileMetadata, InstanceBuilder};
+
+    #[test]
+    fn readers_and_writers() {
+        let shared = SharedDom::new(WeakDom::new(InstanceBuilder::new("Folder")));
+
+        let writers: Vec<_> = (0..4)
+            .map(|_| {
+                let shared = shared.clone();
+                thread::spawn(move || {
+                    for _ in 0..100 {
+                        let snapshot = shared.snapshot();
+                        let children = snapshot.root().children().len();
+
+                        shared.write(|dom| {
+                            let root_ref = dom.root_ref();
+                            dom.insert(root_ref, InstanceBuilder::new("Part"));
+                        });
+
+                        // Writes made after the snapshot was taken aren't
+                        // visible through it.
+                        assert_eq!(snapshot.root().children().len(), children);
+                    }
+                })
+            })
+            .collect();
+
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        assert_eq!(shared.read(|dom| dom.root().children().len()), 400);
+
+        // Without any snapshots alive, writes don't copy the DOM.
+        let before = Arc::as_ptr(&shared.snapshot());
+        shared.write(|dom| dom.root_mut().name = "Changed".to_owned());
+        assert_eq!(Arc::as_ptr(&shared.snapshot()), before);
+    }
+
+    #[test]
+    fn write_keeps_extensions() {
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let mut metadata = FileMetadata::default();
+        metadata
+            .entries
+            .insert("ExplicitAutoJoints".to_owned(), "true".to_owned());
+        dom.insert_extension(dom.root_ref(), metadata.clone());
+
+        let shared = SharedDom::new(dom);
+        let snapshot = shared.snapshot();
+
+        // The snapshot is alive, so this writes to a copy of the DOM.
+        shared.write(|dom| {
+            let root_ref = dom.root_ref();
+            dom.insert(root_ref, InstanceBuilder::new("Workspace"));
+        });
+
+        let after = shared.snapshot();
+        assert!(!Arc::ptr_eq(&snapshot, &after));
+        assert_eq!(after.extension(after.root_ref()), Some(&metadata));
+        assert_eq!(snapshot.extension(snapshot.root_ref()), Some(&metadata));
+    }
+}
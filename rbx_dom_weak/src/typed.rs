@@ -0,0 +1,585 @@
+//! Typed access to instance properties.
+
+use std::convert::TryFrom;
+
+use rbx_reflection::{ClassDescriptor, ReflectionDatabase};
+use rbx_types::{
+    Attributes, Axes, BinaryString, BrickColor, CFrame, Color3, Color3uint8, ColorSequence,
+    Content, Enum, Faces, Font, NumberRange, NumberSequence, PhysicalProperties, Ray, Rect, Ref,
+    Region3, Region3int16, SharedString, Tags, UDim, UDim2, UniqueId, Variant, VariantType,
+    Vector2, Vector2int16, Vector3, Vector3int16,
+};
+use thiserror::Error;
+
+/// Implemented for every type that can be stored in a [`Variant`], allowing it
+/// to be used with [`Instance::get_typed`][crate::Instance::get_typed] and
+/// [`Instance::set_typed`][crate::Instance::set_typed].
+pub trait PropertyType: Into<Variant> + Sized {
+    /// The `VariantType` that values of this type are stored as.
+    const VARIANT_TYPE: VariantType;
+
+    /// Extracts a value of this type from a `Variant`, returning `None` if the
+    /// `Variant` holds a different type. No coercion is performed.
+    fn from_variant(value: Variant) -> Option<Self>;
+}
+
+macro_rules! property_types {
+    ( $( $variant_name:ident ($inner_type:ty), )* ) => {
+        $(
+            impl PropertyType for $inner_type {
+                const VARIANT_TYPE: VariantType = VariantType::$variant_name;
+
+                fn from_variant(value: Variant) -> Option<Self> {
+                    Self::try_from(value).ok()
+                }
+            }
+        )*
+    };
+}
+
+property_types! {
+    Axes(Axes),
+    BinaryString(BinaryString),
+    Bool(bool),
+    BrickColor(BrickColor),
+    CFrame(CFrame),
+    Color3(Color3),
+    Color3uint8(Color3uint8),
+    ColorSequence(ColorSequence),
+    Content(Content),
+    Enum(Enum),
+    Faces(Faces),
+    Float32(f32),
+    Float64(f64),
+    Int32(i32),
+    Int64(i64),
+    NumberRange(NumberRange),
+    NumberSequence(NumberSequence),
+    PhysicalProperties(PhysicalProperties),
+    Ray(Ray),
+    Rect(Rect),
+    Ref(Ref),
+    Region3(Region3),
+    Region3int16(Region3int16),
+    SharedString(SharedString),
+    String(String),
+    UDim(UDim),
+    UDim2(UDim2),
+    Vector2(Vector2),
+    Vector2int16(Vector2int16),
+    Vector3(Vector3),
+    Vector3int16(Vector3int16),
+    OptionalCFrame(Option<CFrame>),
+    Tags(Tags),
+    Attributes(Attributes),
+    Font(Font),
+    UniqueId(UniqueId),
+}
+
+/// Defines a struct with strongly-typed fields that mirror properties of a
+/// Roblox class.
+///
+/// The generated struct has public fields, a `CLASS_NAME` constant, and these
+/// methods:
+/// - `from_instance(&Instance) -> Result<Self, PropertyError>`, which reads
+///   every field using [`Instance::get_typed`][crate::Instance::get_typed].
+/// - `apply_to_instance(&self, &mut Instance) -> Result<(), PropertyError>`,
+///   which writes every field using
+///   [`Instance::set_typed`][crate::Instance::set_typed].
+/// - `from_instance_checked(&Instance, &ReflectionDatabase) -> Result<Self,
+///   PropertyError>`, which also checks that the instance is of the class or
+///   one of its subclasses with [`Instance::is_a`][crate::Instance::is_a],
+///   and reads properties the instance doesn't have from the class's defaults
+///   with [`Instance::get_typed_or_default`][crate::Instance::get_typed_or_default].
+/// - `to_builder(&self) -> InstanceBuilder`, which creates a new instance of
+///   the class with every field set.
+///
+/// `from_instance` doesn't check the instance's ClassName, so that it works
+/// without a reflection database.
+///
+/// ```
+/// use rbx_dom_weak::{rbx_class, InstanceBuilder, WeakDom};
+/// use rbx_dom_weak::types::Vector3;
+///
+/// rbx_class! {
+///     /// A subset of the properties of a Part.
+///     #[derive(Debug, PartialEq)]
+///     pub struct Part: "Part" {
+///         pub size: Vector3 => "Size",
+///         pub anchored: bool => "Anchored",
+///     }
+/// }
+///
+/// let part = Part {
+///     size: Vector3::new(4.0, 1.0, 2.0),
+///     anchored: true,
+/// };
+///
+/// let dom = WeakDom::new(part.to_builder());
+/// assert_eq!(Part::from_instance(dom.root())?, part);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[macro_export]
+macro_rules! rbx_class {
+    (
+        $( #[$struct_attr:meta] )*
+        $struct_vis:vis struct $struct_name:ident : $class_name:literal {
+            $(
+                $( #[$field_attr:meta] )*
+                $field_vis:vis $field_name:ident : $field_type:ty => $property_name:literal
+            ),* $(,)?
+        }
+    ) => {
+        $( #[$struct_attr] )*
+        $struct_vis struct $struct_name {
+            $(
+                $( #[$field_attr] )*
+                $field_vis $field_name: $field_type,
+            )*
+        }
+
+        impl $struct_name {
+            /// The ClassName of instances described by this type.
+            pub const CLASS_NAME: &'static str = $class_name;
+
+            /// Reads every field of this type from the given instance.
+            pub fn from_instance(
+                instance: &$crate::Instance,
+            ) -> ::std::result::Result<Self, $crate::PropertyError> {
+                ::std::result::Result::Ok(Self {
+                    $(
+                        $field_name: instance.get_typed::<$field_type>($property_name)?,
+                    )*
+                })
+            }
+
+            /// Reads every field of this type from the given instance, which
+            /// must be of this class or one of its subclasses. Properties the
+            /// instance doesn't have are read from the class's defaults.
+            pub fn from_instance_checked(
+                instance: &$crate::Instance,
+                database: &$crate::__reflection::ReflectionDatabase,
+            ) -> ::std::result::Result<Self, $crate::PropertyError> {
+                if !instance.is_a($class_name, database) {
+                    return ::std::result::Result::Err($crate::PropertyError::WrongClass {
+                        expected: ::std::borrow::ToOwned::to_owned($class_name),
+                        actual: ::std::clone::Clone::clone(&instance.class),
+                    });
+                }
+
+                ::std::result::Result::Ok(Self {
+                    $(
+                        $field_name: instance
+                            .get_typed_or_default::<$field_type>($property_name, database)?,
+                    )*
+                })
+            }
+
+            /// Writes every field of this type to the given instance.
+            pub fn apply_to_instance(
+                &self,
+                instance: &mut $crate::Instance,
+            ) -> ::std::result::Result<(), $crate::PropertyError> {
+                $(
+                    instance.set_typed($property_name, ::std::clone::Clone::clone(&self.$field_name))?;
+                )*
+                ::std::result::Result::Ok(())
+            }
+
+            /// Creates an `InstanceBuilder` of this class with every field
+            /// set.
+            pub fn to_builder(&self) -> $crate::InstanceBuilder {
+                $crate::InstanceBuilder::new($class_name)
+                    $(
+                        .with_property($property_name, ::std::clone::Clone::clone(&self.$field_name))
+                    )*
+            }
+        }
+    };
+}
+
+/// Converts `value` to the given type if it's already of that type or if there
+/// is an unsurprising conversion available.
+///
+/// Supported conversions are:
+/// - Between `Int32`, `Int64`, `Float32`, and `Float64`. Integers are only
+///   converted if they're representable exactly in the target type, so floats
+///   are never truncated to ints, and ints too large to be represented exactly
+///   as a float, like `16_777_217` as a `Float32`, aren't rounded.
+/// - From `Float64` to `Float32`, rounding to the nearest `Float32`, like
+///   `0.1` becomes `0.1f32`. Values too large for a `Float32`, which would
+///   become infinite, aren't converted.
+/// - Between `Color3` and `Color3uint8`. Converting to `Color3uint8` rounds
+///   each component to the nearest of its 256 steps, clamping components
+///   outside of `0.0..=1.0`.
+/// - Between `String` and `Content`.
+/// - From `CFrame` to `OptionalCFrame`.
+/// - From `Int32` to `BrickColor`, using the BrickColor's number.
+pub fn coerce_variant(value: Variant, target: VariantType) -> Option<Variant> {
+    if value.ty() == target {
+        return Some(value);
+    }
+
+    let converted = match (value, target) {
+        (Variant::Int32(value), VariantType::Int64) => i64::from(value).into(),
+        (Variant::Int32(value), VariantType::Float32) => int_to_f32(value.into())?.into(),
+        (Variant::Int32(value), VariantType::Float64) => f64::from(value).into(),
+        (Variant::Int64(value), VariantType::Int32) => i32::try_from(value).ok()?.into(),
+        (Variant::Int64(value), VariantType::Float32) => int_to_f32(value)?.into(),
+        (Variant::Int64(value), VariantType::Float64) => int_to_f64(value)?.into(),
+        (Variant::Float32(value), VariantType::Float64) => f64::from(value).into(),
+        (Variant::Float64(value), VariantType::Float32) => f64_to_f32(value)?.into(),
+
+        (Variant::Color3(value), VariantType::Color3uint8) => Color3uint8::from(value).into(),
+        (Variant::Color3uint8(value), VariantType::Color3) => Color3::from(value).into(),
+
+        (Variant::String(value), VariantType::Content) => Content::from(value).into(),
+        (Variant::Content(value), VariantType::String) => value.into_string().into(),
+
+        (Variant::CFrame(value), VariantType::OptionalCFrame) => Some(value).into(),
+
+        (Variant::Int32(value), VariantType::BrickColor) => {
+            BrickColor::from_number(u16::try_from(value).ok()?)?.into()
+        }
+
+        _ => return None,
+    };
+
+    Some(converted)
+}
+
+/// Converts an integer to an `f32`, returning `None` if it can't be
+/// represented exactly. Comparing as `i128` catches values that round up to
+/// 2^63, which would otherwise saturate back to `i64::MAX`.
+fn int_to_f32(value: i64) -> Option<f32> {
+    let converted = value as f32;
+    (converted as i128 == i128::from(value)).then_some(converted)
+}
+
+/// Converts an integer to an `f64`, returning `None` if it can't be
+/// represented exactly.
+fn int_to_f64(value: i64) -> Option<f64> {
+    let converted = value as f64;
+    (converted as i128 == i128::from(value)).then_some(converted)
+}
+
+/// Converts an `f64` to the nearest `f32`, returning `None` if it would
+/// overflow to infinity. NaN and infinities stay as they are.
+fn f64_to_f32(value: f64) -> Option<f32> {
+    let converted = value as f32;
+    (converted.is_finite() || !value.is_finite()).then_some(converted)
+}
+
+/// An error that can occur when reading or writing a property with
+/// [`Instance::get_typed`][crate::Instance::get_typed] or
+/// [`Instance::set_typed`][crate::Instance::set_typed], or setting properties
+/// through a [`WeakDom`][crate::WeakDom].
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[non_exhaustive]
+pub enum PropertyError {
+    /// The instance does not have the requested property.
+    #[error("instance of class {class} has no property named {property}")]
+    Missing {
+        /// The ClassName of the instance.
+        class: String,
+        /// The name of the requested property.
+        property: String,
+    },
+
+    /// The property exists, but holds a type that can't be converted to the
+    /// requested one.
+    #[error(
+        "property {class}.{property} is of type {actual:?}, which cannot be converted to {expected:?}"
+    )]
+    WrongType {
+        /// The ClassName of the instance.
+        class: String,
+        /// The name of the requested property.
+        property: String,
+        /// The type the caller asked for.
+        expected: VariantType,
+        /// The type that the property actually has.
+        actual: VariantType,
+    },
+
+    /// The instance is not of the requested class or one of its subclasses.
+    #[error("expected an instance of class {expected}, but got a {actual}")]
+    WrongClass {
+        /// The class that was requested.
+        expected: String,
+        /// The ClassName of the instance.
+        actual: String,
+    },
+
+    /// A path given to
+    /// [`WeakDom::instantiate_template`][crate::WeakDom::instantiate_template]
+    /// does not refer to an instance in the template.
+    #[error("template has no instance at path {path:?}")]
+    NoInstanceAtPath {
+        /// The path that was given.
+        path: String,
+    },
+}
+
+/// Returns the descriptor of the given class followed by those of its
+/// superclasses, stopping early if the database is missing one or they form
+/// a cycle.
+pub(crate) fn class_chain<'a>(
+    database: &'a ReflectionDatabase,
+    class_name: &str,
+) -> Vec<&'a ClassDescriptor<'a>> {
+    let mut chain = Vec::new();
+    let mut next = database.classes.get(class_name);
+
+    while let Some(class) = next {
+        if chain.len() > database.classes.len() {
+            break;
+        }
+
+        chain.push(class);
+        next = class
+            .superclass
+            .as_ref()
+            .and_then(|superclass| database.classes.get(superclass.as_ref()));
+    }
+
+    chain
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::{InstanceBuilder, WeakDom};
+
+    #[test]
+    fn get_typed() {
+        let dom = WeakDom::new(
+            InstanceBuilder::new("Part")
+                .with_property("Size", Vector3::new(4.0, 1.0, 2.0))
+                .with_property("Transparency", 1i32)
+                .with_property("Color", Color3uint8::new(255, 0, 0)),
+        );
+        let part = dom.root();
+
+        assert_eq!(
+            part.get_typed::<Vector3>("Size"),
+            Ok(Vector3::new(4.0, 1.0, 2.0))
+        );
+        assert_eq!(part.get_typed::<f32>("Transparency"), Ok(1.0));
+        assert_eq!(
+            part.get_typed::<Color3>("Color"),
+            Ok(Color3::new(1.0, 0.0, 0.0))
+        );
+
+        assert_eq!(
+            part.get_typed::<bool>("Anchored"),
+            Err(PropertyError::Missing {
+                class: "Part".to_owned(),
+                property: "Anchored".to_owned(),
+            })
+        );
+        assert_eq!(
+            part.get_typed::<String>("Size"),
+            Err(PropertyError::WrongType {
+                class: "Part".to_owned(),
+                property: "Size".to_owned(),
+                expected: VariantType::String,
+                actual: VariantType::Vector3,
+            })
+        );
+    }
+
+    #[test]
+    fn set_typed() {
+        let mut dom =
+            WeakDom::new(InstanceBuilder::new("Part").with_property("Reflectance", 0.0f64));
+        let part = dom.root_mut();
+
+        // New properties are stored as-is.
+        part.set_typed("Anchored", true).unwrap();
+        assert_eq!(part.properties.get("Anchored"), Some(&Variant::Bool(true)));
+
+        // Existing properties keep their type when a conversion is available.
+        part.set_typed("Reflectance", 0.5f32).unwrap();
+        assert_eq!(
+            part.properties.get("Reflectance"),
+            Some(&Variant::Float64(0.5))
+        );
+
+        assert!(part.set_typed("Reflectance", "shiny").is_err());
+    }
+
+    rbx_class! {
+        #[derive(Debug, PartialEq)]
+        struct StringValue: "StringValue" {
+            value: String => "Value",
+        }
+    }
+
+    #[test]
+    fn rbx_class_round_trip() {
+        let mut dom = WeakDom::new(InstanceBuilder::new("StringValue"));
+
+        assert_eq!(
+            StringValue::from_instance(dom.root()),
+            Err(PropertyError::Missing {
+                class: "StringValue".to_owned(),
+                property: "Value".to_owned(),
+            })
+        );
+
+        let value = StringValue {
+            value: "Hello".to_owned(),
+        };
+        value.apply_to_instance(dom.root_mut()).unwrap();
+
+        assert_eq!(StringValue::from_instance(dom.root()).as_ref(), Ok(&value));
+        assert_eq!(StringValue::CLASS_NAME, "StringValue");
+
+        let built = WeakDom::new(value.to_builder());
+        assert_eq!(built.root().class, "StringValue");
+        assert_eq!(StringValue::from_instance(built.root()), Ok(value));
+    }
+
+    #[test]
+    fn rbx_class_checked() {
+        use rbx_reflection::ClassDescriptor;
+
+        let mut string_value = ClassDescriptor::new("StringValue");
+        string_value.superclass = Some("ValueBase".into());
+        string_value
+            .default_properties
+            .insert("Value".into(), Variant::String(String::new()));
+
+        let mut database = ReflectionDatabase::new();
+        database
+            .classes
+            .insert("ValueBase".into(), ClassDescriptor::new("ValueBase"));
+        database.classes.insert("StringValue".into(), string_value);
+
+        let dom = WeakDom::new(InstanceBuilder::new("StringValue"));
+        assert!(dom.root().is_a("ValueBase", &database));
+        assert!(!dom.root().is_a("Folder", &database));
+        assert_eq!(
+            StringValue::from_instance_checked(dom.root(), &database),
+            Ok(StringValue {
+                value: String::new(),
+            })
+        );
+
+        let folder = WeakDom::new(InstanceBuilder::new("Folder"));
+        assert_eq!(
+            StringValue::from_instance_checked(folder.root(), &database),
+            Err(PropertyError::WrongClass {
+                expected: "StringValue".to_owned(),
+                actual: "Folder".to_owned(),
+            })
+        );
+    }
+
+    #[test]
+    fn int_narrowing() {
+        assert_eq!(
+            coerce_variant(Variant::Int64(5), VariantType::Int32),
+            Some(Variant::Int32(5))
+        );
+        assert_eq!(
+            coerce_variant(Variant::Int64(i64::MAX), VariantType::Int32),
+            None
+        );
+        assert_eq!(
+            coerce_variant(Variant::Float32(1.5), VariantType::Int32),
+            None
+        );
+    }
+
+    #[test]
+    fn float_precision() {
+        assert_eq!(
+            coerce_variant(Variant::Int32(16_777_216), VariantType::Float32),
+            Some(Variant::Float32(16_777_216.0))
+        );
+        assert_eq!(
+            coerce_variant(Variant::Int32(16_777_217), VariantType::Float32),
+            None
+        );
+        assert_eq!(
+            coerce_variant(Variant::Int32(i32::MAX), VariantType::Float32),
+            None
+        );
+        assert_eq!(
+            coerce_variant(Variant::Int64(1 << 53), VariantType::Float64),
+            Some(Variant::Float64(9_007_199_254_740_992.0))
+        );
+        assert_eq!(
+            coerce_variant(Variant::Int64((1 << 53) + 1), VariantType::Float64),
+            None
+        );
+        assert_eq!(
+            coerce_variant(Variant::Int64(i64::MAX), VariantType::Float64),
+            None
+        );
+        assert_eq!(
+            coerce_variant(Variant::Int64(i64::MIN), VariantType::Float32),
+            Some(Variant::Float32(i64::MIN as f32))
+        );
+
+        assert_eq!(
+            coerce_variant(Variant::Float64(0.5), VariantType::Float32),
+            Some(Variant::Float32(0.5))
+        );
+        assert_eq!(
+            coerce_variant(Variant::Float64(0.1), VariantType::Float32),
+            Some(Variant::Float32(0.1))
+        );
+        assert_eq!(
+            coerce_variant(Variant::Float64(-f64::from(f32::MAX)), VariantType::Float32),
+            Some(Variant::Float32(f32::MIN))
+        );
+        assert_eq!(
+            coerce_variant(Variant::Float64(1e300), VariantType::Float32),
+            None
+        );
+        assert_eq!(
+            coerce_variant(Variant::Float64(f64::INFINITY), VariantType::Float32),
+            Some(Variant::Float32(f32::INFINITY))
+        );
+        assert!(matches!(
+            coerce_variant(Variant::Float64(f64::NAN), VariantType::Float32),
+            Some(Variant::Float32(value)) if value.is_nan()
+        ));
+    }
+
+    #[test]
+    fn color_rounding() {
+        assert_eq!(
+            coerce_variant(
+                Variant::Color3(Color3::new(0.5, 0.1, 1.5)),
+                VariantType::Color3uint8
+            ),
+            Some(Variant::Color3uint8(Color3uint8::new(128, 26, 255)))
+        );
+        assert_eq!(
+            coerce_variant(
+                Variant::Color3uint8(Color3uint8::new(128, 0, 255)),
+                VariantType::Color3
+            ),
+            Some(Variant::Color3(Color3::new(128.0 / 255.0, 0.0, 1.0)))
+        );
+    }
+
+    #[test]
+    fn set_typed_rounds_floats() {
+        let mut dom =
+            WeakDom::new(InstanceBuilder::new("Part").with_property("Transparency", 0.0f32));
+        let part = dom.root_ref();
+
+        dom.update_properties(part, [("Transparency", 0.1f64)])
+            .unwrap();
+        assert_eq!(
+            dom.root().properties.get("Transparency"),
+            Some(&Variant::Float32(0.1))
+        );
+    }
+}
@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+
+use rbx_types::Ref;
+
+use crate::{Cancelled, EqualityOptions, Instance, TreeVisitor, WeakDom};
+
+/// A read-only view of a [`WeakDom`], made with [`WeakDom::view`].
+///
+/// Views only have the methods of `WeakDom` that read from it, so they can be
+/// handed to plugins and other code that should be able to look at a DOM but
+/// not change it. They're `Copy` and everything they return borrows from the
+/// DOM instead of the view, so they can be passed around freely.
+#[derive(Debug, Clone, Copy)]
+pub struct WeakDomView<'a> {
+    dom: &'a WeakDom,
+}
+
+impl<'a> WeakDomView<'a> {
+    /// Returns the referent of the root instance of the DOM.
+    #[inline]
+    pub fn root_ref(self) -> Ref {
+        self.dom.root_ref()
+    }
+
+    /// Returns the root instance of the DOM.
+    #[inline]
+    pub fn root(self) -> &'a Instance {
+        self.dom.root()
+    }
+
+    /// Returns an instance by referent, or `None` if it is not found.
+    #[inline]
+    pub fn get_by_ref(self, referent: Ref) -> Option<&'a Instance> {
+        self.dom.get_by_ref(referent)
+    }
+
+    /// Returns the metadata attached to the instance with the given referent,
+    /// if there is any.
+    #[inline]
+    pub fn metadata(self, referent: Ref) -> Option<&'a HashMap<String, String>> {
+        self.dom.metadata(referent)
+    }
+
+    /// See [`WeakDom::generation`].
+    #[inline]
+    pub fn generation(self) -> u64 {
+        self.dom.generation()
+    }
+
+    /// See [`WeakDom::descendant_count`].
+    #[inline]
+    pub fn descendant_count(self, referent: Ref) -> usize {
+        self.dom.descendant_count(referent)
+    }
+
+    /// See [`WeakDom::subtree_hash`].
+    #[inline]
+    pub fn subtree_hash(self, referent: Ref) -> u64 {
+        self.dom.subtree_hash(referent)
+    }
+
+    /// See [`WeakDom::subtree_hashes`].
+    #[inline]
+    pub fn subtree_hashes(self, referent: Ref) -> HashMap<Ref, u64> {
+        self.dom.subtree_hashes(referent)
+    }
+
+    /// See [`WeakDom::semantically_equals`].
+    #[inline]
+    pub fn semantically_equals(
+        self,
+        other: WeakDomView<'_>,
+        options: &EqualityOptions,
+    ) -> Result<bool, Cancelled> {
+        self.dom.semantically_equals(other.dom, options)
+    }
+
+    /// See [`WeakDom::walk`].
+    #[inline]
+    pub fn walk<V: TreeVisitor>(self, referent: Ref, visitor: &mut V) {
+        self.dom.walk(referent, visitor)
+    }
+
+    /// See [`WeakDom::par_descendants`].
+    #[cfg(feature = "rayon")]
+    #[inline]
+    pub fn par_descendants(
+        self,
+        referent: Ref,
+    ) -> impl rayon::iter::IndexedParallelIterator<Item = &'a Instance> {
+        self.dom.par_descendants(referent)
+    }
+}
+
+impl<'a> From<&'a WeakDom> for WeakDomView<'a> {
+    #[inline]
+    fn from(dom: &'a WeakDom) -> Self {
+        Self { dom }
+    }
+}
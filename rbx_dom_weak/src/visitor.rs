@@ -0,0 +1,50 @@
+use crate::{Instance, WeakDom};
+
+/// Receives callbacks as [`WeakDom::walk`] moves through a tree of instances.
+///
+/// `enter_instance` is called on an instance before any of its descendants,
+/// and `exit_instance` is called on it after all of them, so visitors that
+/// need to know how deep they are or which instance contains the current one
+/// can keep track without managing a stack of their own.
+///
+/// ```
+/// use rbx_dom_weak::{Instance, InstanceBuilder, TreeVisitor, WeakDom};
+///
+/// struct Printer {
+///     depth: usize,
+///     output: String,
+/// }
+///
+/// impl TreeVisitor for Printer {
+///     fn enter_instance(&mut self, _dom: &WeakDom, instance: &Instance) {
+///         self.output += &format!("{}{}\n", "  ".repeat(self.depth), instance.name);
+///         self.depth += 1;
+///     }
+///
+///     fn exit_instance(&mut self, _dom: &WeakDom, _instance: &Instance) {
+///         self.depth -= 1;
+///     }
+/// }
+///
+/// let dom = WeakDom::new(
+///     InstanceBuilder::new("Folder")
+///         .with_name("Root")
+///         .with_child(InstanceBuilder::new("Part").with_name("Child")),
+/// );
+///
+/// let mut printer = Printer { depth: 0, output: String::new() };
+/// dom.walk(dom.root_ref(), &mut printer);
+///
+/// assert_eq!(printer.output, "Root\n  Child\n");
+/// ```
+pub trait TreeVisitor {
+    /// Called when the walk reaches an instance, before any of its children.
+    fn enter_instance(&mut self, dom: &WeakDom, instance: &Instance) {
+        let _ = (dom, instance);
+    }
+
+    /// Called when the walk leaves an instance, after all of its children.
+    fn exit_instance(&mut self, dom: &WeakDom, instance: &Instance) {
+        let _ = (dom, instance);
+    }
+}
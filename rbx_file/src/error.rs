@@ -0,0 +1,37 @@
+use std::io;
+
+use thiserror::Error;
+
+/// An error that can occur when decoding a model or place.
+#[derive(Debug, Error)]
+pub enum DecodeError {
+    /// The contents didn't look like a binary or XML model or place.
+    #[error("contents are not a Roblox model or place in a known format")]
+    UnknownFormat,
+
+    /// Reading the contents failed.
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    /// The contents looked like a binary model or place, but could not be
+    /// decoded.
+    #[error(transparent)]
+    Binary(#[from] rbx_binary::DecodeError),
+
+    /// The contents looked like an XML model or place, but could not be
+    /// decoded.
+    #[error(transparent)]
+    Xml(#[from] rbx_xml::DecodeError),
+}
+
+/// An error that can occur when encoding a model or place.
+#[derive(Debug, Error)]
+pub enum EncodeError {
+    /// The instances could not be encoded as a binary model or place.
+    #[error(transparent)]
+    Binary(#[from] rbx_binary::EncodeError),
+
+    /// The instances could not be encoded as an XML model or place.
+    #[error(transparent)]
+    Xml(#[from] rbx_xml::EncodeError),
+}
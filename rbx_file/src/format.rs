@@ -0,0 +1,112 @@
+use std::path::Path;
+
+/// The magic bytes that every binary model or place starts with.
+const BINARY_MAGIC: &[u8] = b"<roblox!";
+
+/// The file formats that Roblox models and places can be stored in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Format {
+    /// The binary format, used by `.rbxm` and `.rbxl` files.
+    Binary,
+
+    /// The XML format, used by `.rbxmx` and `.rbxlx` files.
+    Xml,
+}
+
+impl Format {
+    /// Guesses the format of a file from the start of its contents.
+    ///
+    /// Binary files always start with `<roblox!`. XML files start with a
+    /// `<roblox` root element, optionally preceded by a byte order mark,
+    /// whitespace, an XML declaration, or comments. Other XML documents aren't
+    /// models or places, so they aren't detected as either format.
+    pub fn detect(contents: &[u8]) -> Option<Format> {
+        if contents.starts_with(BINARY_MAGIC) {
+            return Some(Format::Binary);
+        }
+
+        let mut contents = contents
+            .strip_prefix(b"\xEF\xBB\xBF".as_ref())
+            .unwrap_or(contents);
+
+        loop {
+            let start = contents
+                .iter()
+                .position(|byte| !byte.is_ascii_whitespace())?;
+            contents = &contents[start..];
+
+            let (open, close): (&[u8], &[u8]) = if contents.starts_with(b"<?") {
+                (b"<?", b"?>")
+            } else if contents.starts_with(b"<!--") {
+                (b"<!--", b"-->")
+            } else {
+                break;
+            };
+
+            let end = contents[open.len()..]
+                .windows(close.len())
+                .position(|window| window == close)?;
+            contents = &contents[open.len() + end + close.len()..];
+        }
+
+        match contents.strip_prefix(b"<roblox".as_ref())?.first() {
+            Some(byte) if byte.is_ascii_whitespace() || *byte == b'>' => Some(Format::Xml),
+            _ => None,
+        }
+    }
+
+    /// Guesses the format of a file from its extension.
+    pub fn from_path(path: &Path) -> Option<Format> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("rbxm") | Some("rbxl") => Some(Format::Binary),
+            Some("rbxmx") | Some("rbxlx") => Some(Format::Xml),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn detect() {
+        assert_eq!(
+            Format::detect(b"<roblox!\x89\xff\r\n\x1a\n"),
+            Some(Format::Binary)
+        );
+        assert_eq!(Format::detect(b"<roblox version=\"4\">"), Some(Format::Xml));
+        assert_eq!(
+            Format::detect(b"\xEF\xBB\xBF\n  <roblox version=\"4\">"),
+            Some(Format::Xml)
+        );
+        assert_eq!(
+            Format::detect(b"<?xml version=\"1.0\"?><roblox version=\"4\">"),
+            Some(Format::Xml)
+        );
+
+        assert_eq!(
+            Format::detect(b"<?xml version=\"1.0\"?>\n<!-- a comment -->\n<roblox>"),
+            Some(Format::Xml)
+        );
+
+        assert_eq!(Format::detect(b"<?xml version=\"1.0\"?><svg>"), None);
+        assert_eq!(Format::detect(b"<?xml version=\"1.0\"?>"), None);
+        assert_eq!(Format::detect(b"<robloxian>"), None);
+        assert_eq!(Format::detect(b"PK\x03\x04"), None);
+        assert_eq!(Format::detect(b""), None);
+    }
+
+    #[test]
+    fn from_path() {
+        assert_eq!(
+            Format::from_path(Path::new("place.rbxl")),
+            Some(Format::Binary)
+        );
+        assert_eq!(
+            Format::from_path(Path::new("model.rbxmx")),
+            Some(Format::Xml)
+        );
+        assert_eq!(Format::from_path(Path::new("model.txt")), None);
+    }
+}
@@ -0,0 +1,167 @@
+/*!
+Reads and writes Roblox model and place files without caring which format
+they're stored in, using rbx_binary and rbx_xml.
+
+The format of a file is detected from its contents with [`Format::detect`], so
+programs that are given arbitrary files, like user uploads, can decode them
+with [`from_reader`] or [`from_slice`] without checking for themselves.
+
+```no_run
+use std::fs;
+
+let contents = fs::read("MyModel.rbxm")?;
+let dom = rbx_file::from_slice(&contents)?;
+
+println!("Root instances in file:");
+for &referent in dom.root().children() {
+    let instance = dom.get_by_ref(referent).unwrap();
+    println!("- {}", instance.name);
+}
+# Ok::<(), Box<dyn std::error::Error>>(())
+```
+
+Properties that aren't in the reflection database are kept when decoding and
+written when encoding, so that converting a file between formats loses as
+little as possible.
+*/
+
+#![deny(missing_docs)]
+
+mod error;
+mod format;
+
+use std::io::{Read, Write};
+
+use rbx_dom_weak::{types::Ref, WeakDom};
+
+pub use crate::{
+    error::{DecodeError, EncodeError},
+    format::Format,
+};
+
+/// Decodes a model or place from a stream, detecting whether it is in the
+/// binary or XML format.
+///
+/// The entire stream is read into memory before decoding starts.
+pub fn from_reader<R: Read>(mut reader: R) -> Result<WeakDom, DecodeError> {
+    let mut contents = Vec::new();
+    reader.read_to_end(&mut contents)?;
+
+    from_slice(&contents)
+}
+
+/// Decodes a model or place from a byte slice, detecting whether it is in the
+/// binary or XML format.
+pub fn from_slice(contents: &[u8]) -> Result<WeakDom, DecodeError> {
+    match Format::detect(contents) {
+        Some(format) => from_slice_with_format(contents, format),
+        None => Err(DecodeError::UnknownFormat),
+    }
+}
+
+/// Decodes a model or place from a byte slice that is known to be in the given
+/// format.
+pub fn from_slice_with_format(contents: &[u8], format: Format) -> Result<WeakDom, DecodeError> {
+    let dom = match format {
+        Format::Binary => rbx_binary::from_reader(contents)?,
+        Format::Xml => {
+            let options = rbx_xml::DecodeOptions::new()
+                .property_behavior(rbx_xml::DecodePropertyBehavior::ReadUnknown);
+
+            rbx_xml::from_reader(contents, options)?
+        }
+    };
+
+    Ok(dom)
+}
+
+/// Encodes the given instances and their descendants in the given format.
+pub fn to_vec(dom: &WeakDom, refs: &[Ref], format: Format) -> Result<Vec<u8>, EncodeError> {
+    let mut contents = Vec::new();
+    to_writer(&mut contents, dom, refs, format)?;
+
+    Ok(contents)
+}
+
+/// Encodes the given instances and their descendants in the given format,
+/// writing the result to a stream.
+pub fn to_writer<W: Write>(
+    writer: W,
+    dom: &WeakDom,
+    refs: &[Ref],
+    format: Format,
+) -> Result<(), EncodeError> {
+    match format {
+        Format::Binary => rbx_binary::to_writer(writer, dom, refs)?,
+        Format::Xml => {
+            let options = rbx_xml::EncodeOptions::new()
+                .property_behavior(rbx_xml::EncodePropertyBehavior::WriteUnknown);
+
+            rbx_xml::to_writer(writer, dom, refs, options)?
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::InstanceBuilder;
+
+    fn folder_dom() -> WeakDom {
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+        dom.insert(
+            dom.root_ref(),
+            InstanceBuilder::new("Folder").with_name("Hello"),
+        );
+        dom
+    }
+
+    fn first_child_name(dom: &WeakDom) -> &str {
+        let child = dom.root().children()[0];
+        &dom.get_by_ref(child).unwrap().name
+    }
+
+    #[test]
+    fn decode_binary() {
+        let dom = folder_dom();
+        let mut encoded = Vec::new();
+        rbx_binary::to_writer(&mut encoded, &dom, dom.root().children()).unwrap();
+
+        let decoded = from_reader(encoded.as_slice()).unwrap();
+        assert_eq!(first_child_name(&decoded), "Hello");
+    }
+
+    #[test]
+    fn decode_xml() {
+        let dom = folder_dom();
+        let mut encoded = Vec::new();
+        rbx_xml::to_writer_default(&mut encoded, &dom, dom.root().children()).unwrap();
+
+        let decoded = from_slice(&encoded).unwrap();
+        assert_eq!(first_child_name(&decoded), "Hello");
+    }
+
+    #[test]
+    fn encode_round_trip() {
+        let dom = folder_dom();
+
+        for format in [Format::Binary, Format::Xml] {
+            let encoded = to_vec(&dom, dom.root().children(), format).unwrap();
+            assert_eq!(Format::detect(&encoded), Some(format));
+
+            let decoded = from_slice(&encoded).unwrap();
+            assert_eq!(first_child_name(&decoded), "Hello");
+        }
+    }
+
+    #[test]
+    fn decode_unknown() {
+        assert!(matches!(
+            from_slice(b"definitely not a model"),
+            Err(DecodeError::UnknownFormat)
+        ));
+    }
+}
@@ -0,0 +1,406 @@
+//! Python bindings for rbx_dom_weak, rbx_binary, and rbx_xml, built with pyo3.
+//!
+//! The module is exposed to Python as `rbx_dom`. Instances are identified by
+//! their referents, which are passed back and forth as 32-character hex
+//! strings.
+//!
+//! ```python
+//! import rbx_dom
+//!
+//! with open("place.rbxl", "rb") as file:
+//!     dom = rbx_dom.decode(file.read())
+//!
+//! for child in dom.children(dom.root):
+//!     print(dom.class_name(child), dom.name(child))
+//!
+//! with open("place.rbxlx", "wb") as file:
+//!     file.write(dom.encode("xml"))
+//! ```
+//!
+//! `diff` computes the changes between two trees as a `Delta`, which can be
+//! serialized, sent somewhere else, and applied to a copy of the old tree
+//! there.
+//!
+//! ```python
+//! delta = rbx_dom.diff(old, new)
+//! updated = rbx_dom.Delta.deserialize(delta.serialize()).apply(old)
+//! ```
+
+#![deny(missing_docs)]
+
+use pyo3::{
+    exceptions::{PyTypeError, PyValueError},
+    prelude::*,
+    types::{PyBool, PyBytes, PyFloat, PyLong, PyString},
+};
+use rbx_dom_weak::{
+    types::{BinaryString, Ref, Variant},
+    Instance, InstanceBuilder, WeakDom,
+};
+use rbx_util::{DeltaError, Format, PlaceDelta};
+
+/// A tree of Roblox instances.
+#[pyclass(name = "Dom", module = "rbx_dom")]
+pub struct Dom {
+    dom: WeakDom,
+}
+
+fn parse_ref(referent: &str) -> PyResult<Ref> {
+    referent
+        .parse()
+        .map_err(|_| PyValueError::new_err(format!("{:?} is not a valid referent", referent)))
+}
+
+fn parse_format(format: &str) -> PyResult<Format> {
+    match format {
+        "binary" => Ok(Format::Binary),
+        "xml" => Ok(Format::Xml),
+        _ => Err(PyValueError::new_err(format!(
+            "unknown format {:?}, expected \"binary\" or \"xml\"",
+            format
+        ))),
+    }
+}
+
+fn variant_to_python(py: Python, value: &Variant) -> PyResult<PyObject> {
+    let object = match value {
+        Variant::Bool(value) => value.into_py(py),
+        Variant::Int32(value) => value.into_py(py),
+        Variant::Int64(value) => value.into_py(py),
+        Variant::Float32(value) => value.into_py(py),
+        Variant::Float64(value) => value.into_py(py),
+        Variant::String(value) => value.into_py(py),
+        Variant::Content(value) => AsRef::<str>::as_ref(value).into_py(py),
+        Variant::BinaryString(value) => PyBytes::new_bound(py, value.as_ref()).into_py(py),
+        Variant::Ref(value) if value.is_none() => py.None(),
+        Variant::Ref(value) => value.to_string().into_py(py),
+        other => {
+            return Err(PyTypeError::new_err(format!(
+                "properties of type {:?} can't be read from Python",
+                other.ty()
+            )))
+        }
+    };
+
+    Ok(object)
+}
+
+fn python_to_variant(value: &Bound<'_, PyAny>) -> PyResult<Variant> {
+    // bool is a subclass of int in Python, so it has to be checked first.
+    let variant = if value.is_instance_of::<PyBool>() {
+        Variant::Bool(value.extract()?)
+    } else if value.is_instance_of::<PyLong>() {
+        Variant::Int64(value.extract()?)
+    } else if value.is_instance_of::<PyFloat>() {
+        Variant::Float64(value.extract()?)
+    } else if value.is_instance_of::<PyString>() {
+        Variant::String(value.extract()?)
+    } else if let Ok(bytes) = value.downcast::<PyBytes>() {
+        Variant::BinaryString(BinaryString::from(bytes.as_bytes().to_vec()))
+    } else {
+        return Err(PyTypeError::new_err(format!(
+            "values of type {} can't be assigned to properties",
+            value.get_type().name()?
+        )));
+    };
+
+    Ok(variant)
+}
+
+impl Dom {
+    fn instance(&self, referent: &str) -> PyResult<&Instance> {
+        self.dom
+            .get_by_ref(parse_ref(referent)?)
+            .ok_or_else(|| PyValueError::new_err(format!("no instance with referent {}", referent)))
+    }
+
+    /// Parses a referent, checking that it refers to an instance in the
+    /// tree.
+    fn existing_ref(&self, referent: &str) -> PyResult<Ref> {
+        Ok(self.instance(referent)?.referent())
+    }
+}
+
+#[pymethods]
+impl Dom {
+    /// Creates a new tree with a root instance of the given class.
+    #[new]
+    fn new(class_name: &str) -> Self {
+        Dom {
+            dom: WeakDom::new(InstanceBuilder::new(class_name)),
+        }
+    }
+
+    /// The referent of the root instance.
+    #[getter]
+    fn root(&self) -> String {
+        self.dom.root_ref().to_string()
+    }
+
+    /// Inserts a new instance under `parent` and returns its referent.
+    fn insert(&mut self, parent: &str, class_name: &str, name: &str) -> PyResult<String> {
+        let parent = parse_ref(parent)?;
+        if self.dom.get_by_ref(parent).is_none() {
+            return Err(PyValueError::new_err(format!(
+                "no instance with referent {}",
+                parent
+            )));
+        }
+
+        let referent = self
+            .dom
+            .insert(parent, InstanceBuilder::new(class_name).with_name(name));
+
+        Ok(referent.to_string())
+    }
+
+    /// Destroys an instance and all of its descendants.
+    fn destroy(&mut self, referent: &str) -> PyResult<()> {
+        let referent_value = parse_ref(referent)?;
+        if referent_value == self.dom.root_ref() {
+            return Err(PyValueError::new_err(
+                "the root instance cannot be destroyed",
+            ));
+        }
+
+        self.instance(referent)?;
+        self.dom.destroy(referent_value);
+        Ok(())
+    }
+
+    /// Returns the ClassName of an instance.
+    fn class_name(&self, referent: &str) -> PyResult<String> {
+        Ok(self.instance(referent)?.class.clone())
+    }
+
+    /// Returns the Name of an instance.
+    fn name(&self, referent: &str) -> PyResult<String> {
+        Ok(self.instance(referent)?.name.clone())
+    }
+
+    /// Changes the Name of an instance. The change is recorded in the tree's
+    /// journal, and the name is cleaned up if the tree's config says to.
+    fn set_name(&mut self, referent: &str, name: String) -> PyResult<()> {
+        let referent = self.existing_ref(referent)?;
+        self.dom.edit(referent).set_name(name);
+        Ok(())
+    }
+
+    /// Returns the referent of an instance's parent, or None for the root.
+    fn parent(&self, referent: &str) -> PyResult<Option<String>> {
+        let parent = self.instance(referent)?.parent();
+        Ok(parent.is_some().then(|| parent.to_string()))
+    }
+
+    /// Returns the referents of an instance's children.
+    fn children(&self, referent: &str) -> PyResult<Vec<String>> {
+        Ok(self
+            .instance(referent)?
+            .children()
+            .iter()
+            .map(|child| child.to_string())
+            .collect())
+    }
+
+    /// Returns the value of a property, or None if it isn't set.
+    fn get_property(&self, py: Python, referent: &str, name: &str) -> PyResult<Option<PyObject>> {
+        self.instance(referent)?
+            .properties
+            .get(name)
+            .map(|value| variant_to_python(py, value))
+            .transpose()
+    }
+
+    /// Sets the value of a property. If the property is already set, the new
+    /// value is converted to its existing type. The value is checked against
+    /// the tree's config, raising ValueError if it's rejected, and the change
+    /// is recorded in the tree's journal.
+    fn set_property(
+        &mut self,
+        referent: &str,
+        name: &str,
+        value: &Bound<'_, PyAny>,
+    ) -> PyResult<()> {
+        let value = python_to_variant(value)?;
+        let referent = self.existing_ref(referent)?;
+
+        self.dom
+            .update_properties(referent, [(name, value)])
+            .map_err(|err| PyValueError::new_err(err.to_string()))
+    }
+
+    /// Encodes the children of the root instance as "binary" or "xml".
+    #[pyo3(signature = (format = "binary"))]
+    fn encode<'py>(&self, py: Python<'py>, format: &str) -> PyResult<Bound<'py, PyBytes>> {
+        let contents =
+            rbx_util::to_vec(&self.dom, self.dom.root().children(), parse_format(format)?)
+                .map_err(|err| PyValueError::new_err(err.to_string()))?;
+
+        Ok(PyBytes::new_bound(py, &contents))
+    }
+}
+
+/// The changes that turn one tree into another, computed by `diff`.
+#[pyclass(name = "Delta", module = "rbx_dom")]
+pub struct Delta {
+    delta: PlaceDelta,
+}
+
+fn delta_error(err: DeltaError) -> PyErr {
+    PyValueError::new_err(err.to_string())
+}
+
+#[pymethods]
+impl Delta {
+    /// Applies the delta to the tree it was computed from and returns the
+    /// new tree. Raises ValueError if `old` is not that tree.
+    fn apply(&self, old: &Dom) -> PyResult<Dom> {
+        let dom = self.delta.apply(&old.dom).map_err(delta_error)?;
+
+        Ok(Dom { dom })
+    }
+
+    /// Writes the delta in a compact binary format.
+    fn serialize<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new_bound(py, &self.delta.to_vec())
+    }
+
+    /// Reads a delta written by `serialize`.
+    #[staticmethod]
+    fn deserialize(data: &[u8]) -> PyResult<Self> {
+        let delta = PlaceDelta::from_slice(data).map_err(delta_error)?;
+
+        Ok(Delta { delta })
+    }
+}
+
+/// Computes the delta that turns tree `a` into tree `b`.
+#[pyfunction]
+fn diff(a: &Dom, b: &Dom) -> PyResult<Delta> {
+    let delta = PlaceDelta::between(&a.dom, &b.dom).map_err(delta_error)?;
+
+    Ok(Delta { delta })
+}
+
+/// Decodes a model or place in either the binary or XML format.
+#[pyfunction]
+fn decode(data: &[u8]) -> PyResult<Dom> {
+    let dom = rbx_util::from_slice(data).map_err(|err| PyValueError::new_err(err.to_string()))?;
+
+    Ok(Dom { dom })
+}
+
+/// The `rbx_dom` Python module.
+#[pymodule]
+fn rbx_dom(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<Dom>()?;
+    module.add_class::<Delta>()?;
+    module.add_function(wrap_pyfunction!(decode, module)?)?;
+    module.add_function(wrap_pyfunction!(diff, module)?)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use pyo3::types::IntoPyDict;
+
+    #[test]
+    fn round_trip() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let module = PyModule::new_bound(py, "rbx_dom").unwrap();
+            rbx_dom(&module).unwrap();
+
+            let locals = [("rbx_dom", &module)].into_py_dict_bound(py);
+            py.run_bound(
+                r#"
+dom = rbx_dom.Dom("DataModel")
+value = dom.insert(dom.root, "IntValue", "Hello")
+dom.set_property(value, "Value", 5)
+
+for format in ["binary", "xml"]:
+    decoded = rbx_dom.decode(dom.encode(format))
+    [child] = decoded.children(decoded.root)
+    assert decoded.name(child) == "Hello"
+    assert decoded.get_property(child, "Value") == 5
+
+dom.set_property(value, "Value", 6)
+assert dom.get_property(value, "Value") == 6
+
+try:
+    dom.set_property(value, "Value", "six")
+    assert False
+except ValueError:
+    pass
+"#,
+                None,
+                Some(&locals),
+            )
+            .unwrap();
+        });
+    }
+
+    #[test]
+    fn setters_are_journaled() {
+        pyo3::prepare_freethreaded_python();
+
+        let mut dom = Dom::new("IntValue");
+        dom.dom.enable_journal();
+        let root = dom.root();
+
+        Python::with_gil(|py| {
+            dom.set_name(&root, "Count".to_owned()).unwrap();
+            dom.set_property(&root, "Value", &5i64.into_py(py).into_bound(py))
+                .unwrap();
+        });
+
+        let entries = dom.dom.drain_journal();
+        assert_eq!(entries.len(), 2);
+        assert!(entries
+            .iter()
+            .all(|entry| matches!(entry, rbx_dom_weak::JournalEntry::Edit { .. })));
+    }
+
+    #[test]
+    fn diff() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let module = PyModule::new_bound(py, "rbx_dom").unwrap();
+            rbx_dom(&module).unwrap();
+
+            let locals = [("rbx_dom", &module)].into_py_dict_bound(py);
+            py.run_bound(
+                r#"
+old = rbx_dom.Dom("DataModel")
+value = old.insert(old.root, "IntValue", "Hello")
+old.set_property(value, "Value", 5)
+
+new = rbx_dom.decode(old.encode())
+[value] = new.children(new.root)
+new.set_property(value, "Value", 6)
+new.insert(new.root, "Folder", "Added")
+
+delta = rbx_dom.Delta.deserialize(rbx_dom.diff(old, new).serialize())
+updated = delta.apply(old)
+[value, folder] = updated.children(updated.root)
+assert updated.get_property(value, "Value") == 6
+assert updated.name(folder) == "Added"
+
+try:
+    delta.apply(new)
+    assert False
+except ValueError:
+    pass
+"#,
+                None,
+                Some(&locals),
+            )
+            .unwrap();
+        });
+    }
+}
@@ -9,6 +9,7 @@ use std::{
 
 use rbx_types::{Variant, VariantType};
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
 use crate::{ClassTag, PropertyMigration, PropertyTag};
 
@@ -39,6 +40,228 @@ impl<'a> ReflectionDatabase<'a> {
             enums: HashMap::new(),
         }
     }
+
+    /// Find both the canonical and serialized property descriptors for a given
+    /// class and property name pair, checking superclasses as needed. These
+    /// might be the same descriptor!
+    ///
+    /// Returns `None` if the database is malformed in a way that stops the
+    /// property from being found, like a superclass or alias that isn't in the
+    /// database, so that databases loaded at runtime can't cause panics.
+    pub fn find_property_descriptors(
+        &self,
+        class_name: &str,
+        property_name: &str,
+    ) -> Option<PropertyDescriptors<'_>> {
+        let mut class_descriptor = self.classes.get(class_name)?;
+
+        // A class can have at most as many ancestors as there are classes, so
+        // any more steps than that mean the superclasses form a cycle.
+        let mut steps = 0;
+
+        // We need to find the canonical property descriptor associated with
+        // the property we're working with.
+        //
+        // At each step of the loop, we're checking a new class descriptor to see if
+        // it has an entry for the property name we're looking for. If that class
+        // doesn't have the property, we'll check its superclass until we reach the
+        // root.
+        loop {
+            // If this class descriptor knows about this property name, we're pretty
+            // much done!
+            if let Some(property_descriptor) = class_descriptor.properties.get(property_name) {
+                match &property_descriptor.kind {
+                    // This property descriptor is the canonical form of this
+                    // logical property. That means we've found one of the two
+                    // descriptors we're looking for!
+                    PropertyKind::Canonical { serialization } => {
+                        let serialized = find_serialized_from_canonical(
+                            class_descriptor,
+                            property_descriptor,
+                            serialization,
+                        )?;
+
+                        return Some(PropertyDescriptors {
+                            canonical: property_descriptor,
+                            serialized,
+                        });
+                    }
+
+                    // This descriptor is an alias for another property. While this
+                    // descriptor might be one of the two descriptors we need to
+                    // return, it's possible that both the canonical and serialized
+                    // forms are different.
+                    PropertyKind::Alias { alias_for } => {
+                        let canonical = class_descriptor.properties.get(alias_for.as_ref())?;
+
+                        if let PropertyKind::Canonical { serialization } = &canonical.kind {
+                            let serialized = find_serialized_from_canonical(
+                                class_descriptor,
+                                canonical,
+                                serialization,
+                            )?;
+
+                            return Some(PropertyDescriptors {
+                                canonical,
+                                serialized,
+                            });
+                        } else {
+                            // If one property in the database calls itself an alias
+                            // of another property, that property must be canonical.
+                            // A database where it isn't is malformed.
+                            return None;
+                        }
+                    }
+                }
+            }
+
+            if let Some(superclass_name) = &class_descriptor.superclass {
+                // If a property descriptor isn't found in our class, check our
+                // superclass.
+
+                steps += 1;
+                if steps > self.classes.len() {
+                    return None;
+                }
+
+                class_descriptor = self.classes.get(superclass_name)?;
+            } else {
+                // This property isn't known by any class in the reflection
+                // database.
+
+                return None;
+            }
+        }
+    }
+
+    /// Returns the name that scripts use for the given property, like `Size`
+    /// for `Part.size`.
+    pub fn canonical_property_name(&self, class_name: &str, property_name: &str) -> Option<&str> {
+        self.find_property_descriptors(class_name, property_name)
+            .map(|descriptors| descriptors.canonical.name.as_ref())
+    }
+
+    /// Returns the name that the given property is written to files with, like
+    /// `size` for `Part.Size`. Returns `None` if the property isn't serialized.
+    pub fn serialized_property_name(&self, class_name: &str, property_name: &str) -> Option<&str> {
+        self.find_property_descriptors(class_name, property_name)?
+            .serialized
+            .map(|descriptor| descriptor.name.as_ref())
+    }
+
+    /// Adds a class that Roblox doesn't know about, like a marker instance
+    /// that only exists inside a tool's own pipeline.
+    ///
+    /// Once registered, the class's properties are found like any other
+    /// class's, so DOMs checked against the database accept it. Encoders
+    /// given the database can leave the class out or write it as a Roblox
+    /// class with their `strip_class` and `map_class` options.
+    ///
+    /// ```
+    /// use rbx_reflection::{ClassDescriptor, DataType, PropertyDescriptor, ReflectionDatabase};
+    /// use rbx_types::VariantType;
+    ///
+    /// let mut database = ReflectionDatabase::new();
+    /// database.register_class(ClassDescriptor::new("Instance")).unwrap();
+    ///
+    /// let mut marker = ClassDescriptor::new("BuildMarker");
+    /// marker.superclass = Some("Instance".into());
+    /// marker.properties.insert(
+    ///     "Label".into(),
+    ///     PropertyDescriptor::new("Label", DataType::Value(VariantType::String)),
+    /// );
+    /// database.register_class(marker).unwrap();
+    ///
+    /// assert_eq!(database.canonical_property_name("BuildMarker", "Label"), Some("Label"));
+    /// ```
+    pub fn register_class(&mut self, class: ClassDescriptor<'a>) -> Result<(), RegisterClassError> {
+        if self.classes.contains_key(&class.name) {
+            return Err(RegisterClassError::AlreadyExists {
+                class: class.name.into_owned(),
+            });
+        }
+
+        if let Some(superclass) = &class.superclass {
+            if !self.classes.contains_key(superclass) {
+                return Err(RegisterClassError::UnknownSuperclass {
+                    class: class.name.into_owned(),
+                    superclass: superclass.clone().into_owned(),
+                });
+            }
+        }
+
+        self.classes.insert(class.name.clone(), class);
+
+        Ok(())
+    }
+}
+
+/// An error returned by [`ReflectionDatabase::register_class`].
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum RegisterClassError {
+    /// The database already has a class with the same name.
+    #[error("class {class} is already in the reflection database")]
+    AlreadyExists {
+        /// The name of the class.
+        class: String,
+    },
+
+    /// The class's superclass isn't in the database.
+    #[error("superclass {superclass} of class {class} is not in the reflection database")]
+    UnknownSuperclass {
+        /// The name of the class.
+        class: String,
+
+        /// The name of its superclass.
+        superclass: String,
+    },
+}
+
+/// The canonical and serialized property descriptors for one logical
+/// property, returned by [`ReflectionDatabase::find_property_descriptors`].
+///
+/// The canonical descriptor has the name used by scripts, like `Size`, while
+/// the serialized descriptor has the name used in files, like `size`. These
+/// are often the same descriptor.
+#[derive(Debug, Clone, Copy)]
+pub struct PropertyDescriptors<'a> {
+    /// The descriptor for the form of the property that scripts use.
+    pub canonical: &'a PropertyDescriptor<'a>,
+
+    /// The descriptor for the form of the property written to files, or
+    /// `None` if the property isn't serialized.
+    pub serialized: Option<&'a PropertyDescriptor<'a>>,
+}
+
+/// Given the canonical property descriptor for a logical property along with
+/// its serialization, returns the serialized form of the logical property if
+/// this property is serializable. Returns `Some(None)` if the property isn't
+/// serializable, and `None` if the database is malformed.
+fn find_serialized_from_canonical<'a>(
+    class: &'a ClassDescriptor<'a>,
+    canonical: &'a PropertyDescriptor<'a>,
+    serialization: &'a PropertySerialization<'a>,
+) -> Option<Option<&'a PropertyDescriptor<'a>>> {
+    match serialization {
+        // This property serializes as-is. This is the happiest path: both the
+        // canonical and serialized descriptors are the same!
+        PropertySerialization::Serializes | PropertySerialization::Migrate { .. } => {
+            Some(Some(canonical))
+        }
+
+        // This property serializes under an alias. That property should have a
+        // corresponding property descriptor within the same class descriptor.
+        PropertySerialization::SerializesAs(serialized_name) => {
+            let serialized_descriptor = class.properties.get(serialized_name.as_ref())?;
+
+            Some(Some(serialized_descriptor))
+        }
+
+        // If this property does not serialize, there is no serialized
+        // descriptor to use.
+        PropertySerialization::DoesNotSerialize => Some(None),
+    }
 }
 
 /// Describes a class of Instance, its properties, and its relation to other
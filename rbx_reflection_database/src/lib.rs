@@ -1,4 +1,5 @@
 use rbx_reflection::ReflectionDatabase;
+use thiserror::Error;
 
 static ENCODED_DATABASE: &[u8] = include_bytes!("../database.msgpack");
 
@@ -8,10 +9,40 @@ lazy_static::lazy_static! {
     };
 }
 
+/// Represents an error that occurred when loading a reflection database with
+/// [`from_msgpack`] or [`from_json`].
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum Error {
+    #[error("could not decode MessagePack reflection database because: {0}")]
+    MessagePack(#[from] rmp_serde::decode::Error),
+
+    #[error("could not decode JSON reflection database because: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// Returns the reflection database bundled with this crate. Its version is
+/// part of this crate's version, like `roblox-572`.
 pub fn get() -> &'static ReflectionDatabase<'static> {
     &DATABASE
 }
 
+/// Loads a reflection database in the MessagePack format used by the bundled
+/// database, like one generated by `rbx_reflector generate`.
+///
+/// This lets tools pick the database that matches the version of Roblox
+/// Studio they target instead of the bundled one. rbx_binary and rbx_xml can
+/// be told to use it through their options.
+pub fn from_msgpack(contents: &[u8]) -> Result<ReflectionDatabase<'static>, Error> {
+    Ok(rmp_serde::decode::from_slice(contents)?)
+}
+
+/// Loads a reflection database in JSON, like one generated by
+/// `rbx_reflector generate` with a `.json` output path.
+pub fn from_json(contents: &str) -> Result<ReflectionDatabase<'static>, Error> {
+    Ok(serde_json::from_str(contents)?)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -20,4 +51,57 @@ mod test {
     fn smoke_test() {
         let _database = get();
     }
+
+    #[test]
+    fn property_names() {
+        let database = get();
+
+        assert_eq!(
+            database.canonical_property_name("Part", "size"),
+            Some("Size")
+        );
+        assert_eq!(
+            database.serialized_property_name("Part", "Size"),
+            Some("size")
+        );
+        assert_eq!(
+            database.serialized_property_name("Part", "Color"),
+            Some("Color3uint8")
+        );
+        assert_eq!(
+            database.canonical_property_name("Part", "Name"),
+            Some("Name")
+        );
+        assert_eq!(database.canonical_property_name("Part", "Nonsense"), None);
+    }
+
+    #[test]
+    fn load_json() {
+        let database = from_json(
+            r#"{
+                "Version": [0, 600, 1, 0],
+                "Classes": {
+                    "Marker": {
+                        "Name": "Marker",
+                        "Tags": [],
+                        "Superclass": null,
+                        "Properties": {},
+                        "DefaultProperties": {}
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(database.version, [0, 600, 1, 0]);
+        assert!(database.classes.contains_key("Marker"));
+
+        assert!(matches!(from_json("{}"), Err(Error::Json(_))));
+    }
+
+    #[test]
+    fn load_msgpack() {
+        let database = from_msgpack(ENCODED_DATABASE).unwrap();
+        assert_eq!(database.classes.len(), get().classes.len());
+    }
 }
@@ -1,42 +1,56 @@
+use std::sync::Arc;
+
 /// Container for untyped binary data.
 ///
 /// `BinaryString` is used in cases where the type of the underlying data is
 /// unknown or unimplemented. Where possible, stronger types that interpret the
 /// underlying bytes should be preferred.
+///
+/// Clones of a `BinaryString` share the same buffer, so cloning one that holds
+/// a large blob, like terrain or mesh data, does not copy it. The buffer is
+/// only copied when a shared `BinaryString` is changed.
 #[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
 pub struct BinaryString {
-    buffer: Vec<u8>,
+    buffer: Arc<Vec<u8>>,
 }
 
 impl BinaryString {
     #[inline]
     pub fn new() -> Self {
-        Self { buffer: Vec::new() }
+        Self::default()
     }
 
+    /// Returns the contents of the `BinaryString`, copying them only if the
+    /// buffer is shared with another `BinaryString`.
     #[inline]
     pub fn into_vec(self) -> Vec<u8> {
-        self.buffer
+        Arc::try_unwrap(self.buffer).unwrap_or_else(|shared| (*shared).clone())
+    }
+
+    /// Tells whether both `BinaryString`s share the same buffer.
+    #[inline]
+    pub fn ptr_eq(&self, other: &BinaryString) -> bool {
+        Arc::ptr_eq(&self.buffer, &other.buffer)
     }
 }
 
 impl From<Vec<u8>> for BinaryString {
     fn from(buffer: Vec<u8>) -> Self {
-        Self { buffer }
+        Self {
+            buffer: Arc::new(buffer),
+        }
     }
 }
 
 impl From<&'_ [u8]> for BinaryString {
     fn from(buffer: &[u8]) -> Self {
-        Self {
-            buffer: buffer.to_vec(),
-        }
+        Self::from(buffer.to_vec())
     }
 }
 
 impl From<BinaryString> for Vec<u8> {
     fn from(value: BinaryString) -> Self {
-        value.buffer
+        value.into_vec()
     }
 }
 
@@ -54,13 +68,13 @@ impl AsRef<Vec<u8>> for BinaryString {
 
 impl AsMut<[u8]> for BinaryString {
     fn as_mut(&mut self) -> &mut [u8] {
-        &mut self.buffer
+        Arc::make_mut(&mut self.buffer).as_mut_slice()
     }
 }
 
 impl AsMut<Vec<u8>> for BinaryString {
     fn as_mut(&mut self) -> &mut Vec<u8> {
-        &mut self.buffer
+        Arc::make_mut(&mut self.buffer)
     }
 }
 
@@ -73,14 +87,14 @@ mod serde_impl {
     impl Serialize for BinaryString {
         fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
             if serializer.is_human_readable() {
-                let encoded = base64::encode(&self.buffer);
+                let encoded = base64::encode(self.buffer.as_slice());
 
                 serializer.serialize_str(&encoded)
             } else {
                 // We need to be opaque here because we're deserializing
                 // using `Vec<u8>`'s serde implementation and we cannot trust
                 // that it'll be implemented the same across versions
-                self.buffer.serialize(serializer)
+                self.buffer.as_slice().serialize(serializer)
             }
         }
     }
@@ -91,15 +105,32 @@ mod serde_impl {
                 let encoded = <&str>::deserialize(deserializer)?;
                 let buffer = base64::decode(encoded).map_err(D::Error::custom)?;
 
-                Ok(BinaryString { buffer })
+                Ok(BinaryString::from(buffer))
             } else {
                 let buffer = <Vec<u8>>::deserialize(deserializer)?;
-                Ok(BinaryString { buffer })
+                Ok(BinaryString::from(buffer))
             }
         }
     }
 }
 
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn clones_share_buffer() {
+        let original = BinaryString::from(b"terrain".to_vec());
+        let mut copy = original.clone();
+        assert!(copy.ptr_eq(&original));
+
+        AsMut::<[u8]>::as_mut(&mut copy)[0] = b'T';
+        assert!(!copy.ptr_eq(&original));
+        assert_eq!(original.into_vec(), b"terrain");
+        assert_eq!(copy.into_vec(), b"Terrain");
+    }
+}
+
 #[cfg(all(test, feature = "serde"))]
 mod serde_test {
     use super::*;
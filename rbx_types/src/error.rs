@@ -1,6 +1,6 @@
 use thiserror::Error;
 
-use crate::{AttributeError, Matrix3Error};
+use crate::{variant::VariantTypeError, AttributeError, Matrix3Error};
 
 /// Represents an error that occurred when using a fallible method.
 #[derive(Debug, Error)]
@@ -25,6 +25,14 @@ impl From<Matrix3Error> for Error {
     }
 }
 
+impl From<VariantTypeError> for Error {
+    fn from(source: VariantTypeError) -> Self {
+        Self {
+            source: Box::new(source.into()),
+        }
+    }
+}
+
 #[derive(Debug, Error)]
 enum InnerError {
     #[error(transparent)]
@@ -32,4 +40,7 @@ enum InnerError {
 
     #[error(transparent)]
     Matrix3Error(#[from] Matrix3Error),
+
+    #[error(transparent)]
+    VariantType(#[from] VariantTypeError),
 }
@@ -14,7 +14,9 @@ mod font;
 mod lister;
 mod physical_properties;
 mod referent;
+mod sha1;
 mod shared_string;
+mod stable_hash;
 mod tags;
 mod unique_id;
 mod variant;
@@ -31,6 +33,7 @@ pub use font::*;
 pub use physical_properties::*;
 pub use referent::*;
 pub use shared_string::*;
+pub use stable_hash::*;
 pub use tags::*;
 pub use unique_id::*;
 pub use variant::*;
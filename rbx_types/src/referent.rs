@@ -2,10 +2,50 @@
 #![allow(clippy::new_without_default)]
 
 use std::fmt;
-use std::num::NonZeroU128;
+use std::num::{NonZeroU128, ParseIntError};
 use std::str::FromStr;
 
+use thiserror::Error;
+
+use crate::sha1::sha1;
+
+/// The digits used by [`Ref::to_base62`], in order of value.
+const BASE62_DIGITS: &[u8; 62] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+/// Represents an error that can occur when parsing a `Ref` from a string.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum RefParseError {
+    /// The string given to [`Ref::parse_str`] is not 32 hexadecimal digits,
+    /// with or without hyphens.
+    #[error("string passed to Ref::parse_str could not be read because: {err}")]
+    InvalidHex {
+        /// Why the digits could not be read.
+        #[from]
+        err: ParseIntError,
+    },
+
+    /// The string given to [`Ref::from_base62`] contains a character that
+    /// isn't a base62 digit.
+    #[error("expected a base62 digit, got {0:?}")]
+    InvalidBase62Digit(char),
+
+    /// The string given to [`Ref::from_base62`] is a number larger than any
+    /// `Ref`.
+    #[error("base62 string is too large to be a Ref")]
+    Base62Overflow,
+
+    /// The string given to [`Ref::from_base62`] is empty. [`Ref::none`] is
+    /// written as `0`.
+    #[error("base62 string is empty")]
+    EmptyBase62,
+}
+
 /// An universally unique, optional reference to a Roblox instance.
+///
+/// `Ref` displays as 32 hexadecimal digits. The alternate form (`{:#}`)
+/// displays the same digits grouped like a UUID, such as
+/// `0123abcd-0000-0000-0000-00000000001e`. Both forms can be read back with
+/// [`Ref::parse_str`] or [`FromStr`].
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Ref(Option<NonZeroU128>);
 
@@ -22,6 +62,61 @@ impl Ref {
         Ref(None)
     }
 
+    /// Derives a `Ref` from a namespace and a name as a version 5 UUID, as
+    /// described in RFC 4122. The same namespace and name always give the same
+    /// `Ref`, and the result never points to nothing.
+    ///
+    /// The namespace is used as the UUID it displays as with `{:#}`, so
+    /// namespaces from other tools' version 5 UUIDs give the same results as
+    /// they do there.
+    ///
+    /// ```
+    /// use rbx_types::Ref;
+    ///
+    /// let namespace = Ref::new();
+    /// assert_eq!(
+    ///     Ref::from_namespace(namespace, b"Workspace"),
+    ///     Ref::from_namespace(namespace, b"Workspace"),
+    /// );
+    /// assert_ne!(
+    ///     Ref::from_namespace(namespace, b"Workspace"),
+    ///     Ref::from_namespace(Ref::new(), b"Workspace"),
+    /// );
+    /// ```
+    pub fn from_namespace(namespace: Ref, name: &[u8]) -> Self {
+        let digest = sha1(&[&namespace.to_bytes(), name]);
+
+        let mut bytes = [0; 16];
+        bytes.copy_from_slice(&digest[..16]);
+        bytes[6] = (bytes[6] & 0x0f) | 0x50;
+        bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+        // The variant bits make the value nonzero.
+        Ref::from_bytes(bytes)
+    }
+
+    /// Returns the 16 bytes of this `Ref` in big-endian order, which is the
+    /// same order its digits are displayed in. [`Ref::none`] is all zeroes.
+    ///
+    /// ```
+    /// use rbx_types::Ref;
+    ///
+    /// let referent = Ref::new();
+    /// assert_eq!(Ref::from_bytes(referent.to_bytes()), referent);
+    /// assert_eq!(Ref::none().to_bytes(), [0; 16]);
+    /// ```
+    #[inline]
+    pub fn to_bytes(&self) -> [u8; 16] {
+        self.value().to_be_bytes()
+    }
+
+    /// Creates a `Ref` from bytes returned by [`Ref::to_bytes`]. All zeroes
+    /// give [`Ref::none`].
+    #[inline]
+    pub fn from_bytes(bytes: [u8; 16]) -> Self {
+        Ref(NonZeroU128::new(u128::from_be_bytes(bytes)))
+    }
+
     /// Tells whether this `Ref` points to something.
     #[inline]
     pub fn is_some(&self) -> bool {
@@ -34,6 +129,86 @@ impl Ref {
         self.0.is_none()
     }
 
+    /// Parses a `Ref` from hexadecimal digits, either as written by `Display`
+    /// or grouped like a UUID. All zeroes parse as [`Ref::none`].
+    ///
+    /// ```
+    /// use rbx_types::Ref;
+    ///
+    /// let referent = Ref::new();
+    /// assert_eq!(Ref::parse_str(&referent.to_string()), Ok(referent));
+    /// assert_eq!(Ref::parse_str(&format!("{:#}", referent)), Ok(referent));
+    /// ```
+    pub fn parse_str(input: &str) -> Result<Self, RefParseError> {
+        Ok(Ref::parse_hex(input)?)
+    }
+
+    /// Parses the digits accepted by [`Ref::parse_str`], returning the error
+    /// that [`FromStr`] has always returned.
+    fn parse_hex(input: &str) -> Result<Self, ParseIntError> {
+        let bytes = input.as_bytes();
+        let is_hyphenated =
+            bytes.len() == 36 && [8, 13, 18, 23].iter().all(|&index| bytes[index] == b'-');
+
+        let value = if is_hyphenated {
+            u128::from_str_radix(&input.replace('-', ""), 16)?
+        } else {
+            u128::from_str_radix(input, 16)?
+        };
+
+        Ok(Ref(NonZeroU128::new(value)))
+    }
+
+    /// Encodes this `Ref` in base62, which is shorter than hexadecimal and
+    /// safe to use in URLs. [`Ref::none`] encodes as `0`.
+    ///
+    /// ```
+    /// use rbx_types::Ref;
+    ///
+    /// let referent = Ref::new();
+    /// assert!(referent.to_base62().len() <= 22);
+    /// assert_eq!(Ref::from_base62(&referent.to_base62()), Ok(referent));
+    /// ```
+    pub fn to_base62(&self) -> String {
+        let mut value = self.value();
+        let mut digits = Vec::new();
+
+        loop {
+            digits.push(BASE62_DIGITS[(value % 62) as usize]);
+            value /= 62;
+
+            if value == 0 {
+                break;
+            }
+        }
+
+        digits.reverse();
+        String::from_utf8(digits).unwrap()
+    }
+
+    /// Decodes a `Ref` written by [`Ref::to_base62`].
+    pub fn from_base62(input: &str) -> Result<Self, RefParseError> {
+        if input.is_empty() {
+            return Err(RefParseError::EmptyBase62);
+        }
+
+        let mut value: u128 = 0;
+
+        for digit in input.chars() {
+            let digit_value = BASE62_DIGITS
+                .iter()
+                .position(|&candidate| candidate as char == digit)
+                .ok_or(RefParseError::InvalidBase62Digit(digit))?;
+
+            value = value
+                .checked_mul(62)
+                .and_then(|value| value.checked_add(digit_value as u128))
+                .ok_or(RefParseError::Base62Overflow)?;
+        }
+
+        Ok(Ref(NonZeroU128::new(value)))
+    }
+
     fn value(&self) -> u128 {
         match self.0 {
             Some(value) => value.get(),
@@ -44,17 +219,31 @@ impl Ref {
 
 impl fmt::Display for Ref {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        write!(formatter, "{:032x}", self.value())
+        if formatter.alternate() {
+            let hex = format!("{:032x}", self.value());
+            write!(
+                formatter,
+                "{}-{}-{}-{}-{}",
+                &hex[0..8],
+                &hex[8..12],
+                &hex[12..16],
+                &hex[16..20],
+                &hex[20..32]
+            )
+        } else {
+            write!(formatter, "{:032x}", self.value())
+        }
     }
 }
 
+/// Parses a `Ref` like [`Ref::parse_str`] does, but returns the
+/// `ParseIntError` from reading the digits, since that's what it has always
+/// returned.
 impl FromStr for Ref {
-    type Err = std::num::ParseIntError;
+    type Err = ParseIntError;
 
     fn from_str(input: &str) -> Result<Self, Self::Err> {
-        let value = u128::from_str_radix(input, 16)?;
-
-        Ok(Ref(NonZeroU128::new(value)))
+        Ref::parse_hex(input)
     }
 }
 
@@ -96,8 +285,7 @@ mod serde_impl {
         }
 
         fn visit_str<E: Error>(self, ref_str: &str) -> Result<Self::Value, E> {
-            let ref_value = u128::from_str_radix(ref_str, 16).map_err(E::custom)?;
-            Ok(Ref(NonZeroU128::new(ref_value)))
+            Ref::parse_str(ref_str).map_err(E::custom)
         }
     }
 
@@ -146,6 +334,63 @@ mod test {
             Ref::from_str("ffffffffffffffffffffffffffffffff").unwrap(),
             Ref(NonZeroU128::new(u128::max_value()))
         );
+
+        let err: ParseIntError = Ref::from_str("not a ref").unwrap_err();
+        assert_eq!(
+            Ref::parse_str("not a ref"),
+            Err(RefParseError::InvalidHex { err })
+        );
+    }
+
+    #[test]
+    fn hyphenated() {
+        let thirty = Ref(NonZeroU128::new(30));
+        assert_eq!(
+            format!("{:#}", thirty),
+            "00000000-0000-0000-0000-00000000001e"
+        );
+        assert_eq!(
+            Ref::parse_str("00000000-0000-0000-0000-00000000001e").unwrap(),
+            thirty
+        );
+
+        assert!(matches!(
+            Ref::parse_str("0000-0000"),
+            Err(RefParseError::InvalidHex { .. })
+        ));
+    }
+
+    #[test]
+    fn from_namespace() {
+        // The DNS namespace from RFC 4122, and the UUID that Python's
+        // uuid.uuid5 gives for it and "python.org".
+        let dns = Ref::parse_str("6ba7b810-9dad-11d1-80b4-00c04fd430c8").unwrap();
+        assert_eq!(
+            format!("{:#}", Ref::from_namespace(dns, b"python.org")),
+            "886313e1-3b8a-5372-9b90-0c9aee199e5d"
+        );
+        assert!(Ref::from_namespace(Ref::none(), b"").is_some());
+    }
+
+    #[test]
+    fn base62() {
+        assert_eq!(Ref::none().to_base62(), "0");
+        assert_eq!(Ref(NonZeroU128::new(61)).to_base62(), "z");
+        assert_eq!(Ref(NonZeroU128::new(62)).to_base62(), "10");
+
+        let max = Ref(NonZeroU128::new(u128::MAX));
+        assert_eq!(Ref::from_base62(&max.to_base62()).unwrap(), max);
+
+        assert_eq!(
+            Ref::from_base62("12-"),
+            Err(RefParseError::InvalidBase62Digit('-'))
+        );
+        assert_eq!(
+            Ref::from_base62("zzzzzzzzzzzzzzzzzzzzzzz"),
+            Err(RefParseError::Base62Overflow)
+        );
+        assert_eq!(Ref::from_base62(""), Err(RefParseError::EmptyBase62));
+        assert_eq!(Ref::from_base62("0"), Ok(Ref::none()));
     }
 
     #[test]
@@ -0,0 +1,293 @@
+use std::hash::{Hash, Hasher};
+
+use crate::{
+    CFrame, Color3, Matrix3, PhysicalProperties, UDim, Variant, Vector2, Vector2int16, Vector3,
+    Vector3int16,
+};
+
+/// A [`Hasher`] whose output only depends on the values given to it, not on
+/// the platform or process, unlike the default hasher from the standard
+/// library. Hashes made with it can be stored and compared later.
+///
+/// Integers are always written in little-endian order, and the hash is made
+/// with BLAKE3.
+#[derive(Debug, Clone, Default)]
+pub struct StableHasher {
+    inner: blake3::Hasher,
+}
+
+impl StableHasher {
+    /// Creates a hasher that hasn't been given any values yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+macro_rules! write_le {
+    ( $( $method:ident($ty:ty), )* ) => {
+        $(
+            fn $method(&mut self, value: $ty) {
+                self.write(&value.to_le_bytes());
+            }
+        )*
+    };
+}
+
+impl Hasher for StableHasher {
+    fn finish(&self) -> u64 {
+        let hash = self.inner.finalize();
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(&hash.as_bytes()[..8]);
+        u64::from_le_bytes(bytes)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.inner.update(bytes);
+    }
+
+    write_le! {
+        write_u16(u16),
+        write_u32(u32),
+        write_u64(u64),
+        write_u128(u128),
+        write_i16(i16),
+        write_i32(i32),
+        write_i64(i64),
+        write_i128(i128),
+    }
+
+    fn write_usize(&mut self, value: usize) {
+        self.write_u64(value as u64);
+    }
+
+    fn write_isize(&mut self, value: isize) {
+        self.write_i64(value as i64);
+    }
+}
+
+impl Variant {
+    /// Returns a hash of this value made with [`StableHasher`], which is the
+    /// same across runs and platforms.
+    ///
+    /// Values that are equal have equal hashes, including `0.0` and `-0.0`.
+    /// `Ref` values are hashed by referent, so they only match within the same
+    /// DOM.
+    ///
+    /// ```
+    /// use rbx_types::{Variant, Vector3};
+    ///
+    /// let a = Variant::Vector3(Vector3::new(1.0, 2.0, 3.0));
+    /// let b = Variant::Vector3(Vector3::new(1.0, 2.0, 3.0));
+    /// assert_eq!(a.stable_hash(), b.stable_hash());
+    /// ```
+    pub fn stable_hash(&self) -> u64 {
+        let mut hasher = StableHasher::new();
+        self.hash_stable(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Feeds this value into the given hasher. Floating point numbers are fed
+    /// in by their bits, so this is only stable when used with a hasher that
+    /// is, like [`StableHasher`].
+    pub fn hash_stable<H: Hasher>(&self, state: &mut H) {
+        state.write_u32(self.ty() as u32);
+
+        match self {
+            Variant::Axes(value) => state.write_u8(value.bits()),
+            Variant::BinaryString(value) => value.hash(state),
+            Variant::Bool(value) => value.hash(state),
+            Variant::BrickColor(value) => state.write_u16(*value as u16),
+            Variant::CFrame(value) => cframe(state, value),
+            Variant::Color3(value) => color3(state, value),
+            Variant::Color3uint8(value) => state.write(&[value.r, value.g, value.b]),
+            Variant::ColorSequence(value) => {
+                state.write_usize(value.keypoints.len());
+                for keypoint in &value.keypoints {
+                    f32(state, keypoint.time);
+                    color3(state, &keypoint.color);
+                }
+            }
+            Variant::Content(value) => value.hash(state),
+            Variant::Enum(value) => state.write_u32(value.to_u32()),
+            Variant::Faces(value) => state.write_u8(value.bits()),
+            Variant::Float32(value) => f32(state, *value),
+            Variant::Float64(value) => f64(state, *value),
+            Variant::Int32(value) => value.hash(state),
+            Variant::Int64(value) => value.hash(state),
+            Variant::NumberRange(value) => {
+                f32(state, value.min);
+                f32(state, value.max);
+            }
+            Variant::NumberSequence(value) => {
+                state.write_usize(value.keypoints.len());
+                for keypoint in &value.keypoints {
+                    f32(state, keypoint.time);
+                    f32(state, keypoint.value);
+                    f32(state, keypoint.envelope);
+                }
+            }
+            Variant::PhysicalProperties(value) => match value {
+                PhysicalProperties::Default => state.write_u8(0),
+                PhysicalProperties::Custom(custom) => {
+                    state.write_u8(1);
+                    f32(state, custom.density);
+                    f32(state, custom.friction);
+                    f32(state, custom.elasticity);
+                    f32(state, custom.friction_weight);
+                    f32(state, custom.elasticity_weight);
+                }
+            },
+            Variant::Ray(value) => {
+                vector3(state, &value.origin);
+                vector3(state, &value.direction);
+            }
+            Variant::Rect(value) => {
+                vector2(state, &value.min);
+                vector2(state, &value.max);
+            }
+            Variant::Ref(value) => value.hash(state),
+            Variant::Region3(value) => {
+                vector3(state, &value.min);
+                vector3(state, &value.max);
+            }
+            Variant::Region3int16(value) => {
+                vector3int16(state, &value.min);
+                vector3int16(state, &value.max);
+            }
+            Variant::SharedString(value) => state.write(value.hash().as_bytes()),
+            Variant::String(value) => value.hash(state),
+            Variant::UDim(value) => udim(state, value),
+            Variant::UDim2(value) => {
+                udim(state, &value.x);
+                udim(state, &value.y);
+            }
+            Variant::Vector2(value) => vector2(state, value),
+            Variant::Vector2int16(value) => vector2int16(state, value),
+            Variant::Vector3(value) => vector3(state, value),
+            Variant::Vector3int16(value) => vector3int16(state, value),
+            Variant::OptionalCFrame(value) => match value {
+                Some(value) => {
+                    state.write_u8(1);
+                    cframe(state, value);
+                }
+                None => state.write_u8(0),
+            },
+            Variant::Tags(value) => {
+                for tag in value.iter() {
+                    tag.hash(state);
+                }
+            }
+            Variant::Attributes(value) => {
+                // Attributes are stored sorted by name, so this doesn't depend
+                // on the order they were added in.
+                for (name, value) in value.iter() {
+                    name.hash(state);
+                    value.hash_stable(state);
+                }
+            }
+            Variant::Font(value) => value.hash(state),
+            Variant::UniqueId(value) => value.hash(state),
+        }
+    }
+}
+
+fn f32<H: Hasher>(state: &mut H, value: f32) {
+    // -0.0 and 0.0 are equal, so they need to hash the same.
+    let value = if value == 0.0 { 0.0 } else { value };
+    state.write_u32(value.to_bits());
+}
+
+fn f64<H: Hasher>(state: &mut H, value: f64) {
+    let value = if value == 0.0 { 0.0 } else { value };
+    state.write_u64(value.to_bits());
+}
+
+fn vector2<H: Hasher>(state: &mut H, value: &Vector2) {
+    f32(state, value.x);
+    f32(state, value.y);
+}
+
+fn vector2int16<H: Hasher>(state: &mut H, value: &Vector2int16) {
+    state.write_i16(value.x);
+    state.write_i16(value.y);
+}
+
+fn vector3<H: Hasher>(state: &mut H, value: &Vector3) {
+    f32(state, value.x);
+    f32(state, value.y);
+    f32(state, value.z);
+}
+
+fn vector3int16<H: Hasher>(state: &mut H, value: &Vector3int16) {
+    state.write_i16(value.x);
+    state.write_i16(value.y);
+    state.write_i16(value.z);
+}
+
+fn matrix3<H: Hasher>(state: &mut H, value: &Matrix3) {
+    vector3(state, &value.x);
+    vector3(state, &value.y);
+    vector3(state, &value.z);
+}
+
+fn cframe<H: Hasher>(state: &mut H, value: &CFrame) {
+    vector3(state, &value.position);
+    matrix3(state, &value.orientation);
+}
+
+fn color3<H: Hasher>(state: &mut H, value: &Color3) {
+    f32(state, value.r);
+    f32(state, value.g);
+    f32(state, value.b);
+}
+
+fn udim<H: Hasher>(state: &mut H, value: &UDim) {
+    f32(state, value.scale);
+    state.write_i32(value.offset);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::{Attributes, Content};
+
+    #[test]
+    fn equal_values_hash_equally() {
+        assert_eq!(
+            Variant::Float32(0.0).stable_hash(),
+            Variant::Float32(-0.0).stable_hash()
+        );
+        assert_ne!(
+            Variant::Float32(1.0).stable_hash(),
+            Variant::Float64(1.0).stable_hash()
+        );
+        assert_ne!(
+            Variant::String("rbxassetid://1".to_owned()).stable_hash(),
+            Variant::Content(Content::from("rbxassetid://1")).stable_hash()
+        );
+
+        let mut a = Attributes::new();
+        a.insert("One".to_owned(), Variant::Bool(true));
+        a.insert("Two".to_owned(), Variant::Int32(2));
+
+        let mut b = Attributes::new();
+        b.insert("Two".to_owned(), Variant::Int32(2));
+        b.insert("One".to_owned(), Variant::Bool(true));
+
+        assert_eq!(
+            Variant::Attributes(a).stable_hash(),
+            Variant::Attributes(b).stable_hash()
+        );
+    }
+
+    #[test]
+    fn stable_between_runs() {
+        // This value should never change. If it does, hashes that people have
+        // stored will no longer match.
+        assert_eq!(
+            Variant::Vector3(Vector3::new(1.0, 2.0, 3.0)).stable_hash(),
+            15895104192394059890
+        );
+    }
+}
@@ -54,18 +54,31 @@ impl UniqueId {
         }
     }
 
+    /// Generates a new `UniqueId` for the current time.
+    ///
+    /// This isn't available on `wasm32-unknown-unknown`, which has no clock.
+    /// Use [`UniqueId::at`] there instead.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
     pub fn now() -> Result<Self, UniqueIdError> {
         let time = SystemTime::now()
             .duration_since(*EPOCH)
             .map_err(|_| UniqueIdError::SystemPastTime)?;
 
-        Ok(Self {
+        Ok(Self::at(
+            u32::try_from(time.as_secs()).map_err(|_| UniqueIdError::Overflow)?,
+        ))
+    }
+
+    /// Generates a new `UniqueId` for the given time, in seconds since the
+    /// `UniqueId` epoch. Unlike [`UniqueId::now`], this doesn't need a clock.
+    pub fn at(time: u32) -> Self {
+        Self {
             index: INDEX.fetch_add(1, Ordering::AcqRel),
-            time: u32::try_from(time.as_secs()).map_err(|_| UniqueIdError::Overflow)?,
+            time,
             // This matches Roblox's behavior, where the value is both an i64
             // but is also always positive.
             random: thread_rng().gen_range(0..i64::MAX),
-        })
+        }
     }
 
     pub fn time(&self) -> u32 {
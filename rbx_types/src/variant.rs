@@ -1,3 +1,7 @@
+use std::convert::TryFrom;
+
+use thiserror::Error;
+
 use crate::{
     Attributes, Axes, BinaryString, BrickColor, CFrame, Color3, Color3uint8, ColorSequence,
     Content, Enum, Faces, Font, NumberRange, NumberSequence, PhysicalProperties, Ray, Rect, Ref,
@@ -5,6 +9,8 @@ use crate::{
     Vector3, Vector3int16,
 };
 
+use crate::Error;
+
 /// Reduces boilerplate from listing different values of Variant by wrapping
 /// them into a macro.
 macro_rules! make_variant {
@@ -52,6 +58,20 @@ macro_rules! make_variant {
                     Self::$variant_name(value)
                 }
             }
+
+            impl TryFrom<Variant> for $inner_type {
+                type Error = Error;
+
+                fn try_from(value: Variant) -> Result<Self, Self::Error> {
+                    match value {
+                        Variant::$variant_name(inner) => Ok(inner),
+                        other => Err(Error::from(VariantTypeError {
+                            expected: VariantType::$variant_name,
+                            actual: other.ty(),
+                        })),
+                    }
+                }
+            }
         )*
 
         /// Represents any type that can be held in a `Variant`.
@@ -78,7 +98,7 @@ macro_rules! make_variant {
             /// this test will start failing.
             #[allow(dead_code)]
             fn conversions_are_exhaustive() {
-                fn trait_test<T: Into<Variant>>() {}
+                fn trait_test<T: Into<Variant> + TryFrom<Variant>>() {}
 
                 $( trait_test::<$inner_type>(); )*
                 trait_test::<SharedString>();
@@ -139,6 +159,33 @@ impl From<&'_ str> for Variant {
     }
 }
 
+#[derive(Debug, Error)]
+#[error("expected a Variant of type {expected:?}, but got {actual:?}")]
+pub(crate) struct VariantTypeError {
+    expected: VariantType,
+    actual: VariantType,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn try_from() {
+        assert!(bool::try_from(Variant::Bool(true)).unwrap());
+        assert_eq!(
+            String::try_from(Variant::from("Hello")).unwrap(),
+            "Hello".to_owned()
+        );
+
+        let error = f32::try_from(Variant::Float64(1.0)).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "expected a Variant of type Float32, but got Float64"
+        );
+    }
+}
+
 #[cfg(all(test, feature = "serde"))]
 mod serde_test {
     use super::*;
@@ -0,0 +1,70 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use rbx_util::{
+    bench::{PropertyMix, TreeGenerator, TreeShape},
+    from_slice, to_vec, Format,
+};
+
+const SIZES: &[usize] = &[1_000, 10_000];
+
+pub fn encode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Encode generated tree");
+
+    for &size in SIZES {
+        let dom = TreeGenerator::new(size)
+            .shape(TreeShape::Balanced { children: 8 })
+            .generate();
+        let refs = [dom.root_ref()];
+
+        for format in [Format::Binary, Format::Xml] {
+            group.bench_with_input(
+                BenchmarkId::new(format!("{:?}", format), size),
+                &dom,
+                |b, dom| b.iter(|| to_vec(dom, &refs, format).unwrap()),
+            );
+        }
+    }
+
+    group.finish();
+}
+
+pub fn decode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Decode generated tree");
+
+    for &size in SIZES {
+        let dom = TreeGenerator::new(size)
+            .shape(TreeShape::Balanced { children: 8 })
+            .generate();
+
+        for format in [Format::Binary, Format::Xml] {
+            let encoded = to_vec(&dom, &[dom.root_ref()], format).unwrap();
+
+            group.bench_with_input(
+                BenchmarkId::new(format!("{:?}", format), size),
+                &encoded,
+                |b, encoded| b.iter(|| from_slice(encoded).unwrap()),
+            );
+        }
+    }
+
+    group.finish();
+}
+
+pub fn diff(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Hash perturbed tree");
+
+    for &size in SIZES {
+        let generator = TreeGenerator::new(size).properties(PropertyMix::Parts);
+        let mut dom = generator.generate();
+        generator.perturb(&mut dom, 0.01);
+
+        group.bench_with_input(BenchmarkId::from_parameter(size), &dom, |b, dom| {
+            b.iter(|| dom.subtree_hashes(dom.root_ref()))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(generated, encode, decode, diff);
+criterion_main!(generated);
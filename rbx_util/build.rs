@@ -0,0 +1,17 @@
+fn main() {
+    #[cfg(feature = "proto")]
+    generate_proto();
+}
+
+/// Generates the message types in `proto` from the schema, with protox
+/// standing in for protoc so that building doesn't need it installed.
+#[cfg(feature = "proto")]
+fn generate_proto() {
+    let schema = "proto/rbx_tree.proto";
+    println!("cargo:rerun-if-changed={}", schema);
+
+    let descriptors = protox::compile([schema], ["proto"]).expect("schema is invalid");
+    prost_build::Config::new()
+        .compile_fds(descriptors)
+        .expect("could not generate protobuf messages");
+}
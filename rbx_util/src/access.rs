@@ -0,0 +1,302 @@
+use std::collections::HashMap;
+
+use rbx_dom_weak::{types::Ref, JournalEntry, WeakDom};
+use serde::{Deserialize, Serialize};
+
+use crate::error::AccessError;
+
+/// Who owns and who has locked the subtrees of a DOM.
+///
+/// Owners and locks apply to an instance and all of its descendants. The
+/// closest ancestor with an owner decides who owns an instance, and the same
+/// goes for locks. An instance can only be changed by its owner, if it has
+/// one, and only by the holder of its lock, if it's locked.
+///
+/// This is kept beside the DOM instead of in it, so it is never written to
+/// model or place files. It can be saved with serde, but referents are only
+/// meaningful for the DOM it was made for, so it should be saved alongside
+/// that DOM's session rather than alongside a file.
+///
+/// ```
+/// use rbx_dom_weak::{InstanceBuilder, WeakDom};
+/// use rbx_util::AccessControl;
+///
+/// let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+/// let root_ref = dom.root_ref();
+/// let workspace = dom.insert(root_ref, InstanceBuilder::new("Workspace"));
+/// let part = dom.insert(workspace, InstanceBuilder::new("Part"));
+///
+/// let mut access = AccessControl::new();
+/// access.set_owner(workspace, "alice");
+/// access.lock(&dom, part, "alice")?;
+///
+/// assert!(access.check(&dom, part, "alice").is_ok());
+/// assert!(access.check(&dom, part, "bob").is_err());
+/// # Ok::<(), rbx_util::AccessError>(())
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessControl {
+    owners: HashMap<Ref, String>,
+    locks: HashMap<Ref, String>,
+}
+
+impl AccessControl {
+    /// Creates an empty model, where anyone can change anything.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes `owner` the owner of the instance and its descendants, replacing
+    /// any owner it had.
+    ///
+    /// Owners are opaque names, so they can be users, teams, or anything else
+    /// an editor checks changes against. A user is allowed to change an owned
+    /// instance only when they are passed as exactly the owner's name.
+    pub fn set_owner<S: Into<String>>(&mut self, referent: Ref, owner: S) {
+        self.owners.insert(referent, owner.into());
+    }
+
+    /// Removes the owner set directly on the instance. It's still owned by the
+    /// owner of its closest owned ancestor, if there is one.
+    pub fn clear_owner(&mut self, referent: Ref) {
+        self.owners.remove(&referent);
+    }
+
+    /// Returns who owns the instance, which is the owner of it or of its
+    /// closest owned ancestor.
+    pub fn owner(&self, dom: &WeakDom, referent: Ref) -> Option<&str> {
+        closest(dom, referent, &self.owners)
+    }
+
+    /// Locks the instance and its descendants for `holder`, so that nobody
+    /// else can change them until it's unlocked.
+    ///
+    /// Fails if any part of the subtree is already locked by someone else,
+    /// or if `holder` isn't allowed to change the instance.
+    pub fn lock<S: Into<String>>(
+        &mut self,
+        dom: &WeakDom,
+        referent: Ref,
+        holder: S,
+    ) -> Result<(), AccessError> {
+        let holder = holder.into();
+        self.check(dom, referent, &holder)?;
+
+        for (&locked, other) in &self.locks {
+            if other != &holder && is_ancestor(dom, referent, locked) {
+                return Err(AccessError::Locked {
+                    referent: locked,
+                    holder: other.clone(),
+                });
+            }
+        }
+
+        self.locks.insert(referent, holder);
+        Ok(())
+    }
+
+    /// Removes the lock on the instance. Only the holder of the lock can
+    /// remove it.
+    pub fn unlock(&mut self, referent: Ref, holder: &str) -> Result<(), AccessError> {
+        match self.locks.get(&referent) {
+            Some(other) if other != holder => Err(AccessError::Locked {
+                referent,
+                holder: other.clone(),
+            }),
+            _ => {
+                self.locks.remove(&referent);
+                Ok(())
+            }
+        }
+    }
+
+    /// Returns who holds the lock on the instance, which is the holder of the
+    /// lock on it or on its closest locked ancestor.
+    pub fn lock_holder(&self, dom: &WeakDom, referent: Ref) -> Option<&str> {
+        closest(dom, referent, &self.locks)
+    }
+
+    /// Checks whether `user` is allowed to change the instance.
+    pub fn check(&self, dom: &WeakDom, referent: Ref, user: &str) -> Result<(), AccessError> {
+        if let Some((locked, holder)) = closest_entry(dom, referent, &self.locks) {
+            if holder != user {
+                return Err(AccessError::Locked {
+                    referent: locked,
+                    holder: holder.to_owned(),
+                });
+            }
+        }
+
+        if let Some((owned, owner)) = closest_entry(dom, referent, &self.owners) {
+            if owner != user {
+                return Err(AccessError::Owned {
+                    referent: owned,
+                    owner: owner.to_owned(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks whether `user` was allowed to make the changes recorded by
+    /// [`WeakDom::drain_journal`], returning the first one that they weren't.
+    ///
+    /// This is the place to enforce ownership and locks in a collaborative
+    /// editor: apply someone's changes to a copy of the DOM with the journal
+    /// enabled, check the journal, and only keep the copy if this succeeds.
+    ///
+    /// Inserting or moving an instance is a change to its new parent, and
+    /// destroying or moving one away is a change to both it and its old
+    /// parent. Like [`JournalFilter`][rbx_dom_weak::JournalFilter], entries
+    /// are checked against the DOM as it is now.
+    pub fn check_journal(
+        &self,
+        dom: &WeakDom,
+        entries: &[JournalEntry],
+        user: &str,
+    ) -> Result<(), AccessError> {
+        for entry in entries {
+            let (changed, parents) = match entry {
+                JournalEntry::Insert { parent, .. } | JournalEntry::TransferIn { parent, .. } => {
+                    (None, vec![*parent])
+                }
+                JournalEntry::Destroy {
+                    referent, parent, ..
+                }
+                | JournalEntry::TransferOut {
+                    referent, parent, ..
+                } => (Some(*referent), vec![*parent]),
+                JournalEntry::Move {
+                    referent,
+                    old_parent,
+                    new_parent,
+                } => (Some(*referent), vec![*old_parent, *new_parent]),
+                JournalEntry::Edit { referent, .. } => (Some(*referent), Vec::new()),
+                _ => continue,
+            };
+
+            // Instances that aren't in the DOM any more, like destroyed ones,
+            // are only checked against the owners and locks set directly on
+            // them, since their ancestors can't be known.
+            for referent in changed.into_iter().chain(parents) {
+                self.check(dom, referent, user)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Forgets the owners and locks of instances that aren't in the DOM any
+    /// more.
+    pub fn retain_existing(&mut self, dom: &WeakDom) {
+        self.owners
+            .retain(|referent, _| dom.get_by_ref(*referent).is_some());
+        self.locks
+            .retain(|referent, _| dom.get_by_ref(*referent).is_some());
+    }
+}
+
+/// Finds the closest instance to `referent`, starting with itself and going up
+/// through its ancestors, that has an entry in `map`.
+fn closest_entry<'a>(
+    dom: &WeakDom,
+    mut referent: Ref,
+    map: &'a HashMap<Ref, String>,
+) -> Option<(Ref, &'a str)> {
+    if map.is_empty() {
+        return None;
+    }
+
+    loop {
+        if let Some(value) = map.get(&referent) {
+            return Some((referent, value));
+        }
+
+        referent = dom.get_by_ref(referent)?.parent();
+        if referent.is_none() {
+            return None;
+        }
+    }
+}
+
+fn closest<'a>(dom: &WeakDom, referent: Ref, map: &'a HashMap<Ref, String>) -> Option<&'a str> {
+    closest_entry(dom, referent, map).map(|(_, value)| value)
+}
+
+/// Tells whether `ancestor` is `referent` or one of its ancestors.
+fn is_ancestor(dom: &WeakDom, ancestor: Ref, mut referent: Ref) -> bool {
+    while referent.is_some() {
+        if referent == ancestor {
+            return true;
+        }
+
+        referent = match dom.get_by_ref(referent) {
+            Some(instance) => instance.parent(),
+            None => return false,
+        };
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::InstanceBuilder;
+
+    #[test]
+    fn access_control() {
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let root_ref = dom.root_ref();
+        let workspace = dom.insert(root_ref, InstanceBuilder::new("Workspace"));
+        let model = dom.insert(workspace, InstanceBuilder::new("Model"));
+        let part = dom.insert(model, InstanceBuilder::new("Part"));
+        let storage = dom.insert(root_ref, InstanceBuilder::new("ReplicatedStorage"));
+
+        let mut access = AccessControl::new();
+        access.set_owner(workspace, "alice");
+        access.lock(&dom, model, "alice").unwrap();
+
+        assert_eq!(access.owner(&dom, part), Some("alice"));
+        assert_eq!(access.lock_holder(&dom, part), Some("alice"));
+        assert_eq!(access.owner(&dom, storage), None);
+
+        // Locking an ancestor of someone else's lock fails.
+        access.set_owner(root_ref, "bob");
+        access.clear_owner(workspace);
+        assert_eq!(access.owner(&dom, part), Some("bob"));
+        assert!(matches!(
+            access.lock(&dom, workspace, "bob"),
+            Err(AccessError::Locked { referent, .. }) if referent == model
+        ));
+        assert!(access.unlock(model, "bob").is_err());
+
+        dom.enable_journal();
+        dom.edit(storage).set_name("Shared");
+        dom.insert(workspace, InstanceBuilder::new("Folder"));
+        let entries = dom.drain_journal();
+        assert!(access.check_journal(&dom, &entries, "bob").is_ok());
+
+        dom.destroy(part);
+        let entries = dom.drain_journal();
+        assert!(matches!(
+            access.check_journal(&dom, &entries, "bob"),
+            Err(AccessError::Locked { holder, .. }) if holder == "alice"
+        ));
+
+        let saved = serde_json::to_string(&access).unwrap();
+        let mut restored: AccessControl = serde_json::from_str(&saved).unwrap();
+        assert_eq!(restored, access);
+
+        access.unlock(model, "alice").unwrap();
+        assert!(access.check(&dom, model, "bob").is_ok());
+
+        restored.set_owner(part, "alice");
+        restored.retain_existing(&dom);
+        assert!(!restored.owners.contains_key(&part));
+        assert_eq!(restored.lock_holder(&dom, model), Some("alice"));
+    }
+}
@@ -0,0 +1,417 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use rbx_dom_weak::{
+    types::{CFrame, Enum, Matrix3, Ref, Vector3},
+    Instance, InstanceBuilder, WeakDom,
+};
+
+use crate::error::AnimationError;
+
+/// The value of `Enum.AnimationPriority.Action`, which new KeyframeSequences
+/// use.
+const ACTION_PRIORITY: u32 = 2;
+
+/// The contents of a KeyframeSequence, which is how Roblox stores animations.
+///
+/// A KeyframeSequence holds Keyframes, and each Keyframe holds a tree of Poses
+/// that mirrors the rig's joints: the Pose for `UpperTorso` is inside the Pose
+/// for `LowerTorso`, and so on. This type keeps each Keyframe's Poses in a
+/// flat list instead, remembering each Pose's parent.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Animation {
+    /// Whether the animation starts over when it ends.
+    pub looped: bool,
+
+    /// The value of the animation's `Enum.AnimationPriority`.
+    pub priority: u32,
+
+    /// The keyframes, in the order they appear in the KeyframeSequence.
+    pub keyframes: Vec<AnimationKeyframe>,
+}
+
+/// A Keyframe of an [`Animation`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationKeyframe {
+    /// The Keyframe's Name.
+    pub name: String,
+
+    /// When the keyframe happens, in seconds from the start of the animation.
+    pub time: f32,
+
+    /// The keyframe's Poses, with each Pose's parent before it.
+    pub poses: Vec<AnimationPose>,
+}
+
+/// A Pose in an [`AnimationKeyframe`], which says where one part of the rig
+/// should be.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationPose {
+    /// The name of the part that the Pose moves, which is also the Pose's
+    /// Name.
+    pub part: String,
+
+    /// The part of the Pose that this Pose is inside of, or `None` if it's
+    /// directly inside the Keyframe.
+    pub parent: Option<String>,
+
+    /// How the part's joint is offset, relative to its resting position.
+    pub cframe: CFrame,
+
+    /// How much the Pose affects the part. Poses with no weight only exist to
+    /// hold other Poses.
+    pub weight: f32,
+
+    /// The value of the Pose's `Enum.PoseEasingStyle`.
+    pub easing_style: u32,
+
+    /// The value of the Pose's `Enum.PoseEasingDirection`.
+    pub easing_direction: u32,
+}
+
+/// Every key of one part in an [`Animation`], created by
+/// [`Animation::to_channels`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AnimationChannel {
+    /// The part whose Poses hold this part's Poses.
+    pub parent: Option<String>,
+
+    /// The part's keys, in order of time.
+    pub keys: Vec<ChannelKey>,
+}
+
+/// A single key in an [`AnimationChannel`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChannelKey {
+    /// When the key happens, in seconds from the start of the animation.
+    pub time: f32,
+
+    /// How the part's joint is offset, relative to its resting position.
+    pub cframe: CFrame,
+
+    /// How much the key affects the part.
+    pub weight: f32,
+
+    /// The value of the key's `Enum.PoseEasingStyle`.
+    pub easing_style: u32,
+
+    /// The value of the key's `Enum.PoseEasingDirection`.
+    pub easing_direction: u32,
+}
+
+impl Animation {
+    /// Reads the KeyframeSequence with the given referent. Properties that
+    /// are missing are given the values that Roblox would use.
+    pub fn from_instance(dom: &WeakDom, referent: Ref) -> Result<Self, AnimationError> {
+        let sequence = dom
+            .get_by_ref(referent)
+            .ok_or(AnimationError::InstanceNotFound(referent))?;
+
+        if sequence.class != "KeyframeSequence" {
+            return Err(AnimationError::NotAKeyframeSequence(sequence.class.clone()));
+        }
+
+        let keyframes = children_of_class(dom, sequence, "Keyframe")
+            .map(|keyframe| {
+                let mut poses = Vec::new();
+                read_poses(dom, keyframe, None, &mut poses);
+
+                AnimationKeyframe {
+                    name: keyframe.name.clone(),
+                    time: keyframe.get_typed::<f32>("Time").unwrap_or(0.0),
+                    poses,
+                }
+            })
+            .collect();
+
+        Ok(Animation {
+            looped: sequence.get_typed::<bool>("Loop").unwrap_or(true),
+            priority: sequence
+                .get_typed::<Enum>("Priority")
+                .map_or(ACTION_PRIORITY, Enum::to_u32),
+            keyframes,
+        })
+    }
+
+    /// Returns a builder for a KeyframeSequence holding this animation. Poses
+    /// whose parent isn't in the same keyframe are put directly inside the
+    /// Keyframe.
+    pub fn build(&self) -> InstanceBuilder {
+        let keyframes = self.keyframes.iter().map(|keyframe| {
+            let parts: BTreeSet<&str> = keyframe
+                .poses
+                .iter()
+                .map(|pose| pose.part.as_str())
+                .collect();
+
+            let top_level = keyframe.poses.iter().enumerate().filter(|(_, pose)| {
+                !pose
+                    .parent
+                    .as_deref()
+                    .is_some_and(|parent| parts.contains(parent))
+            });
+
+            InstanceBuilder::new("Keyframe")
+                .with_name(keyframe.name.as_str())
+                .with_property("Time", keyframe.time)
+                .with_children(top_level.map(|(index, _)| build_pose(&keyframe.poses, index)))
+        });
+
+        InstanceBuilder::new("KeyframeSequence")
+            .with_property("Loop", self.looped)
+            .with_property("Priority", Enum::from_u32(self.priority))
+            .with_children(keyframes)
+    }
+
+    /// Splits the animation into one channel per part. Poses with no weight,
+    /// which only hold other Poses, aren't included as keys, but they still
+    /// give their parts a channel so that the rig's hierarchy is kept.
+    pub fn to_channels(&self) -> BTreeMap<String, AnimationChannel> {
+        let mut channels: BTreeMap<String, AnimationChannel> = BTreeMap::new();
+
+        let mut keyframes: Vec<&AnimationKeyframe> = self.keyframes.iter().collect();
+        keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+        for keyframe in keyframes {
+            for pose in &keyframe.poses {
+                let channel = channels.entry(pose.part.clone()).or_default();
+                if channel.parent.is_none() {
+                    channel.parent = pose.parent.clone();
+                }
+
+                if pose.weight != 0.0 {
+                    channel.keys.push(ChannelKey {
+                        time: keyframe.time,
+                        cframe: pose.cframe,
+                        weight: pose.weight,
+                        easing_style: pose.easing_style,
+                        easing_direction: pose.easing_direction,
+                    });
+                }
+            }
+        }
+
+        channels
+    }
+
+    /// Creates a looping animation from channels, with one keyframe for each
+    /// distinct key time. Parts without a key at a keyframe's time are given
+    /// a Pose with no weight whenever one of their descendants has a key.
+    pub fn from_channels(channels: &BTreeMap<String, AnimationChannel>) -> Self {
+        let mut times: Vec<f32> = channels
+            .values()
+            .flat_map(|channel| channel.keys.iter().map(|key| key.time))
+            .collect();
+        times.sort_by(f32::total_cmp);
+        times.dedup();
+
+        let mut children: BTreeMap<Option<&str>, Vec<&str>> = BTreeMap::new();
+        for (part, channel) in channels {
+            // Parents without channels are treated as if they weren't there,
+            // which is also how `build` treats them.
+            let parent = channel
+                .parent
+                .as_deref()
+                .filter(|parent| channels.contains_key(*parent));
+
+            children.entry(parent).or_default().push(part);
+        }
+
+        let keyframes = times
+            .iter()
+            .map(|&time| {
+                let mut poses = Vec::new();
+                channel_poses(channels, &children, None, time, &mut poses);
+
+                AnimationKeyframe {
+                    name: "Keyframe".to_owned(),
+                    time,
+                    poses,
+                }
+            })
+            .collect();
+
+        Animation {
+            looped: true,
+            priority: ACTION_PRIORITY,
+            keyframes,
+        }
+    }
+}
+
+fn children_of_class<'a>(
+    dom: &'a WeakDom,
+    parent: &'a Instance,
+    class: &'a str,
+) -> impl Iterator<Item = &'a Instance> {
+    parent
+        .children()
+        .iter()
+        .map(move |&child| dom.get_by_ref(child).unwrap())
+        .filter(move |child| child.class == class)
+}
+
+fn read_poses(
+    dom: &WeakDom,
+    parent: &Instance,
+    parent_part: Option<&str>,
+    poses: &mut Vec<AnimationPose>,
+) {
+    for pose in children_of_class(dom, parent, "Pose") {
+        poses.push(AnimationPose {
+            part: pose.name.clone(),
+            parent: parent_part.map(str::to_owned),
+            cframe: pose
+                .get_typed::<CFrame>("CFrame")
+                .unwrap_or_else(|_| identity()),
+            weight: pose.get_typed::<f32>("Weight").unwrap_or(1.0),
+            easing_style: pose
+                .get_typed::<Enum>("EasingStyle")
+                .map_or(0, Enum::to_u32),
+            easing_direction: pose
+                .get_typed::<Enum>("EasingDirection")
+                .map_or(0, Enum::to_u32),
+        });
+
+        read_poses(dom, pose, Some(&pose.name), poses);
+    }
+}
+
+fn build_pose(poses: &[AnimationPose], index: usize) -> InstanceBuilder {
+    let pose = &poses[index];
+    let children = poses
+        .iter()
+        .enumerate()
+        .filter(|(_, child)| child.parent.as_deref() == Some(pose.part.as_str()))
+        .map(|(child, _)| build_pose(poses, child));
+
+    InstanceBuilder::new("Pose")
+        .with_name(pose.part.as_str())
+        .with_property("CFrame", pose.cframe)
+        .with_property("Weight", pose.weight)
+        .with_property("EasingStyle", Enum::from_u32(pose.easing_style))
+        .with_property("EasingDirection", Enum::from_u32(pose.easing_direction))
+        .with_children(children)
+}
+
+/// Adds the Poses of every part under `parent` at the given time, returning
+/// whether any of them has a key there.
+fn channel_poses(
+    channels: &BTreeMap<String, AnimationChannel>,
+    children: &BTreeMap<Option<&str>, Vec<&str>>,
+    parent: Option<&str>,
+    time: f32,
+    poses: &mut Vec<AnimationPose>,
+) -> bool {
+    let mut any_keyed = false;
+
+    for &part in children.get(&parent).into_iter().flatten() {
+        let key = channels[part].keys.iter().find(|key| key.time == time);
+
+        // The Pose has to come before its children's Poses, so it's added
+        // now and taken back out if nothing under it turns out to be keyed.
+        let index = poses.len();
+        poses.push(AnimationPose {
+            part: part.to_owned(),
+            parent: parent.map(str::to_owned),
+            cframe: key.map_or_else(identity, |key| key.cframe),
+            weight: key.map_or(0.0, |key| key.weight),
+            easing_style: key.map_or(0, |key| key.easing_style),
+            easing_direction: key.map_or(0, |key| key.easing_direction),
+        });
+
+        let descendants_keyed = channel_poses(channels, children, Some(part), time, poses);
+
+        if key.is_some() || descendants_keyed {
+            any_keyed = true;
+        } else {
+            poses.truncate(index);
+        }
+    }
+
+    any_keyed
+}
+
+fn identity() -> CFrame {
+    CFrame::new(Vector3::new(0.0, 0.0, 0.0), Matrix3::identity())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn pose(name: &str, weight: f32, y: f32) -> InstanceBuilder {
+        InstanceBuilder::new("Pose")
+            .with_name(name)
+            .with_property("Weight", weight)
+            .with_property(
+                "CFrame",
+                CFrame::new(Vector3::new(0.0, y, 0.0), Matrix3::identity()),
+            )
+    }
+
+    #[test]
+    fn channels_round_trip() {
+        let dom = WeakDom::new(
+            InstanceBuilder::new("KeyframeSequence")
+                .with_property("Loop", true)
+                .with_child(
+                    InstanceBuilder::new("Keyframe")
+                        .with_property("Time", 0.0f32)
+                        .with_child(pose("HumanoidRootPart", 0.0, 0.0).with_child(
+                            pose("LowerTorso", 1.0, 1.0).with_child(pose("UpperTorso", 1.0, 2.0)),
+                        )),
+                )
+                .with_child(
+                    InstanceBuilder::new("Keyframe")
+                        .with_property("Time", 0.5f32)
+                        .with_child(pose("HumanoidRootPart", 0.0, 0.0).with_child(
+                            pose("LowerTorso", 0.0, 0.0).with_child(pose("UpperTorso", 1.0, 3.0)),
+                        )),
+                ),
+        );
+
+        let animation = Animation::from_instance(&dom, dom.root_ref()).unwrap();
+        assert_eq!(animation.priority, ACTION_PRIORITY);
+        assert_eq!(animation.keyframes.len(), 2);
+        assert_eq!(
+            animation.keyframes[1].poses[2].parent.as_deref(),
+            Some("LowerTorso")
+        );
+
+        let channels = animation.to_channels();
+        assert_eq!(
+            channels.keys().map(String::as_str).collect::<Vec<_>>(),
+            ["HumanoidRootPart", "LowerTorso", "UpperTorso"]
+        );
+        assert!(channels["HumanoidRootPart"].keys.is_empty());
+        assert_eq!(channels["LowerTorso"].keys.len(), 1);
+
+        let upper_torso: Vec<_> = channels["UpperTorso"]
+            .keys
+            .iter()
+            .map(|key| (key.time, key.cframe.position.y))
+            .collect();
+        assert_eq!(upper_torso, [(0.0, 2.0), (0.5, 3.0)]);
+
+        let from_channels = Animation::from_channels(&channels);
+        assert_eq!(from_channels.keyframes.len(), 2);
+        for (rebuilt, original) in from_channels.keyframes.iter().zip(&animation.keyframes) {
+            assert_eq!(rebuilt.time, original.time);
+            assert_eq!(rebuilt.poses, original.poses);
+        }
+
+        let rebuilt_dom = WeakDom::new(from_channels.build());
+        let rebuilt = Animation::from_instance(&rebuilt_dom, rebuilt_dom.root_ref()).unwrap();
+        assert_eq!(rebuilt, from_channels);
+    }
+
+    #[test]
+    fn not_a_keyframe_sequence() {
+        let dom = WeakDom::new(InstanceBuilder::new("Folder"));
+
+        assert!(matches!(
+            Animation::from_instance(&dom, dom.root_ref()),
+            Err(AnimationError::NotAKeyframeSequence(class)) if class == "Folder"
+        ));
+    }
+}
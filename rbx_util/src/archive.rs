@@ -0,0 +1,905 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    convert::{TryFrom, TryInto},
+    io::{self, Cursor, Read, Seek, SeekFrom, Write},
+};
+
+use miniz_oxide::inflate::decompress_to_vec_with_limit;
+use rbx_dom_weak::{types::Variant, WeakDom};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::ArchiveError,
+    from_slice, join,
+    path::descendants,
+    split,
+    split::{link_id, LINK_ID_ATTRIBUTE, LINK_REF_PREFIX},
+    to_vec, Format,
+};
+
+/// The version of the manifest written by [`PlaceArchive`].
+const ARCHIVE_VERSION: u32 = 1;
+
+/// The path of the manifest inside an archive.
+const MANIFEST_PATH: &str = "manifest.json";
+
+/// A place stored as a zip archive of binary models, one per child of its
+/// root, along with a manifest that lists them.
+///
+/// The place is split up with [`split`][crate::split], so `Ref` properties
+/// that point from one model into another survive. The manifest records which
+/// fragments refer to which, so that a few fragments can be loaded out of a
+/// giant place without reading the rest of it.
+///
+/// Fragments are stored without compression since binary models are already
+/// compressed. Archives whose entries were compressed with deflate, like ones
+/// re-zipped by another tool, can be read too, and archives too large for a
+/// plain zip file use the zip64 extensions.
+///
+/// ```
+/// use rbx_dom_weak::{InstanceBuilder, WeakDom};
+/// use rbx_util::PlaceArchive;
+///
+/// let dom = WeakDom::new(
+///     InstanceBuilder::new("DataModel")
+///         .with_child(InstanceBuilder::new("Workspace"))
+///         .with_child(InstanceBuilder::new("ReplicatedStorage")),
+/// );
+///
+/// let contents = PlaceArchive::from_dom(dom)?.to_vec()?;
+/// let archive = PlaceArchive::from_slice(&contents)?;
+///
+/// let storage = archive.load(&["fragments/ReplicatedStorage.rbxm"])?;
+/// assert_eq!(storage.root().children().len(), 1);
+/// # Ok::<(), rbx_util::ArchiveError>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct PlaceArchive {
+    manifest: Manifest,
+    entries: HashMap<String, Vec<u8>>,
+}
+
+/// One of the models in a [`PlaceArchive`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveFragment {
+    /// The name of the instance at the top of the model, usually the name of
+    /// a service.
+    pub name: String,
+
+    /// The path of the model inside the archive, which is unique.
+    pub path: String,
+
+    /// The paths of the other fragments that this one has `Ref` properties
+    /// pointing into.
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Manifest {
+    version: u32,
+    fragments: Vec<ArchiveFragment>,
+
+    /// The fragment that holds every instance that's pointed to from another
+    /// fragment, by the link ID that [`split`] gave it.
+    #[serde(default)]
+    links: BTreeMap<String, String>,
+}
+
+impl PlaceArchive {
+    /// Splits a place into fragments and encodes each of them.
+    pub fn from_dom(dom: WeakDom) -> Result<Self, ArchiveError> {
+        let models = split(dom)?;
+
+        let mut paths = Vec::with_capacity(models.len());
+        let mut used = BTreeSet::new();
+        for model in &models {
+            let stem = format!("fragments/{}", crate::file_name(&model.name));
+            let mut path = format!("{}.rbxm", stem);
+            let mut count = 1;
+            while used.contains(&path) {
+                count += 1;
+                path = format!("{}-{}.rbxm", stem, count);
+            }
+
+            used.insert(path.clone());
+            paths.push(path);
+        }
+
+        let mut links = BTreeMap::new();
+        let mut targets = Vec::with_capacity(models.len());
+        for (model, path) in models.iter().zip(&paths) {
+            let mut model_targets = BTreeSet::new();
+
+            for referent in descendants(&model.dom, model.dom.root_ref()) {
+                let instance = model.dom.get_by_ref(referent).unwrap();
+                let attributes = match instance.properties.get("Attributes") {
+                    Some(Variant::Attributes(attributes)) => attributes,
+                    _ => continue,
+                };
+
+                for (key, value) in attributes.iter() {
+                    if key == LINK_ID_ATTRIBUTE {
+                        if let Some(id) = link_id(value.clone()) {
+                            links.insert(id, path.clone());
+                        }
+                    } else if key.starts_with(LINK_REF_PREFIX) {
+                        model_targets.extend(link_id(value.clone()));
+                    }
+                }
+            }
+
+            targets.push(model_targets);
+        }
+
+        let mut fragments = Vec::with_capacity(models.len());
+        let mut entries = HashMap::with_capacity(models.len() + 1);
+        for ((model, path), model_targets) in models.iter().zip(paths).zip(targets) {
+            let depends_on: BTreeSet<&String> = model_targets
+                .iter()
+                .filter_map(|id| links.get(id))
+                .filter(|target| **target != path)
+                .collect();
+
+            fragments.push(ArchiveFragment {
+                name: model.name.clone(),
+                path: path.clone(),
+                depends_on: depends_on.into_iter().cloned().collect(),
+            });
+
+            let encoded = to_vec(&model.dom, model.dom.root().children(), Format::Binary)?;
+            entries.insert(path, encoded);
+        }
+
+        Ok(PlaceArchive {
+            manifest: Manifest {
+                version: ARCHIVE_VERSION,
+                fragments,
+                links,
+            },
+            entries,
+        })
+    }
+
+    /// Reads an archive written by [`PlaceArchive::to_vec`], or one that was
+    /// re-zipped by another tool. Fragments aren't decoded until they're
+    /// loaded.
+    ///
+    /// Every fragment is read into memory. [`ArchiveReader`] only reads the
+    /// fragments that are loaded.
+    pub fn from_slice(contents: &[u8]) -> Result<Self, ArchiveError> {
+        let mut reader = ArchiveReader::new(Cursor::new(contents))?;
+
+        let mut entries = HashMap::with_capacity(reader.manifest.fragments.len());
+        for fragment in &reader.manifest.fragments {
+            let entry = &reader.entries[&fragment.path];
+            let data = read_entry(&mut reader.reader, &fragment.path, entry)?;
+            entries.insert(fragment.path.clone(), data);
+        }
+
+        Ok(PlaceArchive {
+            manifest: reader.manifest,
+            entries,
+        })
+    }
+
+    /// Returns the fragments in the archive, in the order the instances were
+    /// in the place.
+    pub fn fragments(&self) -> &[ArchiveFragment] {
+        &self.manifest.fragments
+    }
+
+    /// Returns the path of the fragment with the given name, if there is one.
+    /// If more than one fragment has the name, the first one is returned.
+    pub fn find_fragment(&self, name: &str) -> Option<&str> {
+        self.manifest.find_fragment(name)
+    }
+
+    /// Decodes the fragments with the given paths and joins them into one
+    /// DOM, in the order they are in the archive.
+    ///
+    /// `Ref` properties between the loaded fragments are restored. Ones that
+    /// point into fragments that weren't loaded are left empty, so the
+    /// fragments listed in [`ArchiveFragment::depends_on`] should usually be
+    /// loaded too.
+    pub fn load(&self, paths: &[&str]) -> Result<WeakDom, ArchiveError> {
+        let models = self
+            .manifest
+            .select(paths)?
+            .map(|fragment| from_slice(&self.entries[&fragment.path]))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(join(models))
+    }
+
+    /// Decodes every fragment and joins them back into the whole place.
+    pub fn load_all(&self) -> Result<WeakDom, ArchiveError> {
+        self.load(&self.manifest.paths())
+    }
+
+    /// Writes the archive as a zip file.
+    pub fn to_vec(&self) -> Result<Vec<u8>, ArchiveError> {
+        let mut output = Vec::new();
+        self.to_writer(&mut output)?;
+        Ok(output)
+    }
+
+    /// Writes the archive as a zip file to something that implements the
+    /// `Write` trait. Archives too large for a plain zip file are written with
+    /// the zip64 extensions.
+    pub fn to_writer<W: Write>(&self, writer: W) -> Result<(), ArchiveError> {
+        self.write_zip(writer, false)
+    }
+
+    fn write_zip<W: Write>(&self, writer: W, force_zip64: bool) -> Result<(), ArchiveError> {
+        let manifest = serde_json::to_vec_pretty(&self.manifest)?;
+
+        let mut entries = vec![(MANIFEST_PATH, manifest.as_slice())];
+        for fragment in &self.manifest.fragments {
+            entries.push((&fragment.path, &self.entries[&fragment.path]));
+        }
+
+        write_zip(writer, &entries, force_zip64)
+    }
+}
+
+/// Reads the fragments of a [`PlaceArchive`] straight out of a file or other
+/// seekable reader, so that a few fragments can be loaded out of a giant place
+/// without the rest of it being read into memory.
+///
+/// ```no_run
+/// use std::fs::File;
+///
+/// use rbx_util::ArchiveReader;
+///
+/// let mut archive = ArchiveReader::new(File::open("place.zip")?)?;
+/// let path = archive.find_fragment("ReplicatedStorage").unwrap().to_owned();
+/// let storage = archive.load(&[&path])?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug)]
+pub struct ArchiveReader<R> {
+    reader: R,
+    manifest: Manifest,
+    entries: HashMap<String, ZipEntry>,
+}
+
+impl<R: Read + Seek> ArchiveReader<R> {
+    /// Reads the archive's central directory and manifest. Fragments are read
+    /// when they're loaded.
+    pub fn new(mut reader: R) -> Result<Self, ArchiveError> {
+        let mut entries: HashMap<String, ZipEntry> =
+            read_directory(&mut reader)?.into_iter().collect();
+
+        let manifest = entries
+            .remove(MANIFEST_PATH)
+            .ok_or_else(|| ArchiveError::MissingEntry(MANIFEST_PATH.to_owned()))?;
+        let manifest = read_entry(&mut reader, MANIFEST_PATH, &manifest)?;
+        let manifest: Manifest = serde_json::from_slice(&manifest)?;
+
+        if manifest.version > ARCHIVE_VERSION {
+            return Err(ArchiveError::UnsupportedVersion(manifest.version));
+        }
+
+        for fragment in &manifest.fragments {
+            if !entries.contains_key(&fragment.path) {
+                return Err(ArchiveError::MissingEntry(fragment.path.clone()));
+            }
+        }
+
+        Ok(ArchiveReader {
+            reader,
+            manifest,
+            entries,
+        })
+    }
+
+    /// Returns the fragments in the archive, in the order the instances were
+    /// in the place.
+    pub fn fragments(&self) -> &[ArchiveFragment] {
+        &self.manifest.fragments
+    }
+
+    /// Returns the path of the fragment with the given name, if there is one.
+    /// If more than one fragment has the name, the first one is returned.
+    pub fn find_fragment(&self, name: &str) -> Option<&str> {
+        self.manifest.find_fragment(name)
+    }
+
+    /// Reads and decodes the fragments with the given paths and joins them
+    /// into one DOM, like [`PlaceArchive::load`].
+    pub fn load(&mut self, paths: &[&str]) -> Result<WeakDom, ArchiveError> {
+        let mut models = Vec::with_capacity(paths.len());
+        for fragment in self.manifest.select(paths)? {
+            let entry = &self.entries[&fragment.path];
+            let data = read_entry(&mut self.reader, &fragment.path, entry)?;
+            models.push(from_slice(&data)?);
+        }
+
+        Ok(join(models))
+    }
+
+    /// Reads and decodes every fragment and joins them back into the whole
+    /// place.
+    pub fn load_all(&mut self) -> Result<WeakDom, ArchiveError> {
+        let paths: Vec<String> = self
+            .manifest
+            .fragments
+            .iter()
+            .map(|fragment| fragment.path.clone())
+            .collect();
+        let paths: Vec<&str> = paths.iter().map(String::as_str).collect();
+
+        self.load(&paths)
+    }
+
+    /// Returns the reader that the archive is being read from.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl Manifest {
+    fn find_fragment(&self, name: &str) -> Option<&str> {
+        self.fragments
+            .iter()
+            .find(|fragment| fragment.name == name)
+            .map(|fragment| fragment.path.as_str())
+    }
+
+    fn paths(&self) -> Vec<&str> {
+        self.fragments
+            .iter()
+            .map(|fragment| fragment.path.as_str())
+            .collect()
+    }
+
+    /// Returns the fragments with the given paths, in the order they are in
+    /// the archive.
+    fn select<'a>(
+        &'a self,
+        paths: &'a [&str],
+    ) -> Result<impl Iterator<Item = &'a ArchiveFragment>, ArchiveError> {
+        for path in paths {
+            if self.fragments.iter().all(|fragment| fragment.path != *path) {
+                return Err(ArchiveError::MissingEntry((*path).to_owned()));
+            }
+        }
+
+        Ok(self
+            .fragments
+            .iter()
+            .filter(move |fragment| paths.contains(&fragment.path.as_str())))
+    }
+}
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x0201_4b50;
+const END_OF_DIRECTORY_SIGNATURE: u32 = 0x0605_4b50;
+const ZIP64_END_OF_DIRECTORY_SIGNATURE: u32 = 0x0606_4b50;
+const ZIP64_LOCATOR_SIGNATURE: u32 = 0x0706_4b50;
+
+/// The ID of the extra field that holds the 64 bit sizes and offset of an
+/// entry.
+const ZIP64_EXTRA_ID: u16 = 0x0001;
+
+/// The versions of the zip format needed to read stored entries, and entries
+/// that use the zip64 extensions.
+const ZIP_VERSION: u16 = 10;
+const ZIP64_VERSION: u16 = 45;
+
+/// The compression methods that can be read.
+const STORED: u16 = 0;
+const DEFLATED: u16 = 8;
+
+/// Marks entry names as UTF-8.
+const UTF8_FLAG: u16 = 0x0800;
+
+/// 1980-01-01, the earliest date zip files can store, in MS-DOS format. Every
+/// entry gets the same date so that writing the same place twice gives the
+/// same bytes.
+const DOS_DATE: u16 = 0x21;
+
+/// Writes the given entries as a zip file, without compressing them. The
+/// zip64 extensions are used for the entries and directory that need them,
+/// or for all of them if `force_zip64` is set.
+fn write_zip<W: Write>(
+    writer: W,
+    entries: &[(&str, &[u8])],
+    force_zip64: bool,
+) -> Result<(), ArchiveError> {
+    let mut writer = io::BufWriter::new(writer);
+    let mut offset = 0u64;
+    let mut directory = Vec::new();
+
+    for (name, data) in entries {
+        let name_len = u16::try_from(name.len()).map_err(|_| ArchiveError::TooLarge)?;
+        let size = data.len() as u64;
+        let crc = crc32(data);
+
+        let large_size = force_zip64 || size >= u64::from(u32::MAX);
+        let large_offset = force_zip64 || offset >= u64::from(u32::MAX);
+        let version = if large_size || large_offset {
+            ZIP64_VERSION
+        } else {
+            ZIP_VERSION
+        };
+
+        let mut local = Vec::with_capacity(30 + name.len() + 20);
+        put_u32(&mut local, LOCAL_HEADER_SIGNATURE);
+        put_u16(&mut local, version);
+        put_u16(&mut local, UTF8_FLAG);
+        put_u16(&mut local, STORED);
+        put_u16(&mut local, 0);
+        put_u16(&mut local, DOS_DATE);
+        put_u32(&mut local, crc);
+        put_u32(&mut local, clamp_u32(size, large_size));
+        put_u32(&mut local, clamp_u32(size, large_size));
+        put_u16(&mut local, name_len);
+        put_u16(&mut local, if large_size { 20 } else { 0 });
+        local.extend_from_slice(name.as_bytes());
+        if large_size {
+            put_u16(&mut local, ZIP64_EXTRA_ID);
+            put_u16(&mut local, 16);
+            put_u64(&mut local, size);
+            put_u64(&mut local, size);
+        }
+
+        // The central directory's extra field only holds the values that
+        // didn't fit, in this order.
+        let mut extra = Vec::new();
+        if large_size {
+            put_u64(&mut extra, size);
+            put_u64(&mut extra, size);
+        }
+        if large_offset {
+            put_u64(&mut extra, offset);
+        }
+
+        put_u32(&mut directory, CENTRAL_HEADER_SIGNATURE);
+        put_u16(&mut directory, ZIP64_VERSION);
+        put_u16(&mut directory, version);
+        put_u16(&mut directory, UTF8_FLAG);
+        put_u16(&mut directory, STORED);
+        put_u16(&mut directory, 0);
+        put_u16(&mut directory, DOS_DATE);
+        put_u32(&mut directory, crc);
+        put_u32(&mut directory, clamp_u32(size, large_size));
+        put_u32(&mut directory, clamp_u32(size, large_size));
+        put_u16(&mut directory, name_len);
+        put_u16(
+            &mut directory,
+            if extra.is_empty() {
+                0
+            } else {
+                4 + extra.len() as u16
+            },
+        );
+        put_u16(&mut directory, 0);
+        put_u16(&mut directory, 0);
+        put_u16(&mut directory, 0);
+        put_u32(&mut directory, 0);
+        put_u32(&mut directory, clamp_u32(offset, large_offset));
+        directory.extend_from_slice(name.as_bytes());
+        if !extra.is_empty() {
+            put_u16(&mut directory, ZIP64_EXTRA_ID);
+            put_u16(&mut directory, extra.len() as u16);
+            directory.extend_from_slice(&extra);
+        }
+
+        writer.write_all(&local)?;
+        writer.write_all(data)?;
+        offset += local.len() as u64 + size;
+    }
+
+    let count = entries.len() as u64;
+    let directory_offset = offset;
+    let directory_size = directory.len() as u64;
+    writer.write_all(&directory)?;
+
+    let zip64 = force_zip64
+        || count >= u64::from(u16::MAX)
+        || directory_size >= u64::from(u32::MAX)
+        || directory_offset >= u64::from(u32::MAX);
+
+    let mut end = Vec::with_capacity(56 + 20 + 22);
+    if zip64 {
+        put_u32(&mut end, ZIP64_END_OF_DIRECTORY_SIGNATURE);
+        put_u64(&mut end, 44);
+        put_u16(&mut end, ZIP64_VERSION);
+        put_u16(&mut end, ZIP64_VERSION);
+        put_u32(&mut end, 0);
+        put_u32(&mut end, 0);
+        put_u64(&mut end, count);
+        put_u64(&mut end, count);
+        put_u64(&mut end, directory_size);
+        put_u64(&mut end, directory_offset);
+
+        put_u32(&mut end, ZIP64_LOCATOR_SIGNATURE);
+        put_u32(&mut end, 0);
+        put_u64(&mut end, directory_offset + directory_size);
+        put_u32(&mut end, 1);
+    }
+
+    let short_count = if zip64 { u16::MAX } else { count as u16 };
+    put_u32(&mut end, END_OF_DIRECTORY_SIGNATURE);
+    put_u16(&mut end, 0);
+    put_u16(&mut end, 0);
+    put_u16(&mut end, short_count);
+    put_u16(&mut end, short_count);
+    put_u32(&mut end, clamp_u32(directory_size, zip64));
+    put_u32(&mut end, clamp_u32(directory_offset, zip64));
+    put_u16(&mut end, 0);
+    writer.write_all(&end)?;
+
+    writer.flush()?;
+    Ok(())
+}
+
+/// Returns `value` as a `u32`, or the `0xffffffff` that marks a value as
+/// being in the zip64 extra field if `large` is set.
+fn clamp_u32(value: u64, large: bool) -> u32 {
+    if large {
+        u32::MAX
+    } else {
+        value as u32
+    }
+}
+
+/// An entry listed in a zip file's central directory.
+#[derive(Debug, Clone)]
+pub(crate) struct ZipEntry {
+    method: u16,
+    pub(crate) crc: u32,
+    compressed_size: u64,
+    size: u64,
+    local_offset: u64,
+}
+
+/// Reads the central directory at the end of a zip file, without reading the
+/// entries themselves.
+pub(crate) fn read_directory<R: Read + Seek>(
+    reader: &mut R,
+) -> Result<Vec<(String, ZipEntry)>, ArchiveError> {
+    let len = reader.seek(SeekFrom::End(0))?;
+
+    // The end of central directory record is at least 22 bytes long, and is
+    // followed by a comment of up to 65535 bytes. The zip64 locator, if there
+    // is one, is the 20 bytes just before it.
+    let tail_len = len.min(20 + 22 + 0xffff);
+    let tail = read_at(reader, len - tail_len, tail_len)?;
+    let end = (0..=tail.len().saturating_sub(22))
+        .rev()
+        .find(|&offset| get_u32(&tail, offset) == Some(END_OF_DIRECTORY_SIGNATURE))
+        .ok_or(ArchiveError::Malformed("no end of central directory"))?;
+
+    let mut count = u64::from(field(get_u16(&tail, end + 10))?);
+    let mut directory_size = u64::from(field(get_u32(&tail, end + 12))?);
+    let mut directory_offset = u64::from(field(get_u32(&tail, end + 16))?);
+
+    if end >= 20 && get_u32(&tail, end - 20) == Some(ZIP64_LOCATOR_SIGNATURE) {
+        let record_offset = field(get_u64(&tail, end - 12))?;
+        let record = read_at(reader, record_offset, 56)?;
+        if get_u32(&record, 0) != Some(ZIP64_END_OF_DIRECTORY_SIGNATURE) {
+            return Err(ArchiveError::Malformed(
+                "bad zip64 end of central directory",
+            ));
+        }
+
+        count = field(get_u64(&record, 32))?;
+        directory_size = field(get_u64(&record, 40))?;
+        directory_offset = field(get_u64(&record, 48))?;
+    }
+
+    if directory_offset.saturating_add(directory_size) > len {
+        return Err(ArchiveError::Malformed("truncated central directory"));
+    }
+    let directory = read_at(reader, directory_offset, directory_size)?;
+
+    let mut position = 0;
+    let mut entries = Vec::new();
+    for _ in 0..count {
+        if get_u32(&directory, position) != Some(CENTRAL_HEADER_SIGNATURE) {
+            return Err(ArchiveError::Malformed("bad central directory entry"));
+        }
+
+        let method = field(get_u16(&directory, position + 10))?;
+        let crc = field(get_u32(&directory, position + 16))?;
+        let compressed_size = field(get_u32(&directory, position + 20))?;
+        let size = field(get_u32(&directory, position + 24))?;
+        let name_len = field(get_u16(&directory, position + 28))? as usize;
+        let extra_len = field(get_u16(&directory, position + 30))? as usize;
+        let comment_len = field(get_u16(&directory, position + 32))? as usize;
+        let local_offset = field(get_u32(&directory, position + 42))?;
+
+        let name_start = position + 46;
+        let name = directory
+            .get(name_start..name_start + name_len)
+            .ok_or(ArchiveError::Malformed("truncated entry name"))?;
+        let name = String::from_utf8_lossy(name).into_owned();
+        let extra = directory
+            .get(name_start + name_len..name_start + name_len + extra_len)
+            .ok_or(ArchiveError::Malformed("truncated entry extra field"))?;
+        position = name_start + name_len + extra_len + comment_len;
+
+        // Sizes and offsets that didn't fit in 32 bits are all 0xffffffff, and
+        // are found in order in the zip64 extra field.
+        let mut large = zip64_extra(extra).into_iter();
+        let mut widen = |value: u32| {
+            if value == u32::MAX {
+                large
+                    .next()
+                    .ok_or(ArchiveError::Malformed("missing zip64 extra field"))
+            } else {
+                Ok(u64::from(value))
+            }
+        };
+
+        let size = widen(size)?;
+        let compressed_size = widen(compressed_size)?;
+        let local_offset = widen(local_offset)?;
+
+        entries.push((
+            name,
+            ZipEntry {
+                method,
+                crc,
+                compressed_size,
+                size,
+                local_offset,
+            },
+        ));
+    }
+
+    Ok(entries)
+}
+
+/// Returns the values in an entry's zip64 extra field, if it has one.
+fn zip64_extra(mut extra: &[u8]) -> Vec<u64> {
+    while let (Some(id), Some(len)) = (get_u16(extra, 0), get_u16(extra, 2)) {
+        let len = len as usize;
+        let data = match extra.get(4..4 + len) {
+            Some(data) => data,
+            None => break,
+        };
+
+        if id == ZIP64_EXTRA_ID {
+            return (0..len / 8)
+                .filter_map(|index| get_u64(data, index * 8))
+                .collect();
+        }
+        extra = &extra[4 + len..];
+    }
+
+    Vec::new()
+}
+
+/// Reads and decompresses one entry of a zip file.
+pub(crate) fn read_entry<R: Read + Seek>(
+    reader: &mut R,
+    name: &str,
+    entry: &ZipEntry,
+) -> Result<Vec<u8>, ArchiveError> {
+    let header = read_at(reader, entry.local_offset, 30)?;
+    if get_u32(&header, 0) != Some(LOCAL_HEADER_SIGNATURE) {
+        return Err(ArchiveError::Malformed("bad local entry header"));
+    }
+
+    let local_name_len = field(get_u16(&header, 26))?;
+    let local_extra_len = field(get_u16(&header, 28))?;
+    let start = entry.local_offset + 30 + u64::from(local_name_len) + u64::from(local_extra_len);
+    let compressed = read_at(reader, start, entry.compressed_size)?;
+
+    let size = usize::try_from(entry.size).map_err(|_| ArchiveError::TooLarge)?;
+    let data = match entry.method {
+        STORED => compressed,
+        DEFLATED => decompress_to_vec_with_limit(&compressed, size)
+            .map_err(|_| ArchiveError::Malformed("entry is not a valid deflate stream"))?,
+        _ => return Err(ArchiveError::UnsupportedCompression(name.to_owned())),
+    };
+
+    if data.len() != size {
+        return Err(ArchiveError::Malformed("entry size does not match"));
+    }
+    if crc32(&data) != entry.crc {
+        return Err(ArchiveError::Malformed("entry checksum does not match"));
+    }
+
+    Ok(data)
+}
+
+/// Reads `len` bytes starting at `offset`. The bytes are read as they come,
+/// so a damaged length can't make this allocate more than the file holds.
+fn read_at<R: Read + Seek>(reader: &mut R, offset: u64, len: u64) -> Result<Vec<u8>, ArchiveError> {
+    reader.seek(SeekFrom::Start(offset))?;
+
+    let mut data = Vec::new();
+    reader.take(len).read_to_end(&mut data)?;
+    if (data.len() as u64) < len {
+        return Err(ArchiveError::Malformed("archive is truncated"));
+    }
+
+    Ok(data)
+}
+
+fn field<T>(value: Option<T>) -> Result<T, ArchiveError> {
+    value.ok_or(ArchiveError::Malformed("truncated central directory"))
+}
+
+fn get_u16(contents: &[u8], offset: usize) -> Option<u16> {
+    let bytes = contents.get(offset..offset + 2)?;
+    Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn get_u32(contents: &[u8], offset: usize) -> Option<u32> {
+    let bytes = contents.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn get_u64(contents: &[u8], offset: usize) -> Option<u64> {
+    let bytes = contents.get(offset..offset + 8)?;
+    Some(u64::from_le_bytes(bytes.try_into().ok()?))
+}
+
+fn put_u16(output: &mut Vec<u8>, value: u16) {
+    output.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u32(output: &mut Vec<u8>, value: u32) {
+    output.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u64(output: &mut Vec<u8>, value: u64) {
+    output.extend_from_slice(&value.to_le_bytes());
+}
+
+/// Computes the CRC-32 checksum that zip files use.
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    const TABLE: [u32; 256] = {
+        let mut table = [0; 256];
+        let mut index = 0;
+        while index < 256 {
+            let mut value = index as u32;
+            let mut bit = 0;
+            while bit < 8 {
+                value = if value & 1 == 1 {
+                    0xedb8_8320 ^ (value >> 1)
+                } else {
+                    value >> 1
+                };
+                bit += 1;
+            }
+            table[index] = value;
+            index += 1;
+        }
+        table
+    };
+
+    !data.iter().fold(!0, |crc, &byte| {
+        TABLE[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::InstanceBuilder;
+
+    #[test]
+    fn archive_round_trip() {
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let root_ref = dom.root_ref();
+        let workspace = dom.insert(root_ref, InstanceBuilder::new("Workspace"));
+        let part = dom.insert(workspace, InstanceBuilder::new("Part"));
+        let storage = dom.insert(root_ref, InstanceBuilder::new("ReplicatedStorage"));
+        dom.insert(
+            storage,
+            InstanceBuilder::new("ObjectValue").with_property("Value", part),
+        );
+        dom.insert(
+            root_ref,
+            InstanceBuilder::new("Folder").with_name("Workspace"),
+        );
+
+        let contents = PlaceArchive::from_dom(dom).unwrap().to_vec().unwrap();
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+
+        let archive = PlaceArchive::from_slice(&contents).unwrap();
+        let paths: Vec<&str> = archive
+            .fragments()
+            .iter()
+            .map(|fragment| fragment.path.as_str())
+            .collect();
+        assert_eq!(
+            paths,
+            [
+                "fragments/Workspace.rbxm",
+                "fragments/ReplicatedStorage.rbxm",
+                "fragments/Workspace-2.rbxm",
+            ]
+        );
+
+        let storage_path = archive.find_fragment("ReplicatedStorage").unwrap();
+        assert_eq!(
+            archive.fragments()[1].depends_on,
+            ["fragments/Workspace.rbxm"]
+        );
+        assert!(archive.fragments()[0].depends_on.is_empty());
+
+        let storage_only = archive.load(&[storage_path]).unwrap();
+        assert_eq!(storage_only.root().children().len(), 1);
+
+        let joined = archive.load_all().unwrap();
+        let children = joined.root().children();
+        assert_eq!(children.len(), 3);
+
+        let part = joined.get_by_ref(children[0]).unwrap().children()[0];
+        let value = joined.get_by_ref(children[1]).unwrap().children()[0];
+        assert_eq!(
+            joined.get_by_ref(value).unwrap().properties.get("Value"),
+            Some(&Variant::Ref(part))
+        );
+
+        assert!(matches!(
+            archive.load(&["fragments/Missing.rbxm"]),
+            Err(ArchiveError::MissingEntry(_))
+        ));
+        assert!(matches!(
+            PlaceArchive::from_slice(&contents[1..]),
+            Err(ArchiveError::Malformed(_))
+        ));
+
+        let mut reader = ArchiveReader::new(Cursor::new(&contents)).unwrap();
+        assert_eq!(reader.fragments(), archive.fragments());
+        let storage_only = reader.load(&[storage_path]).unwrap();
+        assert_eq!(storage_only.root().children().len(), 1);
+        assert_eq!(reader.load_all().unwrap().root().children().len(), 3);
+    }
+
+    #[test]
+    fn zip64() {
+        let dom = WeakDom::new(
+            InstanceBuilder::new("DataModel")
+                .with_child(InstanceBuilder::new("Workspace"))
+                .with_child(InstanceBuilder::new("Lighting")),
+        );
+        let archive = PlaceArchive::from_dom(dom).unwrap();
+
+        let mut contents = Vec::new();
+        archive.write_zip(&mut contents, true).unwrap();
+        assert_ne!(contents, archive.to_vec().unwrap());
+
+        let read = PlaceArchive::from_slice(&contents).unwrap();
+        assert_eq!(read.fragments(), archive.fragments());
+        assert_eq!(read.load_all().unwrap().root().children().len(), 2);
+    }
+
+    #[test]
+    fn deflated_entries() {
+        // Written by Python's zipfile with ZIP_DEFLATED, like an archive that
+        // was unzipped and zipped again by hand.
+        let contents = include_bytes!("../test-files/deflated-archive.zip");
+
+        let mut reader = ArchiveReader::new(Cursor::new(&contents[..])).unwrap();
+        let path = reader.find_fragment("Stuff").unwrap().to_owned();
+        let dom = reader.load(&[&path]).unwrap();
+
+        let folder = dom.get_by_ref(dom.root().children()[0]).unwrap();
+        let value = dom.get_by_ref(folder.children()[0]).unwrap();
+        assert_eq!(folder.name, "Stuff");
+        assert_eq!(
+            value.properties.get("Value"),
+            Some(&Variant::String(
+                "Hello, hello, hello from a deflated archive!".to_owned()
+            ))
+        );
+
+        let mut damaged = contents.to_vec();
+        let data_offset = damaged.len() / 2;
+        damaged[data_offset] ^= 0xff;
+        assert!(PlaceArchive::from_slice(&damaged).is_err());
+    }
+}
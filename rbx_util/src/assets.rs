@@ -0,0 +1,248 @@
+use std::collections::BTreeMap;
+
+use rbx_dom_weak::{
+    types::{Content, Ref, Variant},
+    WeakDom,
+};
+
+use crate::path::{descendants, full_name, text_value};
+
+/// The properties that refer to assets which a place's owner must have
+/// permission to use, such as audio and animations.
+const OWNED_ASSET_PROPERTIES: &[(&str, &str)] =
+    &[("Sound", "SoundId"), ("Animation", "AnimationId")];
+
+/// A property that refers to an asset, found by [`find_assets`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssetReference {
+    /// The referent of the instance with the property.
+    pub referent: Ref,
+
+    /// The instance's full name, like `Workspace.Part.Decal`.
+    pub path: String,
+
+    /// The instance's ClassName.
+    pub class: String,
+
+    /// The name of the property, like `Texture` or `SoundId`.
+    pub property: String,
+
+    /// The URL stored in the property.
+    pub url: String,
+
+    /// The numeric ID of the asset, if the URL is in one of the forms that
+    /// refer to an uploaded asset.
+    pub asset_id: Option<u64>,
+}
+
+/// Parses the numeric asset ID out of a URL like `rbxassetid://1234` or
+/// `https://www.roblox.com/asset/?id=1234`.
+///
+/// ```
+/// assert_eq!(rbx_util::parse_asset_id("rbxassetid://1818"), Some(1818));
+/// assert_eq!(rbx_util::parse_asset_id("http://www.roblox.com/asset/?id=1818"), Some(1818));
+/// assert_eq!(rbx_util::parse_asset_id("rbxasset://textures/face.png"), None);
+/// ```
+pub fn parse_asset_id(url: &str) -> Option<u64> {
+    let url = url.trim();
+
+    if let Some(id) = url.strip_prefix("rbxassetid://") {
+        return id.parse().ok();
+    }
+
+    let lowercase = url.to_ascii_lowercase();
+    let rest = lowercase
+        .strip_prefix("https://")
+        .or_else(|| lowercase.strip_prefix("http://"))?;
+    let rest = rest.strip_prefix("www.")?;
+    let query = rest
+        .strip_prefix("roblox.com/asset/?")
+        .or_else(|| rest.strip_prefix("roblox.com/asset?"))?;
+
+    query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("id="))
+        .and_then(|id| id.parse().ok())
+}
+
+/// Tells whether a string property holds something that looks like an asset
+/// URL. `Content` properties are always treated as asset URLs.
+fn is_asset_url(value: &str) -> bool {
+    parse_asset_id(value).is_some() || value.starts_with("rbxasset://")
+}
+
+/// Finds every asset URL in the DOM: every non-empty `Content` property, plus
+/// string properties whose value is an asset URL.
+pub fn find_assets(dom: &WeakDom) -> Vec<AssetReference> {
+    let mut found = Vec::new();
+
+    for referent in descendants(dom, dom.root_ref()) {
+        let instance = dom.get_by_ref(referent).unwrap();
+
+        // Sort properties so that results are stable between runs.
+        let mut properties: Vec<_> = instance.properties.iter().collect();
+        properties.sort_by(|a, b| a.0.cmp(b.0));
+
+        for (property, value) in properties {
+            let url = match value {
+                Variant::Content(_) => text_value(value).unwrap(),
+                Variant::String(_) | Variant::BinaryString(_) => match text_value(value) {
+                    Some(text) if is_asset_url(&text) => text,
+                    _ => continue,
+                },
+                _ => continue,
+            };
+
+            if url.is_empty() {
+                continue;
+            }
+
+            found.push(AssetReference {
+                referent,
+                path: full_name(dom, referent),
+                class: instance.class.clone(),
+                property: property.clone(),
+                url: url.to_string(),
+                asset_id: parse_asset_id(&url),
+            });
+        }
+    }
+
+    found
+}
+
+/// Calls `rewrite` with the URL of every asset found by [`find_assets`],
+/// replacing the URL with the return value when it is `Some`. Properties keep
+/// their existing type. Returns the number of properties that were changed.
+///
+/// ```
+/// use std::collections::HashMap;
+///
+/// use rbx_dom_weak::{types::Content, InstanceBuilder, WeakDom};
+///
+/// let mut dom = WeakDom::new(InstanceBuilder::new("DataModel").with_child(
+///     InstanceBuilder::new("Decal")
+///         .with_property("Texture", Content::from("rbxassetid://1")),
+/// ));
+///
+/// let mut uploaded = HashMap::new();
+/// uploaded.insert(1, 2);
+///
+/// let changed = rbx_util::rewrite_assets(&mut dom, |url| {
+///     let new_id = uploaded.get(&rbx_util::parse_asset_id(url)?)?;
+///     Some(format!("rbxassetid://{}", new_id))
+/// });
+///
+/// assert_eq!(changed, 1);
+/// assert_eq!(rbx_util::find_assets(&dom)[0].asset_id, Some(2));
+/// ```
+pub fn rewrite_assets<F>(dom: &mut WeakDom, mut rewrite: F) -> usize
+where
+    F: FnMut(&str) -> Option<String>,
+{
+    let mut changed = 0;
+
+    for asset in find_assets(dom) {
+        let new_url = match rewrite(&asset.url) {
+            Some(new_url) if new_url != asset.url => new_url,
+            _ => continue,
+        };
+
+        let instance = dom.get_by_ref_mut(asset.referent).unwrap();
+        let value = instance.properties.get_mut(&asset.property).unwrap();
+
+        *value = match value {
+            Variant::Content(_) => Content::from(new_url).into(),
+            Variant::BinaryString(_) => Variant::BinaryString(new_url.into_bytes().into()),
+            _ => Variant::String(new_url),
+        };
+        changed += 1;
+    }
+
+    changed
+}
+
+/// Finds every Sound and Animation that refers to an uploaded asset, grouped
+/// by asset ID. These assets only load in experiences that are allowed to use
+/// them, so they're the ones to check when auditing or migrating a place.
+pub fn audit_owned_assets(dom: &WeakDom) -> BTreeMap<u64, Vec<AssetReference>> {
+    let mut by_asset: BTreeMap<u64, Vec<AssetReference>> = BTreeMap::new();
+
+    for asset in find_assets(dom) {
+        let owned = OWNED_ASSET_PROPERTIES
+            .iter()
+            .any(|&(class, property)| asset.class == class && asset.property == property);
+
+        if let (true, Some(asset_id)) = (owned, asset.asset_id) {
+            by_asset.entry(asset_id).or_default().push(asset);
+        }
+    }
+
+    by_asset
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::InstanceBuilder;
+
+    #[test]
+    fn finds_content_and_strings() {
+        let dom = WeakDom::new(
+            InstanceBuilder::new("DataModel").with_child(
+                InstanceBuilder::new("Sound")
+                    .with_name("Music")
+                    .with_property("SoundId", Content::from("rbxassetid://5"))
+                    .with_property("Notes", "rbxassetid://6")
+                    .with_property("Description", "not an asset")
+                    .with_property("Empty", Content::new()),
+            ),
+        );
+
+        let found: Vec<_> = find_assets(&dom)
+            .into_iter()
+            .map(|asset| (asset.path, asset.property, asset.asset_id))
+            .collect();
+
+        assert_eq!(
+            found,
+            [
+                ("Music".to_owned(), "Notes".to_owned(), Some(6)),
+                ("Music".to_owned(), "SoundId".to_owned(), Some(5)),
+            ]
+        );
+    }
+
+    #[test]
+    fn audit() {
+        let dom = WeakDom::new(
+            InstanceBuilder::new("DataModel")
+                .with_child(
+                    InstanceBuilder::new("Sound")
+                        .with_name("A")
+                        .with_property("SoundId", Content::from("rbxassetid://10")),
+                )
+                .with_child(
+                    InstanceBuilder::new("Sound")
+                        .with_name("B")
+                        .with_property("SoundId", Content::from("rbxassetid://10")),
+                )
+                .with_child(
+                    InstanceBuilder::new("Animation")
+                        .with_property("AnimationId", Content::from("rbxassetid://20")),
+                )
+                .with_child(
+                    InstanceBuilder::new("Decal")
+                        .with_property("Texture", Content::from("rbxassetid://30")),
+                ),
+        );
+
+        let audit = audit_owned_assets(&dom);
+        assert_eq!(audit.keys().copied().collect::<Vec<_>>(), [10, 20]);
+
+        let mut paths: Vec<_> = audit[&10].iter().map(|asset| asset.path.as_str()).collect();
+        paths.sort_unstable();
+        assert_eq!(paths, ["A", "B"]);
+    }
+}
@@ -0,0 +1,66 @@
+//! Entry points for decoding and encoding models from async streams.
+//!
+//! The binary and XML codecs are synchronous, so these functions buffer the
+//! whole model in memory and only perform I/O asynchronously. Decoding and
+//! encoding themselves still run on the calling task; very large places should
+//! be handled with `spawn_blocking` or similar.
+
+use rbx_dom_weak::{types::Ref, WeakDom};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::{from_slice, to_vec, DecodeError, EncodeError, Format};
+
+/// Decodes a model or place from an async stream, detecting whether it is in
+/// the binary or XML format.
+///
+/// The entire stream is read into memory before decoding starts.
+pub async fn from_async_reader<R>(mut reader: R) -> Result<WeakDom, DecodeError>
+where
+    R: AsyncRead + Unpin,
+{
+    let mut contents = Vec::new();
+    reader.read_to_end(&mut contents).await?;
+
+    from_slice(&contents)
+}
+
+/// Encodes the given instances and their descendants in the given format and
+/// writes the result to an async stream.
+pub async fn to_async_writer<W>(
+    mut writer: W,
+    dom: &WeakDom,
+    refs: &[Ref],
+    format: Format,
+) -> Result<(), EncodeError>
+where
+    W: AsyncWrite + Unpin,
+{
+    let contents = to_vec(dom, refs, format)?;
+    writer.write_all(&contents).await?;
+    writer.flush().await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::InstanceBuilder;
+
+    #[tokio::test]
+    async fn round_trip() {
+        let dom = WeakDom::new(InstanceBuilder::new("Folder").with_name("Hello"));
+
+        for format in [Format::Binary, Format::Xml] {
+            let mut encoded = Vec::new();
+            to_async_writer(&mut encoded, &dom, &[dom.root_ref()], format)
+                .await
+                .unwrap();
+
+            let decoded = from_async_reader(encoded.as_slice()).await.unwrap();
+            let child = decoded.get_by_ref(decoded.root().children()[0]).unwrap();
+            assert_eq!(child.name, "Hello");
+        }
+    }
+}
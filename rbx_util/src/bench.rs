@@ -0,0 +1,340 @@
+//! Generators for large synthetic trees, for benchmarking and load testing.
+//!
+//! Real places are hard to share and come in only one size, so these build
+//! trees of any size with a chosen shape and mix of properties. The same
+//! settings and seed always build the same tree, so results can be compared
+//! across runs.
+//!
+//! ```
+//! use rbx_util::{bench::{PropertyMix, TreeGenerator, TreeShape}, to_vec, Format};
+//!
+//! let generator = TreeGenerator::new(1000)
+//!     .shape(TreeShape::Balanced { children: 8 })
+//!     .properties(PropertyMix::Mixed);
+//!
+//! let dom = generator.generate();
+//! let encoded = to_vec(&dom, &[dom.root_ref()], Format::Binary).unwrap();
+//! assert!(!encoded.is_empty());
+//! ```
+
+use rbx_dom_weak::{
+    types::{Attributes, CFrame, Color3uint8, Enum, Matrix3, Ref, Tags, Vector3},
+    InstanceBuilder, WeakDom,
+};
+
+use crate::path::descendants;
+
+/// How the instances of a generated tree are arranged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TreeShape {
+    /// Every instance is a child of the root.
+    Flat,
+
+    /// Every instance is the only child of the one before it.
+    Deep,
+
+    /// Every instance has the given number of children, filling in the tree
+    /// one level at a time.
+    Balanced {
+        /// How many children each instance has.
+        children: usize,
+    },
+}
+
+/// Which instances and properties a generated tree is made of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PropertyMix {
+    /// Folders with no properties, for measuring the cost of the hierarchy
+    /// alone.
+    Empty,
+
+    /// Parts with the properties a map is mostly made of, like CFrame, Size,
+    /// Color, and Material.
+    Parts,
+
+    /// ModuleScripts with a few dozen lines of source each.
+    Scripts,
+
+    /// Mostly parts, along with Models, ModuleScripts, ObjectValues pointing
+    /// at other instances, and Folders with attributes and tags.
+    Mixed,
+}
+
+/// Builds synthetic trees with a given number of instances.
+#[derive(Debug, Clone)]
+pub struct TreeGenerator {
+    instance_count: usize,
+    shape: TreeShape,
+    properties: PropertyMix,
+    seed: u64,
+}
+
+impl TreeGenerator {
+    /// Creates a generator for trees with the given number of instances under
+    /// the root, arranged as [`TreeShape::Flat`] with [`PropertyMix::Mixed`].
+    pub fn new(instance_count: usize) -> Self {
+        Self {
+            instance_count,
+            shape: TreeShape::Flat,
+            properties: PropertyMix::Mixed,
+            seed: 0x5eed,
+        }
+    }
+
+    /// Sets how the generated instances are arranged.
+    pub fn shape(mut self, shape: TreeShape) -> Self {
+        self.shape = shape;
+        self
+    }
+
+    /// Sets which instances and properties the tree is made of.
+    pub fn properties(mut self, properties: PropertyMix) -> Self {
+        self.properties = properties;
+        self
+    }
+
+    /// Sets the seed that property values are picked with. Generators with
+    /// the same settings and seed build the same trees.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Builds a tree whose root is a Folder named `Generated`.
+    pub fn generate(&self) -> WeakDom {
+        let mut rng = Rng::new(self.seed);
+        let mut dom = WeakDom::new(InstanceBuilder::new("Folder").with_name("Generated"));
+        let mut refs = vec![dom.root_ref()];
+
+        for index in 1..=self.instance_count {
+            let parent = match self.shape {
+                TreeShape::Flat => 0,
+                TreeShape::Deep => index - 1,
+                TreeShape::Balanced { children } => (index - 1) / children.max(1),
+            };
+
+            let builder = self.instance(index, &refs, &mut rng);
+            refs.push(dom.insert(refs[parent], builder));
+        }
+
+        dom
+    }
+
+    /// Changes roughly the given fraction of the instances in a generated
+    /// tree, returning how many were changed. Each one is renamed and, if it's
+    /// a part, moved, which gives diffing benchmarks something to find.
+    pub fn perturb(&self, dom: &mut WeakDom, fraction: f32) -> usize {
+        let mut rng = Rng::new(self.seed ^ 0xd1ff);
+        let mut changed = 0;
+
+        for referent in descendants(dom, dom.root_ref()).into_iter().skip(1) {
+            if rng.next_f32() >= fraction {
+                continue;
+            }
+
+            let instance = dom.get_by_ref_mut(referent).unwrap();
+            instance.name.push_str(" (changed)");
+
+            if instance.properties.contains_key("CFrame") {
+                instance
+                    .properties
+                    .insert("CFrame".to_owned(), random_cframe(&mut rng).into());
+            }
+
+            changed += 1;
+        }
+
+        changed
+    }
+
+    fn instance(&self, index: usize, refs: &[Ref], rng: &mut Rng) -> InstanceBuilder {
+        match self.properties {
+            PropertyMix::Empty => {
+                InstanceBuilder::new("Folder").with_name(format!("Folder{}", index))
+            }
+            PropertyMix::Parts => part(index, rng),
+            PropertyMix::Scripts => module_script(index, rng),
+            PropertyMix::Mixed => match rng.next_below(10) {
+                0..=5 => part(index, rng),
+                6 => InstanceBuilder::new("Model").with_name(format!("Model{}", index)),
+                7 => module_script(index, rng),
+                8 => InstanceBuilder::new("ObjectValue")
+                    .with_name(format!("Link{}", index))
+                    .with_property("Value", random_target(refs, rng)),
+                _ => tagged_folder(index, rng),
+            },
+        }
+    }
+}
+
+const MATERIALS: &[u32] = &[256, 272, 288, 512, 784, 800, 816, 1056, 1280, 1536];
+
+fn part(index: usize, rng: &mut Rng) -> InstanceBuilder {
+    InstanceBuilder::new("Part")
+        .with_name(format!("Part{}", index))
+        .with_property("CFrame", random_cframe(rng))
+        .with_property(
+            "Size",
+            Vector3::new(
+                rng.next_range(1.0, 16.0),
+                rng.next_range(1.0, 4.0),
+                rng.next_range(1.0, 16.0),
+            ),
+        )
+        .with_property(
+            "Color",
+            Color3uint8::new(
+                rng.next_below(256) as u8,
+                rng.next_below(256) as u8,
+                rng.next_below(256) as u8,
+            ),
+        )
+        .with_property(
+            "Material",
+            Enum::from_u32(MATERIALS[rng.next_below(MATERIALS.len())]),
+        )
+        .with_property("Anchored", rng.next_below(4) != 0)
+        .with_property("Transparency", 0.0f32)
+}
+
+fn module_script(index: usize, rng: &mut Rng) -> InstanceBuilder {
+    let mut source = String::from("local module = {}\n\n");
+
+    for line in 0..20 + rng.next_below(40) {
+        source.push_str(&format!(
+            "module.value{} = {}\n",
+            line,
+            rng.next_below(1_000_000)
+        ));
+    }
+
+    source.push_str("\nreturn module\n");
+
+    InstanceBuilder::new("ModuleScript")
+        .with_name(format!("Module{}", index))
+        .with_property("Source", source)
+}
+
+fn tagged_folder(index: usize, rng: &mut Rng) -> InstanceBuilder {
+    let mut tags = Tags::new();
+    tags.push("Generated");
+
+    let attributes = Attributes::new()
+        .with("Index", index as f64)
+        .with("Weight", rng.next_f32() as f64)
+        .with("Enabled", rng.next_below(2) == 0);
+
+    InstanceBuilder::new("Folder")
+        .with_name(format!("Folder{}", index))
+        .with_property("Attributes", attributes)
+        .with_property("Tags", tags)
+}
+
+/// Picks an instance generated before this one, leaving out the root so that
+/// encoding only the root's children doesn't leave the Ref dangling.
+fn random_target(refs: &[Ref], rng: &mut Rng) -> Ref {
+    match refs.len() {
+        0 | 1 => Ref::none(),
+        len => refs[1 + rng.next_below(len - 1)],
+    }
+}
+
+fn random_cframe(rng: &mut Rng) -> CFrame {
+    let position = Vector3::new(
+        rng.next_range(-512.0, 512.0),
+        rng.next_range(0.0, 64.0),
+        rng.next_range(-512.0, 512.0),
+    );
+
+    CFrame::new(position, Matrix3::identity())
+}
+
+/// A small xorshift generator. Benchmarks need the same trees on every run
+/// and platform, not good randomness.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // Xorshift never leaves zero, so nudge the seed away from it.
+        Self(seed | 1)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn next_below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+
+    fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    fn next_range(&mut self, min: f32, max: f32) -> f32 {
+        min + self.next_f32() * (max - min)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::fixtures::{assert_doms_eq, assert_round_trips};
+
+    #[test]
+    fn shapes() {
+        let flat = TreeGenerator::new(10).shape(TreeShape::Flat).generate();
+        assert_eq!(flat.root().children().len(), 10);
+
+        let deep = TreeGenerator::new(10).shape(TreeShape::Deep).generate();
+        assert_eq!(deep.root().children().len(), 1);
+        assert_eq!(descendants(&deep, deep.root_ref()).len(), 11);
+
+        let balanced = TreeGenerator::new(12)
+            .shape(TreeShape::Balanced { children: 3 })
+            .generate();
+        assert_eq!(balanced.root().children().len(), 3);
+        assert_eq!(descendants(&balanced, balanced.root_ref()).len(), 13);
+    }
+
+    #[test]
+    fn same_seed_same_tree() {
+        let generator = TreeGenerator::new(200).properties(PropertyMix::Mixed);
+        assert_doms_eq(&generator.generate(), &generator.generate());
+    }
+
+    #[test]
+    fn mixes_round_trip() {
+        for mix in [
+            PropertyMix::Empty,
+            PropertyMix::Parts,
+            PropertyMix::Scripts,
+            PropertyMix::Mixed,
+        ] {
+            let dom = TreeGenerator::new(100)
+                .shape(TreeShape::Balanced { children: 4 })
+                .properties(mix)
+                .generate();
+
+            assert_round_trips(&dom);
+        }
+    }
+
+    #[test]
+    fn perturb() {
+        let generator = TreeGenerator::new(500).properties(PropertyMix::Parts);
+        let original = generator.generate();
+        let mut changed = original.clone();
+
+        let count = generator.perturb(&mut changed, 0.1);
+        assert!(count > 0 && count < 500);
+        assert_ne!(
+            original.subtree_hash(original.root_ref()),
+            changed.subtree_hash(changed.root_ref())
+        );
+    }
+}
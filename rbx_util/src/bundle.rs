@@ -0,0 +1,508 @@
+use std::collections::HashMap;
+
+use rbx_dom_weak::{types::Ref, Instance, WeakDom};
+use regex::Regex;
+
+use crate::{
+    error::BundleError,
+    path::{full_name, text_value},
+    scripts::SCRIPT_CLASSES,
+};
+
+/// Defines the table of bundled modules and the function that stands in for
+/// `require` inside the bundle. Rolled-up modules are only run once, like they
+/// would be in Roblox.
+const PRELUDE: &str = "\
+local __bundle_modules = {}
+local __bundle_loaded = {}
+local __bundle_loading = {}
+local function __bundle_require(id)
+\tlocal loaded = __bundle_loaded[id]
+\tif loaded == __bundle_loading then
+\t\terror(\"cyclic require in bundled module \" .. id, 2)
+\telseif loaded == nil then
+\t\t__bundle_loaded[id] = __bundle_loading
+\t\tloaded = __bundle_modules[id]()
+\t\t__bundle_loaded[id] = loaded
+\tend
+\treturn loaded
+end
+";
+
+/// A script with the ModuleScripts it requires rolled into it, created by
+/// [`bundle_modules`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModuleBundle {
+    /// The source of the bundled script, which no longer requires any of the
+    /// bundled modules.
+    pub source: String,
+
+    /// The ModuleScripts that were rolled into the bundle, in the order that
+    /// they were first required.
+    pub modules: Vec<Ref>,
+}
+
+/// Rolls every ModuleScript required by the given script, directly or through
+/// other modules, into a single source.
+///
+/// Requires are followed when their argument is a path starting at `script`
+/// or `game`, made of `.Name`, `.Parent`, `["Name"]`, `:FindFirstChild("Name")`,
+/// `:WaitForChild("Name")`, and `:GetService("Name")`. Other requires, like
+/// requires of asset IDs, are left as they are, and so is anything inside
+/// comments and string literals. Inside bundled modules, `script` refers to
+/// the bundled script rather than the original module.
+///
+/// The DOM isn't changed, so the result can be written to the entry script
+/// with [`apply_script_edits`](crate::apply_script_edits) or to a new script.
+///
+/// ```
+/// use rbx_dom_weak::{InstanceBuilder, WeakDom};
+///
+/// let main = InstanceBuilder::new("Script")
+///     .with_property("Source", "local Util = require(script.Util)\nUtil.hello()");
+/// let main_ref = main.referent();
+///
+/// let dom = WeakDom::new(InstanceBuilder::new("DataModel").with_child(
+///     main.with_child(
+///         InstanceBuilder::new("ModuleScript")
+///             .with_name("Util")
+///             .with_property("Source", "return { hello = print }"),
+///     ),
+/// ));
+///
+/// let bundle = rbx_util::bundle_modules(&dom, main_ref)?;
+/// assert_eq!(bundle.modules.len(), 1);
+/// assert!(bundle.source.ends_with("local Util = __bundle_require(1)\nUtil.hello()"));
+/// # Ok::<(), rbx_util::BundleError>(())
+/// ```
+pub fn bundle_modules(dom: &WeakDom, entry: Ref) -> Result<ModuleBundle, BundleError> {
+    let instance = dom
+        .get_by_ref(entry)
+        .ok_or(BundleError::ScriptNotFound(entry))?;
+
+    if !SCRIPT_CLASSES.contains(&instance.class.as_str()) {
+        return Err(BundleError::NotAScript(full_name(dom, entry)));
+    }
+
+    let mut bundler = Bundler {
+        dom,
+        require_pattern: Regex::new(r"\brequire\s*\(((?:[^()]|\([^()]*\))*)\)").unwrap(),
+        ids: HashMap::new(),
+        modules: Vec::new(),
+    };
+
+    let entry_source = bundler.rewrite(entry)?;
+
+    // Rewriting a module can discover more modules, so this can't be a plain
+    // iterator over `modules`.
+    let mut module_sources = Vec::new();
+    while module_sources.len() < bundler.modules.len() {
+        let module = bundler.modules[module_sources.len()];
+        module_sources.push(bundler.rewrite(module)?);
+    }
+
+    if bundler.modules.is_empty() {
+        return Ok(ModuleBundle {
+            source: entry_source,
+            modules: Vec::new(),
+        });
+    }
+
+    let mut source = PRELUDE.to_owned();
+    for (index, (&module, module_source)) in bundler.modules.iter().zip(&module_sources).enumerate()
+    {
+        source.push_str(&format!(
+            "\n__bundle_modules[{}] = function() -- {}\n{}\nend\n",
+            index + 1,
+            full_name(dom, module),
+            module_source
+        ));
+    }
+    source.push('\n');
+    source.push_str(&entry_source);
+
+    Ok(ModuleBundle {
+        source,
+        modules: bundler.modules,
+    })
+}
+
+struct Bundler<'a> {
+    dom: &'a WeakDom,
+    require_pattern: Regex,
+
+    /// The 1-based index of each module in the bundle.
+    ids: HashMap<Ref, usize>,
+    modules: Vec<Ref>,
+}
+
+impl Bundler<'_> {
+    /// Returns the source of the given script with every require that can be
+    /// followed replaced by a require of the bundled module.
+    fn rewrite(&mut self, script: Ref) -> Result<String, BundleError> {
+        let instance = self.dom.get_by_ref(script).unwrap();
+        let source = instance
+            .properties
+            .get("Source")
+            .and_then(text_value)
+            .unwrap_or_default();
+
+        // Requires are searched for in a copy of the source without comments
+        // and strings, but the paths they're given are read from the source,
+        // since they can contain strings themselves.
+        let code = mask_comments_and_strings(&source);
+        let mut rewritten = String::with_capacity(source.len());
+        let mut last_end = 0;
+
+        for captures in self.require_pattern.captures_iter(&code) {
+            let whole = captures.get(0).unwrap();
+            let path = &source[captures.get(1).unwrap().range()];
+            let target = match resolve_path(self.dom, script, path) {
+                Some(target) => target,
+                None => continue,
+            };
+
+            let target_instance = self.dom.get_by_ref(target).unwrap();
+            if target_instance.class != "ModuleScript" {
+                return Err(BundleError::NotAModule {
+                    script: full_name(self.dom, script),
+                    required: full_name(self.dom, target),
+                });
+            }
+
+            let modules = &mut self.modules;
+            let id = *self.ids.entry(target).or_insert_with(|| {
+                modules.push(target);
+                modules.len()
+            });
+
+            rewritten.push_str(&source[last_end..whole.start()]);
+            rewritten.push_str(&format!("__bundle_require({})", id));
+            last_end = whole.end();
+        }
+
+        rewritten.push_str(&source[last_end..]);
+        Ok(rewritten)
+    }
+}
+
+/// Finds the instance named by a path expression like
+/// `script.Parent.Shared:WaitForChild("Util")`, relative to the given script.
+fn resolve_path(dom: &WeakDom, script: Ref, expression: &str) -> Option<Ref> {
+    let (start, mut rest) = identifier(expression.trim())?;
+    let mut current = match start {
+        "script" => script,
+        "game" => dom.root_ref(),
+        _ => return None,
+    };
+
+    loop {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            return Some(current);
+        }
+
+        let instance = dom.get_by_ref(current)?;
+
+        if let Some(after_dot) = rest.strip_prefix('.') {
+            let (name, after_name) = identifier(after_dot.trim_start())?;
+            rest = after_name;
+
+            current = match name {
+                "Parent" => instance.parent(),
+                _ => find_child(dom, instance, |child| child.name == name)?,
+            };
+
+            if current.is_none() {
+                return None;
+            }
+        } else if let Some(after_colon) = rest.strip_prefix(':') {
+            let (method, after_method) = identifier(after_colon.trim_start())?;
+            let after_paren = after_method.trim_start().strip_prefix('(')?;
+            let (name, after_name) = string_literal(after_paren.trim_start())?;
+
+            // WaitForChild can be given a timeout, which doesn't matter here.
+            let close = after_name.find(')')?;
+            rest = &after_name[close + 1..];
+
+            current = match method {
+                "GetService" => find_child(dom, instance, |child| child.class == name)?,
+                "FindFirstChild" | "WaitForChild" => {
+                    find_child(dom, instance, |child| child.name == name)?
+                }
+                _ => return None,
+            };
+        } else if let Some(after_bracket) = rest.strip_prefix('[') {
+            let (name, after_name) = string_literal(after_bracket.trim_start())?;
+            rest = after_name.trim_start().strip_prefix(']')?;
+
+            current = find_child(dom, instance, |child| child.name == name)?;
+        } else {
+            return None;
+        }
+    }
+}
+
+/// Returns a copy of a Luau source with its comments and string literals
+/// replaced by spaces. Only whole characters are replaced, and with one space
+/// per byte, so positions in the copy are the same as in the source.
+fn mask_comments_and_strings(source: &str) -> String {
+    let bytes = source.as_bytes();
+    let mut masked = bytes.to_vec();
+
+    // For each interpolated string whose `{}` expression is being read, how
+    // many braces deep into the expression the code is.
+    let mut interpolations: Vec<usize> = Vec::new();
+
+    let mut index = 0;
+    while index < bytes.len() {
+        let start = index;
+
+        index = match bytes[index] {
+            b'-' if bytes.get(index + 1) == Some(&b'-') => match long_bracket(bytes, index + 2) {
+                Some(level) => close_long_bracket(source, index + 2, level),
+                None => source[index..]
+                    .find('\n')
+                    .map_or(source.len(), |end| index + end),
+            },
+            b'[' if long_bracket(bytes, index).is_some() => {
+                close_long_bracket(source, index, long_bracket(bytes, index).unwrap())
+            }
+            b'"' | b'\'' => end_of_quoted(bytes, index + 1, bytes[index]),
+            b'`' => end_of_interpolated(bytes, index + 1, &mut interpolations),
+            b'{' if !interpolations.is_empty() => {
+                *interpolations.last_mut().unwrap() += 1;
+                index += 1;
+                continue;
+            }
+            b'}' if !interpolations.is_empty() => match interpolations.last_mut().unwrap() {
+                0 => {
+                    interpolations.pop();
+                    end_of_interpolated(bytes, index + 1, &mut interpolations)
+                }
+                depth => {
+                    *depth -= 1;
+                    index += 1;
+                    continue;
+                }
+            },
+            _ => {
+                index += 1;
+                continue;
+            }
+        };
+
+        masked[start..index].fill(b' ');
+    }
+
+    String::from_utf8(masked).unwrap()
+}
+
+/// Returns the level of the long bracket, like `[==[`, starting at `index`.
+fn long_bracket(bytes: &[u8], index: usize) -> Option<usize> {
+    if bytes.get(index) != Some(&b'[') {
+        return None;
+    }
+
+    let level = bytes[index + 1..]
+        .iter()
+        .take_while(|&&byte| byte == b'=')
+        .count();
+
+    match bytes.get(index + 1 + level) {
+        Some(b'[') => Some(level),
+        _ => None,
+    }
+}
+
+/// Returns where the long comment or string opened at `index` ends.
+fn close_long_bracket(source: &str, index: usize, level: usize) -> usize {
+    let close = format!("]{}]", "=".repeat(level));
+
+    source[index..]
+        .find(&close)
+        .map_or(source.len(), |end| index + end + close.len())
+}
+
+/// Returns where the string quoted with `quote`, whose contents start at
+/// `index`, ends. Strings without a closing quote end at the end of the line.
+fn end_of_quoted(bytes: &[u8], mut index: usize, quote: u8) -> usize {
+    while index < bytes.len() {
+        match bytes[index] {
+            b'\\' => index += 2,
+            b'\n' => return index,
+            byte if byte == quote => return index + 1,
+            _ => index += 1,
+        }
+    }
+
+    bytes.len()
+}
+
+/// Returns where the part of an interpolated string starting at `index`
+/// ends, which is either at its closing backtick or at the `{` of an
+/// expression, which is then added to `interpolations`.
+fn end_of_interpolated(bytes: &[u8], mut index: usize, interpolations: &mut Vec<usize>) -> usize {
+    while index < bytes.len() {
+        match bytes[index] {
+            b'\\' => index += 2,
+            b'`' => return index + 1,
+            b'{' => {
+                interpolations.push(0);
+                return index + 1;
+            }
+            _ => index += 1,
+        }
+    }
+
+    bytes.len()
+}
+
+fn find_child<F>(dom: &WeakDom, parent: &Instance, predicate: F) -> Option<Ref>
+where
+    F: Fn(&Instance) -> bool,
+{
+    parent
+        .children()
+        .iter()
+        .copied()
+        .find(|&child| predicate(dom.get_by_ref(child).unwrap()))
+}
+
+/// Splits a Luau identifier off the start of `text`.
+fn identifier(text: &str) -> Option<(&str, &str)> {
+    let end = text
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .unwrap_or(text.len());
+
+    if end == 0 || text.as_bytes()[0].is_ascii_digit() {
+        return None;
+    }
+
+    Some(text.split_at(end))
+}
+
+/// Splits a quoted string without escapes off the start of `text`, returning
+/// its contents and the text after it.
+fn string_literal(text: &str) -> Option<(&str, &str)> {
+    let quote = text.chars().next().filter(|&c| c == '"' || c == '\'')?;
+    let contents = &text[1..];
+    let end = contents.find(quote)?;
+
+    if contents[..end].contains('\\') {
+        return None;
+    }
+
+    Some((&contents[..end], &contents[end + 1..]))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::InstanceBuilder;
+
+    #[test]
+    fn follows_requires() {
+        let main = InstanceBuilder::new("Script").with_property(
+            "Source",
+            "local A = require(script.Parent.A)\n\
+             local B = require(game:GetService(\"ReplicatedStorage\")[\"B\"])\n\
+             local Remote = require(1234)\n\
+             return require(script.Parent:WaitForChild(\"A\", 5))",
+        );
+        let main_ref = main.referent();
+
+        let dom = WeakDom::new(
+            InstanceBuilder::new("DataModel")
+                .with_child(
+                    InstanceBuilder::new("ServerScriptService")
+                        .with_child(main)
+                        .with_child(
+                            InstanceBuilder::new("ModuleScript")
+                                .with_name("A")
+                                .with_property(
+                                    "Source",
+                                    "return require(game.ReplicatedStorage.B)",
+                                ),
+                        ),
+                )
+                .with_child(
+                    InstanceBuilder::new("ReplicatedStorage").with_child(
+                        InstanceBuilder::new("ModuleScript")
+                            .with_name("B")
+                            .with_property("Source", "return 2"),
+                    ),
+                ),
+        );
+
+        let bundle = bundle_modules(&dom, main_ref).unwrap();
+
+        let paths: Vec<_> = bundle
+            .modules
+            .iter()
+            .map(|&module| full_name(&dom, module))
+            .collect();
+        assert_eq!(paths, ["ServerScriptService.A", "ReplicatedStorage.B"]);
+
+        assert!(bundle.source.starts_with(PRELUDE));
+        assert!(bundle.source.contains(
+            "__bundle_modules[1] = function() -- ServerScriptService.A\n\
+             return __bundle_require(2)\n\
+             end\n"
+        ));
+        assert!(bundle.source.ends_with(
+            "local A = __bundle_require(1)\n\
+             local B = __bundle_require(2)\n\
+             local Remote = require(1234)\n\
+             return __bundle_require(1)"
+        ));
+    }
+
+    #[test]
+    fn skips_comments_and_strings() {
+        let source = "-- require(script.A)\n\
+             --[[ require(script.A) ]]\n\
+             --[==[ ]] require(script.A) ]==]\n\
+             local s = \"require(script.A)\" .. 'it\\'s require(script.A)' .. [[require(script.A)]]\n\
+             local t = `{require(script.B)} require(script.A) {{ a = \"}\" }}`";
+
+        let b = InstanceBuilder::new("ModuleScript")
+            .with_name("B")
+            .with_property("Source", "return 2");
+        let b_ref = b.referent();
+
+        let main = InstanceBuilder::new("Script")
+            .with_property("Source", source)
+            .with_child(
+                InstanceBuilder::new("ModuleScript")
+                    .with_name("A")
+                    .with_property("Source", "return 1"),
+            )
+            .with_child(b);
+        let main_ref = main.referent();
+
+        let dom = WeakDom::new(InstanceBuilder::new("DataModel").with_child(main));
+        let bundle = bundle_modules(&dom, main_ref).unwrap();
+
+        assert_eq!(bundle.modules, [b_ref]);
+        assert!(bundle
+            .source
+            .ends_with(&source.replace("`{require(script.B)}", "`{__bundle_require(1)}")));
+    }
+
+    #[test]
+    fn requires_must_be_modules() {
+        let main = InstanceBuilder::new("LocalScript")
+            .with_property("Source", "require(script.Other)")
+            .with_child(InstanceBuilder::new("Script").with_name("Other"));
+        let main_ref = main.referent();
+
+        let dom = WeakDom::new(InstanceBuilder::new("DataModel").with_child(main));
+
+        assert!(matches!(
+            bundle_modules(&dom, main_ref),
+            Err(BundleError::NotAModule { required, .. }) if required == "LocalScript.Other"
+        ));
+    }
+}
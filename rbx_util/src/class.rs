@@ -0,0 +1,41 @@
+use rbx_reflection::{ClassDescriptor, ClassTag};
+
+/// Classes that Roblox only allows to be parented to one class of service,
+/// which the reflection database doesn't describe.
+pub(crate) const REQUIRED_PARENTS: &[(&str, &str)] = &[("Terrain", "Workspace")];
+
+/// Returns the descriptor for `class` followed by the descriptors of each of
+/// its superclasses. Classes that aren't in the reflection database have no
+/// descriptors.
+pub(crate) fn class_descriptors(
+    class: &str,
+) -> impl Iterator<Item = &'static ClassDescriptor<'static>> {
+    let database = rbx_reflection_database::get();
+    let mut current = database.classes.get(class);
+
+    std::iter::from_fn(move || {
+        let descriptor = current?;
+        current = descriptor
+            .superclass
+            .as_deref()
+            .and_then(|superclass| database.classes.get(superclass));
+
+        Some(descriptor)
+    })
+}
+
+/// Tells whether `class` is `superclass` or inherits from it, according to the
+/// reflection database. Classes that aren't in the database only match
+/// themselves.
+pub(crate) fn is_a(class: &str, superclass: &str) -> bool {
+    class == superclass || class_descriptors(class).any(|descriptor| descriptor.name == superclass)
+}
+
+/// Tells whether `class` is a service, which Roblox only allows as a child of
+/// the DataModel.
+pub(crate) fn is_service(class: &str) -> bool {
+    rbx_reflection_database::get()
+        .classes
+        .get(class)
+        .is_some_and(|descriptor| descriptor.tags.contains(&ClassTag::Service))
+}
@@ -0,0 +1,1207 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    convert::TryFrom,
+    hash::Hasher,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use rbx_dom_weak::{
+    types::{Ref, SharedString, StableHasher, Variant, VariantType},
+    Instance, InstanceBuilder, WeakDom,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{error::DeltaError, path::descendants, IgnoreRules};
+
+/// The bytes that every delta file starts with.
+const DELTA_MAGIC: &[u8; 8] = b"RBXDELTA";
+
+/// The version of the delta format written by [`PlaceDelta`].
+const DELTA_VERSION: u8 = 2;
+
+/// The changes that turn one model or place into another, which are usually
+/// much smaller than either of them.
+///
+/// Deltas are computed from the trees themselves, not from any file they were
+/// read from, so they don't depend on the format or encoder the trees are
+/// saved with. Instances in the new tree are matched up with instances in the
+/// old one by their position, class, and name, and the delta records the
+/// instances that were added, removed, or moved, and the names and
+/// properties that changed. Applying it to the same old tree, even one that
+/// was read from a file again, gives back the new tree. Instances keep their
+/// order and properties, but not their referents.
+///
+/// ```
+/// use rbx_dom_weak::{InstanceBuilder, WeakDom};
+/// use rbx_util::PlaceDelta;
+///
+/// let old = WeakDom::new(
+///     InstanceBuilder::new("DataModel").with_child(InstanceBuilder::new("Workspace")),
+/// );
+/// let new = WeakDom::new(
+///     InstanceBuilder::new("DataModel")
+///         .with_child(InstanceBuilder::new("Workspace"))
+///         .with_child(InstanceBuilder::new("Lighting")),
+/// );
+///
+/// let delta = PlaceDelta::between(&old, &new)?.to_vec();
+///
+/// let updated = PlaceDelta::from_slice(&delta)?.apply(&old)?;
+/// assert_eq!(updated.root().children().len(), 2);
+/// # Ok::<(), rbx_util::DeltaError>(())
+/// ```
+///
+/// Deltas can also be stored with serde, for example as JSON in an ops log.
+/// They're written with a `version` field, and reading a delta with a
+/// different version fails instead of giving the wrong tree.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "SerializedDelta", try_from = "SerializedDelta")]
+pub struct PlaceDelta {
+    old_len: u64,
+    old_checksum: u64,
+    new_checksum: u64,
+
+    /// The new tree, starting with its root, parents before their children.
+    ops: Vec<DeltaOp>,
+}
+
+/// One step of a [`PlaceDelta`], given by [`PlaceDelta::ops`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub enum DeltaOp {
+    /// One instance of the new tree.
+    Node(DeltaNode),
+
+    /// Siblings of the old tree which are in the new tree along with all of
+    /// their descendants without any changes.
+    Copy {
+        /// The position of the first sibling in the old tree.
+        index: u64,
+
+        /// How many siblings are copied.
+        count: u64,
+    },
+}
+
+/// One instance of the new tree in a [`PlaceDelta`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeltaNode {
+    source: DeltaSource,
+
+    /// Properties that were added or changed, sorted by name. Every property
+    /// of new instances is listed.
+    set: Vec<(String, DeltaValue)>,
+
+    /// Properties of the old instance that were removed, sorted by name.
+    removed: Vec<String>,
+
+    /// How many of the ops after this one, and their descendants, are its
+    /// children.
+    children: u64,
+}
+
+impl DeltaNode {
+    /// Returns where the instance comes from.
+    pub fn source(&self) -> &DeltaSource {
+        &self.source
+    }
+
+    /// Returns the properties that were added or changed, sorted by name.
+    /// Every property of new instances is listed.
+    pub fn set(&self) -> &[(String, DeltaValue)] {
+        &self.set
+    }
+
+    /// Returns the properties of the old instance that were removed, sorted
+    /// by name.
+    pub fn removed(&self) -> &[String] {
+        &self.removed
+    }
+
+    /// Returns how many of the ops after this one, and their descendants, are
+    /// the instance's children.
+    pub fn children(&self) -> u64 {
+        self.children
+    }
+}
+
+/// Where an instance in a [`DeltaNode`] comes from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub enum DeltaSource {
+    /// The instance is one from the old tree.
+    Old {
+        /// The position of the instance in the old tree. Positions count the
+        /// root and then its descendants, parents before their children.
+        index: u64,
+
+        /// The instance's new name, if it was renamed.
+        name: Option<String>,
+    },
+
+    /// The instance isn't in the old tree.
+    New {
+        /// The ClassName of the instance.
+        class: String,
+
+        /// The Name of the instance.
+        name: String,
+    },
+}
+
+/// The new value of a property in a [`DeltaNode`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub enum DeltaValue {
+    /// A `Ref` to the instance at this position in the new tree, or to
+    /// nothing.
+    Ref(Option<u64>),
+
+    /// The contents of a `SharedString`, which can't be encoded as part of a
+    /// `Variant`.
+    SharedString(Vec<u8>),
+
+    /// Any other value, encoded with MessagePack.
+    Value(Vec<u8>),
+}
+
+impl DeltaValue {
+    /// Decodes the value. `Ref` values give `None`, since they point at a
+    /// position in the new tree instead of at an instance.
+    pub fn variant(&self) -> Result<Option<Variant>, DeltaError> {
+        match self {
+            DeltaValue::Ref(_) => Ok(None),
+            DeltaValue::SharedString(bytes) => Ok(Some(SharedString::new(bytes.clone()).into())),
+            DeltaValue::Value(bytes) => Ok(Some(rmp_serde::from_slice(bytes)?)),
+        }
+    }
+}
+
+/// The form [`PlaceDelta`] takes with serde, which has the version of the
+/// format in it.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SerializedDelta {
+    version: u8,
+    old_len: u64,
+    old_checksum: u64,
+    new_checksum: u64,
+    ops: Vec<DeltaOp>,
+}
+
+impl From<PlaceDelta> for SerializedDelta {
+    fn from(delta: PlaceDelta) -> Self {
+        SerializedDelta {
+            version: DELTA_VERSION,
+            old_len: delta.old_len,
+            old_checksum: delta.old_checksum,
+            new_checksum: delta.new_checksum,
+            ops: delta.ops,
+        }
+    }
+}
+
+impl TryFrom<SerializedDelta> for PlaceDelta {
+    type Error = DeltaError;
+
+    fn try_from(delta: SerializedDelta) -> Result<Self, Self::Error> {
+        if delta.version != DELTA_VERSION {
+            return Err(DeltaError::UnsupportedVersion(delta.version));
+        }
+
+        Ok(PlaceDelta {
+            old_len: delta.old_len,
+            old_checksum: delta.old_checksum,
+            new_checksum: delta.new_checksum,
+            ops: delta.ops,
+        })
+    }
+}
+
+/// The children of an instance in the new tree while its ops are written.
+enum Group {
+    Instance(Ref),
+    Copy { first: Ref, last: Ref, count: u64 },
+}
+
+impl PlaceDelta {
+    /// Computes the delta that turns `old` into `new`.
+    pub fn between(old: &WeakDom, new: &WeakDom) -> Result<Self, DeltaError> {
+        Self::between_cancellable(old, new, Arc::new(AtomicBool::new(false)))
+    }
+
+    /// Like [`PlaceDelta::between`], but stops with
+    /// [`DeltaError::Cancelled`] as soon as another thread sets `cancel` to
+    /// `true`, so that tools can abandon deltas of large places that are no
+    /// longer needed.
+    pub fn between_cancellable(
+        old: &WeakDom,
+        new: &WeakDom,
+        cancel: Arc<AtomicBool>,
+    ) -> Result<Self, DeltaError> {
+        let check = || {
+            if cancel.load(Ordering::Relaxed) {
+                Err(DeltaError::Cancelled)
+            } else {
+                Ok(())
+            }
+        };
+
+        let old_order = descendants(old, old.root_ref());
+        let new_order = descendants(new, new.root_ref());
+        let old_index = positions(&old_order);
+        let new_index = positions(&new_order);
+
+        // Pairs of matching instances, from old to new and the other way.
+        let matched_old = match_trees(old, new, check)?;
+        let matches: HashMap<Ref, Ref> = matched_old
+            .iter()
+            .map(|(&old_ref, &new_ref)| (new_ref, old_ref))
+            .collect();
+
+        // Where a `Ref` property of an old instance points in the new tree,
+        // or `None` if it points at an instance that isn't in it.
+        let old_target = |target: &Ref| {
+            matched_old
+                .get(target)
+                .and_then(|new_ref| new_index.get(new_ref))
+                .map(|&index| index as u64)
+        };
+
+        let mut nodes = HashMap::with_capacity(new_order.len());
+        for &new_ref in &new_order {
+            check()?;
+            let instance = new.get_by_ref(new_ref).unwrap();
+            let old_instance = matches
+                .get(&new_ref)
+                .map(|old_ref| old.get_by_ref(*old_ref).unwrap());
+
+            let mut set = Vec::new();
+            for (key, value) in instance.properties.iter() {
+                let old_value = old_instance.and_then(|old| old.properties.get(key));
+
+                let value = match value {
+                    Variant::Ref(target) => {
+                        let target = new_index.get(target).map(|&index| index as u64);
+                        match old_value {
+                            Some(Variant::Ref(old)) if old_target(old) == target => continue,
+                            _ => DeltaValue::Ref(target),
+                        }
+                    }
+                    value if old_value == Some(value) => continue,
+                    Variant::SharedString(value) => DeltaValue::SharedString(value.data().to_vec()),
+                    value => DeltaValue::Value(rmp_serde::to_vec(value)?),
+                };
+
+                set.push((key.clone(), value));
+            }
+            set.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+
+            let (source, mut removed) = match old_instance {
+                Some(old_instance) => {
+                    let source = DeltaSource::Old {
+                        index: old_index[&matches[&new_ref]] as u64,
+                        name: Some(instance.name.clone()).filter(|name| *name != old_instance.name),
+                    };
+                    let removed: Vec<String> = old_instance
+                        .properties
+                        .keys()
+                        .filter(|key| !instance.properties.contains_key(key.as_str()))
+                        .cloned()
+                        .collect();
+
+                    (source, removed)
+                }
+                None => {
+                    let source = DeltaSource::New {
+                        class: instance.class.clone(),
+                        name: instance.name.clone(),
+                    };
+
+                    (source, Vec::new())
+                }
+            };
+            removed.sort_unstable();
+
+            nodes.insert(
+                new_ref,
+                DeltaNode {
+                    source,
+                    set,
+                    removed,
+                    children: 0,
+                },
+            );
+        }
+
+        // Instances that are the same as the old instance they're matched
+        // with, all the way down, are copied from the old tree instead of
+        // being listed one by one.
+        let mut unchanged = HashSet::new();
+        for &new_ref in new_order.iter().rev() {
+            let node = &nodes[&new_ref];
+            if !matches!(node.source, DeltaSource::Old { name: None, .. })
+                || !node.set.is_empty()
+                || !node.removed.is_empty()
+            {
+                continue;
+            }
+
+            let old_children = old.get_by_ref(matches[&new_ref]).unwrap().children();
+            let new_children = new.get_by_ref(new_ref).unwrap().children();
+            if old_children.len() == new_children.len()
+                && new_children
+                    .iter()
+                    .zip(old_children)
+                    .all(|(new_child, old_child)| {
+                        matches.get(new_child) == Some(old_child) && unchanged.contains(new_child)
+                    })
+            {
+                unchanged.insert(new_ref);
+            }
+        }
+
+        // The position of each old instance among its siblings, to find runs
+        // of unchanged siblings that can be copied together.
+        let mut sibling_index = HashMap::with_capacity(old_order.len());
+        for &old_ref in &old_order {
+            let children = old.get_by_ref(old_ref).unwrap().children();
+            sibling_index.extend(
+                children
+                    .iter()
+                    .enumerate()
+                    .map(|(index, &child)| (child, index)),
+            );
+        }
+
+        let mut ops = Vec::new();
+        let mut stack = vec![Group::Instance(new.root_ref())];
+        while let Some(group) = stack.pop() {
+            check()?;
+            let new_ref = match group {
+                Group::Instance(new_ref) => new_ref,
+                Group::Copy { first, count, .. } => {
+                    let index = old_index[&first] as u64;
+                    ops.push(DeltaOp::Copy { index, count });
+                    continue;
+                }
+            };
+
+            let mut groups = Vec::new();
+            for child in new.get_by_ref(new_ref).unwrap().children() {
+                if !unchanged.contains(child) {
+                    groups.push(Group::Instance(*child));
+                    continue;
+                }
+
+                let old_child = matches[child];
+                match groups.last_mut() {
+                    Some(Group::Copy { last, count, .. })
+                        if sibling_index[last] + 1 == sibling_index[&old_child] =>
+                    {
+                        *last = old_child;
+                        *count += 1;
+                    }
+                    _ => groups.push(Group::Copy {
+                        first: old_child,
+                        last: old_child,
+                        count: 1,
+                    }),
+                }
+            }
+
+            let mut node = nodes.remove(&new_ref).unwrap();
+            node.children = groups.len() as u64;
+            ops.push(DeltaOp::Node(node));
+            stack.extend(groups.into_iter().rev());
+        }
+
+        Ok(PlaceDelta {
+            old_len: old_order.len() as u64,
+            old_checksum: checksum(old, &old_order),
+            new_checksum: checksum(new, &new_order),
+            ops,
+        })
+    }
+
+    /// Computes the delta that turns `old` into `new` as if the instances
+    /// left out by `rules` weren't in either of them. Neither tree is changed.
+    ///
+    /// The delta has to be applied with [`PlaceDelta::apply_ignoring`] and
+    /// the same rules, and gives back `new` without its ignored instances.
+    pub fn between_ignoring(
+        old: &WeakDom,
+        new: &WeakDom,
+        rules: &IgnoreRules,
+    ) -> Result<Self, DeltaError> {
+        Self::between(&rules.without_ignored(old), &rules.without_ignored(new))
+    }
+
+    /// Applies a delta computed by [`PlaceDelta::between_ignoring`] to the
+    /// tree it was computed from, leaving out the instances ignored by
+    /// `rules` like it did.
+    pub fn apply_ignoring(
+        &self,
+        old: &WeakDom,
+        rules: &IgnoreRules,
+    ) -> Result<WeakDom, DeltaError> {
+        self.apply(&rules.without_ignored(old))
+    }
+
+    /// Returns the steps that build the new tree, starting with its root,
+    /// parents before their children. [`DeltaPrinter`][crate::DeltaPrinter]
+    /// shows them as text.
+    pub fn ops(&self) -> &[DeltaOp] {
+        &self.ops
+    }
+
+    /// Applies the delta to the tree it was computed from, returning the new
+    /// tree.
+    ///
+    /// Returns [`DeltaError::WrongBase`] if `old` is not the tree the delta
+    /// was computed from.
+    pub fn apply(&self, old: &WeakDom) -> Result<WeakDom, DeltaError> {
+        let old_order = descendants(old, old.root_ref());
+        self.check_base(old, &old_order)?;
+
+        // Where each instance of the old tree that's kept ends up in the new
+        // one, so that `Ref` properties that didn't change can be moved over,
+        // and the old instances that each copy is made of.
+        let mut kept = HashMap::new();
+        let mut copies = Vec::new();
+        let mut position = 0;
+        for op in &self.ops {
+            match op {
+                DeltaOp::Node(node) => {
+                    if let DeltaSource::Old { index, .. } = node.source {
+                        kept.insert(old_instance(&old_order, index)?, position);
+                    }
+                    position += 1;
+                }
+                DeltaOp::Copy { index, count } => {
+                    let mut copied = Vec::new();
+                    for &sibling in copied_siblings(old, &old_order, *index, *count)? {
+                        for old_ref in descendants(old, sibling) {
+                            kept.insert(old_ref, position);
+                            copied.push(old_ref);
+                            position += 1;
+                        }
+                    }
+                    copies.push(copied);
+                }
+            }
+        }
+
+        let mut refs = Vec::new();
+        let mut ops = self.ops.iter();
+        let root = match ops.next() {
+            Some(DeltaOp::Node(node)) => node,
+            _ => return Err(DeltaError::Malformed("delta has no root instance")),
+        };
+
+        let mut dom = WeakDom::new(build_node(root, old, &old_order, 0, &kept, &mut refs)?);
+        let mut referents = vec![dom.root_ref()];
+        let mut parents = vec![(dom.root_ref(), root.children)];
+        let mut copies = copies.into_iter();
+
+        for op in ops {
+            while let Some((_, 0)) = parents.last() {
+                parents.pop();
+            }
+            let (parent, remaining) = parents
+                .last_mut()
+                .ok_or(DeltaError::Malformed("instance has no parent"))?;
+            *remaining -= 1;
+            let parent = *parent;
+
+            match op {
+                DeltaOp::Node(node) => {
+                    let builder =
+                        build_node(node, old, &old_order, referents.len(), &kept, &mut refs)?;
+                    let referent = dom.insert(parent, builder);
+                    referents.push(referent);
+                    parents.push((referent, node.children));
+                }
+                DeltaOp::Copy { .. } => {
+                    // Copied descendants go under the copies of their
+                    // parents, and the siblings themselves under `parent`.
+                    let mut copied = HashMap::new();
+                    for old_ref in copies.next().unwrap() {
+                        let instance = old.get_by_ref(old_ref).unwrap();
+                        let builder =
+                            copy_instance(instance, referents.len(), |_| false, &kept, &mut refs);
+                        let parent = copied.get(&instance.parent()).copied().unwrap_or(parent);
+
+                        let referent = dom.insert(parent, builder);
+                        copied.insert(old_ref, referent);
+                        referents.push(referent);
+                    }
+                }
+            }
+        }
+
+        if parents.iter().any(|(_, remaining)| *remaining > 0) {
+            return Err(DeltaError::Malformed("instance is missing children"));
+        }
+
+        for (position, key, target) in refs {
+            let target = match target {
+                Some(target) => *usize::try_from(target)
+                    .ok()
+                    .and_then(|target| referents.get(target))
+                    .ok_or(DeltaError::Malformed("Ref is outside of the new tree"))?,
+                None => Ref::none(),
+            };
+
+            dom.edit(referents[position]).set_property(key, target);
+        }
+
+        if checksum(&dom, &descendants(&dom, dom.root_ref())) != self.new_checksum {
+            return Err(DeltaError::Malformed("result checksum does not match"));
+        }
+
+        Ok(dom)
+    }
+
+    /// Returns [`DeltaError::WrongBase`] if `old`, whose instances are in
+    /// `old_order`, is not the tree the delta was computed from.
+    pub(crate) fn check_base(&self, old: &WeakDom, old_order: &[Ref]) -> Result<(), DeltaError> {
+        if old_order.len() as u64 != self.old_len || checksum(old, old_order) != self.old_checksum {
+            return Err(DeltaError::WrongBase);
+        }
+
+        Ok(())
+    }
+
+    /// Writes the delta in a compact binary format.
+    pub fn to_vec(&self) -> Vec<u8> {
+        let mut output = Vec::new();
+        output.extend_from_slice(DELTA_MAGIC);
+        output.push(DELTA_VERSION);
+        put_varint(&mut output, self.old_len);
+        output.extend_from_slice(&self.old_checksum.to_le_bytes());
+        output.extend_from_slice(&self.new_checksum.to_le_bytes());
+        put_varint(&mut output, self.ops.len() as u64);
+
+        for op in &self.ops {
+            let node = match op {
+                DeltaOp::Node(node) => node,
+                DeltaOp::Copy { index, count } => {
+                    output.push(2);
+                    put_varint(&mut output, *index);
+                    put_varint(&mut output, *count);
+                    continue;
+                }
+            };
+
+            match &node.source {
+                DeltaSource::Old { index, name } => {
+                    output.push(0);
+                    put_varint(&mut output, *index);
+                    match name {
+                        Some(name) => {
+                            output.push(1);
+                            put_bytes(&mut output, name.as_bytes());
+                        }
+                        None => output.push(0),
+                    }
+                }
+                DeltaSource::New { class, name } => {
+                    output.push(1);
+                    put_bytes(&mut output, class.as_bytes());
+                    put_bytes(&mut output, name.as_bytes());
+                }
+            }
+
+            put_varint(&mut output, node.set.len() as u64);
+            for (key, value) in &node.set {
+                put_bytes(&mut output, key.as_bytes());
+                match value {
+                    DeltaValue::Ref(None) => output.push(0),
+                    DeltaValue::Ref(Some(target)) => {
+                        output.push(1);
+                        put_varint(&mut output, *target);
+                    }
+                    DeltaValue::Value(bytes) => {
+                        output.push(2);
+                        put_bytes(&mut output, bytes);
+                    }
+                    DeltaValue::SharedString(bytes) => {
+                        output.push(3);
+                        put_bytes(&mut output, bytes);
+                    }
+                }
+            }
+
+            put_varint(&mut output, node.removed.len() as u64);
+            for key in &node.removed {
+                put_bytes(&mut output, key.as_bytes());
+            }
+
+            put_varint(&mut output, node.children);
+        }
+
+        output
+    }
+
+    /// Reads a delta written by [`PlaceDelta::to_vec`].
+    pub fn from_slice(contents: &[u8]) -> Result<Self, DeltaError> {
+        let mut reader = Reader { contents };
+
+        if reader.take(DELTA_MAGIC.len())? != DELTA_MAGIC {
+            return Err(DeltaError::Malformed("not a delta file"));
+        }
+
+        let version = reader.take(1)?[0];
+        if version != DELTA_VERSION {
+            return Err(DeltaError::UnsupportedVersion(version));
+        }
+
+        let old_len = reader.varint()?;
+        let old_checksum = reader.u64()?;
+        let new_checksum = reader.u64()?;
+        let count = reader.varint()?;
+
+        let mut ops = Vec::new();
+        for _ in 0..count {
+            let source = match reader.take(1)?[0] {
+                0 => DeltaSource::Old {
+                    index: reader.varint()?,
+                    name: match reader.take(1)?[0] {
+                        0 => None,
+                        _ => Some(reader.string()?),
+                    },
+                },
+                1 => DeltaSource::New {
+                    class: reader.string()?,
+                    name: reader.string()?,
+                },
+                2 => {
+                    ops.push(DeltaOp::Copy {
+                        index: reader.varint()?,
+                        count: reader.varint()?,
+                    });
+                    continue;
+                }
+                _ => return Err(DeltaError::Malformed("unknown instance source")),
+            };
+
+            let mut set = Vec::new();
+            for _ in 0..reader.varint()? {
+                let key = reader.string()?;
+                let value = match reader.take(1)?[0] {
+                    0 => DeltaValue::Ref(None),
+                    1 => DeltaValue::Ref(Some(reader.varint()?)),
+                    2 => DeltaValue::Value(reader.bytes()?.to_vec()),
+                    3 => DeltaValue::SharedString(reader.bytes()?.to_vec()),
+                    _ => return Err(DeltaError::Malformed("unknown value kind")),
+                };
+                set.push((key, value));
+            }
+
+            let mut removed = Vec::new();
+            for _ in 0..reader.varint()? {
+                removed.push(reader.string()?);
+            }
+
+            ops.push(DeltaOp::Node(DeltaNode {
+                source,
+                set,
+                removed,
+                children: reader.varint()?,
+            }));
+        }
+
+        Ok(PlaceDelta {
+            old_len,
+            old_checksum,
+            new_checksum,
+            ops,
+        })
+    }
+}
+
+pub(crate) fn old_instance(old_order: &[Ref], index: u64) -> Result<Ref, DeltaError> {
+    usize::try_from(index)
+        .ok()
+        .and_then(|index| old_order.get(index))
+        .copied()
+        .ok_or(DeltaError::Malformed("instance is outside of the old tree"))
+}
+
+/// Returns the siblings of the old tree that a [`DeltaOp::Copy`] copies.
+pub(crate) fn copied_siblings<'a>(
+    old: &'a WeakDom,
+    old_order: &[Ref],
+    index: u64,
+    count: u64,
+) -> Result<&'a [Ref], DeltaError> {
+    let first = old_instance(old_order, index)?;
+    let parent = old.get_by_ref(first).unwrap().parent();
+    let siblings = old
+        .get_by_ref(parent)
+        .ok_or(DeltaError::Malformed("copied instance has no parent"))?
+        .children();
+
+    let start = siblings.iter().position(|&child| child == first).unwrap();
+    let end = usize::try_from(count)
+        .ok()
+        .and_then(|count| start.checked_add(count))
+        .filter(|&end| end <= siblings.len())
+        .ok_or(DeltaError::Malformed("copy is outside of the old tree"))?;
+
+    Ok(&siblings[start..end])
+}
+
+/// Builds the instance at `position` in the new tree out of one of the ops of
+/// the delta.
+fn build_node(
+    node: &DeltaNode,
+    old: &WeakDom,
+    old_order: &[Ref],
+    position: usize,
+    kept: &HashMap<Ref, u64>,
+    refs: &mut Vec<(usize, String, Option<u64>)>,
+) -> Result<InstanceBuilder, DeltaError> {
+    let mut builder = match &node.source {
+        DeltaSource::Old { index, name } => {
+            let instance = old.get_by_ref(old_instance(old_order, *index)?).unwrap();
+            let skip = |key: &str| {
+                node.removed.iter().any(|removed| removed == key)
+                    || node.set.iter().any(|(set_key, _)| set_key == key)
+            };
+
+            let builder = copy_instance(instance, position, skip, kept, refs);
+            match name {
+                Some(name) => builder.with_name(name),
+                None => builder,
+            }
+        }
+        DeltaSource::New { class, name } => InstanceBuilder::new(class).with_name(name),
+    };
+
+    for (key, value) in &node.set {
+        match value {
+            DeltaValue::Ref(target) => refs.push((position, key.clone(), *target)),
+            DeltaValue::SharedString(bytes) => {
+                builder = builder.with_property(key.clone(), SharedString::new(bytes.clone()));
+            }
+            DeltaValue::Value(bytes) => {
+                let value: Variant = rmp_serde::from_slice(bytes)?;
+                builder = builder.with_property(key.clone(), value);
+            }
+        }
+    }
+
+    Ok(builder)
+}
+
+/// Starts building a copy of an old instance at `position` in the new tree,
+/// leaving out the properties that `skip` returns `true` for. `Ref`
+/// properties are added to `refs` instead, to be set once every instance they
+/// could point to exists.
+fn copy_instance(
+    instance: &Instance,
+    position: usize,
+    skip: impl Fn(&str) -> bool,
+    kept: &HashMap<Ref, u64>,
+    refs: &mut Vec<(usize, String, Option<u64>)>,
+) -> InstanceBuilder {
+    let mut builder = InstanceBuilder::new(&instance.class).with_name(&instance.name);
+
+    for (key, value) in instance.properties.iter() {
+        if skip(key) {
+            continue;
+        }
+
+        match value {
+            Variant::Ref(target) => refs.push((position, key.clone(), kept.get(target).copied())),
+            value => builder = builder.with_property(key.clone(), value.clone()),
+        }
+    }
+
+    builder
+}
+
+/// Matches up the instances of two trees, starting from their roots, and
+/// returns the instances of `new` keyed by the instances of `old` they match.
+/// `check` is called before each pair is matched, and stops the matching if
+/// it fails.
+pub(crate) fn match_trees(
+    old: &WeakDom,
+    new: &WeakDom,
+    check: impl Fn() -> Result<(), DeltaError>,
+) -> Result<HashMap<Ref, Ref>, DeltaError> {
+    let mut matches = HashMap::new();
+    let mut queue = VecDeque::new();
+    queue.push_back((old.root_ref(), new.root_ref()));
+
+    while let Some((old_ref, new_ref)) = queue.pop_front() {
+        check()?;
+        matches.insert(old_ref, new_ref);
+
+        let old_children = old.get_by_ref(old_ref).unwrap().children();
+        let new_children = new.get_by_ref(new_ref).unwrap().children();
+        queue.extend(match_children(old, new, old_children, new_children));
+    }
+
+    Ok(matches)
+}
+
+fn positions(order: &[Ref]) -> HashMap<Ref, usize> {
+    order
+        .iter()
+        .enumerate()
+        .map(|(index, &referent)| (referent, index))
+        .collect()
+}
+
+/// Pairs up the children of two matching instances, keeping them in order.
+/// Children with the same class and name are paired first, then children of
+/// the same class that are in the same place between those, which are
+/// usually instances that were renamed.
+fn match_children(
+    old: &WeakDom,
+    new: &WeakDom,
+    old_children: &[Ref],
+    new_children: &[Ref],
+) -> Vec<(Ref, Ref)> {
+    let key = |dom: &WeakDom, referent: Ref| {
+        let instance = dom.get_by_ref(referent).unwrap();
+        (instance.class.clone(), instance.name.clone())
+    };
+
+    let mut by_key: HashMap<(String, String), VecDeque<usize>> = HashMap::new();
+    for (index, &child) in old_children.iter().enumerate() {
+        by_key.entry(key(old, child)).or_default().push_back(index);
+    }
+
+    let mut paired: Vec<Option<usize>> = vec![None; new_children.len()];
+    let mut next = 0;
+    for (new_index, &child) in new_children.iter().enumerate() {
+        if let Some(candidates) = by_key.get_mut(&key(new, child)) {
+            while candidates.front().is_some_and(|&index| index < next) {
+                candidates.pop_front();
+            }
+
+            if let Some(index) = candidates.pop_front() {
+                paired[new_index] = Some(index);
+                next = index + 1;
+            }
+        }
+    }
+
+    let mut start = 0;
+    let mut old_start = 0;
+    while start < new_children.len() {
+        if let Some(index) = paired[start] {
+            old_start = index + 1;
+            start += 1;
+            continue;
+        }
+
+        let end = (start..new_children.len())
+            .find(|&index| paired[index].is_some())
+            .unwrap_or(new_children.len());
+        let old_end = paired
+            .get(end)
+            .copied()
+            .flatten()
+            .unwrap_or(old_children.len());
+
+        for (new_index, old_index) in (start..end).zip(old_start..old_end) {
+            let old_class = &old.get_by_ref(old_children[old_index]).unwrap().class;
+            let new_class = &new.get_by_ref(new_children[new_index]).unwrap().class;
+            if old_class == new_class {
+                paired[new_index] = Some(old_index);
+            }
+        }
+
+        start = end;
+    }
+
+    paired
+        .into_iter()
+        .zip(new_children)
+        .filter_map(|(old_index, &new_child)| Some((old_children[old_index?], new_child)))
+        .collect()
+}
+
+/// Hashes the structure, classes, names, and properties of a tree. `Ref`
+/// properties are hashed by the position of the instance they point to, so
+/// that the same tree read from a file again, with new referents, gives the
+/// same checksum.
+pub(crate) fn checksum(dom: &WeakDom, order: &[Ref]) -> u64 {
+    let index = positions(order);
+    let mut hasher = StableHasher::new();
+
+    for &referent in order {
+        let instance = dom.get_by_ref(referent).unwrap();
+        hasher.write(instance.class.as_bytes());
+        hasher.write_u8(0xff);
+        hasher.write(instance.name.as_bytes());
+        hasher.write_u8(0xff);
+        hasher.write_u64(instance.children().len() as u64);
+
+        let mut properties: Vec<_> = instance.properties.iter().collect();
+        properties.sort_unstable_by(|a, b| a.0.cmp(b.0));
+
+        hasher.write_u64(properties.len() as u64);
+        for (key, value) in properties {
+            hasher.write(key.as_bytes());
+            hasher.write_u8(0xff);
+
+            match value {
+                Variant::Ref(target) => {
+                    hasher.write_u32(VariantType::Ref as u32);
+                    hasher.write_u64(index.get(target).map_or(0, |&index| index as u64 + 1));
+                }
+                value => value.hash_stable(&mut hasher),
+            }
+        }
+    }
+
+    hasher.finish()
+}
+
+fn put_varint(output: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        output.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    output.push(value as u8);
+}
+
+fn put_bytes(output: &mut Vec<u8>, bytes: &[u8]) {
+    put_varint(output, bytes.len() as u64);
+    output.extend_from_slice(bytes);
+}
+
+struct Reader<'a> {
+    contents: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], DeltaError> {
+        if self.contents.len() < len {
+            return Err(DeltaError::Malformed("delta is truncated"));
+        }
+
+        let (taken, rest) = self.contents.split_at(len);
+        self.contents = rest;
+        Ok(taken)
+    }
+
+    fn u64(&mut self) -> Result<u64, DeltaError> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    fn varint(&mut self) -> Result<u64, DeltaError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.take(1)?[0];
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+
+        Err(DeltaError::Malformed("number is too long"))
+    }
+
+    fn bytes(&mut self) -> Result<&'a [u8], DeltaError> {
+        let len = usize::try_from(self.varint()?)
+            .map_err(|_| DeltaError::Malformed("value is too long"))?;
+        self.take(len)
+    }
+
+    fn string(&mut self) -> Result<String, DeltaError> {
+        String::from_utf8(self.bytes()?.to_vec())
+            .map_err(|_| DeltaError::Malformed("string is not valid UTF-8"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::types::{Attributes, BinaryString, Color3, Tags, Vector3};
+
+    use crate::{from_slice, to_vec, Format};
+
+    fn place(count: usize) -> WeakDom {
+        let mut workspace = InstanceBuilder::new("Workspace");
+        for index in 0..count {
+            workspace = workspace.with_child(
+                InstanceBuilder::new("Part")
+                    .with_name(format!("Part{}", index))
+                    .with_property("Size", Vector3::new(index as f32, 1.0, 2.0)),
+            );
+        }
+
+        WeakDom::new(InstanceBuilder::new("DataModel").with_child(workspace))
+    }
+
+    fn round_trip(dom: &WeakDom, format: Format) -> WeakDom {
+        from_slice(&to_vec(dom, dom.root().children(), format).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn delta_round_trip() {
+        // The old place is read back from a file, like an auto-updater would,
+        // to make sure it still lines up with the delta.
+        let old = round_trip(&place(500), Format::Binary);
+
+        let mut new = place(500);
+        let workspace = new.root().children()[0];
+        let parts = new.get_by_ref(workspace).unwrap().children().to_vec();
+        new.edit(parts[250]).set_name("Changed");
+        new.edit(parts[100])
+            .set_property("Color", Color3::new(1.0, 0.0, 0.0))
+            .remove_property("Size");
+        new.destroy(parts[10]);
+        new.insert(
+            workspace,
+            InstanceBuilder::new("ObjectValue").with_property("Value", parts[250]),
+        );
+        new.transfer_within(parts[300], parts[301]);
+
+        let delta = PlaceDelta::between(&old, &new).unwrap().to_vec();
+        let full = to_vec(&new, new.root().children(), Format::Binary).unwrap();
+        assert!(delta.len() * 10 < full.len());
+
+        let delta = PlaceDelta::from_slice(&delta).unwrap();
+        let updated = delta.apply(&old).unwrap();
+        assert_eq!(
+            updated.semantically_equals(&new, &Default::default()),
+            Ok(true)
+        );
+
+        let workspace = updated.get_by_ref(updated.root().children()[0]).unwrap();
+        let value = updated
+            .get_by_ref(*workspace.children().last().unwrap())
+            .unwrap();
+        let target = workspace.children()[249];
+        assert_eq!(updated.get_by_ref(target).unwrap().name, "Changed");
+        assert_eq!(value.properties.get("Value"), Some(&Variant::Ref(target)));
+
+        // The delta doesn't depend on the format the old place is read from.
+        let from_xml = round_trip(&place(500), Format::Xml);
+        assert!(delta.apply(&from_xml).is_ok());
+
+        assert!(matches!(delta.apply(&place(3)), Err(DeltaError::WrongBase)));
+        assert!(matches!(
+            PlaceDelta::from_slice(b"RBXDELTA"),
+            Err(DeltaError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn values() {
+        let old = WeakDom::new(InstanceBuilder::new("Folder"));
+        let new = WeakDom::new(
+            InstanceBuilder::new("Folder")
+                .with_property("Nothing", f32::NAN)
+                .with_property("Data", BinaryString::from(vec![0, 1, 2]))
+                .with_property("Shared", SharedString::new(b"shared".to_vec()))
+                .with_property("Tags", Tags::from(vec!["A".to_owned()]))
+                .with_property("Attributes", Attributes::new().with("Health", 100.0)),
+        );
+
+        let delta = PlaceDelta::between(&old, &new).unwrap();
+        let delta = PlaceDelta::from_slice(&delta.to_vec()).unwrap();
+        let updated = delta.apply(&old).unwrap();
+
+        let properties = &updated.root().properties;
+        assert!(
+            matches!(properties.get("Nothing"), Some(Variant::Float32(value)) if value.is_nan())
+        );
+        for key in ["Data", "Shared", "Tags", "Attributes"] {
+            assert_eq!(properties.get(key), new.root().properties.get(key));
+        }
+    }
+
+    #[test]
+    fn cancel_token() {
+        let cancel = Arc::new(AtomicBool::new(true));
+        assert!(matches!(
+            PlaceDelta::between_cancellable(&place(10), &place(20), Arc::clone(&cancel)),
+            Err(DeltaError::Cancelled)
+        ));
+
+        cancel.store(false, Ordering::Relaxed);
+        assert!(PlaceDelta::between_cancellable(&place(10), &place(20), cancel).is_ok());
+    }
+
+    #[test]
+    fn ignoring() {
+        let rules = IgnoreRules {
+            names: vec!["*.spec".to_owned()],
+            ..Default::default()
+        };
+
+        let mut old = place(3);
+        let workspace = old.root().children()[0];
+        old.insert(
+            workspace,
+            InstanceBuilder::new("ModuleScript").with_name("Old.spec"),
+        );
+
+        let mut new = place(4);
+        let workspace = new.root().children()[0];
+        new.insert(
+            workspace,
+            InstanceBuilder::new("ModuleScript").with_name("New.spec"),
+        );
+
+        let delta = PlaceDelta::between_ignoring(&old, &new, &rules).unwrap();
+        assert_eq!(rules.count_ignored(&old), 1);
+        assert_eq!(rules.count_ignored(&new), 1);
+
+        let updated = delta.apply_ignoring(&old, &rules).unwrap();
+        assert_eq!(
+            updated.semantically_equals(&place(4), &Default::default()),
+            Ok(true)
+        );
+        assert!(matches!(delta.apply(&old), Err(DeltaError::WrongBase)));
+    }
+
+    #[test]
+    fn serde() {
+        let old = place(3);
+        let mut new = place(3);
+        let workspace = new.root().children()[0];
+        new.insert(workspace, InstanceBuilder::new("Part").with_name("Added"));
+
+        let delta = PlaceDelta::between(&old, &new).unwrap();
+        let json = serde_json::to_value(&delta).unwrap();
+        assert_eq!(json["version"], DELTA_VERSION);
+
+        let read: PlaceDelta = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(read, delta);
+        assert_eq!(
+            read.apply(&old)
+                .unwrap()
+                .semantically_equals(&new, &Default::default()),
+            Ok(true)
+        );
+
+        let mut newer = json;
+        newer["version"] = (DELTA_VERSION + 1).into();
+        let error = serde_json::from_value::<PlaceDelta>(newer).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!("delta version {} is not supported", DELTA_VERSION + 1)
+        );
+    }
+}
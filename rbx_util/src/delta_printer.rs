@@ -0,0 +1,432 @@
+use std::{borrow::Cow, collections::HashMap, convert::TryFrom, fmt};
+
+use rbx_dom_weak::{
+    types::{Ref, Variant},
+    WeakDom,
+};
+
+use crate::{
+    delta::{copied_siblings, old_instance},
+    error::DeltaError,
+    path::{descendants, full_name},
+    tree::fmt_value,
+    DeltaNode, DeltaOp, DeltaSource, DeltaValue, PlaceDelta,
+};
+
+/// Formats a [`PlaceDelta`] as readable text, one change per line, for
+/// command line output or review comments.
+///
+/// Removed instances are listed first, starting with `-`. The rest of the
+/// changes follow in the order of the new tree: `+` for added instances and
+/// `~` for properties that changed, including `Name`. Properties of added
+/// instances aren't listed, and instances that moved to a new parent show up
+/// as removed and added again, like the delta records them.
+///
+/// ```
+/// use rbx_dom_weak::{InstanceBuilder, WeakDom};
+/// use rbx_util::{DeltaPrinter, PlaceDelta};
+///
+/// let old = WeakDom::new(InstanceBuilder::new("DataModel").with_child(
+///     InstanceBuilder::new("Workspace")
+///         .with_child(InstanceBuilder::new("Part").with_name("Spawn").with_property("Anchored", false))
+///         .with_child(InstanceBuilder::new("Part").with_name("Old")),
+/// ));
+/// let new = WeakDom::new(InstanceBuilder::new("DataModel").with_child(
+///     InstanceBuilder::new("Workspace")
+///         .with_child(InstanceBuilder::new("Part").with_name("Spawn").with_property("Anchored", true))
+///         .with_child(InstanceBuilder::new("Model")),
+/// ));
+///
+/// let delta = PlaceDelta::between(&old, &new)?;
+/// assert_eq!(
+///     DeltaPrinter::new(&delta, &old)?.to_string(),
+///     "- Workspace.Old (Part)\n\
+///      ~ Workspace.Spawn.Anchored: false -> true\n\
+///      + Workspace.Model (Model)\n",
+/// );
+/// # Ok::<(), rbx_util::DeltaError>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct DeltaPrinter {
+    changes: Vec<Change>,
+    color: bool,
+    max_value_len: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+enum Change {
+    Added {
+        path: String,
+        class: String,
+    },
+    Removed {
+        path: String,
+        class: String,
+    },
+    Changed {
+        path: String,
+        property: String,
+        old: String,
+        new: String,
+    },
+}
+
+/// An instance of the new tree, while the changes are worked out.
+struct Slot {
+    name: String,
+    parent: Option<usize>,
+    old: Option<Ref>,
+}
+
+impl DeltaPrinter {
+    /// Works out the changes that `delta` makes to `old`, which has to be the
+    /// tree it was computed from.
+    ///
+    /// Returns [`DeltaError::WrongBase`] if it isn't.
+    pub fn new(delta: &PlaceDelta, old: &WeakDom) -> Result<Self, DeltaError> {
+        let old_order = descendants(old, old.root_ref());
+        delta.check_base(old, &old_order)?;
+
+        let mut slots: Vec<Slot> = Vec::new();
+        let mut nodes: Vec<(usize, &DeltaNode)> = Vec::new();
+        let mut parents: Vec<(usize, u64)> = Vec::new();
+
+        for op in delta.ops() {
+            while let Some((_, 0)) = parents.last() {
+                parents.pop();
+            }
+            let parent = match parents.last_mut() {
+                Some((parent, remaining)) => {
+                    *remaining -= 1;
+                    Some(*parent)
+                }
+                None if slots.is_empty() => None,
+                None => return Err(DeltaError::Malformed("instance has no parent")),
+            };
+
+            match op {
+                DeltaOp::Node(node) => {
+                    let (name, old_ref) = match node.source() {
+                        DeltaSource::Old { index, name } => {
+                            let old_ref = old_instance(&old_order, *index)?;
+                            let name = match name {
+                                Some(name) => name.clone(),
+                                None => old.get_by_ref(old_ref).unwrap().name.clone(),
+                            };
+                            (name, Some(old_ref))
+                        }
+                        DeltaSource::New { name, .. } => (name.clone(), None),
+                    };
+
+                    nodes.push((slots.len(), node));
+                    parents.push((slots.len(), node.children()));
+                    slots.push(Slot {
+                        name,
+                        parent,
+                        old: old_ref,
+                    });
+                }
+                DeltaOp::Copy { index, count } => {
+                    let parent =
+                        parent.ok_or(DeltaError::Malformed("delta has no root instance"))?;
+
+                    // Copied descendants go under the copies of their
+                    // parents, and the siblings themselves under `parent`.
+                    let mut copied = HashMap::new();
+                    for &sibling in copied_siblings(old, &old_order, *index, *count)? {
+                        for old_ref in descendants(old, sibling) {
+                            let instance = old.get_by_ref(old_ref).unwrap();
+                            copied.insert(old_ref, slots.len());
+                            slots.push(Slot {
+                                name: instance.name.clone(),
+                                parent: Some(
+                                    copied.get(&instance.parent()).copied().unwrap_or(parent),
+                                ),
+                                old: Some(old_ref),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        if parents.iter().any(|(_, remaining)| *remaining > 0) {
+            return Err(DeltaError::Malformed("instance is missing children"));
+        }
+
+        let root_name = match slots.first() {
+            Some(root) => root.name.clone(),
+            None => return Err(DeltaError::Malformed("delta has no root instance")),
+        };
+
+        // Parents come before their children, so their paths are always
+        // known by the time their children's are worked out.
+        let mut paths: Vec<String> = Vec::with_capacity(slots.len());
+        for slot in &slots {
+            let path = match slot.parent {
+                None => String::new(),
+                Some(parent) if paths[parent].is_empty() => slot.name.clone(),
+                Some(parent) => format!("{}.{}", paths[parent], slot.name),
+            };
+            paths.push(path);
+        }
+
+        let new_path = |position: usize| match paths[position].as_str() {
+            "" => root_name.clone(),
+            path => path.to_owned(),
+        };
+        let old_path = |referent: Ref| match full_name(old, referent) {
+            path if path.is_empty() => old.root().name.clone(),
+            path => path,
+        };
+        let old_value = |value: Option<&Variant>| match value {
+            Some(Variant::Ref(target)) if old.get_by_ref(*target).is_some() => old_path(*target),
+            Some(Variant::Ref(_)) => "nil".to_owned(),
+            Some(value) => value_text(value),
+            None => "(none)".to_owned(),
+        };
+        let new_value = |value: &DeltaValue| match value {
+            DeltaValue::Ref(Some(target)) => usize::try_from(*target)
+                .ok()
+                .filter(|&target| target < slots.len())
+                .map(new_path)
+                .ok_or(DeltaError::Malformed("Ref is outside of the new tree")),
+            DeltaValue::Ref(None) => Ok("nil".to_owned()),
+            value => Ok(value_text(&value.variant()?.unwrap())),
+        };
+
+        let kept: HashMap<Ref, usize> = slots
+            .iter()
+            .enumerate()
+            .filter_map(|(position, slot)| Some((slot.old?, position)))
+            .collect();
+
+        let mut changes = Vec::new();
+        for &old_ref in old_order.iter().skip(1) {
+            let instance = old.get_by_ref(old_ref).unwrap();
+            if !kept.contains_key(&old_ref) && kept.contains_key(&instance.parent()) {
+                changes.push(Change::Removed {
+                    path: old_path(old_ref),
+                    class: instance.class.clone(),
+                });
+            }
+        }
+
+        for (position, node) in nodes {
+            let path = new_path(position);
+            let name = match node.source() {
+                DeltaSource::New { class, .. } => {
+                    changes.push(Change::Added {
+                        path,
+                        class: class.clone(),
+                    });
+                    continue;
+                }
+                DeltaSource::Old { name, .. } => name,
+            };
+
+            let instance = old.get_by_ref(slots[position].old.unwrap()).unwrap();
+
+            if let Some(name) = name {
+                changes.push(Change::Changed {
+                    path: path.clone(),
+                    property: "Name".to_owned(),
+                    old: format!("{:?}", instance.name),
+                    new: format!("{:?}", name),
+                });
+            }
+
+            for (key, value) in node.set() {
+                changes.push(Change::Changed {
+                    path: path.clone(),
+                    property: key.clone(),
+                    old: old_value(instance.properties.get(key)),
+                    new: new_value(value)?,
+                });
+            }
+
+            for key in node.removed() {
+                changes.push(Change::Changed {
+                    path: path.clone(),
+                    property: key.clone(),
+                    old: old_value(instance.properties.get(key)),
+                    new: "(none)".to_owned(),
+                });
+            }
+        }
+
+        Ok(DeltaPrinter {
+            changes,
+            color: false,
+            max_value_len: None,
+        })
+    }
+
+    /// Sets whether lines are colored with ANSI escape codes, for terminals
+    /// that support them. Defaults to `false`.
+    pub fn color(self, color: bool) -> Self {
+        DeltaPrinter { color, ..self }
+    }
+
+    /// Cuts property values longer than `len` characters short, ending them
+    /// with `...`. Values are printed in full by default.
+    pub fn max_value_len(self, len: usize) -> Self {
+        DeltaPrinter {
+            max_value_len: Some(len),
+            ..self
+        }
+    }
+
+    /// Tells whether the delta changes anything.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    fn truncate<'a>(&self, value: &'a str) -> Cow<'a, str> {
+        match self.max_value_len {
+            Some(len) if value.chars().count() > len => Cow::Owned(format!(
+                "{}...",
+                value.chars().take(len).collect::<String>()
+            )),
+            _ => Cow::Borrowed(value),
+        }
+    }
+}
+
+impl fmt::Display for DeltaPrinter {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for change in &self.changes {
+            if self.color {
+                let color = match change {
+                    Change::Added { .. } => "32",
+                    Change::Removed { .. } => "31",
+                    Change::Changed { .. } => "33",
+                };
+                write!(formatter, "\x1b[{}m", color)?;
+            }
+
+            match change {
+                Change::Added { path, class } => write!(formatter, "+ {} ({})", path, class)?,
+                Change::Removed { path, class } => write!(formatter, "- {} ({})", path, class)?,
+                Change::Changed {
+                    path,
+                    property,
+                    old,
+                    new,
+                } => write!(
+                    formatter,
+                    "~ {}.{}: {} -> {}",
+                    path,
+                    property,
+                    self.truncate(old),
+                    self.truncate(new)
+                )?,
+            }
+
+            if self.color {
+                write!(formatter, "\x1b[0m")?;
+            }
+
+            writeln!(formatter)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes a property value the way [`TreePrinter`][crate::TreePrinter] does.
+fn value_text(value: &Variant) -> String {
+    struct Value<'a>(&'a Variant);
+
+    impl fmt::Display for Value<'_> {
+        fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+            fmt_value(formatter, self.0)
+        }
+    }
+
+    Value(value).to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::InstanceBuilder;
+
+    fn place() -> WeakDom {
+        WeakDom::new(
+            InstanceBuilder::new("DataModel").with_child(
+                InstanceBuilder::new("Workspace")
+                    .with_child(
+                        InstanceBuilder::new("Part")
+                            .with_name("Spawn")
+                            .with_property("Transparency", 0.0f32),
+                    )
+                    .with_child(InstanceBuilder::new("Model").with_name("House"))
+                    .with_child(InstanceBuilder::new("Part").with_name("Door"))
+                    .with_child(InstanceBuilder::new("Part").with_name("Roof")),
+            ),
+        )
+    }
+
+    #[test]
+    fn changes() {
+        let old = place();
+
+        let mut new = place();
+        let workspace = new.root().children()[0];
+        let children = new.get_by_ref(workspace).unwrap().children().to_vec();
+        new.edit(children[0])
+            .set_property("Transparency", 0.5f32)
+            .set_property("Target", children[1])
+            .set_name("Start");
+        new.transfer_within(children[2], children[1]);
+        new.destroy(children[3]);
+        new.insert(
+            workspace,
+            InstanceBuilder::new("Script").with_property("Source", "print(\"hello\")"),
+        );
+
+        let delta = PlaceDelta::between(&old, &new).unwrap();
+        let printer = DeltaPrinter::new(&delta, &old).unwrap();
+        assert!(!printer.is_empty());
+        assert_eq!(
+            printer.to_string(),
+            "- Workspace.Door (Part)\n\
+             - Workspace.Roof (Part)\n\
+             ~ Workspace.Start.Name: \"Spawn\" -> \"Start\"\n\
+             ~ Workspace.Start.Target: (none) -> Workspace.House\n\
+             ~ Workspace.Start.Transparency: 0 -> 0.5\n\
+             + Workspace.House.Door (Part)\n\
+             + Workspace.Script (Script)\n"
+        );
+
+        assert!(matches!(
+            DeltaPrinter::new(&delta, &new),
+            Err(DeltaError::WrongBase)
+        ));
+        assert!(
+            DeltaPrinter::new(&PlaceDelta::between(&old, &old).unwrap(), &old)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn color_and_truncation() {
+        let old = WeakDom::new(InstanceBuilder::new("Script").with_property("Source", "short"));
+        let new = WeakDom::new(
+            InstanceBuilder::new("Script").with_property("Source", "print(\"a long line\")"),
+        );
+
+        let delta = PlaceDelta::between(&old, &new).unwrap();
+        let printer = DeltaPrinter::new(&delta, &old)
+            .unwrap()
+            .color(true)
+            .max_value_len(8);
+        assert_eq!(
+            printer.to_string(),
+            "\x1b[33m~ Script.Source: \"short\" -> \"print(\\...\x1b[0m\n"
+        );
+    }
+}
@@ -0,0 +1,329 @@
+use std::{fmt, iter::FromIterator, slice, vec};
+
+use rbx_dom_weak::types::Ref;
+
+use crate::{DeadRef, HierarchyIssue, HierarchyProblem, SchemaIssue, SchemaProblem};
+
+/// How much a [`Diagnostic`] matters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Something that was handled automatically and only needs to be known
+    /// about.
+    Info,
+
+    /// Something that was worked around, but might not be what was wanted.
+    Warning,
+
+    /// Something that couldn't be worked around, and that will probably
+    /// break the model or place unless it's fixed by hand.
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+/// A problem that didn't stop an operation from finishing, collected in
+/// [`Diagnostics`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// How much the problem matters.
+    pub severity: Severity,
+
+    /// A description of the problem, meant to be shown to people.
+    pub message: String,
+
+    /// The referent of the instance the problem is about, if it's about an
+    /// instance.
+    pub referent: Option<Ref>,
+
+    /// Where the problem is, like `Workspace.Model.Part` for an instance, if
+    /// it's known.
+    pub location: Option<String>,
+}
+
+impl Diagnostic {
+    /// Creates a diagnostic that isn't about any particular instance.
+    pub fn new<S: Into<String>>(severity: Severity, message: S) -> Self {
+        Diagnostic {
+            severity,
+            message: message.into(),
+            referent: None,
+            location: None,
+        }
+    }
+
+    /// Sets the instance the diagnostic is about, and the path to it.
+    pub fn with_instance<S: Into<String>>(self, referent: Ref, path: S) -> Self {
+        Diagnostic {
+            referent: Some(referent),
+            location: Some(path.into()),
+            ..self
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.location {
+            Some(location) if !location.is_empty() => {
+                write!(
+                    formatter,
+                    "{}: {}: {}",
+                    self.severity, location, self.message
+                )
+            }
+            _ => write!(formatter, "{}: {}", self.severity, self.message),
+        }
+    }
+}
+
+/// Problems that didn't stop an operation from finishing, collected so they
+/// can be shown once it's done.
+///
+/// Anything that can be turned into a [`Diagnostic`] can be added, including
+/// the results of [`validate_hierarchy`][crate::validate_hierarchy],
+/// [`dead_refs`][crate::dead_refs], and
+/// [`migrate_schema`][crate::migrate_schema], and the diagnostics reported by
+/// [`from_slice_with_diagnostics`][crate::from_slice_with_diagnostics].
+///
+/// ```
+/// use rbx_dom_weak::{InstanceBuilder, WeakDom};
+/// use rbx_util::{Diagnostics, Severity};
+///
+/// let dom = WeakDom::new(
+///     InstanceBuilder::new("DataModel").with_child(
+///         InstanceBuilder::new("Folder").with_child(InstanceBuilder::new("Terrain")),
+///     ),
+/// );
+///
+/// let mut diagnostics = Diagnostics::new();
+/// diagnostics.extend(rbx_util::validate_hierarchy(&dom));
+///
+/// assert_eq!(diagnostics.max_severity(), Some(Severity::Error));
+/// for diagnostic in &diagnostics {
+///     println!("{}", diagnostic);
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Diagnostics {
+    entries: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    /// Creates an empty collection.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a diagnostic to the end of the collection.
+    pub fn push<D: Into<Diagnostic>>(&mut self, diagnostic: D) {
+        self.entries.push(diagnostic.into());
+    }
+
+    /// Returns the number of diagnostics in the collection.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Tells whether the collection is empty.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns an iterator over the diagnostics, in the order they were
+    /// added.
+    pub fn iter(&self) -> slice::Iter<'_, Diagnostic> {
+        self.entries.iter()
+    }
+
+    /// Returns the severity of the most severe diagnostic, or `None` if there
+    /// aren't any.
+    pub fn max_severity(&self) -> Option<Severity> {
+        self.entries.iter().map(|entry| entry.severity).max()
+    }
+
+    /// Tells whether any of the diagnostics are errors.
+    pub fn has_errors(&self) -> bool {
+        self.max_severity() == Some(Severity::Error)
+    }
+
+    /// Returns the diagnostics about the instance with the given referent.
+    pub fn for_instance(&self, referent: Ref) -> impl Iterator<Item = &Diagnostic> {
+        self.entries
+            .iter()
+            .filter(move |entry| entry.referent == Some(referent))
+    }
+}
+
+impl<D: Into<Diagnostic>> Extend<D> for Diagnostics {
+    fn extend<I: IntoIterator<Item = D>>(&mut self, iter: I) {
+        self.entries.extend(iter.into_iter().map(Into::into));
+    }
+}
+
+impl<D: Into<Diagnostic>> FromIterator<D> for Diagnostics {
+    fn from_iter<I: IntoIterator<Item = D>>(iter: I) -> Self {
+        let mut diagnostics = Self::new();
+        diagnostics.extend(iter);
+        diagnostics
+    }
+}
+
+impl IntoIterator for Diagnostics {
+    type Item = Diagnostic;
+    type IntoIter = vec::IntoIter<Diagnostic>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Diagnostics {
+    type Item = &'a Diagnostic;
+    type IntoIter = slice::Iter<'a, Diagnostic>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter()
+    }
+}
+
+impl From<HierarchyIssue> for Diagnostic {
+    fn from(issue: HierarchyIssue) -> Self {
+        let HierarchyIssue {
+            class,
+            parent_class,
+            ..
+        } = &issue;
+
+        let (severity, message) = match &issue.problem {
+            HierarchyProblem::ServiceOutsideDataModel => (
+                Severity::Error,
+                format!("{} is a service, but is inside a {}", class, parent_class),
+            ),
+            HierarchyProblem::WrongParent { required } => (
+                Severity::Error,
+                format!(
+                    "{} must be inside a {}, but is inside a {}",
+                    class, required, parent_class
+                ),
+            ),
+            HierarchyProblem::ScriptInsideValue => (
+                Severity::Warning,
+                format!(
+                    "{} is inside a {}, where it's easily forgotten",
+                    class, parent_class
+                ),
+            ),
+        };
+
+        Diagnostic::new(severity, message).with_instance(issue.referent, issue.path)
+    }
+}
+
+impl From<SchemaIssue> for Diagnostic {
+    fn from(issue: SchemaIssue) -> Self {
+        let (severity, message) = match &issue.problem {
+            SchemaProblem::ClassReplaced { class, replacement } => (
+                Severity::Warning,
+                format!(
+                    "class {} no longer exists and was replaced with {}",
+                    class, replacement
+                ),
+            ),
+            SchemaProblem::ClassRemoved { class } => {
+                (Severity::Error, format!("class {} no longer exists", class))
+            }
+            SchemaProblem::PropertyRemoved { property, .. } => (
+                Severity::Warning,
+                format!("property {} no longer exists and was removed", property),
+            ),
+            SchemaProblem::EnumItemRemoved { property, item } => (
+                Severity::Error,
+                format!(
+                    "property {} is set to {}, which no longer exists",
+                    property, item
+                ),
+            ),
+            SchemaProblem::MigrationFailed { property, message } => (
+                Severity::Error,
+                format!("property {} could not be migrated: {}", property, message),
+            ),
+        };
+
+        Diagnostic::new(severity, message).with_instance(issue.referent, issue.path)
+    }
+}
+
+impl From<DeadRef> for Diagnostic {
+    fn from(dead: DeadRef) -> Self {
+        let message = format!(
+            "property {} points to {}, which is not in the DOM",
+            dead.property, dead.target
+        );
+
+        Diagnostic::new(Severity::Warning, message).with_instance(dead.referent, dead.path)
+    }
+}
+
+impl From<&rbx_binary::Diagnostic> for Diagnostic {
+    fn from(diagnostic: &rbx_binary::Diagnostic) -> Self {
+        Diagnostic::new(Severity::Warning, diagnostic.to_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::{InstanceBuilder, WeakDom};
+
+    use crate::{dead_refs, from_slice_with_diagnostics, to_vec, validate_hierarchy, Format};
+
+    #[test]
+    fn collects_issues() {
+        let terrain = InstanceBuilder::new("Terrain");
+        let terrain_ref = terrain.referent();
+        let value = InstanceBuilder::new("ObjectValue").with_property("Value", Ref::new());
+        let value_ref = value.referent();
+
+        let dom = WeakDom::new(
+            InstanceBuilder::new("DataModel").with_child(
+                InstanceBuilder::new("Folder")
+                    .with_name("Map")
+                    .with_child(terrain)
+                    .with_child(value),
+            ),
+        );
+
+        let mut diagnostics: Diagnostics = validate_hierarchy(&dom).into_iter().collect();
+        diagnostics.extend(dead_refs(&dom));
+        diagnostics.push(Diagnostic::new(Severity::Info, "checked the map"));
+
+        assert_eq!(diagnostics.len(), 3);
+        assert!(diagnostics.has_errors());
+
+        let messages: Vec<_> = diagnostics.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            messages[0],
+            "error: Map.Terrain: Terrain must be inside a Workspace, but is inside a Folder"
+        );
+        assert_eq!(messages[2], "info: checked the map");
+
+        assert_eq!(diagnostics.for_instance(terrain_ref).count(), 1);
+        let dead = diagnostics.for_instance(value_ref).next().unwrap();
+        assert_eq!(dead.severity, Severity::Warning);
+        assert_eq!(dead.location.as_deref(), Some("Map.ObjectValue"));
+
+        let contents = to_vec(&dom, dom.root().children(), Format::Binary).unwrap();
+        let (decoded, diagnostics) = from_slice_with_diagnostics(&contents).unwrap();
+        assert_eq!(decoded.root().children().len(), 1);
+        assert!(diagnostics.is_empty());
+    }
+}
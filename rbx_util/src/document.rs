@@ -0,0 +1,197 @@
+use std::path::{Path, PathBuf};
+
+use rbx_dom_weak::{FileMetadata, WeakDom};
+
+use crate::{from_slice_with_format, to_vec, DecodeError, EncodeError, Format};
+
+/// A model or place along with the format it was read in and, if it was read
+/// from a file, that file's path. The file's metadata, like the binary format's
+/// `META` chunk, is kept too, and is written back out when the document is
+/// saved.
+///
+/// This is the quickest way to open a file, change it, and save it back without
+/// caring which format it's in:
+///
+/// ```no_run
+/// use rbx_util::Document;
+///
+/// let mut document = Document::open("MyPlace.rbxl")?;
+///
+/// let root_ref = document.dom().root_ref();
+/// document.dom_mut().get_by_ref_mut(root_ref).unwrap().name = "Renamed".to_owned();
+///
+/// document.save()?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug)]
+pub struct Document {
+    dom: WeakDom,
+    format: Format,
+    path: Option<PathBuf>,
+}
+
+impl Document {
+    /// Creates a document from an existing DOM. It has no path until one is
+    /// given with [`Document::save_as`].
+    pub fn new(dom: WeakDom, format: Format) -> Self {
+        Document {
+            dom,
+            format,
+            path: None,
+        }
+    }
+
+    /// Reads a document from a file. The format is detected from the file's
+    /// contents, falling back to its extension.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, DecodeError> {
+        let path = path.as_ref();
+        let contents = fs_err::read(path)?;
+
+        let format = Format::detect(&contents)
+            .or_else(|| Format::from_path(path))
+            .ok_or(DecodeError::UnknownFormat)?;
+
+        Ok(Document {
+            dom: from_slice_with_format(&contents, format)?,
+            format,
+            path: Some(path.to_owned()),
+        })
+    }
+
+    /// Reads a document from a byte slice, detecting its format.
+    pub fn from_slice(contents: &[u8]) -> Result<Self, DecodeError> {
+        let format = Format::detect(contents).ok_or(DecodeError::UnknownFormat)?;
+
+        Ok(Document::new(
+            from_slice_with_format(contents, format)?,
+            format,
+        ))
+    }
+
+    /// The document's DOM.
+    pub fn dom(&self) -> &WeakDom {
+        &self.dom
+    }
+
+    /// A mutable reference to the document's DOM.
+    pub fn dom_mut(&mut self) -> &mut WeakDom {
+        &mut self.dom
+    }
+
+    /// Consumes the document, returning its DOM.
+    pub fn into_dom(self) -> WeakDom {
+        self.dom
+    }
+
+    /// The metadata read from the document's file, if it had any.
+    pub fn file_metadata(&self) -> Option<&FileMetadata> {
+        self.dom.extension(self.dom.root_ref())
+    }
+
+    /// A mutable reference to the document's file metadata, which is empty if
+    /// the document's file didn't have any.
+    pub fn file_metadata_mut(&mut self) -> &mut FileMetadata {
+        let root_ref = self.dom.root_ref();
+
+        if self.dom.extension::<FileMetadata>(root_ref).is_none() {
+            self.dom.insert_extension(root_ref, FileMetadata::default());
+        }
+
+        self.dom.extension_mut(root_ref).unwrap()
+    }
+
+    /// The format that the document will be saved in.
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// Changes the format that the document will be saved in.
+    pub fn set_format(&mut self, format: Format) {
+        self.format = format;
+    }
+
+    /// The path that the document was read from or last saved to.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Encodes the children of the document's root instance in its format.
+    pub fn to_vec(&self) -> Result<Vec<u8>, EncodeError> {
+        to_vec(&self.dom, self.dom.root().children(), self.format)
+    }
+
+    /// Writes the document back to the path it was read from or last saved to,
+    /// in its current format.
+    pub fn save(&self) -> Result<(), EncodeError> {
+        let path = self.path.as_ref().ok_or(EncodeError::NoPath)?;
+        fs_err::write(path, self.to_vec()?)?;
+
+        Ok(())
+    }
+
+    /// Writes the document to a new path, which is used by future calls to
+    /// [`Document::save`]. If the path's extension names a format, the
+    /// document is converted to it.
+    pub fn save_as<P: AsRef<Path>>(&mut self, path: P) -> Result<(), EncodeError> {
+        let path = path.as_ref();
+
+        if let Some(format) = Format::from_path(path) {
+            self.format = format;
+        }
+
+        fs_err::write(path, self.to_vec()?)?;
+        self.path = Some(path.to_owned());
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::InstanceBuilder;
+
+    #[test]
+    fn save_as_converts() {
+        let dir = std::env::temp_dir().join(format!("rbx_util-document-{}", std::process::id()));
+        fs_err::create_dir_all(&dir).unwrap();
+
+        let dom = WeakDom::new(
+            InstanceBuilder::new("DataModel").with_child(InstanceBuilder::new("Folder")),
+        );
+        let mut document = Document::new(dom, Format::Binary);
+        assert!(matches!(document.save(), Err(EncodeError::NoPath)));
+
+        let path = dir.join("model.rbxmx");
+        document.save_as(&path).unwrap();
+        assert_eq!(document.format(), Format::Xml);
+
+        let opened = Document::open(&path).unwrap();
+        assert_eq!(opened.format(), Format::Xml);
+        assert_eq!(opened.path(), Some(path.as_path()));
+        assert_eq!(opened.dom().root().children().len(), 1);
+
+        fs_err::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn keeps_file_metadata() {
+        let dom = WeakDom::new(
+            InstanceBuilder::new("DataModel").with_child(InstanceBuilder::new("Folder")),
+        );
+
+        for format in [Format::Binary, Format::Xml] {
+            let mut document = Document::new(dom.clone(), format);
+            assert_eq!(document.file_metadata(), None);
+
+            document
+                .file_metadata_mut()
+                .entries
+                .insert("ExplicitAutoJoints".to_owned(), "true".to_owned());
+
+            let reopened = Document::from_slice(&document.to_vec().unwrap()).unwrap();
+            assert_eq!(reopened.file_metadata(), document.file_metadata());
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use std::{collections::HashSet, fmt};
+
+use rbx_dom_weak::{
+    types::{Ref, Variant},
+    WeakDom,
+};
+
+use crate::path::descendants;
+
+/// Formats instances as a Graphviz graph in the DOT language, with an edge
+/// from every instance to each of its children.
+///
+/// ```
+/// use rbx_dom_weak::{InstanceBuilder, WeakDom};
+/// use rbx_util::DotGraph;
+///
+/// let dom = WeakDom::new(InstanceBuilder::new("DataModel").with_child(
+///     InstanceBuilder::new("Model").with_child(InstanceBuilder::new("Part")),
+/// ));
+///
+/// let dot = DotGraph::new(&dom).ref_edges(true).to_string();
+/// assert!(dot.starts_with("digraph {"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct DotGraph<'a> {
+    dom: &'a WeakDom,
+    roots: Vec<Ref>,
+    ref_edges: bool,
+}
+
+impl<'a> DotGraph<'a> {
+    /// Creates a graph of every child of the DOM's root and all of their
+    /// descendants.
+    pub fn new(dom: &'a WeakDom) -> Self {
+        DotGraph {
+            dom,
+            roots: dom.root().children().to_vec(),
+            ref_edges: false,
+        }
+    }
+
+    /// Includes only the instance with the given referent and its
+    /// descendants.
+    pub fn subtree(self, referent: Ref) -> Self {
+        DotGraph {
+            roots: vec![referent],
+            ..self
+        }
+    }
+
+    /// Sets whether `Ref` properties are drawn as dashed edges labeled with
+    /// the property's name. Only properties pointing at instances in the graph
+    /// are drawn. Defaults to `false`.
+    pub fn ref_edges(self, ref_edges: bool) -> Self {
+        DotGraph { ref_edges, ..self }
+    }
+}
+
+impl fmt::Display for DotGraph<'_> {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let included: Vec<Ref> = self
+            .roots
+            .iter()
+            .flat_map(|&root| descendants(self.dom, root))
+            .filter(|&referent| self.dom.get_by_ref(referent).is_some())
+            .collect();
+        let included_set: HashSet<Ref> = included.iter().copied().collect();
+
+        writeln!(formatter, "digraph {{")?;
+        writeln!(formatter, "  node [shape=box];")?;
+
+        for &referent in &included {
+            let instance = self.dom.get_by_ref(referent).unwrap();
+            let label = format!("{}\n{}", instance.name, instance.class);
+            writeln!(formatter, "  \"{}\" [label={:?}];", referent, label)?;
+        }
+
+        for &referent in &included {
+            let instance = self.dom.get_by_ref(referent).unwrap();
+
+            for child in instance.children() {
+                writeln!(formatter, "  \"{}\" -> \"{}\";", referent, child)?;
+            }
+
+            if !self.ref_edges {
+                continue;
+            }
+
+            // Sort properties so that output is stable between runs.
+            let mut properties: Vec<_> = instance.properties.iter().collect();
+            properties.sort_by(|a, b| a.0.cmp(b.0));
+
+            for (name, value) in properties {
+                if let Variant::Ref(target) = value {
+                    if included_set.contains(target) {
+                        writeln!(
+                            formatter,
+                            "  \"{}\" -> \"{}\" [style=dashed, label={:?}];",
+                            referent, target, name
+                        )?;
+                    }
+                }
+            }
+        }
+
+        writeln!(formatter, "}}")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::InstanceBuilder;
+
+    #[test]
+    fn children_and_refs() {
+        let part = InstanceBuilder::new("Part").with_name("Root");
+        let part_ref = part.referent();
+
+        let model = InstanceBuilder::new("Model")
+            .with_name("Rig")
+            .with_property("PrimaryPart", part_ref)
+            .with_child(part);
+        let model_ref = model.referent();
+
+        let dom = WeakDom::new(InstanceBuilder::new("DataModel").with_child(model));
+
+        let expected_nodes = format!(
+            "digraph {{\n  node [shape=box];\n  \"{model}\" [label=\"Rig\\nModel\"];\n  \"{part}\" [label=\"Root\\nPart\"];\n  \"{model}\" -> \"{part}\";\n",
+            model = model_ref,
+            part = part_ref,
+        );
+        let ref_edge = format!(
+            "  \"{}\" -> \"{}\" [style=dashed, label=\"PrimaryPart\"];\n",
+            model_ref, part_ref
+        );
+
+        assert_eq!(
+            DotGraph::new(&dom).to_string(),
+            format!("{}}}\n", expected_nodes)
+        );
+        assert_eq!(
+            DotGraph::new(&dom).ref_edges(true).to_string(),
+            format!("{}{}}}\n", expected_nodes, ref_edge)
+        );
+    }
+}
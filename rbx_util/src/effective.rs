@@ -0,0 +1,141 @@
+use rbx_dom_weak::{types::Ref, WeakDom};
+
+use crate::class::is_a;
+
+/// Tells whether a GUI instance would be drawn, taking its ancestors into
+/// account like Roblox does: a GuiObject or 3D GUI is hidden when its own
+/// `Visible` is false or when any GUI ancestor is hidden, and a LayerCollector
+/// like ScreenGui hides everything inside it when `Enabled` is false.
+///
+/// Instances that aren't GUIs are skipped over, so a Frame inside a Folder
+/// inside a ScreenGui still depends on the ScreenGui. Missing properties are
+/// treated as their default, which is `true`.
+///
+/// ## Panics
+/// Panics if `referent` does not refer to an instance in the DOM.
+pub fn effective_visible(dom: &WeakDom, referent: Ref) -> bool {
+    if dom.get_by_ref(referent).is_none() {
+        panic!("cannot get the visibility of an instance that does not exist");
+    }
+
+    let mut current = referent;
+
+    while let Some(instance) = dom.get_by_ref(current) {
+        let property = if is_a(&instance.class, "GuiObject") || is_a(&instance.class, "GuiBase3d") {
+            Some("Visible")
+        } else if is_a(&instance.class, "LayerCollector") {
+            Some("Enabled")
+        } else {
+            None
+        };
+
+        if let Some(property) = property {
+            if !instance.get_typed::<bool>(property).unwrap_or(true) {
+                return false;
+            }
+        }
+
+        current = instance.parent();
+    }
+
+    true
+}
+
+/// Returns the part that Roblox would use as a Model's `PrimaryPart`.
+///
+/// Roblox clears `PrimaryPart` when the part stops being a descendant of the
+/// model, so this returns `None` unless the property refers to a BasePart
+/// that is inside the model.
+///
+/// ## Panics
+/// Panics if `model` does not refer to an instance in the DOM.
+pub fn resolve_primary_part(dom: &WeakDom, model: Ref) -> Option<Ref> {
+    let instance = dom.get_by_ref(model).unwrap_or_else(|| {
+        panic!("cannot resolve the PrimaryPart of an instance that does not exist")
+    });
+
+    let part_ref = instance.get_typed::<Ref>("PrimaryPart").ok()?;
+    let part = dom.get_by_ref(part_ref)?;
+
+    if !is_a(&part.class, "BasePart") {
+        return None;
+    }
+
+    let mut current = part.parent();
+    while let Some(ancestor) = dom.get_by_ref(current) {
+        if current == model {
+            return Some(part_ref);
+        }
+
+        current = ancestor.parent();
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::InstanceBuilder;
+
+    #[test]
+    fn visibility_follows_ancestors() {
+        let hidden = InstanceBuilder::new("Frame").with_property("Visible", false);
+        let hidden_child = InstanceBuilder::new("TextLabel");
+        let hidden_child_ref = hidden_child.referent();
+        let hidden = hidden.with_child(hidden_child);
+
+        let shown = InstanceBuilder::new("Folder").with_child(InstanceBuilder::new("ImageLabel"));
+        let shown_ref = shown.referent();
+
+        let disabled = InstanceBuilder::new("ScreenGui")
+            .with_property("Enabled", false)
+            .with_child(InstanceBuilder::new("Frame"));
+        let disabled_ref = disabled.referent();
+
+        let dom = WeakDom::new(
+            InstanceBuilder::new("DataModel")
+                .with_child(
+                    InstanceBuilder::new("ScreenGui")
+                        .with_child(hidden)
+                        .with_child(shown),
+                )
+                .with_child(disabled),
+        );
+
+        assert!(!effective_visible(&dom, hidden_child_ref));
+
+        let shown_label = dom.get_by_ref(shown_ref).unwrap().children()[0];
+        assert!(effective_visible(&dom, shown_label));
+
+        let disabled_frame = dom.get_by_ref(disabled_ref).unwrap().children()[0];
+        assert!(!effective_visible(&dom, disabled_frame));
+    }
+
+    #[test]
+    fn primary_part_must_be_descendant() {
+        let inside = InstanceBuilder::new("Part");
+        let inside_ref = inside.referent();
+        let outside = InstanceBuilder::new("MeshPart");
+        let outside_ref = outside.referent();
+
+        let good = InstanceBuilder::new("Model")
+            .with_property("PrimaryPart", inside_ref)
+            .with_child(InstanceBuilder::new("Model").with_child(inside));
+        let good_ref = good.referent();
+
+        let bad = InstanceBuilder::new("Model").with_property("PrimaryPart", outside_ref);
+        let bad_ref = bad.referent();
+
+        let dom = WeakDom::new(
+            InstanceBuilder::new("DataModel")
+                .with_child(good)
+                .with_child(bad)
+                .with_child(outside),
+        );
+
+        assert_eq!(resolve_primary_part(&dom, good_ref), Some(inside_ref));
+        assert_eq!(resolve_primary_part(&dom, bad_ref), None);
+    }
+}
@@ -0,0 +1,370 @@
+use std::{io, path::PathBuf};
+
+use rbx_dom_weak::types::Ref;
+use thiserror::Error;
+
+use crate::MergeConflict;
+
+/// An error that can occur when decoding a model or place of either format.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum DecodeError {
+    /// The contents didn't look like a binary or XML model or place.
+    #[error("contents are not a Roblox model or place in a known format")]
+    UnknownFormat,
+
+    /// Reading the contents failed.
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    /// The contents looked like a binary model or place, but could not be
+    /// decoded.
+    #[error(transparent)]
+    Binary(#[from] rbx_binary::DecodeError),
+
+    /// The contents looked like an XML model or place, but could not be
+    /// decoded.
+    #[error(transparent)]
+    Xml(#[from] rbx_xml::DecodeError),
+
+    /// The contents looked like a JSON fragment, but could not be decoded.
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+}
+
+/// An error that can occur when encoding a model or place.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum EncodeError {
+    /// A document was saved without a path to save it to.
+    #[error("document has no path to save to")]
+    NoPath,
+
+    /// Writing the encoded contents failed.
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    /// The instances could not be encoded as a binary model or place.
+    #[error(transparent)]
+    Binary(#[from] rbx_binary::EncodeError),
+
+    /// The instances could not be encoded as an XML model or place.
+    #[error(transparent)]
+    Xml(#[from] rbx_xml::EncodeError),
+
+    /// The instances could not be encoded as a JSON fragment.
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+}
+
+impl From<rbx_file::DecodeError> for DecodeError {
+    fn from(error: rbx_file::DecodeError) -> Self {
+        match error {
+            rbx_file::DecodeError::UnknownFormat => DecodeError::UnknownFormat,
+            rbx_file::DecodeError::Io(error) => DecodeError::Io(error),
+            rbx_file::DecodeError::Binary(error) => DecodeError::Binary(error),
+            rbx_file::DecodeError::Xml(error) => DecodeError::Xml(error),
+        }
+    }
+}
+
+impl From<rbx_file::EncodeError> for EncodeError {
+    fn from(error: rbx_file::EncodeError) -> Self {
+        match error {
+            rbx_file::EncodeError::Binary(error) => EncodeError::Binary(error),
+            rbx_file::EncodeError::Xml(error) => EncodeError::Xml(error),
+        }
+    }
+}
+
+/// An error that can occur when converting a model or place between formats.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum ConvertError {
+    /// The input could not be decoded.
+    #[error(transparent)]
+    Decode(#[from] DecodeError),
+
+    /// The output could not be encoded or written.
+    #[error(transparent)]
+    Encode(#[from] EncodeError),
+}
+
+/// An error that can occur when reading or writing the entries of a
+/// LocalizationTable.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum LocalizationError {
+    /// The table's `Contents` property is not a string.
+    #[error("LocalizationTable.Contents is not a string")]
+    NotAString,
+
+    /// The table's `Contents` property is not valid localization JSON.
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+
+    /// The CSV could not be read or written.
+    #[error(transparent)]
+    Csv(#[from] csv::Error),
+
+    /// Writing the CSV failed.
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// An error that can occur when reading or writing a
+/// [`PlaceArchive`][crate::PlaceArchive].
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum ArchiveError {
+    /// The contents are not a zip file, or are a damaged one.
+    #[error("archive is malformed: {0}")]
+    Malformed(&'static str),
+
+    /// An entry in the archive is compressed with a method other than
+    /// deflate, which isn't supported.
+    #[error("archive entry {0} is compressed with an unsupported method")]
+    UnsupportedCompression(String),
+
+    /// The archive's manifest is newer than this version of rbx_util.
+    #[error("archive version {0} is not supported")]
+    UnsupportedVersion(u32),
+
+    /// The manifest or a fragment is missing from the archive.
+    #[error("archive has no entry named {0}")]
+    MissingEntry(String),
+
+    /// A fragment's path is too long to be written to a zip file, or an
+    /// entry is too large to be read into memory on this platform.
+    #[error("archive entry is too large")]
+    TooLarge,
+
+    /// Reading or writing the archive failed.
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    /// The manifest could not be read or written.
+    #[error(transparent)]
+    Manifest(#[from] serde_json::Error),
+
+    /// A fragment could not be decoded.
+    #[error(transparent)]
+    Decode(#[from] DecodeError),
+
+    /// A fragment could not be encoded.
+    #[error(transparent)]
+    Encode(#[from] EncodeError),
+
+    /// The place could not be split into fragments.
+    #[error(transparent)]
+    Split(#[from] SplitError),
+}
+
+/// An error that can occur when splitting a place with
+/// [`split`][crate::split].
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum SplitError {
+    /// An instance that needs attributes to keep a link to another model has
+    /// an `Attributes` property of a different type, which would have to be
+    /// overwritten.
+    #[error("instance {0} has an Attributes property that is not of type Attributes")]
+    NotAttributes(Ref),
+}
+
+/// An error that can occur when reading or applying a
+/// [`PlaceDelta`][crate::PlaceDelta].
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum DeltaError {
+    /// The contents are not a delta, or are a damaged one.
+    #[error("delta is malformed: {0}")]
+    Malformed(&'static str),
+
+    /// The delta was written by a newer version of rbx_util.
+    #[error("delta version {0} is not supported")]
+    UnsupportedVersion(u8),
+
+    /// The delta was applied to a different model or place than the one it
+    /// was computed from.
+    #[error("delta does not apply to this model or place")]
+    WrongBase,
+
+    /// A property value could not be written to the delta.
+    #[error(transparent)]
+    EncodeValue(#[from] rmp_serde::encode::Error),
+
+    /// A property value in the delta could not be read.
+    #[error(transparent)]
+    DecodeValue(#[from] rmp_serde::decode::Error),
+
+    /// Computing the delta was stopped by its cancel token.
+    #[error("the operation was cancelled")]
+    Cancelled,
+}
+
+/// An error that can occur when merging trees with [`merge`][crate::merge].
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum MergeError {
+    /// Both sides made changes that can't both be kept, and the strategy for
+    /// that kind of conflict didn't pick one.
+    #[error("merge conflict: {0}")]
+    Conflict(Box<MergeConflict>),
+
+    /// The trees could not be matched up, or the delta being merged could not
+    /// be applied.
+    #[error(transparent)]
+    Delta(#[from] DeltaError),
+}
+
+/// An error that can occur when a [`ProjectWatcher`][crate::ProjectWatcher]
+/// reads a file.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum WatchError {
+    /// A mounted file could not be read or decoded.
+    #[error("could not read {}: {source}", path.display())]
+    Read {
+        /// The path of the file.
+        path: PathBuf,
+
+        /// Why it could not be read.
+        source: DecodeError,
+    },
+
+    /// The instance to mount a file under is not in the tree.
+    #[error("instance {0} is not in the tree")]
+    ParentNotFound(Ref),
+
+    /// The instance to mount a file under came from another mounted file,
+    /// which would destroy it whenever that file changes.
+    #[error("instance {parent} is part of {}, which is already mounted", path.display())]
+    ParentInMount {
+        /// The instance the file was to be mounted under.
+        parent: Ref,
+
+        /// The path of the mounted file the instance came from.
+        path: PathBuf,
+    },
+
+    /// The delta between the tree before and after reading the files could
+    /// not be computed.
+    #[error(transparent)]
+    Delta(#[from] DeltaError),
+}
+
+/// An error that can occur when rolling ModuleScripts into a single script.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum BundleError {
+    /// The script to bundle is not in the DOM.
+    #[error("script {0} is not in the DOM")]
+    ScriptNotFound(Ref),
+
+    /// The instance to bundle is not a Script, LocalScript, or ModuleScript.
+    #[error("{0} is not a script")]
+    NotAScript(String),
+
+    /// A script requires an instance that is not a ModuleScript.
+    #[error("{script} requires {required}, which is not a ModuleScript")]
+    NotAModule {
+        /// The full name of the script with the require.
+        script: String,
+
+        /// The full name of the required instance.
+        required: String,
+    },
+}
+
+/// A change that an [`AccessControl`][crate::AccessControl] doesn't allow.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[non_exhaustive]
+pub enum AccessError {
+    /// The instance is in a subtree owned by someone else.
+    #[error("instance {referent} is owned by {owner}")]
+    Owned {
+        /// The referent of the instance the owner was set on.
+        referent: Ref,
+
+        /// The owner of the subtree.
+        owner: String,
+    },
+
+    /// The instance is in a subtree locked by someone else.
+    #[error("instance {referent} is locked by {holder}")]
+    Locked {
+        /// The referent of the instance the lock was set on.
+        referent: Ref,
+
+        /// The holder of the lock.
+        holder: String,
+    },
+}
+
+/// An error that can occur when reading an animation from a KeyframeSequence.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum AnimationError {
+    /// The KeyframeSequence is not in the DOM.
+    #[error("instance {0} is not in the DOM")]
+    InstanceNotFound(Ref),
+
+    /// The instance is not a KeyframeSequence.
+    #[error("expected a KeyframeSequence, but got a {0}")]
+    NotAKeyframeSequence(String),
+}
+
+/// An error that can occur when decoding a mesh file.
+#[cfg(feature = "mesh")]
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum MeshError {
+    /// The contents don't start with a mesh file's version header.
+    #[error("contents are not a Roblox mesh")]
+    NotAMesh,
+
+    /// The mesh is in a version of the format that can't be decoded.
+    #[error("mesh version {0} is not supported")]
+    UnsupportedVersion(String),
+
+    /// The mesh ended before all of its data was read.
+    #[error("mesh ended unexpectedly")]
+    Truncated,
+
+    /// A version 1 mesh was not valid text.
+    #[error("mesh text is malformed")]
+    InvalidText,
+
+    /// A face refers to a vertex that the mesh doesn't have.
+    #[error("a face refers to vertex {0}, which does not exist")]
+    InvalidFace(u32),
+}
+
+/// An error that can occur when reading or writing protobuf messages, or
+/// building a DOM out of them.
+#[cfg(feature = "proto")]
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum ProtoError {
+    /// The message is not valid protobuf, or doesn't match the schema.
+    #[error(transparent)]
+    Decode(#[from] prost::DecodeError),
+
+    /// The message matches the schema, but its contents don't make sense.
+    #[error("protobuf message is malformed: {0}")]
+    Malformed(&'static str),
+
+    /// More than one instance in a tree has the same referent.
+    #[error("referent {0} is used by more than one instance")]
+    DuplicateReferent(Ref),
+
+    /// A value of a type without its own message could not be written or
+    /// read as JSON.
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+
+    /// The instances could not be inserted into a DOM.
+    #[error(transparent)]
+    Insert(#[from] rbx_dom_weak::TreeConfigError),
+}
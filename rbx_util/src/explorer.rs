@@ -0,0 +1,98 @@
+use std::cmp::Ordering;
+
+use rbx_dom_weak::Instance;
+
+use crate::class::is_service;
+
+/// Services in the order Studio's Explorer shows them in a new place.
+const SERVICE_ORDER: &[&str] = &[
+    "Workspace",
+    "Players",
+    "Lighting",
+    "MaterialService",
+    "NetworkClient",
+    "ReplicatedFirst",
+    "ReplicatedStorage",
+    "ServerScriptService",
+    "ServerStorage",
+    "StarterGui",
+    "StarterPack",
+    "StarterPlayer",
+    "Teams",
+    "SoundService",
+    "Chat",
+    "TextChatService",
+    "VoiceChatService",
+    "LocalizationService",
+    "TestService",
+];
+
+/// Classes that the Explorer lists before other instances that aren't
+/// services, in this order.
+const PINNED_ORDER: &[&str] = &["Camera", "Terrain"];
+
+/// Compares two instances the way Studio's Explorer orders siblings: services
+/// first, in the order a new place lists them, then cameras and terrain, then
+/// everything else by name, ignoring case.
+///
+/// Studio takes the order of classes from metadata that isn't part of the
+/// reflection database, so this covers the classes that the Explorer treats
+/// specially and orders everything else by name, which is what the Explorer
+/// does for most classes. Instances that compare equal by name are ordered by
+/// their exact name and then by class, so sorting gives the same result every
+/// time.
+///
+/// ```
+/// use rbx_dom_weak::{InstanceBuilder, WeakDom};
+/// use rbx_util::explorer_order;
+///
+/// let dom = WeakDom::new(
+///     InstanceBuilder::new("DataModel")
+///         .with_child(InstanceBuilder::new("Folder").with_name("zebra"))
+///         .with_child(InstanceBuilder::new("Lighting"))
+///         .with_child(InstanceBuilder::new("Folder").with_name("Apple"))
+///         .with_child(InstanceBuilder::new("Workspace")),
+/// );
+///
+/// let mut children: Vec<_> = dom
+///     .root()
+///     .children()
+///     .iter()
+///     .map(|&child| dom.get_by_ref(child).unwrap())
+///     .collect();
+/// children.sort_by(|a, b| explorer_order(a, b));
+///
+/// let names: Vec<_> = children.iter().map(|child| child.name.as_str()).collect();
+/// assert_eq!(names, ["Workspace", "Lighting", "Apple", "zebra"]);
+/// ```
+pub fn explorer_order(a: &Instance, b: &Instance) -> Ordering {
+    category(a)
+        .cmp(&category(b))
+        .then_with(|| {
+            a.name
+                .chars()
+                .flat_map(char::to_lowercase)
+                .cmp(b.name.chars().flat_map(char::to_lowercase))
+        })
+        .then_with(|| a.name.cmp(&b.name))
+        .then_with(|| a.class.cmp(&b.class))
+}
+
+/// Groups instances so that groups listed earlier in the Explorer get lower
+/// numbers.
+fn category(instance: &Instance) -> usize {
+    let class = instance.class.as_str();
+
+    if let Some(index) = SERVICE_ORDER.iter().position(|&service| service == class) {
+        return index;
+    }
+
+    if is_service(class) {
+        return SERVICE_ORDER.len();
+    }
+
+    match PINNED_ORDER.iter().position(|&pinned| pinned == class) {
+        Some(index) => SERVICE_ORDER.len() + 1 + index,
+        None => SERVICE_ORDER.len() + 1 + PINNED_ORDER.len(),
+    }
+}
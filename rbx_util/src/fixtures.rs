@@ -0,0 +1,184 @@
+//! Representative trees and assertion helpers for testing code built on
+//! rbx-dom.
+//!
+//! Each fixture is a DOM whose root is a DataModel, holding instances that are
+//! known to survive being encoded and decoded in both formats. Downstream
+//! crates can run their own transforms over these trees and compare the
+//! results with [`assert_doms_eq`], or check their output with
+//! [`assert_round_trips`].
+//!
+//! ```
+//! use rbx_util::fixtures;
+//!
+//! for (name, dom) in fixtures::all() {
+//!     println!("checking {}", name);
+//!     fixtures::assert_round_trips(&dom);
+//! }
+//! ```
+
+use rbx_dom_weak::{
+    types::{
+        Attributes, BinaryString, CFrame, Color3, Content, Matrix3, SharedString, Tags, Vector3,
+    },
+    DomViewer, InstanceBuilder, WeakDom,
+};
+
+use crate::{from_slice, to_vec, Format};
+
+/// A UnionOperation, the result of a CSG operation, with the solid modeling
+/// data that Roblox stores for it.
+pub fn union() -> WeakDom {
+    let union = InstanceBuilder::new("UnionOperation")
+        .with_name("Union")
+        .with_property("AssetId", Content::from("rbxassetid://1818"))
+        .with_property("ChildData2", SharedString::new(b"csg child data".to_vec()))
+        .with_property("MeshData2", SharedString::new(b"csg mesh data".to_vec()))
+        .with_property("UsePartColor", true)
+        .with_property("Size", Vector3::new(4.0, 2.0, 6.0))
+        .with_property(
+            "CFrame",
+            CFrame::new(Vector3::new(0.0, 5.0, 0.0), Matrix3::identity()),
+        );
+
+    WeakDom::new(
+        InstanceBuilder::new("DataModel")
+            .with_child(InstanceBuilder::new("Workspace").with_child(union)),
+    )
+}
+
+/// A package: a Model marked by a PackageLink child.
+pub fn package() -> WeakDom {
+    WeakDom::new(
+        InstanceBuilder::new("DataModel").with_child(
+            InstanceBuilder::new("ReplicatedStorage").with_child(
+                InstanceBuilder::new("Model")
+                    .with_name("Tree")
+                    .with_child(
+                        InstanceBuilder::new("PackageLink")
+                            .with_property("PackageId", Content::from("rbxassetid://42"))
+                            .with_property("VersionIdSerialize", 3i64)
+                            .with_property("AutoUpdate", true),
+                    )
+                    .with_child(
+                        InstanceBuilder::new("Part")
+                            .with_name("Trunk")
+                            .with_property("Size", Vector3::new(1.0, 8.0, 1.0)),
+                    ),
+            ),
+        ),
+    )
+}
+
+/// Workspace with a Terrain holding voxel data.
+pub fn terrain() -> WeakDom {
+    WeakDom::new(
+        InstanceBuilder::new("DataModel").with_child(
+            InstanceBuilder::new("Workspace").with_child(
+                InstanceBuilder::new("Terrain")
+                    .with_property("SmoothGrid", BinaryString::from(vec![1, 5, 0, 0, 0]))
+                    .with_property("WaterColor", Color3::new(0.05, 0.33, 0.36))
+                    .with_property("WaterWaveSize", 0.15f32),
+            ),
+        ),
+    )
+}
+
+/// A Folder with attributes of several types and some CollectionService
+/// tags.
+pub fn attributes() -> WeakDom {
+    let mut tags = Tags::new();
+    tags.push("Spawner");
+    tags.push("EditorOnly");
+
+    // String attributes are always decoded as BinaryString, so that's what
+    // this fixture holds.
+    let attributes = Attributes::new()
+        .with("Health", 100.0f64)
+        .with("Enabled", true)
+        .with("Label", BinaryString::from(b"Spawn point".to_vec()))
+        .with("Tint", Color3::new(1.0, 0.5, 0.0))
+        .with("Offset", Vector3::new(0.0, 3.0, 0.0));
+
+    WeakDom::new(
+        InstanceBuilder::new("DataModel").with_child(
+            InstanceBuilder::new("Folder")
+                .with_name("Spawn")
+                .with_property("Attributes", attributes)
+                .with_property("Tags", tags),
+        ),
+    )
+}
+
+/// Every fixture, along with its name.
+pub fn all() -> Vec<(&'static str, WeakDom)> {
+    vec![
+        ("union", union()),
+        ("package", package()),
+        ("terrain", terrain()),
+        ("attributes", attributes()),
+    ]
+}
+
+/// Asserts that the children of both DOMs' roots have the same names,
+/// classes, properties, and children. `Ref` properties are compared by which
+/// instance they point to, rather than by their values, so DOMs that were
+/// decoded separately can be compared.
+///
+/// # Panics
+///
+/// Panics if the DOMs are different, showing both of them.
+pub fn assert_doms_eq(left: &WeakDom, right: &WeakDom) {
+    let left = view(left);
+    let right = view(right);
+
+    if left != right {
+        panic!("DOMs are not equal\n\nleft:\n{}\nright:\n{}", left, right);
+    }
+}
+
+/// Asserts that the DOM comes out the same after being encoded and decoded in
+/// both the binary and XML formats.
+///
+/// # Panics
+///
+/// Panics if the DOM can't be encoded or decoded, or comes out different.
+pub fn assert_round_trips(dom: &WeakDom) {
+    for format in [Format::Binary, Format::Xml] {
+        let encoded = to_vec(dom, dom.root().children(), format)
+            .unwrap_or_else(|err| panic!("could not encode as {:?}: {}", format, err));
+        let decoded = from_slice(&encoded)
+            .unwrap_or_else(|err| panic!("could not decode {:?}: {}", format, err));
+
+        let original = view(dom);
+        let round_tripped = view(&decoded);
+
+        if original != round_tripped {
+            panic!(
+                "DOM changed after a round trip through {:?}\n\nbefore:\n{}\nafter:\n{}",
+                format, original, round_tripped
+            );
+        }
+    }
+}
+
+fn view(dom: &WeakDom) -> String {
+    serde_yaml::to_string(&DomViewer::new().view_children(dom)).unwrap()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn fixtures_round_trip() {
+        for (_, dom) in all() {
+            assert_round_trips(&dom);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "DOMs are not equal")]
+    fn different_doms() {
+        assert_doms_eq(&union(), &package());
+    }
+}
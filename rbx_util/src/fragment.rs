@@ -0,0 +1,225 @@
+use std::collections::{BTreeMap, HashMap};
+
+use rbx_dom_weak::{
+    types::{Ref, Variant},
+    InstanceBuilder, WeakDom,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{from_slice, to_vec, DecodeError, EncodeError, Format};
+
+/// An instance and its descendants, as written to a JSON fragment.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonInstance {
+    referent: Ref,
+    name: String,
+    class_name: String,
+    #[serde(default)]
+    properties: BTreeMap<String, Variant>,
+    #[serde(default)]
+    children: Vec<JsonInstance>,
+}
+
+/// Encodes a single instance and its descendants as a standalone model in the
+/// given format, like what an editor would put on the clipboard when copying
+/// it.
+///
+/// ## Panics
+/// Panics if `referent` does not refer to an instance in the DOM.
+pub fn encode_instance(
+    dom: &WeakDom,
+    referent: Ref,
+    format: Format,
+) -> Result<Vec<u8>, EncodeError> {
+    assert!(
+        dom.get_by_ref(referent).is_some(),
+        "cannot encode an instance that does not exist"
+    );
+
+    to_vec(dom, &[referent], format)
+}
+
+/// Encodes a single instance and its descendants as JSON. Property values are
+/// written using their serde representation.
+///
+/// The result can be read back with [`decode_fragment`].
+///
+/// ## Panics
+/// Panics if `referent` does not refer to an instance in the DOM.
+pub fn encode_instance_json(dom: &WeakDom, referent: Ref) -> Result<String, EncodeError> {
+    let instance = json_instance(dom, referent);
+    Ok(serde_json::to_string_pretty(&instance)?)
+}
+
+fn json_instance(dom: &WeakDom, referent: Ref) -> JsonInstance {
+    let instance = dom
+        .get_by_ref(referent)
+        .unwrap_or_else(|| panic!("cannot encode an instance that does not exist"));
+
+    JsonInstance {
+        referent,
+        name: instance.name.clone(),
+        class_name: instance.class.clone(),
+        properties: instance
+            .properties
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect(),
+        children: instance
+            .children()
+            .iter()
+            .map(|&child| json_instance(dom, child))
+            .collect(),
+    }
+}
+
+/// Decodes a fragment made by [`encode_instance`] or [`encode_instance_json`],
+/// or any model file, and inserts its instances under `parent`. Returns the
+/// referents of the inserted instances.
+///
+/// Every inserted instance gets a new referent, so the same fragment can be
+/// inserted more than once. `Ref` properties pointing inside of the fragment
+/// are kept pointing at the inserted copies.
+///
+/// ```
+/// use rbx_dom_weak::{InstanceBuilder, WeakDom};
+/// use rbx_util::{decode_fragment, encode_instance_json};
+///
+/// let mut dom = WeakDom::new(InstanceBuilder::new("Folder"));
+/// let root_ref = dom.root_ref();
+/// let part = dom.insert(root_ref, InstanceBuilder::new("Part"));
+///
+/// let copied = encode_instance_json(&dom, part)?;
+/// let pasted = decode_fragment(&mut dom, root_ref, copied.as_bytes())?;
+///
+/// assert_eq!(pasted.len(), 1);
+/// assert_eq!(dom.root().children().len(), 2);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+///
+/// ## Panics
+/// Panics if `parent` does not refer to an instance in the DOM.
+pub fn decode_fragment(
+    dom: &mut WeakDom,
+    parent: Ref,
+    contents: &[u8],
+) -> Result<Vec<Ref>, DecodeError> {
+    assert!(
+        dom.get_by_ref(parent).is_some(),
+        "cannot insert into an instance that does not exist"
+    );
+
+    let is_json = contents
+        .iter()
+        .find(|byte| !byte.is_ascii_whitespace())
+        .is_some_and(|&byte| byte == b'{');
+
+    if is_json {
+        let instance: JsonInstance = serde_json::from_slice(contents)?;
+
+        let mut new_refs = HashMap::new();
+        assign_refs(&instance, &mut new_refs);
+
+        let builder = json_builder(instance, &new_refs);
+        return Ok(vec![dom.insert(parent, builder)]);
+    }
+
+    let mut fragment = from_slice(contents)?;
+    let roots = fragment.root().children().to_vec();
+
+    Ok(roots
+        .into_iter()
+        .map(|root| fragment.transfer(root, dom, parent))
+        .collect())
+}
+
+fn assign_refs(instance: &JsonInstance, new_refs: &mut HashMap<Ref, Ref>) {
+    new_refs.insert(instance.referent, Ref::new());
+
+    for child in &instance.children {
+        assign_refs(child, new_refs);
+    }
+}
+
+fn json_builder(instance: JsonInstance, new_refs: &HashMap<Ref, Ref>) -> InstanceBuilder {
+    let properties = instance.properties.into_iter().map(|(key, value)| {
+        let value = match value {
+            Variant::Ref(target) => Variant::Ref(new_refs.get(&target).copied().unwrap_or(target)),
+            other => other,
+        };
+        (key, value)
+    });
+
+    InstanceBuilder::new(instance.class_name)
+        .with_referent(new_refs[&instance.referent])
+        .with_name(instance.name)
+        .with_properties(properties)
+        .with_children(
+            instance
+                .children
+                .into_iter()
+                .map(|child| json_builder(child, new_refs)),
+        )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::types::Vector3;
+
+    use crate::fixtures::assert_doms_eq;
+
+    fn model() -> (WeakDom, Ref) {
+        let handle = InstanceBuilder::new("Part")
+            .with_name("Handle")
+            .with_property("Size", Vector3::new(1.0, 2.0, 3.0));
+        let model = InstanceBuilder::new("Model")
+            .with_name("Tool")
+            .with_property("PrimaryPart", handle.referent())
+            .with_child(handle);
+        let model_ref = model.referent();
+
+        (
+            WeakDom::new(InstanceBuilder::new("DataModel").with_child(model)),
+            model_ref,
+        )
+    }
+
+    #[test]
+    fn paste_twice() {
+        for format in [Some(Format::Binary), Some(Format::Xml), None] {
+            let (source, model_ref) = model();
+            let copied = match format {
+                Some(format) => encode_instance(&source, model_ref, format).unwrap(),
+                None => encode_instance_json(&source, model_ref)
+                    .unwrap()
+                    .into_bytes(),
+            };
+
+            let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+            let root_ref = dom.root_ref();
+            let first = decode_fragment(&mut dom, root_ref, &copied).unwrap();
+            let second = decode_fragment(&mut dom, root_ref, &copied).unwrap();
+            assert_ne!(first, second);
+
+            for pasted in [first[0], second[0]] {
+                let model = dom.get_by_ref(pasted).unwrap();
+                assert_eq!(
+                    model.properties.get("PrimaryPart"),
+                    Some(&Variant::Ref(model.children()[0]))
+                );
+            }
+
+            let mut expected = WeakDom::new(InstanceBuilder::new("DataModel"));
+            let expected_root = expected.root_ref();
+            for _ in 0..2 {
+                let (mut source, model_ref) = model();
+                source.transfer(model_ref, &mut expected, expected_root);
+            }
+
+            assert_doms_eq(&dom, &expected);
+        }
+    }
+}
@@ -0,0 +1,239 @@
+use rbx_dom_weak::{
+    types::{Color3, Content, Ref, UDim, UDim2, Variant, Vector2},
+    InstanceBuilder,
+};
+
+/// Describes a GUI instance and its children, setting common properties with
+/// the types that Roblox expects. Call [`GuiBuilder::build`] to turn the tree
+/// into an [`InstanceBuilder`] that can be inserted into a DOM.
+///
+/// ```
+/// use rbx_dom_weak::{types::Color3, WeakDom};
+/// use rbx_util::{scale, GuiBuilder};
+///
+/// let gui = GuiBuilder::screen_gui("HUD").child(
+///     GuiBuilder::frame("Panel")
+///         .size(scale(0.5, 0.25))
+///         .anchor_point(0.5, 0.0)
+///         .position(scale(0.5, 0.0))
+///         .background_color(Color3::new(0.1, 0.1, 0.1))
+///         .child(GuiBuilder::text_label("Title", "Hello!").text_size(24.0)),
+/// );
+///
+/// let dom = WeakDom::new(gui.build());
+/// assert_eq!(dom.root().class, "ScreenGui");
+/// ```
+#[derive(Debug)]
+pub struct GuiBuilder {
+    instance: InstanceBuilder,
+}
+
+/// Creates a `UDim2` with only scale, like Roblox's `UDim2.fromScale`.
+pub fn scale(x: f32, y: f32) -> UDim2 {
+    UDim2::new(UDim::new(x, 0), UDim::new(y, 0))
+}
+
+/// Creates a `UDim2` with only offset, like Roblox's `UDim2.fromOffset`.
+pub fn offset(x: i32, y: i32) -> UDim2 {
+    UDim2::new(UDim::new(0.0, x), UDim::new(0.0, y))
+}
+
+impl GuiBuilder {
+    /// Describes an instance of any class with the given name.
+    pub fn new<C: Into<String>, N: Into<String>>(class: C, name: N) -> Self {
+        GuiBuilder {
+            instance: InstanceBuilder::new(class).with_name(name),
+        }
+    }
+
+    /// Describes a ScreenGui, which holds GUIs that are drawn on the screen.
+    pub fn screen_gui<N: Into<String>>(name: N) -> Self {
+        Self::new("ScreenGui", name)
+    }
+
+    /// Describes a Frame.
+    pub fn frame<N: Into<String>>(name: N) -> Self {
+        Self::new("Frame", name)
+    }
+
+    /// Describes a TextLabel showing the given text.
+    pub fn text_label<N: Into<String>, T: Into<String>>(name: N, text: T) -> Self {
+        Self::new("TextLabel", name).text(text)
+    }
+
+    /// Describes a TextButton showing the given text.
+    pub fn text_button<N: Into<String>, T: Into<String>>(name: N, text: T) -> Self {
+        Self::new("TextButton", name).text(text)
+    }
+
+    /// Describes an ImageLabel showing the image with the given URL.
+    pub fn image_label<N: Into<String>, U: Into<String>>(name: N, image: U) -> Self {
+        Self::new("ImageLabel", name).image(image)
+    }
+
+    /// Describes an ImageButton showing the image with the given URL.
+    pub fn image_button<N: Into<String>, U: Into<String>>(name: N, image: U) -> Self {
+        Self::new("ImageButton", name).image(image)
+    }
+
+    /// Describes a UIListLayout, which arranges its siblings in a list.
+    pub fn list_layout() -> Self {
+        Self::new("UIListLayout", "UIListLayout")
+    }
+
+    /// Describes a UIPadding with the same padding on every side.
+    pub fn padding(padding: UDim) -> Self {
+        ["PaddingTop", "PaddingBottom", "PaddingLeft", "PaddingRight"]
+            .iter()
+            .fold(Self::new("UIPadding", "UIPadding"), |builder, &side| {
+                builder.property(side, padding)
+            })
+    }
+
+    /// Describes a UICorner with the given corner radius.
+    pub fn corner(radius: UDim) -> Self {
+        Self::new("UICorner", "UICorner").property("CornerRadius", radius)
+    }
+
+    /// The referent the instance will have once it's built, which can be used
+    /// to refer to it from `Ref` properties.
+    pub fn referent(&self) -> Ref {
+        self.instance.referent()
+    }
+
+    /// Sets any property, for properties that don't have their own method.
+    pub fn property<K: Into<String>, V: Into<Variant>>(self, key: K, value: V) -> Self {
+        GuiBuilder {
+            instance: self.instance.with_property(key, value),
+        }
+    }
+
+    /// Adds a child.
+    pub fn child(self, child: GuiBuilder) -> Self {
+        GuiBuilder {
+            instance: self.instance.with_child(child.instance),
+        }
+    }
+
+    /// Adds several children.
+    pub fn children<I: IntoIterator<Item = GuiBuilder>>(self, children: I) -> Self {
+        GuiBuilder {
+            instance: self
+                .instance
+                .with_children(children.into_iter().map(|child| child.instance)),
+        }
+    }
+
+    /// Sets `Size`.
+    pub fn size(self, size: UDim2) -> Self {
+        self.property("Size", size)
+    }
+
+    /// Sets `Position`.
+    pub fn position(self, position: UDim2) -> Self {
+        self.property("Position", position)
+    }
+
+    /// Sets `AnchorPoint`, the point of the instance that `Position` refers
+    /// to, from `(0, 0)` at the top left to `(1, 1)` at the bottom right.
+    pub fn anchor_point(self, x: f32, y: f32) -> Self {
+        self.property("AnchorPoint", Vector2::new(x, y))
+    }
+
+    /// Sets `BackgroundColor3`.
+    pub fn background_color(self, color: Color3) -> Self {
+        self.property("BackgroundColor3", color)
+    }
+
+    /// Sets `BackgroundTransparency`.
+    pub fn background_transparency(self, transparency: f32) -> Self {
+        self.property("BackgroundTransparency", transparency)
+    }
+
+    /// Sets `Visible`.
+    pub fn visible(self, visible: bool) -> Self {
+        self.property("Visible", visible)
+    }
+
+    /// Sets `ZIndex`.
+    pub fn z_index(self, z_index: i32) -> Self {
+        self.property("ZIndex", z_index)
+    }
+
+    /// Sets `LayoutOrder`, which layouts like UIListLayout can sort by.
+    pub fn layout_order(self, layout_order: i32) -> Self {
+        self.property("LayoutOrder", layout_order)
+    }
+
+    /// Sets `Text`.
+    pub fn text<T: Into<String>>(self, text: T) -> Self {
+        self.property("Text", text.into())
+    }
+
+    /// Sets `TextColor3`.
+    pub fn text_color(self, color: Color3) -> Self {
+        self.property("TextColor3", color)
+    }
+
+    /// Sets `TextSize`.
+    pub fn text_size(self, size: f32) -> Self {
+        self.property("TextSize", size)
+    }
+
+    /// Sets `Image`.
+    pub fn image<U: Into<String>>(self, url: U) -> Self {
+        self.property("Image", Content::from(url.into()))
+    }
+
+    /// Finishes the description, returning a builder for the instance and its
+    /// children.
+    pub fn build(self) -> InstanceBuilder {
+        self.instance
+    }
+}
+
+impl From<GuiBuilder> for InstanceBuilder {
+    fn from(builder: GuiBuilder) -> Self {
+        builder.build()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::WeakDom;
+
+    use crate::{from_slice, to_vec, Format};
+
+    #[test]
+    fn properties_round_trip() {
+        let gui = GuiBuilder::screen_gui("Menu").child(
+            GuiBuilder::frame("List")
+                .size(offset(200, 300))
+                .child(GuiBuilder::list_layout())
+                .child(GuiBuilder::padding(UDim::new(0.0, 4)))
+                .children((0..2).map(|i| {
+                    GuiBuilder::text_button(format!("Option{}", i), "Play")
+                        .layout_order(i)
+                        .text_size(18.0)
+                })),
+        );
+
+        let dom = WeakDom::new(InstanceBuilder::new("DataModel").with_child(gui.build()));
+        let encoded = to_vec(&dom, dom.root().children(), Format::Binary).unwrap();
+        let decoded = from_slice(&encoded).unwrap();
+
+        let screen_gui = decoded.get_by_ref(decoded.root().children()[0]).unwrap();
+        let list = decoded.get_by_ref(screen_gui.children()[0]).unwrap();
+        assert_eq!(list.get_typed::<UDim2>("Size").unwrap(), offset(200, 300));
+        assert_eq!(list.children().len(), 4);
+
+        let button = decoded.get_by_ref(list.children()[3]).unwrap();
+        assert_eq!(button.name, "Option1");
+        assert_eq!(button.class, "TextButton");
+        assert_eq!(button.get_typed::<i32>("LayoutOrder").unwrap(), 1);
+        assert_eq!(button.get_typed::<f32>("TextSize").unwrap(), 18.0);
+        assert_eq!(button.get_typed::<String>("Text").unwrap(), "Play");
+    }
+}
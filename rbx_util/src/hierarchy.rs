@@ -0,0 +1,148 @@
+use rbx_dom_weak::{types::Ref, WeakDom};
+
+use crate::{
+    class::{is_a, is_service, REQUIRED_PARENTS},
+    path::{descendants, full_name},
+};
+
+/// An instance in a place it shouldn't be, found by [`validate_hierarchy`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HierarchyIssue {
+    /// The referent of the misplaced instance.
+    pub referent: Ref,
+
+    /// The instance's full name, like `Workspace.StarterGui`.
+    pub path: String,
+
+    /// The instance's ClassName.
+    pub class: String,
+
+    /// The ClassName of the instance's parent.
+    pub parent_class: String,
+
+    /// What is wrong with where the instance is.
+    pub problem: HierarchyProblem,
+}
+
+/// Describes why an instance is misplaced.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum HierarchyProblem {
+    /// The instance is a service, but isn't a child of the DataModel.
+    ServiceOutsideDataModel,
+
+    /// The instance can only be parented to the given class, like Terrain to
+    /// Workspace.
+    WrongParent {
+        /// The class that the instance must be parented to.
+        required: String,
+    },
+
+    /// The instance is a script inside a value object like NumberValue, where
+    /// Studio puts it out of sight and it's easily forgotten.
+    ScriptInsideValue,
+}
+
+/// Finds instances that Roblox Studio would move, refuse to load, or warn
+/// about because of where they are in the tree.
+///
+/// Services are only checked when the root of the DOM is a DataModel, since
+/// models can't contain services at all.
+pub fn validate_hierarchy(dom: &WeakDom) -> Vec<HierarchyIssue> {
+    let is_place = dom.root().class == "DataModel";
+    let mut issues = Vec::new();
+
+    for referent in descendants(dom, dom.root_ref()).into_iter().skip(1) {
+        let instance = dom.get_by_ref(referent).unwrap();
+        let parent = dom.get_by_ref(instance.parent()).unwrap();
+
+        let required_parent = REQUIRED_PARENTS
+            .iter()
+            .find(|(class, _)| *class == instance.class)
+            .map(|&(_, required)| required);
+
+        let problem = if is_service(&instance.class) {
+            if !is_place || parent.referent() == dom.root_ref() {
+                continue;
+            }
+
+            HierarchyProblem::ServiceOutsideDataModel
+        } else if let Some(required) = required_parent {
+            if parent.class == required {
+                continue;
+            }
+
+            HierarchyProblem::WrongParent {
+                required: required.to_owned(),
+            }
+        } else if is_a(&parent.class, "ValueBase")
+            && (is_a(&instance.class, "BaseScript") || instance.class == "ModuleScript")
+        {
+            HierarchyProblem::ScriptInsideValue
+        } else {
+            continue;
+        };
+
+        issues.push(HierarchyIssue {
+            referent,
+            path: full_name(dom, referent),
+            class: instance.class.clone(),
+            parent_class: parent.class.clone(),
+            problem,
+        });
+    }
+
+    issues
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::InstanceBuilder;
+
+    #[test]
+    fn flags_misplaced_instances() {
+        let dom = WeakDom::new(
+            InstanceBuilder::new("DataModel")
+                .with_child(
+                    InstanceBuilder::new("Workspace")
+                        .with_child(InstanceBuilder::new("Terrain"))
+                        .with_child(InstanceBuilder::new("StarterGui")),
+                )
+                .with_child(
+                    InstanceBuilder::new("ReplicatedStorage")
+                        .with_child(InstanceBuilder::new("Terrain"))
+                        .with_child(
+                            InstanceBuilder::new("NumberValue")
+                                .with_child(InstanceBuilder::new("Script")),
+                        ),
+                ),
+        );
+
+        let problems: Vec<_> = validate_hierarchy(&dom)
+            .into_iter()
+            .map(|issue| (issue.path, issue.problem))
+            .collect();
+
+        assert_eq!(
+            problems,
+            [
+                (
+                    "Workspace.StarterGui".to_owned(),
+                    HierarchyProblem::ServiceOutsideDataModel
+                ),
+                (
+                    "ReplicatedStorage.Terrain".to_owned(),
+                    HierarchyProblem::WrongParent {
+                        required: "Workspace".to_owned()
+                    }
+                ),
+                (
+                    "ReplicatedStorage.NumberValue.Script".to_owned(),
+                    HierarchyProblem::ScriptInsideValue
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,217 @@
+use rbx_dom_weak::{
+    types::{Ref, Variant},
+    Instance, WeakDom,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::path::descendants;
+
+/// Describes instances that a tool should leave out, like tests or
+/// editor-only helpers. An instance is ignored if it matches any rule, and
+/// ignoring an instance ignores all of its descendants too.
+///
+/// Rules can be read from a project's configuration with serde:
+///
+/// ```
+/// let rules: rbx_util::IgnoreRules = serde_json::from_str(
+///     r#"{ "names": ["*.spec"], "tags": ["EditorOnly"] }"#,
+/// )?;
+///
+/// assert_eq!(rules.names, ["*.spec"]);
+/// assert!(rules.classes.is_empty());
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IgnoreRules {
+    /// Patterns matched against the whole Name of an instance, like `*.spec`.
+    /// `*` matches any number of characters and `?` matches exactly one.
+    pub names: Vec<String>,
+
+    /// ClassNames to ignore. These are compared exactly, without checking
+    /// superclasses.
+    pub classes: Vec<String>,
+
+    /// CollectionService tags to ignore, like `EditorOnly`.
+    pub tags: Vec<String>,
+}
+
+impl IgnoreRules {
+    /// Tells whether the instance itself matches one of the rules.
+    pub fn is_ignored(&self, instance: &Instance) -> bool {
+        if self
+            .names
+            .iter()
+            .any(|pattern| glob_match(pattern, &instance.name))
+        {
+            return true;
+        }
+
+        if self.classes.contains(&instance.class) {
+            return true;
+        }
+
+        match instance.properties.get("Tags") {
+            Some(Variant::Tags(tags)) => tags.iter().any(|tag| self.tags.iter().any(|t| t == tag)),
+            _ => false,
+        }
+    }
+
+    /// Returns the instances in the DOM that match one of the rules, without
+    /// the ones that are already left out because an ancestor matched. The
+    /// root of the DOM is never included, and the DOM isn't changed.
+    ///
+    /// These can be handed to an encoder, like with
+    /// [`rbx_binary::Serializer::skip_instances`], to leave them out of a
+    /// file while keeping them in the DOM.
+    pub fn ignored(&self, dom: &WeakDom) -> Vec<Ref> {
+        let mut ignored = Vec::new();
+        let mut stack = dom.root().children().to_vec();
+
+        while let Some(referent) = stack.pop() {
+            let instance = dom.get_by_ref(referent).unwrap();
+
+            if self.is_ignored(instance) {
+                ignored.push(referent);
+            } else {
+                stack.extend(instance.children());
+            }
+        }
+
+        ignored
+    }
+
+    /// Returns how many instances in the DOM would be left out by these
+    /// rules, counting descendants of ignored instances.
+    pub fn count_ignored(&self, dom: &WeakDom) -> usize {
+        self.ignored(dom)
+            .into_iter()
+            .map(|referent| descendants(dom, referent).len())
+            .sum()
+    }
+
+    /// Destroys every instance in the DOM that matches one of the rules, along
+    /// with its descendants. The root of the DOM is never destroyed. Returns
+    /// the number of instances that were destroyed, which is the same as what
+    /// [`IgnoreRules::count_ignored`] returns beforehand.
+    ///
+    /// To leave instances out without changing the DOM, use
+    /// [`IgnoreRules::without_ignored`] or pass [`IgnoreRules::ignored`] to
+    /// the encoder instead.
+    pub fn remove_ignored(&self, dom: &mut WeakDom) -> usize {
+        let mut count = 0;
+
+        for referent in self.ignored(dom) {
+            count += descendants(dom, referent).len();
+            dom.destroy(referent);
+        }
+
+        count
+    }
+
+    /// Returns a copy of the DOM without the instances that match one of the
+    /// rules or their descendants, leaving the DOM itself alone.
+    ///
+    /// This is what [`PlaceDelta::between_ignoring`][crate::PlaceDelta::between_ignoring] compares, so that
+    /// diffs leave out the same instances as encoding with
+    /// [`IgnoreRules::ignored`].
+    pub fn without_ignored(&self, dom: &WeakDom) -> WeakDom {
+        let mut copy = dom.clone();
+        self.remove_ignored(&mut copy);
+        copy
+    }
+}
+
+/// Matches `text` against a pattern where `*` matches any run of characters
+/// and `?` matches any single character.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+
+    // Where to resume from if the most recent `*` has to match one more
+    // character than it did last time.
+    let mut backtrack = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    t = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::{types::Tags, InstanceBuilder};
+
+    #[test]
+    fn globs() {
+        assert!(glob_match("*.spec", "Shop.spec"));
+        assert!(glob_match("*.spec", ".spec"));
+        assert!(!glob_match("*.spec", "Shop.spec.old"));
+        assert!(glob_match("Test?", "Test1"));
+        assert!(!glob_match("Test?", "Test"));
+        assert!(glob_match("*a*b*", "xxaxxbxx"));
+        assert!(glob_match("*", ""));
+    }
+
+    #[test]
+    fn removes_ignored() {
+        let mut editor_only = Tags::new();
+        editor_only.push("EditorOnly");
+
+        let mut dom = WeakDom::new(
+            InstanceBuilder::new("DataModel").with_child(
+                InstanceBuilder::new("ReplicatedStorage")
+                    .with_child(InstanceBuilder::new("ModuleScript").with_name("Shop"))
+                    .with_child(
+                        InstanceBuilder::new("ModuleScript")
+                            .with_name("Shop.spec")
+                            .with_child(InstanceBuilder::new("ModuleScript")),
+                    )
+                    .with_child(InstanceBuilder::new("Part").with_property("Tags", editor_only))
+                    .with_child(InstanceBuilder::new("Camera")),
+            ),
+        );
+
+        let rules = IgnoreRules {
+            names: vec!["*.spec".to_owned()],
+            classes: vec!["Camera".to_owned()],
+            tags: vec!["EditorOnly".to_owned()],
+        };
+
+        assert_eq!(rules.ignored(&dom).len(), 3);
+        assert_eq!(rules.count_ignored(&dom), 4);
+
+        let kept = rules.without_ignored(&dom);
+        assert_eq!(rules.count_ignored(&kept), 0);
+        assert_eq!(rules.count_ignored(&dom), 4);
+
+        assert_eq!(rules.remove_ignored(&mut dom), 4);
+        assert_eq!(rules.count_ignored(&dom), 0);
+
+        let storage = dom.get_by_ref(dom.root().children()[0]).unwrap();
+        assert_eq!(storage.children().len(), 1);
+        assert_eq!(dom.get_by_ref(storage.children()[0]).unwrap().name, "Shop");
+    }
+}
@@ -0,0 +1,268 @@
+//! Utilities for working with Roblox model and place files without caring
+//! which format they're stored in, built on top of rbx_binary and rbx_xml.
+//!
+//! ```no_run
+//! use std::fs;
+//!
+//! let contents = fs::read("MyModel.rbxm")?;
+//! let dom = rbx_util::from_slice(&contents)?;
+//!
+//! println!("Root instances in file:");
+//! for &referent in dom.root().children() {
+//!     let instance = dom.get_by_ref(referent).unwrap();
+//!     println!("- {}", instance.name);
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+#![deny(missing_docs)]
+
+mod access;
+mod animation;
+mod archive;
+mod assets;
+#[cfg(feature = "async")]
+mod async_io;
+#[cfg(feature = "bench")]
+pub mod bench;
+mod bundle;
+mod class;
+mod delta;
+mod delta_printer;
+mod diagnostics;
+mod document;
+mod dot;
+mod effective;
+mod error;
+mod explorer;
+pub mod fixtures;
+mod fragment;
+mod gui;
+mod hierarchy;
+mod ignore;
+mod lighting;
+mod line_diff;
+mod localization;
+mod manifest;
+mod merge;
+#[cfg(feature = "mesh")]
+mod mesh;
+mod names;
+mod normalize;
+mod packages;
+mod path;
+#[cfg(feature = "proto")]
+pub mod proto;
+mod refs;
+mod replicable;
+mod rig;
+mod schema;
+mod scripts;
+mod search;
+mod selection;
+mod size;
+mod split;
+mod tree;
+mod vfs;
+mod watch;
+
+use std::{
+    io::{Read, Write},
+    sync::Mutex,
+};
+
+use rbx_dom_weak::{types::Ref, WeakDom};
+
+pub use rbx_file::Format;
+
+pub use crate::{
+    access::AccessControl,
+    animation::{Animation, AnimationChannel, AnimationKeyframe, AnimationPose, ChannelKey},
+    archive::{ArchiveFragment, ArchiveReader, PlaceArchive},
+    assets::{audit_owned_assets, find_assets, parse_asset_id, rewrite_assets, AssetReference},
+    bundle::{bundle_modules, ModuleBundle},
+    delta::{DeltaNode, DeltaOp, DeltaSource, DeltaValue, PlaceDelta},
+    delta_printer::DeltaPrinter,
+    diagnostics::{Diagnostic, Diagnostics, Severity},
+    document::Document,
+    dot::DotGraph,
+    effective::{effective_visible, resolve_primary_part},
+    error::{
+        AccessError, AnimationError, ArchiveError, BundleError, ConvertError, DecodeError,
+        DeltaError, EncodeError, LocalizationError, MergeError, SplitError, WatchError,
+    },
+    explorer::explorer_order,
+    fragment::{decode_fragment, encode_instance, encode_instance_json},
+    gui::{offset, scale, GuiBuilder},
+    hierarchy::{validate_hierarchy, HierarchyIssue, HierarchyProblem},
+    ignore::IgnoreRules,
+    lighting::{Atmosphere, Bloom, ColorCorrection, Environment, Sky, Skybox},
+    line_diff::{diff_lines, unified_diff, LineChange},
+    localization::{
+        localization_from_csv, localization_to_csv, read_localization_table,
+        write_localization_table, LocalizationEntry,
+    },
+    manifest::{BuildManifest, ServiceManifest},
+    merge::{merge, MergeConflict, MergeSide, MergeStrategies, MergeStrategy},
+    names::{duplicate_names, file_name, rename_duplicates, DuplicateNames},
+    normalize::normalize_for_studio,
+    packages::{packages, PackageInfo},
+    refs::{dead_refs, DeadRef},
+    replicable::replicable_copy,
+    rig::{build_rig, RigType},
+    schema::{migrate_schema, SchemaIssue, SchemaProblem},
+    scripts::{apply_script_edits, edit_scripts, scripts, ScriptEdit, ScriptInfo},
+    search::{search, SearchMatch, SearchQuery, SourceLine},
+    selection::{export_selection, Selection},
+    size::estimate_serialized_size,
+    split::{join, split, SplitModel},
+    tree::TreePrinter,
+    vfs::{ArchiveVfs, DirectoryVfs, MemoryVfs, VfsProvider},
+    watch::ProjectWatcher,
+};
+
+#[cfg(feature = "async")]
+pub use crate::async_io::{from_async_reader, to_async_writer};
+
+#[cfg(feature = "mesh")]
+pub use crate::{error::MeshError, mesh::Mesh};
+
+#[cfg(feature = "proto")]
+pub use crate::error::ProtoError;
+
+/// Decodes a model or place from a stream, detecting whether it is in the
+/// binary or XML format.
+///
+/// The entire stream is read into memory before decoding starts.
+pub fn from_reader<R: Read>(reader: R) -> Result<WeakDom, DecodeError> {
+    Ok(rbx_file::from_reader(reader)?)
+}
+
+/// Decodes a model or place from a byte slice, detecting whether it is in the
+/// binary or XML format.
+pub fn from_slice(contents: &[u8]) -> Result<WeakDom, DecodeError> {
+    Ok(rbx_file::from_slice(contents)?)
+}
+
+/// Decodes a model or place from a byte slice that is known to be in the given
+/// format.
+///
+/// Properties that aren't in the reflection database are kept, so that
+/// re-encoding the DOM loses as little as possible.
+pub fn from_slice_with_format(contents: &[u8], format: Format) -> Result<WeakDom, DecodeError> {
+    Ok(rbx_file::from_slice_with_format(contents, format)?)
+}
+
+/// Decodes a model or place from a byte slice like [`from_slice`], also
+/// returning [`Diagnostics`] for anything in the contents that wasn't
+/// understood but didn't stop them from being decoded, like chunks that this
+/// version of rbx_binary doesn't know about.
+///
+/// Binary files from newer versions of Roblox are read as well as possible
+/// instead of failing, using rbx_binary's forward compatible mode.
+pub fn from_slice_with_diagnostics(contents: &[u8]) -> Result<(WeakDom, Diagnostics), DecodeError> {
+    match Format::detect(contents) {
+        Some(Format::Binary) => {
+            let diagnostics = Mutex::new(Diagnostics::new());
+            let dom = rbx_binary::Deserializer::new()
+                .forward_compatible(true)
+                .diagnostics(|diagnostic| diagnostics.lock().unwrap().push(diagnostic))
+                .deserialize(contents)?;
+
+            Ok((dom, diagnostics.into_inner().unwrap()))
+        }
+        Some(format) => Ok((
+            from_slice_with_format(contents, format)?,
+            Diagnostics::new(),
+        )),
+        None => Err(DecodeError::UnknownFormat),
+    }
+}
+
+/// Encodes the given instances and their descendants in the given format.
+///
+/// Properties that aren't in the reflection database are written too.
+pub fn to_vec(dom: &WeakDom, refs: &[Ref], format: Format) -> Result<Vec<u8>, EncodeError> {
+    let mut contents = Vec::new();
+    to_writer(&mut contents, dom, refs, format)?;
+
+    Ok(contents)
+}
+
+/// Encodes the given instances and their descendants in the given format,
+/// writing the result to a stream.
+///
+/// Properties that aren't in the reflection database are written too.
+pub fn to_writer<W: Write>(
+    writer: W,
+    dom: &WeakDom,
+    refs: &[Ref],
+    format: Format,
+) -> Result<(), EncodeError> {
+    Ok(rbx_file::to_writer(writer, dom, refs, format)?)
+}
+
+/// Reads a model or place in either format from `input` and writes it to
+/// `output` in the given format.
+///
+/// ```no_run
+/// use std::fs;
+///
+/// use rbx_util::Format;
+///
+/// let input = fs::File::open("MyPlace.rbxl")?;
+/// let output = fs::File::create("MyPlace.rbxlx")?;
+/// rbx_util::convert(input, output, Format::Xml)?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn convert<R: Read, W: Write>(
+    input: R,
+    mut output: W,
+    format: Format,
+) -> Result<(), ConvertError> {
+    let dom = from_reader(input)?;
+    let contents = to_vec(&dom, dom.root().children(), format)?;
+
+    output.write_all(&contents).map_err(EncodeError::from)?;
+    output.flush().map_err(EncodeError::from)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::InstanceBuilder;
+
+    #[test]
+    fn convert_keeps_unknown_properties() {
+        let dom =
+            WeakDom::new(InstanceBuilder::new("DataModel").with_child(
+                InstanceBuilder::new("Folder").with_property("WILL_NEVER_EXIST", 5.0f64),
+            ));
+        let binary = to_vec(&dom, dom.root().children(), Format::Binary).unwrap();
+
+        let mut xml = Vec::new();
+        convert(binary.as_slice(), &mut xml, Format::Xml).unwrap();
+        assert_eq!(Format::detect(&xml), Some(Format::Xml));
+
+        let mut binary = Vec::new();
+        convert(xml.as_slice(), &mut binary, Format::Binary).unwrap();
+
+        let decoded = from_slice(&binary).unwrap();
+        let folder = decoded.get_by_ref(decoded.root().children()[0]).unwrap();
+        assert_eq!(
+            folder.properties.get("WILL_NEVER_EXIST"),
+            Some(&5.0f64.into())
+        );
+    }
+
+    #[test]
+    fn decode_unknown() {
+        assert!(matches!(
+            from_slice(b"definitely not a model"),
+            Err(DecodeError::UnknownFormat)
+        ));
+    }
+}
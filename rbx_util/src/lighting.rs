@@ -0,0 +1,426 @@
+use rbx_dom_weak::{
+    types::{Color3, Content},
+    InstanceBuilder,
+};
+
+/// The settings of a Lighting service and the effects inside it, which
+/// together make up how a place looks.
+///
+/// Start from one of the presets and change what's needed:
+///
+/// ```
+/// use rbx_dom_weak::{InstanceBuilder, WeakDom};
+/// use rbx_util::{Bloom, Environment};
+///
+/// let environment = Environment {
+///     bloom: Some(Bloom {
+///         intensity: 0.5,
+///         ..Bloom::default()
+///     }),
+///     ..Environment::night()
+/// };
+///
+/// let dom = WeakDom::new(InstanceBuilder::new("DataModel").with_child(environment.build()));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Environment {
+    /// The time of day in hours, from 0 to 24.
+    pub clock_time: f32,
+
+    /// How bright the sun or moon is.
+    pub brightness: f32,
+
+    /// The color of light in places the sky can't reach, like indoors.
+    pub ambient: Color3,
+
+    /// The color of light in places the sky can reach.
+    pub outdoor_ambient: Color3,
+
+    /// Where on Earth the place is, in degrees, which moves the sun's path.
+    pub geographic_latitude: f32,
+
+    /// Whether parts cast shadows.
+    pub global_shadows: bool,
+
+    /// How much light reflects off of the environment onto parts.
+    pub environment_diffuse_scale: f32,
+
+    /// How much shiny parts reflect the environment.
+    pub environment_specular_scale: f32,
+
+    /// An Atmosphere to add, which adds haze and colors the horizon.
+    pub atmosphere: Option<Atmosphere>,
+
+    /// A Sky to add, which sets the skybox and celestial bodies.
+    pub sky: Option<Sky>,
+
+    /// A ColorCorrectionEffect to add.
+    pub color_correction: Option<ColorCorrection>,
+
+    /// A BloomEffect to add.
+    pub bloom: Option<Bloom>,
+}
+
+/// The settings of an Atmosphere. The default matches a new Atmosphere in
+/// Roblox Studio.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Atmosphere {
+    /// How much the atmosphere hides things in the distance.
+    pub density: f32,
+
+    /// How far away the atmosphere starts to hide things.
+    pub offset: f32,
+
+    /// The color of the atmosphere.
+    pub color: Color3,
+
+    /// The color of the atmosphere away from the sun.
+    pub decay: Color3,
+
+    /// How much the sun glows.
+    pub glare: f32,
+
+    /// How hazy the horizon is.
+    pub haze: f32,
+}
+
+/// The settings of a Sky. The default matches a new Sky in Roblox Studio.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sky {
+    /// The images on each side of the skybox, or `None` to keep Roblox's
+    /// default skybox.
+    pub skybox: Option<Skybox>,
+
+    /// Whether the sun, moon, and stars are drawn.
+    pub celestial_bodies_shown: bool,
+
+    /// How big the sun looks, in degrees.
+    pub sun_angular_size: f32,
+
+    /// How big the moon looks, in degrees.
+    pub moon_angular_size: f32,
+
+    /// How many stars there are at night.
+    pub star_count: i32,
+}
+
+/// The asset URLs of the six images that make up a skybox.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Skybox {
+    /// `SkyboxBk`.
+    pub back: String,
+
+    /// `SkyboxDn`.
+    pub down: String,
+
+    /// `SkyboxFt`.
+    pub front: String,
+
+    /// `SkyboxLf`.
+    pub left: String,
+
+    /// `SkyboxRt`.
+    pub right: String,
+
+    /// `SkyboxUp`.
+    pub up: String,
+}
+
+/// The settings of a ColorCorrectionEffect. The default makes no change.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorCorrection {
+    /// How much brighter to make everything, from -1 to 1.
+    pub brightness: f32,
+
+    /// How much more contrast to add, from -1 to 1.
+    pub contrast: f32,
+
+    /// How much more saturated to make colors, from -1 to 1.
+    pub saturation: f32,
+
+    /// A color to multiply everything by.
+    pub tint_color: Color3,
+}
+
+/// The settings of a BloomEffect. The default matches a new BloomEffect in
+/// Roblox Studio.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bloom {
+    /// How bright the glow is.
+    pub intensity: f32,
+
+    /// How far the glow spreads.
+    pub size: f32,
+
+    /// How bright something has to be to glow.
+    pub threshold: f32,
+}
+
+impl Environment {
+    /// Midday with Roblox's current default lighting.
+    pub fn day() -> Self {
+        Environment {
+            clock_time: 14.5,
+            brightness: 3.0,
+            ambient: Color3::new(0.0, 0.0, 0.0),
+            outdoor_ambient: gray(0.5),
+            geographic_latitude: 0.0,
+            global_shadows: true,
+            environment_diffuse_scale: 1.0,
+            environment_specular_scale: 1.0,
+            atmosphere: Some(Atmosphere::default()),
+            sky: Some(Sky::default()),
+            color_correction: None,
+            bloom: Some(Bloom::default()),
+        }
+    }
+
+    /// Late evening, with warm light and a dense, orange horizon.
+    pub fn sunset() -> Self {
+        Environment {
+            clock_time: 17.8,
+            brightness: 2.0,
+            outdoor_ambient: Color3::new(0.55, 0.45, 0.4),
+            atmosphere: Some(Atmosphere {
+                density: 0.45,
+                color: Color3::new(0.95, 0.6, 0.45),
+                decay: Color3::new(0.45, 0.3, 0.35),
+                glare: 1.0,
+                haze: 1.5,
+                ..Atmosphere::default()
+            }),
+            color_correction: Some(ColorCorrection {
+                saturation: 0.1,
+                tint_color: Color3::new(1.0, 0.95, 0.9),
+                ..ColorCorrection::default()
+            }),
+            ..Environment::day()
+        }
+    }
+
+    /// Midnight, lit mostly by ambient light.
+    pub fn night() -> Self {
+        Environment {
+            clock_time: 0.0,
+            brightness: 1.0,
+            outdoor_ambient: Color3::new(0.3, 0.3, 0.4),
+            atmosphere: Some(Atmosphere {
+                color: Color3::new(0.25, 0.3, 0.4),
+                decay: Color3::new(0.1, 0.1, 0.15),
+                ..Atmosphere::default()
+            }),
+            ..Environment::day()
+        }
+    }
+
+    /// Returns a builder for the Lighting service with its effects as
+    /// children.
+    pub fn build(&self) -> InstanceBuilder {
+        let mut lighting = InstanceBuilder::new("Lighting")
+            .with_property("ClockTime", self.clock_time)
+            .with_property("Brightness", self.brightness)
+            .with_property("Ambient", self.ambient)
+            .with_property("OutdoorAmbient", self.outdoor_ambient)
+            .with_property("GeographicLatitude", self.geographic_latitude)
+            .with_property("GlobalShadows", self.global_shadows)
+            .with_property("EnvironmentDiffuseScale", self.environment_diffuse_scale)
+            .with_property("EnvironmentSpecularScale", self.environment_specular_scale);
+
+        if let Some(atmosphere) = &self.atmosphere {
+            lighting.add_child(atmosphere.build());
+        }
+        if let Some(sky) = &self.sky {
+            lighting.add_child(sky.build());
+        }
+        if let Some(color_correction) = &self.color_correction {
+            lighting.add_child(color_correction.build());
+        }
+        if let Some(bloom) = &self.bloom {
+            lighting.add_child(bloom.build());
+        }
+
+        lighting
+    }
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Environment::day()
+    }
+}
+
+impl Atmosphere {
+    /// Returns a builder for an Atmosphere with these settings.
+    pub fn build(&self) -> InstanceBuilder {
+        InstanceBuilder::new("Atmosphere")
+            .with_property("Density", self.density)
+            .with_property("Offset", self.offset)
+            .with_property("Color", self.color)
+            .with_property("Decay", self.decay)
+            .with_property("Glare", self.glare)
+            .with_property("Haze", self.haze)
+    }
+}
+
+impl Default for Atmosphere {
+    fn default() -> Self {
+        Atmosphere {
+            density: 0.395,
+            offset: 0.0,
+            color: rgb(199, 199, 199),
+            decay: rgb(106, 112, 125),
+            glare: 0.0,
+            haze: 0.0,
+        }
+    }
+}
+
+impl Sky {
+    /// Returns a builder for a Sky with these settings.
+    pub fn build(&self) -> InstanceBuilder {
+        let mut sky = InstanceBuilder::new("Sky")
+            .with_property("CelestialBodiesShown", self.celestial_bodies_shown)
+            .with_property("SunAngularSize", self.sun_angular_size)
+            .with_property("MoonAngularSize", self.moon_angular_size)
+            .with_property("StarCount", self.star_count);
+
+        if let Some(skybox) = &self.skybox {
+            let faces = [
+                ("SkyboxBk", &skybox.back),
+                ("SkyboxDn", &skybox.down),
+                ("SkyboxFt", &skybox.front),
+                ("SkyboxLf", &skybox.left),
+                ("SkyboxRt", &skybox.right),
+                ("SkyboxUp", &skybox.up),
+            ];
+
+            for (property, url) in faces {
+                sky.add_property(property, Content::from(url.as_str()));
+            }
+        }
+
+        sky
+    }
+}
+
+impl Default for Sky {
+    fn default() -> Self {
+        Sky {
+            skybox: None,
+            celestial_bodies_shown: true,
+            sun_angular_size: 21.0,
+            moon_angular_size: 11.0,
+            star_count: 3000,
+        }
+    }
+}
+
+impl ColorCorrection {
+    /// Returns a builder for a ColorCorrectionEffect with these settings.
+    pub fn build(&self) -> InstanceBuilder {
+        InstanceBuilder::new("ColorCorrectionEffect")
+            .with_property("Brightness", self.brightness)
+            .with_property("Contrast", self.contrast)
+            .with_property("Saturation", self.saturation)
+            .with_property("TintColor", self.tint_color)
+    }
+}
+
+impl Default for ColorCorrection {
+    fn default() -> Self {
+        ColorCorrection {
+            brightness: 0.0,
+            contrast: 0.0,
+            saturation: 0.0,
+            tint_color: gray(1.0),
+        }
+    }
+}
+
+impl Bloom {
+    /// Returns a builder for a BloomEffect with these settings.
+    pub fn build(&self) -> InstanceBuilder {
+        InstanceBuilder::new("BloomEffect")
+            .with_property("Intensity", self.intensity)
+            .with_property("Size", self.size)
+            .with_property("Threshold", self.threshold)
+    }
+}
+
+impl Default for Bloom {
+    fn default() -> Self {
+        Bloom {
+            intensity: 1.0,
+            size: 24.0,
+            threshold: 2.0,
+        }
+    }
+}
+
+fn gray(value: f32) -> Color3 {
+    Color3::new(value, value, value)
+}
+
+fn rgb(r: u8, g: u8, b: u8) -> Color3 {
+    Color3::new(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::WeakDom;
+    use rbx_reflection::DataType;
+
+    use crate::{class::class_descriptors, path::descendants};
+
+    #[test]
+    fn properties_match_database() {
+        let environment = Environment {
+            sky: Some(Sky {
+                skybox: Some(Skybox {
+                    back: "rbxassetid://1".to_owned(),
+                    down: "rbxassetid://2".to_owned(),
+                    front: "rbxassetid://3".to_owned(),
+                    left: "rbxassetid://4".to_owned(),
+                    right: "rbxassetid://5".to_owned(),
+                    up: "rbxassetid://6".to_owned(),
+                }),
+                ..Sky::default()
+            }),
+            ..Environment::sunset()
+        };
+
+        let dom = WeakDom::new(environment.build());
+        let mut classes = Vec::new();
+
+        for referent in descendants(&dom, dom.root_ref()) {
+            let instance = dom.get_by_ref(referent).unwrap();
+            classes.push(instance.class.as_str());
+
+            for (name, value) in &instance.properties {
+                let descriptor = class_descriptors(&instance.class)
+                    .find_map(|class| class.properties.get(name.as_str()))
+                    .unwrap_or_else(|| panic!("{}.{} is not a property", instance.class, name));
+
+                assert!(
+                    matches!(descriptor.data_type, DataType::Value(ty) if ty == value.ty()),
+                    "{}.{} has the wrong type",
+                    instance.class,
+                    name
+                );
+            }
+        }
+
+        assert_eq!(
+            classes,
+            [
+                "Lighting",
+                "Atmosphere",
+                "Sky",
+                "ColorCorrectionEffect",
+                "BloomEffect"
+            ]
+        );
+    }
+}
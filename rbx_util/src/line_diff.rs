@@ -0,0 +1,346 @@
+use std::fmt::Write;
+
+use crate::ScriptEdit;
+
+/// One line of the difference between two sources, returned by
+/// [`diff_lines`]. Lines include their line ending, if they have one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineChange<'a> {
+    /// A line that is in both sources.
+    Unchanged(&'a str),
+
+    /// A line that is only in the old source.
+    Removed(&'a str),
+
+    /// A line that is only in the new source.
+    Added(&'a str),
+}
+
+impl<'a> LineChange<'a> {
+    /// Returns the line, including its line ending.
+    pub fn text(&self) -> &'a str {
+        match self {
+            LineChange::Unchanged(text) | LineChange::Removed(text) | LineChange::Added(text) => {
+                text
+            }
+        }
+    }
+}
+
+/// Returns the shortest list of lines to remove from `old` and add to it that
+/// turns it into `new`, along with the lines that stay the same, in order.
+///
+/// ```
+/// use rbx_util::{diff_lines, LineChange};
+///
+/// let changes = diff_lines("local a = 1\nprint(a)\n", "local a = 2\nprint(a)\n");
+///
+/// assert_eq!(
+///     changes,
+///     [
+///         LineChange::Removed("local a = 1\n"),
+///         LineChange::Added("local a = 2\n"),
+///         LineChange::Unchanged("print(a)\n"),
+///     ]
+/// );
+/// ```
+pub fn diff_lines<'a>(old: &'a str, new: &'a str) -> Vec<LineChange<'a>> {
+    let old: Vec<&str> = old.split_inclusive('\n').collect();
+    let new: Vec<&str> = new.split_inclusive('\n').collect();
+
+    // Lines at the start and end that are the same don't need to go through
+    // the diff itself, which keeps small changes to long scripts fast.
+    let prefix = old
+        .iter()
+        .zip(&new)
+        .take_while(|(old, new)| old == new)
+        .count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(old, new)| old == new)
+        .count();
+
+    let mut changes = Vec::with_capacity(old.len().max(new.len()));
+    changes.extend(old[..prefix].iter().map(|line| LineChange::Unchanged(line)));
+    myers(
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+        &mut changes,
+    );
+    changes.extend(
+        old[old.len() - suffix..]
+            .iter()
+            .map(|line| LineChange::Unchanged(line)),
+    );
+
+    changes
+}
+
+/// Writes the difference between two sources in the unified format used by
+/// `diff -u` and Git, with `context` unchanged lines around every change.
+/// Returns an empty string if the sources are the same.
+///
+/// `old_name` and `new_name` are written in the `---` and `+++` lines.
+///
+/// ```
+/// let patch = rbx_util::unified_diff("a\nb\n", "a\nc\n", "a/Main", "b/Main", 3);
+///
+/// assert_eq!(patch, "--- a/Main\n+++ b/Main\n@@ -1,2 +1,2 @@\n a\n-b\n+c\n");
+/// ```
+pub fn unified_diff(
+    old: &str,
+    new: &str,
+    old_name: &str,
+    new_name: &str,
+    context: usize,
+) -> String {
+    let changes = diff_lines(old, new);
+    let mut output = String::new();
+
+    let changed: Vec<usize> = changes
+        .iter()
+        .enumerate()
+        .filter(|(_, change)| !matches!(change, LineChange::Unchanged(_)))
+        .map(|(index, _)| index)
+        .collect();
+
+    if changed.is_empty() {
+        return output;
+    }
+
+    writeln!(output, "--- {}", old_name).unwrap();
+    writeln!(output, "+++ {}", new_name).unwrap();
+
+    let mut hunk_start = 0;
+    while hunk_start < changed.len() {
+        // Changes that are close enough for their context to touch go in the
+        // same hunk.
+        let mut hunk_end = hunk_start;
+        while hunk_end + 1 < changed.len()
+            && changed[hunk_end + 1] - changed[hunk_end] <= 2 * context + 1
+        {
+            hunk_end += 1;
+        }
+
+        let start = changed[hunk_start].saturating_sub(context);
+        let end = (changed[hunk_end] + context + 1).min(changes.len());
+        write_hunk(&mut output, &changes, start, end);
+
+        hunk_start = hunk_end + 1;
+    }
+
+    output
+}
+
+impl ScriptEdit {
+    /// Returns the difference between the script's old and new source, line
+    /// by line. See [`diff_lines`].
+    pub fn diff(&self) -> Vec<LineChange<'_>> {
+        diff_lines(&self.old_source, &self.new_source)
+    }
+
+    /// Writes the difference between the script's old and new source as a
+    /// patch in the unified format, named after the script's path. See
+    /// [`unified_diff`].
+    pub fn unified_diff(&self, context: usize) -> String {
+        unified_diff(
+            &self.old_source,
+            &self.new_source,
+            &format!("a/{}", self.path),
+            &format!("b/{}", self.path),
+            context,
+        )
+    }
+}
+
+/// Writes the changes from `start` to `end` as one hunk.
+fn write_hunk(output: &mut String, changes: &[LineChange<'_>], start: usize, end: usize) {
+    let (mut old_line, mut new_line) = (0, 0);
+    for change in &changes[..start] {
+        match change {
+            LineChange::Unchanged(_) => {
+                old_line += 1;
+                new_line += 1;
+            }
+            LineChange::Removed(_) => old_line += 1,
+            LineChange::Added(_) => new_line += 1,
+        }
+    }
+
+    let hunk = &changes[start..end];
+    let old_len = hunk
+        .iter()
+        .filter(|change| !matches!(change, LineChange::Added(_)))
+        .count();
+    let new_len = hunk
+        .iter()
+        .filter(|change| !matches!(change, LineChange::Removed(_)))
+        .count();
+
+    // Like other tools, empty ranges start at the line before them.
+    let range_start = |line: usize, len: usize| if len == 0 { line } else { line + 1 };
+    writeln!(
+        output,
+        "@@ -{},{} +{},{} @@",
+        range_start(old_line, old_len),
+        old_len,
+        range_start(new_line, new_len),
+        new_len
+    )
+    .unwrap();
+
+    for change in hunk {
+        let marker = match change {
+            LineChange::Unchanged(_) => ' ',
+            LineChange::Removed(_) => '-',
+            LineChange::Added(_) => '+',
+        };
+
+        output.push(marker);
+        output.push_str(change.text());
+
+        if !change.text().ends_with('\n') {
+            output.push_str("\n\\ No newline at end of file\n");
+        }
+    }
+}
+
+/// Appends the difference between `old` and `new` to `changes`, using Myers'
+/// algorithm to find the fewest lines to remove and add.
+fn myers<'a>(old: &[&'a str], new: &[&'a str], changes: &mut Vec<LineChange<'a>>) {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let max = n + m;
+    if max == 0 {
+        return;
+    }
+
+    // `furthest[k + max]` is how far along `old` the furthest path on
+    // diagonal `k` of the edit graph has gotten. A copy of the part of it
+    // that can be reached with `d` edits is kept for every `d`, to walk the
+    // path back afterwards.
+    let mut furthest = vec![0isize; 2 * max as usize + 2];
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+    let at = |k: isize| (k + max) as usize;
+
+    'search: for d in 0..=max {
+        trace.push(furthest[at(-d)..=at(d)].to_vec());
+
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && furthest[at(k - 1)] < furthest[at(k + 1)]) {
+                furthest[at(k + 1)]
+            } else {
+                furthest[at(k - 1)] + 1
+            };
+            let mut y = x - k;
+
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+
+            furthest[at(k)] = x;
+
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+    }
+
+    let start = changes.len();
+    let (mut x, mut y) = (n, m);
+
+    for d in (1..trace.len() as isize).rev() {
+        let before = &trace[d as usize];
+        let reached = |k: isize| before[(k + d) as usize];
+
+        let k = x - y;
+        let previous_k = if k == -d || (k != d && reached(k - 1) < reached(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let previous_x = reached(previous_k);
+        let previous_y = previous_x - previous_k;
+
+        while x > previous_x && y > previous_y {
+            x -= 1;
+            y -= 1;
+            changes.push(LineChange::Unchanged(old[x as usize]));
+        }
+
+        if x == previous_x {
+            y -= 1;
+            changes.push(LineChange::Added(new[y as usize]));
+        } else {
+            x -= 1;
+            changes.push(LineChange::Removed(old[x as usize]));
+        }
+    }
+
+    while x > 0 && y > 0 {
+        x -= 1;
+        y -= 1;
+        changes.push(LineChange::Unchanged(old[x as usize]));
+    }
+
+    changes[start..].reverse();
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::{InstanceBuilder, WeakDom};
+
+    use crate::edit_scripts;
+
+    #[test]
+    fn script_diffs() {
+        let old = "local a = 1\nlocal b = 2\nlocal c = 3\nprint(a)\n";
+        let new = "local a = 1\nlocal c = 3\nprint(a)\nprint(c)";
+
+        let changes = diff_lines(old, new);
+        let removed: Vec<_> = changes
+            .iter()
+            .filter(|change| matches!(change, LineChange::Removed(_)))
+            .collect();
+        assert_eq!(removed, [&LineChange::Removed("local b = 2\n")]);
+
+        // Rebuilding both sides from the changes gives back the sources.
+        let rebuilt = |keep: fn(&LineChange) -> bool| -> String {
+            changes
+                .iter()
+                .filter(|change| keep(change))
+                .map(LineChange::text)
+                .collect()
+        };
+        assert_eq!(
+            rebuilt(|change| !matches!(change, LineChange::Added(_))),
+            old
+        );
+        assert_eq!(
+            rebuilt(|change| !matches!(change, LineChange::Removed(_))),
+            new
+        );
+
+        let mut dom = WeakDom::new(
+            InstanceBuilder::new("DataModel").with_child(
+                InstanceBuilder::new("ModuleScript")
+                    .with_name("Main")
+                    .with_property("Source", old),
+            ),
+        );
+        let edits = edit_scripts(&mut dom, |_| Some(new.to_owned()));
+
+        assert_eq!(
+            edits[0].unified_diff(1),
+            "--- a/Main\n+++ b/Main\n\
+             @@ -1,4 +1,4 @@\n local a = 1\n-local b = 2\n local c = 3\n print(a)\n\
+             +print(c)\n\\ No newline at end of file\n"
+        );
+        assert_eq!(edits[0].unified_diff(0).matches("@@ -").count(), 2);
+        assert!(unified_diff(old, old, "a", "b", 3).is_empty());
+    }
+}
@@ -0,0 +1,161 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use rbx_dom_weak::{types::Variant, Instance};
+use serde::{Deserialize, Serialize};
+
+use crate::{error::LocalizationError, path::text_value};
+
+/// The CSV columns that come before the per-locale columns.
+const FIXED_COLUMNS: &[&str] = &["Key", "Context", "Example", "Source"];
+
+/// One entry of a LocalizationTable.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LocalizationEntry {
+    /// The key used to look up this entry from scripts. May be empty.
+    pub key: String,
+
+    /// Where the source text appears, used to tell apart identical source
+    /// text in different places.
+    pub context: String,
+
+    /// An example of the text with its parameters filled in.
+    #[serde(rename = "examples")]
+    pub example: String,
+
+    /// The text in the table's source language.
+    pub source: String,
+
+    /// Translations of the source text, keyed by locale ID like `es` or
+    /// `pt-br`.
+    pub values: BTreeMap<String, String>,
+}
+
+/// Reads the entries of a LocalizationTable from its `Contents` property. A
+/// table with no `Contents` has no entries.
+pub fn read_localization_table(
+    instance: &Instance,
+) -> Result<Vec<LocalizationEntry>, LocalizationError> {
+    let contents = match instance.properties.get("Contents") {
+        Some(value) => text_value(value).ok_or(LocalizationError::NotAString)?,
+        None => return Ok(Vec::new()),
+    };
+
+    if contents.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+
+    Ok(serde_json::from_str(&contents)?)
+}
+
+/// Replaces the `Contents` property of a LocalizationTable with the given
+/// entries.
+pub fn write_localization_table(
+    instance: &mut Instance,
+    entries: &[LocalizationEntry],
+) -> Result<(), LocalizationError> {
+    let contents = serde_json::to_string(entries)?;
+    instance
+        .properties
+        .insert("Contents".to_owned(), Variant::String(contents));
+
+    Ok(())
+}
+
+/// Converts localization entries to CSV in the same layout that Roblox Studio
+/// exports: `Key`, `Context`, `Example`, and `Source` columns followed by one
+/// column per locale.
+pub fn localization_to_csv(entries: &[LocalizationEntry]) -> Result<String, LocalizationError> {
+    let locales: BTreeSet<&str> = entries
+        .iter()
+        .flat_map(|entry| entry.values.keys().map(String::as_str))
+        .collect();
+
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(FIXED_COLUMNS.iter().copied().chain(locales.iter().copied()))?;
+
+    for entry in entries {
+        let fixed = [
+            entry.key.as_str(),
+            entry.context.as_str(),
+            entry.example.as_str(),
+            entry.source.as_str(),
+        ];
+        let values = locales
+            .iter()
+            .map(|locale| entry.values.get(*locale).map_or("", String::as_str));
+
+        writer.write_record(fixed.iter().copied().chain(values))?;
+    }
+
+    let contents = writer
+        .into_inner()
+        .map_err(|err| LocalizationError::Io(err.into_error()))?;
+
+    Ok(String::from_utf8(contents).expect("CSV written from strings should be UTF-8"))
+}
+
+/// Reads localization entries from CSV in the layout written by
+/// [`localization_to_csv`]. Column names are matched without regard to case,
+/// and missing fixed columns are treated as empty.
+pub fn localization_from_csv(contents: &str) -> Result<Vec<LocalizationEntry>, LocalizationError> {
+    let mut reader = csv::Reader::from_reader(contents.as_bytes());
+    let headers = reader.headers()?.clone();
+
+    let mut entries = Vec::new();
+
+    for record in reader.records() {
+        let record = record?;
+        let mut entry = LocalizationEntry::default();
+
+        for (header, value) in headers.iter().zip(record.iter()) {
+            match header.to_ascii_lowercase().as_str() {
+                "key" => entry.key = value.to_owned(),
+                "context" => entry.context = value.to_owned(),
+                "example" => entry.example = value.to_owned(),
+                "source" => entry.source = value.to_owned(),
+                _ if value.is_empty() => {}
+                _ => {
+                    entry.values.insert(header.to_owned(), value.to_owned());
+                }
+            }
+        }
+
+        entries.push(entry);
+    }
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::{InstanceBuilder, WeakDom};
+
+    #[test]
+    fn contents_and_csv_round_trip() {
+        let mut dom = WeakDom::new(InstanceBuilder::new("LocalizationTable").with_property(
+            "Contents",
+            r#"[{"key":"Greeting","context":"","examples":"","source":"Hello, world","values":{"es":"Hola, mundo"}},{"key":"","context":"Shop","examples":"","source":"Buy","values":{"fr":"Acheter"}}]"#,
+        ));
+
+        let entries = read_localization_table(dom.root()).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].values["es"], "Hola, mundo");
+
+        let csv = localization_to_csv(&entries).unwrap();
+        assert_eq!(
+            csv,
+            "Key,Context,Example,Source,es,fr\n\
+             Greeting,,,\"Hello, world\",\"Hola, mundo\",\n\
+             ,Shop,,Buy,,Acheter\n"
+        );
+
+        let from_csv = localization_from_csv(&csv).unwrap();
+        assert_eq!(from_csv, entries);
+
+        write_localization_table(dom.root_mut(), &from_csv).unwrap();
+        assert_eq!(read_localization_table(dom.root()).unwrap(), entries);
+    }
+}
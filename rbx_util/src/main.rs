@@ -4,6 +4,7 @@ use std::process;
 
 use anyhow::{anyhow, bail, Context};
 use fs_err::File;
+use rbx_util::Format;
 use structopt::StructOpt;
 
 #[derive(Debug, StructOpt)]
@@ -19,82 +20,62 @@ enum Subcommand {
 
     /// View a binary file as an undefined text representation.
     ViewBinary { input: PathBuf },
-}
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum ModelKind {
-    Binary,
-    Xml,
+    /// Print the instances in a model or place file as an outline.
+    Tree {
+        input: PathBuf,
+
+        /// How many levels of descendants to print.
+        #[structopt(long)]
+        depth: Option<usize>,
+
+        /// A property to print next to each instance. Can be given more than
+        /// once.
+        #[structopt(long = "property", short = "p")]
+        properties: Vec<String>,
+    },
 }
 
-impl ModelKind {
-    fn from_path(path: &Path) -> anyhow::Result<ModelKind> {
-        match path.extension().and_then(|ext| ext.to_str()) {
-            Some("rbxm") | Some("rbxl") => Ok(ModelKind::Binary),
-            Some("rbxmx") | Some("rbxlx") => Ok(ModelKind::Xml),
-
-            _ => Err(anyhow!(
-                "not a Roblox model or place file: {}",
-                path.display()
-            )),
-        }
-    }
+fn format_from_path(path: &Path) -> anyhow::Result<Format> {
+    Format::from_path(path)
+        .ok_or_else(|| anyhow!("not a Roblox model or place file: {}", path.display()))
 }
 
 fn run(options: Options) -> anyhow::Result<()> {
     match options.subcommand {
         Subcommand::Convert { input, output } => convert(&input, &output)?,
         Subcommand::ViewBinary { input } => view_binary(&input)?,
+        Subcommand::Tree {
+            input,
+            depth,
+            properties,
+        } => tree(&input, depth, properties)?,
     }
 
     Ok(())
 }
 
 fn convert(input_path: &Path, output_path: &Path) -> anyhow::Result<()> {
-    let input_kind = ModelKind::from_path(input_path)?;
-    let output_kind = ModelKind::from_path(output_path)?;
+    let output_kind = format_from_path(output_path)?;
 
     let input_file = BufReader::new(File::open(input_path)?);
-
-    let dom = match input_kind {
-        ModelKind::Xml => {
-            let options = rbx_xml::DecodeOptions::new()
-                .property_behavior(rbx_xml::DecodePropertyBehavior::ReadUnknown);
-
-            rbx_xml::from_reader(input_file, options)
-                .with_context(|| format!("Failed to read {}", input_path.display()))?
-        }
-
-        ModelKind::Binary => rbx_binary::from_reader(input_file)
-            .with_context(|| format!("Failed to read {}", input_path.display()))?,
-    };
-
-    let root_ids = dom.root().children();
-
     let output_file = BufWriter::new(File::create(output_path)?);
 
-    match output_kind {
-        ModelKind::Xml => {
-            let options = rbx_xml::EncodeOptions::new()
-                .property_behavior(rbx_xml::EncodePropertyBehavior::WriteUnknown);
-
-            rbx_xml::to_writer(output_file, &dom, root_ids, options)
-                .with_context(|| format!("Failed to write {}", output_path.display()))?;
-        }
-
-        ModelKind::Binary => {
-            rbx_binary::to_writer(output_file, &dom, root_ids)
-                .with_context(|| format!("Failed to write {}", output_path.display()))?;
-        }
-    }
+    rbx_util::convert(input_file, output_file, output_kind).with_context(|| {
+        format!(
+            "Failed to convert {} to {}",
+            input_path.display(),
+            output_path.display()
+        )
+    })?;
 
     Ok(())
 }
 
 fn view_binary(input_path: &Path) -> anyhow::Result<()> {
-    let input_kind = ModelKind::from_path(input_path)?;
+    let input_kind = format_from_path(input_path)?;
 
-    if input_kind != ModelKind::Binary {
+    if input_kind != Format::Binary {
         bail!("not a binary model or place file: {}", input_path.display());
     }
 
@@ -109,6 +90,24 @@ fn view_binary(input_path: &Path) -> anyhow::Result<()> {
     Ok(())
 }
 
+fn tree(input_path: &Path, depth: Option<usize>, properties: Vec<String>) -> anyhow::Result<()> {
+    let input_file = BufReader::new(File::open(input_path)?);
+    let dom = rbx_util::from_reader(input_file)
+        .with_context(|| format!("Failed to read {}", input_path.display()))?;
+
+    let mut printer = rbx_util::TreePrinter::new(&dom);
+    if let Some(depth) = depth {
+        printer = printer.max_depth(depth);
+    }
+    for property in properties {
+        printer = printer.property(property);
+    }
+
+    print!("{}", printer);
+
+    Ok(())
+}
+
 fn main() {
     let options = Options::from_args();
 
@@ -0,0 +1,196 @@
+use std::collections::BTreeSet;
+
+use rbx_binary::{CompressionType, Serializer};
+use rbx_dom_weak::{types::Ref, WeakDom};
+use serde::{Deserialize, Serialize};
+
+use crate::{find_assets, parse_asset_id, EncodeError};
+
+/// A summary of a built place or model, with a content hash and instance
+/// count for each service and the assets it uses.
+///
+/// Content hashes are BLAKE3 hashes of the instances encoded as uncompressed
+/// binary models, so they don't depend on referents and stay the same when a
+/// file is read again. They can change between versions of rbx_binary, so
+/// manifests should be compared with ones made by the same version of
+/// rbx_util.
+///
+/// ```
+/// use rbx_dom_weak::{InstanceBuilder, WeakDom};
+/// use rbx_util::BuildManifest;
+///
+/// let dom = WeakDom::new(
+///     InstanceBuilder::new("DataModel")
+///         .with_child(InstanceBuilder::new("Workspace"))
+///         .with_child(InstanceBuilder::new("ReplicatedStorage")),
+/// );
+///
+/// let built = BuildManifest::from_dom(&dom)?;
+/// assert_eq!(built.services.len(), 2);
+///
+/// let deployed = BuildManifest::from_dom(&dom)?;
+/// assert!(built.mismatches(&deployed).is_empty());
+/// # Ok::<(), rbx_util::EncodeError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildManifest {
+    /// The content hash of every instance in the DOM, as hexadecimal.
+    pub hash: String,
+
+    /// How many instances are in the DOM, not counting its root.
+    pub instance_count: usize,
+
+    /// One entry for each child of the root, which is usually one for each
+    /// service, in order.
+    pub services: Vec<ServiceManifest>,
+
+    /// The IDs of every asset that the DOM refers to, from lowest to highest.
+    pub asset_ids: Vec<u64>,
+}
+
+/// One of the services in a [`BuildManifest`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceManifest {
+    /// The name of the service.
+    pub name: String,
+
+    /// The ClassName of the service.
+    pub class: String,
+
+    /// The content hash of the service and its descendants, as hexadecimal.
+    /// `Ref` properties that point outside of the service don't affect it.
+    pub hash: String,
+
+    /// How many instances are in the service, counting the service itself.
+    pub instance_count: usize,
+}
+
+impl BuildManifest {
+    /// Makes the manifest of a DOM.
+    pub fn from_dom(dom: &WeakDom) -> Result<Self, EncodeError> {
+        let children = dom.root().children();
+
+        let services = children
+            .iter()
+            .map(|&referent| {
+                let instance = dom.get_by_ref(referent).unwrap();
+
+                Ok(ServiceManifest {
+                    name: instance.name.clone(),
+                    class: instance.class.clone(),
+                    hash: content_hash(dom, &[referent])?,
+                    instance_count: dom.descendant_count(referent) + 1,
+                })
+            })
+            .collect::<Result<_, EncodeError>>()?;
+
+        let asset_ids: BTreeSet<u64> = find_assets(dom)
+            .iter()
+            .filter_map(|asset| parse_asset_id(&asset.url))
+            .collect();
+
+        Ok(BuildManifest {
+            hash: content_hash(dom, children)?,
+            instance_count: dom.descendant_count(dom.root_ref()),
+            services,
+            asset_ids: asset_ids.into_iter().collect(),
+        })
+    }
+
+    /// Compares this manifest with another one, returning a description of
+    /// every difference between them. Returns an empty list if they match.
+    pub fn mismatches(&self, other: &BuildManifest) -> Vec<String> {
+        let mut mismatches = Vec::new();
+
+        for service in &self.services {
+            match other
+                .services
+                .iter()
+                .find(|other| other.name == service.name)
+            {
+                None => mismatches.push(format!("{} is missing", service.name)),
+                Some(other) if other.hash != service.hash => {
+                    mismatches.push(format!("{} has different contents", service.name))
+                }
+                Some(_) => {}
+            }
+        }
+
+        for service in &other.services {
+            if !self.services.iter().any(|own| own.name == service.name) {
+                mismatches.push(format!("{} was not expected", service.name));
+            }
+        }
+
+        if mismatches.is_empty() && other.hash != self.hash {
+            mismatches.push("the DOM has different contents".to_owned());
+        }
+
+        if other.asset_ids != self.asset_ids {
+            mismatches.push("the DOM refers to different assets".to_owned());
+        }
+
+        mismatches
+    }
+}
+
+fn content_hash(dom: &WeakDom, referents: &[Ref]) -> Result<String, EncodeError> {
+    let encoded = canonical_encoding(dom, referents)?;
+    Ok(blake3::hash(&encoded).to_hex().to_string())
+}
+
+/// Encodes the given instances the same way every time, without compression,
+/// so that the same instances always give the same bytes.
+fn canonical_encoding(dom: &WeakDom, referents: &[Ref]) -> Result<Vec<u8>, EncodeError> {
+    let mut output = Vec::new();
+    Serializer::new()
+        .compression_type(CompressionType::None)
+        .serialize(&mut output, dom, referents)?;
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::InstanceBuilder;
+
+    use crate::{from_slice, to_vec, Format};
+
+    #[test]
+    fn build_manifests() {
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let root_ref = dom.root_ref();
+        let workspace = dom.insert(root_ref, InstanceBuilder::new("Workspace"));
+        let part = dom.insert(workspace, InstanceBuilder::new("Part"));
+        dom.insert(
+            part,
+            InstanceBuilder::new("Decal").with_property("Texture", "rbxassetid://42"),
+        );
+        dom.insert(
+            workspace,
+            InstanceBuilder::new("ObjectValue").with_property("Value", part),
+        );
+        dom.insert(root_ref, InstanceBuilder::new("Lighting"));
+
+        let built = BuildManifest::from_dom(&dom).unwrap();
+        assert_eq!(built.instance_count, 5);
+        assert_eq!(built.services[0].instance_count, 4);
+        assert_eq!(built.asset_ids, [42]);
+
+        // Reading the place back gives new referents, but the same hashes.
+        let encoded = to_vec(&dom, dom.root().children(), Format::Binary).unwrap();
+        let mut deployed = from_slice(&encoded).unwrap();
+        assert_eq!(BuildManifest::from_dom(&deployed).unwrap(), built);
+
+        let lighting = deployed.root().children()[1];
+        deployed.get_by_ref_mut(lighting).unwrap().name = "Tampered".to_owned();
+        assert_eq!(
+            built.mismatches(&BuildManifest::from_dom(&deployed).unwrap()),
+            ["Lighting is missing", "Tampered was not expected"]
+        );
+    }
+}
@@ -0,0 +1,536 @@
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    fmt,
+};
+
+use rbx_dom_weak::{
+    types::{Ref, Variant},
+    InstanceBuilder, WeakDom,
+};
+
+use crate::{
+    delta::{checksum, match_trees},
+    error::MergeError,
+    path::{descendants, full_name},
+    PlaceDelta,
+};
+
+/// One side of a merge. "Ours" is the tree being merged into, and "theirs"
+/// is the tree whose changes are being brought in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeSide {
+    /// The tree being merged into.
+    Ours,
+
+    /// The tree whose changes are being brought in.
+    Theirs,
+}
+
+impl fmt::Display for MergeSide {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MergeSide::Ours => write!(f, "ours"),
+            MergeSide::Theirs => write!(f, "theirs"),
+        }
+    }
+}
+
+/// Changes made by both sides of a merge that can't both be kept.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum MergeConflict {
+    /// Both sides changed the name or a property of the same instance to
+    /// different values. This is the kind of conflict
+    /// [`MergeStrategies::properties`] resolves.
+    Property {
+        /// The full name of the instance in the base tree.
+        path: String,
+
+        /// The name of the property, or `Name` if the instance was renamed.
+        key: String,
+
+        /// Our value, or `None` if we removed the property. `Ref` values
+        /// point into our tree.
+        ours: Option<Variant>,
+
+        /// Their value, or `None` if they removed the property. `Ref` values
+        /// point into their tree.
+        theirs: Option<Variant>,
+    },
+
+    /// One side removed an instance that the other side changed, or added
+    /// instances under. This is the kind of conflict
+    /// [`MergeStrategies::structure`] resolves; keeping the side that removed
+    /// the instance leaves it out.
+    Removed {
+        /// The full name of the instance in the base tree.
+        path: String,
+
+        /// The side that removed the instance.
+        removed_by: MergeSide,
+    },
+}
+
+impl fmt::Display for MergeConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MergeConflict::Property { path, key, .. } => {
+                write!(f, "{}.{} was changed by both sides", path, key)
+            }
+            MergeConflict::Removed { path, removed_by } => {
+                let other = match removed_by {
+                    MergeSide::Ours => MergeSide::Theirs,
+                    MergeSide::Theirs => MergeSide::Ours,
+                };
+                write!(
+                    f,
+                    "{} was removed by {} and changed by {}",
+                    path, removed_by, other
+                )
+            }
+        }
+    }
+}
+
+/// How [`merge`] resolves one kind of [`MergeConflict`].
+#[derive(Debug, Clone, Copy, Default)]
+pub enum MergeStrategy {
+    /// Stop the merge with [`MergeError::Conflict`].
+    #[default]
+    Fail,
+
+    /// Keep our side of the conflict.
+    PreferOurs,
+
+    /// Keep their side of the conflict.
+    PreferTheirs,
+
+    /// Ask a function which side to keep. Returning `None` stops the merge
+    /// like [`MergeStrategy::Fail`].
+    Manual(fn(&MergeConflict) -> Option<MergeSide>),
+}
+
+impl MergeStrategy {
+    fn resolve(self, conflict: MergeConflict) -> Result<MergeSide, MergeError> {
+        let side = match self {
+            MergeStrategy::Fail => None,
+            MergeStrategy::PreferOurs => Some(MergeSide::Ours),
+            MergeStrategy::PreferTheirs => Some(MergeSide::Theirs),
+            MergeStrategy::Manual(choose) => choose(&conflict),
+        };
+
+        side.ok_or_else(|| MergeError::Conflict(Box::new(conflict)))
+    }
+}
+
+/// How [`merge`] resolves each kind of [`MergeConflict`]. By default every
+/// conflict stops the merge.
+///
+/// Pipelines that merge automatically usually resolve property conflicts
+/// with a preferred side, and leave structural ones to a person.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MergeStrategies {
+    /// Resolves [`MergeConflict::Property`].
+    pub properties: MergeStrategy,
+
+    /// Resolves [`MergeConflict::Removed`].
+    pub structure: MergeStrategy,
+}
+
+/// Brings the changes from `base` to `theirs` into `ours`, which was also
+/// changed from `base`, and returns the merged tree.
+///
+/// Instances are matched up between the trees like they are for a
+/// [`PlaceDelta`]. Names and properties that only one side changed are taken
+/// from that side, instances that only one side removed are removed, and
+/// instances that they added go after the existing children of their parent.
+/// Changes that both sides made differently are resolved with `strategies`.
+///
+/// ```
+/// use rbx_dom_weak::{InstanceBuilder, WeakDom};
+/// use rbx_util::{MergeStrategies, MergeStrategy};
+///
+/// let part = |tag: i32| {
+///     WeakDom::new(InstanceBuilder::new("DataModel").with_child(
+///         InstanceBuilder::new("Part").with_property("Tag", tag),
+///     ))
+/// };
+///
+/// let strategies = MergeStrategies {
+///     properties: MergeStrategy::PreferTheirs,
+///     ..MergeStrategies::default()
+/// };
+///
+/// let merged = rbx_util::merge(&part(1), &part(2), &part(3), &strategies)?;
+/// let merged_part = merged.get_by_ref(merged.root().children()[0]).unwrap();
+/// assert_eq!(merged_part.properties.get("Tag"), Some(&3.into()));
+/// # Ok::<(), rbx_util::MergeError>(())
+/// ```
+pub fn merge(
+    base: &WeakDom,
+    ours: &WeakDom,
+    theirs: &WeakDom,
+    strategies: &MergeStrategies,
+) -> Result<WeakDom, MergeError> {
+    let to_ours = match_trees(base, ours, || Ok(()))?;
+    let to_theirs = match_trees(base, theirs, || Ok(()))?;
+
+    // Our instances keep their referents in the merged tree, so `placed`
+    // goes from their instances to merged ones.
+    let mut merged = ours.clone();
+    let mut placed: HashMap<Ref, Ref> = to_theirs
+        .iter()
+        .filter_map(|(base_ref, theirs_ref)| Some((*theirs_ref, *to_ours.get(base_ref)?)))
+        .collect();
+
+    // Their instances that were removed by us and are put back.
+    let mut restored = HashSet::new();
+
+    // Instances that only one side removed. Descendants are handled along
+    // with the top instance that was removed.
+    let base_order = descendants(base, base.root_ref());
+    for &base_ref in &base_order {
+        let parent = base.get_by_ref(base_ref).unwrap().parent();
+
+        match (to_ours.get(&base_ref), to_theirs.get(&base_ref)) {
+            (Some(&ours_ref), None) if to_theirs.contains_key(&parent) => {
+                if !same_subtree(base, base_ref, ours, ours_ref) {
+                    let conflict = MergeConflict::Removed {
+                        path: full_name(base, base_ref),
+                        removed_by: MergeSide::Theirs,
+                    };
+                    if strategies.structure.resolve(conflict)? == MergeSide::Ours {
+                        continue;
+                    }
+                }
+
+                merged.destroy(ours_ref);
+            }
+            // Our removal stands unless they changed the instance since.
+            (None, Some(&theirs_ref))
+                if to_ours.contains_key(&parent)
+                    && !same_subtree(base, base_ref, theirs, theirs_ref) =>
+            {
+                let conflict = MergeConflict::Removed {
+                    path: full_name(base, base_ref),
+                    removed_by: MergeSide::Ours,
+                };
+                if strategies.structure.resolve(conflict)? == MergeSide::Theirs {
+                    restored.extend(descendants(theirs, theirs_ref));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // `Ref` properties to set once every instance they could point to is in
+    // the merged tree, with the instance of their tree they point to.
+    let mut refs = Vec::new();
+
+    // Instances that they added, and ones of theirs that are put back. Their
+    // parents come first, so they're placed by the time their children are.
+    let matched_theirs: HashSet<Ref> = to_theirs.values().copied().collect();
+    for theirs_ref in descendants(theirs, theirs.root_ref()) {
+        if placed.contains_key(&theirs_ref)
+            || (matched_theirs.contains(&theirs_ref) && !restored.contains(&theirs_ref))
+        {
+            continue;
+        }
+
+        let instance = theirs.get_by_ref(theirs_ref).unwrap();
+        let parent = match placed.get(&instance.parent()) {
+            Some(&parent) if merged.get_by_ref(parent).is_some() => parent,
+            _ => continue,
+        };
+
+        let mut builder = InstanceBuilder::new(&instance.class).with_name(&instance.name);
+        for (key, value) in instance.properties.iter() {
+            match value {
+                Variant::Ref(target) => refs.push((builder.referent(), key.clone(), *target)),
+                value => builder = builder.with_property(key.clone(), value.clone()),
+            }
+        }
+
+        let referent = merged.insert(parent, builder);
+        placed.insert(theirs_ref, referent);
+    }
+
+    // Names and properties of the instances that are on every side.
+    for &base_ref in &base_order {
+        let (ours_ref, theirs_ref) = match (to_ours.get(&base_ref), to_theirs.get(&base_ref)) {
+            (Some(&ours_ref), Some(&theirs_ref)) if merged.get_by_ref(ours_ref).is_some() => {
+                (ours_ref, theirs_ref)
+            }
+            _ => continue,
+        };
+
+        let base_instance = base.get_by_ref(base_ref).unwrap();
+        let ours_instance = ours.get_by_ref(ours_ref).unwrap();
+        let theirs_instance = theirs.get_by_ref(theirs_ref).unwrap();
+
+        if theirs_instance.name != base_instance.name && ours_instance.name != theirs_instance.name
+        {
+            let take = ours_instance.name == base_instance.name
+                || strategies.properties.resolve(MergeConflict::Property {
+                    path: full_name(base, base_ref),
+                    key: "Name".to_owned(),
+                    ours: Some(ours_instance.name.clone().into()),
+                    theirs: Some(theirs_instance.name.clone().into()),
+                })? == MergeSide::Theirs;
+
+            if take {
+                merged.edit(ours_ref).set_name(&theirs_instance.name);
+            }
+        }
+
+        let keys: BTreeSet<&String> = base_instance
+            .properties
+            .keys()
+            .chain(theirs_instance.properties.keys())
+            .collect();
+
+        for key in keys {
+            let base_value = base_instance.properties.get(key);
+            let ours_value = ours_instance.properties.get(key);
+            let theirs_value = theirs_instance.properties.get(key);
+
+            if same_value(base_value, theirs_value, &to_theirs)
+                || same_value(theirs_value, ours_value, &placed)
+            {
+                continue;
+            }
+
+            if !same_value(base_value, ours_value, &to_ours) {
+                let conflict = MergeConflict::Property {
+                    path: full_name(base, base_ref),
+                    key: key.clone(),
+                    ours: ours_value.cloned(),
+                    theirs: theirs_value.cloned(),
+                };
+                if strategies.properties.resolve(conflict)? == MergeSide::Ours {
+                    continue;
+                }
+            }
+
+            match theirs_value {
+                Some(Variant::Ref(target)) => refs.push((ours_ref, key.clone(), *target)),
+                Some(value) => {
+                    merged
+                        .edit(ours_ref)
+                        .set_property(key.clone(), value.clone());
+                }
+                None => {
+                    merged.edit(ours_ref).remove_property(key);
+                }
+            }
+        }
+    }
+
+    // `Ref` properties pointing at instances that didn't make it into the
+    // merged tree are left empty.
+    for (referent, key, target) in refs {
+        let target = placed
+            .get(&target)
+            .copied()
+            .filter(|&target| merged.get_by_ref(target).is_some())
+            .unwrap_or_else(Ref::none);
+
+        merged.edit(referent).set_property(key, target);
+    }
+
+    Ok(merged)
+}
+
+impl PlaceDelta {
+    /// Applies the delta to `ours`, a tree that was changed from `base`, the
+    /// tree the delta was computed from, by merging the changes that the delta
+    /// makes to `base` into it with [`merge`].
+    ///
+    /// Returns [`DeltaError::WrongBase`][crate::DeltaError::WrongBase] if
+    /// `base` is not the tree the delta was computed from.
+    pub fn merge(
+        &self,
+        base: &WeakDom,
+        ours: &WeakDom,
+        strategies: &MergeStrategies,
+    ) -> Result<WeakDom, MergeError> {
+        let theirs = self.apply(base)?;
+        merge(base, ours, &theirs, strategies)
+    }
+}
+
+/// Returns whether an instance and its descendants are the same in two trees.
+fn same_subtree(old: &WeakDom, old_ref: Ref, new: &WeakDom, new_ref: Ref) -> bool {
+    checksum(old, &descendants(old, old_ref)) == checksum(new, &descendants(new, new_ref))
+}
+
+/// Returns whether two values of a property are the same, where `Ref` values
+/// in the first tree point at the instances `matches` gives in the second.
+fn same_value(a: Option<&Variant>, b: Option<&Variant>, matches: &HashMap<Ref, Ref>) -> bool {
+    match (a, b) {
+        (Some(Variant::Ref(a)), Some(Variant::Ref(b))) if a.is_some() => matches.get(a) == Some(b),
+        (a, b) => a == b,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn tree(parts: &[(&str, i32)]) -> WeakDom {
+        let mut folder = InstanceBuilder::new("Folder").with_name("Parts");
+        for (name, tag) in parts {
+            folder = folder.with_child(
+                InstanceBuilder::new("Part")
+                    .with_name(*name)
+                    .with_property("Tag", *tag),
+            );
+        }
+
+        WeakDom::new(InstanceBuilder::new("DataModel").with_child(folder))
+    }
+
+    fn parts(dom: &WeakDom) -> Vec<(String, Option<Variant>)> {
+        let folder = dom.get_by_ref(dom.root().children()[0]).unwrap();
+        folder
+            .children()
+            .iter()
+            .map(|&child| {
+                let part = dom.get_by_ref(child).unwrap();
+                (part.name.clone(), part.properties.get("Tag").cloned())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn separate_changes() {
+        let base = tree(&[("A", 1), ("B", 2), ("C", 3)]);
+        let ours = tree(&[("A", 10), ("B", 2), ("C", 3)]);
+        let theirs = tree(&[("A", 1), ("C", 30), ("D", 4)]);
+
+        let merged = merge(&base, &ours, &theirs, &MergeStrategies::default()).unwrap();
+        assert_eq!(
+            parts(&merged),
+            [
+                ("A".to_owned(), Some(10.into())),
+                ("C".to_owned(), Some(30.into())),
+                ("D".to_owned(), Some(4.into())),
+            ]
+        );
+    }
+
+    #[test]
+    fn property_conflicts() {
+        let base = tree(&[("A", 1)]);
+        let ours = tree(&[("A", 2)]);
+        let theirs = tree(&[("A", 3)]);
+
+        let err = merge(&base, &ours, &theirs, &MergeStrategies::default()).unwrap_err();
+        assert!(matches!(
+            err,
+            MergeError::Conflict(conflict) if *conflict == MergeConflict::Property {
+                path: "Parts.A".to_owned(),
+                key: "Tag".to_owned(),
+                ours: Some(2.into()),
+                theirs: Some(3.into()),
+            }
+        ));
+
+        for (strategy, tag) in [
+            (MergeStrategy::PreferOurs, 2),
+            (MergeStrategy::PreferTheirs, 3),
+            (MergeStrategy::Manual(|_| Some(MergeSide::Theirs)), 3),
+        ] {
+            let strategies = MergeStrategies {
+                properties: strategy,
+                ..MergeStrategies::default()
+            };
+            let merged = merge(&base, &ours, &theirs, &strategies).unwrap();
+            assert_eq!(parts(&merged), [("A".to_owned(), Some(tag.into()))]);
+        }
+
+        // Property strategies don't resolve structural conflicts.
+        let theirs = tree(&[]);
+        let strategies = MergeStrategies {
+            properties: MergeStrategy::PreferTheirs,
+            ..MergeStrategies::default()
+        };
+        assert!(matches!(
+            merge(&base, &ours, &theirs, &strategies),
+            Err(MergeError::Conflict(conflict)) if matches!(
+                *conflict,
+                MergeConflict::Removed { removed_by: MergeSide::Theirs, .. }
+            )
+        ));
+    }
+
+    #[test]
+    fn removed_conflicts() {
+        let base = tree(&[("A", 1)]);
+        let ours = tree(&[]);
+        let theirs = tree(&[("A", 2)]);
+
+        let keep = |structure| MergeStrategies {
+            structure,
+            ..MergeStrategies::default()
+        };
+
+        let merged = merge(&base, &ours, &theirs, &keep(MergeStrategy::PreferOurs)).unwrap();
+        assert!(parts(&merged).is_empty());
+
+        let merged = merge(&base, &ours, &theirs, &keep(MergeStrategy::PreferTheirs)).unwrap();
+        assert_eq!(parts(&merged), [("A".to_owned(), Some(2.into()))]);
+
+        // Removing an instance that the other side didn't change isn't a
+        // conflict.
+        let merged = merge(&base, &ours, &base, &MergeStrategies::default()).unwrap();
+        assert!(parts(&merged).is_empty());
+    }
+
+    #[test]
+    fn refs() {
+        let build = |tags: &[(&str, i32)], link: &str| {
+            let mut dom = tree(tags);
+            let folder = dom.root().children()[0];
+            let children = dom.get_by_ref(folder).unwrap().children().to_vec();
+            let target = children
+                .iter()
+                .copied()
+                .find(|&child| dom.get_by_ref(child).unwrap().name == link)
+                .unwrap();
+            dom.edit(folder).set_property("Link", target);
+            dom
+        };
+
+        let base = build(&[("A", 1)], "A");
+        let ours = build(&[("A", 2)], "A");
+        let theirs = build(&[("A", 1), ("B", 3)], "B");
+
+        let merged = merge(&base, &ours, &theirs, &MergeStrategies::default()).unwrap();
+        let folder = merged.get_by_ref(merged.root().children()[0]).unwrap();
+        let b = folder.children()[1];
+        assert_eq!(merged.get_by_ref(b).unwrap().name, "B");
+        assert_eq!(folder.properties.get("Link"), Some(&b.into()));
+    }
+
+    #[test]
+    fn delta_merge() {
+        let base = tree(&[("A", 1), ("B", 2)]);
+        let ours = tree(&[("A", 1), ("B", 20)]);
+        let theirs = tree(&[("A", 10), ("B", 2)]);
+
+        let delta = PlaceDelta::between(&base, &theirs).unwrap();
+        assert!(delta.apply(&ours).is_err());
+
+        let merged = delta
+            .merge(&base, &ours, &MergeStrategies::default())
+            .unwrap();
+        assert_eq!(
+            parts(&merged),
+            [
+                ("A".to_owned(), Some(10.into())),
+                ("B".to_owned(), Some(20.into())),
+            ]
+        );
+    }
+}
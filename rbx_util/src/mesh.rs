@@ -0,0 +1,435 @@
+//! Decoding of the `.mesh` files that MeshParts and SpecialMeshes refer to.
+
+use std::convert::TryInto;
+
+use rbx_dom_weak::types::Vector3;
+
+use crate::error::MeshError;
+
+/// The geometry of a Roblox mesh file: its vertex positions and the triangles
+/// between them. Normals, texture coordinates, and skinning data are skipped.
+///
+/// Versions 1 through 5 of the format can be decoded. Only the most detailed
+/// level of detail is kept from meshes that have more than one.
+///
+/// ```
+/// let contents = b"version 1.01\n1\n\
+///     [0,0,0][0,0,1][0,0,0][2,0,0][0,0,1][1,0,0][0,4,0][0,0,1][0,1,0]";
+///
+/// let mesh = rbx_util::Mesh::from_slice(contents)?;
+/// assert_eq!(mesh.faces, [[0, 1, 2]]);
+/// assert_eq!(mesh.size().unwrap().y, 4.0);
+/// # Ok::<(), rbx_util::MeshError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mesh {
+    /// The position of each vertex, in the mesh's own units.
+    pub vertices: Vec<Vector3>,
+
+    /// The triangles of the mesh, as indices into `vertices`.
+    pub faces: Vec<[u32; 3]>,
+}
+
+impl Mesh {
+    /// Decodes a mesh file.
+    pub fn from_slice(contents: &[u8]) -> Result<Self, MeshError> {
+        let header_end = contents
+            .iter()
+            .position(|&byte| byte == b'\n')
+            .ok_or(MeshError::NotAMesh)?;
+        let version = std::str::from_utf8(&contents[..header_end])
+            .ok()
+            .and_then(|header| header.trim_end().strip_prefix("version "))
+            .ok_or(MeshError::NotAMesh)?;
+        let body = &contents[header_end + 1..];
+
+        let mesh = match version {
+            "1.00" => decode_text(body, 0.5)?,
+            "1.01" => decode_text(body, 1.0)?,
+            "2.00" => decode_v2(body)?,
+            "3.00" | "3.01" => decode_v3(body)?,
+            "4.00" | "4.01" | "5.00" => decode_v4(body, version == "5.00")?,
+            _ => return Err(MeshError::UnsupportedVersion(version.to_owned())),
+        };
+
+        let vertex_count = mesh.vertices.len();
+        if let Some(&index) = mesh
+            .faces
+            .iter()
+            .flatten()
+            .find(|&&index| index as usize >= vertex_count)
+        {
+            return Err(MeshError::InvalidFace(index));
+        }
+
+        Ok(mesh)
+    }
+
+    /// Returns the smallest and largest corners of the box around every
+    /// vertex, or `None` if the mesh has no vertices.
+    pub fn bounding_box(&self) -> Option<(Vector3, Vector3)> {
+        let first = *self.vertices.first()?;
+
+        Some(self.vertices.iter().fold((first, first), |(min, max), v| {
+            (
+                Vector3::new(min.x.min(v.x), min.y.min(v.y), min.z.min(v.z)),
+                Vector3::new(max.x.max(v.x), max.y.max(v.y), max.z.max(v.z)),
+            )
+        }))
+    }
+
+    /// Returns the size of the box around every vertex, which is what Roblox
+    /// stores in `MeshPart.MeshSize`.
+    pub fn size(&self) -> Option<Vector3> {
+        let (min, max) = self.bounding_box()?;
+        Some(Vector3::new(max.x - min.x, max.y - min.y, max.z - min.z))
+    }
+
+    /// Returns the vertices as they appear in a MeshPart of the given `Size`:
+    /// Roblox centers a mesh on its part and stretches it to fill the part.
+    pub fn fit_to_size(&self, size: Vector3) -> Vec<Vector3> {
+        let (min, max) = match self.bounding_box() {
+            Some(bounds) => bounds,
+            None => return Vec::new(),
+        };
+
+        // Flat meshes have no extent to stretch along some axis.
+        let scale = |size: f32, min: f32, max: f32| {
+            if max > min {
+                size / (max - min)
+            } else {
+                0.0
+            }
+        };
+        let scale = Vector3::new(
+            scale(size.x, min.x, max.x),
+            scale(size.y, min.y, max.y),
+            scale(size.z, min.z, max.z),
+        );
+        let center = Vector3::new(
+            (min.x + max.x) / 2.0,
+            (min.y + max.y) / 2.0,
+            (min.z + max.z) / 2.0,
+        );
+
+        self.vertices
+            .iter()
+            .map(|v| {
+                Vector3::new(
+                    (v.x - center.x) * scale.x,
+                    (v.y - center.y) * scale.y,
+                    (v.z - center.z) * scale.z,
+                )
+            })
+            .collect()
+    }
+}
+
+/// Decodes version 1, where each face is three vertices written out as
+/// `[x,y,z][nx,ny,nz][u,v,w]`. Version 1.00 meshes are twice the size they
+/// should be, which `scale` undoes.
+fn decode_text(body: &[u8], scale: f32) -> Result<Mesh, MeshError> {
+    let body = std::str::from_utf8(body).map_err(|_| MeshError::InvalidText)?;
+    let mut lines = body.lines();
+
+    let face_count: usize = lines
+        .next()
+        .and_then(|line| line.trim().parse().ok())
+        .ok_or(MeshError::InvalidText)?;
+
+    let vectors: Vec<&str> = lines
+        .flat_map(|line| line.split(['[', ']']))
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .collect();
+
+    let mut vertices = Vec::with_capacity(face_count * 3);
+
+    // Each vertex is a position, a normal, and a texture coordinate.
+    for position in vectors.chunks(3).take(face_count * 3) {
+        let components: Vec<f32> = position[0]
+            .split(',')
+            .map(|component| component.trim().parse())
+            .collect::<Result<_, _>>()
+            .map_err(|_| MeshError::InvalidText)?;
+
+        match components[..] {
+            [x, y, z] => vertices.push(Vector3::new(x * scale, y * scale, z * scale)),
+            _ => return Err(MeshError::InvalidText),
+        }
+    }
+
+    if vertices.len() != face_count * 3 {
+        return Err(MeshError::Truncated);
+    }
+
+    let faces = (0..face_count as u32)
+        .map(|face| [face * 3, face * 3 + 1, face * 3 + 2])
+        .collect();
+
+    Ok(Mesh { vertices, faces })
+}
+
+/// Decodes version 2, which is a small header followed by vertices and faces.
+fn decode_v2(body: &[u8]) -> Result<Mesh, MeshError> {
+    let mut reader = Reader::new(body);
+    let header_size = reader.u16()? as usize;
+    let vertex_size = reader.u8()? as usize;
+    let face_size = reader.u8()? as usize;
+    let vertex_count = reader.u32()? as usize;
+    let face_count = reader.u32()? as usize;
+    reader.seek(header_size)?;
+
+    let vertices = reader.vertices(vertex_count, vertex_size)?;
+    let faces = reader.faces(face_count, face_size)?;
+
+    Ok(Mesh { vertices, faces })
+}
+
+/// Decodes version 3, which adds levels of detail to version 2.
+fn decode_v3(body: &[u8]) -> Result<Mesh, MeshError> {
+    let mut reader = Reader::new(body);
+    let header_size = reader.u16()? as usize;
+    let vertex_size = reader.u8()? as usize;
+    let face_size = reader.u8()? as usize;
+    let lod_size = reader.u16()? as usize;
+    let lod_count = reader.u16()? as usize;
+    let vertex_count = reader.u32()? as usize;
+    let face_count = reader.u32()? as usize;
+    reader.seek(header_size)?;
+
+    let vertices = reader.vertices(vertex_count, vertex_size)?;
+    let faces = reader.faces(face_count, face_size)?;
+    let lods = reader.lods(lod_count, lod_size)?;
+
+    Ok(Mesh {
+        vertices,
+        faces: first_lod(faces, &lods),
+    })
+}
+
+/// Decodes versions 4 and 5, which add skinning to version 3, and in version
+/// 5, facial animation data.
+fn decode_v4(body: &[u8], is_v5: bool) -> Result<Mesh, MeshError> {
+    const VERTEX_SIZE: usize = 40;
+    const ENVELOPE_SIZE: usize = 8;
+    const FACE_SIZE: usize = 12;
+    const LOD_SIZE: usize = 4;
+
+    let mut reader = Reader::new(body);
+    let header_size = reader.u16()? as usize;
+    let _lod_type = reader.u16()?;
+    let vertex_count = reader.u32()? as usize;
+    let face_count = reader.u32()? as usize;
+    let lod_count = reader.u16()? as usize;
+    let bone_count = reader.u16()? as usize;
+
+    let expected_header_size = if is_v5 { 32 } else { 24 };
+    if header_size < expected_header_size {
+        return Err(MeshError::Truncated);
+    }
+    reader.seek(header_size)?;
+
+    let vertices = reader.vertices(vertex_count, VERTEX_SIZE)?;
+    if bone_count > 0 {
+        reader.skip(vertex_count * ENVELOPE_SIZE)?;
+    }
+    let faces = reader.faces(face_count, FACE_SIZE)?;
+    let lods = reader.lods(lod_count, LOD_SIZE)?;
+
+    Ok(Mesh {
+        vertices,
+        faces: first_lod(faces, &lods),
+    })
+}
+
+/// Keeps only the faces of the most detailed level of detail. `lods` holds
+/// the index of the first face of each level, followed by the face count.
+fn first_lod(mut faces: Vec<[u32; 3]>, lods: &[u32]) -> Vec<[u32; 3]> {
+    if let [start, end, ..] = *lods {
+        let (start, end) = (start as usize, end as usize);
+
+        if start <= end && end <= faces.len() {
+            faces.truncate(end);
+            faces.drain(..start);
+        }
+    }
+
+    faces
+}
+
+struct Reader<'a> {
+    contents: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(contents: &'a [u8]) -> Self {
+        Reader {
+            contents,
+            position: 0,
+        }
+    }
+
+    fn seek(&mut self, position: usize) -> Result<(), MeshError> {
+        if position > self.contents.len() {
+            return Err(MeshError::Truncated);
+        }
+
+        self.position = position;
+        Ok(())
+    }
+
+    fn skip(&mut self, length: usize) -> Result<(), MeshError> {
+        self.bytes(length).map(|_| ())
+    }
+
+    fn bytes(&mut self, length: usize) -> Result<&'a [u8], MeshError> {
+        let end = self
+            .position
+            .checked_add(length)
+            .filter(|&end| end <= self.contents.len())
+            .ok_or(MeshError::Truncated)?;
+
+        let bytes = &self.contents[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, MeshError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, MeshError> {
+        Ok(u16::from_le_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, MeshError> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    /// Reads the positions of `count` vertices which are `size` bytes each.
+    /// Positions are always the first 12 bytes of a vertex.
+    fn vertices(&mut self, count: usize, size: usize) -> Result<Vec<Vector3>, MeshError> {
+        if size < 12 {
+            return Err(MeshError::Truncated);
+        }
+
+        let bytes = self.bytes(count.checked_mul(size).ok_or(MeshError::Truncated)?)?;
+
+        Ok(bytes
+            .chunks_exact(size)
+            .map(|vertex| {
+                let component =
+                    |i: usize| f32::from_le_bytes(vertex[i * 4..i * 4 + 4].try_into().unwrap());
+                Vector3::new(component(0), component(1), component(2))
+            })
+            .collect())
+    }
+
+    fn faces(&mut self, count: usize, size: usize) -> Result<Vec<[u32; 3]>, MeshError> {
+        if size < 12 {
+            return Err(MeshError::Truncated);
+        }
+
+        let bytes = self.bytes(count.checked_mul(size).ok_or(MeshError::Truncated)?)?;
+
+        Ok(bytes
+            .chunks_exact(size)
+            .map(|face| {
+                let index =
+                    |i: usize| u32::from_le_bytes(face[i * 4..i * 4 + 4].try_into().unwrap());
+                [index(0), index(1), index(2)]
+            })
+            .collect())
+    }
+
+    fn lods(&mut self, count: usize, size: usize) -> Result<Vec<u32>, MeshError> {
+        if size < 4 {
+            return Err(MeshError::Truncated);
+        }
+
+        let bytes = self.bytes(count.checked_mul(size).ok_or(MeshError::Truncated)?)?;
+
+        Ok(bytes
+            .chunks_exact(size)
+            .map(|lod| u32::from_le_bytes(lod[..4].try_into().unwrap()))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Writes a version 3 mesh with two levels of detail: the first is two
+    /// triangles making a square, and the second is the first triangle.
+    fn square_v3() -> Vec<u8> {
+        let mut contents = b"version 3.00\n".to_vec();
+        contents.extend_from_slice(&16u16.to_le_bytes());
+        contents.push(40);
+        contents.push(12);
+        contents.extend_from_slice(&4u16.to_le_bytes());
+        contents.extend_from_slice(&3u16.to_le_bytes());
+        contents.extend_from_slice(&4u32.to_le_bytes());
+        contents.extend_from_slice(&3u32.to_le_bytes());
+
+        for &(x, y) in &[(0.0f32, 0.0f32), (2.0, 0.0), (2.0, 2.0), (0.0, 2.0)] {
+            for component in &[x, y, 1.0] {
+                contents.extend_from_slice(&component.to_le_bytes());
+            }
+            contents.extend_from_slice(&[0; 28]);
+        }
+
+        for face in &[[0u32, 1, 2], [0, 2, 3], [0, 1, 2]] {
+            for index in face {
+                contents.extend_from_slice(&index.to_le_bytes());
+            }
+        }
+
+        for lod in &[0u32, 2, 3] {
+            contents.extend_from_slice(&lod.to_le_bytes());
+        }
+
+        contents
+    }
+
+    #[test]
+    fn decode_v3_first_lod() {
+        let mesh = Mesh::from_slice(&square_v3()).unwrap();
+
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(mesh.faces, [[0, 1, 2], [0, 2, 3]]);
+        assert_eq!(mesh.size(), Some(Vector3::new(2.0, 2.0, 0.0)));
+
+        let fitted = mesh.fit_to_size(Vector3::new(4.0, 1.0, 1.0));
+        assert_eq!(fitted[0], Vector3::new(-2.0, -0.5, 0.0));
+        assert_eq!(fitted[2], Vector3::new(2.0, 0.5, 0.0));
+    }
+
+    #[test]
+    fn decode_v1_scale() {
+        let contents = b"version 1.00\n1\n\
+            [0,0,0][0,0,1][0,0,0][2,0,0][0,0,1][1,0,0][0,4,0][0,0,1][0,1,0]";
+        let mesh = Mesh::from_slice(contents).unwrap();
+
+        assert_eq!(mesh.size(), Some(Vector3::new(1.0, 2.0, 0.0)));
+    }
+
+    #[test]
+    fn errors() {
+        let mut truncated = square_v3();
+        truncated.truncate(60);
+        assert!(matches!(
+            Mesh::from_slice(&truncated),
+            Err(MeshError::Truncated)
+        ));
+
+        assert!(matches!(
+            Mesh::from_slice(b"version 7.00\nCOREMESH"),
+            Err(MeshError::UnsupportedVersion(version)) if version == "7.00"
+        ));
+
+        assert!(matches!(Mesh::from_slice(b"PNG"), Err(MeshError::NotAMesh)));
+    }
+}
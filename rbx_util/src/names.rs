@@ -0,0 +1,188 @@
+use std::collections::{BTreeMap, HashSet};
+
+use rbx_dom_weak::{sanitize_name, types::Ref, WeakDom};
+
+use crate::path::{descendants, full_name};
+
+/// Siblings that share a name, found by [`duplicate_names`].
+///
+/// Paths like `Workspace.Map.Part` can't tell these instances apart, and
+/// `FindFirstChild` may return any of them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateNames {
+    /// The referent of the instances' parent.
+    pub parent: Ref,
+
+    /// The parent's full name, like `Workspace.Map`. Empty for the root.
+    pub parent_path: String,
+
+    /// The name that the instances share.
+    pub name: String,
+
+    /// The referents of every instance with the name, in the order they
+    /// appear in the parent's children.
+    pub referents: Vec<Ref>,
+}
+
+/// Finds every group of siblings in the DOM that share a name. Groups are in
+/// depth-first order of their parents, then sorted by name.
+pub fn duplicate_names(dom: &WeakDom) -> Vec<DuplicateNames> {
+    let mut found = Vec::new();
+
+    for parent in descendants(dom, dom.root_ref()) {
+        let mut by_name: BTreeMap<&str, Vec<Ref>> = BTreeMap::new();
+
+        for &child in dom.get_by_ref(parent).unwrap().children() {
+            let name = dom.get_by_ref(child).unwrap().name.as_str();
+            by_name.entry(name).or_default().push(child);
+        }
+
+        for (name, referents) in by_name {
+            if referents.len() > 1 {
+                found.push(DuplicateNames {
+                    parent,
+                    parent_path: full_name(dom, parent),
+                    name: name.to_owned(),
+                    referents,
+                });
+            }
+        }
+    }
+
+    found
+}
+
+/// Renames siblings that share a name so that every sibling's name is unique.
+/// The first instance with a name keeps it, and later ones get a suffix like
+/// `Part (2)`, skipping suffixes already used by another sibling. Returns the
+/// number of instances that were renamed.
+///
+/// ```
+/// use rbx_dom_weak::{InstanceBuilder, WeakDom};
+///
+/// let mut dom = WeakDom::new(
+///     InstanceBuilder::new("Folder")
+///         .with_child(InstanceBuilder::new("Part"))
+///         .with_child(InstanceBuilder::new("Part")),
+/// );
+///
+/// assert_eq!(rbx_util::rename_duplicates(&mut dom), 1);
+///
+/// let second = dom.root().children()[1];
+/// assert_eq!(dom.get_by_ref(second).unwrap().name, "Part (2)");
+/// ```
+pub fn rename_duplicates(dom: &mut WeakDom) -> usize {
+    let mut renamed = 0;
+
+    for group in duplicate_names(dom) {
+        let mut taken: HashSet<String> = dom
+            .get_by_ref(group.parent)
+            .unwrap()
+            .children()
+            .iter()
+            .map(|&child| dom.get_by_ref(child).unwrap().name.clone())
+            .collect();
+
+        let mut suffix = 2;
+        for &referent in &group.referents[1..] {
+            let new_name = loop {
+                let candidate = format!("{} ({})", group.name, suffix);
+                suffix += 1;
+
+                if !taken.contains(&candidate) {
+                    break candidate;
+                }
+            };
+
+            taken.insert(new_name.clone());
+            dom.get_by_ref_mut(referent).unwrap().name = new_name;
+            renamed += 1;
+        }
+    }
+
+    renamed
+}
+
+/// Names that Windows doesn't allow files to have, with or without an
+/// extension.
+const RESERVED_FILE_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Turns an instance name into a name that can be used for a file or
+/// directory on Windows, macOS, and Linux.
+///
+/// The name is cleaned up with [`sanitize_name`] first. Then characters that
+/// aren't allowed in file names, like slashes and colons, become underscores,
+/// dots and spaces at the end are removed, and names that are reserved on
+/// Windows or that are empty get an underscore added to them. Different
+/// instance names can turn into the same file name, so callers still need to
+/// handle siblings whose file names collide.
+///
+/// ```
+/// use rbx_util::file_name;
+///
+/// assert_eq!(file_name("Part"), "Part");
+/// assert_eq!(file_name("A/B: C?"), "A_B_ C_");
+/// assert_eq!(file_name("con"), "con_");
+/// assert_eq!(file_name("NUL.lua"), "NUL_.lua");
+/// assert_eq!(file_name(".."), "_");
+/// ```
+pub fn file_name(name: &str) -> String {
+    let mut file_name: String = sanitize_name(name)
+        .chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '_',
+            c => c,
+        })
+        .collect();
+
+    file_name.truncate(file_name.trim_end_matches(&['.', ' '][..]).len());
+
+    // Windows reserves these names even when they're followed by an
+    // extension, so the underscore goes before the first dot.
+    let stem_len = file_name.find('.').unwrap_or(file_name.len());
+    if file_name.is_empty()
+        || RESERVED_FILE_NAMES
+            .iter()
+            .any(|reserved| reserved.eq_ignore_ascii_case(&file_name[..stem_len]))
+    {
+        file_name.insert(stem_len, '_');
+    }
+
+    file_name
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::InstanceBuilder;
+
+    #[test]
+    fn finds_and_renames() {
+        let mut dom = WeakDom::new(
+            InstanceBuilder::new("DataModel").with_child(
+                InstanceBuilder::new("Folder")
+                    .with_name("Map")
+                    .with_child(InstanceBuilder::new("Part").with_name("Tree"))
+                    .with_child(InstanceBuilder::new("Part").with_name("Tree (2)"))
+                    .with_child(InstanceBuilder::new("Part").with_name("Tree"))
+                    .with_child(InstanceBuilder::new("Part").with_name("Rock")),
+            ),
+        );
+
+        let found = duplicate_names(&dom);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].parent_path, "Map");
+        assert_eq!(found[0].name, "Tree");
+        assert_eq!(found[0].referents.len(), 2);
+
+        assert_eq!(rename_duplicates(&mut dom), 1);
+        assert!(duplicate_names(&dom).is_empty());
+
+        let renamed = dom.get_by_ref(found[0].referents[1]).unwrap();
+        assert_eq!(renamed.name, "Tree (3)");
+    }
+}
@@ -0,0 +1,186 @@
+use rbx_dom_weak::{types::Ref, WeakDom};
+use rbx_reflection::{PropertyKind, PropertySerialization};
+
+use crate::{
+    class::{class_descriptors, is_service, REQUIRED_PARENTS},
+    explorer::explorer_order,
+    path::descendants,
+};
+
+/// Rewrites a DOM so that Roblox Studio opens it without changing anything:
+///
+/// - If the root is a DataModel, services that are nested inside other
+///   instances are moved to the root, and classes that must live in a
+///   particular service (like Terrain in Workspace) are moved into it.
+/// - Services are ordered the way Studio's Explorer lists them, using
+///   [`explorer_order`][crate::explorer_order], before the root's other
+///   children, which keep their order.
+/// - Properties that Roblox never saves, according to the reflection
+///   database, are removed.
+/// - Serialized properties that are missing are filled in with their default
+///   values.
+///
+/// Instances of classes that aren't in the reflection database are left
+/// alone.
+pub fn normalize_for_studio(dom: &mut WeakDom) {
+    if dom.root().class == "DataModel" {
+        fix_parents(dom);
+        order_services(dom);
+    }
+
+    for referent in descendants(dom, dom.root_ref()) {
+        let instance = dom.get_by_ref_mut(referent).unwrap();
+
+        // Default values are listed on the instance's own class, including
+        // ones for properties that are inherited from superclasses.
+        let defaults = match class_descriptors(&instance.class).next() {
+            Some(descriptor) => &descriptor.default_properties,
+            None => continue,
+        };
+
+        for descriptor in class_descriptors(&instance.class) {
+            for (name, property) in &descriptor.properties {
+                let serialization = match &property.kind {
+                    PropertyKind::Canonical { serialization } => serialization,
+                    _ => continue,
+                };
+
+                match serialization {
+                    PropertySerialization::DoesNotSerialize => {
+                        instance.properties.remove(name.as_ref());
+                    }
+                    PropertySerialization::Serializes
+                        if !instance.properties.contains_key(name.as_ref()) =>
+                    {
+                        if let Some(default) = defaults.get(name) {
+                            instance
+                                .properties
+                                .insert(name.to_string(), default.clone());
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+fn fix_parents(dom: &mut WeakDom) {
+    let root_ref = dom.root_ref();
+
+    for referent in descendants(dom, root_ref).into_iter().skip(1) {
+        let instance = dom.get_by_ref(referent).unwrap();
+        let parent_class = &dom.get_by_ref(instance.parent()).unwrap().class;
+
+        let dest = if is_service(&instance.class) {
+            root_ref
+        } else if let Some(&(_, required)) = REQUIRED_PARENTS
+            .iter()
+            .find(|(class, _)| *class == instance.class)
+        {
+            if parent_class == required {
+                continue;
+            }
+
+            match find_service(dom, required) {
+                Some(service) => service,
+                None => continue,
+            }
+        } else {
+            continue;
+        };
+
+        if instance.parent() != dest {
+            dom.transfer_within(referent, dest);
+        }
+    }
+}
+
+fn order_services(dom: &mut WeakDom) {
+    let root_ref = dom.root_ref();
+
+    let (mut services, others): (Vec<Ref>, Vec<Ref>) = dom
+        .root()
+        .children()
+        .iter()
+        .partition(|&&child| is_service(&dom.get_by_ref(child).unwrap().class));
+
+    services.sort_by(|a, b| {
+        let a = dom.get_by_ref(*a).unwrap();
+        let b = dom.get_by_ref(*b).unwrap();
+        explorer_order(a, b)
+    });
+
+    let order: Vec<Ref> = services.into_iter().chain(others).collect();
+    if order.as_slice() == dom.root().children() {
+        return;
+    }
+
+    // Moving an instance to the parent it's already in puts it at the end, so
+    // this rebuilds the list of children in order.
+    for child in order {
+        dom.transfer_within(child, root_ref);
+    }
+}
+
+fn find_service(dom: &WeakDom, class: &str) -> Option<Ref> {
+    dom.root()
+        .children()
+        .iter()
+        .copied()
+        .find(|&child| dom.get_by_ref(child).unwrap().class == class)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::{types::Variant, InstanceBuilder};
+
+    fn child_classes(dom: &WeakDom, referent: Ref) -> Vec<&str> {
+        dom.get_by_ref(referent)
+            .unwrap()
+            .children()
+            .iter()
+            .map(|&child| dom.get_by_ref(child).unwrap().class.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn fixes_parents_and_order() {
+        let workspace = InstanceBuilder::new("Workspace");
+        let workspace_ref = workspace.referent();
+
+        let mut dom = WeakDom::new(
+            InstanceBuilder::new("DataModel")
+                .with_child(InstanceBuilder::new("Folder"))
+                .with_child(workspace)
+                .with_child(
+                    InstanceBuilder::new("Lighting")
+                        .with_child(InstanceBuilder::new("ReplicatedStorage"))
+                        .with_child(InstanceBuilder::new("Terrain")),
+                ),
+        );
+
+        normalize_for_studio(&mut dom);
+
+        assert_eq!(
+            child_classes(&dom, dom.root_ref()),
+            ["Workspace", "Lighting", "ReplicatedStorage", "Folder"]
+        );
+        assert_eq!(child_classes(&dom, workspace_ref), ["Terrain"]);
+    }
+
+    #[test]
+    fn fills_and_strips_properties() {
+        let mut dom = WeakDom::new(
+            InstanceBuilder::new("Part").with_property("Position", Variant::Float32(1.0)),
+        );
+
+        normalize_for_studio(&mut dom);
+
+        let part = dom.root();
+        assert!(!part.properties.contains_key("Position"));
+        assert_eq!(part.properties.get("Anchored"), Some(&Variant::Bool(false)));
+    }
+}
@@ -0,0 +1,117 @@
+use rbx_dom_weak::{
+    types::{Content, Ref},
+    Instance, WeakDom,
+};
+
+use crate::{
+    assets::parse_asset_id,
+    path::{descendants, full_name},
+};
+
+/// A package in a DOM, found by [`packages`].
+///
+/// Roblox marks the root instance of a package by giving it a PackageLink
+/// child, which holds everything known about the package.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackageInfo {
+    /// The referent of the package's root instance.
+    pub root: Ref,
+
+    /// The referent of the root's PackageLink.
+    pub link: Ref,
+
+    /// The full name of the package's root, like `Workspace.Tree`.
+    pub path: String,
+
+    /// The URL of the package asset, from `PackageLink.PackageId`.
+    pub package_url: String,
+
+    /// The numeric ID of the package asset, if `package_url` has one.
+    pub package_id: Option<u64>,
+
+    /// The version of the package that this copy is based on.
+    pub version: Option<i64>,
+
+    /// Whether this copy updates itself when a new version is published.
+    pub auto_update: bool,
+
+    /// Whether this copy has been changed since it was updated, meaning it
+    /// differs from the published version.
+    pub modified: bool,
+}
+
+impl PackageInfo {
+    fn from_link(dom: &WeakDom, link: &Instance) -> Self {
+        let package_url = link
+            .get_typed::<Content>("PackageId")
+            .map(Content::into_string)
+            .unwrap_or_default();
+
+        let version = link
+            .get_typed::<i64>("VersionIdSerialize")
+            .or_else(|_| link.get_typed::<i64>("VersionNumber"))
+            .ok();
+
+        PackageInfo {
+            root: link.parent(),
+            link: link.referent(),
+            path: full_name(dom, link.parent()),
+            package_id: parse_asset_id(&package_url),
+            package_url,
+            version,
+            auto_update: link.get_typed::<bool>("AutoUpdate").unwrap_or(false),
+            modified: link.get_typed::<i64>("ModifiedState").unwrap_or(0) != 0,
+        }
+    }
+}
+
+/// Finds every package in the DOM in depth-first order. Packages nested inside
+/// other packages are included.
+pub fn packages(dom: &WeakDom) -> Vec<PackageInfo> {
+    descendants(dom, dom.root_ref())
+        .into_iter()
+        .filter_map(|referent| {
+            let instance = dom.get_by_ref(referent).unwrap();
+
+            if instance.class == "PackageLink" && instance.parent().is_some() {
+                Some(PackageInfo::from_link(dom, instance))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::InstanceBuilder;
+
+    #[test]
+    fn finds_packages() {
+        let dom = WeakDom::new(
+            InstanceBuilder::new("DataModel").with_child(
+                InstanceBuilder::new("Workspace").with_child(
+                    InstanceBuilder::new("Model").with_name("Tree").with_child(
+                        InstanceBuilder::new("PackageLink")
+                            .with_property("PackageId", Content::from("rbxassetid://42"))
+                            .with_property("VersionIdSerialize", 3i64)
+                            .with_property("AutoUpdate", true)
+                            .with_property("ModifiedState", 1i32),
+                    ),
+                ),
+            ),
+        );
+
+        let found = packages(&dom);
+        assert_eq!(found.len(), 1);
+
+        let package = &found[0];
+        assert_eq!(package.path, "Workspace.Tree");
+        assert_eq!(package.package_id, Some(42));
+        assert_eq!(package.version, Some(3));
+        assert!(package.auto_update);
+        assert!(package.modified);
+    }
+}
@@ -0,0 +1,57 @@
+use std::borrow::Cow;
+
+use rbx_dom_weak::{
+    types::{Ref, Variant},
+    WeakDom,
+};
+
+/// Returns the names of an instance and its ancestors joined with dots, like
+/// Roblox's `Instance:GetFullName`. The root of the DOM is not included.
+pub(crate) fn full_name(dom: &WeakDom, referent: Ref) -> String {
+    let mut names = Vec::new();
+    let mut current = referent;
+
+    while current != dom.root_ref() {
+        match dom.get_by_ref(current) {
+            Some(instance) => {
+                names.push(instance.name.as_str());
+                current = instance.parent();
+            }
+            None => break,
+        }
+    }
+
+    names.reverse();
+    names.join(".")
+}
+
+/// Reads a property that holds text. Binary files don't distinguish between
+/// strings and bytes for properties that aren't in the reflection database,
+/// so `BinaryString` values that are valid UTF-8 count too.
+pub(crate) fn text_value(value: &Variant) -> Option<Cow<'_, str>> {
+    match value {
+        Variant::String(value) => Some(Cow::Borrowed(value.as_str())),
+        Variant::Content(value) => Some(Cow::Borrowed(AsRef::<str>::as_ref(value))),
+        Variant::BinaryString(value) => std::str::from_utf8(AsRef::<[u8]>::as_ref(value))
+            .ok()
+            .map(Cow::Borrowed),
+        _ => None,
+    }
+}
+
+/// Returns the given instance and all of its descendants, parents before
+/// children.
+pub(crate) fn descendants(dom: &WeakDom, referent: Ref) -> Vec<Ref> {
+    let mut found = Vec::new();
+    let mut stack = vec![referent];
+
+    while let Some(referent) = stack.pop() {
+        found.push(referent);
+
+        if let Some(instance) = dom.get_by_ref(referent) {
+            stack.extend(instance.children().iter().rev());
+        }
+    }
+
+    found
+}
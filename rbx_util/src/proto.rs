@@ -0,0 +1,852 @@
+//! Protocol buffer messages for exchanging trees of instances, and changes
+//! to them, with tools written in other languages, like Go services or
+//! TypeScript plugins, without them having to read Roblox model and place
+//! files. The schema is in [`SCHEMA`], which those tools can generate code
+//! from with protoc. rbx_util generates its own code from it with prost when
+//! it's built, which doesn't need protoc to be installed.
+//!
+//! ```
+//! use rbx_dom_weak::{InstanceBuilder, WeakDom};
+//! use rbx_util::proto::Tree;
+//!
+//! let dom = WeakDom::new(
+//!     InstanceBuilder::new("DataModel").with_child(InstanceBuilder::new("Workspace")),
+//! );
+//!
+//! let message = Tree::from_dom(&dom, dom.root().children()).encode()?;
+//!
+//! let decoded = Tree::decode(&message)?.to_dom()?;
+//! let workspace = decoded.get_by_ref(decoded.root().children()[0]).unwrap();
+//! assert_eq!(workspace.class, "Workspace");
+//! # Ok::<(), rbx_util::ProtoError>(())
+//! ```
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    convert::TryFrom,
+};
+
+use prost::Message;
+use rbx_dom_weak::{
+    types::{
+        BinaryString, BrickColor, CFrame, Color3, Color3uint8, Enum, Matrix3, NumberRange, Ref,
+        UDim, UDim2, Variant, Vector2, Vector3,
+    },
+    InstanceBuilder, JournalEntry, NameChange, PropertyChange, WeakDom,
+};
+
+use crate::error::ProtoError;
+
+/// The protobuf schema of the messages in this module.
+pub const SCHEMA: &str = include_str!("../proto/rbx_tree.proto");
+
+/// A list of instances and their descendants, the `Tree` message.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Tree {
+    /// Every instance in the tree, parents before their children. Siblings
+    /// are in the order they are in under their parent.
+    pub instances: Vec<Instance>,
+}
+
+impl Tree {
+    /// Collects the given instances and their descendants into a tree.
+    ///
+    /// ## Panics
+    /// Panics if any of `referents` do not refer to an instance in the DOM.
+    pub fn from_dom(dom: &WeakDom, referents: &[Ref]) -> Self {
+        let mut instances = Vec::new();
+        for &referent in referents {
+            collect_subtree(dom, referent, &mut instances);
+        }
+
+        Tree { instances }
+    }
+
+    /// Builds a DOM out of the tree, with a DataModel at its root holding the
+    /// instances at the top of the tree. Instances keep their referents, and
+    /// `Ref` properties that point outside of the tree are cleared.
+    pub fn to_dom(&self) -> Result<WeakDom, ProtoError> {
+        let mut referents = HashSet::new();
+        for instance in &self.instances {
+            if instance.referent.is_none() {
+                return Err(ProtoError::Malformed("instance has no referent"));
+            }
+            if !referents.insert(instance.referent) {
+                return Err(ProtoError::DuplicateReferent(instance.referent));
+            }
+        }
+
+        let mut roots = Vec::new();
+        let mut children: HashMap<Ref, Vec<&Instance>> = HashMap::new();
+        for instance in &self.instances {
+            if referents.contains(&instance.parent) {
+                children.entry(instance.parent).or_default().push(instance);
+            } else {
+                roots.push(instance);
+            }
+        }
+
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let mut queue: VecDeque<(Ref, &Instance)> = roots
+            .into_iter()
+            .map(|instance| (dom.root_ref(), instance))
+            .collect();
+        let mut inserted = 0;
+
+        while let Some((parent, instance)) = queue.pop_front() {
+            let builder = InstanceBuilder::new(&instance.class_name)
+                .with_referent(instance.referent)
+                .with_name(&instance.name)
+                .with_properties(instance.properties.iter().map(|(name, value)| {
+                    let value = match value {
+                        Variant::Ref(target) if !referents.contains(target) => {
+                            Variant::Ref(Ref::none())
+                        }
+                        value => value.clone(),
+                    };
+                    (name.clone(), value)
+                }));
+
+            let referent = dom.try_insert(parent, builder)?;
+            inserted += 1;
+
+            for &child in children.get(&instance.referent).into_iter().flatten() {
+                queue.push_back((referent, child));
+            }
+        }
+
+        // Instances that are their own ancestors can't be reached from the top
+        // of the tree.
+        if inserted != self.instances.len() {
+            return Err(ProtoError::Malformed("instances are their own ancestors"));
+        }
+
+        Ok(dom)
+    }
+
+    /// Writes the tree as a `Tree` message.
+    pub fn encode(&self) -> Result<Vec<u8>, ProtoError> {
+        let tree = message::Tree {
+            instances: self
+                .instances
+                .iter()
+                .map(instance_to_message)
+                .collect::<Result<_, _>>()?,
+        };
+
+        Ok(tree.encode_to_vec())
+    }
+
+    /// Reads a `Tree` message.
+    pub fn decode(contents: &[u8]) -> Result<Self, ProtoError> {
+        let tree = message::Tree::decode(contents)?;
+
+        Ok(Tree {
+            instances: tree
+                .instances
+                .into_iter()
+                .map(instance_from_message)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+/// A single instance, the `Instance` message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instance {
+    /// The referent of the instance.
+    pub referent: Ref,
+
+    /// The referent of the instance's parent. Instances whose parent isn't
+    /// in the same tree are at the top of it.
+    pub parent: Ref,
+
+    /// The instance's ClassName.
+    pub class_name: String,
+
+    /// The instance's Name.
+    pub name: String,
+
+    /// The instance's properties, sorted by name.
+    pub properties: BTreeMap<String, Variant>,
+}
+
+impl Instance {
+    fn from_dom_instance(instance: &rbx_dom_weak::Instance) -> Self {
+        Instance {
+            referent: instance.referent(),
+            parent: instance.parent(),
+            class_name: instance.class.clone(),
+            name: instance.name.clone(),
+            properties: instance
+                .properties
+                .iter()
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect(),
+        }
+    }
+}
+
+/// Changes made to a tree, in the order they were made, the `Patch` message.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Patch {
+    /// The changes, in the order they were made.
+    pub changes: Vec<Change>,
+}
+
+impl Patch {
+    /// Converts entries drained from the journal of a DOM into a patch.
+    /// Inserted instances are sent as they were when they were inserted.
+    pub fn from_journal(entries: &[JournalEntry]) -> Self {
+        let changes = entries
+            .iter()
+            .filter_map(|entry| match entry {
+                JournalEntry::Insert {
+                    referent,
+                    parent,
+                    instances,
+                }
+                | JournalEntry::TransferIn {
+                    referent,
+                    parent,
+                    instances,
+                } => Some(Change::Insert {
+                    referent: *referent,
+                    parent: *parent,
+                    instances: instances.iter().map(Instance::from_dom_instance).collect(),
+                }),
+                JournalEntry::Destroy {
+                    referent, parent, ..
+                }
+                | JournalEntry::TransferOut {
+                    referent, parent, ..
+                } => Some(Change::Destroy {
+                    referent: *referent,
+                    parent: *parent,
+                }),
+                JournalEntry::Move {
+                    referent,
+                    old_parent,
+                    new_parent,
+                } => Some(Change::Move {
+                    referent: *referent,
+                    old_parent: *old_parent,
+                    new_parent: *new_parent,
+                }),
+                JournalEntry::Edit {
+                    referent,
+                    name,
+                    properties,
+                } => Some(Change::Edit {
+                    referent: *referent,
+                    name: name.clone(),
+                    properties: properties.clone(),
+                }),
+                _ => None,
+            })
+            .collect();
+
+        Patch { changes }
+    }
+
+    /// Writes the patch as a `Patch` message.
+    pub fn encode(&self) -> Result<Vec<u8>, ProtoError> {
+        let patch = message::Patch {
+            changes: self
+                .changes
+                .iter()
+                .map(change_to_message)
+                .collect::<Result<_, _>>()?,
+        };
+
+        Ok(patch.encode_to_vec())
+    }
+
+    /// Reads a `Patch` message.
+    pub fn decode(contents: &[u8]) -> Result<Self, ProtoError> {
+        let patch = message::Patch::decode(contents)?;
+        let mut changes = Vec::new();
+        for change in patch.changes {
+            changes.extend(change_from_message(change)?);
+        }
+
+        Ok(Patch { changes })
+    }
+}
+
+/// A single change in a [`Patch`], the `Change` message.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Change {
+    /// An instance was added to the tree along with its descendants.
+    Insert {
+        /// The referent of the inserted instance.
+        referent: Ref,
+        /// The referent of the instance it was inserted into.
+        parent: Ref,
+        /// The inserted instance followed by its descendants, parents before
+        /// their children like in a [`Tree`], as they were when they were
+        /// inserted.
+        instances: Vec<Instance>,
+    },
+
+    /// An instance was removed from the tree along with its descendants.
+    Destroy {
+        /// The referent of the removed instance.
+        referent: Ref,
+        /// The referent of the instance that used to be its parent.
+        parent: Ref,
+    },
+
+    /// An instance was moved to a new parent.
+    Move {
+        /// The referent of the instance that was moved.
+        referent: Ref,
+        /// The referent of the instance that used to be its parent.
+        old_parent: Ref,
+        /// The referent of its new parent.
+        new_parent: Ref,
+    },
+
+    /// The name or properties of an instance were changed.
+    Edit {
+        /// The referent of the instance that was changed.
+        referent: Ref,
+        /// The instance's name before and after the change, if it was
+        /// renamed.
+        name: Option<NameChange>,
+        /// The properties that were set or removed.
+        properties: Vec<PropertyChange>,
+    },
+}
+
+/// Adds the instance with the given referent and its descendants to
+/// `instances`, parents before their children.
+fn collect_subtree(dom: &WeakDom, referent: Ref, instances: &mut Vec<Instance>) {
+    let mut stack = vec![referent];
+
+    while let Some(referent) = stack.pop() {
+        let instance = dom
+            .get_by_ref(referent)
+            .unwrap_or_else(|| panic!("cannot convert an instance that does not exist"));
+
+        instances.push(Instance::from_dom_instance(instance));
+        stack.extend(instance.children().iter().rev());
+    }
+}
+
+/// The message types generated by prost from [`SCHEMA`].
+#[allow(clippy::all)]
+mod message {
+    include!(concat!(env!("OUT_DIR"), "/rbx_tree.rs"));
+}
+
+use message::{change::Kind as ChangeKind, value::Kind as ValueKind};
+
+fn instance_to_message(instance: &Instance) -> Result<message::Instance, ProtoError> {
+    Ok(message::Instance {
+        referent: ref_to_bytes(instance.referent),
+        parent: ref_to_bytes(instance.parent),
+        class_name: instance.class_name.clone(),
+        name: instance.name.clone(),
+        properties: instance
+            .properties
+            .iter()
+            .map(|(name, value)| {
+                Ok(message::Property {
+                    name: name.clone(),
+                    value: Some(value_to_message(value)?),
+                })
+            })
+            .collect::<Result<_, ProtoError>>()?,
+    })
+}
+
+fn instance_from_message(instance: message::Instance) -> Result<Instance, ProtoError> {
+    Ok(Instance {
+        referent: ref_from_bytes(&instance.referent)?,
+        parent: ref_from_bytes(&instance.parent)?,
+        class_name: instance.class_name,
+        name: instance.name,
+        properties: instance
+            .properties
+            .into_iter()
+            .map(|property| {
+                let value = property
+                    .value
+                    .ok_or(ProtoError::Malformed("property has no value"))?;
+                Ok((property.name, value_from_message(value)?))
+            })
+            .collect::<Result<_, ProtoError>>()?,
+    })
+}
+
+fn change_to_message(change: &Change) -> Result<message::Change, ProtoError> {
+    let kind = match change {
+        Change::Insert {
+            referent,
+            parent,
+            instances,
+        } => ChangeKind::Insert(message::Insert {
+            referent: ref_to_bytes(*referent),
+            parent: ref_to_bytes(*parent),
+            instances: instances
+                .iter()
+                .map(instance_to_message)
+                .collect::<Result<_, _>>()?,
+        }),
+        Change::Destroy { referent, parent } => ChangeKind::Destroy(message::Destroy {
+            referent: ref_to_bytes(*referent),
+            parent: ref_to_bytes(*parent),
+        }),
+        Change::Move {
+            referent,
+            old_parent,
+            new_parent,
+        } => ChangeKind::Move(message::Move {
+            referent: ref_to_bytes(*referent),
+            old_parent: ref_to_bytes(*old_parent),
+            new_parent: ref_to_bytes(*new_parent),
+        }),
+        Change::Edit {
+            referent,
+            name,
+            properties,
+        } => ChangeKind::Edit(message::Edit {
+            referent: ref_to_bytes(*referent),
+            old_name: name.as_ref().map(|name| name.old.clone()),
+            new_name: name.as_ref().map(|name| name.new.clone()),
+            properties: properties
+                .iter()
+                .map(|change| {
+                    Ok(message::PropertyChange {
+                        name: change.name.clone(),
+                        old: change.old.as_ref().map(value_to_message).transpose()?,
+                        new: change.new.as_ref().map(value_to_message).transpose()?,
+                    })
+                })
+                .collect::<Result<_, ProtoError>>()?,
+        }),
+    };
+
+    Ok(message::Change { kind: Some(kind) })
+}
+
+/// Converts a `Change` message, returning `None` for kinds of changes that
+/// were added to the schema after this version of rbx_util.
+fn change_from_message(change: message::Change) -> Result<Option<Change>, ProtoError> {
+    let change = match change.kind {
+        Some(ChangeKind::Insert(insert)) => Change::Insert {
+            referent: ref_from_bytes(&insert.referent)?,
+            parent: ref_from_bytes(&insert.parent)?,
+            instances: insert
+                .instances
+                .into_iter()
+                .map(instance_from_message)
+                .collect::<Result<_, _>>()?,
+        },
+        Some(ChangeKind::Destroy(destroy)) => Change::Destroy {
+            referent: ref_from_bytes(&destroy.referent)?,
+            parent: ref_from_bytes(&destroy.parent)?,
+        },
+        Some(ChangeKind::Move(change)) => Change::Move {
+            referent: ref_from_bytes(&change.referent)?,
+            old_parent: ref_from_bytes(&change.old_parent)?,
+            new_parent: ref_from_bytes(&change.new_parent)?,
+        },
+        Some(ChangeKind::Edit(edit)) => Change::Edit {
+            referent: ref_from_bytes(&edit.referent)?,
+            name: match (edit.old_name, edit.new_name) {
+                (Some(old), Some(new)) => Some(NameChange { old, new }),
+                (None, None) => None,
+                _ => return Err(ProtoError::Malformed("edit has only one name")),
+            },
+            properties: edit
+                .properties
+                .into_iter()
+                .map(|change| {
+                    Ok(PropertyChange {
+                        name: change.name,
+                        old: change.old.map(value_from_message).transpose()?,
+                        new: change.new.map(value_from_message).transpose()?,
+                    })
+                })
+                .collect::<Result<_, ProtoError>>()?,
+        },
+        None => return Ok(None),
+    };
+
+    Ok(Some(change))
+}
+
+fn value_to_message(value: &Variant) -> Result<message::Value, ProtoError> {
+    let kind = match value {
+        Variant::Bool(value) => ValueKind::Bool(*value),
+        Variant::Int32(value) => ValueKind::Int32(*value),
+        Variant::Int64(value) => ValueKind::Int64(*value),
+        Variant::Float32(value) => ValueKind::Float32(*value),
+        Variant::Float64(value) => ValueKind::Float64(*value),
+        Variant::String(value) => ValueKind::String(value.clone()),
+        Variant::BinaryString(value) => ValueKind::BinaryString(<[u8]>::to_vec(value.as_ref())),
+        Variant::Ref(value) => ValueKind::Ref(ref_to_bytes(*value)),
+        Variant::Enum(value) => ValueKind::Enum(value.to_u32()),
+        Variant::BrickColor(value) => ValueKind::BrickColor(*value as u32),
+        Variant::Vector2(value) => ValueKind::Vector2(message::Vector2 {
+            x: value.x,
+            y: value.y,
+        }),
+        Variant::Vector3(value) => ValueKind::Vector3(vector3_to_message(value)),
+        Variant::CFrame(value) => ValueKind::Cframe(message::CFrame {
+            position: Some(vector3_to_message(&value.position)),
+            x: Some(vector3_to_message(&value.orientation.x)),
+            y: Some(vector3_to_message(&value.orientation.y)),
+            z: Some(vector3_to_message(&value.orientation.z)),
+        }),
+        Variant::Color3(value) => ValueKind::Color3(message::Color3 {
+            r: value.r,
+            g: value.g,
+            b: value.b,
+        }),
+        Variant::Color3uint8(value) => ValueKind::Color3uint8(message::Color3uint8 {
+            r: u32::from(value.r),
+            g: u32::from(value.g),
+            b: u32::from(value.b),
+        }),
+        Variant::UDim(value) => ValueKind::Udim(udim_to_message(value)),
+        Variant::UDim2(value) => ValueKind::Udim2(message::UDim2 {
+            x: Some(udim_to_message(&value.x)),
+            y: Some(udim_to_message(&value.y)),
+        }),
+        Variant::NumberRange(value) => ValueKind::NumberRange(message::NumberRange {
+            min: value.min,
+            max: value.max,
+        }),
+        other => ValueKind::Other(serde_json::to_string(other)?),
+    };
+
+    Ok(message::Value { kind: Some(kind) })
+}
+
+fn value_from_message(value: message::Value) -> Result<Variant, ProtoError> {
+    let kind = value.kind.ok_or(ProtoError::Malformed(
+        "value has no type this version can read",
+    ))?;
+
+    Ok(match kind {
+        ValueKind::Bool(value) => Variant::Bool(value),
+        ValueKind::Int32(value) => Variant::Int32(value),
+        ValueKind::Int64(value) => Variant::Int64(value),
+        ValueKind::Float32(value) => Variant::Float32(value),
+        ValueKind::Float64(value) => Variant::Float64(value),
+        ValueKind::String(value) => Variant::String(value),
+        ValueKind::BinaryString(value) => Variant::BinaryString(BinaryString::from(value)),
+        ValueKind::Ref(value) => Variant::Ref(ref_from_bytes(&value)?),
+        ValueKind::Enum(value) => Variant::Enum(Enum::from_u32(value)),
+        ValueKind::BrickColor(value) => Variant::BrickColor(
+            u16::try_from(value)
+                .ok()
+                .and_then(BrickColor::from_number)
+                .ok_or(ProtoError::Malformed("unknown BrickColor"))?,
+        ),
+        ValueKind::Vector2(value) => Variant::Vector2(Vector2::new(value.x, value.y)),
+        ValueKind::Vector3(value) => Variant::Vector3(vector3_from_message(Some(value))),
+        ValueKind::Cframe(value) => Variant::CFrame(CFrame::new(
+            vector3_from_message(value.position),
+            Matrix3::new(
+                vector3_from_message(value.x),
+                vector3_from_message(value.y),
+                vector3_from_message(value.z),
+            ),
+        )),
+        ValueKind::Color3(value) => Variant::Color3(Color3::new(value.r, value.g, value.b)),
+        ValueKind::Color3uint8(value) => {
+            let component = |value: u32| {
+                u8::try_from(value)
+                    .map_err(|_| ProtoError::Malformed("Color3uint8 is out of range"))
+            };
+            Variant::Color3uint8(Color3uint8::new(
+                component(value.r)?,
+                component(value.g)?,
+                component(value.b)?,
+            ))
+        }
+        ValueKind::Udim(value) => Variant::UDim(udim_from_message(Some(value))),
+        ValueKind::Udim2(value) => Variant::UDim2(UDim2::new(
+            udim_from_message(value.x),
+            udim_from_message(value.y),
+        )),
+        ValueKind::NumberRange(value) => {
+            Variant::NumberRange(NumberRange::new(value.min, value.max))
+        }
+        ValueKind::Other(value) => serde_json::from_str(&value)?,
+    })
+}
+
+fn vector3_to_message(value: &Vector3) -> message::Vector3 {
+    message::Vector3 {
+        x: value.x,
+        y: value.y,
+        z: value.z,
+    }
+}
+
+/// Converts a `Vector3` message, which is all zeroes if it's unset like any
+/// other message in proto3.
+fn vector3_from_message(value: Option<message::Vector3>) -> Vector3 {
+    let value = value.unwrap_or_default();
+    Vector3::new(value.x, value.y, value.z)
+}
+
+fn udim_to_message(value: &UDim) -> message::UDim {
+    message::UDim {
+        scale: value.scale,
+        offset: value.offset,
+    }
+}
+
+fn udim_from_message(value: Option<message::UDim>) -> UDim {
+    let value = value.unwrap_or_default();
+    UDim::new(value.scale, value.offset)
+}
+
+/// Converts a referent to bytes, leaving them empty if it's none.
+fn ref_to_bytes(referent: Ref) -> Vec<u8> {
+    if referent.is_some() {
+        referent.to_bytes().to_vec()
+    } else {
+        Vec::new()
+    }
+}
+
+fn ref_from_bytes(bytes: &[u8]) -> Result<Ref, ProtoError> {
+    match bytes {
+        [] => Ok(Ref::none()),
+        bytes => <[u8; 16]>::try_from(bytes)
+            .map(Ref::from_bytes)
+            .map_err(|_| ProtoError::Malformed("referent is not 16 bytes long")),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::types::{Attributes, Tags};
+
+    #[test]
+    fn tree_round_trip() {
+        let part = InstanceBuilder::new("Part")
+            .with_name("Body")
+            .with_property("Anchored", true)
+            .with_property("Transparency", 0.5f32)
+            .with_property("Offset", -3i32)
+            .with_property("Material", Enum::from_u32(256))
+            .with_property("BrickColor", BrickColor::ReallyRed)
+            .with_property(
+                "CFrame",
+                CFrame::new(Vector3::new(1.0, 2.0, 3.0), Matrix3::identity()),
+            )
+            .with_property("Color", Color3uint8::new(255, 0, 10))
+            .with_property("Tags", Tags::from(vec!["Tile".to_owned()]))
+            .with_property("Attributes", Attributes::new());
+        let part_ref = part.referent();
+
+        let dom = WeakDom::new(
+            InstanceBuilder::new("DataModel").with_child(
+                InstanceBuilder::new("Model")
+                    .with_property("PrimaryPart", part_ref)
+                    .with_property("WorldPivot", Ref::new())
+                    .with_child(part)
+                    .with_child(InstanceBuilder::new("Script").with_property("Source", "")),
+            ),
+        );
+
+        let tree = Tree::from_dom(&dom, dom.root().children());
+        let decoded = Tree::decode(&tree.encode().unwrap()).unwrap();
+        assert_eq!(decoded, tree);
+
+        let model_ref = dom.root().children()[0];
+        let rebuilt = decoded.to_dom().unwrap();
+        let model = rebuilt.get_by_ref(rebuilt.root().children()[0]).unwrap();
+        assert_eq!(model.referent(), model_ref);
+        assert_eq!(model.children()[0], part_ref);
+        assert_eq!(
+            model.properties.get("PrimaryPart"),
+            Some(&Variant::Ref(part_ref))
+        );
+        // Refs to instances that aren't in the tree are cleared.
+        assert_eq!(
+            model.properties.get("WorldPivot"),
+            Some(&Variant::Ref(Ref::none()))
+        );
+        assert_eq!(
+            rebuilt.get_by_ref(part_ref).unwrap().properties,
+            dom.get_by_ref(part_ref).unwrap().properties
+        );
+    }
+
+    #[test]
+    fn schema_round_trip() {
+        let values = [
+            Variant::Bool(true),
+            Variant::Int32(-3),
+            Variant::Int64(1 << 40),
+            Variant::Float32(0.5),
+            Variant::Float64(-0.25),
+            Variant::String("Hello".to_owned()),
+            Variant::BinaryString(BinaryString::from(vec![0, 1, 2])),
+            Variant::Ref(Ref::none()),
+            Variant::Enum(Enum::from_u32(2)),
+            Variant::BrickColor(BrickColor::ReallyRed),
+            Variant::Vector2(Vector2::new(1.0, 2.0)),
+            Variant::Vector3(Vector3::new(1.0, 2.0, 3.0)),
+            Variant::CFrame(CFrame::new(
+                Vector3::new(1.0, 2.0, 3.0),
+                Matrix3::identity(),
+            )),
+            Variant::Color3(Color3::new(0.0, 0.5, 1.0)),
+            Variant::Color3uint8(Color3uint8::new(0, 128, 255)),
+            Variant::UDim(UDim::new(0.5, -10)),
+            Variant::UDim2(UDim2::new(UDim::new(0.5, 10), UDim::new(1.0, -10))),
+            Variant::NumberRange(NumberRange::new(0.0, 1.0)),
+            Variant::Tags(Tags::from(vec!["Tile".to_owned()])),
+        ];
+
+        // Every value has its own kind in the schema, and reads back from it
+        // unchanged.
+        let mut kinds = HashSet::new();
+        for value in &values {
+            let message = value_to_message(value).unwrap();
+            let bytes = message.encode_to_vec();
+            let decoded = message::Value::decode(bytes.as_slice()).unwrap();
+            assert_eq!(decoded, message);
+
+            let kind = std::mem::discriminant(decoded.kind.as_ref().unwrap());
+            assert!(kinds.insert(kind), "{:?} shares a kind", value);
+            assert_eq!(&value_from_message(decoded).unwrap(), value);
+        }
+
+        // Messages from other tools can leave out fields that are zero, which
+        // read as zero.
+        let udim2 = message::Value {
+            kind: Some(ValueKind::Udim2(message::UDim2 {
+                x: None,
+                y: Some(message::UDim {
+                    scale: 1.0,
+                    offset: 0,
+                }),
+            })),
+        };
+        assert_eq!(
+            value_from_message(message::Value::decode(udim2.encode_to_vec().as_slice()).unwrap())
+                .unwrap(),
+            Variant::UDim2(UDim2::new(UDim::new(0.0, 0), UDim::new(1.0, 0)))
+        );
+
+        // Kinds of values and changes from newer schemas are unset when read
+        // with this one.
+        let unknown_value = [0xa2, 0x01, 0x00];
+        assert!(matches!(
+            value_from_message(message::Value::decode(&unknown_value[..]).unwrap()),
+            Err(ProtoError::Malformed(_))
+        ));
+        let unknown_change = [0x0a, 0x02, 0x2a, 0x00];
+        assert_eq!(Patch::decode(&unknown_change).unwrap(), Patch::default());
+    }
+
+    #[test]
+    fn malformed_trees() {
+        let instance = |referent, parent| Instance {
+            referent,
+            parent,
+            class_name: "Folder".to_owned(),
+            name: String::new(),
+            properties: BTreeMap::new(),
+        };
+        let (a, b) = (Ref::new(), Ref::new());
+
+        let duplicate = Tree {
+            instances: vec![instance(a, Ref::none()), instance(a, Ref::none())],
+        };
+        assert!(matches!(
+            duplicate.to_dom(),
+            Err(ProtoError::DuplicateReferent(referent)) if referent == a
+        ));
+
+        let cycle = Tree {
+            instances: vec![instance(a, b), instance(b, a)],
+        };
+        assert!(matches!(cycle.to_dom(), Err(ProtoError::Malformed(_))));
+
+        assert!(matches!(
+            Tree::decode(&[0x0a, 0x05, 0x00]),
+            Err(ProtoError::Decode(_))
+        ));
+    }
+
+    #[test]
+    fn patch_from_journal() {
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let root_ref = dom.root_ref();
+        dom.enable_journal();
+
+        let folder = dom.insert(
+            root_ref,
+            InstanceBuilder::new("Folder").with_child(InstanceBuilder::new("Part")),
+        );
+        let inserted = Tree::from_dom(&dom, &[folder]).instances;
+        dom.edit(folder)
+            .set_name("Parts")
+            .set_property("Color", Color3::new(1.0, 0.0, 0.0));
+        let part = dom.get_by_ref(folder).unwrap().children()[0];
+        dom.transfer_within(part, root_ref);
+        dom.destroy(folder);
+        let model = dom.insert(
+            root_ref,
+            InstanceBuilder::new("Model").with_child(InstanceBuilder::new("Part")),
+        );
+
+        let entries = dom.drain_journal();
+        let patch = Patch::from_journal(&entries);
+        assert_eq!(
+            patch.changes,
+            [
+                // The folder is sent as it was inserted, even though it was
+                // destroyed before the journal was drained.
+                Change::Insert {
+                    referent: folder,
+                    parent: root_ref,
+                    instances: inserted,
+                },
+                Change::Edit {
+                    referent: folder,
+                    name: Some(NameChange {
+                        old: "Folder".to_owned(),
+                        new: "Parts".to_owned(),
+                    }),
+                    properties: vec![PropertyChange {
+                        name: "Color".to_owned(),
+                        old: None,
+                        new: Some(Variant::Color3(Color3::new(1.0, 0.0, 0.0))),
+                    }],
+                },
+                Change::Move {
+                    referent: part,
+                    old_parent: folder,
+                    new_parent: root_ref,
+                },
+                Change::Destroy {
+                    referent: folder,
+                    parent: root_ref,
+                },
+                Change::Insert {
+                    referent: model,
+                    parent: root_ref,
+                    instances: Tree::from_dom(&dom, &[model]).instances,
+                },
+            ]
+        );
+
+        assert_eq!(Patch::decode(&patch.encode().unwrap()).unwrap(), patch);
+    }
+}
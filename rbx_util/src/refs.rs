@@ -0,0 +1,100 @@
+use rbx_dom_weak::{
+    types::{Ref, Variant},
+    WeakDom,
+};
+
+use crate::path::{descendants, full_name};
+
+/// A `Ref` property that points at an instance that isn't in the DOM, found by
+/// [`dead_refs`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeadRef {
+    /// The referent of the instance with the property.
+    pub referent: Ref,
+
+    /// The instance's full name, like `Workspace.Door.Hinge`.
+    pub path: String,
+
+    /// The instance's ClassName.
+    pub class: String,
+
+    /// The name of the property, like `Value` or `Attachment0`.
+    pub property: String,
+
+    /// The referent that the property points to.
+    pub target: Ref,
+}
+
+/// Finds every `Ref` property in the DOM that points at an instance that
+/// isn't in it, like an ObjectValue whose value was destroyed or a constraint
+/// whose attachments were left out of a model. Roblox loads these as `nil`
+/// without saying anything.
+///
+/// Properties that are empty on purpose are not reported.
+pub fn dead_refs(dom: &WeakDom) -> Vec<DeadRef> {
+    let mut found = Vec::new();
+
+    for referent in descendants(dom, dom.root_ref()) {
+        let instance = dom.get_by_ref(referent).unwrap();
+
+        // Sort properties so that results are stable between runs.
+        let mut properties: Vec<_> = instance.properties.iter().collect();
+        properties.sort_by(|a, b| a.0.cmp(b.0));
+
+        for (property, value) in properties {
+            let target = match value {
+                Variant::Ref(target) if target.is_some() => *target,
+                _ => continue,
+            };
+
+            if dom.get_by_ref(target).is_none() {
+                found.push(DeadRef {
+                    referent,
+                    path: full_name(dom, referent),
+                    class: instance.class.clone(),
+                    property: property.clone(),
+                    target,
+                });
+            }
+        }
+    }
+
+    found
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::InstanceBuilder;
+
+    #[test]
+    fn finds_dead_refs() {
+        let attachment = InstanceBuilder::new("Attachment");
+        let attachment_ref = attachment.referent();
+        let removed = InstanceBuilder::new("Attachment");
+        let removed_ref = removed.referent();
+
+        let mut dom = WeakDom::new(
+            InstanceBuilder::new("DataModel")
+                .with_child(
+                    InstanceBuilder::new("RopeConstraint")
+                        .with_property("Attachment0", attachment_ref)
+                        .with_property("Attachment1", removed_ref),
+                )
+                .with_child(InstanceBuilder::new("ObjectValue").with_property("Value", Ref::none()))
+                .with_child(attachment)
+                .with_child(removed),
+        );
+
+        assert!(dead_refs(&dom).is_empty());
+
+        dom.destroy(removed_ref);
+
+        let found = dead_refs(&dom);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].path, "RopeConstraint");
+        assert_eq!(found[0].property, "Attachment1");
+        assert_eq!(found[0].target, removed_ref);
+    }
+}
@@ -0,0 +1,122 @@
+use rbx_dom_weak::{types::Ref, InstanceBuilder, WeakDom};
+use rbx_reflection::PropertyTag;
+
+use crate::class::is_a;
+
+/// Properties that only matter to Studio, or that identify where an instance
+/// came from, which the reflection database doesn't tag as not replicated.
+const INTERNAL_PROPERTIES: &[(&str, &str)] = &[
+    ("Instance", "HistoryId"),
+    ("Instance", "UniqueId"),
+    ("LuaSourceContainer", "ScriptGuid"),
+];
+
+/// Properties that are tagged as not replicated in the reflection database,
+/// but that Roblox replicates anyway through a different mechanism.
+const REPLICATED_ANYWAY: &[(&str, &str)] = &[("Instance", "Attributes")];
+
+/// Copies the instance with the given referent and its descendants into a
+/// new DOM, leaving out properties that Roblox doesn't send to clients. The
+/// result is safe to publish or hand to a client without giving away data
+/// that only the server or Studio should have, like `SourceAssetId` and the
+/// GUIDs Studio uses to keep track of scripts.
+///
+/// Properties are left out if the reflection database tags the form that is
+/// written to files as `NotReplicated` or `ReadOnly`. Properties that aren't
+/// in the reflection database are kept. The copied instance becomes the root
+/// of the new DOM, and referents are kept the same, so `Ref` properties still
+/// point to the same instances.
+///
+/// ## Panics
+/// Panics if `referent` does not refer to an instance in the DOM.
+pub fn replicable_copy(dom: &WeakDom, referent: Ref) -> WeakDom {
+    assert!(
+        dom.get_by_ref(referent).is_some(),
+        "cannot copy an instance that does not exist"
+    );
+
+    WeakDom::new(replicable_builder(dom, referent))
+}
+
+fn replicable_builder(dom: &WeakDom, referent: Ref) -> InstanceBuilder {
+    let instance = dom.get_by_ref(referent).unwrap();
+
+    let properties = instance
+        .properties
+        .iter()
+        .filter(|(name, _)| is_replicated(&instance.class, name))
+        .map(|(name, value)| (name.clone(), value.clone()));
+
+    InstanceBuilder::new(&instance.class)
+        .with_referent(referent)
+        .with_name(&instance.name)
+        .with_properties(properties)
+        .with_children(
+            instance
+                .children()
+                .iter()
+                .map(|&child| replicable_builder(dom, child)),
+        )
+}
+
+fn is_replicated(class: &str, property: &str) -> bool {
+    let matches = |list: &[(&str, &str)]| {
+        list.iter()
+            .any(|&(owner, name)| name == property && is_a(class, owner))
+    };
+
+    if matches(INTERNAL_PROPERTIES) {
+        return false;
+    }
+
+    if matches(REPLICATED_ANYWAY) {
+        return true;
+    }
+
+    let descriptors =
+        match rbx_reflection_database::get().find_property_descriptors(class, property) {
+            Some(descriptors) => descriptors,
+            None => return true,
+        };
+
+    let tags = &descriptors.serialized.unwrap_or(descriptors.canonical).tags;
+    !tags.contains(&PropertyTag::NotReplicated) && !tags.contains(&PropertyTag::ReadOnly)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::types::{Attributes, Variant, Vector3};
+
+    #[test]
+    fn strips_internal_properties() {
+        let script = InstanceBuilder::new("Script")
+            .with_property("Source", "print('hi')")
+            .with_property("ScriptGuid", "{00000000-0000-0000-0000-000000000000}")
+            .with_property("SourceAssetId", 12345i64);
+        let script_ref = script.referent();
+        let part = InstanceBuilder::new("Part")
+            .with_property("Size", Vector3::new(1.0, 2.0, 3.0))
+            .with_property("Attributes", Attributes::new())
+            .with_property("HistoryId", "abc")
+            .with_property("Unknown", true)
+            .with_child(script);
+        let part_ref = part.referent();
+
+        let dom = WeakDom::new(InstanceBuilder::new("DataModel").with_child(part));
+        let copy = replicable_copy(&dom, part_ref);
+
+        assert_eq!(copy.root_ref(), part_ref);
+        let mut names: Vec<_> = copy.root().properties.keys().cloned().collect();
+        names.sort();
+        assert_eq!(names, ["Attributes", "Size", "Unknown"]);
+
+        let script = copy.get_by_ref(script_ref).unwrap();
+        assert_eq!(script.properties.len(), 1);
+        assert!(matches!(
+            script.properties.get("Source"),
+            Some(Variant::String(_))
+        ));
+    }
+}
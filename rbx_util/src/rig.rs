@@ -0,0 +1,319 @@
+use rbx_dom_weak::{
+    types::{CFrame, Enum, Matrix3, Ref, Vector3},
+    InstanceBuilder,
+};
+
+/// The kinds of character rig that Roblox supports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RigType {
+    /// The classic rig, made of six parts joined to the Torso.
+    R6,
+
+    /// The rig with fifteen parts, whose joints are placed by matching
+    /// attachments in each pair of parts.
+    R15,
+}
+
+impl RigType {
+    /// The value of the `Humanoid.RigType` enum for this kind of rig.
+    fn enum_value(self) -> u32 {
+        match self {
+            RigType::R6 => 0,
+            RigType::R15 => 1,
+        }
+    }
+}
+
+/// A part of a rig: its name, its size, and where its center is when the
+/// character's feet are at the origin.
+struct RigPart(&'static str, (f32, f32, f32), (f32, f32));
+
+/// A Motor6D joining two parts of an R15 rig: its name, the names of the parts
+/// it joins, and where the joint is when the character's feet are at the
+/// origin.
+struct RigJoint(&'static str, &'static str, &'static str, (f32, f32));
+
+const R6_PARTS: &[RigPart] = &[
+    RigPart("HumanoidRootPart", (2.0, 2.0, 1.0), (0.0, 3.0)),
+    RigPart("Torso", (2.0, 2.0, 1.0), (0.0, 3.0)),
+    RigPart("Head", (2.0, 1.0, 1.0), (0.0, 4.5)),
+    RigPart("Left Arm", (1.0, 2.0, 1.0), (-1.5, 3.0)),
+    RigPart("Right Arm", (1.0, 2.0, 1.0), (1.5, 3.0)),
+    RigPart("Left Leg", (1.0, 2.0, 1.0), (-0.5, 1.0)),
+    RigPart("Right Leg", (1.0, 2.0, 1.0), (0.5, 1.0)),
+];
+
+/// Blocky R15 parts, proportioned so that the character takes up the same
+/// space as an R6 character.
+const R15_PARTS: &[RigPart] = &[
+    RigPart("HumanoidRootPart", (2.0, 2.0, 1.0), (0.0, 3.0)),
+    RigPart("LowerTorso", (2.0, 0.4, 1.0), (0.0, 2.2)),
+    RigPart("UpperTorso", (2.0, 1.6, 1.0), (0.0, 3.2)),
+    RigPart("Head", (2.0, 1.0, 1.0), (0.0, 4.5)),
+    RigPart("LeftUpperArm", (1.0, 0.8, 1.0), (-1.5, 3.6)),
+    RigPart("LeftLowerArm", (1.0, 0.8, 1.0), (-1.5, 2.8)),
+    RigPart("LeftHand", (1.0, 0.4, 1.0), (-1.5, 2.2)),
+    RigPart("RightUpperArm", (1.0, 0.8, 1.0), (1.5, 3.6)),
+    RigPart("RightLowerArm", (1.0, 0.8, 1.0), (1.5, 2.8)),
+    RigPart("RightHand", (1.0, 0.4, 1.0), (1.5, 2.2)),
+    RigPart("LeftUpperLeg", (1.0, 0.8, 1.0), (-0.5, 1.6)),
+    RigPart("LeftLowerLeg", (1.0, 0.8, 1.0), (-0.5, 0.8)),
+    RigPart("LeftFoot", (1.0, 0.4, 1.0), (-0.5, 0.2)),
+    RigPart("RightUpperLeg", (1.0, 0.8, 1.0), (0.5, 1.6)),
+    RigPart("RightLowerLeg", (1.0, 0.8, 1.0), (0.5, 0.8)),
+    RigPart("RightFoot", (1.0, 0.4, 1.0), (0.5, 0.2)),
+];
+
+const R15_JOINTS: &[RigJoint] = &[
+    RigJoint("Root", "HumanoidRootPart", "LowerTorso", (0.0, 2.2)),
+    RigJoint("Waist", "LowerTorso", "UpperTorso", (0.0, 2.4)),
+    RigJoint("Neck", "UpperTorso", "Head", (0.0, 4.0)),
+    RigJoint("LeftShoulder", "UpperTorso", "LeftUpperArm", (-1.5, 3.8)),
+    RigJoint("LeftElbow", "LeftUpperArm", "LeftLowerArm", (-1.5, 3.2)),
+    RigJoint("LeftWrist", "LeftLowerArm", "LeftHand", (-1.5, 2.4)),
+    RigJoint("RightShoulder", "UpperTorso", "RightUpperArm", (1.5, 3.8)),
+    RigJoint("RightElbow", "RightUpperArm", "RightLowerArm", (1.5, 3.2)),
+    RigJoint("RightWrist", "RightLowerArm", "RightHand", (1.5, 2.4)),
+    RigJoint("LeftHip", "LowerTorso", "LeftUpperLeg", (-0.5, 2.0)),
+    RigJoint("LeftKnee", "LeftUpperLeg", "LeftLowerLeg", (-0.5, 1.2)),
+    RigJoint("LeftAnkle", "LeftLowerLeg", "LeftFoot", (-0.5, 0.4)),
+    RigJoint("RightHip", "LowerTorso", "RightUpperLeg", (0.5, 2.0)),
+    RigJoint("RightKnee", "RightUpperLeg", "RightLowerLeg", (0.5, 1.2)),
+    RigJoint("RightAnkle", "RightLowerLeg", "RightFoot", (0.5, 0.4)),
+];
+
+/// Builds a character rig: a Model holding a Humanoid and the rig's parts,
+/// joined with Motor6Ds whose `C0` and `C1` line the parts up. The rig
+/// stands with its feet at the origin, and its `PrimaryPart` is its
+/// HumanoidRootPart.
+///
+/// R6 rigs use the same joints as Roblox's default characters. R15 rigs have
+/// blocky parts, with a pair of attachments named after each joint, like
+/// `LeftElbowRigAttachment`, that the joints are built from.
+///
+/// ```
+/// use rbx_dom_weak::WeakDom;
+/// use rbx_util::RigType;
+///
+/// let dom = WeakDom::new(rbx_util::build_rig(RigType::R15).with_name("Dummy"));
+/// assert_eq!(dom.root().children().len(), 17);
+/// ```
+pub fn build_rig(rig_type: RigType) -> InstanceBuilder {
+    let parts = match rig_type {
+        RigType::R6 => R6_PARTS,
+        RigType::R15 => R15_PARTS,
+    };
+
+    let mut builders: Vec<InstanceBuilder> = parts.iter().map(part_builder).collect();
+    let referents: Vec<Ref> = builders.iter().map(InstanceBuilder::referent).collect();
+    let index_of = |name: &str| parts.iter().position(|part| part.0 == name).unwrap();
+
+    match rig_type {
+        RigType::R6 => {
+            let torso = index_of("Torso");
+
+            // These are the joints of Roblox's default R6 character. The
+            // rotations turn each joint so that it swings forwards.
+            let rotate_left = Matrix3::new(
+                Vector3::new(0.0, 0.0, -1.0),
+                Vector3::new(0.0, 1.0, 0.0),
+                Vector3::new(1.0, 0.0, 0.0),
+            );
+            let rotate_right = Matrix3::new(
+                Vector3::new(0.0, 0.0, 1.0),
+                Vector3::new(0.0, 1.0, 0.0),
+                Vector3::new(-1.0, 0.0, 0.0),
+            );
+            let face_up = Matrix3::new(
+                Vector3::new(-1.0, 0.0, 0.0),
+                Vector3::new(0.0, 0.0, 1.0),
+                Vector3::new(0.0, 1.0, 0.0),
+            );
+
+            let joints = [
+                ("Neck", "Head", (0.0, 1.0), (0.0, -0.5), face_up),
+                (
+                    "Left Shoulder",
+                    "Left Arm",
+                    (-1.0, 0.5),
+                    (0.5, 0.5),
+                    rotate_left,
+                ),
+                (
+                    "Right Shoulder",
+                    "Right Arm",
+                    (1.0, 0.5),
+                    (-0.5, 0.5),
+                    rotate_right,
+                ),
+                (
+                    "Left Hip",
+                    "Left Leg",
+                    (-1.0, -1.0),
+                    (-0.5, 1.0),
+                    rotate_left,
+                ),
+                (
+                    "Right Hip",
+                    "Right Leg",
+                    (1.0, -1.0),
+                    (0.5, 1.0),
+                    rotate_right,
+                ),
+            ];
+
+            for (name, part1, c0, c1, orientation) in joints {
+                let part1 = index_of(part1);
+                let motor = motor(
+                    name,
+                    referents[torso],
+                    referents[part1],
+                    CFrame::new(Vector3::new(c0.0, c0.1, 0.0), orientation),
+                    CFrame::new(Vector3::new(c1.0, c1.1, 0.0), orientation),
+                );
+                builders[torso].add_child(motor);
+            }
+
+            let root = index_of("HumanoidRootPart");
+            let root_joint = CFrame::new(Vector3::new(0.0, 0.0, 0.0), face_up);
+            let motor = motor(
+                "RootJoint",
+                referents[root],
+                referents[torso],
+                root_joint,
+                root_joint,
+            );
+            builders[root].add_child(motor);
+        }
+        RigType::R15 => {
+            for &RigJoint(name, part0, part1, position) in R15_JOINTS {
+                let (part0, part1) = (index_of(part0), index_of(part1));
+                let attachment_name = format!("{}RigAttachment", name);
+
+                let c0 = offset_from(&parts[part0], position);
+                let c1 = offset_from(&parts[part1], position);
+
+                builders[part0].add_child(attachment(&attachment_name, c0));
+                builders[part1].add_child(attachment(&attachment_name, c1));
+                let motor = motor(name, referents[part0], referents[part1], c0, c1);
+                builders[part1].add_child(motor);
+            }
+        }
+    }
+
+    let root_part = referents[index_of("HumanoidRootPart")];
+    let humanoid = InstanceBuilder::new("Humanoid")
+        .with_property("RigType", Enum::from_u32(rig_type.enum_value()));
+
+    InstanceBuilder::new("Model")
+        .with_property("PrimaryPart", root_part)
+        .with_child(humanoid)
+        .with_children(builders)
+}
+
+fn part_builder(&RigPart(name, size, center): &RigPart) -> InstanceBuilder {
+    let (x, y, z) = size;
+    let (center_x, center_y) = center;
+
+    let builder = InstanceBuilder::new("Part")
+        .with_name(name)
+        .with_property("Size", Vector3::new(x, y, z))
+        .with_property(
+            "CFrame",
+            CFrame::new(Vector3::new(center_x, center_y, 0.0), Matrix3::identity()),
+        );
+
+    if name == "HumanoidRootPart" {
+        builder
+            .with_property("Transparency", 1.0f32)
+            .with_property("CanCollide", false)
+    } else {
+        builder
+    }
+}
+
+/// Converts a position with the character's feet at the origin to one
+/// relative to the center of the given part.
+fn offset_from(&RigPart(_, _, center): &RigPart, (x, y): (f32, f32)) -> CFrame {
+    CFrame::new(
+        Vector3::new(x - center.0, y - center.1, 0.0),
+        Matrix3::identity(),
+    )
+}
+
+fn attachment(name: &str, cframe: CFrame) -> InstanceBuilder {
+    InstanceBuilder::new("Attachment")
+        .with_name(name)
+        .with_property("CFrame", cframe)
+}
+
+fn motor(name: &str, part0: Ref, part1: Ref, c0: CFrame, c1: CFrame) -> InstanceBuilder {
+    InstanceBuilder::new("Motor6D")
+        .with_name(name)
+        .with_property("Part0", part0)
+        .with_property("Part1", part1)
+        .with_property("C0", c0)
+        .with_property("C1", c1)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::WeakDom;
+
+    use crate::path::descendants;
+
+    /// Checks that every Motor6D puts the point it joins in the same place in
+    /// both parts, which is what keeps a rig from flying apart. The parts of
+    /// a new rig aren't rotated, so only positions need adding up.
+    fn check_joints(rig_type: RigType) -> usize {
+        let dom = WeakDom::new(build_rig(rig_type));
+        let mut joints = 0;
+
+        for referent in descendants(&dom, dom.root_ref()) {
+            let motor = dom.get_by_ref(referent).unwrap();
+            if motor.class != "Motor6D" {
+                continue;
+            }
+
+            let world = |part: &str, offset: &str| {
+                let part = dom
+                    .get_by_ref(motor.get_typed::<Ref>(part).unwrap())
+                    .unwrap();
+                let center = part.get_typed::<CFrame>("CFrame").unwrap().position;
+                let offset = motor.get_typed::<CFrame>(offset).unwrap();
+
+                (center.x + offset.position.x, center.y + offset.position.y)
+            };
+
+            let (x0, y0) = world("Part0", "C0");
+            let (x1, y1) = world("Part1", "C1");
+            assert!(
+                (x0 - x1).abs() < 1e-5 && (y0 - y1).abs() < 1e-5,
+                "{} joins ({}, {}) to ({}, {})",
+                motor.name,
+                x0,
+                y0,
+                x1,
+                y1
+            );
+
+            joints += 1;
+        }
+
+        let humanoid = dom.get_by_ref(dom.root().children()[0]).unwrap();
+        assert_eq!(
+            humanoid.get_typed::<Enum>("RigType").unwrap(),
+            Enum::from_u32(rig_type.enum_value())
+        );
+
+        joints
+    }
+
+    #[test]
+    fn joints_line_up() {
+        assert_eq!(check_joints(RigType::R6), 6);
+        assert_eq!(check_joints(RigType::R15), 15);
+    }
+}
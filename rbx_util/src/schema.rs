@@ -0,0 +1,427 @@
+use rbx_dom_weak::{
+    types::{Enum, Ref, Variant},
+    WeakDom,
+};
+use rbx_reflection::{
+    DataType, PropertyKind, PropertyMigration, PropertySerialization, ReflectionDatabase,
+};
+
+use crate::path::{descendants, full_name};
+
+/// Something [`migrate_schema`] changed or couldn't carry over to the newer
+/// reflection database.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaIssue {
+    /// The referent of the instance the issue is about.
+    pub referent: Ref,
+
+    /// The instance's full name, like `Workspace.Model.Part`.
+    pub path: String,
+
+    /// What happened.
+    pub problem: SchemaProblem,
+}
+
+/// Describes a change that [`migrate_schema`] had to make, or couldn't make.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum SchemaProblem {
+    /// The instance's class no longer exists, so it was changed to the
+    /// closest superclass that does. Properties that only the old class had
+    /// were removed and reported separately.
+    ClassReplaced {
+        /// The class that no longer exists.
+        class: String,
+
+        /// The superclass the instance was changed to.
+        replacement: String,
+    },
+
+    /// The instance's class no longer exists, and neither do any of its
+    /// superclasses. The instance was left as it was.
+    ClassRemoved {
+        /// The class that no longer exists.
+        class: String,
+    },
+
+    /// The property no longer exists and was removed from the instance.
+    PropertyRemoved {
+        /// The name of the removed property.
+        property: String,
+
+        /// The value it had, so that it can be carried over by hand.
+        value: Variant,
+    },
+
+    /// The property's value is an enum item that no longer exists. The value
+    /// was left as it was.
+    EnumItemRemoved {
+        /// The name of the property.
+        property: String,
+
+        /// The name of the enum item, or its number if the older database
+        /// doesn't know it either.
+        item: String,
+    },
+
+    /// The older database said how to migrate the property to a new one, but
+    /// its value couldn't be converted. The property was left as it was.
+    MigrationFailed {
+        /// The name of the property.
+        property: String,
+
+        /// Why the value couldn't be converted.
+        message: String,
+    },
+}
+
+/// Upgrades the properties of every instance in the DOM from how the `from`
+/// reflection database describes them to how the `to` database does.
+///
+/// Renamed properties are moved to their new names, properties the older
+/// database says to migrate are converted, enum values are renumbered if the
+/// item they name now has a different number, and instances of removed
+/// classes fall back to their closest surviving superclass. Everything that
+/// had to be changed in a way that loses information, or couldn't be changed
+/// at all, is returned.
+///
+/// Properties and classes that neither database knows are left alone.
+pub fn migrate_schema(
+    dom: &mut WeakDom,
+    from: &ReflectionDatabase,
+    to: &ReflectionDatabase,
+) -> Vec<SchemaIssue> {
+    let mut issues = Vec::new();
+
+    for referent in descendants(dom, dom.root_ref()) {
+        let path = full_name(dom, referent);
+        let mut report = |problem| {
+            issues.push(SchemaIssue {
+                referent,
+                path: path.clone(),
+                problem,
+            })
+        };
+
+        let instance = dom.get_by_ref_mut(referent).unwrap();
+        let old_class = instance.class.clone();
+
+        if !to.classes.contains_key(old_class.as_str()) {
+            match replacement_class(&old_class, from, to) {
+                Some(replacement) => {
+                    report(SchemaProblem::ClassReplaced {
+                        class: old_class.clone(),
+                        replacement: replacement.to_owned(),
+                    });
+                    instance.class = replacement.to_owned();
+                }
+                None => {
+                    if from.classes.contains_key(old_class.as_str()) {
+                        report(SchemaProblem::ClassRemoved { class: old_class });
+                    }
+                    continue;
+                }
+            }
+        }
+
+        let class = instance.class.clone();
+        let mut names: Vec<String> = instance.properties.keys().cloned().collect();
+        names.sort_unstable();
+
+        for name in names {
+            let value = &instance.properties[&name];
+
+            if let Some(descriptors) = to.find_property_descriptors(&class, &name) {
+                let canonical = descriptors.canonical;
+
+                let value = match (&canonical.data_type, value) {
+                    (DataType::Enum(enum_name), Variant::Enum(value)) => {
+                        match renumber_enum(&old_class, &name, *value, enum_name, from, to) {
+                            Ok(value) => Variant::Enum(value),
+                            Err(item) => {
+                                report(SchemaProblem::EnumItemRemoved {
+                                    property: name.clone(),
+                                    item,
+                                });
+                                continue;
+                            }
+                        }
+                    }
+                    _ => value.clone(),
+                };
+
+                instance.properties.remove(&name);
+                instance
+                    .properties
+                    .insert(canonical.name.as_ref().to_owned(), value);
+                continue;
+            }
+
+            if from.find_property_descriptors(&old_class, &name).is_none() {
+                continue;
+            }
+
+            if let Some(migration) = migration_for(&old_class, &name, from) {
+                if to
+                    .find_property_descriptors(&class, &migration.new_property_name)
+                    .is_some()
+                {
+                    match migration.perform(value) {
+                        Ok(migrated) => {
+                            instance.properties.remove(&name);
+                            if !instance
+                                .properties
+                                .contains_key(&migration.new_property_name)
+                            {
+                                instance
+                                    .properties
+                                    .insert(migration.new_property_name.clone(), migrated);
+                            }
+                        }
+                        Err(err) => report(SchemaProblem::MigrationFailed {
+                            property: name.clone(),
+                            message: err.to_string(),
+                        }),
+                    }
+                    continue;
+                }
+            }
+
+            let value = instance.properties.remove(&name).unwrap();
+            report(SchemaProblem::PropertyRemoved {
+                property: name,
+                value,
+            });
+        }
+    }
+
+    issues
+}
+
+/// Finds the closest superclass of `class`, according to `from`, that still
+/// exists in `to`.
+fn replacement_class<'a>(
+    class: &str,
+    from: &ReflectionDatabase,
+    to: &'a ReflectionDatabase,
+) -> Option<&'a str> {
+    let mut current = from.classes.get(class)?;
+
+    loop {
+        let superclass = current.superclass.as_deref()?;
+        if let Some(descriptor) = to.classes.get(superclass) {
+            return Some(descriptor.name.as_ref());
+        }
+
+        current = from.classes.get(superclass)?;
+    }
+}
+
+/// Finds how the older database says to migrate a property, if it does.
+fn migration_for<'a>(
+    class: &str,
+    property: &str,
+    from: &'a ReflectionDatabase,
+) -> Option<&'a PropertyMigration> {
+    let mut current = from.classes.get(class)?;
+
+    loop {
+        if let Some(descriptor) = current.properties.get(property) {
+            return match &descriptor.kind {
+                PropertyKind::Canonical {
+                    serialization: PropertySerialization::Migrate(migration),
+                } => Some(migration),
+                _ => None,
+            };
+        }
+
+        current = from.classes.get(current.superclass.as_deref()?)?;
+    }
+}
+
+/// Gives an enum value the number that the newer database has for the item it
+/// named in the older one. Returns the item's name if the newer database
+/// doesn't have it.
+fn renumber_enum(
+    class: &str,
+    property: &str,
+    value: Enum,
+    enum_name: &str,
+    from: &ReflectionDatabase,
+    to: &ReflectionDatabase,
+) -> Result<Enum, String> {
+    let number = value.to_u32();
+
+    let old_enum_name = from
+        .find_property_descriptors(class, property)
+        .and_then(|descriptors| match &descriptors.canonical.data_type {
+            DataType::Enum(name) => Some(name.as_ref()),
+            _ => None,
+        })
+        .unwrap_or(enum_name);
+
+    let item = from
+        .enums
+        .get(old_enum_name)
+        .and_then(|descriptor| descriptor.items.iter().find(|(_, &item)| item == number))
+        .map(|(name, _)| name.as_ref());
+
+    let new_items = match to.enums.get(enum_name) {
+        Some(descriptor) => &descriptor.items,
+        None => return Ok(value),
+    };
+
+    match item {
+        Some(item) => match new_items.get(item) {
+            Some(&new_number) => Ok(Enum::from_u32(new_number)),
+            None => Err(item.to_owned()),
+        },
+        None if new_items.values().any(|&item| item == number) => Ok(value),
+        None => Err(number.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::types::{Color3, VariantType};
+    use rbx_dom_weak::InstanceBuilder;
+    use rbx_reflection::{ClassDescriptor, EnumDescriptor, PropertyDescriptor};
+
+    fn database(
+        classes: Vec<ClassDescriptor<'static>>,
+        enums: Vec<EnumDescriptor<'static>>,
+    ) -> ReflectionDatabase<'static> {
+        let mut database = ReflectionDatabase::new();
+        for class in classes {
+            database.classes.insert(class.name.clone(), class);
+        }
+        for descriptor in enums {
+            database.enums.insert(descriptor.name.clone(), descriptor);
+        }
+        database
+    }
+
+    fn class(
+        name: &'static str,
+        superclass: Option<&'static str>,
+        properties: Vec<PropertyDescriptor<'static>>,
+    ) -> ClassDescriptor<'static> {
+        let mut class = ClassDescriptor::new(name);
+        class.superclass = superclass.map(Into::into);
+        for property in properties {
+            class.properties.insert(property.name.clone(), property);
+        }
+        class
+    }
+
+    fn shape(items: &[(&'static str, u32)]) -> EnumDescriptor<'static> {
+        let mut descriptor = EnumDescriptor::new("Shape");
+        descriptor
+            .items
+            .extend(items.iter().map(|&(name, value)| (name.into(), value)));
+        descriptor
+    }
+
+    #[test]
+    fn migrates_between_versions() {
+        let mut alias = PropertyDescriptor::new("Colour", DataType::Value(VariantType::Color3));
+        alias.kind = PropertyKind::Alias {
+            alias_for: "Color".into(),
+        };
+
+        let from = database(
+            vec![
+                class("Instance", None, vec![]),
+                class(
+                    "Thing",
+                    Some("Instance"),
+                    vec![
+                        PropertyDescriptor::new("Colour", DataType::Value(VariantType::Color3)),
+                        PropertyDescriptor::new("Shape", DataType::Enum("Shape".into())),
+                        PropertyDescriptor::new("Legacy", DataType::Value(VariantType::Bool)),
+                    ],
+                ),
+                class("OldThing", Some("Thing"), vec![]),
+            ],
+            vec![shape(&[("Ball", 0), ("Block", 1), ("Wedge", 2)])],
+        );
+
+        let to = database(
+            vec![
+                class("Instance", None, vec![]),
+                class(
+                    "Thing",
+                    Some("Instance"),
+                    vec![
+                        PropertyDescriptor::new("Color", DataType::Value(VariantType::Color3)),
+                        alias,
+                        PropertyDescriptor::new("Shape", DataType::Enum("Shape".into())),
+                    ],
+                ),
+            ],
+            vec![shape(&[("Block", 0), ("Ball", 1)])],
+        );
+
+        let ball = InstanceBuilder::new("Thing")
+            .with_name("Ball")
+            .with_property("Colour", Color3::new(1.0, 0.0, 0.0))
+            .with_property("Shape", Enum::from_u32(0));
+        let ball_ref = ball.referent();
+        let wedge = InstanceBuilder::new("OldThing")
+            .with_name("Wedge")
+            .with_property("Shape", Enum::from_u32(2))
+            .with_property("Legacy", true);
+        let wedge_ref = wedge.referent();
+
+        let mut dom = WeakDom::new(
+            InstanceBuilder::new("Instance")
+                .with_child(ball)
+                .with_child(wedge),
+        );
+
+        let problems: Vec<_> = migrate_schema(&mut dom, &from, &to)
+            .into_iter()
+            .map(|issue| (issue.path, issue.problem))
+            .collect();
+
+        assert_eq!(
+            problems,
+            [
+                (
+                    "Wedge".to_owned(),
+                    SchemaProblem::ClassReplaced {
+                        class: "OldThing".to_owned(),
+                        replacement: "Thing".to_owned(),
+                    }
+                ),
+                (
+                    "Wedge".to_owned(),
+                    SchemaProblem::PropertyRemoved {
+                        property: "Legacy".to_owned(),
+                        value: Variant::Bool(true),
+                    }
+                ),
+                (
+                    "Wedge".to_owned(),
+                    SchemaProblem::EnumItemRemoved {
+                        property: "Shape".to_owned(),
+                        item: "Wedge".to_owned(),
+                    }
+                ),
+            ]
+        );
+
+        let ball = dom.get_by_ref(ball_ref).unwrap();
+        assert!(ball.properties.contains_key("Color"));
+        assert!(!ball.properties.contains_key("Colour"));
+        assert_eq!(
+            ball.properties.get("Shape"),
+            Some(&Variant::Enum(Enum::from_u32(1)))
+        );
+
+        let wedge = dom.get_by_ref(wedge_ref).unwrap();
+        assert_eq!(wedge.class, "Thing");
+    }
+}
@@ -0,0 +1,214 @@
+use std::collections::HashMap;
+
+use rbx_dom_weak::{
+    types::{Ref, Variant},
+    WeakDom,
+};
+
+use crate::path::{descendants, full_name, text_value};
+
+/// The classes whose `Source` property holds Luau code.
+pub(crate) const SCRIPT_CLASSES: &[&str] = &["Script", "LocalScript", "ModuleScript"];
+
+/// A script found by [`scripts`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptInfo {
+    /// The referent of the script.
+    pub referent: Ref,
+
+    /// The script's full name, like `ServerScriptService.Main`.
+    pub path: String,
+
+    /// The script's ClassName.
+    pub class: String,
+
+    /// The script's source. Scripts with no `Source` property have an empty
+    /// source.
+    pub source: String,
+
+    /// The metadata that tools have attached to the script with
+    /// [`WeakDom::metadata_mut`], like the file it was built from.
+    pub metadata: HashMap<String, String>,
+}
+
+/// A change to a script's source made by [`edit_scripts`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptEdit {
+    /// The referent of the script.
+    pub referent: Ref,
+
+    /// The script's full name at the time it was edited.
+    pub path: String,
+
+    /// The source before the edit.
+    pub old_source: String,
+
+    /// The source after the edit.
+    pub new_source: String,
+}
+
+/// Returns every Script, LocalScript, and ModuleScript in the DOM in
+/// depth-first order.
+pub fn scripts(dom: &WeakDom) -> Vec<ScriptInfo> {
+    descendants(dom, dom.root_ref())
+        .into_iter()
+        .filter_map(|referent| {
+            let instance = dom.get_by_ref(referent).unwrap();
+            if !SCRIPT_CLASSES.contains(&instance.class.as_str()) {
+                return None;
+            }
+
+            let source = instance
+                .properties
+                .get("Source")
+                .and_then(text_value)
+                .map(|source| source.into_owned())
+                .unwrap_or_default();
+
+            Some(ScriptInfo {
+                referent,
+                path: full_name(dom, referent),
+                class: instance.class.clone(),
+                source,
+                metadata: dom.metadata(referent).cloned().unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+/// Calls `edit` with every script in the DOM. Whenever it returns a new source
+/// that differs from the old one, the change is recorded.
+///
+/// Every script is visited before any change is applied, so `edit` always sees
+/// the original sources. The applied changes are returned in the order that
+/// the scripts were visited.
+///
+/// This is the place to run per-script passes like minifiers or license
+/// headers before a DOM is encoded. Passes can be chained by calling this once
+/// for each of them.
+///
+/// ```
+/// use rbx_dom_weak::{InstanceBuilder, WeakDom};
+///
+/// let mut dom = WeakDom::new(InstanceBuilder::new("DataModel").with_child(
+///     InstanceBuilder::new("ModuleScript").with_property("Source", "return 1  "),
+/// ));
+///
+/// let edits = rbx_util::edit_scripts(&mut dom, |script| {
+///     Some(script.source.trim_end().to_owned())
+/// });
+///
+/// assert_eq!(edits.len(), 1);
+/// assert_eq!(edits[0].new_source, "return 1");
+/// ```
+pub fn edit_scripts<F>(dom: &mut WeakDom, mut edit: F) -> Vec<ScriptEdit>
+where
+    F: FnMut(&ScriptInfo) -> Option<String>,
+{
+    let edits: Vec<ScriptEdit> = scripts(dom)
+        .into_iter()
+        .filter_map(|script| {
+            let new_source = edit(&script)?;
+            if new_source == script.source {
+                return None;
+            }
+
+            Some(ScriptEdit {
+                referent: script.referent,
+                path: script.path,
+                old_source: script.source,
+                new_source,
+            })
+        })
+        .collect();
+
+    apply_script_edits(dom, &edits);
+    edits
+}
+
+/// Sets the source of every script named by the given edits. Edits for
+/// instances that are no longer in the DOM are skipped.
+pub fn apply_script_edits(dom: &mut WeakDom, edits: &[ScriptEdit]) {
+    for edit in edits {
+        if let Some(instance) = dom.get_by_ref_mut(edit.referent) {
+            instance.properties.insert(
+                "Source".to_owned(),
+                Variant::String(edit.new_source.clone()),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::InstanceBuilder;
+
+    #[test]
+    fn edits_see_original_sources() {
+        let mut dom = WeakDom::new(
+            InstanceBuilder::new("DataModel").with_child(
+                InstanceBuilder::new("ServerScriptService")
+                    .with_child(
+                        InstanceBuilder::new("Script")
+                            .with_name("A")
+                            .with_property("Source", "print('a')"),
+                    )
+                    .with_child(
+                        InstanceBuilder::new("LocalScript")
+                            .with_name("B")
+                            .with_property("Source", "print('b')"),
+                    )
+                    .with_child(InstanceBuilder::new("Folder")),
+            ),
+        );
+
+        let mut seen = Vec::new();
+        let edits = edit_scripts(&mut dom, |script| {
+            seen.push((script.path.clone(), script.source.clone()));
+
+            if script.class == "Script" {
+                Some(script.source.replace("print", "warn"))
+            } else {
+                None
+            }
+        });
+
+        assert_eq!(
+            seen,
+            [
+                ("ServerScriptService.A".to_owned(), "print('a')".to_owned()),
+                ("ServerScriptService.B".to_owned(), "print('b')".to_owned()),
+            ]
+        );
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].old_source, "print('a')");
+
+        let sources: Vec<_> = scripts(&dom)
+            .into_iter()
+            .map(|script| script.source)
+            .collect();
+        assert_eq!(sources, ["warn('a')", "print('b')"]);
+    }
+
+    #[test]
+    fn edits_see_metadata() {
+        let module = InstanceBuilder::new("ModuleScript").with_property("Source", "return 1");
+        let module_ref = module.referent();
+
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel").with_child(module));
+        dom.metadata_mut(module_ref)
+            .insert("path".to_owned(), "src/Util.lua".to_owned());
+
+        edit_scripts(&mut dom, |script| {
+            let path = script.metadata.get("path")?;
+            Some(format!("-- Built from {}\n{}", path, script.source))
+        });
+
+        assert_eq!(
+            scripts(&dom)[0].source,
+            "-- Built from src/Util.lua\nreturn 1"
+        );
+    }
+}
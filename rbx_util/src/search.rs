@@ -0,0 +1,149 @@
+use rbx_dom_weak::{types::Ref, WeakDom};
+use regex::Regex;
+
+use crate::path::{descendants, full_name, text_value};
+
+/// Describes which instances [`search`] should return. Every criterion that is
+/// set must match; a query with no criteria matches every instance.
+///
+/// ```
+/// use rbx_dom_weak::{InstanceBuilder, WeakDom};
+/// use rbx_util::SearchQuery;
+/// use regex::Regex;
+///
+/// let dom = WeakDom::new(InstanceBuilder::new("DataModel").with_child(
+///     InstanceBuilder::new("Script")
+///         .with_name("Main")
+///         .with_property("Source", "local x = 1\nprint(x)"),
+/// ));
+///
+/// let query = SearchQuery {
+///     source_regex: Some(Regex::new(r"print\(")?),
+///     ..SearchQuery::default()
+/// };
+///
+/// let matches = rbx_util::search(&dom, &query);
+/// assert_eq!(matches[0].path, "Main");
+/// assert_eq!(matches[0].source_lines[0].line, 2);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SearchQuery {
+    /// Matches instances whose Name contains a match for this pattern.
+    pub name_regex: Option<Regex>,
+
+    /// Matches instances with exactly this ClassName.
+    pub class: Option<String>,
+
+    /// Matches instances with a `Source` property that contains a match for
+    /// this pattern.
+    pub source_regex: Option<Regex>,
+}
+
+/// An instance found by [`search`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchMatch {
+    /// The referent of the instance.
+    pub referent: Ref,
+
+    /// The instance's full name, like `Workspace.Model.Part`.
+    pub path: String,
+
+    /// The lines of the instance's `Source` that matched the query's
+    /// `source_regex`. Empty if the query had no `source_regex`.
+    pub source_lines: Vec<SourceLine>,
+}
+
+/// A line of a script's source that matched a [`SearchQuery`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLine {
+    /// The line number, starting from 1.
+    pub line: usize,
+
+    /// The contents of the line, without its line ending.
+    pub text: String,
+}
+
+/// Finds every instance in the DOM that matches the query, in depth-first
+/// order. The root of the DOM is never matched.
+pub fn search(dom: &WeakDom, query: &SearchQuery) -> Vec<SearchMatch> {
+    let mut matches = Vec::new();
+
+    for referent in descendants(dom, dom.root_ref()).into_iter().skip(1) {
+        let instance = dom.get_by_ref(referent).unwrap();
+
+        if let Some(class) = &query.class {
+            if &instance.class != class {
+                continue;
+            }
+        }
+
+        if let Some(name_regex) = &query.name_regex {
+            if !name_regex.is_match(&instance.name) {
+                continue;
+            }
+        }
+
+        let mut source_lines = Vec::new();
+        if let Some(source_regex) = &query.source_regex {
+            let source = match instance.properties.get("Source").and_then(text_value) {
+                Some(source) => source,
+                None => continue,
+            };
+
+            source_lines.extend(
+                source
+                    .lines()
+                    .enumerate()
+                    .filter(|(_, text)| source_regex.is_match(text))
+                    .map(|(index, text)| SourceLine {
+                        line: index + 1,
+                        text: text.to_owned(),
+                    }),
+            );
+
+            if source_lines.is_empty() {
+                continue;
+            }
+        }
+
+        matches.push(SearchMatch {
+            referent,
+            path: full_name(dom, referent),
+            source_lines,
+        });
+    }
+
+    matches
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::InstanceBuilder;
+
+    #[test]
+    fn name_and_class() {
+        let dom = WeakDom::new(
+            InstanceBuilder::new("DataModel").with_child(
+                InstanceBuilder::new("Workspace")
+                    .with_child(InstanceBuilder::new("Part").with_name("RedPart"))
+                    .with_child(InstanceBuilder::new("Model").with_name("RedModel"))
+                    .with_child(InstanceBuilder::new("Part").with_name("BluePart")),
+            ),
+        );
+
+        let query = SearchQuery {
+            name_regex: Some(Regex::new("^Red").unwrap()),
+            class: Some("Part".to_owned()),
+            ..SearchQuery::default()
+        };
+
+        let paths: Vec<_> = search(&dom, &query)
+            .into_iter()
+            .map(|found| found.path)
+            .collect();
+        assert_eq!(paths, ["Workspace.RedPart"]);
+    }
+}
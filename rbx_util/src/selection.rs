@@ -0,0 +1,243 @@
+use std::collections::BTreeMap;
+
+use rbx_dom_weak::{
+    types::{Ref, Variant},
+    WeakDom,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{class::is_a, EncodeError};
+
+/// Describes which instances and properties [`export_selection`] should
+/// write, like the names, classes, and positions of every part.
+///
+/// Every selected instance gets its Name and ClassName. Everything else has
+/// to be asked for. Selections can be read with serde, so a dashboard can
+/// send the one it wants:
+///
+/// ```
+/// let selection: rbx_util::Selection = serde_json::from_str(
+///     r#"{ "classes": ["BasePart"], "properties": ["CFrame"] }"#,
+/// )?;
+///
+/// assert_eq!(selection.classes, ["BasePart"]);
+/// assert!(selection.class_properties.is_empty());
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Selection {
+    /// Classes of instances to write, including their subclasses. Instances
+    /// of other classes are left out, and their selected descendants take
+    /// their place under their closest selected ancestor. If this is empty,
+    /// every instance is selected.
+    pub classes: Vec<String>,
+
+    /// Properties to write for every selected instance that has them.
+    pub properties: Vec<String>,
+
+    /// Properties to write only for instances of a class, including its
+    /// subclasses, like `Color` for `BasePart`.
+    pub class_properties: BTreeMap<String, Vec<String>>,
+
+    /// Whether to write the referent of each instance, so that `Ref`
+    /// properties can be matched up with the instances they point at.
+    pub referents: bool,
+
+    /// How many levels of descendants to look at below the instances being
+    /// exported, counting the levels of instances that were left out. With
+    /// no limit, every descendant is looked at.
+    pub max_depth: Option<usize>,
+}
+
+impl Selection {
+    fn selects(&self, class: &str) -> bool {
+        self.classes.is_empty() || self.classes.iter().any(|selected| is_a(class, selected))
+    }
+
+    fn selects_property(&self, class: &str, property: &str) -> bool {
+        self.properties.iter().any(|name| name == property)
+            || self.class_properties.iter().any(|(superclass, names)| {
+                names.iter().any(|name| name == property) && is_a(class, superclass)
+            })
+    }
+}
+
+/// An instance as written by [`export_selection`]. Anything that wasn't
+/// selected or is empty is left out, to keep the output small.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SelectedInstance<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    referent: Option<Ref>,
+    name: &'a str,
+    class_name: &'a str,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    properties: BTreeMap<&'a str, &'a Variant>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    children: Vec<SelectedInstance<'a>>,
+}
+
+/// Writes the parts of the given instances and their descendants that were
+/// asked for by `selection` as a JSON array, trimming everything else.
+///
+/// Property values are written using their serde representation, like
+/// [`encode_instance_json`][crate::encode_instance_json] does.
+///
+/// ```
+/// use rbx_dom_weak::{InstanceBuilder, WeakDom};
+/// use rbx_util::{export_selection, Selection};
+///
+/// let dom = WeakDom::new(InstanceBuilder::new("DataModel").with_child(
+///     InstanceBuilder::new("Model").with_child(InstanceBuilder::new("Part")),
+/// ));
+///
+/// let selection = Selection {
+///     classes: vec!["BasePart".to_owned()],
+///     ..Default::default()
+/// };
+///
+/// let json = export_selection(&dom, dom.root().children(), &selection)?;
+/// assert_eq!(json, r#"[{"name":"Part","className":"Part"}]"#);
+/// # Ok::<(), rbx_util::EncodeError>(())
+/// ```
+///
+/// ## Panics
+/// Panics if any of `referents` do not refer to an instance in the DOM.
+pub fn export_selection(
+    dom: &WeakDom,
+    referents: &[Ref],
+    selection: &Selection,
+) -> Result<String, EncodeError> {
+    let mut selected = Vec::new();
+
+    for &referent in referents {
+        assert!(
+            dom.get_by_ref(referent).is_some(),
+            "cannot export an instance that does not exist"
+        );
+
+        select(dom, referent, selection, 0, &mut selected);
+    }
+
+    Ok(serde_json::to_string(&selected)?)
+}
+
+/// Adds the instance to `output` if it's selected, or its selected
+/// descendants if it isn't.
+fn select<'a>(
+    dom: &'a WeakDom,
+    referent: Ref,
+    selection: &Selection,
+    depth: usize,
+    output: &mut Vec<SelectedInstance<'a>>,
+) {
+    let instance = dom.get_by_ref(referent).unwrap();
+
+    let mut children = Vec::new();
+    if selection
+        .max_depth
+        .is_none_or(|max_depth| depth < max_depth)
+    {
+        for &child in instance.children() {
+            select(dom, child, selection, depth + 1, &mut children);
+        }
+    }
+
+    if !selection.selects(&instance.class) {
+        output.append(&mut children);
+        return;
+    }
+
+    output.push(SelectedInstance {
+        referent: selection.referents.then_some(referent),
+        name: &instance.name,
+        class_name: &instance.class,
+        properties: instance
+            .properties
+            .iter()
+            .filter(|(key, _)| selection.selects_property(&instance.class, key))
+            .map(|(key, value)| (key.as_str(), value))
+            .collect(),
+        children,
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::{
+        types::{CFrame, Color3, Matrix3, Vector3},
+        InstanceBuilder,
+    };
+    use serde_json::json;
+
+    #[test]
+    fn export_selections() {
+        let cframe = CFrame::new(Vector3::new(1.0, 2.0, 3.0), Matrix3::identity());
+        let dom = WeakDom::new(
+            InstanceBuilder::new("DataModel").with_child(
+                InstanceBuilder::new("Folder").with_child(
+                    InstanceBuilder::new("Model")
+                        .with_name("Car")
+                        .with_child(
+                            InstanceBuilder::new("Part")
+                                .with_name("Body")
+                                .with_property("CFrame", cframe)
+                                .with_property("Color", Color3::new(1.0, 0.0, 0.0))
+                                .with_property("Anchored", true),
+                        )
+                        .with_child(
+                            InstanceBuilder::new("Script").with_property("Source", "print(1)"),
+                        ),
+                ),
+            ),
+        );
+        let folder = dom.root().children()[0];
+
+        let positions = Selection {
+            classes: vec!["BasePart".to_owned(), "Model".to_owned()],
+            properties: vec!["CFrame".to_owned()],
+            class_properties: [("Part".to_owned(), vec!["Color".to_owned()])].into(),
+            ..Default::default()
+        };
+        let json: serde_json::Value =
+            serde_json::from_str(&export_selection(&dom, &[folder], &positions).unwrap()).unwrap();
+        assert_eq!(
+            json,
+            json!([{
+                "name": "Car",
+                "className": "Model",
+                "children": [{
+                    "name": "Body",
+                    "className": "Part",
+                    "properties": {
+                        "CFrame": serde_json::to_value(Variant::CFrame(cframe)).unwrap(),
+                        "Color": serde_json::to_value(Variant::Color3(Color3::new(1.0, 0.0, 0.0))).unwrap(),
+                    },
+                }],
+            }])
+        );
+
+        // Levels that were left out still count towards max_depth, so Body is
+        // too deep to be looked at.
+        let shallow = Selection {
+            classes: vec!["BasePart".to_owned()],
+            max_depth: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(export_selection(&dom, &[folder], &shallow).unwrap(), "[]");
+
+        let outline = Selection {
+            referents: true,
+            max_depth: Some(1),
+            ..Default::default()
+        };
+        let json: serde_json::Value =
+            serde_json::from_str(&export_selection(&dom, &[folder], &outline).unwrap()).unwrap();
+        assert_eq!(json[0]["referent"], serde_json::to_value(folder).unwrap());
+        assert_eq!(json[0]["children"][0]["name"], "Car");
+        assert!(json[0]["children"][0].get("children").is_none());
+    }
+}
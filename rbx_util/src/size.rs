@@ -0,0 +1,49 @@
+use std::io::{self, Write};
+
+use rbx_dom_weak::{types::Ref, WeakDom};
+
+use crate::{to_writer, EncodeError, Format};
+
+/// Returns how many bytes the given instance and its descendants take up when
+/// saved as a model in the given format.
+///
+/// The model is encoded to find out, but the encoded bytes are thrown away as
+/// they're written instead of being kept in memory.
+///
+/// ```
+/// use rbx_dom_weak::{InstanceBuilder, WeakDom};
+/// use rbx_util::Format;
+///
+/// let dom = WeakDom::new(InstanceBuilder::new("DataModel").with_child(
+///     InstanceBuilder::new("ModuleScript").with_property("Source", "return {}"),
+/// ));
+/// let module = dom.root().children()[0];
+///
+/// let size = rbx_util::estimate_serialized_size(&dom, module, Format::Binary)?;
+/// assert_eq!(size, rbx_util::to_vec(&dom, &[module], Format::Binary)?.len() as u64);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn estimate_serialized_size(
+    dom: &WeakDom,
+    referent: Ref,
+    format: Format,
+) -> Result<u64, EncodeError> {
+    let mut counter = ByteCounter(0);
+    to_writer(&mut counter, dom, &[referent], format)?;
+
+    Ok(counter.0)
+}
+
+/// A writer that only counts the bytes written to it.
+struct ByteCounter(u64);
+
+impl Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
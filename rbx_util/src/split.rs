@@ -0,0 +1,333 @@
+use std::collections::HashMap;
+
+use rbx_dom_weak::{
+    types::{Attributes, Ref, Variant},
+    InstanceBuilder, WeakDom,
+};
+
+use crate::{error::SplitError, path::descendants};
+
+/// The attribute given to instances that are referred to from another model.
+pub(crate) const LINK_ID_ATTRIBUTE: &str = "RbxUtilLinkId";
+
+/// The attribute that marks an `Attributes` property as having been added by
+/// [`split`], so that [`join`] can remove it again without removing empty
+/// `Attributes` properties that were there to begin with.
+pub(crate) const LINK_ADDED_ATTRIBUTE: &str = "RbxUtilLinkAdded";
+
+/// The prefix of the attributes that replace `Ref` properties pointing into
+/// another model. The rest of the attribute's name is the property's name.
+pub(crate) const LINK_REF_PREFIX: &str = "RbxUtilLinkRef_";
+
+/// One of the models produced by [`split`].
+#[derive(Debug)]
+pub struct SplitModel {
+    /// The name of the instance at the top of this model, usually the name of
+    /// a service. Names are not guaranteed to be unique.
+    pub name: String,
+
+    /// A DOM whose root has the split instance as its only child, ready to be
+    /// encoded as a model.
+    pub dom: WeakDom,
+}
+
+/// Splits a place into one model per child of its root, which is usually one
+/// model per service.
+///
+/// Binary and XML models can't refer to instances outside of themselves, so
+/// `Ref` properties that point into a different model are replaced with
+/// attributes that [`join`] uses to restore them. An error is returned if an
+/// instance that needs those attributes has an `Attributes` property of
+/// another type, since it would have to be overwritten.
+pub fn split(mut dom: WeakDom) -> Result<Vec<SplitModel>, SplitError> {
+    let children = dom.root().children().to_vec();
+
+    let mut owner = HashMap::new();
+    for (index, &child) in children.iter().enumerate() {
+        for referent in descendants(&dom, child) {
+            owner.insert(referent, index);
+        }
+    }
+
+    let mut models: Vec<SplitModel> = children
+        .iter()
+        .map(|&child| {
+            let name = dom.get_by_ref(child).unwrap().name.clone();
+            let mut model = WeakDom::new(InstanceBuilder::new("DataModel"));
+            let model_root = model.root_ref();
+            dom.transfer(child, &mut model, model_root);
+
+            SplitModel { name, dom: model }
+        })
+        .collect();
+
+    let mut linked = Vec::new();
+
+    for (index, model) in models.iter_mut().enumerate() {
+        let root_child = model.dom.root().children()[0];
+
+        for referent in descendants(&model.dom, root_child) {
+            let instance = model.dom.get_by_ref(referent).unwrap();
+
+            let cross_links: Vec<(String, Ref)> = instance
+                .properties
+                .iter()
+                .filter_map(|(key, value)| match value {
+                    Variant::Ref(target) => match owner.get(target) {
+                        Some(&target_index) if target_index != index => {
+                            Some((key.clone(), *target))
+                        }
+                        _ => None,
+                    },
+                    _ => None,
+                })
+                .collect();
+
+            if cross_links.is_empty() {
+                continue;
+            }
+
+            let attributes = cross_links
+                .iter()
+                .map(|(key, target)| {
+                    (
+                        format!("{}{}", LINK_REF_PREFIX, key),
+                        Variant::String(target.to_string()),
+                    )
+                })
+                .collect();
+            add_attributes(&mut model.dom, referent, attributes)?;
+
+            let mut editor = model.dom.edit(referent);
+            for (key, target) in cross_links {
+                editor.set_property(key, Ref::none());
+                linked.push((owner[&target], target));
+            }
+        }
+    }
+
+    for (index, target) in linked {
+        let attributes = vec![(
+            LINK_ID_ATTRIBUTE.to_owned(),
+            Variant::String(target.to_string()),
+        )];
+        add_attributes(&mut models[index].dom, target, attributes)?;
+    }
+
+    Ok(models)
+}
+
+/// Joins models back into a single place, parenting every root instance of each
+/// model to a new DataModel.
+///
+/// `Ref` properties that [`split`] replaced with attributes are restored, and
+/// the attributes are removed, along with `Attributes` properties that
+/// `split` had to add.
+pub fn join<I>(models: I) -> WeakDom
+where
+    I: IntoIterator<Item = WeakDom>,
+{
+    let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+    let root_ref = dom.root_ref();
+
+    for mut model in models {
+        for child in model.root().children().to_vec() {
+            model.transfer(child, &mut dom, root_ref);
+        }
+    }
+
+    let all = descendants(&dom, root_ref);
+
+    let mut link_ids = HashMap::new();
+    for &referent in &all {
+        let instance = dom.get_by_ref(referent).unwrap();
+
+        if let Some(Variant::Attributes(attributes)) = instance.properties.get("Attributes") {
+            if let Some(id) = attributes.get(LINK_ID_ATTRIBUTE).cloned().and_then(link_id) {
+                link_ids.insert(id, referent);
+            }
+        }
+    }
+
+    for &referent in &all {
+        let instance = dom.get_by_ref(referent).unwrap();
+
+        let mut attributes = match instance.properties.get("Attributes") {
+            Some(Variant::Attributes(attributes)) if has_link_attributes(attributes) => {
+                attributes.clone()
+            }
+            _ => continue,
+        };
+
+        let links: Vec<(String, String)> = attributes
+            .iter()
+            .filter_map(|(key, value)| {
+                let property = key.strip_prefix(LINK_REF_PREFIX)?;
+                Some((property.to_owned(), link_id(value.clone())?))
+            })
+            .collect();
+
+        let mut editor = dom.edit(referent);
+        for (property, id) in links {
+            attributes.remove(format!("{}{}", LINK_REF_PREFIX, property).as_str());
+
+            if let Some(&target) = link_ids.get(&id) {
+                editor.set_property(property, Variant::Ref(target));
+            }
+        }
+
+        attributes.remove(LINK_ID_ATTRIBUTE);
+        let added = attributes.remove(LINK_ADDED_ATTRIBUTE).is_some();
+
+        if added && attributes.iter().next().is_none() {
+            editor.remove_property("Attributes");
+        } else {
+            editor.set_property("Attributes", attributes);
+        }
+    }
+
+    dom
+}
+
+/// Reads a link ID from an attribute. String attributes are decoded from
+/// binary files as `BinaryString`, so both types are accepted.
+pub(crate) fn link_id(value: Variant) -> Option<String> {
+    match value {
+        Variant::String(id) => Some(id),
+        Variant::BinaryString(id) => String::from_utf8(id.into_vec()).ok(),
+        _ => None,
+    }
+}
+
+fn has_link_attributes(attributes: &Attributes) -> bool {
+    attributes.iter().any(|(key, _)| {
+        key == LINK_ID_ATTRIBUTE || key == LINK_ADDED_ATTRIBUTE || key.starts_with(LINK_REF_PREFIX)
+    })
+}
+
+/// Adds attributes to an instance. If it has no `Attributes` property, one is
+/// added and marked so that [`join`] knows to remove it again.
+fn add_attributes(
+    dom: &mut WeakDom,
+    referent: Ref,
+    added: Vec<(String, Variant)>,
+) -> Result<(), SplitError> {
+    let instance = dom.get_by_ref(referent).unwrap();
+
+    let mut attributes = match instance.properties.get("Attributes") {
+        Some(Variant::Attributes(attributes)) => attributes.clone(),
+        Some(_) => return Err(SplitError::NotAttributes(referent)),
+        None => Attributes::new().with(LINK_ADDED_ATTRIBUTE, true),
+    };
+
+    for (key, value) in added {
+        attributes.insert(key, value);
+    }
+
+    dom.edit(referent).set_property("Attributes", attributes);
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::{from_slice, to_vec, Format};
+
+    #[test]
+    fn split_and_join() {
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let root_ref = dom.root_ref();
+        let workspace = dom.insert(root_ref, InstanceBuilder::new("Workspace"));
+        let part = dom.insert(workspace, InstanceBuilder::new("Part"));
+        let storage = dom.insert(root_ref, InstanceBuilder::new("ReplicatedStorage"));
+        dom.insert(
+            storage,
+            InstanceBuilder::new("ObjectValue").with_property("Value", part),
+        );
+
+        let models = split(dom).unwrap();
+        assert_eq!(models.len(), 2);
+        assert_eq!(models[0].name, "Workspace");
+        assert_eq!(models[1].name, "ReplicatedStorage");
+
+        // Round-trip every model through a file to make sure the links survive
+        // new referents being generated.
+        let decoded = models.iter().map(|model| {
+            let encoded = to_vec(&model.dom, model.dom.root().children(), Format::Binary).unwrap();
+            from_slice(&encoded).unwrap()
+        });
+        let joined = join(decoded);
+
+        let children = joined.root().children();
+        let workspace = joined.get_by_ref(children[0]).unwrap();
+        let part = workspace.children()[0];
+        assert!(!joined
+            .get_by_ref(part)
+            .unwrap()
+            .properties
+            .contains_key("Attributes"));
+
+        let storage = joined.get_by_ref(children[1]).unwrap();
+        let value = joined.get_by_ref(storage.children()[0]).unwrap();
+        assert_eq!(value.properties.get("Value"), Some(&Variant::Ref(part)));
+        assert!(!value.properties.contains_key("Attributes"));
+    }
+
+    #[test]
+    fn existing_attributes() {
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let root_ref = dom.root_ref();
+        let workspace = dom.insert(root_ref, InstanceBuilder::new("Workspace"));
+        let part = dom.insert(
+            workspace,
+            InstanceBuilder::new("Part").with_property("Attributes", Attributes::new()),
+        );
+        let storage = dom.insert(root_ref, InstanceBuilder::new("ReplicatedStorage"));
+        let value = dom.insert(
+            storage,
+            InstanceBuilder::new("ObjectValue")
+                .with_property("Value", part)
+                .with_property("Attributes", Attributes::new().with("Health", 100.0)),
+        );
+
+        let joined = join(split(dom).unwrap().into_iter().map(|model| model.dom));
+
+        // Attributes that were there before the split are kept as they were,
+        // even when they're empty.
+        assert_eq!(
+            joined
+                .get_by_ref(part)
+                .unwrap()
+                .properties
+                .get("Attributes"),
+            Some(&Variant::Attributes(Attributes::new()))
+        );
+        let value = joined.get_by_ref(value).unwrap();
+        assert_eq!(value.properties.get("Value"), Some(&Variant::Ref(part)));
+        assert_eq!(
+            value.properties.get("Attributes"),
+            Some(&Variant::Attributes(
+                Attributes::new().with("Health", 100.0)
+            ))
+        );
+    }
+
+    #[test]
+    fn wrong_attributes_type() {
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let root_ref = dom.root_ref();
+        let part = dom.insert(root_ref, InstanceBuilder::new("Part"));
+        let value = dom.insert(
+            root_ref,
+            InstanceBuilder::new("ObjectValue")
+                .with_property("Value", part)
+                .with_property("Attributes", "not attributes"),
+        );
+
+        assert!(matches!(
+            split(dom),
+            Err(SplitError::NotAttributes(referent)) if referent == value
+        ));
+    }
+}
@@ -0,0 +1,251 @@
+use std::fmt;
+
+use rbx_dom_weak::{
+    types::{Ref, Variant},
+    WeakDom,
+};
+
+use crate::{explorer::explorer_order, path::text_value};
+
+/// Formats instances as an indented outline of their names and classes.
+///
+/// ```
+/// use rbx_dom_weak::{InstanceBuilder, WeakDom};
+/// use rbx_util::TreePrinter;
+///
+/// let dom = WeakDom::new(InstanceBuilder::new("DataModel").with_child(
+///     InstanceBuilder::new("Model")
+///         .with_name("Car")
+///         .with_child(InstanceBuilder::new("Part").with_name("Wheel").with_property("Anchored", true)),
+/// ));
+///
+/// let printer = TreePrinter::new(&dom).property("Anchored");
+/// assert_eq!(
+///     printer.to_string(),
+///     "Car [Model]\n  Wheel [Part] Anchored: true\n",
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct TreePrinter<'a> {
+    dom: &'a WeakDom,
+    roots: Vec<Ref>,
+    max_depth: Option<usize>,
+    properties: Vec<String>,
+    explorer_order: bool,
+}
+
+impl<'a> TreePrinter<'a> {
+    /// Creates a printer for every child of the DOM's root, which for models
+    /// and places is everything in the file.
+    pub fn new(dom: &'a WeakDom) -> Self {
+        TreePrinter {
+            dom,
+            roots: dom.root().children().to_vec(),
+            max_depth: None,
+            properties: Vec::new(),
+            explorer_order: false,
+        }
+    }
+
+    /// Prints only the instance with the given referent and its descendants.
+    pub fn subtree(self, referent: Ref) -> Self {
+        TreePrinter {
+            roots: vec![referent],
+            ..self
+        }
+    }
+
+    /// Stops printing descendants that are more than `depth` levels below the
+    /// instances being printed. A depth of 0 prints only those instances.
+    pub fn max_depth(self, depth: usize) -> Self {
+        TreePrinter {
+            max_depth: Some(depth),
+            ..self
+        }
+    }
+
+    /// Prints the property with the given name next to every instance that
+    /// has it. Properties are printed in the order they were added.
+    pub fn property<S: Into<String>>(mut self, name: S) -> Self {
+        self.properties.push(name.into());
+        self
+    }
+
+    /// Sets whether siblings are printed in the order Studio's Explorer
+    /// shows them, using [`explorer_order`][crate::explorer_order], instead
+    /// of the order they have in the DOM.
+    pub fn explorer_order(self, explorer_order: bool) -> Self {
+        TreePrinter {
+            explorer_order,
+            ..self
+        }
+    }
+
+    /// Returns the given siblings in the order they should be printed.
+    fn ordered(&self, referents: &[Ref]) -> Vec<Ref> {
+        let mut referents = referents.to_vec();
+
+        if self.explorer_order {
+            // Referents that aren't in the DOM are printed last.
+            referents.sort_by(
+                |&a, &b| match (self.dom.get_by_ref(a), self.dom.get_by_ref(b)) {
+                    (Some(a), Some(b)) => explorer_order(a, b),
+                    (a, b) => b.is_some().cmp(&a.is_some()),
+                },
+            );
+        }
+
+        referents
+    }
+
+    fn fmt_instance(
+        &self,
+        formatter: &mut fmt::Formatter<'_>,
+        referent: Ref,
+        depth: usize,
+    ) -> fmt::Result {
+        let indent = "  ".repeat(depth);
+
+        let instance = match self.dom.get_by_ref(referent) {
+            Some(instance) => instance,
+            None => return writeln!(formatter, "{}<missing {}>", indent, referent),
+        };
+
+        write!(
+            formatter,
+            "{}{} [{}]",
+            indent, instance.name, instance.class
+        )?;
+
+        for name in &self.properties {
+            if let Some(value) = instance.properties.get(name) {
+                write!(formatter, " {}: ", name)?;
+                fmt_value(formatter, value)?;
+            }
+        }
+
+        writeln!(formatter)?;
+
+        let children = instance.children();
+        if self.max_depth.is_some_and(|max| depth >= max) {
+            if !children.is_empty() {
+                writeln!(formatter, "{}  ... {} more", indent, children.len())?;
+            }
+
+            return Ok(());
+        }
+
+        for child in self.ordered(children) {
+            self.fmt_instance(formatter, child, depth + 1)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes a property value compactly, falling back to its `Debug` form for
+/// types without an obvious short form.
+pub(crate) fn fmt_value(formatter: &mut fmt::Formatter<'_>, value: &Variant) -> fmt::Result {
+    if let Some(text) = text_value(value) {
+        return write!(formatter, "{:?}", text);
+    }
+
+    match value {
+        Variant::Bool(value) => write!(formatter, "{}", value),
+        Variant::Int32(value) => write!(formatter, "{}", value),
+        Variant::Int64(value) => write!(formatter, "{}", value),
+        Variant::Float32(value) => write!(formatter, "{}", value),
+        Variant::Float64(value) => write!(formatter, "{}", value),
+        Variant::Ref(value) => write!(formatter, "{}", value),
+        other => write!(formatter, "{:?}", other),
+    }
+}
+
+impl fmt::Display for TreePrinter<'_> {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for root in self.ordered(&self.roots) {
+            self.fmt_instance(formatter, root, 0)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::InstanceBuilder;
+
+    #[test]
+    fn depth_and_properties() {
+        let workspace = InstanceBuilder::new("Workspace").with_child(
+            InstanceBuilder::new("Model").with_name("House").with_child(
+                InstanceBuilder::new("Part")
+                    .with_name("Door")
+                    .with_property("Transparency", 0.5f32),
+            ),
+        );
+        let workspace_ref = workspace.referent();
+
+        let dom = WeakDom::new(
+            InstanceBuilder::new("DataModel")
+                .with_child(workspace)
+                .with_child(InstanceBuilder::new("Script").with_property("Source", "print(1)")),
+        );
+
+        let printer = TreePrinter::new(&dom)
+            .max_depth(1)
+            .property("Source")
+            .property("Transparency");
+        assert_eq!(
+            printer.to_string(),
+            "Workspace [Workspace]\n  \
+             House [Model]\n    \
+             ... 1 more\n\
+             Script [Script] Source: \"print(1)\"\n"
+        );
+
+        let printer = TreePrinter::new(&dom)
+            .subtree(workspace_ref)
+            .property("Transparency");
+        assert_eq!(
+            printer.to_string(),
+            "Workspace [Workspace]\n  \
+             House [Model]\n    \
+             Door [Part] Transparency: 0.5\n"
+        );
+    }
+
+    #[test]
+    fn explorer_order() {
+        let dom = WeakDom::new(
+            InstanceBuilder::new("DataModel")
+                .with_child(InstanceBuilder::new("Folder").with_name("b"))
+                .with_child(
+                    InstanceBuilder::new("Workspace")
+                        .with_child(InstanceBuilder::new("Part").with_name("Zed"))
+                        .with_child(InstanceBuilder::new("Model").with_name("alpha"))
+                        .with_child(InstanceBuilder::new("Terrain"))
+                        .with_child(InstanceBuilder::new("Camera")),
+                )
+                .with_child(InstanceBuilder::new("Folder").with_name("A"))
+                .with_child(InstanceBuilder::new("ReplicatedStorage"))
+                .with_child(InstanceBuilder::new("Players")),
+        );
+
+        let printer = TreePrinter::new(&dom).explorer_order(true);
+        assert_eq!(
+            printer.to_string(),
+            "Workspace [Workspace]\n  \
+             Camera [Camera]\n  \
+             Terrain [Terrain]\n  \
+             alpha [Model]\n  \
+             Zed [Part]\n\
+             Players [Players]\n\
+             ReplicatedStorage [ReplicatedStorage]\n\
+             A [Folder]\n\
+             b [Folder]\n"
+        );
+    }
+}
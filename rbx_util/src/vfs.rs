@@ -0,0 +1,208 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    fs,
+    hash::{Hash, Hasher},
+    io::{self, Read, Seek},
+    path::{Component, Path, PathBuf},
+};
+
+use crate::{
+    archive::{read_directory, read_entry, ZipEntry},
+    error::ArchiveError,
+};
+
+/// Where [`ProjectWatcher`](crate::ProjectWatcher) reads its files from.
+///
+/// [`DirectoryVfs`] reads from the file system, [`ArchiveVfs`] from the
+/// entries of a zip file and [`MemoryVfs`] from files kept in memory, which
+/// is useful for tests and for editors that haven't saved their buffers yet.
+pub trait VfsProvider {
+    /// Returns a number that changes whenever the file at `path` does, or
+    /// `None` if there's no file there. Files are only read again when their
+    /// stamp changes.
+    fn stamp(&self, path: &Path) -> Option<u64>;
+
+    /// Reads the whole file at `path`.
+    fn read(&mut self, path: &Path) -> io::Result<Vec<u8>>;
+}
+
+/// Reads files from the file system, relative to a root directory.
+///
+/// Files are stamped with their modification time and size, so nothing
+/// platform specific is needed to notice that they changed.
+#[derive(Debug, Clone, Default)]
+pub struct DirectoryVfs {
+    root: PathBuf,
+}
+
+impl DirectoryVfs {
+    /// Creates a provider that resolves relative paths against `root`.
+    /// Absolute paths are read as they are.
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        DirectoryVfs { root: root.into() }
+    }
+}
+
+impl VfsProvider for DirectoryVfs {
+    fn stamp(&self, path: &Path) -> Option<u64> {
+        let metadata = fs::metadata(self.root.join(path)).ok()?;
+
+        let mut hasher = DefaultHasher::new();
+        metadata.modified().ok()?.hash(&mut hasher);
+        metadata.len().hash(&mut hasher);
+        Some(hasher.finish())
+    }
+
+    fn read(&mut self, path: &Path) -> io::Result<Vec<u8>> {
+        fs_err::read(self.root.join(path))
+    }
+}
+
+/// Keeps files in memory. Every file written gets a new stamp, even if its
+/// contents didn't change.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryVfs {
+    files: HashMap<PathBuf, (u64, Vec<u8>)>,
+    version: u64,
+}
+
+impl MemoryVfs {
+    /// Creates a provider without any files.
+    pub fn new() -> Self {
+        MemoryVfs::default()
+    }
+
+    /// Writes a file, replacing the one at `path` if there is one.
+    pub fn write<P: Into<PathBuf>, C: Into<Vec<u8>>>(&mut self, path: P, contents: C) {
+        self.version += 1;
+        self.files
+            .insert(path.into(), (self.version, contents.into()));
+    }
+
+    /// Removes the file at `path`, returning its contents if there was one.
+    pub fn remove(&mut self, path: &Path) -> Option<Vec<u8>> {
+        self.files.remove(path).map(|(_, contents)| contents)
+    }
+}
+
+impl VfsProvider for MemoryVfs {
+    fn stamp(&self, path: &Path) -> Option<u64> {
+        self.files.get(path).map(|(version, _)| *version)
+    }
+
+    fn read(&mut self, path: &Path) -> io::Result<Vec<u8>> {
+        self.files
+            .get(path)
+            .map(|(_, contents)| contents.clone())
+            .ok_or_else(|| not_found(path))
+    }
+}
+
+/// Reads files from the entries of a zip file, like a [`PlaceArchive`] or an
+/// exported project. Paths are matched against entry names with `/` between
+/// their components.
+///
+/// Zip files aren't expected to change while they're open, so files are
+/// stamped with their checksums.
+///
+/// [`PlaceArchive`]: crate::PlaceArchive
+#[derive(Debug)]
+pub struct ArchiveVfs<R> {
+    reader: R,
+    entries: HashMap<String, ZipEntry>,
+}
+
+impl<R: Read + Seek> ArchiveVfs<R> {
+    /// Reads the zip file's central directory. Entries are read when they're
+    /// mounted or change.
+    pub fn new(mut reader: R) -> Result<Self, ArchiveError> {
+        let entries = read_directory(&mut reader)?.into_iter().collect();
+        Ok(ArchiveVfs { reader, entries })
+    }
+
+    /// Returns the reader, leaving the entries behind.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    fn entry(&self, path: &Path) -> Option<(String, &ZipEntry)> {
+        let mut name = String::new();
+        for component in path.components() {
+            match component {
+                Component::Normal(part) => {
+                    if !name.is_empty() {
+                        name.push('/');
+                    }
+                    name.push_str(part.to_str()?);
+                }
+                Component::CurDir => {}
+                _ => return None,
+            }
+        }
+
+        let entry = self.entries.get(&name)?;
+        Some((name, entry))
+    }
+}
+
+impl<R: Read + Seek> VfsProvider for ArchiveVfs<R> {
+    fn stamp(&self, path: &Path) -> Option<u64> {
+        self.entry(path).map(|(_, entry)| u64::from(entry.crc))
+    }
+
+    fn read(&mut self, path: &Path) -> io::Result<Vec<u8>> {
+        let (name, entry) = self.entry(path).ok_or_else(|| not_found(path))?;
+        let entry = entry.clone();
+
+        read_entry(&mut self.reader, &name, &entry).map_err(|err| match err {
+            ArchiveError::Io(err) => err,
+            err => io::Error::new(io::ErrorKind::InvalidData, err),
+        })
+    }
+}
+
+fn not_found(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("no file at {}", path.display()),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::io::Cursor;
+
+    use rbx_dom_weak::{InstanceBuilder, WeakDom};
+
+    use crate::PlaceArchive;
+
+    #[test]
+    fn archive_entries() {
+        let dom = WeakDom::new(
+            InstanceBuilder::new("DataModel")
+                .with_child(InstanceBuilder::new("Workspace"))
+                .with_child(InstanceBuilder::new("ReplicatedStorage")),
+        );
+        let archive = PlaceArchive::from_dom(dom).unwrap();
+        let path = archive
+            .find_fragment("ReplicatedStorage")
+            .unwrap()
+            .to_owned();
+
+        let mut vfs = ArchiveVfs::new(Cursor::new(archive.to_vec().unwrap())).unwrap();
+        let stamp = vfs.stamp(Path::new(&path));
+        assert!(stamp.is_some());
+        assert_eq!(stamp, vfs.stamp(&Path::new(".").join(&path)));
+        assert_eq!(vfs.stamp(Path::new("missing.rbxm")), None);
+
+        let contents = vfs.read(Path::new(&path)).unwrap();
+        let storage = crate::from_slice(&contents).unwrap();
+        let top = storage.get_by_ref(storage.root().children()[0]).unwrap();
+        assert_eq!(top.name, "ReplicatedStorage");
+
+        let err = vfs.read(Path::new("missing.rbxm")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+}
@@ -0,0 +1,408 @@
+use std::path::{Path, PathBuf};
+
+use rbx_dom_weak::{types::Ref, WeakDom};
+
+use crate::{
+    error::WatchError,
+    from_slice,
+    vfs::{DirectoryVfs, VfsProvider},
+    DecodeError, PlaceDelta,
+};
+
+/// Keeps a tree in sync with model files on disk, for tools that live-sync a
+/// project into Roblox Studio or another running copy of the tree.
+///
+/// Files are mounted under instances of the tree with
+/// [`ProjectWatcher::mount`], which puts the file's top-level instances there.
+/// [`ProjectWatcher::poll`] then reads only the files that changed since they
+/// were last read, replaces their instances, and returns the
+/// [`PlaceDelta`] from the tree before to the tree after, ready to be sent to
+/// whatever is syncing from it. Files that are deleted leave their instances
+/// out until they come back.
+///
+/// Files are read through a [`VfsProvider`], which also stamps them so the
+/// watcher can tell which ones changed. [`ProjectWatcher::new`] reads them
+/// from disk with a [`DirectoryVfs`], checking their modification time and
+/// size, so the watcher doesn't need anything platform specific and can be
+/// polled from any loop or timer. [`ProjectWatcher::with_vfs`] reads them
+/// from somewhere else, like a zip file or files kept in memory.
+///
+/// ```no_run
+/// use std::{thread, time::Duration};
+///
+/// use rbx_dom_weak::{InstanceBuilder, WeakDom};
+/// use rbx_util::ProjectWatcher;
+///
+/// let storage = InstanceBuilder::new("ReplicatedStorage");
+/// let storage_ref = storage.referent();
+/// let dom = WeakDom::new(InstanceBuilder::new("DataModel").with_child(storage));
+///
+/// let mut watcher = ProjectWatcher::new(dom);
+/// watcher.mount("src/Shared.rbxmx", storage_ref)?;
+///
+/// loop {
+///     if let Some(delta) = watcher.poll()? {
+///         println!("sending {} bytes", delta.to_vec().len());
+///     }
+///
+///     thread::sleep(Duration::from_millis(200));
+/// }
+/// # Ok::<(), rbx_util::WatchError>(())
+/// ```
+#[derive(Debug)]
+pub struct ProjectWatcher<V = DirectoryVfs> {
+    dom: WeakDom,
+    vfs: V,
+    mounts: Vec<Mount>,
+}
+
+#[derive(Debug)]
+struct Mount {
+    path: PathBuf,
+    parent: Ref,
+
+    /// The file's stamp when it was last read, or `None` if it didn't exist.
+    stamp: Option<u64>,
+
+    /// The top-level instances of the file in the tree, in order.
+    instances: Vec<Ref>,
+}
+
+impl ProjectWatcher {
+    /// Creates a watcher for the given tree that reads files from disk,
+    /// without any files mounted yet.
+    pub fn new(dom: WeakDom) -> Self {
+        ProjectWatcher::with_vfs(dom, DirectoryVfs::default())
+    }
+}
+
+impl<V: VfsProvider> ProjectWatcher<V> {
+    /// Creates a watcher for the given tree that reads files from `vfs`,
+    /// without any files mounted yet.
+    pub fn with_vfs(dom: WeakDom, vfs: V) -> Self {
+        ProjectWatcher {
+            dom,
+            vfs,
+            mounts: Vec::new(),
+        }
+    }
+
+    /// Returns the provider files are read from.
+    pub fn vfs(&self) -> &V {
+        &self.vfs
+    }
+
+    /// Returns the provider files are read from, so files kept in memory
+    /// can be changed between polls.
+    pub fn vfs_mut(&mut self) -> &mut V {
+        &mut self.vfs
+    }
+
+    /// Returns the tree, with the files as they were when they were last
+    /// read.
+    pub fn dom(&self) -> &WeakDom {
+        &self.dom
+    }
+
+    /// Returns the tree, leaving the files behind.
+    pub fn into_dom(self) -> WeakDom {
+        self.dom
+    }
+
+    /// Reads the model file at `path` and puts its top-level instances under
+    /// the instance `parent`, where they're kept up to date by
+    /// [`ProjectWatcher::poll`].
+    ///
+    /// `parent` can't be one of the instances of an already mounted file, or
+    /// a descendant of one, since those are replaced whenever that file
+    /// changes.
+    pub fn mount<P: Into<PathBuf>>(&mut self, path: P, parent: Ref) -> Result<(), WatchError> {
+        let mut ancestor = parent;
+        while let Some(instance) = self.dom.get_by_ref(ancestor) {
+            if let Some(mount) = self
+                .mounts
+                .iter()
+                .find(|mount| mount.instances.contains(&ancestor))
+            {
+                return Err(WatchError::ParentInMount {
+                    parent,
+                    path: mount.path.clone(),
+                });
+            }
+
+            ancestor = instance.parent();
+        }
+
+        if self.dom.get_by_ref(parent).is_none() {
+            return Err(WatchError::ParentNotFound(parent));
+        }
+
+        let path = path.into();
+        let stamp = self.vfs.stamp(&path);
+        let file = read(&mut self.vfs, &path)?;
+
+        let mut mount = Mount {
+            path,
+            parent,
+            stamp,
+            instances: Vec::new(),
+        };
+        replace(&mut self.dom, &mut mount, Some(file))?;
+        self.mounts.push(mount);
+
+        Ok(())
+    }
+
+    /// Reads the mounted files that changed since they were last read and
+    /// replaces their instances in the tree. Returns the delta that turns the
+    /// tree from before into the tree after, or `None` if no files changed.
+    ///
+    /// If any of the files can't be read, the tree is left as it was.
+    pub fn poll(&mut self) -> Result<Option<PlaceDelta>, WatchError> {
+        let mut changed = Vec::new();
+        for (index, mount) in self.mounts.iter().enumerate() {
+            let stamp = self.vfs.stamp(&mount.path);
+            if stamp == mount.stamp {
+                continue;
+            }
+
+            if self.dom.get_by_ref(mount.parent).is_none() {
+                return Err(WatchError::ParentNotFound(mount.parent));
+            }
+
+            let file = match stamp {
+                Some(_) => Some(read(&mut self.vfs, &mount.path)?),
+                None => None,
+            };
+            changed.push((index, stamp, file));
+        }
+
+        if changed.is_empty() {
+            return Ok(None);
+        }
+
+        let before = self.dom.clone();
+        for (index, stamp, file) in changed {
+            let mount = &mut self.mounts[index];
+            mount.stamp = stamp;
+            replace(&mut self.dom, mount, file)?;
+        }
+
+        Ok(Some(PlaceDelta::between(&before, &self.dom)?))
+    }
+}
+
+fn read<V: VfsProvider>(vfs: &mut V, path: &Path) -> Result<WeakDom, WatchError> {
+    vfs.read(path)
+        .map_err(DecodeError::from)
+        .and_then(|contents| from_slice(&contents))
+        .map_err(|source| WatchError::Read {
+            path: path.to_owned(),
+            source,
+        })
+}
+
+/// Replaces the instances of a mounted file with the top-level instances of
+/// `file`, keeping them in the same place among their siblings.
+fn replace(dom: &mut WeakDom, mount: &mut Mount, file: Option<WeakDom>) -> Result<(), WatchError> {
+    // Siblings after the file's instances are moved back behind the new ones,
+    // since instances can only be added at the end.
+    let siblings = dom
+        .get_by_ref(mount.parent)
+        .ok_or(WatchError::ParentNotFound(mount.parent))?
+        .children();
+    let later: Vec<Ref> = match siblings
+        .iter()
+        .position(|child| mount.instances.contains(child))
+    {
+        Some(start) => siblings[start..]
+            .iter()
+            .filter(|child| !mount.instances.contains(child))
+            .copied()
+            .collect(),
+        None => Vec::new(),
+    };
+
+    for referent in mount.instances.drain(..) {
+        dom.destroy(referent);
+    }
+
+    if let Some(mut file) = file {
+        for child in file.root().children().to_vec() {
+            let referent = file.transfer(child, dom, mount.parent);
+            mount.instances.push(referent);
+        }
+    }
+
+    for referent in later {
+        dom.transfer_within(referent, mount.parent);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::{
+        fs::{self, File},
+        time::{Duration, SystemTime},
+    };
+
+    use rbx_dom_weak::InstanceBuilder;
+
+    use crate::{to_vec, vfs::MemoryVfs, DeltaPrinter, Format};
+
+    fn model(names: &[&str]) -> Vec<u8> {
+        let mut builder = InstanceBuilder::new("Folder");
+        for name in names {
+            builder = builder.with_child(InstanceBuilder::new("ModuleScript").with_name(*name));
+        }
+
+        let dom = WeakDom::new(builder);
+        to_vec(&dom, dom.root().children(), Format::Xml).unwrap()
+    }
+
+    fn write_model(path: &Path, names: &[&str], modified: SystemTime) {
+        fs::write(path, model(names)).unwrap();
+        File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+    }
+
+    #[test]
+    fn poll() {
+        let directory = std::env::temp_dir().join(format!("rbx_util-watch-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let first = directory.join("First.rbxmx");
+        let second = directory.join("Second.rbxmx");
+
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        write_model(&first, &["A", "B"], start);
+        write_model(&second, &["C"], start);
+
+        let storage = InstanceBuilder::new("ReplicatedStorage");
+        let storage_ref = storage.referent();
+        let dom = WeakDom::new(InstanceBuilder::new("DataModel").with_child(storage));
+
+        let mut watcher = ProjectWatcher::new(dom);
+        watcher.mount(&first, storage_ref).unwrap();
+        watcher.mount(&second, storage_ref).unwrap();
+
+        let names = |watcher: &ProjectWatcher| -> Vec<String> {
+            let dom = watcher.dom();
+            let storage = dom.get_by_ref(storage_ref).unwrap();
+            storage
+                .children()
+                .iter()
+                .map(|&child| dom.get_by_ref(child).unwrap().name.clone())
+                .collect()
+        };
+
+        assert_eq!(names(&watcher), ["A", "B", "C"]);
+        assert!(watcher.poll().unwrap().is_none());
+
+        // Only the file that changed is read again, and its instances stay
+        // in front of the other file's.
+        let before = watcher.dom().clone();
+        write_model(&first, &["A", "D"], start + Duration::from_secs(1));
+        let delta = watcher.poll().unwrap().unwrap();
+        assert_eq!(names(&watcher), ["A", "D", "C"]);
+        assert_eq!(
+            DeltaPrinter::new(&delta, &before).unwrap().to_string(),
+            "~ ReplicatedStorage.D.Name: \"B\" -> \"D\"\n"
+        );
+        assert_eq!(
+            delta
+                .apply(&before)
+                .unwrap()
+                .semantically_equals(watcher.dom(), &Default::default()),
+            Ok(true)
+        );
+
+        fs::remove_file(&second).unwrap();
+        assert!(watcher.poll().unwrap().is_some());
+        assert_eq!(names(&watcher), ["A", "D"]);
+
+        // A file that can't be read leaves the tree alone.
+        fs::write(&first, "not a model").unwrap();
+        assert!(matches!(watcher.poll(), Err(WatchError::Read { .. })));
+        assert_eq!(names(&watcher), ["A", "D"]);
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn nested_mount() {
+        let directory =
+            std::env::temp_dir().join(format!("rbx_util-watch-nested-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let outer = directory.join("Outer.rbxmx");
+        let inner = directory.join("Inner.rbxmx");
+
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        write_model(&outer, &["A"], start);
+        write_model(&inner, &["B"], start);
+
+        let storage = InstanceBuilder::new("ReplicatedStorage");
+        let storage_ref = storage.referent();
+        let dom = WeakDom::new(InstanceBuilder::new("DataModel").with_child(storage));
+
+        let mut watcher = ProjectWatcher::new(dom);
+        watcher.mount(&outer, storage_ref).unwrap();
+
+        // Mounting under an instance of the other file, or under one of its
+        // descendants, would lose the inner file when the outer one changes.
+        let a = watcher.dom().get_by_ref(storage_ref).unwrap().children()[0];
+        assert!(matches!(
+            watcher.mount(&inner, a),
+            Err(WatchError::ParentInMount { parent, ref path }) if parent == a && *path == outer
+        ));
+        assert!(matches!(
+            watcher.mount(&inner, Ref::new()),
+            Err(WatchError::ParentNotFound(_))
+        ));
+
+        write_model(&outer, &["C"], start + Duration::from_secs(1));
+        watcher.poll().unwrap().unwrap();
+        let storage = watcher.dom().get_by_ref(storage_ref).unwrap();
+        assert_eq!(storage.children().len(), 1);
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn memory_vfs() {
+        let mut vfs = MemoryVfs::new();
+        vfs.write("Shared.rbxmx", model(&["A"]));
+
+        let storage = InstanceBuilder::new("ReplicatedStorage");
+        let storage_ref = storage.referent();
+        let dom = WeakDom::new(InstanceBuilder::new("DataModel").with_child(storage));
+
+        let mut watcher = ProjectWatcher::with_vfs(dom, vfs);
+        watcher.mount("Shared.rbxmx", storage_ref).unwrap();
+        assert!(watcher.poll().unwrap().is_none());
+
+        watcher.vfs_mut().write("Shared.rbxmx", model(&["B"]));
+        assert!(watcher.poll().unwrap().is_some());
+
+        let storage = watcher.dom().get_by_ref(storage_ref).unwrap();
+        let child = watcher.dom().get_by_ref(storage.children()[0]).unwrap();
+        assert_eq!(child.name, "B");
+
+        watcher.vfs_mut().remove(Path::new("Shared.rbxmx"));
+        assert!(watcher.poll().unwrap().is_some());
+        let storage = watcher.dom().get_by_ref(storage_ref).unwrap();
+        assert!(storage.children().is_empty());
+
+        assert!(matches!(
+            watcher.mount("Missing.rbxmx", storage_ref),
+            Err(WatchError::Read { .. })
+        ));
+    }
+}
@@ -1,6 +1,9 @@
-use std::io::{Read, Write};
+use std::{
+    fmt,
+    io::{Read, Write},
+};
 
-use rbx_reflection::{PropertyDescriptor, PropertyKind, PropertySerialization};
+use rbx_reflection::{PropertyDescriptor, ReflectionDatabase};
 
 use crate::{
     deserializer_core::XmlEventReader,
@@ -39,109 +42,47 @@ pub trait XmlType: Sized {
     }
 }
 
-pub fn find_canonical_property_descriptor(
-    class_name: &str,
-    property_name: &str,
-) -> Option<&'static PropertyDescriptor<'static>> {
-    find_property_descriptors(class_name, property_name).map(|(canonical, _serialized)| canonical)
+/// The reflection database chosen through `DecodeOptions` or
+/// `EncodeOptions`. Only its version is shown when debug printing, since the
+/// whole database is very large.
+#[derive(Clone, Copy)]
+pub struct Database<'db>(pub &'db ReflectionDatabase<'db>);
+
+impl Default for Database<'_> {
+    fn default() -> Self {
+        Database(rbx_reflection_database::get())
+    }
 }
 
-pub fn find_serialized_property_descriptor(
-    class_name: &str,
-    property_name: &str,
-) -> Option<&'static PropertyDescriptor<'static>> {
-    find_property_descriptors(class_name, property_name).map(|(_canonical, serialized)| serialized)
+impl fmt::Debug for Database<'_> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "ReflectionDatabase({:?})", self.0.version)
+    }
 }
 
-/// Find both the canonical and serialized property descriptors for a given
-/// class and property name pair. These might be the same descriptor!
-fn find_property_descriptors(
+/// Finds the canonical property descriptor for a property that is being read
+/// from a file. Properties that don't serialize have none.
+pub fn find_canonical_property_descriptor<'db>(
+    database: Database<'db>,
     class_name: &str,
     property_name: &str,
-) -> Option<(
-    &'static PropertyDescriptor<'static>,
-    &'static PropertyDescriptor<'static>,
-)> {
-    let class_descriptor = rbx_reflection_database::get().classes.get(class_name)?;
-
-    let mut current_class_descriptor = class_descriptor;
+) -> Option<&'db PropertyDescriptor<'db>> {
+    let descriptors = database
+        .0
+        .find_property_descriptors(class_name, property_name)?;
 
-    // We need to find the canonical property descriptor associated with
-    // the property we're trying to deserialize.
-    //
-    // At each step of the loop, we're checking a new class descriptor
-    // to see if it has an entry for the property name we're looking for.
-    loop {
-        // If this class descriptor knows about this property name,
-        // we're pretty much done!
-        if let Some(property_descriptor) = current_class_descriptor.properties.get(property_name) {
-            match &property_descriptor.kind {
-                PropertyKind::Canonical { serialization } => match serialization {
-                    PropertySerialization::Serializes | PropertySerialization::Migrate { .. } => {
-                        return Some((property_descriptor, property_descriptor))
-                    }
-                    PropertySerialization::DoesNotSerialize => {
-                        // FIXME: Is this the correct solution?
-                        return None;
-                    }
-                    PropertySerialization::SerializesAs(serialized_name) => {
-                        let serialized_descriptor = current_class_descriptor
-                            .properties
-                            .get(serialized_name.as_ref())
-                            .unwrap();
-
-                        return Some((property_descriptor, serialized_descriptor));
-                    }
-                    _ => unimplemented!(),
-                },
-                PropertyKind::Alias { alias_for } => {
-                    let canonical_descriptor = current_class_descriptor
-                        .properties
-                        .get(alias_for.as_ref())
-                        .unwrap();
-
-                    // FIXME: This code is duplicated with above.
-                    match &canonical_descriptor.kind {
-                        PropertyKind::Canonical { serialization } => match serialization {
-                            PropertySerialization::Serializes
-                            | PropertySerialization::Migrate { .. } => {
-                                return Some((canonical_descriptor, canonical_descriptor))
-                            }
-                            PropertySerialization::DoesNotSerialize => {
-                                // FIXME: Is this the correct solution?
-                                return None;
-                            }
-                            PropertySerialization::SerializesAs(serialized_name) => {
-                                let serialized_descriptor = current_class_descriptor
-                                    .properties
-                                    .get(serialized_name.as_ref())
-                                    .unwrap();
-
-                                return Some((canonical_descriptor, serialized_descriptor));
-                            }
-                            _ => unimplemented!(),
-                        },
-                        _ => return None,
-                    }
-                }
-                // FIXME
-                _ => unimplemented!(),
-            }
-        }
-
-        if let Some(superclass_name) = &current_class_descriptor.superclass {
-            // If a property descriptor isn't found in our class, check
-            // our superclass.
-
-            current_class_descriptor = rbx_reflection_database::get()
-                .classes
-                .get(superclass_name)
-                .expect("Superclass in reflection database didn't exist");
-        } else {
-            // This property isn't known by any class in the reflection
-            // database.
+    descriptors.serialized.map(|_| descriptors.canonical)
+}
 
-            return None;
-        }
-    }
+/// Finds the property descriptor that a property should be written to a file
+/// with. Properties that don't serialize have none.
+pub fn find_serialized_property_descriptor<'db>(
+    database: Database<'db>,
+    class_name: &str,
+    property_name: &str,
+) -> Option<&'db PropertyDescriptor<'db>> {
+    database
+        .0
+        .find_property_descriptors(class_name, property_name)?
+        .serialized
 }
@@ -1,36 +1,59 @@
 use std::{
-    collections::{HashMap, HashSet},
+    borrow::Cow,
+    collections::{BTreeMap, HashMap, HashSet},
     io::Read,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
 };
 
 use log::trace;
 use rbx_dom_weak::{
     types::{Ref, SharedString, Variant, VariantType},
-    InstanceBuilder, WeakDom,
+    FileMetadata, InstanceBuilder, LineEndings, WeakDom,
 };
-use rbx_reflection::{DataType, PropertyKind, PropertySerialization};
+use rbx_reflection::{DataType, PropertyKind, PropertySerialization, ReflectionDatabase};
 
 use crate::{
     conversion::ConvertVariant,
-    core::find_canonical_property_descriptor,
+    core::{find_canonical_property_descriptor, Database},
     error::{DecodeError, DecodeErrorKind},
+    repair::Repairing,
     types::read_value_xml,
 };
 
 use crate::deserializer_core::{XmlEventReader, XmlReadEvent};
 
 pub fn decode_internal<R: Read>(source: R, options: DecodeOptions) -> Result<WeakDom, DecodeError> {
-    let mut tree = WeakDom::new(InstanceBuilder::new("DataModel"));
+    if options.repairs() {
+        let repairing = Repairing::new(source, options.clone());
+        return decode_events(XmlEventReader::from_source(repairing), options);
+    }
+
+    decode_events(XmlEventReader::from_source(source), options)
+}
+
+fn decode_events<R: Read>(
+    mut iterator: XmlEventReader<R>,
+    options: DecodeOptions,
+) -> Result<WeakDom, DecodeError> {
+    let mut tree =
+        WeakDom::new(InstanceBuilder::new("DataModel").with_referent(options.root_referent()));
 
     let root_id = tree.root_ref();
 
-    let mut iterator = XmlEventReader::from_source(source);
     let mut state = ParseState::new(&mut tree, options);
 
     deserialize_root(&mut iterator, &mut state, root_id)?;
     apply_referent_rewrites(&mut state);
     apply_shared_string_rewrites(&mut state);
 
+    let metadata = std::mem::take(&mut state.metadata);
+    if !metadata.is_empty() {
+        tree.insert_extension(root_id, FileMetadata { entries: metadata });
+    }
+
     Ok(tree)
 }
 
@@ -67,18 +90,48 @@ pub enum DecodePropertyBehavior {
     NoReflection,
 }
 
+/// Describes what rbx_xml should do with text that isn't valid UTF-8, which
+/// usually shows up in the sources of scripts that were saved by tools that
+/// didn't know their encoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum InvalidUtf8Behavior {
+    /// Returns an error if the document isn't valid UTF-8.
+    ///
+    /// This is the default.
+    Error,
+
+    /// Replaces every sequence of bytes that isn't valid UTF-8 with U+FFFD,
+    /// the replacement character.
+    Replace,
+}
+
 /// Options available for deserializing an XML-format model or place.
 #[derive(Debug, Clone)]
-pub struct DecodeOptions {
+pub struct DecodeOptions<'db> {
     property_behavior: DecodePropertyBehavior,
+    namespace: Option<Ref>,
+    database: Database<'db>,
+    pub(crate) invalid_utf8: InvalidUtf8Behavior,
+    pub(crate) lenient_entities: bool,
+    pub(crate) lenient_cdata: bool,
+    line_endings: LineEndings,
+    cancel: Option<Arc<AtomicBool>>,
 }
 
-impl DecodeOptions {
+impl<'db> DecodeOptions<'db> {
     /// Constructs a `DecodeOptions` with all values set to their defaults.
     #[inline]
     pub fn new() -> Self {
         DecodeOptions {
             property_behavior: DecodePropertyBehavior::IgnoreUnknown,
+            namespace: None,
+            database: Database::default(),
+            invalid_utf8: InvalidUtf8Behavior::Error,
+            lenient_entities: false,
+            lenient_cdata: false,
+            line_endings: LineEndings::Preserve,
+            cancel: None,
         }
     }
 
@@ -86,7 +139,137 @@ impl DecodeOptions {
     /// ones.
     #[inline]
     pub fn property_behavior(self, property_behavior: DecodePropertyBehavior) -> Self {
-        DecodeOptions { property_behavior }
+        DecodeOptions {
+            property_behavior,
+            ..self
+        }
+    }
+
+    /// Sets the reflection database used to decide how properties are read.
+    /// Defaults to the database bundled with `rbx_reflection_database`.
+    #[inline]
+    pub fn reflection_database(self, database: &'db ReflectionDatabase<'db>) -> Self {
+        DecodeOptions {
+            database: Database(database),
+            ..self
+        }
+    }
+
+    /// Derives the referent of every instance from `namespace` and the
+    /// instance's `referent` attribute, using [`Ref::from_namespace`], instead
+    /// of choosing referents at random.
+    ///
+    /// Decoding the same file twice with the same namespace gives instances
+    /// the same referents both times, so separate processes can tell which
+    /// instances match without sharing a table of referents. Instances with no
+    /// `referent` attribute are still given random referents, and the root
+    /// DataModel is given `namespace` itself.
+    #[inline]
+    pub fn namespace(self, namespace: Ref) -> Self {
+        DecodeOptions {
+            namespace: Some(namespace),
+            ..self
+        }
+    }
+
+    /// Determines what rbx_xml will do with text that isn't valid UTF-8.
+    /// Defaults to [`InvalidUtf8Behavior::Error`].
+    #[inline]
+    pub fn invalid_utf8_behavior(self, invalid_utf8: InvalidUtf8Behavior) -> Self {
+        DecodeOptions {
+            invalid_utf8,
+            ..self
+        }
+    }
+
+    /// Sets whether an `&` that doesn't start one of XML's predefined
+    /// entities, like `&amp;`, or a character reference, like `&#10;`, is
+    /// read as a plain `&` instead of being an error. Defaults to `false`.
+    ///
+    /// Files written by hand or by tools that don't escape text often have
+    /// these, like `a & b` or HTML entities like `&nbsp;`, which are read as
+    /// they are written.
+    #[inline]
+    pub fn lenient_entities(self, lenient_entities: bool) -> Self {
+        DecodeOptions {
+            lenient_entities,
+            ..self
+        }
+    }
+
+    /// Sets whether a `]]>` inside of a CDATA section is read as text when
+    /// it isn't followed by markup, instead of ending the section. Defaults
+    /// to `false`.
+    ///
+    /// Tools that put script sources in CDATA sections without splitting up
+    /// any `]]>` inside of them end the section early, which leaves the rest
+    /// of the source behind as text and usually stops the file from loading.
+    #[inline]
+    pub fn lenient_cdata(self, lenient_cdata: bool) -> Self {
+        DecodeOptions {
+            lenient_cdata,
+            ..self
+        }
+    }
+
+    /// Determines which line endings rbx_xml will give script sources, which
+    /// are the `Source` property of scripts. Defaults to
+    /// [`LineEndings::Preserve`].
+    #[inline]
+    pub fn line_endings(self, line_endings: LineEndings) -> Self {
+        DecodeOptions {
+            line_endings,
+            ..self
+        }
+    }
+
+    /// Lets another thread stop decoding by setting `token` to `true`. The
+    /// token is checked before each instance is read, and decoding fails as
+    /// soon as it sees it set.
+    ///
+    /// Errors caused by cancellation can be told apart with
+    /// [`DecodeError::is_cancelled`][crate::DecodeError::is_cancelled].
+    #[inline]
+    pub fn cancel_token(self, token: Arc<AtomicBool>) -> Self {
+        DecodeOptions {
+            cancel: Some(token),
+            ..self
+        }
+    }
+
+    /// Tells whether the token given to [`DecodeOptions::cancel_token`] has
+    /// been set.
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.cancel
+            .as_ref()
+            .is_some_and(|cancel| cancel.load(Ordering::Relaxed))
+    }
+
+    /// Tells whether the document needs to be repaired before it is parsed.
+    pub(crate) fn repairs(&self) -> bool {
+        self.invalid_utf8 != InvalidUtf8Behavior::Error
+            || self.lenient_entities
+            || self.lenient_cdata
+    }
+
+    /// Returns the referent to give the root DataModel, which isn't in the
+    /// file. With a namespace, that's the namespace itself, which can't be
+    /// derived from any `referent` attribute.
+    pub(crate) fn root_referent(&self) -> Ref {
+        self.namespace
+            .filter(|namespace| namespace.is_some())
+            .unwrap_or_else(Ref::new)
+    }
+
+    /// Returns the referent to give an instance, given its `referent`
+    /// attribute.
+    pub(crate) fn referent_for(&self, referent: Option<&str>) -> Ref {
+        match (self.namespace, referent) {
+            (Some(namespace), Some(referent)) => {
+                Ref::from_namespace(namespace, referent.as_bytes())
+            }
+            _ => Ref::new(),
+        }
     }
 
     /// A utility function to determine whether or not we should reference the
@@ -96,8 +279,8 @@ impl DecodeOptions {
     }
 }
 
-impl Default for DecodeOptions {
-    fn default() -> DecodeOptions {
+impl Default for DecodeOptions<'_> {
+    fn default() -> Self {
         DecodeOptions::new()
     }
 }
@@ -105,12 +288,12 @@ impl Default for DecodeOptions {
 /// The state needed to deserialize an XML model into an `WeakDom`.
 pub struct ParseState<'a> {
     tree: &'a mut WeakDom,
-    options: DecodeOptions,
+    options: DecodeOptions<'a>,
 
     /// Metadata deserialized from 'Meta' fields in the file.
     /// Known fields are:
     /// - ExplicitAutoJoints
-    metadata: HashMap<String, String>,
+    metadata: BTreeMap<String, String>,
 
     /// A map referent strings to IDs. This map is filled up as instances are
     /// deserialized, and referred to when filling out Ref properties.
@@ -151,11 +334,11 @@ struct SharedStringRewrite {
 }
 
 impl<'a> ParseState<'a> {
-    fn new(tree: &mut WeakDom, options: DecodeOptions) -> ParseState {
+    fn new(tree: &'a mut WeakDom, options: DecodeOptions<'a>) -> ParseState<'a> {
         ParseState {
             tree,
             options,
-            metadata: HashMap::new(),
+            metadata: BTreeMap::new(),
             referents_to_ids: HashMap::new(),
             referent_rewrites: Vec::new(),
             known_shared_strings: HashMap::new(),
@@ -433,8 +616,26 @@ fn deserialize_instance<R: Read>(
 
     trace!("Class {} with referent {:?}", class_name, referent);
 
-    let builder = InstanceBuilder::new(class_name);
-    let instance_id = state.tree.insert(parent_id, builder);
+    if state.options.is_cancelled() {
+        return Err(reader.error(DecodeErrorKind::Cancelled));
+    }
+
+    if let Some(referent) = &referent {
+        if state.referents_to_ids.contains_key(referent) {
+            return Err(reader.error(DecodeErrorKind::DuplicateReferent(referent.clone())));
+        }
+    }
+
+    let builder = InstanceBuilder::new(class_name)
+        .with_referent(state.options.referent_for(referent.as_deref()));
+    let builder_referent = builder.referent();
+    // The DOM has no TreeConfig checks, so the only way inserting can fail is
+    // a derived referent that is already in use.
+    let instance_id = state.tree.try_insert(parent_id, builder).map_err(|_| {
+        reader.error(DecodeErrorKind::DuplicateReferent(
+            builder_referent.to_string(),
+        ))
+    })?;
 
     if let Some(referent) = referent {
         state.referents_to_ids.insert(referent, instance_id);
@@ -487,7 +688,7 @@ fn deserialize_instance<R: Read>(
         None => instance.class.clone(),
     };
 
-    instance.properties = properties;
+    instance.properties = properties.into();
 
     Ok(())
 }
@@ -559,7 +760,11 @@ fn deserialize_properties<R: Read>(
         );
 
         let maybe_descriptor = if state.options.use_reflection() {
-            find_canonical_property_descriptor(&class_name, &xml_property_name)
+            find_canonical_property_descriptor(
+                state.options.database,
+                &class_name,
+                &xml_property_name,
+            )
         } else {
             None
         };
@@ -632,6 +837,7 @@ fn deserialize_properties<R: Read>(
                     }
                 }
                 _ => {
+                    let value = apply_line_endings(state, &descriptor.name, value);
                     props.insert(descriptor.name.to_string(), value);
                 }
             };
@@ -663,6 +869,7 @@ fn deserialize_properties<R: Read>(
                         Some(value) => value,
                         None => continue,
                     };
+                    let value = apply_line_endings(state, &xml_property_name, value);
                     props.insert(xml_property_name, value);
                 }
                 DecodePropertyBehavior::ErrorOnUnknown => {
@@ -675,3 +882,17 @@ fn deserialize_properties<R: Read>(
         }
     }
 }
+
+/// Gives the value of a script's `Source` property the line endings that were
+/// asked for. Other properties are returned as they are.
+fn apply_line_endings(state: &ParseState, property_name: &str, value: Variant) -> Variant {
+    match value {
+        Variant::String(source) if property_name == "Source" => {
+            match state.options.line_endings.apply(&source) {
+                Cow::Borrowed(_) => Variant::String(source),
+                Cow::Owned(source) => Variant::String(source),
+            }
+        }
+        value => value,
+    }
+}
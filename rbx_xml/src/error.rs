@@ -41,6 +41,13 @@ impl DecodeError {
     pub fn column(&self) -> usize {
         self.inner.column
     }
+
+    /// Tells whether decoding stopped because the token given to
+    /// [`DecodeOptions::cancel_token`][crate::DecodeOptions::cancel_token]
+    /// was set.
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self.inner.kind, DecodeErrorKind::Cancelled)
+    }
 }
 
 impl fmt::Display for DecodeError {
@@ -97,6 +104,8 @@ pub(crate) enum DecodeErrorKind {
         property_type: &'static str,
         error: String,
     },
+    DuplicateReferent(String),
+    Cancelled,
 }
 
 impl fmt::Display for DecodeErrorKind {
@@ -151,6 +160,12 @@ impl fmt::Display for DecodeErrorKind {
                 output,
                 "Could not decode property of type {property_type} because: {error}"
             ),
+            DuplicateReferent(referent) => write!(
+                output,
+                "More than one instance has the referent '{}'",
+                referent
+            ),
+            Cancelled => write!(output, "The operation was cancelled"),
         }
     }
 }
@@ -211,6 +226,13 @@ impl EncodeError {
             kind: Box::new(kind),
         }
     }
+
+    /// Tells whether encoding stopped because the token given to
+    /// [`EncodeOptions::cancel_token`][crate::EncodeOptions::cancel_token]
+    /// was set.
+    pub fn is_cancelled(&self) -> bool {
+        matches!(*self.kind, EncodeErrorKind::Cancelled)
+    }
 }
 
 impl fmt::Display for EncodeError {
@@ -243,6 +265,11 @@ pub(crate) enum EncodeErrorKind {
         actual_type: VariantType,
         message: String,
     },
+    NonFiniteNumber {
+        class_name: String,
+        property_name: String,
+    },
+    Cancelled,
 }
 
 impl fmt::Display for EncodeErrorKind {
@@ -277,6 +304,15 @@ impl fmt::Display for EncodeErrorKind {
                  When trying to convert the value, this error occured: {}",
                 class_name, property_name, expected_type, actual_type, message
             ),
+            NonFiniteNumber {
+                class_name,
+                property_name,
+            } => write!(
+                output,
+                "Property {}.{} contains a NaN or infinite number, which is not allowed",
+                class_name, property_name
+            ),
+            Cancelled => write!(output, "The operation was cancelled"),
         }
     }
 }
@@ -292,7 +328,9 @@ impl std::error::Error for EncodeErrorKind {
 
             UnknownProperty { .. }
             | UnsupportedPropertyType(_)
-            | UnsupportedPropertyConversion { .. } => None,
+            | UnsupportedPropertyConversion { .. }
+            | NonFiniteNumber { .. }
+            | Cancelled => None,
         }
     }
 }
@@ -89,9 +89,10 @@
 //! ```
 //!
 //! ## Configuration
-//! rbx_xml exposes no useful configuration yet, but there are methods that
-//! accept [`DecodeOptions`][DecodeOptions] and
-//! [`EncodeOptions`][EncodeOptions] that will be useful when it does.
+//! Methods like [`from_str`][from_str] and [`to_writer`][to_writer] accept
+//! [`DecodeOptions`][DecodeOptions] and [`EncodeOptions`][EncodeOptions],
+//! which control how unknown properties are handled and how output is
+//! formatted.
 //!
 //! [DecodeOptions]: struct.DecodeOptions.html
 //! [EncodeOptions]: struct.EncodeOptions.html
@@ -110,6 +111,8 @@ mod core;
 mod deserializer;
 mod deserializer_core;
 mod error;
+mod non_finite;
+mod repair;
 mod serializer;
 mod serializer_core;
 mod types;
@@ -125,10 +128,12 @@ use rbx_dom_weak::{types::Ref, WeakDom};
 
 use crate::{deserializer::decode_internal, serializer::encode_internal};
 
+pub use rbx_dom_weak::LineEndings;
+
 pub use crate::{
-    deserializer::{DecodeOptions, DecodePropertyBehavior},
+    deserializer::{DecodeOptions, DecodePropertyBehavior, InvalidUtf8Behavior},
     error::{DecodeError, EncodeError},
-    serializer::{EncodeOptions, EncodePropertyBehavior},
+    serializer::{EncodeOptions, EncodePropertyBehavior, NonFiniteBehavior},
 };
 
 /// Decodes an XML-format model or place from something that implements the
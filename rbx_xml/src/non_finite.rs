@@ -0,0 +1,239 @@
+//! Applies [`NonFiniteBehavior`] to the numbers inside property values.
+
+use std::borrow::Cow;
+
+use rbx_dom_weak::types::{
+    CFrame, Color3, ColorSequence, ColorSequenceKeypoint, CustomPhysicalProperties, Matrix3,
+    NumberRange, NumberSequence, NumberSequenceKeypoint, PhysicalProperties, Ray, Rect, Region3,
+    UDim, UDim2, Variant, Vector2, Vector3,
+};
+
+use crate::serializer::NonFiniteBehavior;
+
+/// A property value contained a NaN or infinite number while non-finite
+/// numbers were set to be an error.
+pub struct NonFiniteError;
+
+/// Returns the value to write in place of `value`, or an error if it contains
+/// a non-finite number that isn't allowed.
+pub fn apply_non_finite_behavior(
+    behavior: NonFiniteBehavior,
+    value: &Variant,
+) -> Result<Cow<'_, Variant>, NonFiniteError> {
+    match behavior {
+        NonFiniteBehavior::Preserve => Ok(Cow::Borrowed(value)),
+        _ if !has_non_finite(value) => Ok(Cow::Borrowed(value)),
+        NonFiniteBehavior::Error => Err(NonFiniteError),
+        NonFiniteBehavior::Clamp => {
+            Ok(map_floats(value, &mut clamp).map_or(Cow::Borrowed(value), Cow::Owned))
+        }
+    }
+}
+
+/// Tells whether `value` contains a NaN or infinite number, without copying
+/// it.
+fn has_non_finite(value: &Variant) -> bool {
+    let vector2 = |value: &Vector2| !(value.x.is_finite() && value.y.is_finite());
+    let vector3 =
+        |value: &Vector3| !(value.x.is_finite() && value.y.is_finite() && value.z.is_finite());
+    let cframe = |value: &CFrame| {
+        vector3(&value.position)
+            || vector3(&value.orientation.x)
+            || vector3(&value.orientation.y)
+            || vector3(&value.orientation.z)
+    };
+    let color3 =
+        |value: &Color3| !(value.r.is_finite() && value.g.is_finite() && value.b.is_finite());
+
+    match value {
+        Variant::Float32(value) => !value.is_finite(),
+        Variant::Float64(value) => !value.is_finite(),
+        Variant::Vector2(value) => vector2(value),
+        Variant::Vector3(value) => vector3(value),
+        Variant::CFrame(value) => cframe(value),
+        Variant::OptionalCFrame(value) => value.as_ref().is_some_and(cframe),
+        Variant::Color3(value) => color3(value),
+        Variant::UDim(value) => !value.scale.is_finite(),
+        Variant::UDim2(value) => !(value.x.scale.is_finite() && value.y.scale.is_finite()),
+        Variant::Rect(value) => vector2(&value.min) || vector2(&value.max),
+        Variant::Ray(value) => vector3(&value.origin) || vector3(&value.direction),
+        Variant::Region3(value) => vector3(&value.min) || vector3(&value.max),
+        Variant::NumberRange(value) => !(value.min.is_finite() && value.max.is_finite()),
+        Variant::NumberSequence(value) => value.keypoints.iter().any(|keypoint| {
+            !(keypoint.time.is_finite()
+                && keypoint.value.is_finite()
+                && keypoint.envelope.is_finite())
+        }),
+        Variant::ColorSequence(value) => value
+            .keypoints
+            .iter()
+            .any(|keypoint| !keypoint.time.is_finite() || color3(&keypoint.color)),
+        Variant::PhysicalProperties(PhysicalProperties::Custom(value)) => ![
+            value.density,
+            value.friction,
+            value.elasticity,
+            value.friction_weight,
+            value.elasticity_weight,
+        ]
+        .iter()
+        .all(|number| number.is_finite()),
+        _ => false,
+    }
+}
+
+/// Replaces NaN with zero and infinities with the largest finite number of the
+/// same sign.
+fn clamp(number: f64, max: f64) -> f64 {
+    if number.is_nan() {
+        0.0
+    } else {
+        number.clamp(-max, max)
+    }
+}
+
+/// Calls `map` on every floating point number in `value`, along with the
+/// largest finite number its type can hold, and returns the value built from
+/// the results. Returns `None` for types that don't hold floating point
+/// numbers.
+fn map_floats(value: &Variant, map: &mut dyn FnMut(f64, f64) -> f64) -> Option<Variant> {
+    if let Variant::Float64(value) = value {
+        return Some(Variant::Float64(map(*value, f64::MAX)));
+    }
+
+    let mut float = |number: f32| map(number as f64, f32::MAX as f64) as f32;
+
+    let vector2 = |value: &Vector2, float: &mut dyn FnMut(f32) -> f32| {
+        Vector2::new(float(value.x), float(value.y))
+    };
+    let vector3 = |value: &Vector3, float: &mut dyn FnMut(f32) -> f32| {
+        Vector3::new(float(value.x), float(value.y), float(value.z))
+    };
+    let cframe = |value: &CFrame, float: &mut dyn FnMut(f32) -> f32| {
+        CFrame::new(
+            vector3(&value.position, float),
+            Matrix3::new(
+                vector3(&value.orientation.x, float),
+                vector3(&value.orientation.y, float),
+                vector3(&value.orientation.z, float),
+            ),
+        )
+    };
+    let color3 = |value: &Color3, float: &mut dyn FnMut(f32) -> f32| {
+        Color3::new(float(value.r), float(value.g), float(value.b))
+    };
+    let udim = |value: &UDim, float: &mut dyn FnMut(f32) -> f32| {
+        UDim::new(float(value.scale), value.offset)
+    };
+
+    Some(match value {
+        Variant::Float32(value) => Variant::Float32(float(*value)),
+        Variant::Vector2(value) => Variant::Vector2(vector2(value, &mut float)),
+        Variant::Vector3(value) => Variant::Vector3(vector3(value, &mut float)),
+        Variant::CFrame(value) => Variant::CFrame(cframe(value, &mut float)),
+        Variant::OptionalCFrame(value) => {
+            Variant::OptionalCFrame(value.as_ref().map(|value| cframe(value, &mut float)))
+        }
+        Variant::Color3(value) => Variant::Color3(color3(value, &mut float)),
+        Variant::UDim(value) => Variant::UDim(udim(value, &mut float)),
+        Variant::UDim2(value) => Variant::UDim2(UDim2::new(
+            udim(&value.x, &mut float),
+            udim(&value.y, &mut float),
+        )),
+        Variant::Rect(value) => Variant::Rect(Rect::new(
+            vector2(&value.min, &mut float),
+            vector2(&value.max, &mut float),
+        )),
+        Variant::Ray(value) => Variant::Ray(Ray::new(
+            vector3(&value.origin, &mut float),
+            vector3(&value.direction, &mut float),
+        )),
+        Variant::Region3(value) => Variant::Region3(Region3::new(
+            vector3(&value.min, &mut float),
+            vector3(&value.max, &mut float),
+        )),
+        Variant::NumberRange(value) => {
+            Variant::NumberRange(NumberRange::new(float(value.min), float(value.max)))
+        }
+        Variant::NumberSequence(value) => Variant::NumberSequence(NumberSequence {
+            keypoints: value
+                .keypoints
+                .iter()
+                .map(|keypoint| {
+                    NumberSequenceKeypoint::new(
+                        float(keypoint.time),
+                        float(keypoint.value),
+                        float(keypoint.envelope),
+                    )
+                })
+                .collect(),
+        }),
+        Variant::ColorSequence(value) => Variant::ColorSequence(ColorSequence {
+            keypoints: value
+                .keypoints
+                .iter()
+                .map(|keypoint| {
+                    ColorSequenceKeypoint::new(
+                        float(keypoint.time),
+                        color3(&keypoint.color, &mut float),
+                    )
+                })
+                .collect(),
+        }),
+        Variant::PhysicalProperties(PhysicalProperties::Custom(value)) => {
+            Variant::PhysicalProperties(PhysicalProperties::Custom(CustomPhysicalProperties {
+                density: float(value.density),
+                friction: float(value.friction),
+                elasticity: float(value.elasticity),
+                friction_weight: float(value.friction_weight),
+                elasticity_weight: float(value.elasticity_weight),
+            }))
+        }
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn preserve() {
+        let value = Variant::Float32(f32::NAN);
+        let applied = apply_non_finite_behavior(NonFiniteBehavior::Preserve, &value);
+        assert!(matches!(applied, Ok(Cow::Borrowed(Variant::Float32(n))) if n.is_nan()));
+    }
+
+    #[test]
+    fn error() {
+        let value = Variant::Vector3(Vector3::new(1.0, f32::INFINITY, 2.0));
+        assert!(apply_non_finite_behavior(NonFiniteBehavior::Error, &value).is_err());
+
+        let value = Variant::Vector3(Vector3::new(1.0, 2.0, 3.0));
+        assert!(apply_non_finite_behavior(NonFiniteBehavior::Error, &value).is_ok());
+    }
+
+    #[test]
+    fn has_non_finite_cframe() {
+        let mut cframe = CFrame::new(Vector3::new(1.0, 2.0, 3.0), Matrix3::identity());
+        assert!(!has_non_finite(&Variant::CFrame(cframe)));
+
+        cframe.orientation.z.y = f32::NAN;
+        assert!(has_non_finite(&Variant::CFrame(cframe)));
+        assert!(has_non_finite(&Variant::OptionalCFrame(Some(cframe))));
+        assert!(!has_non_finite(&Variant::OptionalCFrame(None)));
+    }
+
+    #[test]
+    fn clamp() {
+        let value = Variant::NumberRange(NumberRange::new(f32::NEG_INFINITY, f32::NAN));
+        let applied = apply_non_finite_behavior(NonFiniteBehavior::Clamp, &value).ok();
+        assert_eq!(
+            applied.as_deref(),
+            Some(&Variant::NumberRange(NumberRange::new(f32::MIN, 0.0)))
+        );
+
+        let value = Variant::Float64(f64::INFINITY);
+        let applied = apply_non_finite_behavior(NonFiniteBehavior::Clamp, &value).ok();
+        assert_eq!(applied.as_deref(), Some(&Variant::Float64(f64::MAX)));
+    }
+}
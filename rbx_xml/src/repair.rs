@@ -0,0 +1,154 @@
+//! Fixes for common ways that XML model files found in the wild are broken,
+//! applied to the whole document before it is parsed.
+
+use std::io::{self, Cursor, Read};
+
+use crate::deserializer::{DecodeOptions, InvalidUtf8Behavior};
+
+const CDATA_START: &[u8] = b"<![CDATA[";
+const CDATA_END: &[u8] = b"]]>";
+
+/// Wraps a source so that the document read through it is repaired with
+/// [`repair`]. The source is read all at once, the first time anything is
+/// read, so that errors reading it are reported by the XML parser like any
+/// other error reading the document.
+pub(crate) struct Repairing<'db, R> {
+    source: Option<R>,
+    options: DecodeOptions<'db>,
+    repaired: Cursor<Vec<u8>>,
+}
+
+impl<'db, R: Read> Repairing<'db, R> {
+    pub(crate) fn new(source: R, options: DecodeOptions<'db>) -> Self {
+        Repairing {
+            source: Some(source),
+            options,
+            repaired: Cursor::new(Vec::new()),
+        }
+    }
+}
+
+impl<R: Read> Read for Repairing<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if let Some(mut source) = self.source.take() {
+            let mut contents = Vec::new();
+            source.read_to_end(&mut contents)?;
+            self.repaired = Cursor::new(repair(&contents, &self.options));
+        }
+
+        self.repaired.read(buf)
+    }
+}
+
+/// Repairs a document as much as the given options allow.
+pub(crate) fn repair(contents: &[u8], options: &DecodeOptions) -> Vec<u8> {
+    let mut repaired = Vec::with_capacity(contents.len());
+    let mut rest = contents;
+
+    while let Some(&byte) = rest.first() {
+        if options.lenient_cdata && rest.starts_with(CDATA_START) {
+            let len = repair_cdata(rest, &mut repaired);
+            rest = &rest[len..];
+            continue;
+        }
+
+        // Comments and CDATA sections are copied as they are, since nothing
+        // inside of them is markup.
+        if let Some(len) = skip_section(rest, b"<!--", b"-->")
+            .or_else(|| skip_section(rest, CDATA_START, CDATA_END))
+        {
+            repaired.extend_from_slice(&rest[..len]);
+            rest = &rest[len..];
+            continue;
+        }
+
+        if options.lenient_entities && byte == b'&' && !starts_with_reference(rest) {
+            repaired.extend_from_slice(b"&amp;");
+            rest = &rest[1..];
+            continue;
+        }
+
+        repaired.push(byte);
+        rest = &rest[1..];
+    }
+
+    match options.invalid_utf8 {
+        InvalidUtf8Behavior::Replace => match String::from_utf8_lossy(&repaired) {
+            std::borrow::Cow::Borrowed(_) => repaired,
+            std::borrow::Cow::Owned(replaced) => replaced.into_bytes(),
+        },
+        InvalidUtf8Behavior::Error => repaired,
+    }
+}
+
+/// Copies the CDATA section that `contents` starts with, returning its length.
+///
+/// The section is taken to end at the first `]]>` that is followed by markup
+/// or by the end of the document, instead of at the first `]]>`. Any `]]>`
+/// before that is split across two sections, the same way Roblox writes them,
+/// so that it's read as text.
+fn repair_cdata(contents: &[u8], repaired: &mut Vec<u8>) -> usize {
+    let mut position = CDATA_START.len();
+    repaired.extend_from_slice(CDATA_START);
+
+    while position < contents.len() {
+        let rest = &contents[position..];
+
+        if rest.starts_with(CDATA_END) {
+            let after = &rest[CDATA_END.len()..];
+            let next = after.iter().find(|byte| !byte.is_ascii_whitespace());
+
+            if matches!(next, None | Some(b'<')) {
+                repaired.extend_from_slice(CDATA_END);
+                return position + CDATA_END.len();
+            }
+
+            repaired.extend_from_slice(b"]]]]><![CDATA[>");
+            position += CDATA_END.len();
+            continue;
+        }
+
+        repaired.push(rest[0]);
+        position += 1;
+    }
+
+    position
+}
+
+/// If `contents` starts with a section that begins with `start`, returns the
+/// length of the section including `end`. Sections that are never ended last
+/// until the end of the document.
+fn skip_section(contents: &[u8], start: &[u8], end: &[u8]) -> Option<usize> {
+    if !contents.starts_with(start) {
+        return None;
+    }
+
+    let inside = &contents[start.len()..];
+    let len = inside
+        .windows(end.len())
+        .position(|window| window == end)
+        .map_or(inside.len(), |position| position + end.len());
+
+    Some(start.len() + len)
+}
+
+/// Tells whether `contents`, which starts with `&`, starts with one of XML's
+/// predefined entities or a character reference.
+fn starts_with_reference(contents: &[u8]) -> bool {
+    const PREDEFINED: &[&[u8]] = &[b"&amp;", b"&lt;", b"&gt;", b"&quot;", b"&apos;"];
+
+    if PREDEFINED.iter().any(|entity| contents.starts_with(entity)) {
+        return true;
+    }
+
+    let (digits, is_digit): (&[u8], fn(&u8) -> bool) = if contents.starts_with(b"&#x") {
+        (&contents[3..], u8::is_ascii_hexdigit)
+    } else if contents.starts_with(b"&#") {
+        (&contents[2..], u8::is_ascii_digit)
+    } else {
+        return false;
+    };
+
+    let len = digits.iter().take_while(|byte| is_digit(byte)).count();
+    len > 0 && digits.get(len) == Some(&b';')
+}
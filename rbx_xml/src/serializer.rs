@@ -1,18 +1,24 @@
 use std::{
-    collections::{BTreeMap, HashMap},
+    borrow::Cow,
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
     io::Write,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
 };
 
 use rbx_dom_weak::{
     types::{Ref, SharedString, SharedStringHash, Variant, VariantType},
-    WeakDom,
+    FileMetadata, Instance, LineEndings, WeakDom,
 };
-use rbx_reflection::DataType;
+use rbx_reflection::{DataType, ReflectionDatabase};
 
 use crate::{
     conversion::ConvertVariant,
-    core::find_serialized_property_descriptor,
+    core::{find_serialized_property_descriptor, Database},
     error::{EncodeError as NewEncodeError, EncodeErrorKind},
+    non_finite::{apply_non_finite_behavior, NonFiniteError},
     types::write_value_xml,
 };
 
@@ -24,11 +30,18 @@ pub fn encode_internal<W: Write>(
     ids: &[Ref],
     options: EncodeOptions,
 ) -> Result<(), NewEncodeError> {
-    let mut writer = XmlEventWriter::from_output(output);
+    let mut writer = XmlEventWriter::from_output(output, options.effective_indent_width());
+    writer.set_canonical_floats(options.canonical_floats);
     let mut state = EmitState::new(options);
 
     writer.write(XmlWriteEvent::start_element("roblox").attr("version", "4"))?;
 
+    serialize_metadata(&mut writer, tree)?;
+
+    if state.options.deterministic_referents {
+        assign_deterministic_referents(&mut state, tree, ids);
+    }
+
     let mut property_buffer = Vec::new();
     for id in ids {
         serialize_instance(&mut writer, &mut state, tree, *id, &mut property_buffer)?;
@@ -71,18 +84,63 @@ pub enum EncodePropertyBehavior {
     NoReflection,
 }
 
+/// Describes what rbx_xml should do with NaN and infinite numbers in property
+/// values.
+///
+/// Roblox doesn't handle these consistently: some properties keep them, while
+/// others turn into garbage or stop the file from loading.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum NonFiniteBehavior {
+    /// Writes NaN and infinite numbers as `NAN`, `INF`, and `-INF`.
+    ///
+    /// This is the default.
+    Preserve,
+
+    /// Writes NaN as zero, and infinite numbers as the largest finite number
+    /// with the same sign.
+    Clamp,
+
+    /// Returns an error if any property contains a NaN or infinite number.
+    Error,
+}
+
 /// Options available for serializing an XML-format model or place.
 #[derive(Debug, Clone)]
-pub struct EncodeOptions {
+pub struct EncodeOptions<'db> {
     property_behavior: EncodePropertyBehavior,
+    pretty: bool,
+    indent_width: usize,
+    skip_default_properties: bool,
+    deterministic_referents: bool,
+    canonical_floats: bool,
+    non_finite_behavior: NonFiniteBehavior,
+    line_endings: LineEndings,
+    database: Database<'db>,
+    strip_classes: HashSet<String>,
+    skip_instances: HashSet<Ref>,
+    class_map: HashMap<String, String>,
+    cancel: Option<Arc<AtomicBool>>,
 }
 
-impl EncodeOptions {
+impl<'db> EncodeOptions<'db> {
     /// Constructs a `EncodeOptions` with all values set to their defaults.
     #[inline]
     pub fn new() -> Self {
         EncodeOptions {
             property_behavior: EncodePropertyBehavior::IgnoreUnknown,
+            pretty: true,
+            indent_width: 2,
+            skip_default_properties: false,
+            deterministic_referents: false,
+            canonical_floats: false,
+            non_finite_behavior: NonFiniteBehavior::Preserve,
+            line_endings: LineEndings::Preserve,
+            database: Database::default(),
+            strip_classes: HashSet::new(),
+            skip_instances: HashSet::new(),
+            class_map: HashMap::new(),
+            cancel: None,
         }
     }
 
@@ -90,22 +148,206 @@ impl EncodeOptions {
     /// ones.
     #[inline]
     pub fn property_behavior(self, property_behavior: EncodePropertyBehavior) -> Self {
-        EncodeOptions { property_behavior }
+        EncodeOptions {
+            property_behavior,
+            ..self
+        }
+    }
+
+    /// Determines whether rbx_xml will put each element on its own line and
+    /// indent it. Defaults to `true`.
+    ///
+    /// Turning this off produces smaller files that are harder to read.
+    #[inline]
+    pub fn pretty(self, pretty: bool) -> Self {
+        EncodeOptions { pretty, ..self }
+    }
+
+    /// Sets the number of spaces used for each level of indentation when
+    /// `pretty` is enabled. Defaults to 2.
+    #[inline]
+    pub fn indent_width(self, indent_width: usize) -> Self {
+        EncodeOptions {
+            indent_width,
+            ..self
+        }
+    }
+
+    /// Determines whether rbx_xml will leave out properties whose values are
+    /// the same as the reflection database's default value for the
+    /// instance's class. Defaults to `false`.
+    ///
+    /// Roblox fills in missing properties with their defaults when loading a
+    /// file, so this only changes the size of the output. It has no effect
+    /// when property behavior is set to `NoReflection`.
+    #[inline]
+    pub fn skip_default_properties(self, skip_default_properties: bool) -> Self {
+        EncodeOptions {
+            skip_default_properties,
+            ..self
+        }
+    }
+
+    /// Sets the reflection database used to decide how properties are written.
+    /// Defaults to the database bundled with `rbx_reflection_database`.
+    #[inline]
+    pub fn reflection_database(self, database: &'db ReflectionDatabase<'db>) -> Self {
+        EncodeOptions {
+            database: Database(database),
+            ..self
+        }
+    }
+
+    /// Determines whether rbx_xml will assign referents as if every
+    /// instance's children were sorted by name and class, instead of in the
+    /// order instances are written. Defaults to `false`.
+    ///
+    /// Instances are still written in the order they appear in the DOM, so
+    /// sibling order is kept, and files whose siblings are in a different
+    /// order are still different. With this on, referents depend only on the
+    /// names, classes, and contents of the instances being written, so moving
+    /// one instance doesn't renumber every instance after it. That keeps
+    /// line-based diffs of files checked into version control small.
+    ///
+    /// rbx_binary has no such option. Its referents aren't diffed as text,
+    /// and they're already numbered in the order instances are written.
+    #[inline]
+    pub fn deterministic_referents(self, deterministic_referents: bool) -> Self {
+        EncodeOptions {
+            deterministic_referents,
+            ..self
+        }
+    }
+
+    /// Determines whether rbx_xml will write floating point numbers in
+    /// whichever of decimal or exponent notation is shorter, like `1e-20`
+    /// instead of a long run of zeroes. Defaults to `false`.
+    ///
+    /// Numbers are always written with the fewest digits that read back as
+    /// exactly the same value, so reading a file and writing it back doesn't
+    /// change how its numbers are written. With this on, that's true however
+    /// big or small they are. rbx_binary always writes the exact bits of every
+    /// number, so it has no equivalent option.
+    #[inline]
+    pub fn canonical_floats(self, canonical_floats: bool) -> Self {
+        EncodeOptions {
+            canonical_floats,
+            ..self
+        }
+    }
+
+    /// Determines what rbx_xml will do with NaN and infinite numbers in
+    /// property values. Defaults to [`NonFiniteBehavior::Preserve`].
+    #[inline]
+    pub fn non_finite_behavior(self, non_finite_behavior: NonFiniteBehavior) -> Self {
+        EncodeOptions {
+            non_finite_behavior,
+            ..self
+        }
+    }
+
+    /// Determines which line endings rbx_xml will give script sources, which
+    /// are the `Source` property of scripts. Defaults to
+    /// [`LineEndings::Preserve`].
+    #[inline]
+    pub fn line_endings(self, line_endings: LineEndings) -> Self {
+        EncodeOptions {
+            line_endings,
+            ..self
+        }
+    }
+
+    /// Leaves out every instance with the given ClassName, along with all of
+    /// its descendants.
+    ///
+    /// This is meant for classes that only exist inside a tool's own
+    /// pipeline, like marker instances, which Roblox would not be able to
+    /// load.
+    #[inline]
+    pub fn strip_class<S: Into<String>>(mut self, class: S) -> Self {
+        self.strip_classes.insert(class.into());
+        self
+    }
+
+    /// Leaves out the instances with the given referents, along with all of
+    /// their descendants, without changing the tree.
+    ///
+    /// This lets tools leave out instances picked by their own rules, like
+    /// tests or editor-only helpers, and still write the whole tree elsewhere.
+    #[inline]
+    pub fn skip_instances<I: IntoIterator<Item = Ref>>(mut self, referents: I) -> Self {
+        self.skip_instances.extend(referents);
+        self
+    }
+
+    /// Writes every instance with the ClassName `from` as if its ClassName was
+    /// `to`. Properties are written using the reflection information for
+    /// `to`.
+    ///
+    /// Like [`EncodeOptions::strip_class`], this is meant for classes that
+    /// only exist inside a tool's own pipeline, which can be written out as a
+    /// class that Roblox knows about, like `Folder`.
+    #[inline]
+    pub fn map_class<F: Into<String>, T: Into<String>>(mut self, from: F, to: T) -> Self {
+        self.class_map.insert(from.into(), to.into());
+        self
+    }
+
+    /// Lets another thread stop encoding by setting `token` to `true`. The
+    /// token is checked before each instance is written, and encoding fails
+    /// as soon as it sees it set.
+    ///
+    /// Errors caused by cancellation can be told apart with
+    /// [`EncodeError::is_cancelled`][crate::EncodeError::is_cancelled].
+    #[inline]
+    pub fn cancel_token(self, token: Arc<AtomicBool>) -> Self {
+        EncodeOptions {
+            cancel: Some(token),
+            ..self
+        }
+    }
+
+    /// Tells whether the token given to [`EncodeOptions::cancel_token`] has
+    /// been set.
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.cancel
+            .as_ref()
+            .is_some_and(|cancel| cancel.load(Ordering::Relaxed))
+    }
+
+    /// Returns the ClassName that the instance should be written as, or
+    /// `None` if it should be left out.
+    pub(crate) fn written_class<'a>(&'a self, instance: &'a Instance) -> Option<&'a str> {
+        let class = instance.class.as_str();
+        if self.strip_classes.contains(class) || self.skip_instances.contains(&instance.referent())
+        {
+            None
+        } else {
+            Some(self.class_map.get(class).map_or(class, String::as_str))
+        }
     }
 
     pub(crate) fn use_reflection(&self) -> bool {
         self.property_behavior != EncodePropertyBehavior::NoReflection
     }
+
+    pub(crate) fn effective_indent_width(&self) -> Option<usize> {
+        if self.pretty {
+            Some(self.indent_width)
+        } else {
+            None
+        }
+    }
 }
 
-impl Default for EncodeOptions {
-    fn default() -> EncodeOptions {
+impl Default for EncodeOptions<'_> {
+    fn default() -> Self {
         EncodeOptions::new()
     }
 }
 
-pub struct EmitState {
-    options: EncodeOptions,
+pub struct EmitState<'db> {
+    options: EncodeOptions<'db>,
 
     /// A map of IDs written so far to the generated referent that they use.
     /// This map is used to correctly emit Ref properties.
@@ -119,8 +361,8 @@ pub struct EmitState {
     shared_strings_to_emit: BTreeMap<SharedStringHash, SharedString>,
 }
 
-impl EmitState {
-    pub fn new(options: EncodeOptions) -> EmitState {
+impl<'db> EmitState<'db> {
+    pub fn new(options: EncodeOptions<'db>) -> Self {
         EmitState {
             options,
             referent_map: HashMap::new(),
@@ -157,12 +399,20 @@ fn serialize_instance<'a, W: Write>(
     id: Ref,
     property_buffer: &mut Vec<(&'a String, &'a Variant)>,
 ) -> Result<(), NewEncodeError> {
+    if state.options.is_cancelled() {
+        return Err(writer.error(EncodeErrorKind::Cancelled));
+    }
+
     let instance = tree.get_by_ref(id).unwrap();
+    let class = match state.options.written_class(instance) {
+        Some(class) => class.to_owned(),
+        None => return Ok(()),
+    };
     let mapped_id = state.map_id(id);
 
     writer.write(
         XmlWriteEvent::start_element("Item")
-            .attr("class", &instance.class)
+            .attr("class", &class)
             .attr("referent", &mapped_id.to_string()),
     )?;
 
@@ -181,8 +431,35 @@ fn serialize_instance<'a, W: Write>(
     property_buffer.sort_unstable_by_key(|(key, _)| *key);
 
     for (property_name, value) in property_buffer.drain(..) {
+        let value = match apply_non_finite_behavior(state.options.non_finite_behavior, value) {
+            Ok(value) => value,
+            Err(NonFiniteError) => {
+                return Err(writer.error(EncodeErrorKind::NonFiniteNumber {
+                    class_name: instance.class.clone(),
+                    property_name: property_name.clone(),
+                }))
+            }
+        };
+        let value = match value {
+            Cow::Borrowed(Variant::String(source)) if property_name == "Source" => {
+                match state.options.line_endings.apply(source) {
+                    Cow::Borrowed(_) => value,
+                    Cow::Owned(source) => Cow::Owned(Variant::String(source)),
+                }
+            }
+            _ => value,
+        };
+        let value = value.as_ref();
+
+        if state.options.skip_default_properties
+            && state.options.use_reflection()
+            && is_default_value(state.options.database, &class, property_name, value)
+        {
+            continue;
+        }
+
         let maybe_serialized_descriptor = if state.options.use_reflection() {
-            find_serialized_property_descriptor(&instance.class, property_name)
+            find_serialized_property_descriptor(state.options.database, &class, property_name)
         } else {
             None
         };
@@ -231,8 +508,8 @@ fn serialize_instance<'a, W: Write>(
 
     writer.write(XmlWriteEvent::end_element())?;
 
-    for child_id in instance.children() {
-        serialize_instance(writer, state, tree, *child_id, property_buffer)?;
+    for &child_id in instance.children() {
+        serialize_instance(writer, state, tree, child_id, property_buffer)?;
     }
 
     writer.write(XmlWriteEvent::end_element())?;
@@ -240,6 +517,71 @@ fn serialize_instance<'a, W: Write>(
     Ok(())
 }
 
+/// Numbers every instance that will be written as if every instance's
+/// children were sorted by name, class, and contents, so that referents don't
+/// depend on the order siblings are in.
+fn assign_deterministic_referents(state: &mut EmitState, tree: &WeakDom, ids: &[Ref]) {
+    let mut subtree_hashes = HashMap::new();
+    for &id in ids {
+        subtree_hashes.extend(tree.subtree_hashes(id));
+    }
+
+    let mut to_visit: VecDeque<Ref> = ids.iter().copied().collect();
+    while let Some(id) = to_visit.pop_front() {
+        let instance = tree.get_by_ref(id).unwrap();
+        if state.options.written_class(instance).is_none() {
+            continue;
+        }
+
+        state.map_id(id);
+
+        let mut children = instance.children().to_vec();
+        children.sort_by_cached_key(|child| {
+            let child_instance = tree.get_by_ref(*child).unwrap();
+            (
+                &child_instance.name,
+                &child_instance.class,
+                subtree_hashes[child],
+            )
+        });
+        to_visit.extend(children);
+    }
+}
+
+/// Tells whether the given value is the reflection database's default value
+/// for the property on the given class.
+fn is_default_value(
+    database: Database,
+    class_name: &str,
+    property_name: &str,
+    value: &Variant,
+) -> bool {
+    database
+        .0
+        .classes
+        .get(class_name)
+        .and_then(|class| class.default_properties.get(property_name))
+        == Some(value)
+}
+
+fn serialize_metadata<W: Write>(
+    writer: &mut XmlEventWriter<W>,
+    tree: &WeakDom,
+) -> Result<(), NewEncodeError> {
+    let metadata = match tree.extension::<FileMetadata>(tree.root_ref()) {
+        Some(metadata) => metadata,
+        None => return Ok(()),
+    };
+
+    for (name, value) in &metadata.entries {
+        writer.write(XmlWriteEvent::start_element("Meta").attr("name", name))?;
+        writer.write_string(value)?;
+        writer.end_element()?;
+    }
+
+    Ok(())
+}
+
 fn serialize_shared_strings<W: Write>(
     writer: &mut XmlEventWriter<W>,
     state: &mut EmitState,
@@ -14,13 +14,18 @@ use crate::{
 pub struct XmlEventWriter<W> {
     inner: EventWriter<W>,
     character_buffer: String,
+    canonical_floats: bool,
 }
 
 impl<W: Write> XmlEventWriter<W> {
     /// Constructs an `XmlEventWriter` from an output that implements `Write`.
-    pub fn from_output(output: W) -> XmlEventWriter<W> {
+    ///
+    /// Each level of nesting is indented by `indent_width` spaces, or
+    /// everything is written on one line if `indent_width` is `None`.
+    pub fn from_output(output: W, indent_width: Option<usize>) -> XmlEventWriter<W> {
         let inner = EmitterConfig::new()
-            .perform_indent(true)
+            .perform_indent(indent_width.is_some())
+            .indent_string(" ".repeat(indent_width.unwrap_or(0)))
             .write_document_declaration(false)
             .normalize_empty_elements(false)
             .create_writer(output);
@@ -28,9 +33,17 @@ impl<W: Write> XmlEventWriter<W> {
         XmlEventWriter {
             inner,
             character_buffer: String::new(),
+            canonical_floats: false,
         }
     }
 
+    /// Sets whether floating point numbers are written in whichever of
+    /// decimal or exponent notation is shorter. See
+    /// [`EncodeOptions::canonical_floats`][crate::EncodeOptions::canonical_floats].
+    pub fn set_canonical_floats(&mut self, canonical_floats: bool) {
+        self.canonical_floats = canonical_floats;
+    }
+
     pub(crate) fn error<T: Into<EncodeErrorKind>>(&self, kind: T) -> NewEncodeError {
         NewEncodeError::new_from_writer(kind.into(), &self.inner)
     }
@@ -67,6 +80,45 @@ impl<W: Write> XmlEventWriter<W> {
         Ok(())
     }
 
+    /// Writes a floating point number as characters.
+    ///
+    /// Numbers are always written with the fewest digits that read back as
+    /// the same value. With canonical floats turned on, exponent notation is
+    /// used when it's shorter, so very large and very small numbers don't
+    /// come out as long runs of zeroes. NaN and infinite numbers are written
+    /// as `NAN`, `INF`, and `-INF`.
+    pub fn write_float<T>(&mut self, value: T) -> Result<(), NewEncodeError>
+    where
+        T: std::fmt::Display + std::fmt::LowerExp + Into<f64> + Copy,
+    {
+        let number: f64 = value.into();
+        if number.is_nan() {
+            return self.write_characters("NAN");
+        } else if number == f64::INFINITY {
+            return self.write_characters("INF");
+        } else if number == f64::NEG_INFINITY {
+            return self.write_characters("-INF");
+        }
+
+        write!(self.character_buffer, "{}", value).unwrap();
+
+        if self.canonical_floats {
+            let decimal_len = self.character_buffer.len();
+            write!(self.character_buffer, "{:e}", value).unwrap();
+
+            if self.character_buffer.len() - decimal_len < decimal_len {
+                self.character_buffer.replace_range(..decimal_len, "");
+            } else {
+                self.character_buffer.truncate(decimal_len);
+            }
+        }
+
+        write_characters_or_cdata(&mut self.inner, &self.character_buffer)?;
+        self.character_buffer.clear();
+
+        Ok(())
+    }
+
     pub fn write_value<T: XmlType>(&mut self, value: &T) -> Result<(), NewEncodeError> {
         value.write_xml(self)
     }
@@ -92,23 +144,6 @@ impl<W: Write> XmlEventWriter<W> {
         self.write_characters(value)?;
         self.write(XmlWriteEvent::end_element())
     }
-
-    /// Writes a list of values that implement `Display`, with each wrapped in
-    /// an associated tag. This method uses the same optimization as
-    /// `write_characters` to avoid extra allocations.
-    pub fn write_tag_array<T: std::fmt::Display>(
-        &mut self,
-        values: &[T],
-        tags: &[&str],
-    ) -> Result<(), NewEncodeError> {
-        assert_eq!(values.len(), tags.len());
-
-        for (index, component) in values.iter().enumerate() {
-            self.write_tag_characters(tags[index], component)?;
-        }
-
-        Ok(())
-    }
 }
 
 /// Given a value, writes a `Characters` event or a `CData` event depending on
@@ -9,7 +9,7 @@ where
     let _ = env_logger::try_init();
 
     let mut buffer = Vec::new();
-    let mut writer = XmlEventWriter::from_output(&mut buffer);
+    let mut writer = XmlEventWriter::from_output(&mut buffer, Some(2));
 
     test_value.write_outer_xml("foo", &mut writer).unwrap();
 
@@ -30,7 +30,7 @@ where
     let _ = env_logger::try_init();
 
     let mut buffer = Vec::new();
-    let mut writer = XmlEventWriter::from_output(&mut buffer);
+    let mut writer = XmlEventWriter::from_output(&mut buffer, Some(2));
 
     test_value.write_outer_xml("foo", &mut writer).unwrap();
 
@@ -1,9 +1,9 @@
 //! Basic functionality tests
 
 use rbx_dom_weak::types::{
-    Attributes, BinaryString, BrickColor, Color3, ColorSequence, ColorSequenceKeypoint,
-    NumberRange, NumberSequence, NumberSequenceKeypoint, Rect, Tags, UDim, UDim2, UniqueId,
-    Variant, Vector2, Vector3,
+    Attributes, BinaryString, BrickColor, CFrame, Color3, ColorSequence, ColorSequenceKeypoint,
+    Matrix3, NumberRange, NumberSequence, NumberSequenceKeypoint, Rect, Ref, Tags, UDim, UDim2,
+    UniqueId, Variant, Vector2, Vector3,
 };
 use rbx_dom_weak::{InstanceBuilder, WeakDom};
 
@@ -230,3 +230,622 @@ fn read_unique_id() {
         )))
     );
 }
+
+#[test]
+fn write_not_pretty() {
+    let _ = env_logger::try_init();
+
+    let tree = WeakDom::new(InstanceBuilder::new("Folder").with_name("Root"));
+
+    let mut buffer = Vec::new();
+    crate::to_writer(
+        &mut buffer,
+        &tree,
+        &[tree.root_ref()],
+        crate::EncodeOptions::new().pretty(false),
+    )
+    .unwrap();
+
+    let output = String::from_utf8(buffer).unwrap();
+    assert!(!output.contains('\n'));
+
+    let decoded = crate::from_str_default(&output).unwrap();
+    let folder = decoded.get_by_ref(decoded.root().children()[0]).unwrap();
+    assert_eq!(folder.name, "Root");
+}
+
+#[test]
+fn write_indent_width() {
+    let _ = env_logger::try_init();
+
+    let tree = WeakDom::new(InstanceBuilder::new("Folder"));
+
+    let mut buffer = Vec::new();
+    crate::to_writer(
+        &mut buffer,
+        &tree,
+        &[tree.root_ref()],
+        crate::EncodeOptions::new().indent_width(4),
+    )
+    .unwrap();
+
+    let output = String::from_utf8(buffer).unwrap();
+    assert!(output.contains("\n    <Item class=\"Folder\""));
+}
+
+#[test]
+fn skip_default_properties() {
+    let _ = env_logger::try_init();
+
+    let mut tree = WeakDom::new(InstanceBuilder::new("Folder"));
+    let default_ref = tree.insert(
+        tree.root_ref(),
+        InstanceBuilder::new("BoolValue").with_property("Value", false),
+    );
+    let changed_ref = tree.insert(
+        tree.root_ref(),
+        InstanceBuilder::new("BoolValue").with_property("Value", true),
+    );
+
+    let mut buffer = Vec::new();
+    crate::to_writer(
+        &mut buffer,
+        &tree,
+        &[default_ref, changed_ref],
+        crate::EncodeOptions::new().skip_default_properties(true),
+    )
+    .unwrap();
+
+    let output = String::from_utf8(buffer).unwrap();
+    assert!(!output.contains("<bool name=\"Value\">false</bool>"));
+    assert!(output.contains("<bool name=\"Value\">true</bool>"));
+}
+
+#[test]
+fn namespaced_referents() {
+    let _ = env_logger::try_init();
+
+    let document = r#"
+        <roblox version="4">
+            <Item class="Folder" referent="RBX1">
+                <Item class="ObjectValue" referent="RBX2">
+                    <Properties>
+                        <Ref name="Value">RBX1</Ref>
+                    </Properties>
+                </Item>
+            </Item>
+        </roblox>
+    "#;
+
+    let namespace = Ref::new();
+    let options = crate::DecodeOptions::new().namespace(namespace);
+    let tree = crate::from_str(document, options.clone()).unwrap();
+
+    let folder_ref = tree.root().children()[0];
+    assert_eq!(folder_ref, Ref::from_namespace(namespace, b"RBX1"));
+
+    let value = tree
+        .get_by_ref(tree.get_by_ref(folder_ref).unwrap().children()[0])
+        .unwrap();
+    assert_eq!(value.referent(), Ref::from_namespace(namespace, b"RBX2"));
+    assert_eq!(
+        value.properties.get("Value"),
+        Some(&Variant::Ref(folder_ref))
+    );
+    assert_eq!(tree.root_ref(), namespace);
+
+    // Instances that share a referent are an error instead of replacing one
+    // another, with or without a namespace.
+    let duplicated = r#"
+        <roblox version="4">
+            <Item class="Folder" referent="RBX1" />
+            <Item class="Folder" referent="RBX1" />
+        </roblox>
+    "#;
+    assert!(crate::from_str(duplicated, options.clone()).is_err());
+    assert!(crate::from_str_default(duplicated).is_err());
+
+    let empty = r#"
+        <roblox version="4">
+            <Item class="Folder" referent="" />
+        </roblox>
+    "#;
+    let tree = crate::from_str(empty, options).unwrap();
+    assert_eq!(tree.root().children().len(), 1);
+    assert_eq!(tree.root().class, "DataModel");
+}
+
+#[test]
+fn deterministic_referents() {
+    let _ = env_logger::try_init();
+
+    let tree = WeakDom::new(InstanceBuilder::new("Folder").with_children(
+        ["B", "C", "A"].iter().map(|name| {
+            InstanceBuilder::new("Part")
+                .with_name(*name)
+                .with_child(InstanceBuilder::new("Decal"))
+        }),
+    ));
+
+    let mut buffer = Vec::new();
+    let options = crate::EncodeOptions::new().deterministic_referents(true);
+    crate::to_writer(&mut buffer, &tree, &[tree.root_ref()], options).unwrap();
+    let encoded = String::from_utf8(buffer).unwrap();
+
+    // Siblings are written in their original order, but numbered as if they
+    // were sorted.
+    let referent_of = |name: &str| {
+        let name_at = encoded.find(&format!(">{}<", name)).unwrap();
+        let item_at = encoded[..name_at].rfind("referent=\"").unwrap();
+        let referent = &encoded[item_at + "referent=\"".len()..];
+        referent[..referent.find('"').unwrap()].to_owned()
+    };
+    assert_eq!(referent_of("A"), "1");
+    assert_eq!(referent_of("B"), "2");
+    assert_eq!(referent_of("C"), "3");
+    assert!(encoded.find(">B<").unwrap() < encoded.find(">A<").unwrap());
+}
+
+#[test]
+fn canonical_floats() {
+    let _ = env_logger::try_init();
+
+    let cframe = CFrame::new(
+        Vector3::new(1e-20, 0.3, 0.0),
+        Matrix3::new(
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+            Vector3::new(0.0, 0.0, 1e20),
+        ),
+    );
+    let tree = WeakDom::new(
+        InstanceBuilder::new("NumberValue")
+            .with_property("Value", 1e-20f64)
+            .with_child(InstanceBuilder::new("NumberValue").with_property("Value", 0.3f64))
+            .with_child(InstanceBuilder::new("CFrameValue").with_property("Value", cframe)),
+    );
+
+    let encode = |canonical_floats| {
+        let mut buffer = Vec::new();
+        let options = crate::EncodeOptions::new()
+            .property_behavior(crate::EncodePropertyBehavior::WriteUnknown)
+            .canonical_floats(canonical_floats);
+        crate::to_writer(&mut buffer, &tree, &[tree.root_ref()], options).unwrap();
+        String::from_utf8(buffer).unwrap()
+    };
+
+    let plain = encode(false);
+    assert!(plain.contains(">0.00000000000000000001<"));
+    assert!(plain.contains(">0.3<"));
+
+    let canonical = encode(true);
+    assert!(canonical.contains(">1e-20<"));
+    assert!(canonical.contains(">0.3<"));
+    assert!(canonical.contains("<X>1e-20</X>"));
+    assert!(canonical.contains("<R22>1e20</R22>"));
+
+    let decoded = crate::from_str_default(&canonical).unwrap();
+    let value = decoded.get_by_ref(decoded.root().children()[0]).unwrap();
+    assert_eq!(
+        value.properties.get("Value"),
+        Some(&Variant::Float64(1e-20))
+    );
+    let value = decoded.get_by_ref(value.children()[1]).unwrap();
+    assert_eq!(
+        value.properties.get("Value"),
+        Some(&Variant::CFrame(cframe))
+    );
+}
+
+#[test]
+fn non_finite_behavior() {
+    let _ = env_logger::try_init();
+
+    let tree =
+        WeakDom::new(InstanceBuilder::new("NumberValue").with_property("Value", f64::INFINITY));
+
+    let encode = |behavior| {
+        let mut buffer = Vec::new();
+        let options = crate::EncodeOptions::new().non_finite_behavior(behavior);
+        crate::to_writer(&mut buffer, &tree, &[tree.root_ref()], options)
+            .map(|_| String::from_utf8(buffer).unwrap())
+    };
+
+    let preserved = encode(crate::NonFiniteBehavior::Preserve).unwrap();
+    assert!(preserved.contains(">INF<"));
+
+    let clamped = encode(crate::NonFiniteBehavior::Clamp).unwrap();
+    let decoded = crate::from_str_default(&clamped).unwrap();
+    let value = decoded.get_by_ref(decoded.root().children()[0]).unwrap();
+    assert_eq!(
+        value.properties.get("Value"),
+        Some(&Variant::Float64(f64::MAX))
+    );
+
+    let error = encode(crate::NonFiniteBehavior::Error).unwrap_err();
+    assert!(error.to_string().contains("NumberValue.Value"));
+
+    let mut cframe = CFrame::new(Vector3::new(1.0, 2.0, 3.0), Matrix3::identity());
+    cframe.orientation.y.z = f32::NAN;
+    cframe.position.x = f32::NEG_INFINITY;
+    let tree = WeakDom::new(InstanceBuilder::new("CFrameValue").with_property("Value", cframe));
+
+    let encode = |behavior| {
+        let mut buffer = Vec::new();
+        let options = crate::EncodeOptions::new().non_finite_behavior(behavior);
+        crate::to_writer(&mut buffer, &tree, &[tree.root_ref()], options)
+            .map(|_| String::from_utf8(buffer).unwrap())
+    };
+
+    let preserved = encode(crate::NonFiniteBehavior::Preserve).unwrap();
+    assert!(preserved.contains("<X>-INF</X>"));
+    assert!(preserved.contains("<R12>NAN</R12>"));
+
+    let clamped = encode(crate::NonFiniteBehavior::Clamp).unwrap();
+    assert!(clamped.contains("<R12>0</R12>"));
+
+    let error = encode(crate::NonFiniteBehavior::Error).unwrap_err();
+    assert!(error.to_string().contains("CFrameValue.Value"));
+}
+
+#[test]
+fn custom_reflection_database() {
+    let _ = env_logger::try_init();
+
+    let document = r#"
+        <roblox version="4">
+            <Item class="Part" referent="RBX1">
+                <Properties>
+                    <Vector3 name="size">
+                        <X>1</X>
+                        <Y>2</Y>
+                        <Z>3</Z>
+                    </Vector3>
+                </Properties>
+            </Item>
+        </roblox>
+    "#;
+
+    let empty = rbx_reflection::ReflectionDatabase::new();
+    let options = crate::DecodeOptions::new()
+        .property_behavior(crate::DecodePropertyBehavior::ReadUnknown)
+        .reflection_database(&empty);
+    let tree = crate::from_str(document, options).unwrap();
+
+    // Part.Size is written to files as `size`. Without a database that knows
+    // about it, the property keeps that name.
+    let part = tree.get_by_ref(tree.root().children()[0]).unwrap();
+    assert_eq!(
+        part.properties.get("size"),
+        Some(&Variant::Vector3(Vector3::new(1.0, 2.0, 3.0)))
+    );
+
+    let tree = crate::from_str_default(document).unwrap();
+    let part = tree.get_by_ref(tree.root().children()[0]).unwrap();
+    assert!(part.properties.contains_key("Size"));
+
+    // A malformed database, with a superclass and property aliases that
+    // aren't in it, is treated as not knowing about the property instead of
+    // panicking.
+    use rbx_dom_weak::types::VariantType;
+    use rbx_reflection::{
+        ClassDescriptor, DataType, PropertyDescriptor, PropertyKind, PropertySerialization,
+    };
+
+    let mut part = ClassDescriptor::new("Part");
+    part.superclass = Some("Missing".into());
+    let mut size = PropertyDescriptor::new("size", DataType::Value(VariantType::Vector3));
+    size.kind = PropertyKind::Alias {
+        alias_for: "Size".into(),
+    };
+    part.properties.insert("size".into(), size);
+    let mut color = PropertyDescriptor::new("Color", DataType::Value(VariantType::Color3));
+    color.kind = PropertyKind::Canonical {
+        serialization: PropertySerialization::SerializesAs("Color3uint8".into()),
+    };
+    part.properties.insert("Color".into(), color);
+    let mut malformed = rbx_reflection::ReflectionDatabase::new();
+    malformed.classes.insert("Part".into(), part);
+
+    let options = crate::DecodeOptions::new()
+        .property_behavior(crate::DecodePropertyBehavior::ReadUnknown)
+        .reflection_database(&malformed);
+    let mut tree = crate::from_str(document, options).unwrap();
+
+    let part = tree.root().children()[0];
+    tree.get_by_ref_mut(part)
+        .unwrap()
+        .properties
+        .insert("Color".into(), Variant::Color3(Color3::new(1.0, 0.0, 0.0)));
+
+    let options = crate::EncodeOptions::new()
+        .property_behavior(crate::EncodePropertyBehavior::WriteUnknown)
+        .reflection_database(&malformed);
+    let mut encoded = Vec::new();
+    crate::to_writer(&mut encoded, &tree, &[part], options).unwrap();
+}
+
+#[test]
+fn strip_and_map_classes() {
+    let _ = env_logger::try_init();
+
+    let tree = WeakDom::new(
+        InstanceBuilder::new("Folder")
+            .with_child(
+                InstanceBuilder::new("BuildMarker").with_child(InstanceBuilder::new("Part")),
+            )
+            .with_child(
+                InstanceBuilder::new("ScriptGroup")
+                    .with_name("Scripts")
+                    .with_child(InstanceBuilder::new("Script")),
+            ),
+    );
+
+    let options = crate::EncodeOptions::new()
+        .strip_class("BuildMarker")
+        .map_class("ScriptGroup", "Folder");
+
+    let mut buffer = Vec::new();
+    crate::to_writer(&mut buffer, &tree, &[tree.root_ref()], options).unwrap();
+
+    let decoded = crate::from_reader_default(buffer.as_slice()).unwrap();
+    let folder = decoded.get_by_ref(decoded.root().children()[0]).unwrap();
+    assert_eq!(folder.children().len(), 1);
+
+    let scripts = decoded.get_by_ref(folder.children()[0]).unwrap();
+    assert_eq!(scripts.name, "Scripts");
+    assert_eq!(scripts.class, "Folder");
+    assert_eq!(scripts.children().len(), 1);
+}
+
+#[test]
+fn skip_instances() {
+    let _ = env_logger::try_init();
+
+    let mut tree = WeakDom::new(InstanceBuilder::new("Folder"));
+    let root = tree.root_ref();
+    let shop = tree.insert(root, InstanceBuilder::new("ModuleScript").with_name("Shop"));
+    let spec = tree.insert(
+        root,
+        InstanceBuilder::new("ModuleScript")
+            .with_name("Shop.spec")
+            .with_child(InstanceBuilder::new("ModuleScript")),
+    );
+    tree.insert(
+        shop,
+        InstanceBuilder::new("ObjectValue").with_property("Value", spec),
+    );
+
+    let options = crate::EncodeOptions::new().skip_instances([spec]);
+
+    let mut buffer = Vec::new();
+    crate::to_writer(&mut buffer, &tree, &[root], options).unwrap();
+
+    // The tree itself is left alone.
+    assert_eq!(tree.root().children(), [shop, spec]);
+
+    let decoded = crate::from_reader_default(buffer.as_slice()).unwrap();
+    let folder = decoded.get_by_ref(decoded.root().children()[0]).unwrap();
+    assert_eq!(folder.children().len(), 1);
+
+    let shop = decoded.get_by_ref(folder.children()[0]).unwrap();
+    assert_eq!(shop.name, "Shop");
+
+    let value = decoded.get_by_ref(shop.children()[0]).unwrap();
+    assert_eq!(
+        value.properties.get("Value"),
+        Some(&Variant::Ref(Ref::none()))
+    );
+}
+
+#[test]
+fn registered_class() {
+    use rbx_dom_weak::types::VariantType;
+    use rbx_reflection::{ClassDescriptor, DataType, PropertyDescriptor};
+
+    let _ = env_logger::try_init();
+
+    let mut database = rbx_reflection_database::get().clone();
+    let mut marker = ClassDescriptor::new("BuildMarker");
+    marker.superclass = Some("Instance".into());
+    marker.properties.insert(
+        "Label".into(),
+        PropertyDescriptor::new("Label", DataType::Value(VariantType::String)),
+    );
+    database.register_class(marker).unwrap();
+
+    let document = r#"
+        <roblox version="4">
+            <Item class="BuildMarker" referent="RBX1">
+                <Properties>
+                    <string name="Label">Spawn</string>
+                </Properties>
+            </Item>
+        </roblox>
+    "#;
+
+    // Without the registered class, the unknown property is ignored.
+    let tree = crate::from_str_default(document).unwrap();
+    let marker = tree.get_by_ref(tree.root().children()[0]).unwrap();
+    assert_eq!(marker.properties.get("Label"), None);
+
+    let options = crate::DecodeOptions::new().reflection_database(&database);
+    let tree = crate::from_str(document, options).unwrap();
+    let marker = tree.get_by_ref(tree.root().children()[0]).unwrap();
+    assert_eq!(
+        marker.properties.get("Label"),
+        Some(&Variant::String("Spawn".to_owned()))
+    );
+
+    let mut buffer = Vec::new();
+    let options = crate::EncodeOptions::new().reflection_database(&database);
+    crate::to_writer(&mut buffer, &tree, tree.root().children(), options).unwrap();
+    assert!(std::str::from_utf8(&buffer).unwrap().contains("Spawn"));
+
+    let mut buffer = Vec::new();
+    let options = crate::EncodeOptions::new()
+        .reflection_database(&database)
+        .strip_class("BuildMarker");
+    crate::to_writer(&mut buffer, &tree, tree.root().children(), options).unwrap();
+    assert!(!std::str::from_utf8(&buffer)
+        .unwrap()
+        .contains("BuildMarker"));
+}
+
+#[test]
+fn lenient_decoding() {
+    use crate::{DecodeOptions, InvalidUtf8Behavior};
+
+    let _ = env_logger::try_init();
+
+    let mut document = br#"
+        <roblox version="4">
+            <Item class="Script" referent="hello">
+                <Properties>
+                    <string name="Name">Fish &amp; Chips & Peas&nbsp;&#33;</string>
+                    <ProtectedString name="Source"><![CDATA[print("a]]>b") -- "#
+        .to_vec();
+    document.push(0xff);
+    document.extend_from_slice(
+        br#"]]></ProtectedString>
+                </Properties>
+            </Item>
+        </roblox>
+    "#,
+    );
+
+    assert!(crate::from_reader_default(document.as_slice()).is_err());
+    assert!(crate::from_reader(
+        document.as_slice(),
+        DecodeOptions::new()
+            .lenient_entities(true)
+            .lenient_cdata(true),
+    )
+    .is_err());
+
+    let options = DecodeOptions::new()
+        .invalid_utf8_behavior(InvalidUtf8Behavior::Replace)
+        .lenient_entities(true)
+        .lenient_cdata(true);
+    let dom = crate::from_reader(document.as_slice(), options).unwrap();
+    let script = dom.get_by_ref(dom.root().children()[0]).unwrap();
+
+    assert_eq!(script.name, "Fish & Chips & Peas&nbsp;!");
+    assert_eq!(
+        script.properties.get("Source"),
+        Some(&Variant::String("print(\"a]]>b\") -- \u{FFFD}".to_owned()))
+    );
+}
+
+#[test]
+fn line_endings() {
+    use crate::{DecodeOptions, EncodeOptions, LineEndings};
+
+    let _ = env_logger::try_init();
+
+    let mixed = "local a = 1\r\nlocal b = 2\nprint(a + b)\r";
+    let tree = WeakDom::new(
+        InstanceBuilder::new("Folder")
+            .with_child(InstanceBuilder::new("Script").with_property("Source", mixed)),
+    );
+
+    let encode = |options: EncodeOptions| {
+        let mut buffer = Vec::new();
+        crate::to_writer(&mut buffer, &tree, tree.root().children(), options).unwrap();
+        buffer
+    };
+    let source = |buffer: &[u8], options: DecodeOptions| {
+        let decoded = crate::from_reader(buffer, options).unwrap();
+        let script = decoded.get_by_ref(decoded.root().children()[0]).unwrap();
+        script.properties.get("Source").cloned()
+    };
+
+    let preserved = encode(EncodeOptions::new());
+    assert_eq!(
+        source(&preserved, DecodeOptions::new()),
+        Some(Variant::String(mixed.to_owned()))
+    );
+    assert_eq!(
+        source(
+            &preserved,
+            DecodeOptions::new().line_endings(LineEndings::Lf)
+        ),
+        Some(Variant::String(
+            "local a = 1\nlocal b = 2\nprint(a + b)\n".to_owned()
+        ))
+    );
+
+    let crlf = encode(EncodeOptions::new().line_endings(LineEndings::Crlf));
+    assert_eq!(
+        source(&crlf, DecodeOptions::new()),
+        Some(Variant::String(
+            "local a = 1\r\nlocal b = 2\r\nprint(a + b)\r\n".to_owned()
+        ))
+    );
+}
+
+#[test]
+fn cancel_token() {
+    use std::sync::{atomic::AtomicBool, Arc};
+
+    use crate::{DecodeOptions, EncodeOptions};
+
+    let tree =
+        WeakDom::new(InstanceBuilder::new("DataModel").with_child(InstanceBuilder::new("Folder")));
+    let mut buffer = Vec::new();
+    crate::to_writer_default(&mut buffer, &tree, tree.root().children()).unwrap();
+
+    let token = Arc::new(AtomicBool::new(false));
+    crate::from_reader(
+        buffer.as_slice(),
+        DecodeOptions::new().cancel_token(Arc::clone(&token)),
+    )
+    .expect("decoding shouldn't be cancelled before the token is set");
+
+    token.store(true, std::sync::atomic::Ordering::Relaxed);
+
+    let error = crate::from_reader(
+        buffer.as_slice(),
+        DecodeOptions::new().cancel_token(Arc::clone(&token)),
+    )
+    .expect_err("decoding should have been cancelled");
+    assert!(error.is_cancelled());
+
+    let error = crate::to_writer(
+        Vec::new(),
+        &tree,
+        tree.root().children(),
+        EncodeOptions::new().cancel_token(token),
+    )
+    .expect_err("encoding should have been cancelled");
+    assert!(error.is_cancelled());
+}
+
+#[test]
+fn file_metadata() {
+    use rbx_dom_weak::FileMetadata;
+
+    let document = r#"
+        <roblox version="4">
+            <Meta name="ExplicitAutoJoints">true</Meta>
+            <Item class="Folder" referent="RBX1">
+                <Properties>
+                    <string name="Name">Folder</string>
+                </Properties>
+            </Item>
+        </roblox>
+    "#;
+
+    let tree = crate::from_str_default(document).unwrap();
+
+    let mut expected = FileMetadata::default();
+    expected
+        .entries
+        .insert("ExplicitAutoJoints".to_owned(), "true".to_owned());
+    assert_eq!(tree.extension(tree.root_ref()), Some(&expected));
+
+    let mut buffer = Vec::new();
+    crate::to_writer_default(&mut buffer, &tree, tree.root().children()).unwrap();
+    let decoded = crate::from_reader_default(buffer.as_slice()).unwrap();
+    assert_eq!(decoded.extension(decoded.root_ref()), Some(&expected));
+}
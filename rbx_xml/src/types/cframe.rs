@@ -33,7 +33,9 @@ impl XmlType for CFrame {
             self.orientation.z.z,
         ];
 
-        writer.write_tag_array(as_slice, &TAG_NAMES)?;
+        for (component, tag_name) in as_slice.iter().zip(&TAG_NAMES) {
+            writer.write_value_in_tag(component, tag_name)?;
+        }
 
         Ok(())
     }
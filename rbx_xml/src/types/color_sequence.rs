@@ -14,13 +14,13 @@ impl XmlType for ColorSequence {
 
     fn write_xml<W: Write>(&self, writer: &mut XmlEventWriter<W>) -> Result<(), EncodeError> {
         for keypoint in &self.keypoints {
-            writer.write_characters(keypoint.time)?;
+            writer.write_float(keypoint.time)?;
             writer.write(XmlWriteEvent::characters(" "))?;
-            writer.write_characters(keypoint.color.r)?;
+            writer.write_float(keypoint.color.r)?;
             writer.write(XmlWriteEvent::characters(" "))?;
-            writer.write_characters(keypoint.color.g)?;
+            writer.write_float(keypoint.color.g)?;
             writer.write(XmlWriteEvent::characters(" "))?;
-            writer.write_characters(keypoint.color.b)?;
+            writer.write_float(keypoint.color.b)?;
             writer.write(XmlWriteEvent::characters(" "))?;
 
             // Envelope is always 0 for ColorSequenceKeypoint. This value isn't
@@ -13,9 +13,9 @@ impl XmlType for NumberRange {
     const XML_TAG_NAME: &'static str = "NumberRange";
 
     fn write_xml<W: Write>(&self, writer: &mut XmlEventWriter<W>) -> Result<(), EncodeError> {
-        writer.write_characters(self.min)?;
+        writer.write_float(self.min)?;
         writer.write(XmlWriteEvent::characters(" "))?;
-        writer.write_characters(self.max)?;
+        writer.write_float(self.max)?;
         writer.write(XmlWriteEvent::characters(" "))?;
 
         Ok(())
@@ -14,11 +14,11 @@ impl XmlType for NumberSequence {
 
     fn write_xml<W: Write>(&self, writer: &mut XmlEventWriter<W>) -> Result<(), EncodeError> {
         for keypoint in &self.keypoints {
-            writer.write_characters(keypoint.time)?;
+            writer.write_float(keypoint.time)?;
             writer.write(XmlWriteEvent::characters(" "))?;
-            writer.write_characters(keypoint.value)?;
+            writer.write_float(keypoint.value)?;
             writer.write(XmlWriteEvent::characters(" "))?;
-            writer.write_characters(keypoint.envelope)?;
+            writer.write_float(keypoint.envelope)?;
             writer.write(XmlWriteEvent::characters(" "))?;
         }
 
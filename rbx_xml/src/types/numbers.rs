@@ -16,15 +16,7 @@ macro_rules! float_type {
                 &self,
                 writer: &mut XmlEventWriter<W>,
             ) -> Result<(), EncodeError> {
-                if *self == std::$rust_type::INFINITY {
-                    writer.write_characters("INF")
-                } else if *self == std::$rust_type::NEG_INFINITY {
-                    writer.write_characters("-INF")
-                } else if self.is_nan() {
-                    writer.write_characters("NAN")
-                } else {
-                    writer.write_characters(self)
-                }
+                writer.write_float(*self)
             }
 
             fn read_xml<R: Read>(reader: &mut XmlEventReader<R>) -> Result<Self, DecodeError> {